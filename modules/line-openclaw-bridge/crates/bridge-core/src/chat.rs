@@ -0,0 +1,128 @@
+//! 對話流程的共用步驟
+//! 延續對話階段並取出脈絡、組合系統指示、保存問答，以及回答含禁用詞時的重問；與通道無關，
+//! 各通道（LINE、WhatsApp、Matrix、CLI）與其他執行檔共用，排版與送出由呼叫端處理
+
+use tracing::warn;
+
+use crate::moderation;
+use crate::openclaw::{ChatMessage, OpenClawClient, StreamTimings};
+use crate::privacy;
+use crate::store::ConversationStore;
+
+/// OpenClaw 回答（含串流時間統計）或錯誤訊息
+pub type Answer = Result<(String, Option<StreamTimings>), String>;
+
+/// 一輪對話：目前對話階段與送給 OpenClaw 的脈絡（系統指示在前，先前對話舊到新）
+pub struct Turn {
+    /// 對話階段 ID；資料庫失敗時為 None，問答仍照常進行但不歸入階段
+    pub session: Option<i64>,
+    pub history: Vec<ChatMessage>,
+}
+
+impl Turn {
+    /// 延續（或開始）使用者的對話階段，取出最近 `turns` 輪作為脈絡（0 代表不帶脈絡）
+    pub fn begin(store: &ConversationStore, user_id: &str, session_gap_secs: i64, turns: usize) -> Self {
+        let session = store
+            .continue_session(user_id, session_gap_secs)
+            .map_err(|e| warn!("Failed to continue conversation session: {}", e))
+            .ok();
+        let history = match (turns, session) {
+            (0, _) | (_, None) => Vec::new(),
+            (turns, Some(session)) => store.session_messages(session, turns * 2).unwrap_or_else(|e| {
+                warn!("Failed to load conversation history: {}", e);
+                Vec::new()
+            }),
+        };
+        let history = history.into_iter().map(|(role, content)| ChatMessage { role, content }).collect();
+        Self { session, history }
+    }
+
+    /// 在最前面加上一則系統指示；後加入的排在前面
+    pub fn instruct(&mut self, instruction: String) {
+        self.history.insert(0, ChatMessage { role: "system".to_string(), content: instruction });
+    }
+
+    /// 開頭的系統指示（多步驟流程只帶這些，不帶先前對話）
+    pub fn instructions(&self) -> &[ChatMessage] {
+        let pinned = self.history.iter().take_while(|m| m.role == "system").count();
+        &self.history[..pinned]
+    }
+
+    /// 保存一則訊息到目前的對話階段（失敗僅記錄警告）
+    pub fn record(&self, store: &ConversationStore, user_id: &str, role: &str, content: &str, model: Option<&str>) {
+        if let Err(e) = store.record(user_id, role, content, model, self.session) {
+            warn!("Failed to store {} message: {}", role, e);
+        }
+    }
+}
+
+/// 回答含禁用詞時以更嚴格的指示重問一次，回傳重問的結果：`ok` 為重問後通過，`blocked` 為仍含禁用詞或重問失敗
+/// （呼叫端應改回政策訊息）；回答沒有問題時不重問，回傳 None
+pub async fn moderate(
+    client: &OpenClawClient,
+    user_id: &str,
+    history: &mut Vec<ChatMessage>,
+    text: &str,
+    blocklist: &[String],
+    answer: &mut Answer,
+) -> Option<&'static str> {
+    if !matches!(answer, Ok((content, _)) if moderation::flagged(blocklist, content)) {
+        return None;
+    }
+    warn!("OpenClaw answer for user={} was flagged by moderation, asking again", privacy::id(user_id));
+    history.insert(0, ChatMessage { role: "system".to_string(), content: moderation::strict_instruction() });
+    *answer = client.send_message(user_id, history.clone(), text).await;
+    Some(match answer {
+        Ok((content, _)) if !moderation::flagged(blocklist, content) => "ok",
+        Ok(_) => "blocked",
+        Err(e) => {
+            warn!("Moderation retry request failed: {}", e);
+            "blocked"
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> ConversationStore {
+        ConversationStore::open(":memory:").unwrap()
+    }
+
+    #[test]
+    fn begin_without_turns_has_no_history() {
+        let store = store();
+        let turn = Turn::begin(&store, "U1", 3600, 0);
+        turn.record(&store, "U1", "user", "第一題", None);
+        let turn = Turn::begin(&store, "U1", 3600, 0);
+        assert!(turn.session.is_some());
+        assert!(turn.history.is_empty());
+    }
+
+    #[test]
+    fn begin_loads_previous_turns_of_the_session() {
+        let store = store();
+        let turn = Turn::begin(&store, "U1", 3600, 2);
+        turn.record(&store, "U1", "user", "第一題", None);
+        turn.record(&store, "U1", "assistant", "第一個回答", Some("model"));
+        let next = Turn::begin(&store, "U1", 3600, 2);
+        assert_eq!(next.session, turn.session);
+        let history: Vec<_> = next.history.iter().map(|m| (m.role.as_str(), m.content.as_str())).collect();
+        assert_eq!(history, [("user", "第一題"), ("assistant", "第一個回答")]);
+        assert!(Turn::begin(&store, "U2", 3600, 2).history.is_empty());
+    }
+
+    #[test]
+    fn instructions_are_pinned_before_history() {
+        let store = store();
+        let turn = Turn::begin(&store, "U1", 3600, 1);
+        turn.record(&store, "U1", "user", "問題", None);
+        let mut turn = Turn::begin(&store, "U1", 3600, 1);
+        turn.instruct("語言".to_string());
+        turn.instruct("品牌".to_string());
+        let instructions: Vec<_> = turn.instructions().iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(instructions, ["品牌", "語言"]);
+        assert_eq!(turn.history.last().unwrap().content, "問題");
+    }
+}
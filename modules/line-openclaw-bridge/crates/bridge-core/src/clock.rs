@@ -0,0 +1,90 @@
+//! 時鐘模組
+//! 排程、頻率限制與對話階段逾時都經由注入的 `Clock` 取得目前時間；正式執行使用系統時鐘，
+//! 測試可改用 `ManualClock` 手動推進時間，不必真的等待就能重現時間相關的行為
+
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 目前時間的來源
+pub trait Clock: Send + Sync {
+    /// 目前的 UTC 時間
+    fn now(&self) -> DateTime<Utc>;
+
+    /// 單調時間（計算間隔與時間窗用）
+    fn instant(&self) -> Instant;
+
+    /// 目前的 Unix 時間（秒）
+    fn timestamp(&self) -> i64 {
+        self.now().timestamp()
+    }
+}
+
+/// 系統時鐘
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// 手動推進的時鐘：時間停在建立時的值，只在呼叫 `advance` 時前進
+#[derive(Debug)]
+pub struct ManualClock {
+    start: DateTime<Utc>,
+    base: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self { start, base: Instant::now(), elapsed: Mutex::new(Duration::ZERO) }
+    }
+
+    /// 讓時間前進
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        let elapsed = *self.elapsed.lock().unwrap();
+        self.start + chrono::Duration::from_std(elapsed).unwrap_or(chrono::Duration::MAX)
+    }
+
+    fn instant(&self) -> Instant {
+        self.base + *self.elapsed.lock().unwrap()
+    }
+}
+
+/// 系統時鐘（供預設注入）
+pub fn system() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn manual_clock_only_moves_when_advanced() {
+        let start = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let clock = ManualClock::new(start);
+        let instant = clock.instant();
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.instant(), instant);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), start + chrono::Duration::seconds(90));
+        assert_eq!(clock.timestamp(), start.timestamp() + 90);
+        assert_eq!(clock.instant() - instant, Duration::from_secs(90));
+    }
+}
@@ -0,0 +1,175 @@
+//! 對話內容壓縮模組
+//! 以由對話文字訓練的 zstd 字典將 `conversations.content` 壓縮成 BLOB，並註冊 SQL 函式 `conversation_text()` 透明解壓；
+//! 讀取、搜尋與全文索引都經由此函式，未壓縮的舊資料照常讀取
+
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
+
+/// 解壓對話內容的 SQL 函式名稱
+pub const SQL_FUNCTION: &str = "conversation_text";
+/// zstd 壓縮等級
+const LEVEL: i32 = 3;
+/// 短於此長度的內容不壓縮（壓縮後通常不會更小）
+pub const MIN_BYTES: usize = 64;
+/// 字典大小上限
+pub const DICT_MAX_BYTES: usize = 64 * 1024;
+/// 訓練字典所需的最少樣本數
+pub const DICT_MIN_SAMPLES: usize = 500;
+
+/// 壓縮設定與已載入的字典（字典 ID 取自 zstd 字典本身，寫在每個壓縮框架的標頭）
+#[derive(Default)]
+pub struct Codec {
+    enabled: AtomicBool,
+    decoders: RwLock<HashMap<u32, DecoderDictionary<'static>>>,
+    /// 新資料使用的字典（最新訓練的一份）
+    encoder: RwLock<Option<(u32, EncoderDictionary<'static>)>>,
+}
+
+impl Codec {
+    /// 開始壓縮新寫入的內容
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// 新資料使用的字典 ID
+    pub fn active_dictionary(&self) -> Option<u32> {
+        self.encoder.read().unwrap().as_ref().map(|(id, _)| *id)
+    }
+
+    /// 載入字典並設為新資料使用的字典，回傳字典 ID；不是有效的 zstd 字典時回傳 None
+    pub fn add_dictionary(&self, dictionary: &[u8]) -> Option<u32> {
+        let id = zstd::zstd_safe::get_dict_id_from_dict(dictionary)?.get();
+        self.decoders.write().unwrap().insert(id, DecoderDictionary::copy(dictionary));
+        *self.encoder.write().unwrap() = Some((id, EncoderDictionary::copy(dictionary, LEVEL)));
+        Some(id)
+    }
+
+    /// 寫入資料庫的值：已開啟且壓縮後較小時為 BLOB，否則維持文字
+    pub fn pack(&self, text: &str) -> Value {
+        if !self.enabled() || text.len() < MIN_BYTES {
+            return Value::Text(text.to_string());
+        }
+        let encoder = self.encoder.read().unwrap();
+        let compressed = match encoder.as_ref() {
+            Some((_, dictionary)) => {
+                zstd::bulk::Compressor::with_prepared_dictionary(dictionary).and_then(|mut c| c.compress(text.as_bytes()))
+            }
+            None => zstd::bulk::compress(text.as_bytes(), LEVEL),
+        };
+        match compressed {
+            Ok(data) if data.len() < text.len() => Value::Blob(data),
+            _ => Value::Text(text.to_string()),
+        }
+    }
+
+    /// 解壓 BLOB；字典未載入或資料損毀時回傳錯誤
+    pub fn unpack(&self, data: &[u8]) -> Result<String, String> {
+        let mut text = String::new();
+        match zstd::zstd_safe::get_dict_id_from_frame(data) {
+            Some(id) => {
+                let decoders = self.decoders.read().unwrap();
+                let dictionary = decoders.get(&id.get()).ok_or_else(|| format!("找不到壓縮字典 {}", id))?;
+                zstd::stream::read::Decoder::with_prepared_dictionary(data, dictionary)
+                    .and_then(|mut d| d.read_to_string(&mut text))
+                    .map_err(|e| e.to_string())?;
+            }
+            None => {
+                zstd::stream::read::Decoder::with_buffer(data)
+                    .and_then(|mut d| d.read_to_string(&mut text))
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// 由對話文字訓練字典
+pub fn train(samples: &[String]) -> Result<Vec<u8>, String> {
+    zstd::dict::from_samples(samples, DICT_MAX_BYTES).map_err(|e| format!("訓練壓縮字典失敗: {}", e))
+}
+
+/// 註冊 `conversation_text(content)`：BLOB 解壓為文字，文字原樣回傳
+pub fn register(conn: &Connection, codec: Arc<Codec>) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        SQL_FUNCTION,
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| match ctx.get_raw(0) {
+            ValueRef::Blob(data) => codec
+                .unpack(data)
+                .map(Some)
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into())),
+            ValueRef::Text(text) => Ok(Some(String::from_utf8_lossy(text).into_owned())),
+            ValueRef::Null => Ok(None),
+            ValueRef::Integer(n) => Ok(Some(n.to_string())),
+            ValueRef::Real(n) => Ok(Some(n.to_string())),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples() -> Vec<String> {
+        (0..DICT_MIN_SAMPLES)
+            .map(|i| format!("使用者 {} 詢問：今天的天氣如何？助理回答：台北市第 {} 區今天晴時多雲，氣溫約 {} 度。", i, i % 12, 20 + i % 10))
+            .collect()
+    }
+
+    #[test]
+    fn disabled_or_short_text_stays_text() {
+        let codec = Codec::default();
+        let long = "重複的內容".repeat(20);
+        assert_eq!(codec.pack(&long), Value::Text(long.clone()));
+        codec.enable();
+        assert_eq!(codec.pack("短"), Value::Text("短".to_string()));
+        assert!(matches!(codec.pack(&long), Value::Blob(_)));
+    }
+
+    #[test]
+    fn round_trips_without_dictionary() {
+        let codec = Codec::default();
+        codec.enable();
+        let text = "沒有字典時也會壓縮的內容。".repeat(10);
+        let Value::Blob(data) = codec.pack(&text) else { panic!("expected a blob") };
+        assert_eq!(codec.unpack(&data).unwrap(), text);
+    }
+
+    #[test]
+    fn round_trips_with_dictionary() {
+        let codec = Codec::default();
+        codec.enable();
+        let id = codec.add_dictionary(&train(&samples()).unwrap()).unwrap();
+        assert_eq!(codec.active_dictionary(), Some(id));
+        let text = samples()[7].repeat(2);
+        let Value::Blob(data) = codec.pack(&text) else { panic!("expected a blob") };
+        assert_eq!(zstd::zstd_safe::get_dict_id_from_frame(&data).map(|id| id.get()), Some(id));
+        assert_eq!(codec.unpack(&data).unwrap(), text);
+        // 未載入字典的解碼端無法解壓
+        assert!(Codec::default().unpack(&data).is_err());
+    }
+
+    #[test]
+    fn sql_function_reads_blobs_and_text() {
+        let codec = Arc::new(Codec::default());
+        codec.enable();
+        let conn = Connection::open_in_memory().unwrap();
+        register(&conn, codec.clone()).unwrap();
+        let text = "經由 SQL 函式解壓的內容。".repeat(10);
+        let packed = codec.pack(&text);
+        let sql = format!("SELECT {}(?1)", SQL_FUNCTION);
+        assert_eq!(conn.query_row(&sql, [packed], |row| row.get::<_, String>(0)).unwrap(), text);
+        assert_eq!(conn.query_row(&sql, ["原文"], |row| row.get::<_, String>(0)).unwrap(), "原文");
+    }
+}
@@ -0,0 +1,10 @@
+//! Bridge 核心函式庫
+//! OpenClaw 客戶端、與通道無關的對話流程步驟、對話儲存（含內容壓縮）、回答審查、隱私雜湊與可注入的時鐘，供 Automation_Tools 的各個執行檔共用
+
+pub mod chat;
+pub mod clock;
+pub mod compression;
+pub mod moderation;
+pub mod openclaw;
+pub mod privacy;
+pub mod store;
@@ -0,0 +1,24 @@
+//! 回答審查模組
+//! 以禁用詞清單檢查 OpenClaw 的回答，命中時附上更嚴格的指示重問一次，仍命中才改回政策訊息
+
+/// 解析禁用詞清單（逗號或換行分隔，不分大小寫）
+pub fn parse_blocklist(value: &str) -> Vec<String> {
+    value
+        .split([',', '\n'])
+        .map(|term| term.trim().to_lowercase())
+        .filter(|term| !term.is_empty())
+        .collect()
+}
+
+/// 回答是否含有禁用詞
+pub fn flagged(blocklist: &[String], answer: &str) -> bool {
+    let answer = answer.to_lowercase();
+    blocklist.iter().any(|term| answer.contains(term.as_str()))
+}
+
+/// 重問時附上的系統指示
+pub fn strict_instruction() -> String {
+    "Your previous answer was rejected by the content filter. Answer again without profanity, slurs, \
+     insults or sexual language, even when quoting or repeating the user. Rephrase or omit such words entirely."
+        .to_string()
+}
@@ -0,0 +1,643 @@
+//! OpenClaw 本地 API 客戶端模組
+//! 與本地運行的 OpenClaw AI 助理通訊
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Once, RwLock};
+use std::time::Instant;
+use tracing::{info, error, warn};
+
+use crate::privacy;
+
+/// OpenClaw 客戶端
+pub struct OpenClawClient {
+    client: Client,
+    base_url: String,
+    /// 可在執行中輪替（外部密鑰管理）
+    gateway_token: RwLock<Option<String>>,
+    /// 設定的模型（`OPENCLAW_MODEL`）
+    model: String,
+    /// 實際使用的模型：設定的模型不在可用清單時改用預設模型
+    active_model: RwLock<String>,
+    /// 最近一次從 `/v1/models` 取得的可用模型；尚未取得時為 None，不檢查模型名稱
+    available_models: RwLock<Option<Vec<String>>>,
+    /// 對話是否以串流（SSE）取得回應
+    streaming: bool,
+    /// 是否在請求附上 `user` 與 `metadata`（雜湊後的使用者 ID 與對話來源）
+    send_metadata: bool,
+}
+
+tokio::task_local! {
+    /// 目前處理中事件的對話來源
+    static CONTEXT: RequestContext;
+}
+
+/// 對話來源，附在請求的 `metadata` 供 OpenClaw 端記錄與依對話提供功能
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// 訊息平台（如 `line`）
+    pub channel: &'static str,
+    /// `user`（一對一）、`group` 或 `room`
+    pub chat_type: &'static str,
+    /// 群組或聊天室 ID（一對一時為 None）
+    pub group_id: Option<String>,
+}
+
+/// 在 `context` 範圍內執行 `future`：期間送出的請求附上對話來源（不延伸到其中另外 spawn 的工作）
+pub async fn with_context<F: Future>(context: RequestContext, future: F) -> F::Output {
+    CONTEXT.scope(context, future).await
+}
+
+/// 目前處理中事件的對話來源（不在 [`with_context`] 範圍內時為 None）
+pub fn current_context() -> Option<RequestContext> {
+    CONTEXT.try_with(RequestContext::clone).ok()
+}
+
+/// 預設使用的模型（可用 `OPENCLAW_MODEL` 覆寫）
+pub const DEFAULT_MODEL: &str = "google-antigravity/claude-opus-4-5-thinking";
+
+/// Chat message for OpenAI-compatible API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// 發送給 OpenClaw Chat Completions 的請求
+#[derive(Debug, Serialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    /// 串流時要求在最後一個 chunk 附上 token 用量
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<serde_json::Value>,
+    /// 結構化輸出（JSON Schema）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+    /// 雜湊後的使用者 ID（OpenAI 相容的 `user` 欄位）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// 對話來源：`user_id`、`channel`、`chat_type` 與 `group_id`（ID 皆已雜湊）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<BTreeMap<String, String>>,
+}
+
+/// Chat Completions API 的回應
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionResponse {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatChoice {
+    pub index: i32,
+    pub message: ChatMessage,
+    pub finish_reason: String,
+}
+
+/// OpenClaw 健康檢查回應
+#[derive(Debug, Deserialize)]
+pub struct HealthResponse {
+    pub status: String,
+}
+
+/// 判斷 context 超限的錯誤訊息片段（OpenAI 相容伺服器與常見本地推論引擎）
+const CONTEXT_OVERFLOW_MARKERS: [&str; 6] = [
+    "context_length_exceeded",
+    "maximum context length",
+    "context window",
+    "context length",
+    "too many tokens",
+    "prompt is too long",
+];
+
+/// OpenClaw 呼叫失敗的原因
+enum CallError {
+    /// 超出模型的 context window，縮減對話後可重試
+    ContextOverflow(String),
+    Other(String),
+}
+
+impl From<CallError> for String {
+    fn from(error: CallError) -> Self {
+        match error {
+            CallError::ContextOverflow(message) | CallError::Other(message) => message,
+        }
+    }
+}
+
+impl From<String> for CallError {
+    fn from(message: String) -> Self {
+        CallError::Other(message)
+    }
+}
+
+impl From<&str> for CallError {
+    fn from(message: &str) -> Self {
+        CallError::Other(message.to_string())
+    }
+}
+
+/// 串流回應的一個 chunk
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: Delta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    completion_tokens: u64,
+}
+
+/// 串流回應的時間統計
+#[derive(Debug, Clone, Copy)]
+pub struct StreamTimings {
+    /// 送出請求到收到第一個 token 的時間
+    pub first_token_ms: u64,
+    /// 送出請求到串流結束的時間
+    pub total_ms: u64,
+    /// 產生的 token 數（伺服器未回報用量時以內容 chunk 數估計）
+    pub tokens: u64,
+}
+
+impl StreamTimings {
+    /// 第一個 token 之後的生成速度
+    pub fn tokens_per_sec(&self) -> f64 {
+        let generation_ms = self.total_ms.saturating_sub(self.first_token_ms).max(1);
+        self.tokens as f64 * 1000.0 / generation_ms as f64
+    }
+}
+
+/// `/v1/models` 的回應
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+impl OpenClawClient {
+    /// 建立新的 OpenClaw 客戶端
+    pub fn new(base_url: String, gateway_token: Option<String>, model: String, streaming: bool) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(std::time::Duration::from_secs(60))
+                .build()
+                .unwrap_or_else(|_| Client::new()),
+            base_url,
+            gateway_token: RwLock::new(gateway_token),
+            active_model: RwLock::new(model.clone()),
+            available_models: RwLock::new(None),
+            model,
+            streaming,
+            send_metadata: true,
+        }
+    }
+
+    /// 設定是否在請求附上 `user` 與 `metadata`（不接受額外欄位的伺服器可關閉）
+    pub fn with_metadata(mut self, enabled: bool) -> Self {
+        self.send_metadata = enabled;
+        self
+    }
+
+    /// 更換 gateway token（之後的請求立即使用新 token）
+    pub fn set_gateway_token(&self, token: Option<String>) {
+        *self.gateway_token.write().unwrap() = token;
+    }
+
+    /// 目前使用的模型名稱（設定的模型不存在時為改用的預設模型）
+    pub fn model(&self) -> String {
+        self.active_model.read().unwrap().clone()
+    }
+
+    /// 設定的模型名稱
+    pub fn configured_model(&self) -> &str {
+        &self.model
+    }
+
+    /// 最近一次取得的可用模型；尚未取得時為 None
+    pub fn available_models(&self) -> Option<Vec<String>> {
+        self.available_models.read().unwrap().clone()
+    }
+
+    /// 模型是否可用；尚未取得清單時一律視為可用
+    pub fn is_available(&self, model: &str) -> bool {
+        self.available_models.read().unwrap().as_ref().is_none_or(|models| models.iter().any(|m| m == model))
+    }
+
+    /// 取得 OpenClaw 的可用模型清單（`/v1/models`）
+    pub async fn fetch_models(&self) -> Result<Vec<String>, String> {
+        let mut builder = self.client.get(format!("{}/v1/models", self.base_url));
+        if let Some(token) = self.gateway_token.read().unwrap().as_ref() {
+            builder = builder.header("Authorization", format!("Bearer {}", token));
+        }
+        let list: ModelList = builder
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("無法取得模型清單: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("模型清單格式錯誤: {}", e))?;
+        Ok(list.data.into_iter().map(|m| m.id).collect())
+    }
+
+    /// 更新可用模型清單並重新決定使用的模型：設定的模型不存在時改用 [`DEFAULT_MODEL`]，
+    /// 預設模型也不存在時改用清單中的第一個；回傳使用的模型
+    pub fn set_available_models(&self, models: Vec<String>) -> String {
+        let contains = |model: &str| models.iter().any(|m| m == model);
+        let active = if models.is_empty() || contains(&self.model) {
+            self.model.clone()
+        } else if contains(DEFAULT_MODEL) {
+            DEFAULT_MODEL.to_string()
+        } else {
+            models[0].clone()
+        };
+        let mut current = self.active_model.write().unwrap();
+        if *current != active {
+            if active == self.model {
+                info!("Configured model {} is available again", self.model);
+            } else {
+                warn!("Configured model {} is not served by OpenClaw, falling back to {}", self.model, active);
+            }
+            *current = active.clone();
+        }
+        *self.available_models.write().unwrap() = Some(models);
+        active
+    }
+
+    /// 指定的模型不存在時改用目前的模型，避免每次呼叫都得到 404
+    fn resolve(&self, model: &str) -> String {
+        if self.is_available(model) {
+            return model.to_string();
+        }
+        let active = self.model();
+        warn!("Model {} is not served by OpenClaw, using {}", model, active);
+        active
+    }
+
+    /// 檢查 OpenClaw 是否在線
+    pub async fn health_check(&self) -> Result<bool, reqwest::Error> {
+        let url = format!("{}/health", self.base_url);
+        
+        let response = self.client
+            .get(&url)
+            .send()
+            .await?;
+        
+        Ok(response.status().is_success())
+    }
+
+    /// 發送訊息給 OpenClaw 並取得回應
+    /// 使用 OpenAI-compatible Chat Completions API，`history` 為先前的對話（舊到新）；啟用串流時一併回傳時間統計。
+    /// 超出 context window 時捨棄較舊的一半對話重試一次
+    pub async fn send_message(
+        &self,
+        user_id: &str,
+        history: Vec<ChatMessage>,
+        message: &str,
+    ) -> Result<(String, Option<StreamTimings>), String> {
+        info!("Sending message to OpenClaw: user={}, message={}", privacy::id(user_id), privacy::text(message));
+        self.send_with_history(user_id, history, message, None).await.map_err(String::from)
+    }
+
+    /// 帶對話脈絡發送；超出 context window 時捨棄較舊的一半對話（開頭的系統指示除外）重試一次。
+    /// 指定 `response_format` 時要求結構化結果且不使用串流
+    async fn send_with_history(
+        &self,
+        user_id: &str,
+        mut history: Vec<ChatMessage>,
+        message: &str,
+        response_format: Option<serde_json::Value>,
+    ) -> Result<(String, Option<StreamTimings>), CallError> {
+        let mut retried = false;
+        loop {
+            let mut messages = history.clone();
+            messages.push(ChatMessage {
+                role: "user".to_string(),
+                content: message.to_string(),
+            });
+            let result = if self.streaming && response_format.is_none() {
+                self.stream(user_id, messages).await.map(|(content, timings)| (content, Some(timings)))
+            } else {
+                self.complete(user_id, messages, response_format.clone()).await.map(|content| (content, None))
+            };
+            // 開頭的系統指示不參與縮減
+            let pinned = history.iter().take_while(|m| m.role == "system").count();
+            match result {
+                Err(CallError::ContextOverflow(e)) if !retried && history.len() > pinned => {
+                    let dropped = (history.len() - pinned).div_ceil(2);
+                    warn!(
+                        "OpenClaw context overflow for user={}, dropping {} of {} history messages and retrying: {}",
+                        privacy::id(user_id),
+                        dropped,
+                        history.len() - pinned,
+                        e
+                    );
+                    history.drain(pinned..pinned + dropped);
+                    retried = true;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// 附上系統指示發送訊息（簡報摘要等非對話用途）
+    pub async fn send_with_instructions(&self, user_id: &str, instructions: &str, message: &str) -> Result<String, String> {
+        info!("Sending instructed request to OpenClaw: user={}", privacy::id(user_id));
+        self.complete(user_id, instructed(instructions, message), None).await.map_err(String::from)
+    }
+
+    /// 要求 OpenClaw 依 JSON Schema 回傳結構化結果（回傳 JSON 字串，去除 ``` 包裝）
+    pub async fn send_structured(
+        &self,
+        user_id: &str,
+        instructions: &str,
+        message: &str,
+        name: &str,
+        schema: serde_json::Value,
+    ) -> Result<String, String> {
+        info!("Sending structured request to OpenClaw: user={}, schema={}", privacy::id(user_id), name);
+        let response_format = serde_json::json!({
+            "type": "json_schema",
+            "json_schema": { "name": name, "schema": schema }
+        });
+        let content = self.complete(user_id, instructed(instructions, message), Some(response_format)).await?;
+        Ok(strip_code_fence(&content))
+    }
+
+    /// 附上對話脈絡要求結構化結果（不使用串流）；`history` 開頭可放系統指示，超出 context window 時與 [`Self::send_message`] 同樣縮減重試
+    pub async fn send_message_structured(
+        &self,
+        user_id: &str,
+        history: Vec<ChatMessage>,
+        message: &str,
+        name: &str,
+        schema: serde_json::Value,
+    ) -> Result<String, String> {
+        info!("Sending structured message to OpenClaw: user={}, schema={}", privacy::id(user_id), name);
+        let response_format = serde_json::json!({
+            "type": "json_schema",
+            "json_schema": { "name": name, "schema": schema }
+        });
+        let (content, _) = self.send_with_history(user_id, history, message, Some(response_format)).await?;
+        Ok(strip_code_fence(&content))
+    }
+
+    /// 以完整的訊息列表發送（多步驟流程等），`model` 未指定時使用預設模型
+    pub async fn send_chat(&self, user_id: &str, model: Option<&str>, messages: Vec<ChatMessage>) -> Result<String, String> {
+        let model = model.map_or_else(|| self.model(), |model| self.resolve(model));
+        info!("Sending chat to OpenClaw: user={}, model={}", privacy::id(user_id), model);
+        self.complete_with(user_id, &model, messages, None).await.map_err(String::from)
+    }
+
+    async fn complete(
+        &self,
+        user_id: &str,
+        messages: Vec<ChatMessage>,
+        response_format: Option<serde_json::Value>,
+    ) -> Result<String, CallError> {
+        self.complete_with(user_id, &self.model(), messages, response_format).await
+    }
+
+    async fn complete_with(
+        &self,
+        user_id: &str,
+        model: &str,
+        messages: Vec<ChatMessage>,
+        response_format: Option<serde_json::Value>,
+    ) -> Result<String, CallError> {
+        // 構建 Chat Completions 請求
+        let (user, metadata) = self.identity(user_id);
+        let request = ChatCompletionRequest {
+            model: model.to_string(),
+            messages,
+            stream: Some(false),
+            stream_options: None,
+            response_format,
+            user,
+            metadata,
+        };
+        
+        // 發送請求
+        let response = self.post(&request).await?;
+        match response.json::<ChatCompletionResponse>().await {
+            Ok(chat_response) => {
+                if let Some(choice) = chat_response.choices.first() {
+                    info!("Got response from OpenClaw: {}", privacy::text(&choice.message.content));
+                    return Ok(choice.message.content.clone());
+                }
+                Err(CallError::Other("OpenClaw 回應格式錯誤：沒有選擇項".to_string()))
+            }
+            Err(e) => {
+                error!("Failed to parse OpenClaw response: {}", e);
+                Err(CallError::Other(format!("解析 OpenClaw 回應失敗: {}", e)))
+            }
+        }
+    }
+
+    /// 請求的 `user` 與 `metadata`；ID 一律以隱私金鑰雜湊，未設定金鑰時不附上，不把原始 LINE ID 交給 OpenClaw
+    fn identity(&self, user_id: &str) -> (Option<String>, Option<BTreeMap<String, String>>) {
+        if !self.send_metadata {
+            return (None, None);
+        }
+        let Some(user) = privacy::pseudonym(user_id) else {
+            static WARNED: Once = Once::new();
+            WARNED.call_once(|| warn!("OpenClaw metadata is enabled but PRIVACY_HASH_KEY is not set, not sending user IDs"));
+            return (None, None);
+        };
+        let mut metadata = BTreeMap::from([("user_id".to_string(), user.clone())]);
+        let _ = CONTEXT.try_with(|context| {
+            metadata.insert("channel".to_string(), context.channel.to_string());
+            metadata.insert("chat_type".to_string(), context.chat_type.to_string());
+            if let Some(group_id) = context.group_id.as_deref().and_then(privacy::pseudonym) {
+                metadata.insert("group_id".to_string(), group_id);
+            }
+        });
+        (Some(user), Some(metadata))
+    }
+
+    /// 送出 Chat Completions 請求；非 2xx 時讀取錯誤內容判斷是否為 context 超限
+    async fn post(&self, request: &ChatCompletionRequest) -> Result<reqwest::Response, CallError> {
+        let url = format!("{}/v1/chat/completions", self.base_url);
+        let mut builder = self.client.post(&url).header("Content-Type", "application/json");
+        if let Some(token) = self.gateway_token.read().unwrap().as_ref() {
+            builder = builder.header("Authorization", format!("Bearer {}", token));
+        }
+        let response = builder.json(request).send().await.map_err(|e| {
+            error!("Failed to connect to OpenClaw: {}", e);
+            CallError::Other(format!("無法連接到 OpenClaw: {}", e))
+        })?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        error!("OpenClaw returned error status: {} {}", status, body);
+        let message = format!("OpenClaw 返回錯誤狀態: {}", status);
+        let lower = body.to_lowercase();
+        if matches!(status.as_u16(), 400 | 413 | 422) && CONTEXT_OVERFLOW_MARKERS.iter().any(|m| lower.contains(m)) {
+            return Err(CallError::ContextOverflow(message));
+        }
+        Err(CallError::Other(message))
+    }
+
+    /// 以串流方式取得回應，記錄第一個 token 的時間與生成速度
+    async fn stream(&self, user_id: &str, messages: Vec<ChatMessage>) -> Result<(String, StreamTimings), CallError> {
+        let (user, metadata) = self.identity(user_id);
+        let request = ChatCompletionRequest {
+            model: self.model(),
+            messages,
+            stream: Some(true),
+            stream_options: Some(serde_json::json!({ "include_usage": true })),
+            response_format: None,
+            user,
+            metadata,
+        };
+
+        let started = Instant::now();
+        let mut response = self.post(&request).await?;
+
+        let mut content = String::new();
+        let mut buffer = Vec::new();
+        let mut first_token = None;
+        let mut chunks = 0u64;
+        let mut usage = None;
+        'read: while let Some(bytes) = response.chunk().await.map_err(|e| format!("讀取 OpenClaw 串流失敗: {}", e))? {
+            buffer.extend_from_slice(&bytes);
+            // SSE 以行為單位，保留尚未收完的最後一行
+            while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else { continue };
+                if data == "[DONE]" {
+                    break 'read;
+                }
+                let chunk: ChatCompletionChunk =
+                    serde_json::from_str(data).map_err(|e| format!("解析 OpenClaw 串流失敗: {}", e))?;
+                if let Some(text) = chunk.choices.first().and_then(|c| c.delta.content.as_deref()) {
+                    if !text.is_empty() {
+                        first_token.get_or_insert_with(|| started.elapsed());
+                        chunks += 1;
+                        content.push_str(text);
+                    }
+                }
+                if let Some(u) = chunk.usage {
+                    usage = Some(u.completion_tokens);
+                }
+            }
+        }
+
+        let total = started.elapsed();
+        let first_token = first_token.ok_or("OpenClaw 串流沒有回傳任何內容")?;
+        let timings = StreamTimings {
+            first_token_ms: first_token.as_millis() as u64,
+            total_ms: total.as_millis() as u64,
+            tokens: usage.unwrap_or(chunks),
+        };
+        info!(
+            "Got streamed response from OpenClaw: ttft_ms={}, tokens={}, tokens_per_sec={:.1}, content={}",
+            timings.first_token_ms,
+            timings.tokens,
+            timings.tokens_per_sec(),
+            privacy::text(&content)
+        );
+        Ok((content, timings))
+    }
+
+    /// 透過 WebSocket 連接 OpenClaw（進階功能）
+    /// 這是更穩定的連接方式，但需要額外的 WebSocket 處理
+    pub async fn connect_websocket(&self) -> Result<(), String> {
+        // TODO: 實作 WebSocket 連接
+        // OpenClaw 主要使用 WebSocket 進行即時通訊
+        Err("WebSocket 連接尚未實作".to_string())
+    }
+}
+
+/// 系統指示 + 使用者訊息
+fn instructed(instructions: &str, message: &str) -> Vec<ChatMessage> {
+    vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: instructions.to_string(),
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: message.to_string(),
+        },
+    ]
+}
+
+/// 去除模型有時加上的 ``` 程式碼區塊包裝
+fn strip_code_fence(content: &str) -> String {
+    let content = content.trim();
+    let content = content
+        .strip_prefix("```json")
+        .or_else(|| content.strip_prefix("```"))
+        .and_then(|c| c.strip_suffix("```"))
+        .unwrap_or(content);
+    content.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(model: &str) -> OpenClawClient {
+        OpenClawClient::new("http://127.0.0.1:9".to_string(), None, model.to_string(), false)
+    }
+
+    fn models(names: &[&str]) -> Vec<String> {
+        names.iter().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn configured_model_is_kept_when_served_or_unknown() {
+        let client = client("model-a");
+        assert!(client.is_available("anything"));
+        assert_eq!(client.set_available_models(models(&["model-b", "model-a"])), "model-a");
+        assert_eq!(client.set_available_models(Vec::new()), "model-a");
+    }
+
+    #[test]
+    fn missing_model_falls_back_to_default_then_first() {
+        let client = client("typo");
+        assert_eq!(client.set_available_models(models(&["model-b", DEFAULT_MODEL])), DEFAULT_MODEL);
+        assert_eq!(client.set_available_models(models(&["model-b", "model-c"])), "model-b");
+        assert_eq!(client.model(), "model-b");
+        assert_eq!(client.set_available_models(models(&["typo"])), "typo");
+        assert_eq!(client.configured_model(), "typo");
+    }
+
+    #[test]
+    fn unavailable_requested_model_resolves_to_the_active_one() {
+        let client = client("model-a");
+        client.set_available_models(models(&["model-a", "model-b"]));
+        assert_eq!(client.resolve("model-b"), "model-b");
+        assert_eq!(client.resolve("missing"), "model-a");
+    }
+}
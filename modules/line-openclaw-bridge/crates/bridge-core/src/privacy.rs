@@ -0,0 +1,94 @@
+//! 隱私模組
+//! 設定 `PRIVACY_HASH_KEY` 後，日誌、錯誤回報與匯出中的使用者/群組 ID 一律以 HMAC 雜湊取代，IP 位址遮蔽末段；
+//! 持有金鑰者可用 `resolve` 指令反查 ID
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::OnceLock;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 雜湊金鑰；未設定代表隱私模式停用
+static KEY: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// 啟用隱私模式（僅第一次呼叫生效）
+pub fn init(key: &str) {
+    let _ = KEY.set(key.as_bytes().to_vec());
+}
+
+/// 是否啟用隱私模式
+pub fn enabled() -> bool {
+    KEY.get().is_some()
+}
+
+/// 以金鑰計算 ID 的雜湊，保留 LINE ID 的類型字首（U 使用者、C 群組、R 聊天室），例如 `U#3fa1…`
+pub fn hash_with(key: &[u8], id: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC 可接受任意長度金鑰");
+    mac.update(id.as_bytes());
+    let kind = id.chars().next().filter(char::is_ascii_uppercase).unwrap_or('X');
+    format!("{}#{}", kind, hex::encode(&mac.finalize().into_bytes()[..8]))
+}
+
+/// 隱私模式啟用時回傳雜湊，否則原樣回傳；寫入日誌或匯出前使用
+pub fn id(id: &str) -> Cow<'_, str> {
+    match KEY.get() {
+        Some(key) => Cow::Owned(hash_with(key, id)),
+        None => Cow::Borrowed(id),
+    }
+}
+
+/// 隱私模式啟用時只回傳字數，否則原樣回傳；記錄使用者訊息或 AI 回答前使用
+pub fn text(text: &str) -> Cow<'_, str> {
+    match KEY.get() {
+        Some(_) => Cow::Owned(format!("<{} chars>", text.chars().count())),
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// 送往外部服務的 ID：以隱私模式的金鑰雜湊（與 [`id`] 相同）；未設定金鑰時回傳 None，
+/// 不帶金鑰的雜湊可被任何持有原始 ID 的人比對，不應送出
+pub fn pseudonym(id: &str) -> Option<String> {
+    KEY.get().map(|key| hash_with(key, id))
+}
+
+/// 隱私模式啟用時遮蔽 IP 位址末段（IPv4 保留前 24 位元、IPv6 保留前 48 位元），否則原樣回傳；寫入日誌前使用
+pub fn ip(ip: IpAddr) -> IpAddr {
+    if !enabled() {
+        return ip;
+    }
+    match ip {
+        IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from(u32::from(v4) & 0xFFFF_FF00)),
+        IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(u128::from(v6) & !((1u128 << 80) - 1))),
+    }
+}
+
+/// 在候選 ID 中找出雜湊相符者
+pub fn resolve(key: &[u8], hash: &str, candidates: impl IntoIterator<Item = String>) -> Option<String> {
+    let hash = hash.trim();
+    candidates.into_iter().find(|id| hash_with(key, id) == hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_keeps_the_id_kind_and_depends_on_the_key() {
+        let hash = hash_with(b"key", "U0123");
+        assert!(hash.starts_with("U#"));
+        assert_eq!(hash.len(), 2 + 16);
+        assert_eq!(hash, hash_with(b"key", "U0123"));
+        assert_ne!(hash, hash_with(b"other", "U0123"));
+        assert!(hash_with(b"key", "group").starts_with("X#"));
+    }
+
+    #[test]
+    fn resolve_finds_the_matching_candidate() {
+        let hash = hash_with(b"key", "C42");
+        let candidates = ["C41", "C42"].map(String::from);
+        assert_eq!(resolve(b"key", &format!(" {} ", hash), candidates.clone()).as_deref(), Some("C42"));
+        assert_eq!(resolve(b"other", &hash, candidates), None);
+    }
+}
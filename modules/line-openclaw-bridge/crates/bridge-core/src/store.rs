@@ -0,0 +1,2767 @@
+//! 對話儲存模組
+//! 以 SQLite (FTS5) 保存對話紀錄並提供全文搜尋
+
+use chrono::NaiveDate;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::clock::{self, Clock};
+use crate::compression::{self, Codec};
+
+/// 搜尋結果摘錄的前後字數
+const EXCERPT_RADIUS: usize = 30;
+
+/// `?1` 本身及與其連結的所有身分
+const LINKED_IDS_SQL: &str = "SELECT ?1 UNION SELECT user_id FROM identity_links
+     WHERE account_id = (SELECT account_id FROM identity_links WHERE user_id = ?1)";
+
+/// `automations` 的欄位（`automation_from_row` 的順序）
+const AUTOMATION_COLUMNS: &str =
+    "id, user_id, target, name, time, weekdays, timezone, kind, content, status, last_run_on, created_at";
+
+/// `feed_sources` 的欄位（讀寫共用的順序）
+const FEED_SOURCE_COLUMNS: &str = "url, etag, last_modified, titles, fetches, not_modified, failures, \
+     consecutive_failures, robots_blocked, last_error, last_fetched_at, retry_at";
+
+/// 資料表（列數統計與軟上限使用）
+pub const TABLES: [&str; 28] = [
+    "conversations",
+    "audit_log",
+    "announcement_templates",
+    "announcements",
+    "archives",
+    "snippets",
+    "answer_pages",
+    "polls",
+    "poll_votes",
+    "quizzes",
+    "quiz_answers",
+    "quiz_scores",
+    "briefing_settings",
+    "reminders",
+    "notification_queue",
+    "dead_letters",
+    "dry_run_messages",
+    "identity_links",
+    "link_codes",
+    "link_attempts",
+    "incidents",
+    "incident_opt_outs",
+    "sessions",
+    "user_profiles",
+    "feed_sources",
+    "group_settings",
+    "automations",
+    "compression_dicts",
+];
+
+/// 附加於訊息的媒體檔
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Attachment {
+    Image {
+        key: String,
+        /// 預覽圖（未指定時使用原圖）
+        preview_key: Option<String>,
+    },
+    Audio {
+        key: String,
+        duration_ms: u64,
+    },
+}
+
+/// 單一題目（OpenClaw 結構化輸出）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuizQuestion {
+    pub question: String,
+    pub options: Vec<String>,
+    /// 正確選項的索引（從 0 開始）
+    pub answer: usize,
+    pub explanation: String,
+}
+
+/// 對話儲存（SQLite）
+pub struct ConversationStore {
+    conn: Mutex<Connection>,
+    /// 對話內容的壓縮設定與字典
+    codec: Arc<Codec>,
+    /// 寫入時間、到期與對話階段逾時使用的時鐘
+    clock: Arc<dyn Clock>,
+}
+
+/// 壓縮既有對話的結果
+#[derive(Debug, Default, Serialize)]
+pub struct CompressionReport {
+    /// 這次新訓練的字典 ID
+    pub trained_dictionary: Option<u32>,
+    /// 這次壓縮的對話筆數
+    pub compressed_rows: usize,
+    /// 壓縮前後的內容位元組數
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// 時間區間（Unix 秒，`from` 含、`to` 不含）
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TimeRange {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+impl TimeRange {
+    /// 由日期建立區間（UTC，兩端日期皆包含）
+    pub fn from_dates(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Self {
+        let day_start = |d: NaiveDate| d.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+        Self {
+            from: from.map(day_start),
+            to: to.and_then(|d| d.succ_opt()).map(day_start),
+        }
+    }
+}
+
+/// 搜尋條件
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    pub keywords: Vec<String>,
+    pub user_id: Option<String>,
+    /// 一併搜尋與 `user_id` 連結的其他平台身分
+    pub include_linked: bool,
+    pub range: TimeRange,
+    pub model: Option<String>,
+    pub limit: usize,
+}
+
+/// 待寫入的稽核事件
+#[derive(Debug, Default)]
+pub struct AuditEvent<'a> {
+    pub user_id: &'a str,
+    /// LINE 事件類型（message、postback…）
+    pub event_type: &'a str,
+    /// 處理動作（chat、command、reply…）
+    pub action: &'a str,
+    /// 結果（ok、fallback、error）
+    pub status: &'a str,
+    pub model: Option<&'a str>,
+    pub latency_ms: Option<i64>,
+    pub detail: Option<&'a str>,
+}
+
+/// 稽核紀錄
+#[derive(Debug, Serialize)]
+pub struct AuditRecord {
+    pub id: i64,
+    pub created_at: i64,
+    pub user_id: String,
+    pub event_type: String,
+    pub action: String,
+    pub status: String,
+    pub model: Option<String>,
+    pub latency_ms: Option<i64>,
+    pub detail: Option<String>,
+}
+
+/// 每日用量統計（依模型分組）
+#[derive(Debug, Serialize)]
+pub struct UsageRecord {
+    pub day: String,
+    pub model: String,
+    pub requests: i64,
+    pub fallbacks: i64,
+    pub errors: i64,
+    pub unique_users: i64,
+    pub avg_latency_ms: Option<f64>,
+}
+
+/// 公告範本
+#[derive(Debug, Serialize)]
+pub struct AnnouncementTemplate {
+    pub name: String,
+    pub body: String,
+    /// 以此範本發出的一般通知的合併時間窗（秒）；0 代表不合併，未設定則使用預設值
+    pub batch_window_secs: Option<i64>,
+    pub updated_at: i64,
+}
+
+/// 公告（草稿 → 預覽 → 核准 → 發送）
+#[derive(Debug, Serialize)]
+pub struct Announcement {
+    pub id: i64,
+    pub template: Option<String>,
+    pub text: String,
+    pub attachments: Vec<Attachment>,
+    /// 指定的收件人（以 multicast 送出）；None 代表廣播給所有好友
+    pub recipients: Option<Vec<String>>,
+    /// 以群發（narrowcast）送出時 LINE 回傳的 request ID；群發在 LINE 完成前狀態為 `sending`
+    pub narrowcast_request_id: Option<String>,
+    /// 已送出的收件人數（依 `recipients` 的順序逐批記錄），送出失敗後再次核准時由此繼續
+    pub delivered: usize,
+    pub status: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// 死信：reply 與 push 都失敗、未能送達的回覆
+#[derive(Debug, Serialize)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub user_id: String,
+    /// push 的對象（使用者、群組或聊天室 ID）
+    pub target: String,
+    pub event_type: String,
+    pub messages: serde_json::Value,
+    /// 最後一步失敗的原因代碼
+    pub reason: String,
+    /// 依序嘗試的每一步（原因代碼與錯誤訊息）
+    pub steps: Vec<String>,
+    pub created_at: i64,
+}
+
+/// 試運行時攔下、未實際送出的訊息
+#[derive(Debug, Serialize)]
+pub struct DryRunEntry {
+    pub id: i64,
+    /// `reply`、`push`、`multicast` 或 `broadcast`
+    pub endpoint: String,
+    /// reply token 或推送對象；廣播時為 None
+    pub target: Option<String>,
+    /// 原本要送給 LINE 的請求內容
+    pub body: serde_json::Value,
+    pub created_at: i64,
+}
+
+/// 快取的 LINE 使用者個人資料
+#[derive(Debug, Serialize)]
+pub struct UserProfile {
+    pub user_id: String,
+    pub display_name: Option<String>,
+    pub language: Option<String>,
+    pub picture_url: Option<String>,
+    pub status_message: Option<String>,
+    /// 使用者已封鎖機器人（取消好友或取得資料 404）
+    pub blocked: bool,
+    /// 未加好友的群組或聊天室成員：資料取自此群組或聊天室的成員個人資料
+    pub member_of: Option<String>,
+    pub fetched_at: i64,
+    pub updated_at: i64,
+}
+
+/// 由 LINE 取得、要保存的個人資料
+pub struct ProfileFields<'a> {
+    pub display_name: &'a str,
+    pub language: Option<&'a str>,
+    pub picture_url: Option<&'a str>,
+    pub status_message: Option<&'a str>,
+    /// 取自成員個人資料時為該群組或聊天室 ID；好友的個人資料為 None
+    pub member_of: Option<&'a str>,
+}
+
+/// 使用者資料快照的格式版本（2 起包含對話階段）
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// 使用者資料快照：對話紀錄與個人設定，供舊實例匯出後匯入新的資料庫
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSnapshot {
+    pub version: u32,
+    pub exported_at: i64,
+    /// 對話紀錄（匯入時重新編號）；串流匯出時另外附加在最後，為空時不輸出
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conversations: Vec<ConversationRecord>,
+    /// 對話階段（匯入時重新編號，對話紀錄的 `session_id` 一併對應）
+    pub sessions: Vec<SnapshotSession>,
+    pub briefings: Vec<BriefingSettings>,
+    /// 尚未送出的提醒
+    pub reminders: Vec<SnapshotReminder>,
+    pub identity_links: Vec<SnapshotLink>,
+    /// 關閉事故通知的使用者
+    pub incident_opt_outs: Vec<String>,
+    pub profiles: Vec<SnapshotProfile>,
+}
+
+/// 快照中的對話階段
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotSession {
+    pub id: i64,
+    pub user_id: String,
+    pub title: Option<String>,
+    pub started_at: i64,
+    pub last_at: i64,
+}
+
+/// 快照中的提醒
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotReminder {
+    pub user_id: String,
+    pub text: String,
+    pub created_at: i64,
+}
+
+/// 快照中的身分連結
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotLink {
+    pub user_id: String,
+    pub account_id: String,
+    pub linked_at: i64,
+}
+
+/// 快照中的個人資料（封鎖狀態由新實例重新偵測）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotProfile {
+    pub user_id: String,
+    pub display_name: Option<String>,
+    pub language: Option<String>,
+}
+
+/// 匯入快照時各項實際寫入的筆數
+#[derive(Debug, Default, Serialize)]
+pub struct SnapshotImport {
+    pub conversations: usize,
+    pub sessions: usize,
+    pub briefings: usize,
+    pub reminders: usize,
+    pub identity_links: usize,
+    pub incident_opt_outs: usize,
+    pub profiles: usize,
+}
+
+/// 完整對話紀錄（封存與還原用）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConversationRecord {
+    pub id: i64,
+    pub user_id: String,
+    pub role: String,
+    pub content: String,
+    pub model: Option<String>,
+    pub created_at: i64,
+    /// 所屬對話階段（舊版封存檔與快照沒有此欄位）
+    #[serde(default)]
+    pub session_id: Option<i64>,
+}
+
+/// 兌換帳號連結碼的結果
+#[derive(Debug, PartialEq, Eq)]
+pub enum LinkRedemption {
+    /// 已連結，附產生連結碼的身分
+    Linked(String),
+    /// 連結碼不存在、過期或屬於自己
+    Invalid,
+    /// 短時間內輸入錯誤太多次，暫時不受理
+    Locked,
+}
+
+/// 封存檔資訊
+#[derive(Debug, Serialize)]
+pub struct ArchiveInfo {
+    pub name: String,
+    pub row_count: i64,
+    pub first_created_at: i64,
+    pub last_created_at: i64,
+    pub archived_at: i64,
+    pub restored_at: Option<i64>,
+}
+
+/// AI 回答中的程式碼片段（供複製頁面使用）
+#[derive(Debug)]
+pub struct Snippet {
+    pub language: Option<String>,
+    pub code: String,
+}
+
+/// 投票（open → closed）
+#[derive(Debug)]
+pub struct Poll {
+    pub id: i64,
+    /// 群組/聊天室 ID，一對一時為 userId
+    pub chat_id: String,
+    pub creator_id: String,
+    pub question: String,
+    pub options: Vec<String>,
+    pub status: String,
+    pub deadline: Option<i64>,
+}
+
+/// 問答遊戲（active → finished / stopped）
+#[derive(Debug)]
+pub struct Quiz {
+    pub id: i64,
+    pub chat_id: String,
+    pub questions: Vec<QuizQuestion>,
+    /// 目前題號（從 0 開始）
+    pub current: usize,
+    pub status: String,
+}
+
+/// 排行榜上的一位使用者
+#[derive(Debug, Serialize)]
+pub struct QuizScore {
+    pub user_id: String,
+    pub correct: i64,
+    pub answered: i64,
+}
+
+/// 每日簡報設定（每位使用者一筆）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BriefingSettings {
+    pub user_id: String,
+    pub enabled: bool,
+    /// 當地時間 `HH:MM`
+    pub time: String,
+    /// IANA 時區（如 `Asia/Taipei`）
+    pub timezone: String,
+    pub city: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// iCalendar（.ics）網址
+    pub calendar_url: Option<String>,
+    /// RSS / Atom 網址
+    pub feeds: Vec<String>,
+    /// 最後一次送出的當地日期（`YYYY-MM-DD`）
+    pub last_sent_on: Option<String>,
+}
+
+/// 待提醒事項（隨下一次簡報送出）
+#[derive(Debug)]
+pub struct Reminder {
+    pub id: i64,
+    pub text: String,
+}
+
+/// 管理員以對話建立的自動化排程（draft → active / cancelled）
+#[derive(Debug, Clone, Serialize)]
+pub struct Automation {
+    pub id: i64,
+    /// 建立者（管理員）
+    pub user_id: String,
+    /// 推送對象（建立時所在的聊天）
+    pub target: String,
+    pub name: String,
+    /// 當地時間 `HH:MM`
+    pub time: String,
+    /// 執行的星期（1 = 星期一 … 7 = 星期日），空白代表每天
+    pub weekdays: Vec<u32>,
+    /// IANA 時區（如 `Asia/Taipei`）
+    pub timezone: String,
+    /// `prompt`（交由 OpenClaw 產生內容）或 `message`（推送固定文字）
+    pub kind: String,
+    pub content: String,
+    pub status: String,
+    /// 最後一次執行的當地日期（`YYYY-MM-DD`）
+    pub last_run_on: Option<String>,
+    pub created_at: i64,
+}
+
+/// RSS 來源的條件式抓取狀態與統計（所有使用者共用）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FeedSource {
+    pub url: String,
+    /// 上次回應的 `ETag` 與 `Last-Modified`，下次以 `If-None-Match` / `If-Modified-Since` 帶回
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// 上次成功取得的標題（304 或暫停抓取時沿用）
+    pub titles: Vec<String>,
+    pub fetches: i64,
+    pub not_modified: i64,
+    pub failures: i64,
+    pub consecutive_failures: i64,
+    /// robots.txt 不允許抓取的次數
+    pub robots_blocked: i64,
+    pub last_error: Option<String>,
+    pub last_fetched_at: Option<i64>,
+    /// 連續失敗後暫停抓取至此時間
+    pub retry_at: Option<i64>,
+}
+
+/// 完整回答頁面（LIFF）
+#[derive(Debug)]
+pub struct AnswerPage {
+    pub markdown: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+/// 服務事故（如 OpenClaw 離線）
+#[derive(Debug, Serialize)]
+pub struct Incident {
+    pub id: i64,
+    pub kind: String,
+    pub detail: Option<String>,
+    pub started_at: i64,
+    /// 尚未排除為 null
+    pub resolved_at: Option<i64>,
+    /// 已收到事故通知的使用者，尚未通知為 null
+    pub announced_to: Option<Vec<String>>,
+}
+
+/// 對話階段（兩則訊息間隔超過設定時間即開新階段）
+#[derive(Debug, Serialize)]
+pub struct Session {
+    pub id: i64,
+    pub user_id: String,
+    /// 由 OpenClaw 產生的標題，尚未產生為 null
+    pub title: Option<String>,
+    pub started_at: i64,
+    pub last_at: i64,
+    /// 對話則數
+    pub messages: i64,
+}
+
+/// 單筆搜尋結果
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub id: i64,
+    pub user_id: String,
+    pub role: String,
+    pub model: Option<String>,
+    pub excerpt: String,
+    pub created_at: i64,
+}
+
+impl ConversationStore {
+    /// 開啟（或建立）SQLite 資料庫，使用系統時鐘
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        Self::open_with_clock(path, clock::system())
+    }
+
+    /// 開啟（或建立）SQLite 資料庫，時間相關的判斷使用指定的時鐘
+    pub fn open_with_clock(path: &str, clock: Arc<dyn Clock>) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        let codec = Arc::new(Codec::default());
+        compression::register(&conn, codec.clone())?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS conversations (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 user_id TEXT NOT NULL,
+                 role TEXT NOT NULL,
+                 content TEXT NOT NULL,
+                 model TEXT,
+                 created_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS idx_conversations_user_time
+                 ON conversations (user_id, created_at);
+             CREATE VIRTUAL TABLE IF NOT EXISTS conversations_fts USING fts5(
+                 content, content = 'conversations', content_rowid = 'id', tokenize = 'trigram'
+             );
+             DROP TRIGGER IF EXISTS conversations_ai;
+             CREATE TRIGGER conversations_ai AFTER INSERT ON conversations BEGIN
+                 INSERT INTO conversations_fts (rowid, content) VALUES (new.id, conversation_text(new.content));
+             END;
+             DROP TRIGGER IF EXISTS conversations_ad;
+             CREATE TRIGGER conversations_ad AFTER DELETE ON conversations BEGIN
+                 INSERT INTO conversations_fts (conversations_fts, rowid, content)
+                     VALUES ('delete', old.id, conversation_text(old.content));
+             END;
+             CREATE TABLE IF NOT EXISTS compression_dicts (
+                 id INTEGER PRIMARY KEY,
+                 dictionary BLOB NOT NULL,
+                 samples INTEGER NOT NULL,
+                 created_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS audit_log (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 created_at INTEGER NOT NULL,
+                 user_id TEXT NOT NULL,
+                 event_type TEXT NOT NULL,
+                 action TEXT NOT NULL,
+                 status TEXT NOT NULL,
+                 model TEXT,
+                 latency_ms INTEGER,
+                 detail TEXT
+             );
+             CREATE INDEX IF NOT EXISTS idx_audit_log_time ON audit_log (created_at);
+             CREATE TABLE IF NOT EXISTS announcement_templates (
+                 name TEXT PRIMARY KEY,
+                 body TEXT NOT NULL,
+                 updated_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS announcements (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 template TEXT,
+                 text TEXT NOT NULL,
+                 status TEXT NOT NULL,
+                 created_at INTEGER NOT NULL,
+                 updated_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS archives (
+                 name TEXT PRIMARY KEY,
+                 row_count INTEGER NOT NULL,
+                 first_created_at INTEGER NOT NULL,
+                 last_created_at INTEGER NOT NULL,
+                 archived_at INTEGER NOT NULL,
+                 restored_at INTEGER
+             );
+             CREATE TABLE IF NOT EXISTS snippets (
+                 id TEXT PRIMARY KEY,
+                 user_id TEXT NOT NULL,
+                 language TEXT,
+                 code TEXT NOT NULL,
+                 created_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS answer_pages (
+                 id TEXT PRIMARY KEY,
+                 user_id TEXT NOT NULL,
+                 markdown TEXT NOT NULL,
+                 created_at INTEGER NOT NULL,
+                 expires_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS polls (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 chat_id TEXT NOT NULL,
+                 creator_id TEXT NOT NULL,
+                 question TEXT NOT NULL,
+                 options TEXT NOT NULL,
+                 status TEXT NOT NULL,
+                 deadline INTEGER,
+                 created_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS idx_polls_chat_status ON polls (chat_id, status);
+             CREATE TABLE IF NOT EXISTS poll_votes (
+                 poll_id INTEGER NOT NULL,
+                 user_id TEXT NOT NULL,
+                 option INTEGER NOT NULL,
+                 created_at INTEGER NOT NULL,
+                 PRIMARY KEY (poll_id, user_id)
+             );
+             CREATE TABLE IF NOT EXISTS quizzes (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 chat_id TEXT NOT NULL,
+                 creator_id TEXT NOT NULL,
+                 topic TEXT,
+                 questions TEXT NOT NULL,
+                 current INTEGER NOT NULL,
+                 status TEXT NOT NULL,
+                 created_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS idx_quizzes_chat_status ON quizzes (chat_id, status);
+             CREATE TABLE IF NOT EXISTS quiz_answers (
+                 quiz_id INTEGER NOT NULL,
+                 question INTEGER NOT NULL,
+                 user_id TEXT NOT NULL,
+                 option INTEGER NOT NULL,
+                 correct INTEGER NOT NULL,
+                 created_at INTEGER NOT NULL,
+                 PRIMARY KEY (quiz_id, question, user_id)
+             );
+             CREATE TABLE IF NOT EXISTS quiz_scores (
+                 chat_id TEXT NOT NULL,
+                 user_id TEXT NOT NULL,
+                 correct INTEGER NOT NULL,
+                 answered INTEGER NOT NULL,
+                 updated_at INTEGER NOT NULL,
+                 PRIMARY KEY (chat_id, user_id)
+             );
+             CREATE TABLE IF NOT EXISTS briefing_settings (
+                 user_id TEXT PRIMARY KEY,
+                 enabled INTEGER NOT NULL,
+                 time TEXT NOT NULL,
+                 timezone TEXT NOT NULL,
+                 city TEXT,
+                 latitude REAL,
+                 longitude REAL,
+                 calendar_url TEXT,
+                 feeds TEXT NOT NULL,
+                 last_sent_on TEXT,
+                 updated_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS reminders (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 user_id TEXT NOT NULL,
+                 text TEXT NOT NULL,
+                 created_at INTEGER NOT NULL,
+                 delivered_at INTEGER
+             );
+             CREATE INDEX IF NOT EXISTS idx_reminders_pending ON reminders (user_id, delivered_at);
+             CREATE TABLE IF NOT EXISTS notification_queue (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 user_id TEXT NOT NULL,
+                 template TEXT,
+                 text TEXT NOT NULL,
+                 created_at INTEGER NOT NULL,
+                 due_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS idx_notification_queue_user ON notification_queue (user_id, due_at);
+             CREATE TABLE IF NOT EXISTS dead_letters (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 user_id TEXT NOT NULL,
+                 target TEXT NOT NULL,
+                 event_type TEXT NOT NULL,
+                 messages TEXT NOT NULL,
+                 reason TEXT NOT NULL,
+                 steps TEXT NOT NULL,
+                 created_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS dry_run_messages (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 endpoint TEXT NOT NULL,
+                 target TEXT,
+                 body TEXT NOT NULL,
+                 created_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS identity_links (
+                 user_id TEXT PRIMARY KEY,
+                 account_id TEXT NOT NULL,
+                 linked_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS idx_identity_links_account ON identity_links (account_id);
+             CREATE TABLE IF NOT EXISTS link_codes (
+                 code TEXT PRIMARY KEY,
+                 user_id TEXT NOT NULL,
+                 expires_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS link_attempts (
+                 user_id TEXT PRIMARY KEY,
+                 failures INTEGER NOT NULL,
+                 first_failed_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS incidents (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 kind TEXT NOT NULL,
+                 detail TEXT,
+                 started_at INTEGER NOT NULL,
+                 resolved_at INTEGER
+             );
+             CREATE INDEX IF NOT EXISTS idx_incidents_started ON incidents (started_at);
+             CREATE TABLE IF NOT EXISTS incident_opt_outs (
+                 user_id TEXT PRIMARY KEY,
+                 created_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS sessions (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 user_id TEXT NOT NULL,
+                 title TEXT,
+                 started_at INTEGER NOT NULL,
+                 last_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS idx_sessions_user_last ON sessions (user_id, last_at);
+             CREATE TABLE IF NOT EXISTS user_profiles (
+                 user_id TEXT PRIMARY KEY,
+                 display_name TEXT,
+                 language TEXT,
+                 blocked INTEGER NOT NULL DEFAULT 0,
+                 fetched_at INTEGER NOT NULL,
+                 updated_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS idx_user_profiles_fetched ON user_profiles (fetched_at);
+             CREATE TABLE IF NOT EXISTS feed_sources (
+                 url TEXT PRIMARY KEY,
+                 etag TEXT,
+                 last_modified TEXT,
+                 titles TEXT NOT NULL,
+                 fetches INTEGER NOT NULL DEFAULT 0,
+                 not_modified INTEGER NOT NULL DEFAULT 0,
+                 failures INTEGER NOT NULL DEFAULT 0,
+                 consecutive_failures INTEGER NOT NULL DEFAULT 0,
+                 robots_blocked INTEGER NOT NULL DEFAULT 0,
+                 last_error TEXT,
+                 last_fetched_at INTEGER,
+                 retry_at INTEGER
+             );
+             CREATE TABLE IF NOT EXISTS group_settings (
+                 chat_id TEXT PRIMARY KEY,
+                 mention_only INTEGER NOT NULL,
+                 updated_at INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS automations (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 user_id TEXT NOT NULL,
+                 target TEXT NOT NULL,
+                 name TEXT NOT NULL,
+                 time TEXT NOT NULL,
+                 weekdays TEXT NOT NULL,
+                 timezone TEXT NOT NULL,
+                 kind TEXT NOT NULL,
+                 content TEXT NOT NULL,
+                 status TEXT NOT NULL,
+                 last_run_on TEXT,
+                 created_at INTEGER NOT NULL,
+                 updated_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS idx_automations_status ON automations (status);",
+        )?;
+        add_column_if_missing(&conn, "announcements", "attachments", "TEXT")?;
+        add_column_if_missing(&conn, "announcements", "recipients", "TEXT")?;
+        add_column_if_missing(&conn, "announcements", "narrowcast_request_id", "TEXT")?;
+        add_column_if_missing(&conn, "announcements", "delivered", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column_if_missing(&conn, "announcement_templates", "batch_window_secs", "INTEGER")?;
+        add_column_if_missing(&conn, "incidents", "announced_to", "TEXT")?;
+        add_column_if_missing(&conn, "conversations", "session_id", "INTEGER")?;
+        add_column_if_missing(&conn, "conversations", "restored_at", "INTEGER")?;
+        add_column_if_missing(&conn, "user_profiles", "picture_url", "TEXT")?;
+        add_column_if_missing(&conn, "user_profiles", "status_message", "TEXT")?;
+        add_column_if_missing(&conn, "user_profiles", "member_of", "TEXT")?;
+        // 舊的字典仍用於解壓舊資料，最新的一份用於新資料
+        let dictionaries = conn
+            .prepare("SELECT dictionary FROM compression_dicts ORDER BY created_at, id")?
+            .query_map([], |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for dictionary in dictionaries {
+            codec.add_dictionary(&dictionary);
+        }
+        Ok(Self { conn: Mutex::new(conn), codec, clock })
+    }
+
+    /// 此資料庫使用的時鐘
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// 開始壓縮新寫入的對話內容（讀取不受此設定影響）
+    pub fn enable_compression(&self) {
+        self.codec.enable();
+    }
+
+    pub fn compression_enabled(&self) -> bool {
+        self.codec.enabled()
+    }
+
+    /// 尚無字典且樣本足夠時由最近的對話訓練字典，再壓縮最多 `limit` 筆尚未壓縮的對話
+    pub fn compress_conversations(&self, limit: usize) -> rusqlite::Result<CompressionReport> {
+        let mut report = CompressionReport::default();
+        let conn = self.conn.lock().unwrap();
+        if self.codec.active_dictionary().is_none() {
+            let samples: Vec<String> = conn
+                .prepare("SELECT conversation_text(content) FROM conversations ORDER BY id DESC LIMIT 10000")?
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            if samples.len() >= compression::DICT_MIN_SAMPLES {
+                match compression::train(&samples) {
+                    Ok(dictionary) => {
+                        if let Some(id) = self.codec.add_dictionary(&dictionary) {
+                            conn.execute(
+                                "INSERT OR REPLACE INTO compression_dicts (id, dictionary, samples, created_at) VALUES (?1, ?2, ?3, ?4)",
+                                params![id, dictionary, samples.len() as i64, self.clock.now().timestamp()],
+                            )?;
+                            report.trained_dictionary = Some(id);
+                        }
+                    }
+                    Err(e) => warn!("{}", e),
+                }
+            }
+        }
+        let rows: Vec<(i64, String)> = conn
+            .prepare(
+                "SELECT id, content FROM conversations
+                 WHERE typeof(content) = 'text' AND length(CAST(content AS BLOB)) >= ?1 ORDER BY id LIMIT ?2",
+            )?
+            .query_map(params![compression::MIN_BYTES as i64, limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut stmt = conn.prepare("UPDATE conversations SET content = ?2 WHERE id = ?1")?;
+        for (id, text) in rows {
+            report.bytes_before += text.len() as u64;
+            match self.codec.pack(&text) {
+                rusqlite::types::Value::Blob(data) => {
+                    report.bytes_after += data.len() as u64;
+                    report.compressed_rows += stmt.execute(params![id, data])?;
+                }
+                _ => report.bytes_after += text.len() as u64,
+            }
+        }
+        Ok(report)
+    }
+
+    /// 寫入一筆對話紀錄
+    pub fn record(&self, user_id: &str, role: &str, content: &str, model: Option<&str>, session_id: Option<i64>) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO conversations (user_id, role, content, model, created_at, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![user_id, role, self.codec.pack(content), model, self.clock.now().timestamp(), session_id],
+        )?;
+        Ok(())
+    }
+
+    /// 使用者最近的對話（舊到新，回傳角色與內容），不含離線備援回覆
+    pub fn recent_messages(&self, user_id: &str, limit: usize) -> rusqlite::Result<Vec<(String, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT role, content FROM (
+                 SELECT id, role, conversation_text(content) AS content FROM conversations
+                 WHERE user_id IN ({}) AND (model IS NULL OR model != 'fallback')
+                 ORDER BY id DESC LIMIT ?2
+             ) ORDER BY id",
+            LINKED_IDS_SQL
+        ))?;
+        let rows = stmt.query_map(params![user_id, limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// 取得使用者（含連結身分）目前的對話階段：最後互動在 `gap_secs` 內則延續，否則開新階段。回傳階段 ID
+    pub fn continue_session(&self, user_id: &str, gap_secs: i64) -> rusqlite::Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let now = self.clock.now().timestamp();
+        let current: Option<i64> = conn
+            .query_row(
+                &format!(
+                    "SELECT id FROM sessions WHERE user_id IN ({}) AND last_at >= ?2 ORDER BY last_at DESC LIMIT 1",
+                    LINKED_IDS_SQL
+                ),
+                params![user_id, now - gap_secs],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match current {
+            Some(id) => {
+                conn.execute("UPDATE sessions SET last_at = ?2 WHERE id = ?1", params![id, now])?;
+                Ok(id)
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO sessions (user_id, started_at, last_at) VALUES (?1, ?2, ?2)",
+                    params![user_id, now],
+                )?;
+                Ok(conn.last_insert_rowid())
+            }
+        }
+    }
+
+    /// 對話階段最近的對話（舊到新，回傳角色與內容），不含離線備援回覆
+    pub fn session_messages(&self, session_id: i64, limit: usize) -> rusqlite::Result<Vec<(String, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT role, content FROM (
+                 SELECT id, role, conversation_text(content) AS content FROM conversations
+                 WHERE session_id = ?1 AND (model IS NULL OR model != 'fallback')
+                 ORDER BY id DESC LIMIT ?2
+             ) ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![session_id, limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// 使用者（含連結身分）最近的對話階段（新到舊），只列出仍有對話紀錄的階段
+    pub fn recent_sessions(&self, user_id: &str, limit: usize) -> rusqlite::Result<Vec<Session>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT s.id, s.user_id, s.title, s.started_at, s.last_at,
+                    (SELECT COUNT(*) FROM conversations c WHERE c.session_id = s.id) AS messages
+             FROM sessions s WHERE s.user_id IN ({}) AND messages > 0
+             ORDER BY s.last_at DESC LIMIT ?2",
+            LINKED_IDS_SQL
+        ))?;
+        let rows = stmt.query_map(params![user_id, limit as i64], session_from_row)?;
+        rows.collect()
+    }
+
+    /// 使用者（含連結身分）的某個對話階段
+    pub fn session(&self, user_id: &str, session_id: i64) -> rusqlite::Result<Option<Session>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            &format!(
+                "SELECT s.id, s.user_id, s.title, s.started_at, s.last_at,
+                        (SELECT COUNT(*) FROM conversations c WHERE c.session_id = s.id)
+                 FROM sessions s WHERE s.id = ?2 AND s.user_id IN ({})",
+                LINKED_IDS_SQL
+            ),
+            params![user_id, session_id],
+            session_from_row,
+        )
+        .optional()
+    }
+
+    /// 將對話階段設為目前階段（之後的訊息延續該階段）
+    pub fn resume_session(&self, session_id: i64) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sessions SET last_at = ?2 WHERE id = ?1",
+            params![session_id, self.clock.now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_session_title(&self, session_id: i64, title: &str) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE sessions SET title = ?2 WHERE id = ?1", params![session_id, title])?;
+        Ok(())
+    }
+
+    /// 對話階段的完整紀錄（舊到新，回傳角色、內容與時間）
+    pub fn session_transcript(&self, session_id: i64) -> rusqlite::Result<Vec<(String, String, i64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT role, conversation_text(content), created_at FROM conversations WHERE session_id = ?1 ORDER BY id")?;
+        let rows = stmt.query_map(params![session_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// 依關鍵字與篩選條件搜尋對話，回傳含摘錄的結果（新到舊）
+    pub fn search(&self, query: &SearchQuery) -> rusqlite::Result<Vec<SearchHit>> {
+        let mut sql = String::from(
+            "SELECT c.id, c.user_id, c.role, c.model, conversation_text(c.content), c.created_at FROM conversations c WHERE 1 = 1",
+        );
+        let mut args: Vec<rusqlite::types::Value> = Vec::new();
+
+        // trigram 分詞器只能比對 3 字以上的詞，較短的關鍵字（常見於中文）改用 LIKE
+        let (long, short): (Vec<&String>, Vec<&String>) =
+            query.keywords.iter().partition(|k| k.chars().count() >= 3);
+        if !long.is_empty() {
+            let expr = long
+                .iter()
+                .map(|k| format!("\"{}\"", k.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" ");
+            sql.push_str(" AND c.id IN (SELECT rowid FROM conversations_fts WHERE conversations_fts MATCH ?)");
+            args.push(expr.into());
+        }
+        for keyword in short {
+            sql.push_str(" AND conversation_text(c.content) LIKE ? ESCAPE '\\'");
+            args.push(format!("%{}%", escape_like(keyword)).into());
+        }
+        match &query.user_id {
+            Some(user_id) if query.include_linked => {
+                sql.push_str(&format!(" AND c.user_id IN ({})", LINKED_IDS_SQL.replace("?1", "?")));
+                args.push(user_id.clone().into());
+                args.push(user_id.clone().into());
+            }
+            Some(user_id) => {
+                sql.push_str(" AND c.user_id = ?");
+                args.push(user_id.clone().into());
+            }
+            None => {}
+        }
+        if let Some(from) = query.range.from {
+            sql.push_str(" AND c.created_at >= ?");
+            args.push(from.into());
+        }
+        if let Some(to) = query.range.to {
+            sql.push_str(" AND c.created_at < ?");
+            args.push(to.into());
+        }
+        if let Some(model) = &query.model {
+            sql.push_str(" AND c.model = ?");
+            args.push(model.clone().into());
+        }
+        sql.push_str(" ORDER BY c.created_at DESC, c.id DESC LIMIT ?");
+        args.push((query.limit as i64).into());
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), |row| {
+            let content: String = row.get(4)?;
+            Ok(SearchHit {
+                id: row.get(0)?,
+                user_id: row.get(1)?,
+                role: row.get(2)?,
+                model: row.get(3)?,
+                excerpt: excerpt(&content, &query.keywords),
+                created_at: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// 寫入一筆稽核事件
+    pub fn audit(&self, event: &AuditEvent) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO audit_log (created_at, user_id, event_type, action, status, model, latency_ms, detail)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                self.clock.now().timestamp(),
+                event.user_id,
+                event.event_type,
+                event.action,
+                event.status,
+                event.model,
+                event.latency_ms,
+                event.detail,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 分頁讀取稽核紀錄（以 id 遞增，`after_id` 之後的 `limit` 筆）
+    pub fn audit_page(&self, range: TimeRange, after_id: i64, limit: usize) -> rusqlite::Result<Vec<AuditRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, created_at, user_id, event_type, action, status, model, latency_ms, detail
+             FROM audit_log
+             WHERE id > ?1 AND created_at >= ?2 AND created_at < ?3
+             ORDER BY id LIMIT ?4",
+        )?;
+        let rows = stmt.query_map(
+            params![after_id, range.from.unwrap_or(i64::MIN), range.to.unwrap_or(i64::MAX), limit as i64],
+            |row| {
+                Ok(AuditRecord {
+                    id: row.get(0)?,
+                    created_at: row.get(1)?,
+                    user_id: row.get(2)?,
+                    event_type: row.get(3)?,
+                    action: row.get(4)?,
+                    status: row.get(5)?,
+                    model: row.get(6)?,
+                    latency_ms: row.get(7)?,
+                    detail: row.get(8)?,
+                })
+            },
+        )?;
+        rows.collect()
+    }
+
+    /// 新增或更新公告範本
+    pub fn save_template(&self, name: &str, body: &str, batch_window_secs: Option<i64>) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO announcement_templates (name, body, batch_window_secs, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (name) DO UPDATE SET
+                 body = excluded.body,
+                 batch_window_secs = excluded.batch_window_secs,
+                 updated_at = excluded.updated_at",
+            params![name, body, batch_window_secs, self.clock.now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// 讀取公告範本內容
+    pub fn get_template(&self, name: &str) -> rusqlite::Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT body FROM announcement_templates WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+
+    /// 範本的合併時間窗設定（範本不存在或未設定時為 None）
+    pub fn template_batch_window(&self, name: &str) -> rusqlite::Result<Option<i64>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT batch_window_secs FROM announcement_templates WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map(Option::flatten)
+    }
+
+    /// 列出所有公告範本
+    pub fn list_templates(&self) -> rusqlite::Result<Vec<AnnouncementTemplate>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT name, body, batch_window_secs, updated_at FROM announcement_templates ORDER BY name")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(AnnouncementTemplate {
+                name: row.get(0)?,
+                body: row.get(1)?,
+                batch_window_secs: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// 建立公告草稿，回傳 id；`recipients` 為 None 時廣播給所有好友
+    pub fn create_announcement(
+        &self,
+        template: Option<&str>,
+        text: &str,
+        attachments: &[Attachment],
+        recipients: Option<&[String]>,
+    ) -> rusqlite::Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let now = self.clock.now().timestamp();
+        let attachments = serde_json::to_string(attachments).unwrap_or_else(|_| "[]".to_string());
+        let recipients = recipients.map(|r| serde_json::to_string(r).unwrap_or_else(|_| "[]".to_string()));
+        conn.execute(
+            "INSERT INTO announcements (template, text, attachments, recipients, status, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, 'draft', ?5, ?5)",
+            params![template, text, attachments, recipients, now],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// 讀取單筆公告
+    pub fn get_announcement(&self, id: i64) -> rusqlite::Result<Option<Announcement>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, template, text, attachments, status, created_at, updated_at, recipients, narrowcast_request_id, delivered
+             FROM announcements WHERE id = ?1",
+            params![id],
+            announcement_from_row,
+        )
+        .optional()
+    }
+
+    /// 列出最近的公告
+    pub fn list_announcements(&self, limit: usize) -> rusqlite::Result<Vec<Announcement>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, template, text, attachments, status, created_at, updated_at, recipients, narrowcast_request_id, delivered
+             FROM announcements ORDER BY id DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], announcement_from_row)?;
+        rows.collect()
+    }
+
+    /// 僅在目前狀態為 `from` 時更新為 `to`，回傳是否成功（避免重複核准或發送）
+    pub fn transition_announcement(&self, id: i64, from: &str, to: &str) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            "UPDATE announcements SET status = ?3, updated_at = ?4 WHERE id = ?1 AND status = ?2",
+            params![id, from, to, self.clock.now().timestamp()],
+        )?;
+        Ok(changed == 1)
+    }
+
+    /// 記錄分批送出的進度（已送出的收件人數）
+    pub fn record_announcement_delivered(&self, id: i64, delivered: usize) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE announcements SET delivered = ?2, updated_at = ?3 WHERE id = ?1",
+            params![id, delivered as i64, self.clock.now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// 記錄已由 LINE 受理的群發：保存 request ID 並將狀態由 `approved` 轉為 `sending`
+    pub fn start_narrowcast(&self, id: i64, request_id: &str) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            "UPDATE announcements SET narrowcast_request_id = ?2, status = 'sending', updated_at = ?3
+             WHERE id = ?1 AND status = 'approved'",
+            params![id, request_id, self.clock.now().timestamp()],
+        )?;
+        Ok(changed == 1)
+    }
+
+    /// 尚在 LINE 送出中的群發（公告 id 與 request ID），重啟後據此繼續追蹤進度
+    pub fn sending_narrowcasts(&self) -> rusqlite::Result<Vec<(i64, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, narrowcast_request_id FROM announcements
+             WHERE status = 'sending' AND narrowcast_request_id IS NOT NULL ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// 依 id 順序取出早於 `cutoff` 的對話（封存用）；自封存檔還原的對話自還原時起重新計算
+    pub fn conversations_before(&self, cutoff: i64, limit: usize) -> rusqlite::Result<Vec<ConversationRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, user_id, role, conversation_text(content), model, created_at, session_id FROM conversations
+             WHERE COALESCE(restored_at, created_at) < ?1 ORDER BY id LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![cutoff, limit as i64], |row| {
+            Ok(ConversationRecord {
+                id: row.get(0)?,
+                user_id: row.get(1)?,
+                role: row.get(2)?,
+                content: row.get(3)?,
+                model: row.get(4)?,
+                created_at: row.get(5)?,
+                session_id: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// 分頁讀取區間內的對話（以 id 遞增，`after_id` 之後的 `limit` 筆；資料集匯出用）
+    pub fn conversation_page(&self, range: TimeRange, after_id: i64, limit: usize) -> rusqlite::Result<Vec<ConversationRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, user_id, role, conversation_text(content), model, created_at, session_id FROM conversations
+             WHERE id > ?1 AND created_at >= ?2 AND created_at < ?3
+             ORDER BY id LIMIT ?4",
+        )?;
+        let rows = stmt.query_map(
+            params![after_id, range.from.unwrap_or(i64::MIN), range.to.unwrap_or(i64::MAX), limit as i64],
+            |row| {
+                Ok(ConversationRecord {
+                    id: row.get(0)?,
+                    user_id: row.get(1)?,
+                    role: row.get(2)?,
+                    content: row.get(3)?,
+                    model: row.get(4)?,
+                    created_at: row.get(5)?,
+                    session_id: row.get(6)?,
+                })
+            },
+        )?;
+        rows.collect()
+    }
+
+    /// 記錄封存檔並刪除已封存的對話（同一交易內完成）
+    pub fn mark_archived(&self, name: &str, records: &[ConversationRecord], cutoff: i64) -> rusqlite::Result<()> {
+        let (Some(first), Some(last)) = (records.first(), records.last()) else { return Ok(()) };
+        let first_created = records.iter().map(|r| r.created_at).min().unwrap_or(first.created_at);
+        let last_created = records.iter().map(|r| r.created_at).max().unwrap_or(last.created_at);
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO archives (name, row_count, first_created_at, last_created_at, archived_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (name) DO UPDATE SET row_count = excluded.row_count,
+                 archived_at = excluded.archived_at, restored_at = NULL",
+            params![name, records.len() as i64, first_created, last_created, self.clock.now().timestamp()],
+        )?;
+        tx.execute(
+            "DELETE FROM conversations WHERE id BETWEEN ?1 AND ?2 AND COALESCE(restored_at, created_at) < ?3",
+            params![first.id, last.id, cutoff],
+        )?;
+        tx.commit()
+    }
+
+    /// 將封存的對話寫回（保留原 id 與對話階段，已存在者略過），回傳實際寫回筆數。
+    /// 寫回的對話記下還原時間，保存期限自還原時起重新計算，不會在下一次封存時又被移出
+    pub fn restore_conversations(&self, name: &str, records: &[ConversationRecord]) -> rusqlite::Result<usize> {
+        let now = self.clock.now().timestamp();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut restored = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO conversations (id, user_id, role, content, model, created_at, session_id, restored_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for r in records {
+                restored += stmt.execute(params![
+                    r.id,
+                    r.user_id,
+                    r.role,
+                    self.codec.pack(&r.content),
+                    r.model,
+                    r.created_at,
+                    r.session_id,
+                    now
+                ])?;
+            }
+        }
+        tx.execute("UPDATE archives SET restored_at = ?2 WHERE name = ?1", params![name, now])?;
+        tx.commit()?;
+        Ok(restored)
+    }
+
+    /// 列出封存檔
+    pub fn list_archives(&self) -> rusqlite::Result<Vec<ArchiveInfo>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT name, row_count, first_created_at, last_created_at, archived_at, restored_at
+             FROM archives ORDER BY archived_at DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ArchiveInfo {
+                name: row.get(0)?,
+                row_count: row.get(1)?,
+                first_created_at: row.get(2)?,
+                last_created_at: row.get(3)?,
+                archived_at: row.get(4)?,
+                restored_at: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// 保存程式碼片段
+    pub fn save_snippet(&self, id: &str, user_id: &str, language: Option<&str>, code: &str) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO snippets (id, user_id, language, code, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, user_id, language, code, self.clock.now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// 讀取程式碼片段
+    pub fn get_snippet(&self, id: &str) -> rusqlite::Result<Option<Snippet>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT language, code FROM snippets WHERE id = ?1",
+            params![id],
+            |row| Ok(Snippet { language: row.get(0)?, code: row.get(1)? }),
+        )
+        .optional()
+    }
+
+    /// 保存完整回答頁面，`ttl_secs` 秒後失效
+    pub fn save_answer_page(&self, id: &str, user_id: &str, markdown: &str, ttl_secs: i64) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = self.clock.now().timestamp();
+        conn.execute(
+            "INSERT INTO answer_pages (id, user_id, markdown, created_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, user_id, markdown, now, now + ttl_secs],
+        )?;
+        Ok(())
+    }
+
+    /// 讀取完整回答頁面（含已過期者，由呼叫端判斷）
+    pub fn get_answer_page(&self, id: &str) -> rusqlite::Result<Option<AnswerPage>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT markdown, created_at, expires_at FROM answer_pages WHERE id = ?1",
+            params![id],
+            |row| Ok(AnswerPage { markdown: row.get(0)?, created_at: row.get(1)?, expires_at: row.get(2)? }),
+        )
+        .optional()
+    }
+
+    /// 建立投票，回傳 ID
+    pub fn create_poll(
+        &self,
+        chat_id: &str,
+        creator_id: &str,
+        question: &str,
+        options: &[String],
+        deadline: Option<i64>,
+    ) -> rusqlite::Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let options = serde_json::to_string(options).unwrap_or_else(|_| "[]".to_string());
+        conn.execute(
+            "INSERT INTO polls (chat_id, creator_id, question, options, status, deadline, created_at)
+             VALUES (?1, ?2, ?3, ?4, 'open', ?5, ?6)",
+            params![chat_id, creator_id, question, options, deadline, self.clock.now().timestamp()],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// 讀取單筆投票
+    pub fn get_poll(&self, id: i64) -> rusqlite::Result<Option<Poll>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, chat_id, creator_id, question, options, status, deadline FROM polls WHERE id = ?1",
+            params![id],
+            poll_from_row,
+        )
+        .optional()
+    }
+
+    /// 此聊天中進行中的投票（每個聊天同時只有一個）
+    pub fn open_poll(&self, chat_id: &str) -> rusqlite::Result<Option<Poll>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, chat_id, creator_id, question, options, status, deadline FROM polls
+             WHERE chat_id = ?1 AND status = 'open' ORDER BY id DESC LIMIT 1",
+            params![chat_id],
+            poll_from_row,
+        )
+        .optional()
+    }
+
+    /// 已超過截止時間但仍進行中的投票
+    pub fn due_polls(&self, now: i64) -> rusqlite::Result<Vec<Poll>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, chat_id, creator_id, question, options, status, deadline FROM polls
+             WHERE status = 'open' AND deadline IS NOT NULL AND deadline <= ?1",
+        )?;
+        let rows = stmt.query_map(params![now], poll_from_row)?;
+        rows.collect()
+    }
+
+    /// 結束投票，回傳是否由本次呼叫結束（避免重複公布結果）
+    pub fn close_poll(&self, id: i64) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute("UPDATE polls SET status = 'closed' WHERE id = ?1 AND status = 'open'", params![id])?;
+        Ok(changed == 1)
+    }
+
+    /// 投票；已投過時回傳 false
+    pub fn vote(&self, poll_id: i64, user_id: &str, option: usize) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            "INSERT OR IGNORE INTO poll_votes (poll_id, user_id, option, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![poll_id, user_id, option as i64, self.clock.now().timestamp()],
+        )?;
+        Ok(changed == 1)
+    }
+
+    /// 各選項的票數（依選項順序）
+    pub fn poll_tally(&self, poll_id: i64, option_count: usize) -> rusqlite::Result<Vec<u64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT option, COUNT(*) FROM poll_votes WHERE poll_id = ?1 GROUP BY option")?;
+        let rows = stmt.query_map(params![poll_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+        let mut tally = vec![0; option_count];
+        for row in rows {
+            let (option, count) = row?;
+            if let Some(slot) = tally.get_mut(option as usize) {
+                *slot = count as u64;
+            }
+        }
+        Ok(tally)
+    }
+
+    /// 建立問答遊戲，回傳 ID
+    pub fn create_quiz(&self, chat_id: &str, creator_id: &str, topic: Option<&str>, questions: &[QuizQuestion]) -> rusqlite::Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let questions = serde_json::to_string(questions).unwrap_or_else(|_| "[]".to_string());
+        conn.execute(
+            "INSERT INTO quizzes (chat_id, creator_id, topic, questions, current, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, 0, 'active', ?5)",
+            params![chat_id, creator_id, topic, questions, self.clock.now().timestamp()],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// 讀取單筆問答遊戲
+    pub fn get_quiz(&self, id: i64) -> rusqlite::Result<Option<Quiz>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, chat_id, questions, current, status FROM quizzes WHERE id = ?1",
+            params![id],
+            quiz_from_row,
+        )
+        .optional()
+    }
+
+    /// 此聊天中進行中的問答遊戲
+    pub fn active_quiz(&self, chat_id: &str) -> rusqlite::Result<Option<Quiz>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, chat_id, questions, current, status FROM quizzes
+             WHERE chat_id = ?1 AND status = 'active' ORDER BY id DESC LIMIT 1",
+            params![chat_id],
+            quiz_from_row,
+        )
+        .optional()
+    }
+
+    /// 僅在目前題號為 `from` 時進到下一題，回傳是否成功（多人同時答對時只前進一次）
+    pub fn advance_quiz(&self, id: i64, from: usize) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            "UPDATE quizzes SET current = current + 1 WHERE id = ?1 AND current = ?2 AND status = 'active'",
+            params![id, from as i64],
+        )?;
+        Ok(changed == 1)
+    }
+
+    /// 結束問答遊戲（finished / stopped）
+    pub fn finish_quiz(&self, id: i64, status: &str) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            "UPDATE quizzes SET status = ?2 WHERE id = ?1 AND status = 'active'",
+            params![id, status],
+        )?;
+        Ok(changed == 1)
+    }
+
+    /// 記錄作答並累計排行榜；同一題已作答時回傳 false
+    pub fn record_quiz_answer(
+        &self,
+        quiz: &Quiz,
+        question: usize,
+        user_id: &str,
+        option: usize,
+        correct: bool,
+    ) -> rusqlite::Result<bool> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let now = self.clock.now().timestamp();
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO quiz_answers (quiz_id, question, user_id, option, correct, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![quiz.id, question as i64, user_id, option as i64, correct, now],
+        )?;
+        if inserted == 0 {
+            return Ok(false);
+        }
+        tx.execute(
+            "INSERT INTO quiz_scores (chat_id, user_id, correct, answered, updated_at) VALUES (?1, ?2, ?3, 1, ?4)
+             ON CONFLICT (chat_id, user_id) DO UPDATE SET
+                 correct = correct + excluded.correct, answered = answered + 1, updated_at = excluded.updated_at",
+            params![quiz.chat_id, user_id, correct as i64, now],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// 聊天的問答排行榜（答對題數多者在前）
+    pub fn quiz_leaderboard(&self, chat_id: &str, limit: usize) -> rusqlite::Result<Vec<QuizScore>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT user_id, correct, answered FROM quiz_scores WHERE chat_id = ?1
+             ORDER BY correct DESC, answered ASC, updated_at ASC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![chat_id, limit as i64], |row| {
+            Ok(QuizScore {
+                user_id: row.get(0)?,
+                correct: row.get(1)?,
+                answered: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// 讀取使用者的簡報設定
+    pub fn get_briefing(&self, user_id: &str) -> rusqlite::Result<Option<BriefingSettings>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT user_id, enabled, time, timezone, city, latitude, longitude, calendar_url, feeds, last_sent_on
+             FROM briefing_settings WHERE user_id = ?1",
+            params![user_id],
+            briefing_from_row,
+        )
+        .optional()
+    }
+
+    /// 新增或更新簡報設定
+    pub fn save_briefing(&self, settings: &BriefingSettings) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let feeds = serde_json::to_string(&settings.feeds).unwrap_or_else(|_| "[]".to_string());
+        conn.execute(
+            "INSERT INTO briefing_settings
+                 (user_id, enabled, time, timezone, city, latitude, longitude, calendar_url, feeds, last_sent_on, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT (user_id) DO UPDATE SET
+                 enabled = excluded.enabled, time = excluded.time, timezone = excluded.timezone,
+                 city = excluded.city, latitude = excluded.latitude, longitude = excluded.longitude,
+                 calendar_url = excluded.calendar_url, feeds = excluded.feeds,
+                 last_sent_on = excluded.last_sent_on, updated_at = excluded.updated_at",
+            params![
+                settings.user_id,
+                settings.enabled,
+                settings.time,
+                settings.timezone,
+                settings.city,
+                settings.latitude,
+                settings.longitude,
+                settings.calendar_url,
+                feeds,
+                settings.last_sent_on,
+                self.clock.now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 所有已訂閱簡報的使用者
+    pub fn briefing_subscribers(&self) -> rusqlite::Result<Vec<BriefingSettings>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT user_id, enabled, time, timezone, city, latitude, longitude, calendar_url, feeds, last_sent_on
+             FROM briefing_settings WHERE enabled = 1",
+        )?;
+        let rows = stmt.query_map([], briefing_from_row)?;
+        rows.collect()
+    }
+
+    /// 記錄簡報已於當地日期 `date` 送出
+    pub fn mark_briefing_sent(&self, user_id: &str, date: &str) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE briefing_settings SET last_sent_on = ?2 WHERE user_id = ?1",
+            params![user_id, date],
+        )?;
+        Ok(())
+    }
+
+    /// 新增待提醒事項
+    pub fn add_reminder(&self, user_id: &str, text: &str) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO reminders (user_id, text, created_at) VALUES (?1, ?2, ?3)",
+            params![user_id, text, self.clock.now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// 尚未送出的提醒事項（依建立順序）
+    pub fn pending_reminders(&self, user_id: &str) -> rusqlite::Result<Vec<Reminder>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, text FROM reminders WHERE user_id = ?1 AND delivered_at IS NULL ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![user_id], |row| Ok(Reminder { id: row.get(0)?, text: row.get(1)? }))?;
+        rows.collect()
+    }
+
+    /// 標記提醒事項已送出
+    pub fn mark_reminders_delivered(&self, ids: &[i64]) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = self.clock.now().timestamp();
+        for id in ids {
+            conn.execute("UPDATE reminders SET delivered_at = ?2 WHERE id = ?1", params![id, now])?;
+        }
+        Ok(())
+    }
+
+    /// 將一般通知排入合併佇列，於 `due_at` 前與同一使用者的其他通知合併送出
+    pub fn queue_notification(&self, user_ids: &[String], template: Option<&str>, text: &str, due_at: i64) -> rusqlite::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let now = self.clock.now().timestamp();
+        for user_id in user_ids {
+            tx.execute(
+                "INSERT INTO notification_queue (user_id, template, text, created_at, due_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![user_id, template, text, now, due_at],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// 佇列中最早的通知已到期的使用者
+    pub fn due_notification_users(&self, now: i64) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT DISTINCT user_id FROM notification_queue WHERE due_at <= ?1")?;
+        let rows = stmt.query_map(params![now], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// 取出並移除使用者佇列中的所有通知文字（依排入順序）
+    pub fn take_queued_notifications(&self, user_id: &str) -> rusqlite::Result<Vec<String>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let items = {
+            let mut stmt = tx.prepare("SELECT text FROM notification_queue WHERE user_id = ?1 ORDER BY id")?;
+            let rows = stmt.query_map(params![user_id], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        tx.execute("DELETE FROM notification_queue WHERE user_id = ?1", params![user_id])?;
+        tx.commit()?;
+        Ok(items)
+    }
+
+    /// 寫入死信佇列，回傳 id
+    pub fn add_dead_letter(
+        &self,
+        user_id: &str,
+        target: &str,
+        event_type: &str,
+        messages: &serde_json::Value,
+        reason: &str,
+        steps: &[String],
+    ) -> rusqlite::Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO dead_letters (user_id, target, event_type, messages, reason, steps, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                user_id,
+                target,
+                event_type,
+                messages.to_string(),
+                reason,
+                serde_json::to_string(steps).unwrap_or_else(|_| "[]".to_string()),
+                self.clock.now().timestamp()
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// 列出最近的死信
+    pub fn list_dead_letters(&self, limit: usize) -> rusqlite::Result<Vec<DeadLetter>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, user_id, target, event_type, messages, reason, steps, created_at
+             FROM dead_letters ORDER BY id DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| {
+            let messages: String = row.get(4)?;
+            let steps: String = row.get(6)?;
+            Ok(DeadLetter {
+                id: row.get(0)?,
+                user_id: row.get(1)?,
+                target: row.get(2)?,
+                event_type: row.get(3)?,
+                messages: serde_json::from_str(&messages).unwrap_or_default(),
+                reason: row.get(5)?,
+                steps: serde_json::from_str(&steps).unwrap_or_default(),
+                created_at: row.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// 保存試運行時攔下的訊息
+    pub fn add_dry_run_message(&self, endpoint: &str, target: Option<&str>, body: &serde_json::Value) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO dry_run_messages (endpoint, target, body, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![endpoint, target, body.to_string(), self.clock.now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// 列出最近試運行攔下的訊息
+    pub fn list_dry_run_messages(&self, limit: usize) -> rusqlite::Result<Vec<DryRunEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, endpoint, target, body, created_at FROM dry_run_messages ORDER BY id DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| {
+            let body: String = row.get(3)?;
+            Ok(DryRunEntry {
+                id: row.get(0)?,
+                endpoint: row.get(1)?,
+                target: row.get(2)?,
+                body: serde_json::from_str(&body).unwrap_or_default(),
+                created_at: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// 資料庫中出現過的所有使用者/群組/聊天室 ID（反查雜湊用）
+    pub fn known_ids(&self) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT user_id FROM conversations
+             UNION SELECT user_id FROM audit_log
+             UNION SELECT chat_id FROM polls
+             UNION SELECT user_id FROM poll_votes
+             UNION SELECT chat_id FROM quizzes
+             UNION SELECT user_id FROM quiz_scores
+             UNION SELECT user_id FROM briefing_settings
+             UNION SELECT user_id FROM notification_queue
+             UNION SELECT target FROM dead_letters
+             UNION SELECT user_id FROM identity_links",
+        )?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// 建立帳號連結碼（同一身分只保留最新的一組）
+    pub fn create_link_code(&self, user_id: &str, code: &str, ttl_secs: i64) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = self.clock.now().timestamp();
+        conn.execute("DELETE FROM link_codes WHERE user_id = ?1 OR expires_at < ?2", params![user_id, now])?;
+        conn.execute(
+            "INSERT INTO link_codes (code, user_id, expires_at) VALUES (?1, ?2, ?3)",
+            params![code, user_id, now + ttl_secs],
+        )?;
+        Ok(())
+    }
+
+    /// 以連結碼連結兩個身分（兩邊原有的連結會合併）。
+    /// `window_secs` 內輸入錯誤達 `max_failures` 次的身分在時間窗結束前一律回傳 [`LinkRedemption::Locked`]，避免猜測連結碼
+    pub fn redeem_link_code(
+        &self,
+        code: &str,
+        user_id: &str,
+        max_failures: u32,
+        window_secs: i64,
+    ) -> rusqlite::Result<LinkRedemption> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let now = self.clock.now().timestamp();
+        let failures: u32 = tx
+            .query_row(
+                "SELECT failures FROM link_attempts WHERE user_id = ?1 AND first_failed_at >= ?2",
+                params![user_id, now - window_secs],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(0);
+        if failures >= max_failures {
+            return Ok(LinkRedemption::Locked);
+        }
+        let owner: Option<String> = tx
+            .query_row(
+                "SELECT user_id FROM link_codes WHERE code = ?1 AND expires_at >= ?2",
+                params![code, now],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(owner) = owner.filter(|owner| owner != user_id) else {
+            // 時間窗已過的舊紀錄重新起算
+            tx.execute(
+                "INSERT INTO link_attempts (user_id, failures, first_failed_at) VALUES (?1, 1, ?2)
+                 ON CONFLICT (user_id) DO UPDATE SET
+                     failures = CASE WHEN first_failed_at >= ?3 THEN failures + 1 ELSE 1 END,
+                     first_failed_at = CASE WHEN first_failed_at >= ?3 THEN first_failed_at ELSE ?2 END",
+                params![user_id, now, now - window_secs],
+            )?;
+            tx.commit()?;
+            return Ok(LinkRedemption::Invalid);
+        };
+        tx.execute("DELETE FROM link_codes WHERE code = ?1", params![code])?;
+        tx.execute("DELETE FROM link_attempts WHERE user_id = ?1", params![user_id])?;
+
+        let account_of = |id: &str| -> rusqlite::Result<Option<String>> {
+            tx.query_row("SELECT account_id FROM identity_links WHERE user_id = ?1", params![id], |row| row.get(0))
+                .optional()
+        };
+        let account = account_of(&owner)?.unwrap_or_else(|| owner.clone());
+        if let Some(previous) = account_of(user_id)?.filter(|previous| *previous != account) {
+            tx.execute("UPDATE identity_links SET account_id = ?1 WHERE account_id = ?2", params![account, previous])?;
+        }
+        for id in [&owner, &user_id.to_string()] {
+            tx.execute(
+                "INSERT INTO identity_links (user_id, account_id, linked_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (user_id) DO UPDATE SET account_id = excluded.account_id, linked_at = excluded.linked_at",
+                params![id, account, now],
+            )?;
+        }
+        tx.commit()?;
+        Ok(LinkRedemption::Linked(owner))
+    }
+
+    /// 與此身分連結的其他身分
+    pub fn linked_identities(&self, user_id: &str) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT user_id FROM identity_links
+             WHERE account_id = (SELECT account_id FROM identity_links WHERE user_id = ?1) AND user_id != ?1
+             ORDER BY linked_at, user_id",
+        )?;
+        let rows = stmt.query_map(params![user_id], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// 解除此身分的連結；回傳是否原本有連結
+    pub fn unlink_identity(&self, user_id: &str) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute("DELETE FROM identity_links WHERE user_id = ?1", params![user_id])?;
+        // 只剩一個身分的帳號不再需要連結紀錄
+        conn.execute(
+            "DELETE FROM identity_links WHERE account_id IN (
+                 SELECT account_id FROM identity_links GROUP BY account_id HAVING COUNT(*) < 2
+             )",
+            [],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// 開始一筆事故；同類事故尚未排除時不重複建立，回傳新事故的 ID
+    pub fn open_incident(&self, kind: &str, detail: Option<&str>) -> rusqlite::Result<Option<i64>> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            "INSERT INTO incidents (kind, detail, started_at)
+             SELECT ?1, ?2, ?3 WHERE NOT EXISTS (SELECT 1 FROM incidents WHERE kind = ?1 AND resolved_at IS NULL)",
+            params![kind, detail, self.clock.now().timestamp()],
+        )?;
+        Ok((changed > 0).then(|| conn.last_insert_rowid()))
+    }
+
+    /// 排除同類尚未排除的事故，回傳被排除的事故 ID
+    pub fn resolve_incident(&self, kind: &str) -> rusqlite::Result<Option<i64>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "UPDATE incidents SET resolved_at = ?2 WHERE kind = ?1 AND resolved_at IS NULL RETURNING id",
+            params![kind, self.clock.now().timestamp()],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+
+    /// 同類尚未排除的事故
+    pub fn current_incident(&self, kind: &str) -> rusqlite::Result<Option<Incident>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, kind, detail, started_at, resolved_at, announced_to FROM incidents
+             WHERE kind = ?1 AND resolved_at IS NULL ORDER BY id DESC LIMIT 1",
+            params![kind],
+            incident_from_row,
+        )
+        .optional()
+    }
+
+    /// 最近開始的事故
+    pub fn last_incident(&self) -> rusqlite::Result<Option<Incident>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, kind, detail, started_at, resolved_at, announced_to FROM incidents
+             ORDER BY started_at DESC, id DESC LIMIT 1",
+            [],
+            incident_from_row,
+        )
+        .optional()
+    }
+
+    /// 記錄已收到事故通知的使用者（恢復通知會送給同一群人）
+    pub fn mark_incident_announced(&self, id: i64, recipients: &[String]) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let recipients = serde_json::to_string(recipients).unwrap_or_else(|_| "[]".to_string());
+        conn.execute("UPDATE incidents SET announced_to = ?2 WHERE id = ?1", params![id, recipients])?;
+        Ok(())
+    }
+
+    /// `since` 之後與機器人互動過且未關閉事故通知的使用者
+    pub fn recent_users(&self, since: i64) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT user_id FROM audit_log
+             WHERE created_at >= ?1 AND event_type IN ('message', 'postback', 'follow') AND user_id != ''
+               AND user_id NOT IN (SELECT user_id FROM incident_opt_outs)",
+        )?;
+        let rows = stmt.query_map(params![since], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// 開啟或關閉事故通知
+    pub fn set_incident_notices(&self, user_id: &str, enabled: bool) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        if enabled {
+            conn.execute("DELETE FROM incident_opt_outs WHERE user_id = ?1", params![user_id])?;
+        } else {
+            conn.execute(
+                "INSERT OR IGNORE INTO incident_opt_outs (user_id, created_at) VALUES (?1, ?2)",
+                params![user_id, self.clock.now().timestamp()],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 使用者是否接收事故通知（預設接收）
+    pub fn incident_notices_enabled(&self, user_id: &str) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let opted_out: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM incident_opt_outs WHERE user_id = ?1)",
+            params![user_id],
+            |row| row.get(0),
+        )?;
+        Ok(!opted_out)
+    }
+
+    /// 需要更新個人資料的 LINE 好友：曾互動但尚無快取者優先，其次為 `fetched_before` 前取得的快取（舊的優先）。
+    /// 已標記封鎖者同樣定期重新確認；未加好友的成員取不到個人資料，改由 [`Self::stale_member_profiles`] 更新
+    pub fn stale_profiles(&self, fetched_before: i64, limit: usize) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT user_id FROM (
+                 SELECT DISTINCT user_id, 0 AS fetched_at FROM audit_log
+                 WHERE user_id GLOB 'U*' AND user_id NOT IN (SELECT user_id FROM user_profiles)
+                 UNION ALL
+                 SELECT user_id, fetched_at FROM user_profiles
+                 WHERE user_id GLOB 'U*' AND fetched_at < ?1 AND member_of IS NULL
+             ) ORDER BY fetched_at LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![fetched_before, limit as i64], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// 需要更新的成員個人資料：`fetched_before` 前取得者與所在的群組或聊天室 ID（舊的優先）
+    pub fn stale_member_profiles(&self, fetched_before: i64, limit: usize) -> rusqlite::Result<Vec<(String, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT user_id, member_of FROM user_profiles
+             WHERE member_of IS NOT NULL AND fetched_at < ?1 ORDER BY fetched_at LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![fetched_before, limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// 保存取得的個人資料（同時解除封鎖標記），回傳顯示名稱是否有變更
+    pub fn save_profile(&self, user_id: &str, profile: &ProfileFields) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let now = self.clock.now().timestamp();
+        let previous: Option<Option<String>> = conn
+            .query_row("SELECT display_name FROM user_profiles WHERE user_id = ?1", params![user_id], |row| row.get(0))
+            .optional()?;
+        conn.execute(
+            "INSERT INTO user_profiles
+                 (user_id, display_name, language, picture_url, status_message, member_of, blocked, fetched_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?7, ?7)
+             ON CONFLICT (user_id) DO UPDATE SET display_name = ?2, language = ?3, picture_url = ?4, status_message = ?5,
+                 member_of = ?6, blocked = 0, fetched_at = ?7, updated_at = ?7",
+            params![
+                user_id,
+                profile.display_name,
+                profile.language,
+                profile.picture_url,
+                profile.status_message,
+                profile.member_of,
+                now
+            ],
+        )?;
+        Ok(matches!(previous, Some(Some(name)) if name != profile.display_name))
+    }
+
+    /// 記錄取不到個人資料：更新取得時間，短時間內不再重複查詢；`member_of` 為查詢時所在的群組或聊天室
+    pub fn record_profile_miss(&self, user_id: &str, member_of: Option<&str>) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = self.clock.now().timestamp();
+        conn.execute(
+            "INSERT INTO user_profiles (user_id, member_of, blocked, fetched_at, updated_at) VALUES (?1, ?2, 0, ?3, ?3)
+             ON CONFLICT (user_id) DO UPDATE SET member_of = COALESCE(?2, member_of), fetched_at = ?3",
+            params![user_id, member_of, now],
+        )?;
+        Ok(())
+    }
+
+    /// 清除取得時間，讓下一輪更新優先重新確認此使用者
+    pub fn expire_profile(&self, user_id: &str) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO user_profiles (user_id, blocked, fetched_at, updated_at) VALUES (?1, 0, 0, ?2)
+             ON CONFLICT (user_id) DO UPDATE SET fetched_at = 0",
+            params![user_id, self.clock.now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// 標記使用者已封鎖或重新加入；重新加入時清除取得時間，讓下次更新優先處理
+    pub fn set_profile_blocked(&self, user_id: &str, blocked: bool) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = self.clock.now().timestamp();
+        let fetched_at = if blocked { now } else { 0 };
+        conn.execute(
+            "INSERT INTO user_profiles (user_id, blocked, fetched_at, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (user_id) DO UPDATE SET blocked = ?2, fetched_at = ?3, updated_at = ?4",
+            params![user_id, blocked, fetched_at, now],
+        )?;
+        Ok(())
+    }
+
+    /// 快取的個人資料
+    pub fn profile(&self, user_id: &str) -> rusqlite::Result<Option<UserProfile>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT user_id, display_name, language, picture_url, status_message, blocked, member_of, fetched_at, updated_at
+             FROM user_profiles WHERE user_id = ?1",
+            params![user_id],
+            |row| {
+                Ok(UserProfile {
+                    user_id: row.get(0)?,
+                    display_name: row.get(1)?,
+                    language: row.get(2)?,
+                    picture_url: row.get(3)?,
+                    status_message: row.get(4)?,
+                    blocked: row.get(5)?,
+                    member_of: row.get(6)?,
+                    fetched_at: row.get(7)?,
+                    updated_at: row.get(8)?,
+                })
+            },
+        )
+        .optional()
+    }
+
+    /// RSS 來源的抓取狀態；尚未抓取過時回傳 None
+    pub fn feed_source(&self, url: &str) -> rusqlite::Result<Option<FeedSource>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            &format!("SELECT {} FROM feed_sources WHERE url = ?1", FEED_SOURCE_COLUMNS),
+            params![url],
+            feed_source_from_row,
+        )
+        .optional()
+    }
+
+    /// 寫入 RSS 來源的抓取狀態
+    pub fn save_feed_source(&self, source: &FeedSource) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let titles = serde_json::to_string(&source.titles).unwrap_or_else(|_| "[]".to_string());
+        conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO feed_sources ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                FEED_SOURCE_COLUMNS
+            ),
+            params![
+                source.url,
+                source.etag,
+                source.last_modified,
+                titles,
+                source.fetches,
+                source.not_modified,
+                source.failures,
+                source.consecutive_failures,
+                source.robots_blocked,
+                source.last_error,
+                source.last_fetched_at,
+                source.retry_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 所有 RSS 來源的抓取統計（依網址排序）
+    pub fn feed_sources(&self) -> rusqlite::Result<Vec<FeedSource>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM feed_sources ORDER BY url", FEED_SOURCE_COLUMNS))?;
+        let rows = stmt.query_map([], feed_source_from_row)?;
+        rows.collect()
+    }
+
+    /// 群組或聊天室是否只在被 @提及時回應；未設定時回傳 None
+    pub fn mention_only(&self, chat_id: &str) -> rusqlite::Result<Option<bool>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT mention_only FROM group_settings WHERE chat_id = ?1",
+            params![chat_id],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+
+    /// 設定群組或聊天室是否只在被 @提及時回應
+    pub fn set_mention_only(&self, chat_id: &str, mention_only: bool) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO group_settings (chat_id, mention_only, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (chat_id) DO UPDATE SET mention_only = ?2, updated_at = ?3",
+            params![chat_id, mention_only, self.clock.now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// 建立自動化草稿，回傳 id（忽略 `automation` 的 id、狀態、執行紀錄與建立時間）
+    pub fn create_automation(&self, automation: &Automation) -> rusqlite::Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let weekdays = serde_json::to_string(&automation.weekdays).unwrap_or_else(|_| "[]".to_string());
+        conn.execute(
+            "INSERT INTO automations (user_id, target, name, time, weekdays, timezone, kind, content, status, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'draft', ?9, ?9)",
+            params![
+                automation.user_id,
+                automation.target,
+                automation.name,
+                automation.time,
+                weekdays,
+                automation.timezone,
+                automation.kind,
+                automation.content,
+                self.clock.now().timestamp()
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// 讀取單筆自動化
+    pub fn get_automation(&self, id: i64) -> rusqlite::Result<Option<Automation>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            &format!("SELECT {} FROM automations WHERE id = ?1", AUTOMATION_COLUMNS),
+            params![id],
+            automation_from_row,
+        )
+        .optional()
+    }
+
+    /// 使用者建立且已啟用的自動化
+    pub fn automations_for(&self, user_id: &str) -> rusqlite::Result<Vec<Automation>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM automations WHERE user_id = ?1 AND status = 'active' ORDER BY id",
+            AUTOMATION_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![user_id], automation_from_row)?;
+        rows.collect()
+    }
+
+    /// 所有已啟用的自動化
+    pub fn active_automations(&self) -> rusqlite::Result<Vec<Automation>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM automations WHERE status = 'active' ORDER BY id", AUTOMATION_COLUMNS))?;
+        let rows = stmt.query_map([], automation_from_row)?;
+        rows.collect()
+    }
+
+    /// 僅在目前狀態為 `from` 時更新為 `to`，回傳是否成功（避免重複確認）
+    pub fn transition_automation(&self, id: i64, from: &str, to: &str) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            "UPDATE automations SET status = ?3, updated_at = ?4 WHERE id = ?1 AND status = ?2",
+            params![id, from, to, self.clock.now().timestamp()],
+        )?;
+        Ok(changed == 1)
+    }
+
+    /// 記錄自動化已於當地日期 `date` 執行
+    pub fn mark_automation_run(&self, id: i64, date: &str) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE automations SET last_run_on = ?2 WHERE id = ?1", params![id, date])?;
+        Ok(())
+    }
+
+    /// 已封鎖機器人的使用者
+    pub fn blocked_users(&self) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT user_id FROM user_profiles WHERE blocked = 1")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// 快照中對話紀錄以外的部分（對話紀錄量大，由呼叫端以 [`Self::conversation_page`] 分批讀取，不必長時間持有連線）
+    pub fn snapshot_settings(&self) -> rusqlite::Result<UserSnapshot> {
+        let conn = self.conn.lock().unwrap();
+        let sessions = conn
+            .prepare("SELECT id, user_id, title, started_at, last_at FROM sessions ORDER BY id")?
+            .query_map([], |row| {
+                Ok(SnapshotSession {
+                    id: row.get(0)?,
+                    user_id: row.get(1)?,
+                    title: row.get(2)?,
+                    started_at: row.get(3)?,
+                    last_at: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let briefings = conn
+            .prepare(
+                "SELECT user_id, enabled, time, timezone, city, latitude, longitude, calendar_url, feeds, last_sent_on
+                 FROM briefing_settings ORDER BY user_id",
+            )?
+            .query_map([], briefing_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        let reminders = conn
+            .prepare("SELECT user_id, text, created_at FROM reminders WHERE delivered_at IS NULL ORDER BY id")?
+            .query_map([], |row| Ok(SnapshotReminder { user_id: row.get(0)?, text: row.get(1)?, created_at: row.get(2)? }))?
+            .collect::<rusqlite::Result<_>>()?;
+        let identity_links = conn
+            .prepare("SELECT user_id, account_id, linked_at FROM identity_links ORDER BY user_id")?
+            .query_map([], |row| Ok(SnapshotLink { user_id: row.get(0)?, account_id: row.get(1)?, linked_at: row.get(2)? }))?
+            .collect::<rusqlite::Result<_>>()?;
+        let incident_opt_outs = conn
+            .prepare("SELECT user_id FROM incident_opt_outs ORDER BY user_id")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        let profiles = conn
+            .prepare("SELECT user_id, display_name, language FROM user_profiles ORDER BY user_id")?
+            .query_map([], |row| Ok(SnapshotProfile { user_id: row.get(0)?, display_name: row.get(1)?, language: row.get(2)? }))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(UserSnapshot {
+            version: SNAPSHOT_VERSION,
+            exported_at: self.clock.now().timestamp(),
+            conversations: Vec::new(),
+            sessions,
+            briefings,
+            reminders,
+            identity_links,
+            incident_opt_outs,
+            profiles,
+        })
+    }
+
+    /// 在同一交易內匯入快照：已有資料者保留現有設定，對話紀錄只寫入尚無紀錄的使用者（重複匯入不會重複寫入）
+    pub fn import_snapshot(&self, snapshot: &UserSnapshot) -> rusqlite::Result<SnapshotImport> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut imported = SnapshotImport::default();
+        {
+            let existing: std::collections::HashSet<String> = tx
+                .prepare("SELECT DISTINCT user_id FROM conversations")?
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            // 對話階段重新編號，並記下舊編號以對應對話紀錄
+            let mut session_ids = std::collections::HashMap::new();
+            let mut stmt = tx.prepare("INSERT INTO sessions (user_id, title, started_at, last_at) VALUES (?1, ?2, ?3, ?4)")?;
+            for session in snapshot.sessions.iter().filter(|s| !existing.contains(&s.user_id)) {
+                stmt.execute(params![session.user_id, session.title, session.started_at, session.last_at])?;
+                session_ids.insert(session.id, tx.last_insert_rowid());
+                imported.sessions += 1;
+            }
+            let mut stmt = tx.prepare(
+                "INSERT INTO conversations (user_id, role, content, model, created_at, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for r in snapshot.conversations.iter().filter(|r| !existing.contains(&r.user_id)) {
+                let session_id = r.session_id.and_then(|id| session_ids.get(&id));
+                imported.conversations +=
+                    stmt.execute(params![r.user_id, r.role, self.codec.pack(&r.content), r.model, r.created_at, session_id])?;
+            }
+
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO briefing_settings
+                     (user_id, enabled, time, timezone, city, latitude, longitude, calendar_url, feeds, last_sent_on, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            for b in &snapshot.briefings {
+                let feeds = serde_json::to_string(&b.feeds).unwrap_or_else(|_| "[]".to_string());
+                imported.briefings += stmt.execute(params![
+                    b.user_id,
+                    b.enabled,
+                    b.time,
+                    b.timezone,
+                    b.city,
+                    b.latitude,
+                    b.longitude,
+                    b.calendar_url,
+                    feeds,
+                    b.last_sent_on,
+                    snapshot.exported_at
+                ])?;
+            }
+
+            // 提醒沒有唯一鍵，只寫入尚無待送提醒的使用者
+            let pending: std::collections::HashSet<String> = tx
+                .prepare("SELECT DISTINCT user_id FROM reminders WHERE delivered_at IS NULL")?
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            let mut stmt = tx.prepare("INSERT INTO reminders (user_id, text, created_at) VALUES (?1, ?2, ?3)")?;
+            for r in snapshot.reminders.iter().filter(|r| !pending.contains(&r.user_id)) {
+                imported.reminders += stmt.execute(params![r.user_id, r.text, r.created_at])?;
+            }
+
+            let mut stmt = tx.prepare("INSERT OR IGNORE INTO identity_links (user_id, account_id, linked_at) VALUES (?1, ?2, ?3)")?;
+            for l in &snapshot.identity_links {
+                imported.identity_links += stmt.execute(params![l.user_id, l.account_id, l.linked_at])?;
+            }
+
+            let mut stmt = tx.prepare("INSERT OR IGNORE INTO incident_opt_outs (user_id, created_at) VALUES (?1, ?2)")?;
+            for user_id in &snapshot.incident_opt_outs {
+                imported.incident_opt_outs += stmt.execute(params![user_id, snapshot.exported_at])?;
+            }
+
+            // 取得時間設為 0，讓背景更新優先重新取得
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO user_profiles (user_id, display_name, language, blocked, fetched_at, updated_at)
+                 VALUES (?1, ?2, ?3, 0, 0, ?4)",
+            )?;
+            for p in &snapshot.profiles {
+                imported.profiles += stmt.execute(params![p.user_id, p.display_name, p.language, snapshot.exported_at])?;
+            }
+        }
+        tx.commit()?;
+        Ok(imported)
+    }
+
+    /// `since` 起每 `bucket_secs` 秒的 AI 對話數，共 `buckets` 格（舊到新）
+    pub fn message_volume(&self, since: i64, bucket_secs: i64, buckets: usize) -> rusqlite::Result<Vec<u64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT (created_at - ?1) / ?2 AS bucket, COUNT(*)
+             FROM audit_log
+             WHERE action = 'chat' AND created_at >= ?1
+             GROUP BY bucket",
+        )?;
+        let mut volume = vec![0; buckets];
+        let rows = stmt.query_map(params![since, bucket_secs], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+        for row in rows {
+            let (bucket, count) = row?;
+            if let Some(slot) = usize::try_from(bucket).ok().and_then(|bucket| volume.get_mut(bucket)) {
+                *slot = count as u64;
+            }
+        }
+        Ok(volume)
+    }
+
+    /// 各資料表的列數
+    pub fn table_counts(&self) -> rusqlite::Result<Vec<(&'static str, u64)>> {
+        let conn = self.conn.lock().unwrap();
+        TABLES
+            .iter()
+            .map(|table| {
+                let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
+                Ok((*table, count as u64))
+            })
+            .collect()
+    }
+
+    /// 刪除過期資料：已過期的完整回答頁，以及 `cutoff` 之前已送達的提醒、死信、試運行訊息、已結束的投票與問答、已排除的事故；
+    /// 指定 `audit_cutoff` 時一併刪除更早的稽核紀錄。回傳各資料表刪除的列數
+    pub fn prune(&self, now: i64, cutoff: i64, audit_cutoff: Option<i64>) -> rusqlite::Result<Vec<(&'static str, usize)>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut pruned = vec![
+            ("answer_pages", tx.execute("DELETE FROM answer_pages WHERE expires_at < ?1", params![now])?),
+            (
+                "reminders",
+                tx.execute("DELETE FROM reminders WHERE delivered_at IS NOT NULL AND delivered_at < ?1", params![cutoff])?,
+            ),
+            ("dead_letters", tx.execute("DELETE FROM dead_letters WHERE created_at < ?1", params![cutoff])?),
+            ("dry_run_messages", tx.execute("DELETE FROM dry_run_messages WHERE created_at < ?1", params![cutoff])?),
+            (
+                "poll_votes",
+                tx.execute(
+                    "DELETE FROM poll_votes WHERE poll_id IN (SELECT id FROM polls WHERE status != 'open' AND created_at < ?1)",
+                    params![cutoff],
+                )?,
+            ),
+            ("polls", tx.execute("DELETE FROM polls WHERE status != 'open' AND created_at < ?1", params![cutoff])?),
+            (
+                "quiz_answers",
+                tx.execute(
+                    "DELETE FROM quiz_answers WHERE quiz_id IN (SELECT id FROM quizzes WHERE status != 'active' AND created_at < ?1)",
+                    params![cutoff],
+                )?,
+            ),
+            ("quizzes", tx.execute("DELETE FROM quizzes WHERE status != 'active' AND created_at < ?1", params![cutoff])?),
+            (
+                "automations",
+                tx.execute("DELETE FROM automations WHERE status != 'active' AND updated_at < ?1", params![cutoff])?,
+            ),
+            (
+                "incidents",
+                tx.execute("DELETE FROM incidents WHERE resolved_at IS NOT NULL AND resolved_at < ?1", params![cutoff])?,
+            ),
+            (
+                "sessions",
+                tx.execute(
+                    "DELETE FROM sessions WHERE last_at < ?1 AND NOT EXISTS (SELECT 1 FROM conversations c WHERE c.session_id = sessions.id)",
+                    params![cutoff],
+                )?,
+            ),
+        ];
+        if let Some(audit_cutoff) = audit_cutoff {
+            pruned.push(("audit_log", tx.execute("DELETE FROM audit_log WHERE created_at < ?1", params![audit_cutoff])?));
+        }
+        tx.commit()?;
+        Ok(pruned)
+    }
+
+    /// 更新查詢統計並重建資料庫以回收空頁，最後截斷 WAL 檔。另開連線執行，期間不佔用共用連線
+    pub fn vacuum(&self) -> rusqlite::Result<()> {
+        let path = self.conn.lock().unwrap().path().filter(|p| !p.is_empty()).map(str::to_string);
+        match path {
+            Some(path) => vacuum_on(&Connection::open(path)?),
+            // 記憶體資料庫無法另開連線
+            None => vacuum_on(&self.conn.lock().unwrap()),
+        }
+    }
+
+    /// 資料庫檔案大小（位元組，含尚未回收的空頁）
+    pub fn database_bytes(&self) -> rusqlite::Result<u64> {
+        let conn = self.conn.lock().unwrap();
+        let bytes: i64 = conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(bytes as u64)
+    }
+
+    /// 依日期與模型彙總 OpenClaw 用量
+    pub fn usage_daily(&self, range: TimeRange) -> rusqlite::Result<Vec<UsageRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT date(created_at, 'unixepoch') AS day,
+                    COALESCE(model, '') AS model,
+                    COUNT(*),
+                    SUM(status = 'fallback'),
+                    SUM(status = 'error'),
+                    COUNT(DISTINCT user_id),
+                    AVG(latency_ms)
+             FROM audit_log
+             WHERE action = 'chat' AND created_at >= ?1 AND created_at < ?2
+             GROUP BY day, model
+             ORDER BY day, model",
+        )?;
+        let rows = stmt.query_map(
+            params![range.from.unwrap_or(i64::MIN), range.to.unwrap_or(i64::MAX)],
+            |row| {
+                Ok(UsageRecord {
+                    day: row.get(0)?,
+                    model: row.get(1)?,
+                    requests: row.get(2)?,
+                    fallbacks: row.get(3)?,
+                    errors: row.get(4)?,
+                    unique_users: row.get(5)?,
+                    avg_latency_ms: row.get(6)?,
+                })
+            },
+        )?;
+        rows.collect()
+    }
+}
+
+fn announcement_from_row(row: &rusqlite::Row) -> rusqlite::Result<Announcement> {
+    let attachments: Option<String> = row.get(3)?;
+    Ok(Announcement {
+        id: row.get(0)?,
+        template: row.get(1)?,
+        text: row.get(2)?,
+        attachments: attachments
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        recipients: row
+            .get::<_, Option<String>>(7)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        narrowcast_request_id: row.get(8)?,
+        delivered: row.get::<_, i64>(9)? as usize,
+        status: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+fn poll_from_row(row: &rusqlite::Row) -> rusqlite::Result<Poll> {
+    let options: String = row.get(4)?;
+    Ok(Poll {
+        id: row.get(0)?,
+        chat_id: row.get(1)?,
+        creator_id: row.get(2)?,
+        question: row.get(3)?,
+        options: serde_json::from_str(&options).unwrap_or_default(),
+        status: row.get(5)?,
+        deadline: row.get(6)?,
+    })
+}
+
+fn quiz_from_row(row: &rusqlite::Row) -> rusqlite::Result<Quiz> {
+    let questions: String = row.get(2)?;
+    Ok(Quiz {
+        id: row.get(0)?,
+        chat_id: row.get(1)?,
+        questions: serde_json::from_str(&questions).unwrap_or_default(),
+        current: row.get::<_, i64>(3)? as usize,
+        status: row.get(4)?,
+    })
+}
+
+fn briefing_from_row(row: &rusqlite::Row) -> rusqlite::Result<BriefingSettings> {
+    let feeds: String = row.get(8)?;
+    Ok(BriefingSettings {
+        user_id: row.get(0)?,
+        enabled: row.get(1)?,
+        time: row.get(2)?,
+        timezone: row.get(3)?,
+        city: row.get(4)?,
+        latitude: row.get(5)?,
+        longitude: row.get(6)?,
+        calendar_url: row.get(7)?,
+        feeds: serde_json::from_str(&feeds).unwrap_or_default(),
+        last_sent_on: row.get(9)?,
+    })
+}
+
+fn automation_from_row(row: &rusqlite::Row) -> rusqlite::Result<Automation> {
+    let weekdays: String = row.get(5)?;
+    Ok(Automation {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        target: row.get(2)?,
+        name: row.get(3)?,
+        time: row.get(4)?,
+        weekdays: serde_json::from_str(&weekdays).unwrap_or_default(),
+        timezone: row.get(6)?,
+        kind: row.get(7)?,
+        content: row.get(8)?,
+        status: row.get(9)?,
+        last_run_on: row.get(10)?,
+        created_at: row.get(11)?,
+    })
+}
+
+fn incident_from_row(row: &rusqlite::Row) -> rusqlite::Result<Incident> {
+    let announced_to: Option<String> = row.get(5)?;
+    Ok(Incident {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        detail: row.get(2)?,
+        started_at: row.get(3)?,
+        resolved_at: row.get(4)?,
+        announced_to: announced_to.map(|json| serde_json::from_str(&json).unwrap_or_default()),
+    })
+}
+
+fn session_from_row(row: &rusqlite::Row) -> rusqlite::Result<Session> {
+    Ok(Session {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        title: row.get(2)?,
+        started_at: row.get(3)?,
+        last_at: row.get(4)?,
+        messages: row.get(5)?,
+    })
+}
+
+fn feed_source_from_row(row: &rusqlite::Row) -> rusqlite::Result<FeedSource> {
+    let titles: String = row.get(3)?;
+    Ok(FeedSource {
+        url: row.get(0)?,
+        etag: row.get(1)?,
+        last_modified: row.get(2)?,
+        titles: serde_json::from_str(&titles).unwrap_or_default(),
+        fetches: row.get(4)?,
+        not_modified: row.get(5)?,
+        failures: row.get(6)?,
+        consecutive_failures: row.get(7)?,
+        robots_blocked: row.get(8)?,
+        last_error: row.get(9)?,
+        last_fetched_at: row.get(10)?,
+        retry_at: row.get(11)?,
+    })
+}
+
+/// 舊版資料庫缺少的欄位以 ALTER TABLE 補上
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> rusqlite::Result<()> {
+    let exists = conn
+        .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))?
+        .exists(params![column])?;
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))?;
+    }
+    Ok(())
+}
+
+/// 擷取第一個命中關鍵字附近的文字作為摘錄
+fn excerpt(content: &str, keywords: &[String]) -> String {
+    let chars: Vec<char> = content.chars().collect();
+    // 轉小寫可能改變字元的位元組長度（如 `İ`），因此逐字轉換並記下小寫字串每個位元組對應的原文字元位置
+    let mut lower = String::with_capacity(content.len());
+    let mut origin = Vec::with_capacity(content.len());
+    for (index, c) in chars.iter().enumerate() {
+        lower.extend(c.to_lowercase());
+        origin.resize(lower.len(), index);
+    }
+    let hit = keywords
+        .iter()
+        .filter_map(|k| lower.find(&k.to_lowercase()))
+        .min()
+        .map(|byte_idx| origin[byte_idx])
+        .unwrap_or(0);
+
+    let start = hit.saturating_sub(EXCERPT_RADIUS);
+    let end = (hit + EXCERPT_RADIUS * 2).min(chars.len());
+    let mut text: String = chars[start..end].iter().collect();
+    if start > 0 {
+        text.insert(0, '…');
+    }
+    if end < chars.len() {
+        text.push('…');
+    }
+    text
+}
+
+/// 跳脫 LIKE 的萬用字元（搭配 `ESCAPE '\'`）
+fn escape_like(keyword: &str) -> String {
+    keyword.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// 在指定連線上執行 ANALYZE / VACUUM 並截斷 WAL 檔
+fn vacuum_on(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("ANALYZE; VACUUM;")?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excerpt_centers_on_first_keyword() {
+        let content = format!("{}命中{}", "前".repeat(40), "後".repeat(80));
+        let text = excerpt(&content, &["命中".to_string()]);
+        assert_eq!(text, format!("…{}命中{}…", "前".repeat(EXCERPT_RADIUS), "後".repeat(EXCERPT_RADIUS * 2 - 2)));
+    }
+
+    #[test]
+    fn excerpt_offsets_survive_lowercase_length_changes() {
+        // `İ` 轉小寫後由 2 位元組變成 3 位元組，命中位置仍須對應原文
+        let content = format!("{}Keyword{}", "İ".repeat(40), "x".repeat(80));
+        let text = excerpt(&content, &["keyword".to_string()]);
+        assert!(text.starts_with(&format!("…{}Keyword", "İ".repeat(EXCERPT_RADIUS))));
+    }
+
+    #[test]
+    fn compressed_conversations_read_back_as_text() {
+        let store = ConversationStore::open(":memory:").unwrap();
+        let long = "這是一段夠長而且會重複的對話內容，".repeat(10);
+        store.record("U1", "user", &long, None, None).unwrap();
+        store.enable_compression();
+        store.record("U1", "assistant", &long, Some("model"), None).unwrap();
+        store.record("U1", "user", "短訊息", None, None).unwrap();
+        let report = store.compress_conversations(100).unwrap();
+        assert_eq!(report.compressed_rows, 1);
+        assert!(report.bytes_after < report.bytes_before);
+        let messages: Vec<_> = store.recent_messages("U1", 10).unwrap().into_iter().map(|(_, content)| content).collect();
+        assert_eq!(messages, [long.clone(), long, "短訊息".to_string()]);
+    }
+
+    #[test]
+    fn table_counts_cover_compression_dicts() {
+        let store = ConversationStore::open(":memory:").unwrap();
+        let counts = store.table_counts().unwrap();
+        assert!(counts.contains(&("compression_dicts", 0)));
+    }
+
+    #[test]
+    fn narrowcasts_stay_sending_until_finished() {
+        let store = ConversationStore::open(":memory:").unwrap();
+        let id = store.create_announcement(None, "群發", &[], None).unwrap();
+        assert!(!store.start_narrowcast(id, "req-1").unwrap());
+        store.transition_announcement(id, "draft", "approved").unwrap();
+        assert!(store.start_narrowcast(id, "req-1").unwrap());
+        assert_eq!(store.sending_narrowcasts().unwrap(), [(id, "req-1".to_string())]);
+        let announcement = store.get_announcement(id).unwrap().unwrap();
+        assert_eq!((announcement.status.as_str(), announcement.narrowcast_request_id.as_deref()), ("sending", Some("req-1")));
+        store.transition_announcement(id, "sending", "sent").unwrap();
+        assert!(store.sending_narrowcasts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn excerpt_without_hit_starts_at_beginning() {
+        assert_eq!(excerpt("短短的內容", &["沒有".to_string()]), "短短的內容");
+    }
+}
@@ -0,0 +1,439 @@
+//! Flex 訊息模組
+//! 以型別建構 Flex 訊息的容器（bubble、carousel）與元件（box、text、span、button、image、separator），
+//! 取代手寫 JSON，欄位名稱與列舉值由型別保證
+
+use serde::Serialize;
+
+use crate::Action;
+
+/// carousel 可容納的 bubble 數上限
+pub const CAROUSEL_LIMIT: usize = 12;
+
+/// Flex 容器
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FlexContainer {
+    Bubble(Bubble),
+    /// 可左右滑動的多個 bubble（最多 12 個）
+    Carousel { contents: Vec<Bubble> },
+}
+
+impl FlexContainer {
+    /// 建立 carousel，超過上限的 bubble 會被捨棄
+    pub fn carousel(mut bubbles: Vec<Bubble>) -> Self {
+        bubbles.truncate(CAROUSEL_LIMIT);
+        FlexContainer::Carousel { contents: bubbles }
+    }
+}
+
+impl From<Bubble> for FlexContainer {
+    fn from(bubble: Bubble) -> Self {
+        FlexContainer::Bubble(bubble)
+    }
+}
+
+/// 單張卡片；header、body、footer 皆為 box
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Bubble {
+    /// nano、micro、kilo、mega（預設）、giga
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<Box<FlexComponent>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hero: Option<Box<FlexComponent>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<Box<FlexComponent>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub footer: Option<Box<FlexComponent>>,
+}
+
+impl Bubble {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn size(mut self, size: impl Into<String>) -> Self {
+        self.size = Some(size.into());
+        self
+    }
+
+    pub fn header(mut self, header: FlexBox) -> Self {
+        self.header = Some(Box::new(header.into()));
+        self
+    }
+
+    /// 主視覺（通常為圖片）
+    pub fn hero(mut self, hero: impl Into<FlexComponent>) -> Self {
+        self.hero = Some(Box::new(hero.into()));
+        self
+    }
+
+    pub fn body(mut self, body: FlexBox) -> Self {
+        self.body = Some(Box::new(body.into()));
+        self
+    }
+
+    pub fn footer(mut self, footer: FlexBox) -> Self {
+        self.footer = Some(Box::new(footer.into()));
+        self
+    }
+}
+
+/// Flex 元件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FlexComponent {
+    Box(FlexBox),
+    Text(FlexText),
+    Button(FlexButton),
+    Image(FlexImage),
+    Separator {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        margin: Option<String>,
+    },
+}
+
+impl FlexComponent {
+    pub fn separator() -> Self {
+        FlexComponent::Separator { margin: None }
+    }
+
+    /// 帶上方間距的分隔線
+    pub fn separator_with_margin(margin: impl Into<String>) -> Self {
+        FlexComponent::Separator { margin: Some(margin.into()) }
+    }
+}
+
+/// box 的排列方向
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    Vertical,
+    Horizontal,
+    Baseline,
+}
+
+/// 容納其他元件的 box
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlexBox {
+    pub layout: Layout,
+    pub contents: Vec<FlexComponent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spacing: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub padding_all: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<Action>,
+}
+
+impl FlexBox {
+    pub fn new(layout: Layout, contents: Vec<FlexComponent>) -> Self {
+        Self {
+            layout,
+            contents,
+            spacing: None,
+            margin: None,
+            background_color: None,
+            padding_all: None,
+            action: None,
+        }
+    }
+
+    pub fn vertical(contents: Vec<FlexComponent>) -> Self {
+        Self::new(Layout::Vertical, contents)
+    }
+
+    pub fn horizontal(contents: Vec<FlexComponent>) -> Self {
+        Self::new(Layout::Horizontal, contents)
+    }
+
+    pub fn spacing(mut self, spacing: impl Into<String>) -> Self {
+        self.spacing = Some(spacing.into());
+        self
+    }
+
+    pub fn margin(mut self, margin: impl Into<String>) -> Self {
+        self.margin = Some(margin.into());
+        self
+    }
+
+    pub fn background_color(mut self, color: impl Into<String>) -> Self {
+        self.background_color = Some(color.into());
+        self
+    }
+
+    pub fn padding_all(mut self, padding: impl Into<String>) -> Self {
+        self.padding_all = Some(padding.into());
+        self
+    }
+
+    /// 點擊整個 box 時的動作
+    pub fn action(mut self, action: Action) -> Self {
+        self.action = Some(action);
+        self
+    }
+}
+
+/// 文字元件
+#[derive(Debug, Clone, Serialize)]
+pub struct FlexText {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flex: Option<u32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub wrap: bool,
+    /// 各段樣式不同的文字；設定時 LINE 忽略 `text`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contents: Vec<FlexSpan>,
+}
+
+impl FlexText {
+    /// LINE 不接受空字串，空白文字以一個空格代替
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self {
+            text: if text.is_empty() { " ".to_string() } else { text },
+            size: None,
+            weight: None,
+            color: None,
+            align: None,
+            margin: None,
+            flex: None,
+            wrap: false,
+            contents: Vec::new(),
+        }
+    }
+
+    /// 以多段 span 組成文字；`text` 保留為純文字內容
+    pub fn spans(mut self, spans: Vec<FlexSpan>) -> Self {
+        self.contents = spans;
+        self
+    }
+
+    /// xxs、xs、sm、md、lg、xl… 或像素值
+    pub fn size(mut self, size: impl Into<String>) -> Self {
+        self.size = Some(size.into());
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.weight = Some("bold".to_string());
+        self
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    /// start、center、end
+    pub fn align(mut self, align: impl Into<String>) -> Self {
+        self.align = Some(align.into());
+        self
+    }
+
+    pub fn margin(mut self, margin: impl Into<String>) -> Self {
+        self.margin = Some(margin.into());
+        self
+    }
+
+    /// 在水平 box 中所佔的比例
+    pub fn flex(mut self, flex: u32) -> Self {
+        self.flex = Some(flex);
+        self
+    }
+
+    /// 自動換行
+    pub fn wrap(mut self) -> Self {
+        self.wrap = true;
+        self
+    }
+}
+
+/// 文字元件中的一段文字
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename = "span")]
+pub struct FlexSpan {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// none、underline、line-through
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoration: Option<String>,
+}
+
+impl FlexSpan {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self { text: text.into(), size: None, weight: None, color: None, decoration: None }
+    }
+
+    pub fn size(mut self, size: impl Into<String>) -> Self {
+        self.size = Some(size.into());
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.weight = Some("bold".to_string());
+        self
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.decoration = Some("underline".to_string());
+        self
+    }
+}
+
+/// 按鈕樣式
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ButtonStyle {
+    Primary,
+    Secondary,
+    Link,
+}
+
+/// 按鈕元件
+#[derive(Debug, Clone, Serialize)]
+pub struct FlexButton {
+    pub action: Action,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<ButtonStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// sm、md
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin: Option<String>,
+}
+
+impl FlexButton {
+    pub fn new(action: Action) -> Self {
+        Self {
+            action,
+            style: None,
+            color: None,
+            height: None,
+            margin: None,
+        }
+    }
+
+    pub fn style(mut self, style: ButtonStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    pub fn height(mut self, height: impl Into<String>) -> Self {
+        self.height = Some(height.into());
+        self
+    }
+
+    pub fn margin(mut self, margin: impl Into<String>) -> Self {
+        self.margin = Some(margin.into());
+        self
+    }
+}
+
+/// 圖片元件（網址需為 HTTPS）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlexImage {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    /// 寬:高，例如 `20:13`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<String>,
+    /// fit 或 cover
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspect_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<Action>,
+}
+
+impl FlexImage {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            size: None,
+            aspect_ratio: None,
+            aspect_mode: None,
+            action: None,
+        }
+    }
+
+    pub fn size(mut self, size: impl Into<String>) -> Self {
+        self.size = Some(size.into());
+        self
+    }
+
+    pub fn aspect_ratio(mut self, ratio: impl Into<String>) -> Self {
+        self.aspect_ratio = Some(ratio.into());
+        self
+    }
+
+    /// 裁切填滿（預設為完整顯示）
+    pub fn cover(mut self) -> Self {
+        self.aspect_mode = Some("cover".to_string());
+        self
+    }
+
+    pub fn action(mut self, action: Action) -> Self {
+        self.action = Some(action);
+        self
+    }
+}
+
+impl From<FlexBox> for FlexComponent {
+    fn from(component: FlexBox) -> Self {
+        FlexComponent::Box(component)
+    }
+}
+
+impl From<FlexText> for FlexComponent {
+    fn from(component: FlexText) -> Self {
+        FlexComponent::Text(component)
+    }
+}
+
+impl From<FlexButton> for FlexComponent {
+    fn from(component: FlexButton) -> Self {
+        FlexComponent::Button(component)
+    }
+}
+
+impl From<FlexImage> for FlexComponent {
+    fn from(component: FlexImage) -> Self {
+        FlexComponent::Image(component)
+    }
+}
@@ -0,0 +1,1586 @@
+//! LINE Messaging API 客戶端模組
+//! 處理 LINE 訊息發送與事件解析
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use subtle::ConstantTimeEq;
+
+pub mod flex;
+
+pub use flex::{Bubble, ButtonStyle, FlexBox, FlexButton, FlexComponent, FlexContainer, FlexImage, FlexSpan, FlexText};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 官方 Messaging API 位址
+const OFFICIAL_API_BASE_URL: &str = "https://api.line.me";
+/// 官方的訊息內容下載位址
+const OFFICIAL_DATA_API_BASE_URL: &str = "https://api-data.line.me";
+/// 單次 reply、push 或 multicast 可送出的訊息上限
+pub const MAX_MESSAGES: usize = 5;
+/// 單次 multicast 的收件人上限
+pub const MULTICAST_LIMIT: usize = 500;
+/// 防止重試時重複送達的冪等 key（UUID，LINE 保留 24 小時）
+const RETRY_KEY_HEADER: &str = "X-Line-Retry-Key";
+/// 以已受理過的 retry key 重送時，409 回應附上的原請求 ID
+const ACCEPTED_REQUEST_ID_HEADER: &str = "x-line-accepted-request-id";
+
+tokio::task_local! {
+    /// 單次請求的試運行旗標
+    static DRY_RUN: bool;
+}
+
+/// 以試運行模式執行 `future`：期間送出的訊息只交給記錄器，不呼叫 LINE（不延伸到其中另外 spawn 的工作）
+pub async fn dry_run<F: Future>(future: F) -> F::Output {
+    DRY_RUN.scope(true, future).await
+}
+
+/// 目前的工作是否在 [`dry_run`] 範圍內
+pub fn in_dry_run() -> bool {
+    DRY_RUN.try_with(|dry_run| *dry_run).unwrap_or(false)
+}
+
+/// 試運行時攔下的訊息
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunMessage {
+    /// `reply`、`push`、`multicast`、`broadcast` 或 `narrowcast`
+    pub endpoint: &'static str,
+    /// reply token、推送對象或以逗號分隔的多位對象；廣播時為 None
+    pub target: Option<String>,
+    /// 原本要送出的請求內容
+    pub body: serde_json::Value,
+}
+
+type DryRunRecorder = Box<dyn Fn(DryRunMessage) + Send + Sync>;
+/// 推送因本月訊息額度用完被拒時呼叫
+type QuotaListener = Box<dyn Fn() + Send + Sync>;
+
+/// 以 channel secret 計算 webhook 內容的 HMAC-SHA256 簽名（Base64）
+fn sign(secret: &str, body: &[u8]) -> Option<String> {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).ok()?;
+    mac.update(body);
+    Some(BASE64.encode(mac.finalize().into_bytes()))
+}
+
+/// 以固定時間比較簽名，避免由回應時間推測正確的簽名
+fn signature_matches(secret: &str, body: &[u8], signature: &str) -> bool {
+    sign(secret, body).is_some_and(|expected| bool::from(expected.as_bytes().ct_eq(signature.as_bytes())))
+}
+
+/// LINE API 客戶端
+pub struct LineClient {
+    client: Client,
+    /// 可在執行中輪替（外部密鑰管理）
+    channel_access_token: RwLock<String>,
+    channel_secret: String,
+    /// 輪替期間仍接受的舊 channel secret
+    previous_channel_secret: Option<String>,
+    /// Messaging API 位址（本機開發可指向 mock-line）
+    api_base_url: String,
+    /// 下載訊息內容的位址（官方為 api-data.line.me，其他位址沿用 `api_base_url`）
+    data_api_base_url: String,
+    /// 全域試運行：所有送出的訊息都只記錄、不呼叫 LINE
+    dry_run: AtomicBool,
+    dry_run_recorder: RwLock<Option<DryRunRecorder>>,
+    quota_listener: RwLock<Option<QuotaListener>>,
+}
+
+/// reply 失敗
+#[derive(Debug)]
+pub struct ReplyError {
+    pub error: reqwest::Error,
+    /// LINE 以 400 `Invalid reply token` 拒絕：token 已過期或已使用。其他 400（訊息內容不合法等）為 false
+    pub invalid_token: bool,
+}
+
+impl std::fmt::Display for ReplyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for ReplyError {}
+
+/// Webhook 簽名相符的 channel secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureKey {
+    Current,
+    /// 輪替前的舊 secret
+    Previous,
+}
+
+/// LINE 訊息事件
+#[derive(Debug, Deserialize)]
+pub struct WebhookEvent {
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    #[serde(rename = "message")]
+    Message(Box<MessageEvent>),
+    #[serde(rename = "postback")]
+    Postback(PostbackEvent),
+    #[serde(rename = "follow")]
+    Follow(FollowEvent),
+    #[serde(rename = "unfollow")]
+    Unfollow(UnfollowEvent),
+    #[serde(other)]
+    Unknown,
+}
+
+impl Event {
+    /// 事件的來源；未知事件為 None
+    pub fn source(&self) -> Option<&Source> {
+        match self {
+            Event::Message(event) => Some(&event.source),
+            Event::Postback(event) => Some(&event.source),
+            Event::Follow(event) => Some(&event.source),
+            Event::Unfollow(event) => Some(&event.source),
+            Event::Unknown => None,
+        }
+    }
+
+    /// 事件的 `webhookEventId`（重送判斷用）
+    pub fn webhook_event_id(&self) -> Option<&str> {
+        match self {
+            Event::Message(event) => event.webhook_event_id.as_deref(),
+            Event::Postback(event) => event.webhook_event_id.as_deref(),
+            Event::Follow(event) => event.webhook_event_id.as_deref(),
+            Event::Unfollow(event) => event.webhook_event_id.as_deref(),
+            Event::Unknown => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MessageEvent {
+    /// 事件 ID；LINE 重送同一事件時不變
+    #[serde(rename = "webhookEventId", default)]
+    pub webhook_event_id: Option<String>,
+    #[serde(rename = "replyToken")]
+    pub reply_token: String,
+    pub source: Source,
+    pub message: Message,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostbackEvent {
+    /// 事件 ID；LINE 重送同一事件時不變
+    #[serde(rename = "webhookEventId", default)]
+    pub webhook_event_id: Option<String>,
+    #[serde(rename = "replyToken")]
+    pub reply_token: String,
+    pub source: Source,
+    pub postback: Postback,
+}
+
+/// 加入好友（或解除封鎖）事件
+#[derive(Debug, Deserialize)]
+pub struct FollowEvent {
+    /// 事件 ID；LINE 重送同一事件時不變
+    #[serde(rename = "webhookEventId", default)]
+    pub webhook_event_id: Option<String>,
+    #[serde(rename = "replyToken")]
+    pub reply_token: String,
+    pub source: Source,
+}
+
+/// 取消好友（封鎖）事件；沒有 reply token
+#[derive(Debug, Deserialize)]
+pub struct UnfollowEvent {
+    /// 事件 ID；LINE 重送同一事件時不變
+    #[serde(rename = "webhookEventId", default)]
+    pub webhook_event_id: Option<String>,
+    pub source: Source,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Source {
+    #[serde(rename = "userId")]
+    pub user_id: Option<String>,
+    #[serde(rename = "groupId")]
+    pub group_id: Option<String>,
+    #[serde(rename = "roomId")]
+    pub room_id: Option<String>,
+}
+
+impl Source {
+    /// 回覆/推送的對象：群組、聊天室或一對一時的使用者
+    pub fn chat_id(&self) -> Option<&str> {
+        self.group_id.as_deref().or(self.room_id.as_deref()).or(self.user_id.as_deref())
+    }
+
+    /// 是否來自群組或聊天室
+    pub fn is_group(&self) -> bool {
+        self.group_id.is_some() || self.room_id.is_some()
+    }
+}
+
+/// multicast 第 `index` 批的 retry key：第一批沿用原 key，其餘以原 key 與序號雜湊成新的 UUID
+fn batch_retry_key(key: &str, index: usize) -> String {
+    if index == 0 {
+        return key.to_string();
+    }
+    let digest = hex::encode(&Sha256::digest(format!("{}:{}", key, index).as_bytes())[..16]);
+    format!("{}-{}-4{}-a{}-{}", &digest[0..8], &digest[8..12], &digest[13..16], &digest[17..20], &digest[20..32])
+}
+
+/// 是否為 LINE userId（`U` 加 32 位十六進位）；multicast 只要有一個無效 ID 就會整批失敗
+pub fn is_user_id(id: &str) -> bool {
+    id.strip_prefix('U')
+        .is_some_and(|hex| hex.len() == 32 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Message {
+    /// 訊息 ID（下載圖片等內容時使用）
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub text: Option<String>,
+    /// 圖片、影片與音訊的內容來源
+    #[serde(rename = "contentProvider")]
+    pub content_provider: Option<ContentProvider>,
+    /// 影片與音訊的長度（毫秒）
+    pub duration: Option<u64>,
+    /// 檔案訊息的檔名與大小（位元組）
+    #[serde(rename = "fileName")]
+    pub file_name: Option<String>,
+    #[serde(rename = "fileSize")]
+    pub file_size: Option<u64>,
+    /// 貼圖的包裝與貼圖 ID
+    #[serde(rename = "packageId")]
+    pub package_id: Option<String>,
+    #[serde(rename = "stickerId")]
+    pub sticker_id: Option<String>,
+    /// 貼圖表達的意思（LINE 提供的關鍵字，可能沒有）
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// 位置訊息的名稱、地址與座標
+    pub title: Option<String>,
+    pub address: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// 文字訊息中的 @提及
+    pub mention: Option<Mention>,
+    /// 引用此訊息時使用的 token（文字、貼圖、圖片與影片訊息）
+    #[serde(rename = "quoteToken")]
+    pub quote_token: Option<String>,
+}
+
+impl Message {
+    /// 是否 @提及了本機器人
+    pub fn mentions_self(&self) -> bool {
+        self.mention.as_ref().is_some_and(|m| m.mentionees.iter().any(|m| m.is_self))
+    }
+}
+
+/// 文字訊息中的 @提及
+#[derive(Debug, Deserialize)]
+pub struct Mention {
+    pub mentionees: Vec<Mentionee>,
+}
+
+/// 被提及的對象；`index` 與 `length` 以 UTF-16 計算
+#[derive(Debug, Deserialize)]
+pub struct Mentionee {
+    pub index: usize,
+    pub length: usize,
+    /// `user`（個別使用者）或 `all`（@All）
+    #[serde(rename = "type")]
+    pub mention_type: String,
+    #[serde(rename = "userId")]
+    pub user_id: Option<String>,
+    /// 被提及的是本機器人
+    #[serde(rename = "isSelf", default)]
+    pub is_self: bool,
+}
+
+/// 影片與音訊內容的轉檔狀態；`succeeded` 之後才能下載
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscodingStatus {
+    Processing,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Deserialize)]
+struct TranscodingResponse {
+    status: TranscodingStatus,
+}
+
+/// 媒體內容的來源：`line` 需以 [`LineClient::get_message_content`] 下載，`external` 則直接提供網址
+#[derive(Debug, Deserialize)]
+pub struct ContentProvider {
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    #[serde(rename = "originalContentUrl")]
+    pub original_content_url: Option<String>,
+    #[serde(rename = "previewImageUrl")]
+    pub preview_image_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Postback {
+    pub data: String,
+}
+
+/// 發送訊息請求
+#[derive(Debug, Serialize)]
+pub struct ReplyMessageRequest<'a> {
+    #[serde(rename = "replyToken")]
+    pub reply_token: &'a str,
+    pub messages: &'a [OutgoingMessage],
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushMessageRequest<'a> {
+    pub to: &'a str,
+    pub messages: &'a [OutgoingMessage],
+}
+
+#[derive(Debug, Serialize)]
+pub struct MulticastRequest<'a> {
+    pub to: &'a [String],
+    pub messages: &'a [OutgoingMessage],
+}
+
+#[derive(Debug, Serialize)]
+pub struct BroadcastRequest {
+    pub messages: Vec<OutgoingMessage>,
+}
+
+/// 群發（narrowcast）請求；`recipient` 與 `filter` 為 LINE 的收件人物件（受眾群組等，可用 and/or/not 組合）
+/// 與屬性篩選（`{"demographic": ...}`），原樣轉交給 LINE 驗證
+#[derive(Debug, Serialize)]
+pub struct NarrowcastRequest {
+    pub messages: Vec<OutgoingMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<NarrowcastLimit>,
+}
+
+/// 群發人數上限
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct NarrowcastLimit {
+    /// 最多送出人數（從符合條件者中隨機選取）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<u32>,
+    /// 只送到本月剩餘額度為止
+    #[serde(rename = "upToRemainingQuota")]
+    pub up_to_remaining_quota: bool,
+}
+
+/// 群發進度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NarrowcastProgress {
+    /// `waiting`（計算對象中）、`sending`、`succeeded` 或 `failed`
+    pub phase: String,
+    #[serde(rename(deserialize = "successCount"))]
+    pub success_count: Option<u64>,
+    #[serde(rename(deserialize = "failureCount"))]
+    pub failure_count: Option<u64>,
+    #[serde(rename(deserialize = "targetCount"))]
+    pub target_count: Option<u64>,
+    /// 失敗原因（`phase` 為 `failed` 時）
+    #[serde(rename(deserialize = "failedDescription"))]
+    pub failed_description: Option<String>,
+    #[serde(rename(deserialize = "errorCode"))]
+    pub error_code: Option<i64>,
+    #[serde(rename(deserialize = "acceptedTime"))]
+    pub accepted_time: Option<String>,
+    #[serde(rename(deserialize = "completedTime"))]
+    pub completed_time: Option<String>,
+}
+
+/// 顯示載入動畫請求
+#[derive(Debug, Serialize)]
+pub struct LoadingRequest<'a> {
+    #[serde(rename = "chatId")]
+    pub chat_id: &'a str,
+    #[serde(rename = "loadingSeconds")]
+    pub loading_seconds: u32,
+}
+
+/// 可發送的訊息種類
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum OutgoingMessage {
+    Text(TextMessage),
+    Template(TemplateMessage),
+    Image(ImageMessage),
+    Audio(AudioMessage),
+    Flex(FlexMessage),
+    Sticker(StickerMessage),
+    Location(LocationMessage),
+    TextV2(TextV2Message),
+}
+
+impl OutgoingMessage {
+    /// 附上快速回覆（取代原有的快速回覆）
+    pub fn set_quick_reply(&mut self, quick_reply: QuickReply) {
+        let slot = match self {
+            OutgoingMessage::Text(message) => &mut message.quick_reply,
+            OutgoingMessage::Template(message) => &mut message.quick_reply,
+            OutgoingMessage::Image(message) => &mut message.quick_reply,
+            OutgoingMessage::Audio(message) => &mut message.quick_reply,
+            OutgoingMessage::Flex(message) => &mut message.quick_reply,
+            OutgoingMessage::Sticker(message) => &mut message.quick_reply,
+            OutgoingMessage::Location(message) => &mut message.quick_reply,
+            OutgoingMessage::TextV2(message) => &mut message.quick_reply,
+        };
+        *slot = Some(quick_reply);
+    }
+
+    /// 引用使用者的訊息；僅文字與貼圖訊息支援，其他種類回傳 false
+    pub fn set_quote_token(&mut self, quote_token: impl Into<String>) -> bool {
+        let slot = match self {
+            OutgoingMessage::Text(message) => &mut message.quote_token,
+            OutgoingMessage::TextV2(message) => &mut message.quote_token,
+            OutgoingMessage::Sticker(message) => &mut message.quote_token,
+            _ => return false,
+        };
+        *slot = Some(quote_token.into());
+        true
+    }
+}
+
+impl From<TextMessage> for OutgoingMessage {
+    fn from(message: TextMessage) -> Self {
+        OutgoingMessage::Text(message)
+    }
+}
+
+impl From<TextV2Message> for OutgoingMessage {
+    fn from(message: TextV2Message) -> Self {
+        OutgoingMessage::TextV2(message)
+    }
+}
+
+impl From<TemplateMessage> for OutgoingMessage {
+    fn from(message: TemplateMessage) -> Self {
+        OutgoingMessage::Template(message)
+    }
+}
+
+impl From<ImageMessage> for OutgoingMessage {
+    fn from(message: ImageMessage) -> Self {
+        OutgoingMessage::Image(message)
+    }
+}
+
+impl From<AudioMessage> for OutgoingMessage {
+    fn from(message: AudioMessage) -> Self {
+        OutgoingMessage::Audio(message)
+    }
+}
+
+impl From<FlexMessage> for OutgoingMessage {
+    fn from(message: FlexMessage) -> Self {
+        OutgoingMessage::Flex(message)
+    }
+}
+
+impl From<StickerMessage> for OutgoingMessage {
+    fn from(message: StickerMessage) -> Self {
+        OutgoingMessage::Sticker(message)
+    }
+}
+
+impl From<LocationMessage> for OutgoingMessage {
+    fn from(message: LocationMessage) -> Self {
+        OutgoingMessage::Location(message)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextMessage {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub text: String,
+    /// LINE 表情貼（每個對應 `text` 中的一個 `$`，最多 20 個）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub emojis: Vec<Emoji>,
+    #[serde(rename = "quickReply", skip_serializing_if = "Option::is_none")]
+    pub quick_reply: Option<QuickReply>,
+    /// 引用的使用者訊息
+    #[serde(rename = "quoteToken", skip_serializing_if = "Option::is_none")]
+    pub quote_token: Option<String>,
+}
+
+/// 文字訊息中的 LINE 表情貼
+#[derive(Debug, Clone, Serialize)]
+pub struct Emoji {
+    /// `$` 在文字中的位置（UTF-16 code unit）
+    pub index: usize,
+    #[serde(rename = "productId")]
+    pub product_id: String,
+    #[serde(rename = "emojiId")]
+    pub emoji_id: String,
+}
+
+impl TextMessage {
+    /// 文字長度上限
+    pub const TEXT_LIMIT: usize = 5000;
+
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            message_type: "text".to_string(),
+            text: text.into(),
+            emojis: Vec::new(),
+            quick_reply: None,
+            quote_token: None,
+        }
+    }
+
+    /// 附上快速回覆按鈕（最多 13 個）
+    pub fn with_quick_reply(mut self, quick_reply: impl Into<QuickReply>) -> Self {
+        self.quick_reply = Some(quick_reply.into());
+        self
+    }
+}
+
+/// textV2 文字訊息：`text` 中的 `{key}` 依 `substitution` 換成提及或表情貼，
+/// 字面的大括號寫成 `{{`、`}}`（key 僅限英數字與底線，最多 20 字元）
+#[derive(Debug, Serialize)]
+pub struct TextV2Message {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub substitution: BTreeMap<String, Substitution>,
+    #[serde(rename = "quickReply", skip_serializing_if = "Option::is_none")]
+    pub quick_reply: Option<QuickReply>,
+    /// 引用的使用者訊息
+    #[serde(rename = "quoteToken", skip_serializing_if = "Option::is_none")]
+    pub quote_token: Option<String>,
+}
+
+/// textV2 訊息中 `{key}` 的替換內容
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Substitution {
+    Mention {
+        mentionee: MentionTarget,
+    },
+    Emoji {
+        #[serde(rename = "productId")]
+        product_id: String,
+        #[serde(rename = "emojiId")]
+        emoji_id: String,
+    },
+}
+
+/// 提及的對象
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MentionTarget {
+    User {
+        #[serde(rename = "userId")]
+        user_id: String,
+    },
+    /// 群組中的所有人（@All）
+    All,
+}
+
+impl TextV2Message {
+    /// 以一般文字建立（大括號會自動跳脫）
+    pub fn new(text: &str) -> Self {
+        Self {
+            message_type: "textV2".to_string(),
+            text: escape_braces(text),
+            substitution: BTreeMap::new(),
+            quick_reply: None,
+            quote_token: None,
+        }
+    }
+
+    /// 在文字開頭 @提及使用者
+    pub fn mention_first(mut self, key: &str, user_id: impl Into<String>) -> Self {
+        self.text = format!("{{{}}} {}", key, self.text);
+        self.substitution
+            .insert(key.to_string(), Substitution::Mention { mentionee: MentionTarget::User { user_id: user_id.into() } });
+        self
+    }
+}
+
+/// 轉為 textV2：文字中的大括號跳脫，表情貼的 `$` 換成 `{emojiN}`
+impl From<TextMessage> for TextV2Message {
+    fn from(message: TextMessage) -> Self {
+        let mut emojis = message.emojis.into_iter().peekable();
+        let mut substitution = BTreeMap::new();
+        let mut text = String::with_capacity(message.text.len());
+        let mut index = 0;
+        for c in message.text.chars() {
+            match emojis.next_if(|emoji| emoji.index == index) {
+                Some(emoji) => {
+                    let key = format!("emoji{}", substitution.len());
+                    text.push_str(&format!("{{{}}}", key));
+                    substitution.insert(key, Substitution::Emoji { product_id: emoji.product_id, emoji_id: emoji.emoji_id });
+                }
+                None => match c {
+                    '{' => text.push_str("{{"),
+                    '}' => text.push_str("}}"),
+                    c => text.push(c),
+                },
+            }
+            index += c.len_utf16();
+        }
+        Self {
+            message_type: "textV2".to_string(),
+            text,
+            substitution,
+            quick_reply: message.quick_reply,
+            quote_token: message.quote_token,
+        }
+    }
+}
+
+fn escape_braces(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+/// 快速回覆（附在訊息上，僅在該則為最後一則訊息時顯示）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QuickReply {
+    pub items: Vec<QuickReplyItem>,
+}
+
+impl QuickReply {
+    /// 按鈕數上限
+    pub const LIMIT: usize = 13;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入按鈕，超過上限的按鈕會被捨棄
+    pub fn action(mut self, action: Action) -> Self {
+        if self.items.len() < Self::LIMIT {
+            self.items.push(QuickReplyItem::new(action));
+        }
+        self
+    }
+
+    /// 點擊後以 `text` 代替使用者發言（例如 `/help`）
+    pub fn message(self, label: impl Into<String>, text: impl Into<String>) -> Self {
+        self.action(Action::message(label, text))
+    }
+
+    pub fn postback(self, label: impl Into<String>, data: impl Into<String>) -> Self {
+        self.action(Action::postback(label, data))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl From<Vec<Action>> for QuickReply {
+    fn from(actions: Vec<Action>) -> Self {
+        actions.into_iter().fold(Self::new(), Self::action)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuickReplyItem {
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub action: Action,
+}
+
+impl QuickReplyItem {
+    pub fn new(action: Action) -> Self {
+        Self {
+            item_type: "action".to_string(),
+            action,
+        }
+    }
+}
+
+/// 圖片訊息（網址需為 HTTPS）
+#[derive(Debug, Serialize)]
+pub struct ImageMessage {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    #[serde(rename = "originalContentUrl")]
+    pub original_content_url: String,
+    #[serde(rename = "previewImageUrl")]
+    pub preview_image_url: String,
+    #[serde(rename = "quickReply", skip_serializing_if = "Option::is_none")]
+    pub quick_reply: Option<QuickReply>,
+}
+
+impl ImageMessage {
+    pub fn new(original_content_url: impl Into<String>, preview_image_url: impl Into<String>) -> Self {
+        Self {
+            message_type: "image".to_string(),
+            original_content_url: original_content_url.into(),
+            preview_image_url: preview_image_url.into(),
+            quick_reply: None,
+        }
+    }
+}
+
+/// 音訊訊息（m4a，`duration` 單位為毫秒）
+#[derive(Debug, Serialize)]
+pub struct AudioMessage {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    #[serde(rename = "originalContentUrl")]
+    pub original_content_url: String,
+    pub duration: u64,
+    #[serde(rename = "quickReply", skip_serializing_if = "Option::is_none")]
+    pub quick_reply: Option<QuickReply>,
+}
+
+impl AudioMessage {
+    pub fn new(original_content_url: impl Into<String>, duration: u64) -> Self {
+        Self {
+            message_type: "audio".to_string(),
+            original_content_url: original_content_url.into(),
+            duration,
+            quick_reply: None,
+        }
+    }
+}
+
+/// 貼圖訊息（可傳送的貼圖見 LINE 官方的貼圖清單）
+#[derive(Debug, Serialize)]
+pub struct StickerMessage {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    #[serde(rename = "packageId")]
+    pub package_id: String,
+    #[serde(rename = "stickerId")]
+    pub sticker_id: String,
+    #[serde(rename = "quickReply", skip_serializing_if = "Option::is_none")]
+    pub quick_reply: Option<QuickReply>,
+    /// 引用的使用者訊息
+    #[serde(rename = "quoteToken", skip_serializing_if = "Option::is_none")]
+    pub quote_token: Option<String>,
+}
+
+impl StickerMessage {
+    pub fn new(package_id: impl Into<String>, sticker_id: impl Into<String>) -> Self {
+        Self {
+            message_type: "sticker".to_string(),
+            package_id: package_id.into(),
+            sticker_id: sticker_id.into(),
+            quick_reply: None,
+            quote_token: None,
+        }
+    }
+}
+
+/// 位置訊息（以地圖圖釘顯示；名稱與地址各最多 100 字）
+#[derive(Debug, Serialize)]
+pub struct LocationMessage {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub title: String,
+    pub address: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(rename = "quickReply", skip_serializing_if = "Option::is_none")]
+    pub quick_reply: Option<QuickReply>,
+}
+
+impl LocationMessage {
+    pub fn new(title: impl Into<String>, address: impl Into<String>, latitude: f64, longitude: f64) -> Self {
+        Self {
+            message_type: "location".to_string(),
+            title: title.into(),
+            address: address.into(),
+            latitude,
+            longitude,
+            quick_reply: None,
+        }
+    }
+}
+
+/// Flex 訊息（`contents` 為 bubble 或 carousel 容器，以 [`flex`] 的型別建構）
+#[derive(Debug, Serialize)]
+pub struct FlexMessage {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    #[serde(rename = "altText")]
+    pub alt_text: String,
+    pub contents: FlexContainer,
+    #[serde(rename = "quickReply", skip_serializing_if = "Option::is_none")]
+    pub quick_reply: Option<QuickReply>,
+}
+
+impl FlexMessage {
+    pub fn new(alt_text: impl Into<String>, contents: impl Into<FlexContainer>) -> Self {
+        Self {
+            message_type: "flex".to_string(),
+            alt_text: alt_text.into(),
+            contents: contents.into(),
+            quick_reply: None,
+        }
+    }
+}
+
+/// 範本訊息
+#[derive(Debug, Serialize)]
+pub struct TemplateMessage {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    #[serde(rename = "altText")]
+    pub alt_text: String,
+    pub template: Template,
+    #[serde(rename = "quickReply", skip_serializing_if = "Option::is_none")]
+    pub quick_reply: Option<QuickReply>,
+}
+
+impl TemplateMessage {
+    pub fn new(alt_text: impl Into<String>, template: Template) -> Self {
+        Self {
+            message_type: "template".to_string(),
+            alt_text: alt_text.into(),
+            template,
+            quick_reply: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Template {
+    /// 確認範本（兩個按鈕）
+    Confirm { text: String, actions: Vec<Action> },
+    /// 可左右滑動的多張卡片（最多 10 欄，以 [`Template::carousel`] 建立）
+    Carousel { columns: Vec<CarouselColumn> },
+    /// 按鈕範本（最多 4 個按鈕，以 [`Template::buttons`] 建立）
+    Buttons(ButtonsTemplate),
+}
+
+impl Template {
+    /// carousel 的欄數上限
+    pub const CAROUSEL_LIMIT: usize = 10;
+
+    /// 建立 carousel：超過上限的欄位會被捨棄，過長的標題與內文會被截斷；
+    /// LINE 要求每欄按鈕數相同，各欄按鈕數不同時回傳錯誤（不替呼叫端捨棄按鈕）
+    pub fn carousel(mut columns: Vec<CarouselColumn>) -> Result<Self, String> {
+        columns.truncate(Self::CAROUSEL_LIMIT);
+        if let Some(first) = columns.first() {
+            let actions = first.actions.len();
+            if let Some(index) = columns.iter().position(|c| c.actions.len() != actions) {
+                return Err(format!(
+                    "carousel 各欄的按鈕數需相同：第 1 欄有 {} 個，第 {} 欄有 {} 個",
+                    actions,
+                    index + 1,
+                    columns[index].actions.len()
+                ));
+            }
+        }
+        for column in &mut columns {
+            column.fit();
+        }
+        Ok(Template::Carousel { columns })
+    }
+
+    /// 建立按鈕範本，過長的標題與內文會被截斷
+    pub fn buttons(mut template: ButtonsTemplate) -> Self {
+        if let Some(title) = &mut template.title {
+            *title = truncate(title, CarouselColumn::TITLE_LIMIT);
+        }
+        let limit = match template.title.is_some() || template.thumbnail_image_url.is_some() {
+            true => CarouselColumn::SHORT_TEXT_LIMIT,
+            false => ButtonsTemplate::TEXT_LIMIT,
+        };
+        template.text = non_empty(truncate(&template.text, limit));
+        Template::Buttons(template)
+    }
+}
+
+/// 按鈕範本：標題、縮圖、內文與 1–4 個按鈕
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ButtonsTemplate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_image_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub text: String,
+    pub actions: Vec<Action>,
+    /// 點擊圖片、標題或文字時的動作
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_action: Option<Action>,
+}
+
+impl ButtonsTemplate {
+    /// 無標題與圖片時的內文上限（有標題或圖片時為 60 字）
+    const TEXT_LIMIT: usize = 160;
+    /// 按鈕數上限
+    pub const ACTION_LIMIT: usize = 4;
+
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            thumbnail_image_url: None,
+            title: None,
+            text: text.into(),
+            actions: Vec::new(),
+            default_action: None,
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// 縮圖（網址需為 HTTPS）
+    pub fn thumbnail(mut self, url: impl Into<String>) -> Self {
+        self.thumbnail_image_url = Some(url.into());
+        self
+    }
+
+    /// 加入按鈕，超過上限的按鈕會被捨棄
+    pub fn action(mut self, action: Action) -> Self {
+        if self.actions.len() < Self::ACTION_LIMIT {
+            self.actions.push(action);
+        }
+        self
+    }
+
+    pub fn default_action(mut self, action: Action) -> Self {
+        self.default_action = Some(action);
+        self
+    }
+}
+
+/// carousel 的一欄
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CarouselColumn {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_image_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub text: String,
+    /// 1–3 個按鈕
+    pub actions: Vec<Action>,
+    /// 點擊圖片、標題或文字時的動作
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_action: Option<Action>,
+}
+
+impl CarouselColumn {
+    /// 內文上限：無標題與圖片時 120 字，否則 60 字
+    const TEXT_LIMIT: usize = 120;
+    const SHORT_TEXT_LIMIT: usize = 60;
+    const TITLE_LIMIT: usize = 40;
+    /// 每欄按鈕數上限
+    pub const ACTION_LIMIT: usize = 3;
+
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            thumbnail_image_url: None,
+            title: None,
+            text: text.into(),
+            actions: Vec::new(),
+            default_action: None,
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// 縮圖（網址需為 HTTPS）
+    pub fn thumbnail(mut self, url: impl Into<String>) -> Self {
+        self.thumbnail_image_url = Some(url.into());
+        self
+    }
+
+    /// 加入按鈕，超過上限的按鈕會被捨棄
+    pub fn action(mut self, action: Action) -> Self {
+        if self.actions.len() < Self::ACTION_LIMIT {
+            self.actions.push(action);
+        }
+        self
+    }
+
+    pub fn default_action(mut self, action: Action) -> Self {
+        self.default_action = Some(action);
+        self
+    }
+
+    /// 依 LINE 的長度限制截斷標題與內文（超過時以 … 結尾）
+    fn fit(&mut self) {
+        if let Some(title) = &mut self.title {
+            *title = truncate(title, Self::TITLE_LIMIT);
+        }
+        let limit = match self.title.is_some() || self.thumbnail_image_url.is_some() {
+            true => Self::SHORT_TEXT_LIMIT,
+            false => Self::TEXT_LIMIT,
+        };
+        self.text = non_empty(truncate(&self.text, limit));
+    }
+}
+
+/// LINE 不接受空字串，空白文字以一個空格代替
+fn non_empty(text: String) -> String {
+    if text.is_empty() { " ".to_string() } else { text }
+}
+
+/// 截斷為最多 `limit` 個字元
+fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(limit - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+
+/// 按鈕動作
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Action {
+    Postback {
+        label: String,
+        data: String,
+        #[serde(rename = "displayText", skip_serializing_if = "Option::is_none")]
+        display_text: Option<String>,
+    },
+    /// 開啟網址（可為 LIFF 網址）
+    Uri { label: String, uri: String },
+    /// 點擊後以 `text` 代替使用者發言
+    Message { label: String, text: String },
+}
+
+impl Action {
+    pub fn postback(label: impl Into<String>, data: impl Into<String>) -> Self {
+        Action::Postback {
+            label: label.into(),
+            data: data.into(),
+            display_text: None,
+        }
+    }
+
+    /// 點擊後以 `display_text` 代替使用者發言
+    pub fn postback_with_text(label: impl Into<String>, data: impl Into<String>, display_text: impl Into<String>) -> Self {
+        Action::Postback {
+            label: label.into(),
+            data: data.into(),
+            display_text: Some(display_text.into()),
+        }
+    }
+
+    pub fn uri(label: impl Into<String>, uri: impl Into<String>) -> Self {
+        Action::Uri {
+            label: label.into(),
+            uri: uri.into(),
+        }
+    }
+
+    pub fn message(label: impl Into<String>, text: impl Into<String>) -> Self {
+        Action::Message {
+            label: label.into(),
+            text: text.into(),
+        }
+    }
+}
+
+/// LINE 使用者個人資料
+#[derive(Debug, Deserialize)]
+pub struct Profile {
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    /// 使用者的 LINE 語言設定（BCP 47，如 `zh-TW`），未同意提供時為空
+    pub language: Option<String>,
+    /// 大頭貼網址，未設定大頭貼時為空
+    #[serde(rename = "pictureUrl")]
+    pub picture_url: Option<String>,
+    /// 狀態消息
+    #[serde(rename = "statusMessage")]
+    pub status_message: Option<String>,
+}
+
+/// 群組資訊
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroupSummary {
+    #[serde(rename = "groupId")]
+    pub group_id: String,
+    #[serde(rename = "groupName")]
+    pub group_name: String,
+    /// 群組圖示網址，未設定時為空
+    #[serde(rename = "pictureUrl")]
+    pub picture_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MemberCount {
+    count: u64,
+}
+
+impl LineClient {
+    /// 建立新的 LINE 客戶端
+    pub fn new(channel_access_token: String, channel_secret: String, api_base_url: String) -> Self {
+        let data_api_base_url = match api_base_url.as_str() {
+            OFFICIAL_API_BASE_URL => OFFICIAL_DATA_API_BASE_URL.to_string(),
+            other => other.to_string(),
+        };
+        Self {
+            client: Client::new(),
+            channel_access_token: RwLock::new(channel_access_token),
+            channel_secret,
+            previous_channel_secret: None,
+            api_base_url,
+            data_api_base_url,
+            dry_run: AtomicBool::new(false),
+            dry_run_recorder: RwLock::new(None),
+            quota_listener: RwLock::new(None),
+        }
+    }
+
+    /// 輪替 channel secret 期間，簽名驗證一併接受舊的 secret
+    pub fn with_previous_secret(mut self, secret: Option<String>) -> Self {
+        self.previous_channel_secret = secret;
+        self
+    }
+
+    /// 開啟或關閉全域試運行
+    pub fn set_dry_run(&self, enabled: bool) {
+        self.dry_run.store(enabled, Ordering::Relaxed);
+    }
+
+    /// 目前送出的訊息是否會被攔下（全域試運行或在 [`dry_run`] 範圍內）
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run.load(Ordering::Relaxed) || in_dry_run()
+    }
+
+    /// 設定試運行時接收被攔下訊息的記錄器
+    pub fn set_dry_run_recorder(&self, recorder: impl Fn(DryRunMessage) + Send + Sync + 'static) {
+        *self.dry_run_recorder.write().unwrap() = Some(Box::new(recorder));
+    }
+
+    /// 試運行時將請求交給記錄器並回傳 true，呼叫端應直接視為送出成功
+    fn intercept(&self, endpoint: &'static str, target: Option<String>, request: &impl Serialize) -> bool {
+        if !self.is_dry_run() {
+            return false;
+        }
+        if let Some(recorder) = self.dry_run_recorder.read().unwrap().as_ref() {
+            let body = serde_json::to_value(request).unwrap_or_default();
+            recorder(DryRunMessage { endpoint, target, body });
+        }
+        true
+    }
+
+    /// 設定本月訊息額度用完時的通知（push、multicast、broadcast、narrowcast 回傳 429 且訊息為 monthly limit）
+    pub fn set_quota_listener(&self, listener: impl Fn() + Send + Sync + 'static) {
+        *self.quota_listener.write().unwrap() = Some(Box::new(listener));
+    }
+
+    /// 檢查推送類請求的回應；額度用完與一般的 429 限流同樣回傳錯誤，但會另外通知額度監聽器。
+    /// 以相同 `X-Line-Retry-Key` 重試而 LINE 回覆 409 並附上已受理的 request ID 時，代表先前的請求已送出，視為成功
+    async fn check_push(&self, response: reqwest::Response) -> Result<(), reqwest::Error> {
+        let Err(error) = response.error_for_status_ref() else { return Ok(()) };
+        if response.status() == reqwest::StatusCode::CONFLICT && response.headers().contains_key(ACCEPTED_REQUEST_ID_HEADER) {
+            return Ok(());
+        }
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let body = response.text().await.unwrap_or_default();
+            if body.contains("monthly limit") {
+                if let Some(listener) = self.quota_listener.read().unwrap().as_ref() {
+                    listener();
+                }
+            }
+        }
+        Err(error)
+    }
+
+    /// 更換 access token（之後的請求立即使用新 token）
+    pub fn set_access_token(&self, token: String) {
+        *self.channel_access_token.write().unwrap() = token;
+    }
+
+    fn bearer(&self) -> String {
+        format!("Bearer {}", self.channel_access_token.read().unwrap())
+    }
+
+    /// 驗證 LINE Webhook 簽名，回傳相符的 secret；都不相符時回傳 None
+    pub fn verify_signature(&self, body: &[u8], signature: &str) -> Option<SignatureKey> {
+        if signature_matches(&self.channel_secret, body, signature) {
+            return Some(SignatureKey::Current);
+        }
+        let previous = self.previous_channel_secret.as_deref()?;
+        signature_matches(previous, body, signature).then_some(SignatureKey::Previous)
+    }
+
+    /// 解析 Webhook 事件
+    pub fn parse_events(&self, body: &str) -> Result<WebhookEvent, serde_json::Error> {
+        serde_json::from_str(body)
+    }
+
+    /// 使用 reply token 回覆訊息：文字、貼圖、圖片、Flex 等可混合，最多 [`MAX_MESSAGES`] 則
+    pub async fn reply_messages(&self, reply_token: &str, messages: &[OutgoingMessage]) -> Result<(), ReplyError> {
+        let request = ReplyMessageRequest { reply_token, messages };
+        if self.intercept("reply", Some(reply_token.to_string()), &request) {
+            return Ok(());
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/v2/bot/message/reply", self.api_base_url))
+            .header("Authorization", self.bearer())
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .map_err(|error| ReplyError { error, invalid_token: false })?;
+        let Err(error) = response.error_for_status_ref() else { return Ok(()) };
+        // 只有錯誤訊息為 Invalid reply token 時才是 token 的問題；內容錯誤同樣回傳 400
+        let invalid_token = response.status() == reqwest::StatusCode::BAD_REQUEST
+            && response.text().await.unwrap_or_default().contains("Invalid reply token");
+        Err(ReplyError { error, invalid_token })
+    }
+
+    /// 在一對一聊天顯示載入（輸入中）動畫，送出訊息或 `seconds` 秒後自動消失；群組與聊天室不支援。
+    /// 試運行時不呼叫 LINE
+    pub async fn start_loading(&self, user_id: &str, seconds: u32) -> Result<(), reqwest::Error> {
+        if self.is_dry_run() {
+            return Ok(());
+        }
+
+        self.client
+            .post(format!("{}/v2/bot/chat/loading/start", self.api_base_url))
+            .header("Authorization", self.bearer())
+            .header("Content-Type", "application/json")
+            .json(&LoadingRequest { chat_id: user_id, loading_seconds: seconds })
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// 取得使用者個人資料
+    pub async fn get_profile(&self, user_id: &str) -> Result<Profile, reqwest::Error> {
+        self.client
+            .get(format!("{}/v2/bot/profile/{}", self.api_base_url, user_id))
+            .header("Authorization", self.bearer())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
+    /// 取得群組成員的個人資料（只有顯示名稱與大頭貼）；成員未加 Bot 為好友時也能取得
+    pub async fn get_group_member_profile(&self, group_id: &str, user_id: &str) -> Result<Profile, reqwest::Error> {
+        self.member_profile("group", group_id, user_id).await
+    }
+
+    /// 取得聊天室成員的個人資料（只有顯示名稱與大頭貼）
+    pub async fn get_room_member_profile(&self, room_id: &str, user_id: &str) -> Result<Profile, reqwest::Error> {
+        self.member_profile("room", room_id, user_id).await
+    }
+
+    async fn member_profile(&self, kind: &str, chat_id: &str, user_id: &str) -> Result<Profile, reqwest::Error> {
+        self.client
+            .get(format!("{}/v2/bot/{}/{}/member/{}", self.api_base_url, kind, chat_id, user_id))
+            .header("Authorization", self.bearer())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
+    /// 取得 Bot 所在群組的名稱與圖示
+    pub async fn get_group_summary(&self, group_id: &str) -> Result<GroupSummary, reqwest::Error> {
+        self.client
+            .get(format!("{}/v2/bot/group/{}/summary", self.api_base_url, group_id))
+            .header("Authorization", self.bearer())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
+    /// 取得群組成員人數（包含未加 Bot 為好友的成員）
+    pub async fn get_group_member_count(&self, group_id: &str) -> Result<u64, reqwest::Error> {
+        let count: MemberCount = self
+            .client
+            .get(format!("{}/v2/bot/group/{}/members/count", self.api_base_url, group_id))
+            .header("Authorization", self.bearer())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(count.count)
+    }
+
+    /// 下載使用者傳來的圖片等內容；回傳的 Response 可用 `chunk()` 逐段讀取，`Content-Type` 標頭為內容格式
+    pub async fn get_message_content(&self, message_id: &str) -> Result<reqwest::Response, reqwest::Error> {
+        self.client
+            .get(format!("{}/v2/bot/message/{}/content", self.data_api_base_url, message_id))
+            .header("Authorization", self.bearer())
+            .send()
+            .await?
+            .error_for_status()
+    }
+
+    /// 查詢影片或音訊內容的轉檔狀態
+    pub async fn get_content_transcoding(&self, message_id: &str) -> Result<TranscodingStatus, reqwest::Error> {
+        let response: TranscodingResponse = self
+            .client
+            .get(format!("{}/v2/bot/message/{}/content/transcoding", self.data_api_base_url, message_id))
+            .header("Authorization", self.bearer())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.status)
+    }
+
+    /// 主動推送訊息給用戶
+    pub async fn push_message(&self, user_id: &str, text: &str) -> Result<(), reqwest::Error> {
+        self.push_messages(user_id, &[TextMessage::new(text).into()]).await
+    }
+
+    /// 主動推送多則訊息（最多 [`MAX_MESSAGES`] 則）
+    pub async fn push_messages(&self, to: &str, messages: &[OutgoingMessage]) -> Result<(), reqwest::Error> {
+        self.push_with_retry_key(to, messages, None).await
+    }
+
+    /// 附上 `X-Line-Retry-Key`（UUID）推送；重試時沿用同一個 key，LINE 已受理過的請求不會重複送達
+    pub async fn push_with_retry_key(
+        &self,
+        to: &str,
+        messages: &[OutgoingMessage],
+        retry_key: Option<&str>,
+    ) -> Result<(), reqwest::Error> {
+        let request = PushMessageRequest { to, messages };
+        if self.intercept("push", Some(to.to_string()), &request) {
+            return Ok(());
+        }
+
+        let mut builder = self
+            .client
+            .post(format!("{}/v2/bot/message/push", self.api_base_url))
+            .header("Authorization", self.bearer())
+            .header("Content-Type", "application/json");
+        if let Some(key) = retry_key {
+            builder = builder.header(RETRY_KEY_HEADER, key);
+        }
+        let response = builder.json(&request).send().await?;
+        self.check_push(response).await
+    }
+
+    /// 一次推送給多位使用者；超過 [`MULTICAST_LIMIT`] 人時自動分批送出，
+    /// 任一批失敗即停止並回傳錯誤（之前的批次已送出）。需要在失敗後續送的呼叫端應自行逐批呼叫並記錄進度，
+    /// 或改用 [`Self::multicast_with_retry_key`]
+    pub async fn multicast(&self, to: &[String], messages: &[OutgoingMessage]) -> Result<(), reqwest::Error> {
+        self.multicast_with_retry_key(to, messages, None).await
+    }
+
+    /// 附上 `X-Line-Retry-Key` 的 multicast；分批時第二批起以 key 與批次序號推得各自的 key，
+    /// 以同樣的收件人與 key 重試時，已送出的批次會被 LINE 視為重複而略過
+    pub async fn multicast_with_retry_key(
+        &self,
+        to: &[String],
+        messages: &[OutgoingMessage],
+        retry_key: Option<&str>,
+    ) -> Result<(), reqwest::Error> {
+        for (index, batch) in to.chunks(MULTICAST_LIMIT).enumerate() {
+            let request = MulticastRequest { to: batch, messages };
+            if self.intercept("multicast", Some(batch.join(",")), &request) {
+                continue;
+            }
+
+            let mut builder = self
+                .client
+                .post(format!("{}/v2/bot/message/multicast", self.api_base_url))
+                .header("Authorization", self.bearer())
+                .header("Content-Type", "application/json");
+            if let Some(key) = retry_key {
+                builder = builder.header(RETRY_KEY_HEADER, batch_retry_key(key, index));
+            }
+            let response = builder.json(&request).send().await?;
+            self.check_push(response).await?;
+        }
+        Ok(())
+    }
+
+    /// 廣播訊息給所有好友
+    pub async fn broadcast(&self, messages: Vec<OutgoingMessage>) -> Result<(), reqwest::Error> {
+        let request = BroadcastRequest { messages };
+        if self.intercept("broadcast", None, &request) {
+            return Ok(());
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/v2/bot/message/broadcast", self.api_base_url))
+            .header("Authorization", self.bearer())
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await?;
+        self.check_push(response).await
+    }
+
+    /// 依受眾或屬性篩選群發；LINE 在背景計算對象後送出，回傳供 [`Self::narrowcast_progress`] 查詢的 request ID。
+    /// LINE 已受理但回應缺少 `X-Line-Request-Id` 時回傳 None；試運行時回傳 `dry-run`
+    pub async fn narrowcast(&self, request: &NarrowcastRequest) -> Result<Option<String>, reqwest::Error> {
+        if self.intercept("narrowcast", None, request) {
+            return Ok(Some("dry-run".to_string()));
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/v2/bot/message/narrowcast", self.api_base_url))
+            .header("Authorization", self.bearer())
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await?;
+        let request_id = response
+            .headers()
+            .get("x-line-request-id")
+            .and_then(|v| v.to_str().ok())
+            .filter(|id| !id.is_empty())
+            .map(str::to_string);
+        self.check_push(response).await?;
+        Ok(request_id)
+    }
+
+    /// 查詢群發進度
+    pub async fn narrowcast_progress(&self, request_id: &str) -> Result<NarrowcastProgress, reqwest::Error> {
+        self.client
+            .get(format!("{}/v2/bot/message/progress/narrowcast", self.api_base_url))
+            .query(&[("requestId", request_id)])
+            .header("Authorization", self.bearer())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "123e4567-e89b-42d3-a456-426614174000";
+    const BODY: &[u8] = br#"{"destination":"U0","events":[]}"#;
+
+    fn client(previous: Option<&str>) -> LineClient {
+        LineClient::new("token".to_string(), "current-secret".to_string(), OFFICIAL_API_BASE_URL.to_string())
+            .with_previous_secret(previous.map(str::to_string))
+    }
+
+    #[test]
+    fn verify_signature_matches_current_secret() {
+        let signature = sign("current-secret", BODY).unwrap();
+        assert_eq!(client(Some("old-secret")).verify_signature(BODY, &signature), Some(SignatureKey::Current));
+    }
+
+    #[test]
+    fn verify_signature_matches_previous_secret() {
+        let signature = sign("old-secret", BODY).unwrap();
+        assert_eq!(client(Some("old-secret")).verify_signature(BODY, &signature), Some(SignatureKey::Previous));
+        assert_eq!(client(None).verify_signature(BODY, &signature), None);
+    }
+
+    #[test]
+    fn verify_signature_rejects_tampered_body_and_signature() {
+        let signature = sign("current-secret", BODY).unwrap();
+        assert_eq!(client(None).verify_signature(b"{}", &signature), None);
+        assert_eq!(client(None).verify_signature(BODY, &signature[1..]), None);
+        assert_eq!(client(None).verify_signature(BODY, ""), None);
+    }
+
+    #[test]
+    fn batch_retry_key_keeps_first_key() {
+        assert_eq!(batch_retry_key(KEY, 0), KEY);
+    }
+
+    #[test]
+    fn batch_retry_key_is_stable_and_distinct() {
+        let second = batch_retry_key(KEY, 1);
+        let third = batch_retry_key(KEY, 2);
+        assert_eq!(second, batch_retry_key(KEY, 1));
+        assert_ne!(second, KEY);
+        assert_ne!(second, third);
+        for key in [&second, &third] {
+            let groups: Vec<&str> = key.split('-').collect();
+            assert_eq!(groups.iter().map(|g| g.len()).collect::<Vec<_>>(), [8, 4, 4, 4, 12]);
+            assert!(groups[2].starts_with('4'));
+            assert!(groups[3].starts_with('a'));
+        }
+    }
+
+    fn emoji(index: usize, emoji_id: &str) -> Emoji {
+        Emoji { index, product_id: "p1".to_string(), emoji_id: emoji_id.to_string() }
+    }
+
+    #[test]
+    fn text_v2_escapes_braces_and_maps_emoji_by_utf16_index() {
+        let mut message = TextMessage::new("😀$ {x} $");
+        // 😀 佔兩個 UTF-16 單位，第一個 `$` 位於 2
+        message.emojis = vec![emoji(2, "001"), emoji(8, "002")];
+        message.quote_token = Some("quote".to_string());
+        let v2 = TextV2Message::from(message);
+        assert_eq!(v2.text, "😀{emoji0} {{x}} {emoji1}");
+        assert_eq!(v2.quote_token.as_deref(), Some("quote"));
+        let json = serde_json::to_value(&v2).unwrap();
+        assert_eq!(json["type"], "textV2");
+        assert_eq!(json["substitution"]["emoji0"], serde_json::json!({ "type": "emoji", "productId": "p1", "emojiId": "001" }));
+        assert_eq!(json["substitution"]["emoji1"]["emojiId"], "002");
+    }
+
+    #[test]
+    fn text_v2_keeps_dollars_without_emoji() {
+        let v2 = TextV2Message::from(TextMessage::new("$5 {}"));
+        assert_eq!(v2.text, "$5 {{}}");
+        assert!(v2.substitution.is_empty());
+    }
+
+    #[test]
+    fn text_v2_mentions_at_the_start() {
+        let v2 = TextV2Message::new("回答 {a}").mention_first("asker", "U1");
+        assert_eq!(v2.text, "{asker} 回答 {{a}}");
+        let json = serde_json::to_value(&v2).unwrap();
+        assert_eq!(json["substitution"]["asker"], serde_json::json!({ "type": "mention", "mentionee": { "type": "user", "userId": "U1" } }));
+    }
+
+    #[test]
+    fn carousel_rejects_columns_with_different_action_counts() {
+        let column = |actions: usize| {
+            (0..actions).fold(CarouselColumn::new("內文"), |column, i| column.action(Action::postback(format!("按鈕{}", i), "data")))
+        };
+        let Ok(Template::Carousel { columns }) = Template::carousel(vec![column(2), column(2)]) else { panic!("expected a carousel") };
+        assert!(columns.iter().all(|c| c.actions.len() == 2));
+        let error = Template::carousel(vec![column(2), column(2), column(1)]).unwrap_err();
+        assert!(error.contains("第 3 欄"));
+        // 超過上限而被捨棄的欄位不影響檢查
+        let mut columns: Vec<_> = (0..Template::CAROUSEL_LIMIT).map(|_| column(1)).collect();
+        columns.push(column(3));
+        assert!(Template::carousel(columns).is_ok());
+    }
+}
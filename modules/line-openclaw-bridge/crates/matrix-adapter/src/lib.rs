@@ -0,0 +1,242 @@
+//! Matrix 客戶端模組
+//! 以 matrix-sdk 連線 homeserver：同步房間事件、加入房間、輸入中提示與發送文字訊息；端對端加密（E2EE）由 SDK 的
+//! Olm/Megolm 實作處理，裝置金鑰與房間金鑰存於 SQLite 加密儲存，重啟後沿用同一個裝置即可繼續解密
+
+use matrix_sdk::config::SyncSettings;
+use matrix_sdk::matrix_auth::{MatrixSession, MatrixSessionTokens};
+use matrix_sdk::ruma::events::room::encrypted::OriginalSyncRoomEncryptedEvent;
+use matrix_sdk::ruma::events::room::member::StrippedRoomMemberEvent;
+use matrix_sdk::ruma::events::room::message::{MessageType, OriginalSyncRoomMessageEvent, Relation, RoomMessageEventContent};
+use matrix_sdk::ruma::{OwnedUserId, RoomId, RoomOrAliasId};
+use matrix_sdk::{Client, LoopCtrl, Room, RoomState, SessionMeta};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// 單則文字訊息的字數上限（事件上限為 64 KiB，中文以 UTF-8 計約 21000 字，保留 metadata 與加密的空間）
+pub const TEXT_LIMIT: usize = 16_000;
+/// 同步失敗後重試的間隔
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Matrix 操作失敗的原因
+#[derive(Debug)]
+pub enum Error {
+    /// SDK 或 homeserver 回傳的錯誤
+    Sdk(Box<matrix_sdk::Error>),
+    /// 建立客戶端（開啟儲存、連線 homeserver）失敗
+    Build(Box<matrix_sdk::ClientBuildError>),
+    /// 查詢權杖所屬身分失敗
+    Http(reqwest::Error),
+    /// 存取權杖沒有對應的裝置（E2EE 需要固定的裝置）
+    NoDevice,
+    /// 房間 ID、別名或使用者 ID 格式錯誤
+    InvalidId(String),
+    /// 機器人不在此房間
+    UnknownRoom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Sdk(e) => write!(f, "{}", e),
+            Error::Build(e) => write!(f, "{}", e),
+            Error::Http(e) => write!(f, "{}", e),
+            Error::NoDevice => write!(f, "access token is not bound to a device; log in with /login to get one"),
+            Error::InvalidId(id) => write!(f, "invalid Matrix ID: {}", id),
+            Error::UnknownRoom(room_id) => write!(f, "not a member of room {}", room_id),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<matrix_sdk::Error> for Error {
+    fn from(e: matrix_sdk::Error) -> Self {
+        Error::Sdk(Box::new(e))
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+/// 房間中收到的一則文字訊息（加密房間的訊息已解密）
+#[derive(Debug, Clone)]
+pub struct Incoming {
+    pub room_id: String,
+    pub event_id: String,
+    /// 傳送者的 Matrix ID（如 `@alice:example.org`）
+    pub sender: String,
+    pub body: String,
+    /// `m.mentions` 列出機器人，或內文包含機器人的 ID（較舊的客戶端）
+    pub mentioned: bool,
+    /// 房間標記為一對一對話（`m.direct`）
+    pub direct: bool,
+    pub encrypted: bool,
+}
+
+/// 連線設定
+pub struct ConnectOptions<'a> {
+    /// homeserver 位址，不含 `/_matrix`
+    pub homeserver_url: &'a str,
+    /// 機器人專用裝置的存取權杖（以 `/login` 取得）
+    pub access_token: &'a str,
+    /// 同步狀態與加密金鑰的 SQLite 儲存目錄
+    pub store_path: &'a Path,
+    /// 加密儲存內容的密碼；未設定時以明文儲存
+    pub store_passphrase: Option<&'a str>,
+}
+
+/// Matrix 客戶端
+#[derive(Clone)]
+pub struct MatrixClient {
+    client: Client,
+    user_id: OwnedUserId,
+}
+
+impl MatrixClient {
+    /// 開啟儲存並以存取權杖恢復工作階段；權杖所屬的使用者與裝置由 `whoami` 取得
+    pub async fn connect(options: &ConnectOptions<'_>) -> Result<Self, Error> {
+        let client = Client::builder()
+            .homeserver_url(options.homeserver_url.trim_end_matches('/'))
+            .sqlite_store(options.store_path, options.store_passphrase)
+            .build()
+            .await
+            .map_err(|e| Error::Build(Box::new(e)))?;
+        let whoami = whoami(options.homeserver_url, options.access_token).await?;
+        let device_id = whoami.device_id.ok_or(Error::NoDevice)?;
+        let tokens = MatrixSessionTokens { access_token: options.access_token.to_string(), refresh_token: None };
+        let session = MatrixSession { meta: SessionMeta { user_id: whoami.user_id.clone(), device_id }, tokens };
+        client.matrix_auth().restore_session(session).await?;
+        Ok(Self { client, user_id: whoami.user_id })
+    }
+
+    /// 機器人的 Matrix ID
+    pub fn user_id(&self) -> &str {
+        self.user_id.as_str()
+    }
+
+    /// 加入房間（房間 ID 或 `#別名:伺服器`），回傳房間 ID；已在房間中時同樣成功
+    pub async fn join(&self, room: &str) -> Result<String, Error> {
+        let id = <&RoomOrAliasId>::try_from(room).map_err(|_| Error::InvalidId(room.to_string()))?;
+        let room = self.client.join_room_by_id_or_alias(id, &[]).await?;
+        Ok(room.room_id().to_string())
+    }
+
+    /// 持續同步並將新的文字訊息交給 `on_message`；啟動前累積的事件只用來更新狀態，不會交出。
+    /// 一對一對話（`is_direct`）的邀請自動接受。之後的同步失敗時等待 [`RETRY_DELAY`] 後重試，只有第一次同步失敗時回傳錯誤
+    pub async fn run<F>(&self, on_message: F) -> Result<(), Error>
+    where
+        F: Fn(Incoming) + Clone + Send + Sync + 'static,
+    {
+        let response = self.client.sync_once(SyncSettings::default()).await?;
+
+        let own_id = self.user_id.clone();
+        self.client.add_event_handler(move |event: StrippedRoomMemberEvent, room: Room| {
+            let own_id = own_id.clone();
+            async move {
+                if event.state_key != own_id || event.content.is_direct != Some(true) || room.state() != RoomState::Invited {
+                    return;
+                }
+                match room.join().await {
+                    Ok(()) => info!("Accepted Matrix direct chat {}", room.room_id()),
+                    Err(e) => warn!("Failed to accept Matrix invite to {}: {}", room.room_id(), e),
+                }
+            }
+        });
+
+        let own_id = self.user_id.clone();
+        self.client.add_event_handler(move |event: OriginalSyncRoomMessageEvent, room: Room| {
+            let (own_id, on_message) = (own_id.clone(), on_message.clone());
+            async move {
+                if event.sender == own_id || room.state() != RoomState::Joined {
+                    return;
+                }
+                if let Some(message) = incoming(&room, &own_id, event).await {
+                    on_message(message);
+                }
+            }
+        });
+
+        // 無法解密（尚未收到房間金鑰）的事件只記錄一次警告
+        let undecryptable: Arc<Mutex<HashSet<String>>> = Arc::default();
+        self.client.add_event_handler(move |event: OriginalSyncRoomEncryptedEvent, room: Room| {
+            let undecryptable = undecryptable.clone();
+            async move {
+                debug!("Undecryptable Matrix event {} in {}", event.event_id, room.room_id());
+                if undecryptable.lock().unwrap().insert(room.room_id().to_string()) {
+                    warn!("Could not decrypt a message in Matrix room {}; the sender has not shared the room key with this device", room.room_id());
+                }
+            }
+        });
+
+        let settings = SyncSettings::default().token(response.next_batch);
+        self.client
+            .sync_with_result_callback(settings, |result| async move {
+                if let Err(e) = result {
+                    warn!("Matrix sync failed: {}", e);
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+                Ok(LoopCtrl::Continue)
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// 顯示或取消「輸入中」提示
+    pub async fn set_typing(&self, room_id: &str, typing: bool) -> Result<(), Error> {
+        self.room(room_id)?.typing_notice(typing).await?;
+        Ok(())
+    }
+
+    /// 發送文字訊息（最多 [`TEXT_LIMIT`] 字），回傳事件 ID；加密房間自動加密。以 `m.notice` 送出，
+    /// 依慣例其他機器人不會回應，避免互相觸發
+    pub async fn send_text(&self, room_id: &str, text: &str) -> Result<String, Error> {
+        let response = self.room(room_id)?.send(RoomMessageEventContent::notice_plain(text)).await?;
+        Ok(response.event_id.to_string())
+    }
+
+    fn room(&self, room_id: &str) -> Result<Room, Error> {
+        let id = <&RoomId>::try_from(room_id).map_err(|_| Error::InvalidId(room_id.to_string()))?;
+        self.client.get_room(id).ok_or_else(|| Error::UnknownRoom(room_id.to_string()))
+    }
+}
+
+/// 查詢權杖所屬的使用者與裝置（恢復工作階段需要裝置 ID）
+async fn whoami(homeserver_url: &str, access_token: &str) -> Result<WhoAmI, Error> {
+    let url = format!("{}/_matrix/client/v3/account/whoami", homeserver_url.trim_end_matches('/'));
+    let response = reqwest::Client::new().get(url).bearer_auth(access_token).send().await?.error_for_status()?;
+    Ok(response.json().await?)
+}
+
+#[derive(serde::Deserialize)]
+struct WhoAmI {
+    user_id: OwnedUserId,
+    device_id: Option<matrix_sdk::ruma::OwnedDeviceId>,
+}
+
+/// 一般文字訊息轉為 [`Incoming`]；通知（`m.notice`，其他機器人的回覆）與編輯舊訊息的事件回傳 None
+async fn incoming(room: &Room, own_id: &OwnedUserId, event: OriginalSyncRoomMessageEvent) -> Option<Incoming> {
+    if matches!(event.content.relates_to, Some(Relation::Replacement(_))) {
+        return None;
+    }
+    let MessageType::Text(text) = event.content.msgtype else { return None };
+    let listed = event.content.mentions.as_ref().is_some_and(|mentions| mentions.user_ids.contains(own_id));
+    let mentioned = listed || text.body.contains(own_id.as_str());
+    let encrypted = room.is_encrypted().await.unwrap_or(false);
+    let direct = room.is_direct().await.unwrap_or(false);
+    Some(Incoming {
+        room_id: room.room_id().to_string(),
+        event_id: event.event_id.to_string(),
+        sender: event.sender.to_string(),
+        body: text.body,
+        mentioned,
+        direct,
+        encrypted,
+    })
+}
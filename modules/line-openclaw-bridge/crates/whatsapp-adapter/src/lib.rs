@@ -0,0 +1,279 @@
+//! WhatsApp Business Cloud API 客戶端模組
+//! 處理 webhook 驗證（訂閱時的 hub.challenge 與 X-Hub-Signature-256 簽章）、事件解析，以及文字與範本訊息的發送
+
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 官方 Graph API 位址（含版本）
+pub const DEFAULT_API_BASE_URL: &str = "https://graph.facebook.com/v19.0";
+/// 單則文字訊息的字數上限
+pub const TEXT_LIMIT: usize = 4096;
+/// webhook 簽章標頭
+pub const SIGNATURE_HEADER: &str = "x-hub-signature-256";
+
+/// WhatsApp Cloud API 客戶端
+pub struct WhatsAppClient {
+    access_token: String,
+    app_secret: String,
+    phone_number_id: String,
+    api_base_url: String,
+    client: Client,
+}
+
+/// 範本訊息；`components` 為 Cloud API 的元件陣列（header／body 參數、按鈕）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Template {
+    pub name: String,
+    /// 範本語言代碼，如 `zh_TW`、`en_US`
+    pub language: String,
+    #[serde(default)]
+    pub components: Vec<serde_json::Value>,
+}
+
+/// Webhook 內容
+#[derive(Debug, Deserialize)]
+pub struct WebhookPayload {
+    #[serde(default)]
+    pub entry: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Entry {
+    #[serde(default)]
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Change {
+    pub field: String,
+    pub value: ChangeValue,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeValue {
+    #[serde(default)]
+    pub contacts: Vec<Contact>,
+    #[serde(default)]
+    pub messages: Vec<InboundMessage>,
+}
+
+/// 傳訊者的聯絡資訊
+#[derive(Debug, Deserialize)]
+pub struct Contact {
+    pub wa_id: String,
+    pub profile: Option<ContactProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContactProfile {
+    pub name: String,
+}
+
+/// 使用者傳來的訊息
+#[derive(Debug, Deserialize)]
+pub struct InboundMessage {
+    /// 傳訊者的 WhatsApp ID（國碼開頭的電話號碼，不含 +）
+    pub from: String,
+    pub id: String,
+    pub timestamp: String,
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub text: Option<InboundText>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InboundText {
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+struct SendResponse {
+    #[serde(default)]
+    messages: Vec<SentMessage>,
+}
+
+#[derive(Deserialize)]
+struct SentMessage {
+    id: String,
+}
+
+impl WebhookPayload {
+    /// 所有訊息與傳訊者的顯示名稱（`messages` 欄位以外的變更，如送達狀態，會略過）
+    pub fn messages(&self) -> Vec<(&InboundMessage, Option<&str>)> {
+        self.entry
+            .iter()
+            .flat_map(|entry| &entry.changes)
+            .filter(|change| change.field == "messages")
+            .flat_map(|change| {
+                change.value.messages.iter().map(|message| {
+                    let name = change
+                        .value
+                        .contacts
+                        .iter()
+                        .find(|contact| contact.wa_id == message.from)
+                        .and_then(|contact| contact.profile.as_ref())
+                        .map(|profile| profile.name.as_str());
+                    (message, name)
+                })
+            })
+            .collect()
+    }
+}
+
+/// 訂閱驗證：`hub.mode` 為 `subscribe` 且 `hub.verify_token` 相符時回傳要原樣回應的 `hub.challenge`
+pub fn verify_subscription(mode: Option<&str>, token: Option<&str>, challenge: Option<&str>, verify_token: &str) -> Option<String> {
+    (mode == Some("subscribe") && token == Some(verify_token)).then(|| challenge.map(str::to_string)).flatten()
+}
+
+impl WhatsAppClient {
+    /// 建立新的客戶端；`api_base_url` 含 Graph API 版本
+    pub fn new(access_token: String, app_secret: String, phone_number_id: String, api_base_url: String) -> Self {
+        Self {
+            access_token,
+            app_secret,
+            phone_number_id,
+            api_base_url: api_base_url.trim_end_matches('/').to_string(),
+            client: Client::new(),
+        }
+    }
+
+    /// 驗證 `X-Hub-Signature-256`（`sha256=<hex>`，以 App Secret 對原始內容計算的 HMAC-SHA256）
+    pub fn verify_signature(&self, body: &[u8], signature: &str) -> bool {
+        let Some(expected) = signature.strip_prefix("sha256=").and_then(|hex| hex::decode(hex).ok()) else {
+            return false;
+        };
+        let mut mac = HmacSha256::new_from_slice(self.app_secret.as_bytes()).expect("HMAC 可接受任意長度金鑰");
+        mac.update(body);
+        mac.verify_slice(&expected).is_ok()
+    }
+
+    /// 解析 Webhook 內容
+    pub fn parse_payload(&self, body: &[u8]) -> Result<WebhookPayload, serde_json::Error> {
+        serde_json::from_slice(body)
+    }
+
+    /// 發送文字訊息（最多 [`TEXT_LIMIT`] 字），回傳訊息 ID；只能在使用者最後一則訊息後 24 小時內發送
+    pub async fn send_text(&self, to: &str, text: &str) -> Result<String, reqwest::Error> {
+        self.send(json!({
+            "messaging_product": "whatsapp",
+            "recipient_type": "individual",
+            "to": to,
+            "type": "text",
+            "text": { "preview_url": false, "body": text },
+        }))
+        .await
+    }
+
+    /// 發送已核准的範本訊息，回傳訊息 ID；24 小時對話期間以外只能發送範本
+    pub async fn send_template(&self, to: &str, template: &Template) -> Result<String, reqwest::Error> {
+        self.send(json!({
+            "messaging_product": "whatsapp",
+            "recipient_type": "individual",
+            "to": to,
+            "type": "template",
+            "template": {
+                "name": template.name,
+                "language": { "code": template.language },
+                "components": template.components,
+            },
+        }))
+        .await
+    }
+
+    /// 將收到的訊息標示為已讀（使用者會看到藍色勾勾）
+    pub async fn mark_read(&self, message_id: &str) -> Result<(), reqwest::Error> {
+        self.client
+            .post(self.messages_url())
+            .bearer_auth(&self.access_token)
+            .json(&json!({ "messaging_product": "whatsapp", "status": "read", "message_id": message_id }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn send(&self, body: serde_json::Value) -> Result<String, reqwest::Error> {
+        let response: SendResponse = self
+            .client
+            .post(self.messages_url())
+            .bearer_auth(&self.access_token)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.messages.into_iter().next().map(|m| m.id).unwrap_or_default())
+    }
+
+    fn messages_url(&self) -> String {
+        format!("{}/{}/messages", self.api_base_url, self.phone_number_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "app-secret";
+    const BODY: &[u8] = br#"{"object":"whatsapp_business_account"}"#;
+    /// 以 `SECRET` 對 `BODY` 計算的 HMAC-SHA256
+    const SIGNATURE: &str = "sha256=0bf7374433906636bed1653bc7c64affb42fa5da9a39e251ab95c2e3a9c06923";
+
+    fn client() -> WhatsAppClient {
+        WhatsAppClient::new("token".to_string(), SECRET.to_string(), "123".to_string(), DEFAULT_API_BASE_URL.to_string())
+    }
+
+    #[test]
+    fn signature_matches_known_vector() {
+        assert!(client().verify_signature(BODY, SIGNATURE));
+    }
+
+    #[test]
+    fn signature_rejects_tampering_and_bad_format() {
+        let client = client();
+        assert!(!client.verify_signature(br#"{"object":"page"}"#, SIGNATURE));
+        assert!(!client.verify_signature(BODY, SIGNATURE.trim_start_matches("sha256=")));
+        assert!(!client.verify_signature(BODY, "sha256=not-hex"));
+        assert!(!client.verify_signature(BODY, ""));
+    }
+
+    #[test]
+    fn subscription_echoes_challenge_only_for_matching_token() {
+        assert_eq!(verify_subscription(Some("subscribe"), Some("verify"), Some("1158201444"), "verify").as_deref(), Some("1158201444"));
+        assert_eq!(verify_subscription(Some("subscribe"), Some("wrong"), Some("1158201444"), "verify"), None);
+        assert_eq!(verify_subscription(Some("unsubscribe"), Some("verify"), Some("1158201444"), "verify"), None);
+        assert_eq!(verify_subscription(Some("subscribe"), Some("verify"), None, "verify"), None);
+    }
+
+    #[test]
+    fn messages_pair_sender_names_and_skip_statuses() {
+        let payload = client()
+            .parse_payload(
+                br#"{"object":"whatsapp_business_account","entry":[{"id":"1","changes":[
+                    {"field":"messages","value":{
+                        "contacts":[{"wa_id":"886912345678","profile":{"name":"Alice"}}],
+                        "messages":[
+                            {"from":"886912345678","id":"wamid.1","timestamp":"1700000000","type":"text","text":{"body":"hi"}},
+                            {"from":"886900000000","id":"wamid.2","timestamp":"1700000001","type":"image"}
+                        ],
+                        "statuses":[{"id":"wamid.0","status":"delivered"}]
+                    }},
+                    {"field":"account_update","value":{}}
+                ]}]}"#,
+            )
+            .unwrap();
+        let messages = payload.messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].0.text.as_ref().unwrap().body, "hi");
+        assert_eq!(messages[0].1, Some("Alice"));
+        assert_eq!(messages[1].0.message_type, "image");
+        assert!(messages[1].0.text.is_none());
+        assert_eq!(messages[1].1, None);
+    }
+}
@@ -0,0 +1,1206 @@
+//! 管理 API 模組
+//! 提供需 `ADMIN_TOKEN` 驗證的管理端點
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use chrono::NaiveDate;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use tokio::io::AsyncWriteExt;
+use tower_http::compression::CompressionLayer;
+use tracing::{error, info, warn};
+
+use crate::announcements;
+use crate::archive;
+use crate::channels;
+use crate::config::ConfigReport;
+use crate::encryption::{self, Recipient};
+use crate::maintenance::{self, MaintenanceReport};
+use crate::media;
+use crate::models;
+use crate::notify;
+use crate::pipeline::{self, SamplingReport, SamplingUpdate, Trace};
+use crate::privacy;
+use crate::proxy::ClientIp;
+use crate::quota;
+use crate::reporting;
+use crate::resources::{self, Snapshot};
+use crate::snapshot;
+use crate::export::{self, Dataset, ExportFormat};
+use crate::i18n::Locale;
+use crate::jobs::{self, Job};
+use crate::liff;
+use crate::line;
+use crate::store::{Attachment, AuditEvent, SearchQuery, TimeRange, UserProfile};
+use crate::{AppState, SharedState};
+
+/// 背景匯出檔在物件儲存中的路徑前綴
+const EXPORT_PREFIX: &str = "exports/";
+/// 背景匯出完成後下載網址的有效期
+const EXPORT_URL_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// 背景工作查詢群發進度的間隔
+const NARROWCAST_POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// 群發進度連續查詢失敗幾次後放棄追蹤
+const NARROWCAST_POLL_ERRORS: u32 = 5;
+
+/// 建立管理路由（掛載於 `/admin`），需 `ADMIN_TOKEN` 驗證
+pub fn router(state: SharedState) -> Router<SharedState> {
+    routes().route_layer(middleware::from_fn_with_state(state, require_admin))
+}
+
+/// 管理端點本身（不含驗證，mTLS 監聽埠改以用戶端憑證授權）
+pub fn routes() -> Router<SharedState> {
+    Router::new()
+        .route("/config", get(show_config))
+        .route("/flags", get(show_flags))
+        .route("/flags/reload", post(reload_flags))
+        .route("/search", get(search))
+        .route("/export/:dataset", get(export_data))
+        .route("/templates", get(list_templates))
+        .route("/templates/:name", put(save_template))
+        .route("/announcements", get(list_announcements).post(create_announcement))
+        .route("/media/*key", put(upload_media))
+        .route("/notify", post(create_notification))
+        .route("/notify/:id", get(show_notification))
+        .route("/notify/:id/events", get(notification_events))
+        .route("/broadcast", post(send_broadcast))
+        .route("/narrowcast", post(send_narrowcast))
+        .route("/narrowcast/:request_id", get(show_narrowcast))
+        .route("/whatsapp/messages", post(send_whatsapp))
+        .route("/dead-letters", get(list_dead_letters))
+        .route("/dry-run", get(list_dry_run_messages))
+        .route("/stats", get(show_stats))
+        .route("/links/:user_id", get(show_links).delete(remove_link))
+        .route("/profiles/:user_id", get(show_profile))
+        .route("/archives", get(list_archives))
+        .route("/archives/run", post(run_archive))
+        .route("/archives/:name/restore", post(restore_archive))
+        .route("/maintenance/run", post(run_maintenance))
+        .route("/test-message", post(test_message))
+        .route("/quota", delete(reset_quota))
+        .route("/snapshot", get(export_snapshot))
+        .route("/jobs", get(list_jobs))
+        .route("/jobs/:id", get(show_job))
+        .route("/feeds", get(list_feeds))
+        .route("/models", get(show_models))
+        .route("/traces", get(list_traces))
+        .route("/traces/sampling", get(show_trace_sampling).put(update_trace_sampling))
+        .route("/traces/:id", get(show_trace))
+        .layer(CompressionLayer::new())
+}
+
+/// 驗證 `Authorization: Bearer <ADMIN_TOKEN>`
+async fn require_admin(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let provided = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    let authorized = {
+        let state = state.read().await;
+        match (&state.config.admin_token, provided) {
+            (Some(expected), Some(provided)) => expected.as_bytes().ct_eq(provided.as_bytes()).into(),
+            _ => false,
+        }
+    };
+
+    if !authorized {
+        let client = request.extensions().get::<ClientIp>().map(|ClientIp(ip)| privacy::ip(*ip).to_string());
+        warn!("Rejected admin request to {} from {}", request.uri().path(), client.as_deref().unwrap_or("-"));
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(next.run(request).await)
+}
+
+/// 目前的功能旗標
+async fn show_flags(State(state): State<SharedState>) -> Json<serde_json::Value> {
+    let state = state.read().await;
+    Json(json!({ "environment": state.flags.environment(), "flags": state.flags.snapshot() }))
+}
+
+/// 立即重新讀取功能旗標檔
+async fn reload_flags(State(state): State<SharedState>) -> Result<StatusCode, (StatusCode, String)> {
+    let state = state.read().await;
+    state
+        .flags
+        .reload()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 有效設定報告（機密值已遮蔽）
+async fn show_config(State(state): State<SharedState>) -> Json<ConfigReport> {
+    let state = state.read().await;
+    Json(state.config.report(&state.blobs))
+}
+
+/// 管理端搜尋參數
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    #[serde(default)]
+    q: String,
+    user: Option<String>,
+    /// 起始日期 `YYYY-MM-DD`（含）
+    from: Option<NaiveDate>,
+    /// 結束日期 `YYYY-MM-DD`（含）
+    to: Option<NaiveDate>,
+    model: Option<String>,
+    limit: Option<usize>,
+    /// 一併搜尋與 `user` 連結的其他平台身分
+    #[serde(default)]
+    linked: bool,
+}
+
+/// 搜尋所有使用者的對話紀錄
+async fn search(
+    State(state): State<SharedState>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let query = SearchQuery {
+        keywords: params.q.split_whitespace().map(str::to_string).collect(),
+        user_id: params.user,
+        include_linked: params.linked,
+        range: TimeRange::from_dates(params.from, params.to),
+        model: params.model,
+        limit: params.limit.unwrap_or(50).min(500),
+    };
+
+    let state = state.read().await;
+    let hits = state.store.search(&query).map_err(internal_error)?;
+
+    Ok(Json(json!({
+        "count": hits.len(),
+        "results": hits,
+    })))
+}
+
+/// 匯出參數
+#[derive(Debug, Deserialize)]
+struct ExportParams {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    #[serde(default)]
+    format: ExportFormat,
+    /// 加密的收件者：`age1…` 公鑰，或伺服器 keyring 中的 GPG 金鑰 ID／email
+    recipient: Option<String>,
+    /// 在背景匯出至物件儲存（`exports/`），立即回傳工作
+    #[serde(default, rename = "async")]
+    run_async: bool,
+}
+
+/// 以串流下載稽核紀錄（`audit`）或用量統計（`usage`）；指定 `recipient` 時加密後再輸出，
+/// `async=true` 時改為背景工作，完成後的結果帶有物件鍵與下載網址
+async fn export_data(
+    State(state): State<SharedState>,
+    Path(dataset): Path<Dataset>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, (StatusCode, String)> {
+    let recipient = match params.recipient.as_deref() {
+        Some(value) => Some(Recipient::parse(value).await.map_err(|e| (StatusCode::BAD_REQUEST, e))?),
+        None => None,
+    };
+    let store = state.read().await.store.clone();
+    let range = TimeRange::from_dates(params.from, params.to);
+    let mut filename = format!(
+        "{}_{}_{}.{}",
+        match dataset {
+            Dataset::Audit => "audit",
+            Dataset::Usage => "usage",
+        },
+        params.from.map(|d| d.to_string()).unwrap_or_else(|| "all".to_string()),
+        params.to.map(|d| d.to_string()).unwrap_or_else(|| "now".to_string()),
+        params.format.extension(),
+    );
+
+    let chunks = export::stream(store, dataset, range, params.format);
+    if params.run_async {
+        let state = state.read().await;
+        if let Some(recipient) = &recipient {
+            filename = format!("{}.{}", filename, recipient.extension());
+        }
+        let key = format!("{}{}-{}", EXPORT_PREFIX, state.clock.now().format("%Y%m%d%H%M%S"), filename);
+        let blobs = state.blobs.clone();
+        let job = state.jobs.start("export", |handle| async move {
+            // 先寫入暫存檔再上傳，匯出檔再大也不必整個放在記憶體中
+            let spool = std::env::temp_dir().join(format!("bridge-export-{}", liff::new_id()));
+            let result = async {
+                let bytes = match recipient {
+                    Some(recipient) => spool_export(encryption::encrypt(chunks, recipient), &spool, &handle).await?,
+                    None => spool_export(chunks, &spool, &handle).await?,
+                };
+                blobs.put_file(&key, &spool).await?;
+                Ok::<_, String>(bytes)
+            }
+            .await;
+            if let Err(e) = tokio::fs::remove_file(&spool).await {
+                warn!("Failed to remove export spool {}: {}", spool.display(), e);
+            }
+            let bytes = result?;
+            let url = blobs.presigned_url(&key, EXPORT_URL_TTL).ok();
+            Ok(json!({ "key": key, "bytes": bytes, "url": url }))
+        });
+        return Ok(accepted(job));
+    }
+    let (body, content_type) = match recipient {
+        Some(recipient) => {
+            filename = format!("{}.{}", filename, recipient.extension());
+            (Body::from_stream(encryption::encrypt(chunks, recipient)), "application/octet-stream")
+        }
+        None => (Body::from_stream(chunks), params.format.content_type()),
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// 將匯出串流寫入暫存檔，並以已寫入的位元組數回報進度；回傳總位元組數
+async fn spool_export(
+    chunks: impl futures::Stream<Item = Result<Vec<u8>, String>>,
+    path: &std::path::Path,
+    handle: &jobs::JobHandle,
+) -> Result<u64, String> {
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| format!("無法建立暫存檔 {}: {}", path.display(), e))?;
+    let mut chunks = std::pin::pin!(chunks);
+    let mut written = 0u64;
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("無法寫入暫存檔 {}: {}", path.display(), e))?;
+        written += chunk.len() as u64;
+        handle.progress(written, None);
+    }
+    file.flush().await.map_err(|e| format!("無法寫入暫存檔 {}: {}", path.display(), e))?;
+    Ok(written)
+}
+
+/// 推播通知：指定收件人與範本或文字；`urgent` 為 true 時立即送出並略過批次間隔
+#[derive(Debug, Deserialize)]
+struct NewNotification {
+    user_ids: Vec<String>,
+    template: Option<String>,
+    text: Option<String>,
+    #[serde(default)]
+    variables: HashMap<String, String>,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+    #[serde(default)]
+    urgent: bool,
+}
+
+/// 建立推播通知：一般純文字通知依範本的合併時間窗排入摘要佇列，
+/// 其餘（緊急、含媒體或時間窗為 0）立即在背景分批發送，回傳初始進度與 SSE 進度網址
+async fn create_notification(
+    State(shared): State<SharedState>,
+    Json(payload): Json<NewNotification>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    let mut user_ids = payload.user_ids;
+    user_ids.sort();
+    user_ids.dedup();
+    if user_ids.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "user_ids 不可為空".to_string()));
+    }
+
+    let state = shared.read().await;
+    // 已封鎖機器人的使用者收不到推播，送出只會浪費額度
+    let blocked: HashSet<String> = state
+        .store
+        .blocked_users()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .collect();
+    user_ids.retain(|id| !blocked.contains(id));
+    if user_ids.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "所有收件人皆已封鎖機器人".to_string()));
+    }
+    let text = message_text(&state, payload.template.as_deref(), payload.text, &payload.variables)?;
+    if text.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "通知內容不可為空".to_string()));
+    }
+
+    // 額度用完時緊急與含媒體的通知無法延後，直接回報；一般通知延後到額度重置後
+    let quota_reset = quota::exhausted(&state);
+    if let Some(resets_at) = quota_reset.filter(|_| payload.urgent || !payload.attachments.is_empty()) {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, quota::exhausted_message(resets_at)));
+    }
+    let window = match &payload.template {
+        _ if quota_reset.is_some() => 0,
+        _ if payload.urgent || !payload.attachments.is_empty() => 0,
+        Some(name) => state
+            .store
+            .template_batch_window(name)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .unwrap_or(state.config.notify_batch_window_secs),
+        None => state.config.notify_batch_window_secs,
+    };
+    if window > 0 || quota_reset.is_some() {
+        let due_at = (state.clock.timestamp() + window).max(quota_reset.unwrap_or(0));
+        state
+            .store
+            .queue_notification(&user_ids, payload.template.as_deref(), &text, due_at)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        return Ok((StatusCode::ACCEPTED, Json(json!({ "queued": user_ids.len(), "due_at": due_at }))));
+    }
+
+    let messages = announcements::content_messages(&state.blobs, &text, &payload.attachments)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let pacing = Duration::from_millis(state.config.notify_pacing_ms);
+    let progress = state
+        .notifications
+        .start(shared.clone(), user_ids, messages, payload.urgent, pacing);
+    let job = track_notification(&state, progress.id);
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({
+            "progress": progress,
+            "events_url": format!("/admin/notify/{}/events", progress.id),
+            "job": job.map(|job| job.id),
+        })),
+    ))
+}
+
+/// 在背景工作清單中追蹤通知的進度（已送出與失敗的人數 / 收件人數）
+fn track_notification(state: &AppState, id: u64) -> Option<Job> {
+    let mut receiver = state.notifications.subscribe(id)?;
+    Some(state.jobs.start("notify", |handle| async move {
+        loop {
+            let progress = receiver.borrow_and_update().clone();
+            handle.progress((progress.sent + progress.failed) as u64, Some(progress.recipients as u64));
+            if progress.done {
+                return match progress.failed {
+                    0 => Ok(json!(progress)),
+                    failed => Err(format!("通知 {} 有 {} 位收件人發送失敗", id, failed)),
+                };
+            }
+            if receiver.changed().await.is_err() {
+                return Err(format!("通知 {} 的發送中斷", id));
+            }
+        }
+    }))
+}
+
+/// 直接廣播的內容
+#[derive(Debug, Deserialize)]
+struct NewBroadcast {
+    template: Option<String>,
+    text: Option<String>,
+    #[serde(default)]
+    variables: HashMap<String, String>,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+}
+
+/// 立即廣播訊息給所有好友（不經 LINE 上的預覽與核准，適合由排程或其他系統觸發；需 `ADMIN_DIRECT_BROADCAST`）。
+/// 每次廣播都記錄為公告並寫入稽核紀錄
+async fn send_broadcast(
+    State(state): State<SharedState>,
+    Json(payload): Json<NewBroadcast>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let state = state.read().await;
+    if !state.config.admin_direct_broadcast {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "未啟用直接廣播（ADMIN_DIRECT_BROADCAST），請改用 POST /admin/announcements 經核准後送出".to_string(),
+        ));
+    }
+    let text = message_text(&state, payload.template.as_deref(), payload.text, &payload.variables)?;
+    if text.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "廣播內容不可為空".to_string()));
+    }
+    if let Some(resets_at) = quota::exhausted(&state) {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, quota::exhausted_message(resets_at)));
+    }
+
+    let messages = announcements::content_messages(&state.blobs, &text, &payload.attachments)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let count = messages.len();
+    // 與核准後的公告相同，先記為 approved 再依結果轉為 sent 或 failed
+    let id = state
+        .store
+        .create_announcement(payload.template.as_deref(), &text, &payload.attachments, None)
+        .and_then(|id| state.store.transition_announcement(id, "draft", "approved").map(|_| id))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let result = state.line_client.broadcast(messages).await;
+    let status = if result.is_ok() { "sent" } else { "failed" };
+    if let Err(e) = state.store.transition_announcement(id, "approved", status) {
+        error!("Failed to update announcement {}: {}", id, e);
+    }
+    let detail = format!("announcement={} messages={}", id, count);
+    if let Err(e) = state.store.audit(&AuditEvent {
+        user_id: "admin",
+        event_type: "admin",
+        action: "broadcast",
+        status: if result.is_ok() { "ok" } else { "error" },
+        detail: Some(&detail),
+        ..Default::default()
+    }) {
+        warn!("Failed to write audit log: {}", e);
+    }
+    result.map_err(|e| {
+        error!("Failed to broadcast announcement {}: {}", id, e);
+        (StatusCode::BAD_GATEWAY, format!("廣播失敗：{}", e))
+    })?;
+    info!("Broadcast sent via admin API as announcement {} ({} messages)", id, count);
+    Ok(Json(json!({ "sent": true, "id": id, "messages": count })))
+}
+
+/// 群發的內容與對象
+#[derive(Debug, Deserialize)]
+struct NewNarrowcast {
+    template: Option<String>,
+    text: Option<String>,
+    #[serde(default)]
+    variables: HashMap<String, String>,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+    /// 受眾群組 ID，等同 `recipient` 為 `{"type": "audience", "audienceGroupId": ...}`
+    audience_group_id: Option<i64>,
+    /// LINE 的收件人物件（受眾群組、重新導向對象，可用 `operator` 組合 and/or/not）
+    recipient: Option<serde_json::Value>,
+    /// LINE 的屬性篩選（`{"demographic": ...}`：性別、年齡、地區、作業系統、加入好友天數等）
+    filter: Option<serde_json::Value>,
+    /// 最多送出人數
+    max: Option<u32>,
+    /// 只送到本月剩餘額度為止
+    #[serde(default)]
+    up_to_remaining_quota: bool,
+}
+
+/// 依受眾群組或屬性篩選群發；LINE 在背景計算對象後送出，回傳 request ID 與查詢進度的網址。
+/// 每次群發都記錄為公告（LINE 完成前狀態為 `sending`）並寫入稽核紀錄
+async fn send_narrowcast(
+    State(shared): State<SharedState>,
+    Json(payload): Json<NewNarrowcast>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    let state = shared.read().await;
+    let recipient = match (payload.audience_group_id, payload.recipient) {
+        (Some(_), Some(_)) => {
+            return Err((StatusCode::BAD_REQUEST, "audience_group_id 與 recipient 只能擇一".to_string()));
+        }
+        (Some(id), None) => Some(json!({ "type": "audience", "audienceGroupId": id })),
+        (None, recipient) => recipient,
+    };
+    if payload.filter.as_ref().is_some_and(|filter| filter.get("demographic").is_none()) {
+        return Err((StatusCode::BAD_REQUEST, "filter 需為 {\"demographic\": ...}".to_string()));
+    }
+    let text = message_text(&state, payload.template.as_deref(), payload.text, &payload.variables)?;
+    if text.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "群發內容不可為空".to_string()));
+    }
+    if let Some(resets_at) = quota::exhausted(&state) {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, quota::exhausted_message(resets_at)));
+    }
+
+    let messages = announcements::content_messages(&state.blobs, &text, &payload.attachments)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let limit = (payload.max.is_some() || payload.up_to_remaining_quota).then_some(line::NarrowcastLimit {
+        max: payload.max,
+        up_to_remaining_quota: payload.up_to_remaining_quota,
+    });
+    let request = line::NarrowcastRequest { messages, recipient, filter: payload.filter, limit };
+    // 與直接廣播相同，先記為 approved，LINE 受理後轉為 sending，完成後由追蹤工作轉為 sent 或 failed
+    let id = state
+        .store
+        .create_announcement(payload.template.as_deref(), &text, &payload.attachments, None)
+        .and_then(|id| state.store.transition_announcement(id, "draft", "approved").map(|_| id))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let result = state.line_client.narrowcast(&request).await;
+    let detail = match &result {
+        Ok(Some(request_id)) => format!("announcement={} request_id={}", id, request_id),
+        _ => format!("announcement={}", id),
+    };
+    if let Err(e) = state.store.audit(&AuditEvent {
+        user_id: "admin",
+        event_type: "admin",
+        action: "narrowcast",
+        status: if result.is_ok() { "ok" } else { "error" },
+        detail: Some(&detail),
+        ..Default::default()
+    }) {
+        warn!("Failed to write audit log: {}", e);
+    }
+    let request_id = match result {
+        Ok(Some(request_id)) => request_id,
+        Ok(None) => {
+            // LINE 已受理，但沒有 request ID 便無法追蹤進度
+            error!("Narrowcast for announcement {} was accepted without a request ID", id);
+            if let Err(e) = state.store.transition_announcement(id, "approved", "sent") {
+                error!("Failed to update announcement {}: {}", id, e);
+            }
+            return Err((
+                StatusCode::BAD_GATEWAY,
+                format!("LINE 已受理群發（公告 {}），但未回傳 request ID，無法追蹤進度", id),
+            ));
+        }
+        Err(e) => {
+            error!("Failed to narrowcast announcement {}: {}", id, e);
+            if let Err(e) = state.store.transition_announcement(id, "approved", "failed") {
+                error!("Failed to update announcement {}: {}", id, e);
+            }
+            return Err((StatusCode::BAD_GATEWAY, format!("群發失敗：{}", e)));
+        }
+    };
+    if let Err(e) = state.store.start_narrowcast(id, &request_id) {
+        error!("Failed to update announcement {}: {}", id, e);
+    }
+    info!("Narrowcast accepted by LINE as announcement {}: request_id={}", id, request_id);
+    let job = track_narrowcast(&state, shared.clone(), id, request_id.clone());
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({
+            "id": id,
+            "request_id": request_id,
+            "progress_url": format!("/admin/narrowcast/{}", request_id),
+            "job": job.id,
+        })),
+    ))
+}
+
+/// 重啟後繼續追蹤尚在送出中的群發
+pub(crate) async fn resume_narrowcasts(shared: &SharedState) {
+    let state = shared.read().await;
+    let narrowcasts = match state.store.sending_narrowcasts() {
+        Ok(narrowcasts) => narrowcasts,
+        Err(e) => {
+            error!("Failed to load pending narrowcasts: {}", e);
+            return;
+        }
+    };
+    for (id, request_id) in narrowcasts {
+        info!("Resuming narrowcast tracking for announcement {}: request_id={}", id, request_id);
+        track_narrowcast(&state, shared.clone(), id, request_id);
+    }
+}
+
+/// 在背景工作清單中追蹤群發：定期向 LINE 查詢進度（成功與失敗人數 / 目標人數），直到成功或失敗，
+/// 結束時將公告由 `sending` 轉為 `sent` 或 `failed`
+fn track_narrowcast(state: &AppState, shared: SharedState, id: i64, request_id: String) -> Job {
+    state.jobs.start("narrowcast", move |handle| async move {
+        let mut errors = 0;
+        loop {
+            tokio::time::sleep(NARROWCAST_POLL_INTERVAL).await;
+            let progress = shared.read().await.line_client.narrowcast_progress(&request_id).await;
+            let progress = match progress {
+                Ok(progress) => progress,
+                Err(e) if errors < NARROWCAST_POLL_ERRORS => {
+                    errors += 1;
+                    warn!("Failed to poll narrowcast {}: {}", request_id, e);
+                    continue;
+                }
+                Err(e) => return Err(format!("無法取得群發 {} 的進度：{}", request_id, e)),
+            };
+            errors = 0;
+            let done = progress.success_count.unwrap_or(0) + progress.failure_count.unwrap_or(0);
+            handle.progress(done, progress.target_count);
+            let status = match progress.phase.as_str() {
+                "succeeded" => "sent",
+                "failed" => "failed",
+                _ => continue,
+            };
+            if let Err(e) = shared.read().await.store.transition_announcement(id, "sending", status) {
+                error!("Failed to update announcement {}: {}", id, e);
+            }
+            if status == "failed" {
+                return Err(format!(
+                    "群發 {} 失敗：{}",
+                    request_id,
+                    progress.failed_description.as_deref().unwrap_or("unknown")
+                ));
+            }
+            return Ok(json!({ "id": id, "request_id": request_id, "progress": progress }));
+        }
+    })
+}
+
+/// 查詢群發進度（`phase` 為 `waiting`、`sending`、`succeeded` 或 `failed`）
+async fn show_narrowcast(
+    State(state): State<SharedState>,
+    Path(request_id): Path<String>,
+) -> Result<Json<line::NarrowcastProgress>, (StatusCode, String)> {
+    let state = state.read().await;
+    state.line_client.narrowcast_progress(&request_id).await.map(Json).map_err(|e| match e.status() {
+        Some(status) if matches!(status.as_u16(), 400 | 404) => {
+            (StatusCode::NOT_FOUND, format!("找不到群發：{}", request_id))
+        }
+        _ => {
+            error!("Failed to fetch narrowcast progress: {}", e);
+            (StatusCode::BAD_GATEWAY, format!("查詢群發進度失敗：{}", e))
+        }
+    })
+}
+
+/// 要送給 WhatsApp 使用者的訊息：文字或已核准的範本擇一
+#[derive(Debug, Deserialize)]
+struct NewWhatsAppMessage {
+    /// 收件人的 WhatsApp ID（國碼開頭的電話號碼）
+    to: String,
+    text: Option<String>,
+    template: Option<whatsapp_adapter::Template>,
+}
+
+/// 主動發送 WhatsApp 訊息；距使用者最後一則訊息超過 24 小時時只能使用範本
+async fn send_whatsapp(
+    State(state): State<SharedState>,
+    Json(payload): Json<NewWhatsAppMessage>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let state = state.read().await;
+    let Some(client) = &state.whatsapp_client else {
+        return Err((StatusCode::NOT_FOUND, "未設定 WhatsApp".to_string()));
+    };
+    let to = payload.to.trim().trim_start_matches('+');
+    if to.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "收件人不可為空".to_string()));
+    }
+    let result = match (payload.text.as_deref().map(str::trim).filter(|text| !text.is_empty()), &payload.template) {
+        (Some(text), None) => channels::send_answer(client, to, text).await.map(|count| json!({ "sent": true, "messages": count })),
+        (None, Some(template)) => client
+            .send_template(to, template)
+            .await
+            .map(|message_id| json!({ "sent": true, "message_id": message_id }))
+            .map_err(|e| e.to_string()),
+        _ => return Err((StatusCode::BAD_REQUEST, "需提供 text 或 template 其中之一".to_string())),
+    };
+    let body = result.map_err(|e| {
+        error!("Failed to send WhatsApp message to {}: {}", privacy::id(to), e);
+        (StatusCode::BAD_GATEWAY, format!("發送失敗：{}", e))
+    })?;
+    info!("WhatsApp message sent to {} via admin API", privacy::id(to));
+    Ok(Json(body))
+}
+
+/// 依範本或文字產生訊息內容並代入變數
+fn message_text(
+    state: &AppState,
+    template: Option<&str>,
+    text: Option<String>,
+    variables: &HashMap<String, String>,
+) -> Result<String, (StatusCode, String)> {
+    let source = match (template, text) {
+        (Some(name), _) => state
+            .store
+            .get_template(name)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, format!("找不到範本：{}", name)))?,
+        (None, Some(text)) => text,
+        (None, None) => return Err((StatusCode::BAD_REQUEST, "需提供 template 或 text".to_string())),
+    };
+    announcements::render(&source, variables).map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+/// 查詢推播通知進度
+async fn show_notification(
+    State(state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> Result<Json<notify::Progress>, StatusCode> {
+    let state = state.read().await;
+    state.notifications.progress(id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// 以 SSE 即時回報推播進度：每次變化送出 `progress` 事件，完成時送出 `done` 後結束
+async fn notification_events(
+    State(state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> Result<Sse<impl futures::Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let receiver = state.read().await.notifications.subscribe(id).ok_or(StatusCode::NOT_FOUND)?;
+    let stream = futures::stream::unfold(Some((receiver, true)), |next| async move {
+        let (mut receiver, first) = next?;
+        // 第一個事件立即送出目前進度，之後等待變化；發送端中途結束則關閉串流
+        if !first && receiver.changed().await.is_err() {
+            return None;
+        }
+        let progress = receiver.borrow_and_update().clone();
+        let data = serde_json::to_string(&progress).unwrap_or_default();
+        let event = Event::default().event(if progress.done { "done" } else { "progress" }).data(data);
+        Some((Ok(event), (!progress.done).then_some((receiver, false))))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// 列出公告範本
+async fn list_templates(State(state): State<SharedState>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let state = state.read().await;
+    let templates = state.store.list_templates().map_err(internal_error)?;
+    Ok(Json(json!({ "templates": templates })))
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateBody {
+    body: String,
+    /// 以此範本發出的一般通知的合併時間窗（秒），0 代表立即發送
+    batch_window_secs: Option<i64>,
+}
+
+/// 新增或更新公告範本（內容可使用 `{{變數}}`）
+async fn save_template(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(payload): Json<TemplateBody>,
+) -> Result<StatusCode, StatusCode> {
+    let state = state.read().await;
+    if payload.batch_window_secs.is_some_and(|w| w < 0) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    state
+        .store
+        .save_template(&name, &payload.body, payload.batch_window_secs)
+        .map_err(internal_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 列出最近的公告
+async fn list_announcements(State(state): State<SharedState>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let state = state.read().await;
+    let announcements = state.store.list_announcements(50).map_err(internal_error)?;
+    Ok(Json(json!({ "announcements": announcements })))
+}
+
+/// 建立公告：指定範本名稱或直接提供文字
+#[derive(Debug, Deserialize)]
+struct NewAnnouncement {
+    template: Option<String>,
+    text: Option<String>,
+    #[serde(default)]
+    variables: HashMap<String, String>,
+    /// 附加的圖片/音訊（需先以 `PUT /admin/media/*key` 上傳）
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+    /// 指定收件人（LINE userId）；未指定時廣播給所有好友
+    recipients: Option<Vec<String>>,
+}
+
+/// 建立公告草稿並推送預覽給管理員 LINE 帳號，核准後才會廣播（或 multicast 給指定收件人）
+async fn create_announcement(
+    State(state): State<SharedState>,
+    Json(payload): Json<NewAnnouncement>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let state = state.read().await;
+    let admin_user = state.config.admin_line_user_id.as_deref().ok_or((
+        StatusCode::CONFLICT,
+        "ADMIN_LINE_USER_ID 未設定，無法推送預覽".to_string(),
+    ))?;
+
+    let text = message_text(&state, payload.template.as_deref(), payload.text, &payload.variables)?;
+    let recipients = match payload.recipients {
+        Some(mut recipients) => {
+            recipients.sort();
+            recipients.dedup();
+            // multicast 只要有一個無效 ID 就會整批失敗，建立時先擋下
+            if let Some(invalid) = recipients.iter().find(|id| !line::is_user_id(id)) {
+                return Err((StatusCode::BAD_REQUEST, format!("無效的 LINE userId：{}", invalid)));
+            }
+            if recipients.is_empty() {
+                return Err((StatusCode::BAD_REQUEST, "recipients 不可為空".to_string()));
+            }
+            Some(recipients)
+        }
+        None => None,
+    };
+
+    let id = state
+        .store
+        .create_announcement(payload.template.as_deref(), &text, &payload.attachments, recipients.as_deref())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let preview = announcements::preview_messages(
+        &state.i18n,
+        &state.blobs,
+        id,
+        &text,
+        &payload.attachments,
+        recipients.as_ref().map(Vec::len),
+    )
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    state
+        .line_client
+        .push_messages(admin_user, &preview)
+        .await
+        .map_err(|e| {
+            error!("Failed to push announcement preview: {}", e);
+            (StatusCode::BAD_GATEWAY, format!("預覽推送失敗：{}", e))
+        })?;
+    state
+        .store
+        .transition_announcement(id, "draft", "previewed")
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(json!({ "id": id, "status": "previewed", "text": text })))
+}
+
+/// 上傳媒體檔到物件儲存，回傳可供 LINE 使用的預簽網址
+async fn upload_media(
+    State(state): State<SharedState>,
+    Path(key): Path<String>,
+    body: axum::body::Bytes,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let state = state.read().await;
+    let full_key = media::media_key(&key);
+    state
+        .blobs
+        .put(&full_key, body.to_vec())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let url = state.blobs.presigned_url(&full_key, std::time::Duration::from_secs(3600)).ok();
+    Ok(Json(json!({ "key": key, "url": url })))
+}
+
+/// 死信佇列與試運行紀錄的參數
+#[derive(Debug, Deserialize)]
+struct LimitParams {
+    limit: Option<usize>,
+}
+
+/// 列出最近未能送達的回覆（死信佇列）
+async fn list_dead_letters(
+    State(state): State<SharedState>,
+    Query(params): Query<LimitParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let state = state.read().await;
+    let dead_letters = state
+        .store
+        .list_dead_letters(params.limit.unwrap_or(50).min(500))
+        .map_err(internal_error)?;
+    Ok(Json(json!({ "dead_letters": dead_letters })))
+}
+
+/// 列出最近試運行攔下、未實際送出的訊息
+async fn list_dry_run_messages(
+    State(state): State<SharedState>,
+    Query(params): Query<LimitParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let state = state.read().await;
+    let messages = state
+        .store
+        .list_dry_run_messages(params.limit.unwrap_or(50).min(500))
+        .map_err(internal_error)?;
+    Ok(Json(json!({ "dry_run": state.line_client.is_dry_run(), "messages": messages })))
+}
+
+/// 立即執行資料庫維護（刪除過期資料與 VACUUM），回傳回收的空間；`async=true` 時改為背景工作
+async fn run_maintenance(
+    State(state): State<SharedState>,
+    Query(params): Query<AsyncParams>,
+) -> Result<Response, StatusCode> {
+    let (store, config) = {
+        let state = state.read().await;
+        (state.store.clone(), state.config.maintenance.clone())
+    };
+    if params.run_async {
+        let job = state.read().await.jobs.start("maintenance", |_| async move {
+            let report = tokio::task::spawn_blocking(move || maintenance::run_once(&store, &config))
+                .await
+                .map_err(|e| e.to_string())??;
+            serde_json::to_value(report).map_err(|e| e.to_string())
+        });
+        return Ok(accepted(job));
+    }
+    tokio::task::spawn_blocking(move || maintenance::run_once(&store, &config))
+        .await
+        .map_err(internal_error)?
+        .map(|report: MaintenanceReport| Json(report).into_response())
+        .map_err(internal_error)
+}
+
+/// 與此身分連結的其他平台身分
+async fn show_links(State(state): State<SharedState>, Path(user_id): Path<String>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let state = state.read().await;
+    let linked = state.store.linked_identities(&user_id).map_err(internal_error)?;
+    Ok(Json(json!({ "user_id": user_id, "linked": linked })))
+}
+
+/// 解除此身分的連結
+async fn remove_link(State(state): State<SharedState>, Path(user_id): Path<String>) -> Result<StatusCode, StatusCode> {
+    let state = state.read().await;
+    match state.store.unlink_identity(&user_id).map_err(internal_error)? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// 手動解除額度用完狀態（例如已升級方案），延後的通知隨即送出
+async fn reset_quota(State(state): State<SharedState>) -> Result<StatusCode, StatusCode> {
+    let state = state.read().await;
+    match state.store.resolve_incident(quota::LINE_QUOTA).map_err(internal_error)? {
+        Some(id) => {
+            info!("Incident #{} resolved manually: LINE message quota reset", id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// 各 RSS 來源的抓取統計（條件式抓取、robots.txt 與退避狀態）
+async fn list_feeds(State(state): State<SharedState>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let state = state.read().await;
+    let feeds = state.store.feed_sources().map_err(internal_error)?;
+    Ok(Json(json!({ "feeds": feeds })))
+}
+
+/// 設定中的模型與 OpenClaw 提供的模型
+async fn show_models(State(state): State<SharedState>) -> Json<models::Report> {
+    let state = state.read().await;
+    Json(models::report(&state))
+}
+
+/// 追蹤紀錄的篩選條件
+#[derive(Debug, Deserialize)]
+struct TraceFilter {
+    /// 只列出此結果（例如 `dead_letter`、`no_reply`）
+    outcome: Option<String>,
+    /// 只列出總耗時至少此毫秒數的事件
+    min_ms: Option<f64>,
+}
+
+/// 最近 webhook 事件的各階段耗時（新的在前）
+async fn list_traces(State(state): State<SharedState>, Query(filter): Query<TraceFilter>) -> Json<serde_json::Value> {
+    let state = state.read().await;
+    let traces: Vec<Trace> = state
+        .traces
+        .list()
+        .into_iter()
+        .filter(|trace| filter.outcome.as_ref().is_none_or(|outcome| &trace.outcome == outcome))
+        .filter(|trace| filter.min_ms.is_none_or(|min_ms| trace.total_ms >= min_ms))
+        .collect();
+    Json(json!({ "traces": traces }))
+}
+
+/// 處理追蹤的抽樣設定與統計
+async fn show_trace_sampling(State(state): State<SharedState>) -> Json<SamplingReport> {
+    Json(state.read().await.traces.sampling())
+}
+
+/// 調整處理追蹤的抽樣設定（`{"percent": 10, "slow_ms": 2000}`，可只提供其一）；重新啟動後恢復環境變數的設定
+async fn update_trace_sampling(
+    State(state): State<SharedState>,
+    Json(update): Json<SamplingUpdate>,
+) -> Result<Json<SamplingReport>, (StatusCode, String)> {
+    let state = state.read().await;
+    let sampling = state.traces.update_sampling(update).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    info!("Trace sampling updated: percent={} slow_ms={}", sampling.percent, sampling.slow_ms);
+    Ok(Json(state.traces.sampling()))
+}
+
+/// 顯示格式
+#[derive(Debug, Deserialize)]
+struct TraceFormat {
+    /// `html` 時以瀑布圖呈現，否則回傳 JSON
+    format: Option<String>,
+}
+
+/// 單一事件的各階段耗時
+async fn show_trace(
+    State(state): State<SharedState>,
+    Path(id): Path<u64>,
+    Query(params): Query<TraceFormat>,
+) -> Result<Response, StatusCode> {
+    let state = state.read().await;
+    let trace = state.traces.get(id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(match params.format.as_deref() {
+        Some("html") => Html(pipeline::render_html(&state.i18n, state.i18n.default_locale(), &trace)).into_response(),
+        _ => Json(trace).into_response(),
+    })
+}
+
+/// 快取的個人資料與封鎖狀態
+async fn show_profile(State(state): State<SharedState>, Path(user_id): Path<String>) -> Result<Json<UserProfile>, StatusCode> {
+    let state = state.read().await;
+    state.store.profile(&user_id).map_err(internal_error)?.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// 使用者資料快照的匯出參數
+#[derive(Debug, Deserialize)]
+struct SnapshotParams {
+    /// 加密的收件者（同 [`ExportParams::recipient`]）
+    recipient: Option<String>,
+}
+
+/// 以串流匯出使用者資料快照（新實例以 `SNAPSHOT_IMPORT_PATH` 匯入）；指定 `recipient` 時加密後以檔案下載
+async fn export_snapshot(
+    State(state): State<SharedState>,
+    Query(params): Query<SnapshotParams>,
+) -> Result<Response, (StatusCode, String)> {
+    let recipient = match params.recipient.as_deref() {
+        Some(value) => Some(Recipient::parse(value).await.map_err(|e| (StatusCode::BAD_REQUEST, e))?),
+        None => None,
+    };
+    let state = state.read().await;
+    let chunks = snapshot::stream(state.store.clone());
+    let filename = format!("snapshot_{}.json", state.clock.now().format("%Y%m%d%H%M%S"));
+    let (body, content_type, filename) = match recipient {
+        Some(recipient) => (
+            Body::from_stream(encryption::encrypt(chunks, recipient.clone())),
+            "application/octet-stream",
+            format!("{}.{}", filename, recipient.extension()),
+        ),
+        None => (Body::from_stream(chunks), "application/json", filename),
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// 記憶體、tokio 工作、資料表列數與資料庫大小
+async fn show_stats(State(state): State<SharedState>) -> Result<Json<Snapshot>, StatusCode> {
+    let state = state.read().await;
+    resources::snapshot(&state.store, &state.row_counts, &state.config.store_soft_limits)
+        .map(Json)
+        .map_err(internal_error)
+}
+
+/// 列出封存檔
+async fn list_archives(State(state): State<SharedState>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let state = state.read().await;
+    let archives = state.store.list_archives().map_err(internal_error)?;
+    Ok(Json(json!({ "retention_days": state.config.retention_days, "archives": archives })))
+}
+
+/// 立即執行一次封存；`async=true` 時改為背景工作
+async fn run_archive(
+    State(state): State<SharedState>,
+    Query(params): Query<AsyncParams>,
+) -> Result<Response, (StatusCode, String)> {
+    let state = state.read().await;
+    let days = state
+        .config
+        .retention_days
+        .ok_or((StatusCode::CONFLICT, "RETENTION_DAYS 未設定".to_string()))?;
+    if params.run_async {
+        let store = state.store.clone();
+        let blobs = state.blobs.clone();
+        let job = state.jobs.start("archive", |_| async move {
+            let archived = archive::run_once(&store, &blobs, days).await?;
+            Ok(json!({ "archived": archived }))
+        });
+        return Ok(accepted(job));
+    }
+    let archived = archive::run_once(&state.store, &state.blobs, days)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(json!({ "archived": archived })).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct AsyncParams {
+    /// 在背景執行並立即回傳工作，之後以 `GET /admin/jobs/:id` 查詢結果
+    #[serde(default, rename = "async")]
+    run_async: bool,
+}
+
+/// 已在背景啟動的工作：回傳 202 與工作狀態
+fn accepted(job: Job) -> Response {
+    (StatusCode::ACCEPTED, Json(job)).into_response()
+}
+
+/// 列出進行中與最近完成的背景工作
+async fn list_jobs(State(state): State<SharedState>) -> Json<serde_json::Value> {
+    let state = state.read().await;
+    Json(json!({ "jobs": state.jobs.list() }))
+}
+
+/// 背景工作的進度與結果
+async fn show_job(State(state): State<SharedState>, Path(id): Path<u64>) -> Result<Json<Job>, StatusCode> {
+    let state = state.read().await;
+    state.jobs.get(id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// 將封存檔還原回資料庫
+async fn restore_archive(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let state = state.read().await;
+    let restored = archive::restore(&state.store, &state.blobs, &name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(json!({ "restored": restored })))
+}
+
+#[derive(Debug, Deserialize)]
+struct TestMessage {
+    user_id: String,
+    text: String,
+    /// 語系標籤（如 `en`），未指定則使用預設語系，不向 LINE 查詢
+    locale: Option<String>,
+    /// 是否實際推送回應給使用者；預設只回傳預覽
+    #[serde(default)]
+    confirm: bool,
+}
+
+/// 測試訊息的對話 ID 前綴，對話紀錄與稽核紀錄寫入 `test:<userId>`，不混入使用者真正的對話
+const TEST_CONVERSATION_PREFIX: &str = "test:";
+
+/// 以指定使用者送出模擬訊息，走完整處理流程（指令、AI 回答、稽核與對話紀錄），回傳將送出的訊息；
+/// 處理時使用獨立的測試對話 ID，`confirm` 為 true 時才推送給該使用者。稽核紀錄的事件類型為 `test`
+async fn test_message(
+    State(state): State<SharedState>,
+    Json(payload): Json<TestMessage>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if payload.user_id.trim().is_empty() || payload.text.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "user_id 與 text 不可為空".to_string()));
+    }
+    let state = state.read().await;
+    let locale = match payload.locale.as_deref() {
+        Some(tag) => Locale::from_tag(tag).ok_or((StatusCode::BAD_REQUEST, format!("不支援的語系：{}", tag)))?,
+        None => state.i18n.default_locale(),
+    };
+
+    let started = Instant::now();
+    let conversation_id = format!("{}{}", TEST_CONVERSATION_PREFIX, payload.user_id);
+    let messages = crate::respond(&state, "test", &conversation_id, &conversation_id, locale, &payload.text).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let sent = payload.confirm && !messages.is_empty();
+    if sent {
+        state.line_client.push_messages(&payload.user_id, &messages).await.map_err(|e| {
+            error!("Failed to push test message: {}", e);
+            (StatusCode::BAD_GATEWAY, format!("推送失敗：{}", e))
+        })?;
+    }
+    Ok(Json(json!({
+        "conversation_id": conversation_id,
+        "locale": locale.tag(),
+        "latency_ms": latency_ms,
+        "sent": sent,
+        "messages": messages,
+    })))
+}
+
+fn internal_error(e: impl std::fmt::Display) -> StatusCode {
+    error!("Admin request failed: {}", e);
+    reporting::capture(reporting::Level::Error, "admin request failed", &reporting::Context {
+        detail: Some(&e.to_string()),
+        ..Default::default()
+    });
+    StatusCode::INTERNAL_SERVER_ERROR
+}
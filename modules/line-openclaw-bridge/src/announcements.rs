@@ -0,0 +1,209 @@
+//! 公告流程模組
+//! 公告草稿先推送預覽給管理員，經 postback 核准後才廣播給所有好友（指定收件人時改以 multicast 送出）
+
+use std::collections::{HashMap, HashSet};
+use tracing::{error, info, warn};
+
+use crate::blob::BlobStore;
+use crate::i18n::{I18n, Locale};
+use crate::line::{Action, OutgoingMessage, Template, TemplateMessage, TextMessage, MULTICAST_LIMIT};
+use crate::media;
+use crate::privacy;
+use crate::quota;
+use crate::store::Attachment;
+use crate::AppState;
+
+/// postback 資料前綴
+const POSTBACK_PREFIX: &str = "announcement:";
+
+/// 管理員對公告預覽的操作
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    Approve(i64),
+    Cancel(i64),
+}
+
+/// 套用範本變數：`{{name}}` 以 `variables` 取代，內建 `{{date}}`（今天日期）
+pub fn render(template: &str, variables: &HashMap<String, String>) -> Result<String, String> {
+    let mut text = template.replace("{{date}}", &chrono::Local::now().format("%Y-%m-%d").to_string());
+    for (name, value) in variables {
+        text = text.replace(&format!("{{{{{}}}}}", name), value);
+    }
+
+    if let Some(start) = text.find("{{") {
+        let missing = text[start + 2..].split("}}").next().unwrap_or_default();
+        return Err(format!("範本變數未提供：{}", missing));
+    }
+    Ok(text)
+}
+
+/// 公告本文與附件組成的訊息（LINE 單次最多 5 則）
+pub fn content_messages(blobs: &BlobStore, text: &str, attachments: &[Attachment]) -> Result<Vec<OutgoingMessage>, String> {
+    if attachments.len() > 3 {
+        return Err("公告最多可附加 3 個媒體檔".to_string());
+    }
+    let mut messages = vec![TextMessage::new(text).into()];
+    for attachment in attachments {
+        messages.push(media::to_message(blobs, attachment)?);
+    }
+    Ok(messages)
+}
+
+/// 組出推送給管理員的預覽訊息（內容 + 核准/取消按鈕）；`recipients` 為指定收件人數，None 代表廣播
+pub fn preview_messages(
+    i18n: &I18n,
+    blobs: &BlobStore,
+    id: i64,
+    text: &str,
+    attachments: &[Attachment],
+    recipients: Option<usize>,
+) -> Result<Vec<OutgoingMessage>, String> {
+    let locale = i18n.default_locale();
+    let id_arg = id.to_string();
+    let count = recipients.unwrap_or_default().to_string();
+    let args = [("id", id_arg.as_str()), ("count", count.as_str())];
+    let title = i18n.text(locale, "announcement.preview", &args);
+    let mut messages = content_messages(blobs, &format!("{}\n\n{}", title, text), attachments)?;
+    messages.push(
+        TemplateMessage::new(
+            i18n.text(locale, "announcement.confirm_alt", &args),
+            Template::Confirm {
+                text: match recipients {
+                    Some(_) => i18n.text(locale, "announcement.confirm_text_recipients", &args),
+                    None => i18n.text(locale, "announcement.confirm_text", &args),
+                },
+                actions: vec![
+                    Action::postback(
+                        i18n.text(locale, "announcement.approve_label", &[]),
+                        format!("{}approve:{}", POSTBACK_PREFIX, id),
+                    ),
+                    Action::postback(
+                        i18n.text(locale, "announcement.cancel_label", &[]),
+                        format!("{}cancel:{}", POSTBACK_PREFIX, id),
+                    ),
+                ],
+            },
+        )
+        .into(),
+    );
+    Ok(messages)
+}
+
+/// 解析公告相關的 postback 資料
+pub fn parse_postback(data: &str) -> Option<Decision> {
+    let (action, id) = data.strip_prefix(POSTBACK_PREFIX)?.split_once(':')?;
+    let id = id.parse().ok()?;
+    match action {
+        "approve" => Some(Decision::Approve(id)),
+        "cancel" => Some(Decision::Cancel(id)),
+        _ => None,
+    }
+}
+
+/// 處理管理員的核准/取消，回傳要回覆給管理員的文字
+pub async fn handle_decision(state: &AppState, user_id: &str, locale: Locale, decision: Decision) -> String {
+    let t = |key: &str, id: i64| state.i18n.text(locale, key, &[("id", &id.to_string())]);
+    if state.config.admin_line_user_id.as_deref() != Some(user_id) {
+        warn!("Non-admin user {} tried to act on an announcement", privacy::id(user_id));
+        return state.i18n.text(locale, "announcement.admin_only", &[]);
+    }
+
+    match decision {
+        Decision::Cancel(id) => match state.store.transition_announcement(id, "previewed", "cancelled") {
+            Ok(true) => t("announcement.cancelled", id),
+            Ok(false) => t("announcement.cannot_cancel", id),
+            Err(e) => {
+                error!("Failed to cancel announcement {}: {}", id, e);
+                t("announcement.error", id)
+            }
+        },
+        Decision::Approve(id) => {
+            // 額度用完時保留待核准，重置後再核准即可
+            if let Some(resets_at) = quota::exhausted(state) {
+                let time = quota::format_reset(resets_at);
+                return state.i18n.text(locale, "announcement.quota_exhausted", &[("id", &id.to_string()), ("time", &time)]);
+            }
+            let Ok(Some(announcement)) = state.store.get_announcement(id) else {
+                return t("announcement.not_found", id);
+            };
+            // 先將狀態轉為 approved，確保同一則公告只會廣播一次；指定收件人的公告送出失敗後可再次核准，
+            // 由已記錄的進度繼續送出未送出的批次
+            let from = match (announcement.status.as_str(), &announcement.recipients) {
+                ("failed", Some(_)) => "failed",
+                _ => "previewed",
+            };
+            match state.store.transition_announcement(id, from, "approved") {
+                Ok(true) => {}
+                Ok(false) => return t("announcement.already_handled", id),
+                Err(e) => {
+                    error!("Failed to approve announcement {}: {}", id, e);
+                    return t("announcement.error", id);
+                }
+            }
+
+            let mut delivered = announcement.delivered;
+            let result = match content_messages(&state.blobs, &announcement.text, &announcement.attachments) {
+                Ok(messages) => match &announcement.recipients {
+                    Some(recipients) => multicast(state, id, recipients, &messages, &mut delivered).await,
+                    None => state.line_client.broadcast(messages).await.map_err(|e| e.to_string()),
+                },
+                Err(e) => Err(e),
+            };
+
+            let (status, reply) = match result {
+                Ok(()) => {
+                    info!("Announcement {} sent", id);
+                    ("sent", t("announcement.sent", id))
+                }
+                Err(e) => {
+                    error!("Failed to send announcement {}: {}", id, e);
+                    let id = id.to_string();
+                    let reply = match &announcement.recipients {
+                        Some(recipients) if delivered > 0 => state.i18n.text(
+                            locale,
+                            "announcement.failed_partial",
+                            &[
+                                ("id", &id),
+                                ("error", &e),
+                                ("delivered", &delivered.to_string()),
+                                ("count", &recipients.len().to_string()),
+                            ],
+                        ),
+                        _ => state.i18n.text(locale, "announcement.failed", &[("id", &id), ("error", &e)]),
+                    };
+                    ("failed", reply)
+                }
+            };
+            if let Err(e) = state.store.transition_announcement(id, "approved", status) {
+                error!("Failed to update announcement {}: {}", id, e);
+            }
+            reply
+        }
+    }
+}
+
+/// 以 multicast 逐批送給指定收件人，跳過已送出的前 `delivered` 位與已封鎖機器人的使用者；
+/// 每批送出後記錄進度，中途失敗時已送出的批次不會在再次核准時重送
+async fn multicast(
+    state: &AppState,
+    id: i64,
+    recipients: &[String],
+    messages: &[OutgoingMessage],
+    delivered: &mut usize,
+) -> Result<(), String> {
+    let blocked: HashSet<String> = state.store.blocked_users().map_err(|e| e.to_string())?.into_iter().collect();
+    while *delivered < recipients.len() {
+        let batch = &recipients[*delivered..(*delivered + MULTICAST_LIMIT).min(recipients.len())];
+        let to: Vec<String> = batch.iter().filter(|id| !blocked.contains(*id)).cloned().collect();
+        if to.is_empty() {
+            warn!("All recipients in an announcement batch have blocked the bot");
+        } else {
+            state.line_client.multicast(&to, messages).await.map_err(|e| e.to_string())?;
+        }
+        *delivered += batch.len();
+        if let Err(e) = state.store.record_announcement_delivered(id, *delivered) {
+            error!("Failed to record announcement {} progress: {}", id, e);
+        }
+    }
+    Ok(())
+}
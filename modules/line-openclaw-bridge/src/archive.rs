@@ -0,0 +1,98 @@
+//! 對話封存模組
+//! 將超過保存期限的對話壓縮（zstd JSONL）移出資料庫，並可由管理員還原
+
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::blob::BlobStore;
+use crate::store::{ConversationRecord, ConversationStore};
+
+/// 每個封存檔最多包含的對話筆數
+const ARCHIVE_BATCH: usize = 5000;
+/// 封存檔在物件儲存中的路徑前綴
+const ARCHIVE_PREFIX: &str = "archives/";
+/// 背景封存檢查間隔
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// 封存所有超過 `retention_days` 的對話，回傳封存筆數
+pub async fn run_once(store: &ConversationStore, blobs: &BlobStore, retention_days: i64) -> Result<usize, String> {
+    let cutoff = store.clock().timestamp() - retention_days * 86_400;
+    let mut total = 0;
+
+    loop {
+        let records = store
+            .conversations_before(cutoff, ARCHIVE_BATCH)
+            .map_err(|e| format!("讀取對話失敗: {}", e))?;
+        let (Some(first), Some(last)) = (records.first(), records.last()) else { break };
+
+        let name = format!(
+            "conversations-{}-{}-{}.jsonl.zst",
+            store.clock().now().format("%Y%m%d"),
+            first.id,
+            last.id
+        );
+        let (records, data) = tokio::task::spawn_blocking(move || compress(&records).map(|data| (records, data)))
+            .await
+            .map_err(|e| format!("壓縮封存檔失敗: {}", e))??;
+
+        // 先寫入封存檔，成功後才從資料庫刪除
+        blobs.put(&format!("{}{}", ARCHIVE_PREFIX, name), data).await?;
+        store
+            .mark_archived(&name, &records, cutoff)
+            .map_err(|e| format!("更新資料庫失敗: {}", e))?;
+
+        info!("Archived {} conversations into {}", records.len(), name);
+        total += records.len();
+    }
+
+    Ok(total)
+}
+
+/// 將封存檔的對話寫回資料庫，回傳寫回筆數
+pub async fn restore(store: &ConversationStore, blobs: &BlobStore, name: &str) -> Result<usize, String> {
+    let data = blobs.get(&format!("{}{}", ARCHIVE_PREFIX, name)).await?;
+    let records = tokio::task::spawn_blocking(move || decompress(&data))
+        .await
+        .map_err(|e| format!("解壓縮封存檔失敗: {}", e))??;
+    let restored = store
+        .restore_conversations(name, &records)
+        .map_err(|e| format!("寫回資料庫失敗: {}", e))?;
+    info!("Restored {} conversations from {}", restored, name);
+    Ok(restored)
+}
+
+/// 啟動背景封存工作
+pub fn spawn(store: Arc<ConversationStore>, blobs: Arc<BlobStore>, retention_days: i64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ARCHIVE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_once(&store, &blobs, retention_days).await {
+                error!("Conversation archival failed: {}", e);
+            }
+        }
+    });
+}
+
+fn compress(records: &[ConversationRecord]) -> Result<Vec<u8>, String> {
+    let mut encoder = zstd::Encoder::new(Vec::new(), 0).map_err(|e| e.to_string())?;
+    for record in records {
+        serde_json::to_writer(&mut encoder, record).map_err(|e| e.to_string())?;
+        encoder.write_all(b"\n").map_err(|e| e.to_string())?;
+    }
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+fn decompress(data: &[u8]) -> Result<Vec<ConversationRecord>, String> {
+    let decoder = zstd::Decoder::new(data).map_err(|e| e.to_string())?;
+    std::io::BufReader::new(decoder)
+        .lines()
+        .filter(|line| !matches!(line, Ok(l) if l.trim().is_empty()))
+        .map(|line| {
+            let line = line.map_err(|e| e.to_string())?;
+            serde_json::from_str(&line).map_err(|e| format!("封存檔格式錯誤: {}", e))
+        })
+        .collect()
+}
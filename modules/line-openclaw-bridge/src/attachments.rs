@@ -0,0 +1,279 @@
+//! 附件封存模組
+//! 開啟 `ATTACHMENT_ARCHIVE` 後，使用者傳來的圖片、影片、語音與檔案存入物件儲存（本機、S3 或 WebDAV 的 `attachments/` 前綴），
+//! 並回覆永久連結與擷取的資訊（格式、大小、圖片尺寸、長度、SHA-256），讓官方帳號也能當作隨手存檔的工具
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::blob::uri_encode;
+use crate::clock::Clock;
+use crate::i18n::Locale;
+use crate::incoming::{Content, Handled};
+use crate::media;
+use crate::tokens::{Audience, TokenSigner};
+use crate::{AppState, SharedState};
+
+/// 封存附件在物件儲存中的路徑前綴
+pub const ATTACHMENT_PREFIX: &str = "attachments/";
+/// 永久連結的簽章期限（9999-12-31）
+const PERMANENT_EXPIRES_AT: i64 = 253_402_300_799;
+/// 回覆中顯示的 SHA-256 長度
+const DIGEST_CHARS: usize = 12;
+/// 物件 key 中隨機字串的位元組數；公開連結不帶簽章，只靠 key 無法被猜出
+const NONCE_BYTES: usize = 8;
+
+/// 永久連結的產生方式
+pub enum AttachmentLinks {
+    /// 物件可直接公開存取（公開 bucket、CDN 或 WebDAV 分享），連結為此前綴加上物件 key
+    Public(String),
+    /// 由 Bridge 的 `/attachments/*key` 提供，網址附上不會過期的簽章
+    Signed { base_url: String, signing_key: String },
+}
+
+impl AttachmentLinks {
+    fn link(&self, key: &str, clock: Arc<dyn Clock>) -> String {
+        match self {
+            AttachmentLinks::Public(base_url) => format!("{}/{}", base_url, uri_encode(key, false)),
+            AttachmentLinks::Signed { base_url, signing_key } => {
+                let token = TokenSigner::new(signing_key, clock).issue(Audience::Attachment, key, PERMANENT_EXPIRES_AT);
+                format!("{}/{}?token={}", base_url, uri_encode(key, false), token)
+            }
+        }
+    }
+}
+
+/// 封存下載的內容，回覆永久連結與附件資訊
+pub async fn archive(state: &AppState, links: &AttachmentLinks, locale: Locale, content: Content) -> Result<Handled, String> {
+    let key = object_key(&content, state.clock.now(), &nonce());
+    let name = key.rsplit('/').next().unwrap_or_default().to_string();
+    let details = details(&content);
+    let bytes = content.data.len();
+    state.blobs.put(&key, content.data).await?;
+    let link = links.link(&key, state.clock.clone());
+    let reply = state.i18n.text(locale, "attachment.archived", &[("name", &name), ("link", &link), ("details", &details)]);
+    Ok(Handled { detail: format!("archived key={} bytes={}", key, bytes), reference: key, reply: Some(reply) })
+}
+
+/// 物件 key 的隨機字串（16 字元十六進位），取自作業系統亂數
+fn nonce() -> String {
+    let mut bytes = [0u8; NONCE_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// 物件 key：`attachments/<年-月>/<訊息 ID>-<隨機字串>.<副檔名>`；有原始檔名的檔案保留檔名，放在 `<訊息 ID>-<隨機字串>` 目錄下。
+/// 訊息 ID 是遞增的數字，加上隨機字串避免公開連結被依序列舉
+fn object_key(content: &Content, now: DateTime<Utc>, nonce: &str) -> String {
+    let month = now.format("%Y-%m");
+    let file_name = content
+        .file_name
+        .as_deref()
+        .map(|name| {
+            name.chars()
+                .map(|c| if c.is_control() || matches!(c, '/' | '\\' | '?' | '#' | '%') { '_' } else { c })
+                .collect::<String>()
+                .trim()
+                .trim_start_matches('.')
+                .to_string()
+        })
+        .filter(|name| !name.is_empty());
+    match file_name {
+        Some(name) => format!("{}{}/{}-{}/{}", ATTACHMENT_PREFIX, month, content.message_id, nonce, name),
+        None => format!("{}{}/{}-{}.{}", ATTACHMENT_PREFIX, month, content.message_id, nonce, content.extension()),
+    }
+}
+
+/// 附件資訊：格式、大小、圖片尺寸、長度與 SHA-256 開頭，以「·」分隔
+fn details(content: &Content) -> String {
+    let mut parts = vec![content.content_type.clone().unwrap_or_else(|| content.kind.name().to_string()), size(content.data.len())];
+    if let Some((width, height)) = image_dimensions(&content.data) {
+        parts.push(format!("{}×{}", width, height));
+    }
+    if let Some(duration) = content.duration_ms {
+        let seconds = duration / 1000;
+        parts.push(format!("{}:{:02}", seconds / 60, seconds % 60));
+    }
+    let digest = hex::encode(Sha256::digest(&content.data));
+    parts.push(format!("SHA-256 {}…", &digest[..DIGEST_CHARS]));
+    parts.join(" · ")
+}
+
+fn size(bytes: usize) -> String {
+    match bytes {
+        bytes if bytes >= 1024 * 1024 => format!("{:.1} MB", bytes as f64 / 1024.0 / 1024.0),
+        bytes if bytes >= 1024 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        bytes => format!("{} B", bytes),
+    }
+}
+
+/// 由檔頭讀出 PNG、GIF、JPEG、WebP 的寬高；其他格式或檔頭不完整時回傳 None
+fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |at: usize| Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le16 = |at: usize| Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le24 = |at: usize| data.get(at..at + 3).map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16);
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+        return Some((width, height));
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some((le16(6)?, le16(8)?));
+    }
+    if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        return match data.get(12..16)? {
+            b"VP8 " => Some((le16(26)? & 0x3FFF, le16(28)? & 0x3FFF)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(data.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            _ => None,
+        };
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        // 依序走過各區段，SOF（0xC0–0xCF，不含 DHT、JPG、DAC）記有寬高
+        let mut at = 2;
+        while *data.get(at)? == 0xFF {
+            let marker = *data.get(at + 1)?;
+            if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                return Some((be16(at + 7)?, be16(at + 5)?));
+            }
+            at += 2 + be16(at + 2)? as usize;
+        }
+    }
+    None
+}
+
+/// 永久連結參數
+#[derive(Deserialize)]
+pub struct LinkParams {
+    token: String,
+}
+
+/// 以永久簽章連結提供封存的附件（`ATTACHMENT_LINK_BASE_URL` 未設定時使用）
+pub async fn serve(
+    State(state): State<SharedState>,
+    Path(path): Path<String>,
+    Query(params): Query<LinkParams>,
+) -> Result<([(header::HeaderName, &'static str); 1], Vec<u8>), StatusCode> {
+    let state = state.read().await;
+    let Some(AttachmentLinks::Signed { signing_key, .. }) = &state.config.attachment_archive else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let key = format!("{}{}", ATTACHMENT_PREFIX, path);
+    if TokenSigner::new(signing_key, state.clock.clone()).verify(Audience::Attachment, &key, &params.token).is_err() {
+        warn!("Rejected attachment request {}: invalid token", key);
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let data = state.blobs.get(&key).await.map_err(|e| {
+        warn!("Failed to read attachment {}: {}", key, e);
+        StatusCode::NOT_FOUND
+    })?;
+    Ok(([(header::CONTENT_TYPE, media::content_type(&key))], data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::incoming::Kind;
+    use chrono::TimeZone;
+
+    fn content(file_name: Option<&str>, content_type: &str) -> Content {
+        Content {
+            message_id: "510000000000".to_string(),
+            kind: if file_name.is_some() { Kind::File } else { Kind::Image },
+            content_type: Some(content_type.to_string()),
+            file_name: file_name.map(str::to_string),
+            duration_ms: None,
+            data: Vec::new(),
+        }
+    }
+
+    fn key(file_name: Option<&str>, content_type: &str) -> String {
+        let now = Utc.with_ymd_and_hms(2026, 3, 9, 12, 0, 0).unwrap();
+        object_key(&content(file_name, content_type), now, "0123456789abcdef")
+    }
+
+    #[test]
+    fn object_key_uses_month_message_id_and_nonce() {
+        assert_eq!(key(None, "image/png"), "attachments/2026-03/510000000000-0123456789abcdef.png");
+        assert_eq!(key(Some("報告.pdf"), "application/pdf"), "attachments/2026-03/510000000000-0123456789abcdef/報告.pdf");
+    }
+
+    #[test]
+    fn object_key_neutralizes_path_traversal() {
+        assert_eq!(key(Some("../../etc/passwd"), "text/plain"), "attachments/2026-03/510000000000-0123456789abcdef/_.._etc_passwd");
+        assert_eq!(key(Some("..\\secret?.txt"), "text/plain"), "attachments/2026-03/510000000000-0123456789abcdef/_secret_.txt");
+        assert_eq!(key(Some(" .. "), "text/plain"), "attachments/2026-03/510000000000-0123456789abcdef.bin");
+    }
+
+    #[test]
+    fn nonce_is_random_hex() {
+        let (a, b) = (nonce(), nonce());
+        assert_eq!(a.len(), NONCE_BYTES * 2);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn png_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(image_dimensions(&png), Some((640, 480)));
+        assert_eq!(image_dimensions(&png[..20]), None);
+    }
+
+    #[test]
+    fn gif_dimensions() {
+        assert_eq!(image_dimensions(b"GIF89a\x40\x01\xf0\x00"), Some((320, 240)));
+        assert_eq!(image_dimensions(b"GIF87a\x01"), None);
+    }
+
+    #[test]
+    fn webp_dimensions() {
+        let riff = |chunk: &[u8], payload: &[u8]| [b"RIFF\0\0\0\0WEBP".as_slice(), chunk, payload].concat();
+        // VP8（有損）：關鍵影格的起始碼後為 14 位元寬高
+        let lossy = riff(b"VP8 ", b"\0\0\0\0\0\0\0\x9d\x01\x2a\x80\x02\xe0\x01");
+        assert_eq!(image_dimensions(&lossy), Some((640, 480)));
+        // VP8L（無損）：簽章 0x2f 後為寬高減一
+        let bits: u32 = (640 - 1) | ((480 - 1) << 14);
+        let lossless = riff(b"VP8L", &[&[0, 0, 0, 0, 0x2f][..], &bits.to_le_bytes()].concat());
+        assert_eq!(image_dimensions(&lossless), Some((640, 480)));
+        // VP8X（延伸）：24 位元的寬高減一
+        let extended = riff(b"VP8X", b"\0\0\0\0\0\0\0\0\x7f\x02\0\xdf\x01\0");
+        assert_eq!(image_dimensions(&extended), Some((640, 480)));
+        assert_eq!(image_dimensions(&riff(b"ALPH", b"")), None);
+    }
+
+    #[test]
+    fn jpeg_dimensions_skip_segments_before_sof() {
+        let jpeg = [
+            &[0xFF, 0xD8][..],
+            // APP0（長度 16）與 DHT（0xC4，不是 SOF）
+            &[0xFF, 0xE0, 0x00, 0x10],
+            &[0; 14],
+            &[0xFF, 0xC4, 0x00, 0x04, 0x00, 0x00],
+            // SOF0：精度、高、寬
+            &[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0xE0, 0x02, 0x80],
+        ]
+        .concat();
+        assert_eq!(image_dimensions(&jpeg), Some((640, 480)));
+        assert_eq!(image_dimensions(&jpeg[..30]), None);
+    }
+
+    #[test]
+    fn unknown_format_has_no_dimensions() {
+        assert_eq!(image_dimensions(b"%PDF-1.7"), None);
+        assert_eq!(image_dimensions(b""), None);
+    }
+}
@@ -0,0 +1,453 @@
+//! 自動化排程模組
+//! 管理員以 `/automation <描述>` 用一句話建立排程（如「每天早上八點推送天氣給我」），由 OpenClaw 以結構化輸出
+//! 轉為規則草稿，經 postback 確認後啟用；背景每分鐘檢查，到了當地時間就推送到建立時所在的聊天
+
+use chrono::{Datelike, Utc};
+use chrono_tz::Tz;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::briefing;
+use crate::delivery;
+use crate::i18n::Locale;
+use crate::line::{Action, OutgoingMessage, Template, TemplateMessage, TextMessage};
+use crate::privacy;
+use crate::quota;
+use crate::store::Automation;
+use crate::{AppState, SharedState};
+
+/// postback 資料前綴
+const POSTBACK_PREFIX: &str = "automation:";
+/// 檢查是否該執行的間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// 同時執行的自動化數量上限
+const CONCURRENCY: usize = 4;
+/// 內容（提示或固定文字）的長度上限
+const MAX_CONTENT_CHARS: usize = 1000;
+
+/// 管理員對草稿的操作
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    Confirm(i64),
+    Cancel(i64),
+}
+
+/// OpenClaw 產生的規則草稿
+#[derive(Debug, Deserialize)]
+struct Draft {
+    /// 描述不是定期排程（或無法理解）時為 false
+    understood: bool,
+    name: String,
+    time: String,
+    weekdays: Vec<u32>,
+    kind: String,
+    content: String,
+}
+
+/// 處理 `/automation [list|delete <id>|描述]`（僅限管理員）
+pub async fn handle_command(state: &AppState, chat_id: &str, user_id: &str, locale: Locale, args: Vec<String>) -> Vec<OutgoingMessage> {
+    let text = |text: String| vec![TextMessage::new(text).into()];
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    if !state.flags.is_enabled("automation_command", Some(user_id), true) {
+        return text(t("command.unavailable"));
+    }
+    if state.config.admin_line_user_id.as_deref() != Some(user_id) {
+        warn!("Non-admin user {} tried to configure an automation", privacy::id(user_id));
+        return text(t("automation.admin_only"));
+    }
+
+    let subcommand = args.first().map(|a| a.to_lowercase());
+    match (subcommand.as_deref(), args.get(1)) {
+        (None | Some("list"), None) => text(list(state, user_id, locale)),
+        (Some("delete"), Some(id)) => match id.trim_start_matches('#').parse() {
+            Ok(id) => text(delete(state, user_id, locale, id)),
+            Err(_) => text(t("automation.usage")),
+        },
+        _ => create(state, chat_id, user_id, locale, &args.join(" ")).await,
+    }
+}
+
+/// 列出已啟用的自動化
+fn list(state: &AppState, user_id: &str, locale: Locale) -> String {
+    let automations = match state.store.automations_for(user_id) {
+        Ok(automations) => automations,
+        Err(e) => {
+            error!("Failed to list automations: {}", e);
+            return state.i18n.text(locale, "automation.error", &[]);
+        }
+    };
+    let usage = state.i18n.text(locale, "automation.usage", &[]);
+    if automations.is_empty() {
+        return format!("{}\n\n{}", state.i18n.text(locale, "automation.list_empty", &[]), usage);
+    }
+    let rows: Vec<String> = automations
+        .iter()
+        .map(|automation| {
+            state.i18n.text(locale, "automation.list_row", &[
+                ("id", &automation.id.to_string()),
+                ("name", &automation.name),
+                ("schedule", &schedule(state, locale, automation)),
+            ])
+        })
+        .collect();
+    format!("{}\n{}\n\n{}", state.i18n.text(locale, "automation.list_header", &[]), rows.join("\n"), usage)
+}
+
+/// 停用自己建立的自動化
+fn delete(state: &AppState, user_id: &str, locale: Locale, id: i64) -> String {
+    let t = |key: &str| state.i18n.text(locale, key, &[("id", &id.to_string())]);
+    match state.store.get_automation(id) {
+        Ok(Some(automation)) if automation.user_id == user_id => {}
+        Ok(_) => return t("automation.not_found"),
+        Err(e) => {
+            error!("Failed to load automation {}: {}", id, e);
+            return t("automation.error");
+        }
+    }
+    match state.store.transition_automation(id, "active", "cancelled") {
+        Ok(true) => {
+            info!("Automation {} deleted by {}", id, privacy::id(user_id));
+            t("automation.deleted")
+        }
+        Ok(false) => t("automation.not_found"),
+        Err(e) => {
+            error!("Failed to delete automation {}: {}", id, e);
+            t("automation.error")
+        }
+    }
+}
+
+/// 由描述產生草稿並請管理員確認
+async fn create(state: &AppState, chat_id: &str, user_id: &str, locale: Locale, description: &str) -> Vec<OutgoingMessage> {
+    let text = |key: &str| vec![TextMessage::new(state.i18n.text(locale, key, &[])).into()];
+    // 沿用管理員簡報設定的時區
+    let timezone = match state.store.get_briefing(user_id) {
+        Ok(settings) => settings.map(|s| s.timezone).unwrap_or_else(|| briefing::DEFAULT_TIMEZONE.to_string()),
+        Err(e) => {
+            error!("Failed to load briefing settings: {}", e);
+            briefing::DEFAULT_TIMEZONE.to_string()
+        }
+    };
+    let draft = match generate(state, user_id, locale, &timezone, description).await {
+        Ok(Some(draft)) => draft,
+        Ok(None) => return text("automation.not_understood"),
+        Err(e) => {
+            warn!("Automation draft generation failed: {}", e);
+            return text("automation.error");
+        }
+    };
+
+    let mut automation = Automation {
+        id: 0,
+        user_id: user_id.to_string(),
+        target: chat_id.to_string(),
+        name: draft.name,
+        time: draft.time,
+        weekdays: draft.weekdays,
+        timezone,
+        kind: draft.kind,
+        content: draft.content,
+        status: "draft".to_string(),
+        last_run_on: None,
+        created_at: 0,
+    };
+    automation.id = match state.store.create_automation(&automation) {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Failed to save automation draft: {}", e);
+            return text("automation.error");
+        }
+    };
+    draft_messages(state, locale, &automation)
+}
+
+/// 以結構化輸出將描述轉為草稿並驗證；不是定期排程時回傳 None
+async fn generate(state: &AppState, user_id: &str, locale: Locale, timezone: &str, description: &str) -> Result<Option<Draft>, String> {
+    let tz: Tz = timezone.parse().unwrap_or(chrono_tz::Asia::Taipei);
+    let now = state.clock.now().with_timezone(&tz);
+    let instructions = format!(
+        "You turn a LINE bot administrator's request into a daily or weekly scheduled automation. \
+         It is now {} ({}) in {}. `time` is the local 24-hour time as HH:MM. \
+         `weekdays` lists ISO weekday numbers (1 = Monday … 7 = Sunday) and is empty for every day. \
+         Use kind `prompt` when the content must be generated at run time (weather, news, tips, summaries); \
+         `content` is then the instruction for the assistant. Use kind `message` for fixed reminders; \
+         `content` is then the exact text to send. `name` is a short title in {}. \
+         Set `understood` to false when the request is not a recurring schedule. Reply with JSON only.",
+        now.format("%Y-%m-%d %H:%M"),
+        now.format("%A"),
+        timezone,
+        locale.language_name()
+    );
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "understood": { "type": "boolean" },
+            "name": { "type": "string" },
+            "time": { "type": "string" },
+            "weekdays": { "type": "array", "items": { "type": "integer" } },
+            "kind": { "type": "string", "enum": ["prompt", "message"] },
+            "content": { "type": "string" }
+        },
+        "required": ["understood", "name", "time", "weekdays", "kind", "content"],
+        "additionalProperties": false
+    });
+
+    let content = state
+        .openclaw_client
+        .send_structured(user_id, &instructions, description, "automation", schema)
+        .await?;
+    let mut draft: Draft = serde_json::from_str(&content).map_err(|e| format!("自動化格式錯誤: {}", e))?;
+    let content_chars = draft.content.trim().chars().count();
+    if !draft.understood || draft.name.trim().is_empty() || !(1..=MAX_CONTENT_CHARS).contains(&content_chars) {
+        return Ok(None);
+    }
+    if !matches!(draft.kind.as_str(), "prompt" | "message") {
+        return Err(format!("未知的自動化類型：{}", draft.kind));
+    }
+    draft.time = briefing::parse_time(&draft.time).ok_or_else(|| format!("時間格式錯誤：{}", draft.time))?;
+    draft.weekdays.retain(|day| (1..=7).contains(day));
+    draft.weekdays.sort_unstable();
+    draft.weekdays.dedup();
+    if draft.weekdays.len() == 7 {
+        draft.weekdays.clear();
+    }
+    draft.name = draft.name.trim().to_string();
+    draft.content = draft.content.trim().to_string();
+    Ok(Some(draft))
+}
+
+/// 草稿內容與確認/取消按鈕
+fn draft_messages(state: &AppState, locale: Locale, automation: &Automation) -> Vec<OutgoingMessage> {
+    let id = automation.id.to_string();
+    let args = [("id", id.as_str())];
+    let kind = state.i18n.text(locale, &format!("automation.kind_{}", automation.kind), &[]);
+    let details = state.i18n.text(locale, "automation.draft", &[
+        ("id", &id),
+        ("name", &automation.name),
+        ("schedule", &schedule(state, locale, automation)),
+        ("kind", &kind),
+        ("content", &automation.content),
+    ]);
+    vec![
+        TextMessage::new(details).into(),
+        TemplateMessage::new(
+            state.i18n.text(locale, "automation.confirm_alt", &args),
+            Template::Confirm {
+                text: state.i18n.text(locale, "automation.confirm_text", &args),
+                actions: vec![
+                    Action::postback(
+                        state.i18n.text(locale, "automation.confirm_label", &[]),
+                        format!("{}confirm:{}", POSTBACK_PREFIX, automation.id),
+                    ),
+                    Action::postback(
+                        state.i18n.text(locale, "automation.cancel_label", &[]),
+                        format!("{}cancel:{}", POSTBACK_PREFIX, automation.id),
+                    ),
+                ],
+            },
+        )
+        .into(),
+    ]
+}
+
+/// 執行時間的描述（如「每週一、三 08:00（Asia/Taipei）」）
+fn schedule(state: &AppState, locale: Locale, automation: &Automation) -> String {
+    let names = state.i18n.text(locale, "automation.weekdays", &[]);
+    let names: Vec<&str> = names.split(',').collect();
+    let days = if automation.weekdays.is_empty() {
+        state.i18n.text(locale, "automation.every_day", &[])
+    } else {
+        let days: Vec<&str> = automation
+            .weekdays
+            .iter()
+            .filter_map(|day| names.get(*day as usize - 1).copied())
+            .collect();
+        let separator = state.i18n.text(locale, "automation.day_separator", &[]);
+        state.i18n.text(locale, "automation.on_days", &[("days", &days.join(&separator))])
+    };
+    state.i18n.text(locale, "automation.schedule", &[
+        ("days", &days),
+        ("time", &automation.time),
+        ("timezone", &automation.timezone),
+    ])
+}
+
+/// 解析自動化相關的 postback 資料
+pub fn parse_postback(data: &str) -> Option<Decision> {
+    let (action, id) = data.strip_prefix(POSTBACK_PREFIX)?.split_once(':')?;
+    let id = id.parse().ok()?;
+    match action {
+        "confirm" => Some(Decision::Confirm(id)),
+        "cancel" => Some(Decision::Cancel(id)),
+        _ => None,
+    }
+}
+
+/// 處理管理員的確認/取消，回傳要回覆的文字
+pub fn handle_decision(state: &AppState, user_id: &str, locale: Locale, decision: Decision) -> String {
+    let (Decision::Confirm(id) | Decision::Cancel(id)) = decision;
+    let t = |key: &str| state.i18n.text(locale, key, &[("id", &id.to_string())]);
+    if state.config.admin_line_user_id.as_deref() != Some(user_id) {
+        warn!("Non-admin user {} tried to act on an automation", privacy::id(user_id));
+        return t("automation.admin_only");
+    }
+
+    let (status, key) = match decision {
+        Decision::Confirm(_) => ("active", "automation.activated"),
+        Decision::Cancel(_) => ("cancelled", "automation.cancelled"),
+    };
+    match state.store.transition_automation(id, "draft", status) {
+        Ok(true) => {
+            info!("Automation {} {} by {}", id, status, privacy::id(user_id));
+            t(key)
+        }
+        Ok(false) => t("automation.already_handled"),
+        Err(e) => {
+            error!("Failed to update automation {}: {}", id, e);
+            t("automation.error")
+        }
+    }
+}
+
+/// 在背景每分鐘檢查，到了自動化的當地時間就執行並推送；OpenClaw 在狀態鎖外呼叫，同時最多執行 [`CONCURRENCY`] 個
+pub fn spawn(state: SharedState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let due = {
+                let state = state.read().await;
+                // 與簡報相同，延後送出便失去意義，額度用完時直接略過
+                if quota::exhausted(&state).is_some() {
+                    continue;
+                }
+                let automations = match state.store.active_automations() {
+                    Ok(automations) => automations,
+                    Err(e) => {
+                        error!("Failed to load automations: {}", e);
+                        continue;
+                    }
+                };
+                let now = state.clock.now();
+                let mut due = Vec::new();
+                for automation in automations {
+                    let Some(today) = due_date(&automation, now) else { continue };
+                    // 先記錄再執行，避免失敗時每分鐘重試造成重複推送
+                    if let Err(e) = state.store.mark_automation_run(automation.id, &today) {
+                        error!("Failed to mark automation {} as run: {}", automation.id, e);
+                        continue;
+                    }
+                    due.push(automation);
+                }
+                due
+            };
+            futures::stream::iter(due)
+                .for_each_concurrent(CONCURRENCY, |automation| {
+                    let state = state.clone();
+                    async move { run(&state, &automation).await }
+                })
+                .await;
+        }
+    });
+}
+
+/// 今天（且為指定的星期）該執行時回傳當地日期
+fn due_date(automation: &Automation, now: chrono::DateTime<Utc>) -> Option<String> {
+    let today = briefing::due(&automation.time, &automation.timezone, automation.last_run_on.as_deref(), now)?;
+    let weekday = today.weekday().number_from_monday();
+    (automation.weekdays.is_empty() || automation.weekdays.contains(&weekday)).then(|| today.to_string())
+}
+
+/// 執行一次：固定文字直接推送，提示則先交由 OpenClaw 產生內容（產生期間不持有狀態鎖）
+async fn run(state: &SharedState, automation: &Automation) {
+    let text = match automation.kind.as_str() {
+        "message" => automation.content.clone(),
+        _ => {
+            let (client, locale) = {
+                let state = state.read().await;
+                (state.openclaw_client.clone(), state.i18n.locale_for(&state.line_client, &automation.user_id).await)
+            };
+            let instructions = format!(
+                "You run a scheduled automation named \"{}\" for a LINE chat. Follow the instruction below and reply in {} \
+                 as plain text (no markdown) under 1000 characters.",
+                automation.name,
+                locale.language_name()
+            );
+            match client.send_with_instructions(&automation.user_id, &instructions, &automation.content).await {
+                Ok(text) if !text.trim().is_empty() => text,
+                Ok(_) => {
+                    warn!("Automation {} produced an empty answer", automation.id);
+                    return;
+                }
+                Err(e) => {
+                    warn!("Automation {} failed: {}", automation.id, e);
+                    return;
+                }
+            }
+        }
+    };
+    let state = state.read().await;
+    match delivery::push(&state, &automation.target, &[TextMessage::new(text).into()]).await {
+        Ok(()) => info!("Automation {} sent to {}", automation.id, privacy::id(&automation.target)),
+        Err(e) => warn!("Failed to push automation {} to {}: {}", automation.id, privacy::id(&automation.target), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn automation(time: &str, weekdays: Vec<u32>, last_run_on: Option<&str>) -> Automation {
+        Automation {
+            id: 1,
+            user_id: "U1".to_string(),
+            target: "U1".to_string(),
+            name: "天氣".to_string(),
+            time: time.to_string(),
+            weekdays,
+            timezone: "Asia/Taipei".to_string(),
+            kind: "prompt".to_string(),
+            content: "今天的天氣".to_string(),
+            status: "active".to_string(),
+            last_run_on: last_run_on.map(str::to_string),
+            created_at: 0,
+        }
+    }
+
+    /// 台北時間 2024-06-03（星期一）的指定時刻
+    fn monday_at(hour: u32, minute: u32) -> chrono::DateTime<Utc> {
+        chrono_tz::Asia::Taipei.with_ymd_and_hms(2024, 6, 3, hour, minute, 0).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn due_once_per_local_day_after_the_time() {
+        assert_eq!(due_date(&automation("08:00", vec![], None), monday_at(7, 59)), None);
+        assert_eq!(due_date(&automation("08:00", vec![], None), monday_at(8, 0)).as_deref(), Some("2024-06-03"));
+        assert_eq!(due_date(&automation("08:00", vec![], Some("2024-06-03")), monday_at(9, 0)), None);
+        assert_eq!(due_date(&automation("08:00", vec![], Some("2024-06-02")), monday_at(9, 0)).as_deref(), Some("2024-06-03"));
+    }
+
+    #[test]
+    fn due_only_on_listed_weekdays() {
+        assert!(due_date(&automation("08:00", vec![1, 3], None), monday_at(8, 30)).is_some());
+        assert!(due_date(&automation("08:00", vec![2, 7], None), monday_at(8, 30)).is_none());
+        // UTC 仍是星期日，但以當地日期判斷星期
+        let early = monday_at(0, 30);
+        assert_eq!(early.weekday(), chrono::Weekday::Sun);
+        assert!(due_date(&automation("00:15", vec![1], None), early).is_some());
+    }
+
+    #[test]
+    fn parses_only_well_formed_postbacks() {
+        assert_eq!(parse_postback("automation:confirm:12"), Some(Decision::Confirm(12)));
+        assert_eq!(parse_postback("automation:cancel:3"), Some(Decision::Cancel(3)));
+        assert_eq!(parse_postback("automation:delete:3"), None);
+        assert_eq!(parse_postback("automation:confirm:abc"), None);
+        assert_eq!(parse_postback("automation:confirm"), None);
+        assert_eq!(parse_postback("poll:confirm:12"), None);
+    }
+}
@@ -0,0 +1,411 @@
+//! 模擬 LINE Messaging API 伺服器
+//! 接受 reply/push/narrowcast/profile/group/content/transcoding 等請求並記錄下來供檢查，另可產生帶簽章的 webhook 事件送往 Bridge，方便在本機離線跑完整流程
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+/// 1×1 透明 PNG（content API 的預設回應）
+const PLACEHOLDER_PNG: &[u8] = &[
+    0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1F, 0x15, 0xC4, 0x89, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x44, 0x41,
+    0x54, 0x78, 0x9C, 0x63, 0x00, 0x01, 0x00, 0x00, 0x05, 0x00, 0x01, 0x0D, 0x0A, 0x2D, 0xB4, 0x00, 0x00, 0x00, 0x00, 0x49,
+    0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+];
+
+struct MockState {
+    /// 簽章 webhook 用的 channel secret（須與 Bridge 的 LINE_CHANNEL_SECRET 相同）
+    channel_secret: String,
+    /// Bridge 的 webhook 網址
+    webhook_url: String,
+    /// 個人資料回傳的語言
+    language: String,
+    http: reqwest::Client,
+    /// 每月可推送的次數（push、multicast、broadcast 合計）；超過後回傳 429 monthly limit，None 代表不限
+    monthly_quota: Option<usize>,
+    requests: Mutex<Vec<RecordedRequest>>,
+    next_id: AtomicU64,
+}
+
+/// 收到的 API 請求
+#[derive(Clone, Serialize)]
+struct RecordedRequest {
+    id: u64,
+    method: String,
+    path: String,
+    body: Value,
+    /// `X-Line-Retry-Key` 標頭
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_key: Option<String>,
+    received_at: String,
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env().add_directive("mock_line=debug".parse().unwrap()))
+        .init();
+
+    let var = |key: &str, default: &str| std::env::var(key).ok().filter(|v| !v.is_empty()).unwrap_or_else(|| default.to_string());
+    let port = var("MOCK_LINE_PORT", "18790");
+    let state = Arc::new(MockState {
+        channel_secret: std::env::var("MOCK_LINE_CHANNEL_SECRET")
+            .or_else(|_| std::env::var("LINE_CHANNEL_SECRET"))
+            .expect("請設定 MOCK_LINE_CHANNEL_SECRET 或 LINE_CHANNEL_SECRET"),
+        webhook_url: var("MOCK_LINE_WEBHOOK_URL", "http://127.0.0.1:3000/callback"),
+        language: var("MOCK_LINE_LANGUAGE", "zh-TW"),
+        http: reqwest::Client::new(),
+        monthly_quota: std::env::var("MOCK_LINE_MONTHLY_QUOTA").ok().and_then(|v| v.parse().ok()),
+        requests: Mutex::new(Vec::new()),
+        next_id: AtomicU64::new(1),
+    });
+
+    let app = Router::new()
+        .route("/v2/bot/profile/:user_id", get(profile))
+        .route("/v2/bot/group/:group_id/summary", get(group_summary))
+        .route("/v2/bot/group/:group_id/members/count", get(group_member_count))
+        .route("/v2/bot/group/:group_id/member/:user_id", get(group_member_profile))
+        .route("/v2/bot/room/:room_id/member/:user_id", get(room_member_profile))
+        .route("/v2/bot/message/:message_id/content", get(content))
+        .route("/v2/bot/message/:message_id/content/transcoding", get(transcoding))
+        .route("/v2/bot/message/narrowcast", axum::routing::post(narrowcast))
+        .route("/v2/bot/message/progress/narrowcast", get(narrowcast_progress))
+        .route("/mock/requests", get(list_requests).delete(clear_requests))
+        .route("/mock/webhook", axum::routing::post(emit_webhook))
+        .fallback(record)
+        .with_state(state.clone());
+
+    let addr = format!("127.0.0.1:{}", port);
+    info!("🧪 Mock LINE API 監聽: http://{}（LINE_API_BASE_URL=http://{}）", addr, addr);
+    info!("📌 Webhook 事件將送往 {}", state.webhook_url);
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    axum::serve(listener, app).await.unwrap();
+}
+
+/// 是否帶有 Bearer token（與 LINE 相同，缺少時回傳 401）
+fn authorized(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|token| !token.is_empty())
+}
+
+fn unauthorized() -> Response {
+    (StatusCode::UNAUTHORIZED, Json(json!({ "message": "Authentication failed" }))).into_response()
+}
+
+impl MockState {
+    fn record(&self, method: &Method, path: &str, body: Value) -> u64 {
+        self.record_with_retry_key(method, path, body, None)
+    }
+
+    fn record_with_retry_key(&self, method: &Method, path: &str, body: Value, retry_key: Option<String>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        info!("#{} {} {}", id, method, path);
+        self.requests.lock().unwrap().push(RecordedRequest {
+            id,
+            method: method.to_string(),
+            path: path.to_string(),
+            body,
+            retry_key,
+            received_at: chrono::Utc::now().to_rfc3339(),
+        });
+        id
+    }
+}
+
+/// 其他 API（reply、push、multicast、broadcast…）一律記錄並回傳成功；
+/// 同一路徑重複使用已接受過的 `X-Line-Retry-Key` 時與 LINE 相同回傳 409 與原請求的 ID，
+/// `expired` 開頭的 reply token 模擬過期回傳 400
+async fn record(State(state): State<Arc<MockState>>, request: Request) -> Response {
+    if !authorized(request.headers()) {
+        return unauthorized();
+    }
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let retry_key = request.headers().get("x-line-retry-key").and_then(|v| v.to_str().ok()).map(str::to_string);
+    if let Some(key) = &retry_key {
+        let accepted = state.requests.lock().unwrap().iter().find(|r| r.path == path && r.retry_key.as_ref() == Some(key)).map(|r| r.id);
+        if let Some(id) = accepted {
+            warn!("{} {} rejected: retry key {} already accepted", method, path, key);
+            return (
+                StatusCode::CONFLICT,
+                [("x-line-accepted-request-id", format!("mock-{}", id))],
+                Json(json!({ "message": "The retry key is already accepted" })),
+            )
+                .into_response();
+        }
+    }
+    let body = axum::body::to_bytes(request.into_body(), usize::MAX).await.unwrap_or_default();
+    let body = serde_json::from_slice(&body).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned()));
+    if path == "/v2/bot/message/reply" && body["replyToken"].as_str().is_some_and(|token| token.starts_with("expired")) {
+        warn!("{} {} rejected: reply token expired", method, path);
+        return (StatusCode::BAD_REQUEST, Json(json!({ "message": "Invalid reply token" }))).into_response();
+    }
+    if is_push(&path) && state.monthly_quota.is_some_and(|quota| state.requests.lock().unwrap().iter().filter(|r| is_push(&r.path)).count() >= quota) {
+        warn!("{} {} rejected: monthly quota reached", method, path);
+        return (StatusCode::TOO_MANY_REQUESTS, Json(json!({ "message": "You have reached your monthly limit." }))).into_response();
+    }
+    state.record_with_retry_key(&method, &path, body, retry_key);
+    Json(json!({})).into_response()
+}
+
+async fn profile(State(state): State<Arc<MockState>>, headers: HeaderMap, Path(user_id): Path<String>) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    state.record(&Method::GET, &format!("/v2/bot/profile/{}", user_id), Value::Null);
+    // 模擬未加好友的群組成員：只能由成員個人資料取得
+    if user_id.starts_with("Ustranger") {
+        return (StatusCode::NOT_FOUND, Json(json!({ "message": "Not found" }))).into_response();
+    }
+    Json(json!({
+        "userId": user_id,
+        "displayName": format!("Mock {}", user_id),
+        "language": state.language,
+        "pictureUrl": format!("https://profile.line-scdn.net/mock/{}", user_id),
+        "statusMessage": "Hello from mock-line",
+    }))
+    .into_response()
+}
+
+async fn group_summary(State(state): State<Arc<MockState>>, headers: HeaderMap, Path(group_id): Path<String>) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    state.record(&Method::GET, &format!("/v2/bot/group/{}/summary", group_id), Value::Null);
+    Json(json!({
+        "groupId": group_id,
+        "groupName": format!("Mock {}", group_id),
+        "pictureUrl": format!("https://profile.line-scdn.net/mock/{}", group_id),
+    }))
+    .into_response()
+}
+
+async fn group_member_count(State(state): State<Arc<MockState>>, headers: HeaderMap, Path(group_id): Path<String>) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    state.record(&Method::GET, &format!("/v2/bot/group/{}/members/count", group_id), Value::Null);
+    Json(json!({ "count": 3 })).into_response()
+}
+
+async fn group_member_profile(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Path((group_id, user_id)): Path<(String, String)>,
+) -> Response {
+    member_profile(&state, &headers, &format!("/v2/bot/group/{}/member/{}", group_id, user_id), &user_id)
+}
+
+async fn room_member_profile(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Path((room_id, user_id)): Path<(String, String)>,
+) -> Response {
+    member_profile(&state, &headers, &format!("/v2/bot/room/{}/member/{}", room_id, user_id), &user_id)
+}
+
+/// 群組與聊天室的成員個人資料（沒有語言與狀態消息）
+fn member_profile(state: &MockState, headers: &HeaderMap, path: &str, user_id: &str) -> Response {
+    if !authorized(headers) {
+        return unauthorized();
+    }
+    state.record(&Method::GET, path, Value::Null);
+    Json(json!({
+        "userId": user_id,
+        "displayName": format!("Member {}", user_id),
+        "pictureUrl": format!("https://profile.line-scdn.net/mock/{}", user_id),
+    }))
+    .into_response()
+}
+
+/// 計入每月額度的推送類 API
+fn is_push(path: &str) -> bool {
+    matches!(
+        path,
+        "/v2/bot/message/push" | "/v2/bot/message/multicast" | "/v2/bot/message/broadcast" | "/v2/bot/message/narrowcast"
+    )
+}
+
+async fn content(State(state): State<Arc<MockState>>, headers: HeaderMap, Path(message_id): Path<String>) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    state.record(&Method::GET, &format!("/v2/bot/message/{}/content", message_id), Value::Null);
+    ([(header::CONTENT_TYPE, "image/png")], PLACEHOLDER_PNG).into_response()
+}
+
+/// 轉檔狀態：每則訊息第一次查詢回傳 processing，之後回傳 succeeded（用來測試輪詢）
+async fn transcoding(State(state): State<Arc<MockState>>, headers: HeaderMap, Path(message_id): Path<String>) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let path = format!("/v2/bot/message/{}/content/transcoding", message_id);
+    let polled = state.requests.lock().unwrap().iter().any(|r| r.path == path);
+    state.record(&Method::GET, &path, Value::Null);
+    let status = if polled { "succeeded" } else { "processing" };
+    Json(json!({ "status": status })).into_response()
+}
+
+/// 群發：記錄請求並回傳 202 與 `X-Line-Request-Id`
+async fn narrowcast(State(state): State<Arc<MockState>>, headers: HeaderMap, body: Bytes) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let id = state.record(&Method::POST, "/v2/bot/message/narrowcast", body);
+    (StatusCode::ACCEPTED, [("x-line-request-id", format!("mock-{}", id))], Json(json!({}))).into_response()
+}
+
+#[derive(Deserialize)]
+struct ProgressParams {
+    #[serde(rename = "requestId")]
+    request_id: String,
+}
+
+/// 群發進度：每個 request ID 第一次查詢回傳 sending，之後回傳 succeeded（用來測試輪詢）
+async fn narrowcast_progress(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Query(params): Query<ProgressParams>,
+) -> Response {
+    if !authorized(&headers) {
+        return unauthorized();
+    }
+    let known = params
+        .request_id
+        .strip_prefix("mock-")
+        .and_then(|id| id.parse::<u64>().ok())
+        .is_some_and(|id| state.requests.lock().unwrap().iter().any(|r| r.id == id && r.path == "/v2/bot/message/narrowcast"));
+    if !known {
+        return (StatusCode::NOT_FOUND, Json(json!({ "message": "Not found" }))).into_response();
+    }
+    let path = format!("/v2/bot/message/progress/narrowcast?requestId={}", params.request_id);
+    let polled = state.requests.lock().unwrap().iter().any(|r| r.path == path);
+    state.record(&Method::GET, &path, Value::Null);
+    let accepted = chrono::Utc::now().to_rfc3339();
+    if polled {
+        Json(json!({
+            "phase": "succeeded",
+            "successCount": 3,
+            "failureCount": 0,
+            "targetCount": 3,
+            "acceptedTime": accepted,
+            "completedTime": chrono::Utc::now().to_rfc3339(),
+        }))
+        .into_response()
+    } else {
+        Json(json!({ "phase": "sending", "targetCount": 3, "acceptedTime": accepted })).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    /// 只列出路徑以此開頭的請求
+    path: Option<String>,
+}
+
+/// 檢視收到的請求
+async fn list_requests(State(state): State<Arc<MockState>>, Query(params): Query<ListParams>) -> Json<Vec<RecordedRequest>> {
+    let requests = state.requests.lock().unwrap();
+    Json(
+        requests
+            .iter()
+            .filter(|r| params.path.as_deref().is_none_or(|prefix| r.path.starts_with(prefix)))
+            .cloned()
+            .collect(),
+    )
+}
+
+async fn clear_requests(State(state): State<Arc<MockState>>) -> StatusCode {
+    state.requests.lock().unwrap().clear();
+    StatusCode::NO_CONTENT
+}
+
+/// 簡寫的文字訊息事件；也可直接傳入完整的 `{"events": [...]}`
+#[derive(Deserialize)]
+struct TextEvent {
+    text: String,
+    #[serde(default = "default_user_id")]
+    user_id: String,
+    group_id: Option<String>,
+}
+
+fn default_user_id() -> String {
+    "Umock".to_string()
+}
+
+/// 產生帶簽章的 webhook 事件送往 Bridge，回傳 Bridge 的狀態碼與內容
+async fn emit_webhook(State(state): State<Arc<MockState>>, body: Bytes) -> Response {
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("無效的 JSON: {}", e)).into_response(),
+    };
+    let payload = if payload.get("events").is_some() {
+        payload
+    } else {
+        let event: TextEvent = match serde_json::from_value(payload) {
+            Ok(event) => event,
+            Err(e) => return (StatusCode::BAD_REQUEST, format!("需要 events 或 text 欄位: {}", e)).into_response(),
+        };
+        let id = state.next_id.fetch_add(1, Ordering::Relaxed);
+        let source = match &event.group_id {
+            Some(group_id) => json!({ "type": "group", "groupId": group_id, "userId": event.user_id }),
+            None => json!({ "type": "user", "userId": event.user_id }),
+        };
+        json!({
+            "destination": "Umockbot",
+            "events": [{
+                "type": "message",
+                "mode": "active",
+                "timestamp": chrono::Utc::now().timestamp_millis(),
+                "webhookEventId": format!("mock-event-{}", id),
+                "replyToken": format!("mock-reply-{}", id),
+                "source": source,
+                "message": { "type": "text", "id": id.to_string(), "text": event.text }
+            }]
+        })
+    };
+
+    let body = payload.to_string();
+    let mut mac = Hmac::<Sha256>::new_from_slice(state.channel_secret.as_bytes()).expect("HMAC 可接受任意長度金鑰");
+    mac.update(body.as_bytes());
+    let signature = BASE64.encode(mac.finalize().into_bytes());
+
+    match state
+        .http
+        .post(&state.webhook_url)
+        .header("Content-Type", "application/json")
+        .header("X-Line-Signature", signature)
+        .body(body)
+        .send()
+        .await
+    {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let text = response.text().await.unwrap_or_default();
+            info!("Webhook delivered to bridge: {}", status);
+            Json(json!({ "status": status, "body": text, "payload": payload })).into_response()
+        }
+        Err(e) => {
+            warn!("Failed to deliver webhook: {}", e);
+            (StatusCode::BAD_GATEWAY, format!("無法送出 webhook: {}", e)).into_response()
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! 模擬 OpenClaw 伺服器
+//! 提供 `/health`、`/v1/models` 與 `/v1/chat/completions`（含串流），以固定或回聲回應取代真正的 AI，可設定延遲與錯誤注入，方便在本機開發 Bridge
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 模擬設定（皆由環境變數讀取）
+struct MockConfig {
+    /// 固定回應；未設定時回聲最後一則使用者訊息
+    reply: Option<String>,
+    /// 回應前的延遲；串流時為每個 chunk 之間的延遲
+    latency: Duration,
+    /// 每 N 個請求回傳一次錯誤（0 代表不注入）
+    error_every: u64,
+    error_status: StatusCode,
+    /// 訊息數超過此值時回傳 context 超限錯誤（0 代表不限制）
+    context_limit: usize,
+    /// `/v1/models` 回傳的模型；未設定時該端點回傳 404（模擬不支援的伺服器）
+    models: Option<Vec<String>>,
+    requests: AtomicU64,
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    match std::env::var(key) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| panic!("{} 格式錯誤：{}", key, value)),
+        Err(_) => default,
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env().add_directive("mock_openclaw=debug".parse().unwrap()))
+        .init();
+
+    let port: u16 = env_or("MOCK_OPENCLAW_PORT", 18789);
+    let config = Arc::new(MockConfig {
+        reply: std::env::var("MOCK_OPENCLAW_REPLY").ok().filter(|r| !r.is_empty()),
+        latency: Duration::from_millis(env_or("MOCK_OPENCLAW_LATENCY_MS", 0)),
+        error_every: env_or("MOCK_OPENCLAW_ERROR_EVERY", 0),
+        error_status: StatusCode::from_u16(env_or("MOCK_OPENCLAW_ERROR_STATUS", 500)).expect("MOCK_OPENCLAW_ERROR_STATUS 不是有效的狀態碼"),
+        context_limit: env_or("MOCK_OPENCLAW_CONTEXT_LIMIT", 0),
+        models: std::env::var("MOCK_OPENCLAW_MODELS")
+            .ok()
+            .map(|models| models.split(',').map(str::trim).filter(|m| !m.is_empty()).map(str::to_string).collect()),
+        requests: AtomicU64::new(0),
+    });
+
+    let app = Router::new()
+        .route("/health", get(|| async { Json(json!({ "status": "ok" })) }))
+        .route("/v1/models", get(models))
+        .route("/v1/chat/completions", post(chat_completions))
+        .with_state(config);
+
+    let addr = format!("127.0.0.1:{}", port);
+    info!("🧪 Mock OpenClaw 監聽: http://{}（OPENCLAW_BASE_URL=http://{}）", addr, addr);
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    axum::serve(listener, app).await.unwrap();
+}
+
+/// OpenAI 相容的模型清單
+async fn models(State(config): State<Arc<MockConfig>>) -> Response {
+    let Some(models) = &config.models else {
+        return error(StatusCode::NOT_FOUND, "not_found", "Model listing is not enabled (set MOCK_OPENCLAW_MODELS)");
+    };
+    let data: Vec<Value> = models.iter().map(|id| json!({ "id": id, "object": "model", "owned_by": "mock" })).collect();
+    Json(json!({ "object": "list", "data": data })).into_response()
+}
+
+/// OpenAI 相容的 Chat Completions
+async fn chat_completions(State(config): State<Arc<MockConfig>>, Json(request): Json<Value>) -> Response {
+    let count = config.requests.fetch_add(1, Ordering::Relaxed) + 1;
+    let messages = request["messages"].as_array().cloned().unwrap_or_default();
+    let model = request["model"].as_str().unwrap_or("mock").to_string();
+    let stream = request["stream"].as_bool().unwrap_or(false);
+    info!("Request #{}: model={} messages={} stream={}", count, model, messages.len(), stream);
+    if let Some(metadata) = request.get("metadata") {
+        info!("Request #{}: user={} metadata={}", count, request["user"].as_str().unwrap_or("-"), metadata);
+    }
+
+    if config.error_every > 0 && count % config.error_every == 0 {
+        warn!("Injecting {} for request #{}", config.error_status, count);
+        return error(config.error_status, "mock_error", "Injected error from mock OpenClaw");
+    }
+    if config.context_limit > 0 && messages.len() > config.context_limit {
+        warn!("Rejecting request #{} with {} messages as context overflow", count, messages.len());
+        return error(
+            StatusCode::BAD_REQUEST,
+            "context_length_exceeded",
+            "This model's maximum context length was exceeded",
+        );
+    }
+
+    let reply = config.reply.clone().unwrap_or_else(|| {
+        let last = messages
+            .iter()
+            .rev()
+            .find(|m| m["role"] == "user")
+            .and_then(|m| m["content"].as_str())
+            .unwrap_or_default();
+        format!("Echo: {}", last)
+    });
+
+    if stream {
+        let include_usage = request["stream_options"]["include_usage"].as_bool().unwrap_or(false);
+        return stream_reply(config.latency, model, reply, include_usage).into_response();
+    }
+
+    tokio::time::sleep(config.latency).await;
+    Json(json!({
+        "id": format!("mock-{}", count),
+        "object": "chat.completion",
+        "created": chrono::Utc::now().timestamp(),
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": reply },
+            "finish_reason": "stop"
+        }],
+        "usage": { "completion_tokens": reply.split_whitespace().count() }
+    }))
+    .into_response()
+}
+
+/// 以空白切分回應逐段送出，最後附上用量與 `[DONE]`
+fn stream_reply(
+    latency: Duration,
+    model: String,
+    reply: String,
+    include_usage: bool,
+) -> Sse<impl futures::Stream<Item = Result<Event, Infallible>>> {
+    let pieces: Vec<String> = reply.split_inclusive(' ').map(str::to_string).collect();
+    let tokens = pieces.len();
+    let mut events: Vec<String> = pieces
+        .into_iter()
+        .map(|piece| json!({ "model": model, "choices": [{ "index": 0, "delta": { "content": piece } }] }).to_string())
+        .collect();
+    if include_usage {
+        events.push(json!({ "model": model, "choices": [], "usage": { "completion_tokens": tokens } }).to_string());
+    }
+    events.push("[DONE]".to_string());
+
+    let stream = futures::stream::unfold(events.into_iter(), move |mut events| async move {
+        let data = events.next()?;
+        tokio::time::sleep(latency).await;
+        Some((Ok(Event::default().data(data)), events))
+    });
+    Sse::new(stream)
+}
+
+fn error(status: StatusCode, code: &str, message: &str) -> Response {
+    (status, Json(json!({ "error": { "message": message, "code": code } }))).into_response()
+}
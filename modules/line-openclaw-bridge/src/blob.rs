@@ -0,0 +1,367 @@
+//! 物件儲存模組
+//! 封存檔與媒體檔的存放位置（本機目錄、S3 相容儲存或 WebDAV），並產生可公開存取的簽章網址
+
+use reqwest::{Client, Method, Url};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::io::ReaderStream;
+
+use crate::clock::Clock;
+use crate::sigv4;
+use crate::tokens::{Audience, TokenError, TokenSigner};
+
+/// 內部讀寫 S3 時使用的簽章有效期
+const INTERNAL_URL_TTL: Duration = Duration::from_secs(300);
+
+/// 物件儲存後端
+pub enum BlobStore {
+    /// 本機目錄；`public_base_url` 設定時可透過 `/files/*key` 提供簽章網址
+    Local {
+        root: PathBuf,
+        public_base_url: Option<String>,
+        signer: TokenSigner,
+    },
+    /// S3 相容儲存（AWS S3、MinIO、R2…），以 path-style 存取
+    S3(S3Config),
+    /// WebDAV（Nextcloud、Synology…）；公開網址與本機儲存相同由 `/files/*key` 提供
+    WebDav(WebDavConfig),
+}
+
+/// S3 連線設定
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub client: Client,
+}
+
+/// WebDAV 連線設定
+pub struct WebDavConfig {
+    /// 存放物件的集合網址
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub public_base_url: Option<String>,
+    pub signer: TokenSigner,
+    pub client: Client,
+}
+
+impl BlobStore {
+    /// 使用本機目錄作為儲存位置
+    pub fn local(root: impl Into<PathBuf>, public_base_url: Option<String>, signing_key: String, clock: Arc<dyn Clock>) -> Self {
+        BlobStore::Local {
+            root: root.into(),
+            public_base_url: public_base_url.map(|u| u.trim_end_matches('/').to_string()),
+            signer: TokenSigner::new(&signing_key, clock),
+        }
+    }
+
+    /// 使用 S3 相容儲存；`endpoint` 未指定時使用 AWS 區域端點
+    pub fn s3(
+        endpoint: Option<String>,
+        bucket: String,
+        region: String,
+        access_key_id: String,
+        secret_access_key: String,
+    ) -> Self {
+        let endpoint = endpoint
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region))
+            .trim_end_matches('/')
+            .to_string();
+        BlobStore::S3(S3Config {
+            endpoint,
+            bucket,
+            region,
+            access_key_id,
+            secret_access_key,
+            client: Client::new(),
+        })
+    }
+
+    /// 使用 WebDAV 儲存；`username` 設定時以 Basic 認證存取
+    pub fn webdav(
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+        public_base_url: Option<String>,
+        signing_key: String,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        BlobStore::WebDav(WebDavConfig {
+            url: url.trim_end_matches('/').to_string(),
+            username,
+            password,
+            public_base_url: public_base_url.map(|u| u.trim_end_matches('/').to_string()),
+            signer: TokenSigner::new(&signing_key, clock),
+            client: Client::new(),
+        })
+    }
+
+    /// 後端名稱（日誌用）
+    pub fn describe(&self) -> String {
+        match self {
+            BlobStore::Local { root, .. } => format!("local:{}", root.display()),
+            BlobStore::S3(s3) => format!("s3:{}/{}", s3.endpoint, s3.bucket),
+            BlobStore::WebDav(webdav) => format!("webdav:{}", webdav.url),
+        }
+    }
+
+    /// 儲存物件（同名覆蓋）
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        match self {
+            BlobStore::Local { root, .. } => {
+                let path = local_path(root, key)?;
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent)
+                        .await
+                        .map_err(|e| format!("無法建立目錄 {}: {}", parent.display(), e))?;
+                }
+                tokio::fs::write(&path, data)
+                    .await
+                    .map_err(|e| format!("無法寫入 {}: {}", path.display(), e))
+            }
+            BlobStore::S3(s3) => {
+                let url = s3.presign(Method::PUT, key, INTERNAL_URL_TTL)?;
+                s3.client
+                    .put(url)
+                    .body(data)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| format!("S3 上傳 {} 失敗: {}", key, e))?;
+                Ok(())
+            }
+            BlobStore::WebDav(webdav) => {
+                local_path(Path::new(""), key)?;
+                webdav.create_collections(key).await?;
+                webdav
+                    .request(Method::PUT, key)
+                    .body(data)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| format!("WebDAV 上傳 {} 失敗: {}", key, e))?;
+                Ok(())
+            }
+        }
+    }
+
+    /// 以檔案內容儲存物件（同名覆蓋）；S3 與 WebDAV 以串流上傳，不必把整個檔案讀進記憶體
+    pub async fn put_file(&self, key: &str, file: &Path) -> Result<(), String> {
+        let body = || async {
+            let opened = tokio::fs::File::open(file)
+                .await
+                .map_err(|e| format!("無法讀取 {}: {}", file.display(), e))?;
+            let length = opened
+                .metadata()
+                .await
+                .map_err(|e| format!("無法讀取 {}: {}", file.display(), e))?
+                .len();
+            Ok::<_, String>((reqwest::Body::wrap_stream(ReaderStream::new(opened)), length))
+        };
+        match self {
+            BlobStore::Local { root, .. } => {
+                let path = local_path(root, key)?;
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent)
+                        .await
+                        .map_err(|e| format!("無法建立目錄 {}: {}", parent.display(), e))?;
+                }
+                tokio::fs::copy(file, &path)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("無法寫入 {}: {}", path.display(), e))
+            }
+            BlobStore::S3(s3) => {
+                let url = s3.presign(Method::PUT, key, INTERNAL_URL_TTL)?;
+                let (body, length) = body().await?;
+                s3.client
+                    .put(url)
+                    .header(reqwest::header::CONTENT_LENGTH, length)
+                    .body(body)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| format!("S3 上傳 {} 失敗: {}", key, e))?;
+                Ok(())
+            }
+            BlobStore::WebDav(webdav) => {
+                local_path(Path::new(""), key)?;
+                webdav.create_collections(key).await?;
+                let (body, length) = body().await?;
+                webdav
+                    .request(Method::PUT, key)
+                    .header(reqwest::header::CONTENT_LENGTH, length)
+                    .body(body)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| format!("WebDAV 上傳 {} 失敗: {}", key, e))?;
+                Ok(())
+            }
+        }
+    }
+
+    /// 讀取物件
+    pub async fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        match self {
+            BlobStore::Local { root, .. } => {
+                let path = local_path(root, key)?;
+                tokio::fs::read(&path)
+                    .await
+                    .map_err(|e| format!("無法讀取 {}: {}", path.display(), e))
+            }
+            BlobStore::S3(s3) => {
+                let url = s3.presign(Method::GET, key, INTERNAL_URL_TTL)?;
+                let response = s3
+                    .client
+                    .get(url)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| format!("S3 下載 {} 失敗: {}", key, e))?;
+                response
+                    .bytes()
+                    .await
+                    .map(|b| b.to_vec())
+                    .map_err(|e| format!("S3 下載 {} 失敗: {}", key, e))
+            }
+            BlobStore::WebDav(webdav) => {
+                local_path(Path::new(""), key)?;
+                let response = webdav
+                    .request(Method::GET, key)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| format!("WebDAV 下載 {} 失敗: {}", key, e))?;
+                response
+                    .bytes()
+                    .await
+                    .map(|b| b.to_vec())
+                    .map_err(|e| format!("WebDAV 下載 {} 失敗: {}", key, e))
+            }
+        }
+    }
+
+    /// 產生限時可公開存取的網址（供 LINE 圖片/音訊訊息使用）
+    pub fn presigned_url(&self, key: &str, ttl: Duration) -> Result<String, String> {
+        match self {
+            BlobStore::Local { public_base_url, signer, .. }
+            | BlobStore::WebDav(WebDavConfig { public_base_url, signer, .. }) => {
+                let base = public_base_url
+                    .as_deref()
+                    .ok_or("本機與 WebDAV 儲存需設定 PUBLIC_BASE_URL 才能產生公開網址")?;
+                let expires = signer.clock().timestamp() + ttl.as_secs() as i64;
+                let token = signer.issue(Audience::Media, key, expires);
+                Ok(format!("{}/files/{}?token={}", base, uri_encode(key, false), token))
+            }
+            BlobStore::S3(s3) => s3.presign(Method::GET, key, ttl).map(|url| url.to_string()),
+        }
+    }
+
+    /// 驗證簽章網址並讀取物件（`/files/*key` 使用，S3 改用預簽網址）
+    pub async fn get_signed(&self, key: &str, token: &str) -> Result<Vec<u8>, String> {
+        let (BlobStore::Local { signer, .. } | BlobStore::WebDav(WebDavConfig { signer, .. })) = self else {
+            return Err("S3 儲存不提供簽章網址".to_string());
+        };
+        match signer.verify(Audience::Media, key, token) {
+            Ok(()) => self.get(key).await,
+            Err(TokenError::Expired) => Err("網址已過期".to_string()),
+            Err(TokenError::Invalid) => Err("簽章不符".to_string()),
+        }
+    }
+}
+
+impl S3Config {
+    /// 產生 AWS Signature V4 預簽網址（query string 簽章、UNSIGNED-PAYLOAD）
+    fn presign(&self, method: Method, key: &str, ttl: Duration) -> Result<Url, String> {
+        let mut url = Url::parse(&format!(
+            "{}/{}/{}",
+            self.endpoint,
+            uri_encode(&self.bucket, false),
+            uri_encode(key, false)
+        ))
+        .map_err(|e| format!("S3 網址錯誤: {}", e))?;
+        let host = sigv4::host(&url).ok_or("S3 端點缺少主機名稱")?;
+        let scope = sigv4::Scope::new(chrono::Utc::now(), &self.region, "s3");
+
+        let mut query = [
+            ("X-Amz-Algorithm", sigv4::ALGORITHM.to_string()),
+            ("X-Amz-Credential", scope.credential(&self.access_key_id)),
+            ("X-Amz-Date", scope.amz_date.clone()),
+            ("X-Amz-Expires", ttl.as_secs().min(604_800).to_string()),
+            ("X-Amz-SignedHeaders", "host".to_string()),
+        ];
+        query.sort_by(|a, b| a.0.cmp(b.0));
+        let canonical_query = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", uri_encode(k, true), uri_encode(v, true)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD",
+            method.as_str(),
+            url.path(),
+            canonical_query,
+            host
+        );
+        let signature = scope.sign(&self.secret_access_key, &canonical_request);
+
+        url.set_query(Some(&format!("{}&X-Amz-Signature={}", canonical_query, signature)));
+        Ok(url)
+    }
+}
+
+impl WebDavConfig {
+    fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}/{}", self.url, uri_encode(path, false)));
+        match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_deref()),
+            None => request,
+        }
+    }
+
+    /// 依序建立物件 key 上層的集合（MKCOL）；已存在時伺服器回傳 405，視為成功
+    async fn create_collections(&self, key: &str) -> Result<(), String> {
+        let mkcol = Method::from_bytes(b"MKCOL").expect("MKCOL 是合法的 HTTP 方法");
+        let parts: Vec<&str> = key.split('/').collect();
+        for depth in 1..parts.len() {
+            let collection = format!("{}/", parts[..depth].join("/"));
+            let response = self
+                .request(mkcol.clone(), &collection)
+                .send()
+                .await
+                .map_err(|e| format!("WebDAV 建立 {} 失敗: {}", collection, e))?;
+            let status = response.status();
+            if !status.is_success() && status != reqwest::StatusCode::METHOD_NOT_ALLOWED {
+                return Err(format!("WebDAV 建立 {} 失敗: HTTP {}", collection, status));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// RFC 3986 URI 編碼；`encode_slash` 為 false 時保留路徑分隔符號
+pub fn uri_encode(input: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(byte as char),
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+/// 將物件 key 對應到本機路徑，拒絕跳出根目錄的 key
+fn local_path(root: &Path, key: &str) -> Result<PathBuf, String> {
+    if key.is_empty() || key.starts_with('/') || key.split('/').any(|part| part == "..") {
+        return Err(format!("不合法的物件名稱：{}", key));
+    }
+    Ok(root.join(key))
+}
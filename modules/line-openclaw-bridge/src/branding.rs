@@ -0,0 +1,131 @@
+//! 品牌設定模組
+//! 從 branding.toml 讀取機器人名稱、個性描述、表情符號風格與問候語，讓同一個執行檔以設定檔區分不同的官方帳號
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::i18n::{I18n, Locale};
+
+/// 未設定品牌檔時的機器人名稱
+const DEFAULT_NAME: &str = "LINE-OpenClaw Bridge";
+
+/// 表情符號風格
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiStyle {
+    /// 不指示 OpenClaw，沿用模型習慣
+    #[default]
+    Default,
+    None,
+    Light,
+    Rich,
+}
+
+/// 貼圖（LINE 的包裝與貼圖 ID）
+#[derive(Debug, Clone, Deserialize)]
+pub struct StickerRef {
+    pub package_id: String,
+    pub sticker_id: String,
+}
+
+/// LINE 表情貼（商品與表情貼 ID）
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmojiRef {
+    pub product_id: String,
+    pub emoji_id: String,
+}
+
+/// 品牌設定
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Branding {
+    pub name: String,
+    /// 附在系統指示中的個性描述
+    pub personality: Option<String>,
+    pub emoji: EmojiStyle,
+    /// 加入好友時的問候語：語系標籤 → 訊息（可含 `{name}`）；未設定的語系使用內建訊息
+    pub greeting: HashMap<String, String>,
+    /// AI 情緒標籤（小寫，如 `happy`）→ 貼圖；有設定時 OpenClaw 可在回答後附上一張貼圖
+    pub stickers: HashMap<String, StickerRef>,
+    /// 表情貼名稱（小寫，如 `smile`）→ LINE 表情貼；有設定時 OpenClaw 可在回答中以 `$smile$` 插入
+    pub emojis: HashMap<String, EmojiRef>,
+}
+
+impl Default for Branding {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_NAME.to_string(),
+            personality: None,
+            emoji: EmojiStyle::Default,
+            greeting: HashMap::new(),
+            stickers: HashMap::new(),
+            emojis: HashMap::new(),
+        }
+    }
+}
+
+impl Branding {
+    /// 讀取品牌檔；未指定路徑時使用預設品牌
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let Some(path) = path else { return Ok(Self::default()) };
+        let content = std::fs::read_to_string(path).map_err(|e| format!("無法讀取 {}: {}", path.display(), e))?;
+        let branding: Self = toml::from_str(&content).map_err(|e| format!("{} 格式錯誤: {}", path.display(), e))?;
+        if branding.name.trim().is_empty() {
+            return Err(format!("{} 的 name 不可為空白", path.display()));
+        }
+        if let Some(tag) = branding.greeting.keys().find(|tag| Locale::from_tag(tag).is_none()) {
+            return Err(format!("{} 的 greeting 僅支援 zh-TW、ja、en，目前為 {}", path.display(), tag));
+        }
+        let invalid_tag = |tag: &String| {
+            tag.is_empty() || !tag.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        };
+        if let Some(tag) = branding.stickers.keys().find(|tag| invalid_tag(tag)) {
+            return Err(format!("{} 的 stickers 標籤僅能使用小寫英數字、底線與連字號，目前為 {}", path.display(), tag));
+        }
+        if let Some((tag, _)) = branding
+            .stickers
+            .iter()
+            .find(|(_, s)| s.package_id.trim().is_empty() || s.sticker_id.trim().is_empty())
+        {
+            return Err(format!("{} 的貼圖 {} 缺少 package_id 或 sticker_id", path.display(), tag));
+        }
+        if let Some(name) = branding.emojis.keys().find(|name| invalid_tag(name)) {
+            return Err(format!("{} 的 emojis 名稱僅能使用小寫英數字、底線與連字號，目前為 {}", path.display(), name));
+        }
+        if let Some((name, _)) = branding
+            .emojis
+            .iter()
+            .find(|(_, e)| e.product_id.trim().is_empty() || e.emoji_id.trim().is_empty())
+        {
+            return Err(format!("{} 的表情貼 {} 缺少 product_id 或 emoji_id", path.display(), name));
+        }
+        Ok(branding)
+    }
+
+    /// 附在對話前的系統指示；沒有任何品牌設定時回傳 None
+    pub fn instruction(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if self.name != DEFAULT_NAME {
+            parts.push(format!("You are {}, a LINE assistant.", self.name));
+        }
+        if let Some(personality) = self.personality.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            parts.push(personality.to_string());
+        }
+        match self.emoji {
+            EmojiStyle::Default => {}
+            EmojiStyle::None => parts.push("Do not use emoji.".to_string()),
+            EmojiStyle::Light => parts.push("Use emoji sparingly, at most one or two per answer.".to_string()),
+            EmojiStyle::Rich => parts.push("Use emoji freely to keep the tone friendly.".to_string()),
+        }
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// 加入好友時的問候語
+    pub fn greeting(&self, i18n: &I18n, locale: Locale) -> String {
+        match self.greeting.iter().find(|(tag, _)| Locale::from_tag(tag) == Some(locale)) {
+            Some((_, text)) => text.replace("{name}", &self.name),
+            None => i18n.text(locale, "onboarding.greeting", &[("name", &self.name)]),
+        }
+    }
+}
@@ -0,0 +1,527 @@
+//! 每日簡報模組
+//! 每天早上依使用者當地時間，彙整天氣、行事曆、RSS 與待提醒事項，經 OpenClaw 摘要後推送
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use futures::StreamExt;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::delivery;
+use crate::feeds;
+use crate::i18n::Locale;
+use crate::line::TextMessage;
+use crate::outbound;
+use crate::privacy;
+use crate::quota;
+use crate::store::{BriefingSettings, ConversationStore, Reminder};
+use crate::{AppState, SharedState};
+
+/// 預設送出時間（當地時間）
+const DEFAULT_TIME: &str = "07:30";
+/// 預設時區
+pub const DEFAULT_TIMEZONE: &str = "Asia/Taipei";
+/// 檢查是否該送出簡報的間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// 超過預定時間太久（例如服務停機）就略過當天
+const MAX_DELAY_MINUTES: i64 = 120;
+/// 每位使用者最多的 RSS 來源數
+const MAX_FEEDS: usize = 5;
+/// 每個 RSS 來源取前幾則
+const ITEMS_PER_FEED: usize = 3;
+/// 行事曆最多列出幾項
+const MAX_EVENTS: usize = 10;
+/// 行事曆（iCalendar）回應的大小上限
+const MAX_CALENDAR_BYTES: usize = 2 * 1024 * 1024;
+/// 同時準備的簡報數
+const CONCURRENCY: usize = 4;
+
+/// 從外部來源收集的簡報內容（不需持有應用程式狀態的鎖）
+struct Sources {
+    forecast: Option<DailyForecast>,
+    events: Option<Vec<String>>,
+    headlines: Vec<String>,
+}
+
+fn defaults(user_id: &str) -> BriefingSettings {
+    BriefingSettings {
+        user_id: user_id.to_string(),
+        enabled: false,
+        time: DEFAULT_TIME.to_string(),
+        timezone: DEFAULT_TIMEZONE.to_string(),
+        city: None,
+        latitude: None,
+        longitude: None,
+        calendar_url: None,
+        feeds: Vec::new(),
+        last_sent_on: None,
+    }
+}
+
+/// 處理 `/briefing` 指令，回傳回覆文字
+pub async fn handle_command(state: &AppState, user_id: &str, locale: Locale, args: Vec<String>) -> String {
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    if !state.flags.is_enabled("briefing_command", Some(user_id), true) {
+        return t("command.unavailable");
+    }
+    let mut settings = match state.store.get_briefing(user_id) {
+        Ok(settings) => settings.unwrap_or_else(|| defaults(user_id)),
+        Err(e) => {
+            error!("Failed to load briefing settings: {}", e);
+            return t("briefing.error");
+        }
+    };
+
+    let subcommand = args.first().map(|a| a.to_lowercase());
+    let value = args.get(1..).map(|rest| rest.join(" ")).unwrap_or_default();
+    match (subcommand.as_deref(), value.as_str()) {
+        (None, _) => return describe(state, locale, &settings),
+        (Some("now"), _) => return compose(state, &settings, locale).await.0,
+        (Some("on"), time) => {
+            if !time.is_empty() {
+                let Some(time) = parse_time(time) else { return t("briefing.invalid_time") };
+                settings.time = time;
+            }
+            settings.enabled = true;
+        }
+        (Some("off"), _) => settings.enabled = false,
+        (Some("time"), time) => match parse_time(time) {
+            Some(time) => settings.time = time,
+            None => return t("briefing.invalid_time"),
+        },
+        (Some("tz"), timezone) => match timezone.parse::<Tz>() {
+            Ok(tz) => settings.timezone = tz.name().to_string(),
+            Err(_) => return state.i18n.text(locale, "briefing.invalid_timezone", &[("timezone", timezone)]),
+        },
+        (Some("city"), "off") => (settings.city, settings.latitude, settings.longitude) = (None, None, None),
+        (Some("city"), city) if !city.is_empty() => match geocode(city).await {
+            Ok(Some((name, latitude, longitude))) => {
+                (settings.city, settings.latitude, settings.longitude) = (Some(name), Some(latitude), Some(longitude))
+            }
+            Ok(None) => return state.i18n.text(locale, "briefing.city_not_found", &[("city", city)]),
+            Err(e) => {
+                warn!("Geocoding failed: {}", e);
+                return t("briefing.error");
+            }
+        },
+        (Some("calendar"), "off") => settings.calendar_url = None,
+        (Some("calendar"), url) if is_http_url(url) => settings.calendar_url = Some(url.to_string()),
+        (Some("rss"), rest) => {
+            let (action, url) = rest.split_once(' ').unwrap_or((rest, ""));
+            match (action, url.trim()) {
+                ("add", url) if is_http_url(url) => {
+                    if settings.feeds.len() >= MAX_FEEDS {
+                        return state.i18n.text(locale, "briefing.too_many_feeds", &[("max", &MAX_FEEDS.to_string())]);
+                    }
+                    if !settings.feeds.iter().any(|f| f == url) {
+                        settings.feeds.push(url.to_string());
+                    }
+                }
+                ("remove", url) if !url.is_empty() => settings.feeds.retain(|f| f != url),
+                ("clear", _) => settings.feeds.clear(),
+                _ => return t("briefing.usage"),
+            }
+        }
+        (Some("remind"), text) if !text.is_empty() => {
+            return match state.store.add_reminder(user_id, text) {
+                Ok(()) => t("briefing.reminder_added"),
+                Err(e) => {
+                    error!("Failed to add reminder: {}", e);
+                    t("briefing.error")
+                }
+            };
+        }
+        _ => return t("briefing.usage"),
+    }
+
+    match state.store.save_briefing(&settings) {
+        Ok(()) => describe(state, locale, &settings),
+        Err(e) => {
+            error!("Failed to save briefing settings: {}", e);
+            t("briefing.error")
+        }
+    }
+}
+
+/// 目前設定
+fn describe(state: &AppState, locale: Locale, settings: &BriefingSettings) -> String {
+    let none = state.i18n.text(locale, "briefing.not_set", &[]);
+    let status = state.i18n.text(locale, if settings.enabled { "briefing.on" } else { "briefing.off" }, &[]);
+    let feeds = if settings.feeds.is_empty() { none.clone() } else { settings.feeds.join("\n  ") };
+    let settings_text = state.i18n.text(locale, "briefing.settings", &[
+        ("status", &status),
+        ("time", &settings.time),
+        ("timezone", &settings.timezone),
+        ("city", settings.city.as_deref().unwrap_or(&none)),
+        ("calendar", settings.calendar_url.as_deref().unwrap_or(&none)),
+        ("feeds", &feeds),
+    ]);
+    format!("{}\n\n{}", settings_text, state.i18n.text(locale, "briefing.usage", &[]))
+}
+
+/// 正規化 `HH:MM` 時間
+pub fn parse_time(text: &str) -> Option<String> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M").ok().map(|t| t.format("%H:%M").to_string())
+}
+
+fn is_http_url(text: &str) -> bool {
+    outbound::check_url(text).is_ok()
+}
+
+/// 在背景每分鐘檢查，到了使用者當地的簡報時間就推送；外部來源在鎖外抓取，同時最多準備 [`CONCURRENCY`] 份
+pub fn spawn(state: SharedState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let (store, due) = {
+                let state = state.read().await;
+                // 簡報延後送出便失去意義，額度用完時直接略過
+                if quota::exhausted(&state).is_some() {
+                    continue;
+                }
+                let subscribers = match state.store.briefing_subscribers() {
+                    Ok(subscribers) => subscribers,
+                    Err(e) => {
+                        error!("Failed to load briefing subscribers: {}", e);
+                        continue;
+                    }
+                };
+                let now = state.clock.now();
+                let mut due = Vec::new();
+                for settings in subscribers {
+                    let Some(today) = due_date(&settings, now) else { continue };
+                    // 先記錄再送出，避免送出失敗時每分鐘重試造成重複推送
+                    if let Err(e) = state.store.mark_briefing_sent(&settings.user_id, &today) {
+                        error!("Failed to mark briefing as sent: {}", e);
+                        continue;
+                    }
+                    let local = local_today(&settings, now);
+                    due.push((settings, local));
+                }
+                (state.store.clone(), due)
+            };
+            futures::stream::iter(due)
+                .for_each_concurrent(CONCURRENCY, |(settings, (tz, today))| {
+                    let (state, store) = (state.clone(), store.clone());
+                    async move {
+                        let sources = gather(&store, &settings, tz, today).await;
+                        let state = state.read().await;
+                        send(&state, &settings, today, sources).await;
+                    }
+                })
+                .await;
+        }
+    });
+}
+
+/// 摘要並推送一份簡報，成功後標記納入的提醒事項
+async fn send(state: &AppState, settings: &BriefingSettings, today: NaiveDate, sources: Sources) {
+    let locale = state.i18n.locale_for(&state.line_client, &settings.user_id).await;
+    let (text, reminders) = summarize(state, settings, locale, today, sources).await;
+    match delivery::push(state, &settings.user_id, &[TextMessage::new(text).into()]).await {
+        Ok(()) => {
+            info!("Morning briefing sent to {}", privacy::id(&settings.user_id));
+            let ids: Vec<i64> = reminders.iter().map(|r| r.id).collect();
+            if let Err(e) = state.store.mark_reminders_delivered(&ids) {
+                error!("Failed to mark reminders as delivered: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to push briefing to {}: {}", privacy::id(&settings.user_id), e),
+    }
+}
+
+/// 使用者的時區與當地日期
+fn local_today(settings: &BriefingSettings, now: DateTime<Utc>) -> (Tz, NaiveDate) {
+    let tz: Tz = settings.timezone.parse().unwrap_or(chrono_tz::Asia::Taipei);
+    (tz, now.with_timezone(&tz).date_naive())
+}
+
+/// 今天該送出時回傳當地日期；已送過、時間未到或延誤太久時回傳 None
+fn due_date(settings: &BriefingSettings, now: DateTime<Utc>) -> Option<String> {
+    due(&settings.time, &settings.timezone, settings.last_sent_on.as_deref(), now).map(|date| date.to_string())
+}
+
+/// 當地時間 `time` 已到（且延誤不超過上限）、今天尚未執行時回傳當地日期；`last_run_on` 為上次執行的當地日期
+pub fn due(time: &str, timezone: &str, last_run_on: Option<&str>, now: DateTime<Utc>) -> Option<NaiveDate> {
+    let tz: Tz = timezone.parse().ok()?;
+    let now = now.with_timezone(&tz);
+    let today = now.date_naive();
+    if last_run_on == Some(today.to_string().as_str()) {
+        return None;
+    }
+    let scheduled = NaiveTime::parse_from_str(time, "%H:%M").ok()?;
+    let late_by = (now.time() - scheduled).num_minutes();
+    (0..=MAX_DELAY_MINUTES).contains(&late_by).then_some(today)
+}
+
+/// 組出簡報（`/briefing now` 使用）
+async fn compose(state: &AppState, settings: &BriefingSettings, locale: Locale) -> (String, Vec<Reminder>) {
+    let (tz, today) = local_today(settings, state.clock.now());
+    let sources = gather(&state.store, settings, tz, today).await;
+    summarize(state, settings, locale, today, sources).await
+}
+
+/// 同時抓取天氣、行事曆與 RSS
+async fn gather(store: &ConversationStore, settings: &BriefingSettings, tz: Tz, today: NaiveDate) -> Sources {
+    let (forecast, events, headlines) = futures::join!(
+        forecast(settings),
+        calendar(settings, tz, today),
+        headlines(store, &settings.feeds),
+    );
+    Sources { forecast, events, headlines }
+}
+
+/// 收集到的內容加上待提醒事項，交由 OpenClaw 摘要，失敗時直接列出原始內容。同時回傳納入的提醒事項
+async fn summarize(
+    state: &AppState,
+    settings: &BriefingSettings,
+    locale: Locale,
+    today: NaiveDate,
+    sources: Sources,
+) -> (String, Vec<Reminder>) {
+    let reminders = state.store.pending_reminders(&settings.user_id).unwrap_or_else(|e| {
+        error!("Failed to load reminders: {}", e);
+        Vec::new()
+    });
+    let Sources { forecast, events, headlines } = sources;
+    let weather = forecast.and_then(|daily| weather(state, locale, settings, daily));
+
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    let mut sections = Vec::new();
+    if let Some(weather) = weather {
+        sections.push(format!("{}\n{}", t("briefing.section_weather"), weather));
+    }
+    if let Some(events) = events {
+        let body = if events.is_empty() { t("briefing.no_events") } else { bullet(&events) };
+        sections.push(format!("{}\n{}", t("briefing.section_calendar"), body));
+    }
+    if !headlines.is_empty() {
+        sections.push(format!("{}\n{}", t("briefing.section_news"), bullet(&headlines)));
+    }
+    if !reminders.is_empty() {
+        let texts: Vec<String> = reminders.iter().map(|r| r.text.clone()).collect();
+        sections.push(format!("{}\n{}", t("briefing.section_reminders"), bullet(&texts)));
+    }
+    if sections.is_empty() {
+        return (t("briefing.empty"), reminders);
+    }
+
+    let raw = format!("{}\n\n{}", state.i18n.text(locale, "briefing.title", &[("date", &today.to_string())]), sections.join("\n\n"));
+    let instructions = format!(
+        "You write a short, friendly morning briefing for a LINE chat in {}. \
+         Summarize the sections below into plain text (no markdown), keep every calendar event time and reminder, \
+         mention the most relevant headlines, and stay under 800 characters.",
+        locale.language_name()
+    );
+    let text = match state.openclaw_client.send_with_instructions(&settings.user_id, &instructions, &raw).await {
+        Ok(summary) if !summary.trim().is_empty() => summary,
+        Ok(_) => raw,
+        Err(e) => {
+            warn!("Briefing summary failed, sending raw sections: {}", e);
+            raw
+        }
+    };
+    (text, reminders)
+}
+
+fn bullet(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("• {}", line)).collect::<Vec<_>>().join("\n")
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodingResponse {
+    #[serde(default)]
+    results: Vec<GeocodingResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodingResult {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+/// 以 Open-Meteo 查詢城市座標
+async fn geocode(city: &str) -> Result<Option<(String, f64, f64)>, String> {
+    let response: GeocodingResponse = outbound::client()
+        .get("https://geocoding-api.open-meteo.com/v1/search")
+        .query(&[("name", city), ("count", "1")])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(response.results.into_iter().next().map(|r| (r.name, r.latitude, r.longitude)))
+}
+
+#[derive(Debug, Deserialize)]
+struct ForecastResponse {
+    daily: DailyForecast,
+}
+
+#[derive(Debug, Deserialize)]
+struct DailyForecast {
+    weather_code: Vec<Option<u8>>,
+    temperature_2m_max: Vec<Option<f64>>,
+    temperature_2m_min: Vec<Option<f64>>,
+    precipitation_probability_max: Vec<Option<u8>>,
+}
+
+/// 今日天氣預報（Open-Meteo，免金鑰）；未設定城市或查詢失敗時回傳 None
+async fn forecast(settings: &BriefingSettings) -> Option<DailyForecast> {
+    let (latitude, longitude) = (settings.latitude?, settings.longitude?);
+    let result = outbound::client()
+        .get("https://api.open-meteo.com/v1/forecast")
+        .query(&[
+            ("latitude", latitude.to_string()),
+            ("longitude", longitude.to_string()),
+            ("daily", "weather_code,temperature_2m_max,temperature_2m_min,precipitation_probability_max".to_string()),
+            ("timezone", settings.timezone.clone()),
+            ("forecast_days", "1".to_string()),
+        ])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    match result {
+        Ok(response) => match response.json::<ForecastResponse>().await {
+            Ok(forecast) => Some(forecast.daily),
+            Err(e) => {
+                warn!("Invalid weather response: {}", e);
+                None
+            }
+        },
+        Err(e) => {
+            warn!("Failed to fetch weather: {}", e);
+            None
+        }
+    }
+}
+
+/// 今日天氣的文字
+fn weather(state: &AppState, locale: Locale, settings: &BriefingSettings, daily: DailyForecast) -> Option<String> {
+    let city = settings.city.as_deref()?;
+    let first = |values: &[Option<f64>]| values.first().copied().flatten().map(|v| format!("{:.0}", v)).unwrap_or_else(|| "-".to_string());
+    let condition = match daily.weather_code.first().copied().flatten() {
+        Some(0) => "weather.clear",
+        Some(1..=3) => "weather.cloudy",
+        Some(45 | 48) => "weather.fog",
+        Some(51..=57) => "weather.drizzle",
+        Some(61..=67) => "weather.rain",
+        Some(71..=77 | 85 | 86) => "weather.snow",
+        Some(80..=82) => "weather.showers",
+        Some(95..=99) => "weather.thunderstorm",
+        _ => "weather.unknown",
+    };
+    Some(state.i18n.text(locale, "briefing.weather", &[
+        ("city", city),
+        ("condition", &state.i18n.text(locale, condition, &[])),
+        ("min", &first(&daily.temperature_2m_min)),
+        ("max", &first(&daily.temperature_2m_max)),
+        ("rain", &daily.precipitation_probability_max.first().copied().flatten().unwrap_or(0).to_string()),
+    ]))
+}
+
+/// 今天的行事曆事件（iCalendar）；未設定或 robots.txt 不允許時回傳 None。僅處理單次事件，不展開 RRULE 週期規則
+async fn calendar(settings: &BriefingSettings, tz: Tz, today: NaiveDate) -> Option<Vec<String>> {
+    let url = settings.calendar_url.as_deref()?;
+    match feeds::robots_allowed(url).await {
+        Ok(true) => {}
+        Ok(false) => {
+            info!("Calendar {} is disallowed by robots.txt", url);
+            return None;
+        }
+        Err(e) => {
+            warn!("Failed to fetch calendar: {}", e);
+            return None;
+        }
+    }
+    let response = outbound::get_public(url, HeaderMap::new())
+        .await
+        .and_then(|r| r.error_for_status().map_err(|e| e.to_string()));
+    let body = match response {
+        Ok(response) => outbound::read_limited(response, MAX_CALENDAR_BYTES).await,
+        Err(e) => Err(e),
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Failed to fetch calendar: {}", e);
+            return None;
+        }
+    };
+
+    // 展開折行（以空白開頭的行接續上一行）
+    let mut lines: Vec<String> = Vec::new();
+    for line in body.lines() {
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut events: Vec<(Option<NaiveTime>, String)> = Vec::new();
+    let (mut start, mut summary) = (None, None);
+    for line in &lines {
+        match line.as_str() {
+            "BEGIN:VEVENT" => (start, summary) = (None, None),
+            "END:VEVENT" => {
+                if let (Some((date, time)), Some(summary)) = (start.take(), summary.take()) {
+                    if date == today {
+                        events.push((time, summary));
+                    }
+                }
+            }
+            _ => {
+                let Some((name, value)) = line.split_once(':') else { continue };
+                let (property, params) = name.split_once(';').unwrap_or((name, ""));
+                match property {
+                    "DTSTART" => start = event_start(params, value, tz),
+                    "SUMMARY" => summary = Some(value.replace("\\,", ",").replace("\\;", ";").replace("\\n", " ")),
+                    _ => {}
+                }
+            }
+        }
+    }
+    events.sort();
+    Some(
+        events
+            .into_iter()
+            .take(MAX_EVENTS)
+            .map(|(time, summary)| match time {
+                Some(time) => format!("{} {}", time.format("%H:%M"), summary),
+                None => summary,
+            })
+            .collect(),
+    )
+}
+
+/// DTSTART 轉為使用者時區的日期與時間（整天事件沒有時間）
+fn event_start(params: &str, value: &str, tz: Tz) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    if params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME") {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(|d| (d, None));
+    }
+    let local = if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        Utc.from_utc_datetime(&naive).with_timezone(&tz).naive_local()
+    } else {
+        let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        let source_tz = params
+            .split(';')
+            .find_map(|p| p.strip_prefix("TZID="))
+            .and_then(|id| id.parse::<Tz>().ok())
+            .unwrap_or(tz);
+        source_tz.from_local_datetime(&naive).earliest()?.with_timezone(&tz).naive_local()
+    };
+    Some((local.date(), Some(local.time())))
+}
+
+/// 各 RSS / Atom 來源的前幾則標題
+async fn headlines(store: &ConversationStore, feeds: &[String]) -> Vec<String> {
+    let results = futures::future::join_all(feeds.iter().map(|url| feeds::titles(store, url))).await;
+    results.into_iter().flat_map(|titles| titles.into_iter().take(ITEMS_PER_FEED)).collect()
+}
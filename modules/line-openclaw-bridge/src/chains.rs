@@ -0,0 +1,216 @@
+//! 多步驟回答流程模組
+//! 從 chains.toml 讀取具名流程（如擷取意圖 → 草稿 → 自我檢查 → 定稿），每一步可有自己的提示與模型；
+//! 以 `/chain <名稱> <訊息>` 或關鍵字觸發，讓重要的問題多花幾次呼叫換取更好的回答
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::info;
+
+use crate::i18n::Locale;
+use crate::line::{OutgoingMessage, TextMessage};
+use crate::openclaw::{ChatMessage, OpenClawClient};
+use crate::privacy;
+use crate::AppState;
+
+/// 提示中代表使用者原始訊息的變數
+const INPUT: &str = "input";
+/// 提示中代表上一步輸出的變數
+const PREVIOUS: &str = "previous";
+
+/// 所有流程
+#[derive(Debug, Default)]
+pub struct Chains {
+    chains: Vec<Chain>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChainsFile {
+    #[serde(default)]
+    chain: Vec<Chain>,
+}
+
+/// 具名流程
+#[derive(Debug, Deserialize)]
+pub struct Chain {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// 一般訊息含任一關鍵字（不分大小寫）時改用此流程
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(rename = "step")]
+    pub steps: Vec<Step>,
+}
+
+/// 流程中的一步
+#[derive(Debug, Deserialize)]
+pub struct Step {
+    pub name: String,
+    /// 未設定則使用 OPENCLAW_MODEL
+    pub model: Option<String>,
+    /// 此步驟的系統指示
+    pub system: Option<String>,
+    /// 送出的提示；`{input}` 為使用者訊息，`{previous}` 為上一步輸出，`{<步驟名稱>}` 為該步輸出
+    pub prompt: String,
+}
+
+impl Chains {
+    /// 讀取流程設定檔；未指定路徑時沒有任何流程
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let Some(path) = path else { return Ok(Self::default()) };
+        let content = std::fs::read_to_string(path).map_err(|e| format!("無法讀取 {}: {}", path.display(), e))?;
+        let file: ChainsFile = toml::from_str(&content).map_err(|e| format!("{} 格式錯誤: {}", path.display(), e))?;
+        let mut chains: Vec<Chain> = Vec::with_capacity(file.chain.len());
+        for mut chain in file.chain {
+            chain.validate().map_err(|e| format!("{} 的流程 {}：{}", path.display(), chain.name, e))?;
+            chain.name = chain.name.to_lowercase();
+            if chains.iter().any(|c| c.name == chain.name) {
+                return Err(format!("{} 的流程名稱 {} 重複", path.display(), chain.name));
+            }
+            chain.keywords = chain.keywords.iter().map(|k| k.trim().to_lowercase()).filter(|k| !k.is_empty()).collect();
+            chains.push(chain);
+        }
+        Ok(Self { chains })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chains.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Chain> {
+        self.chains.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Chain> {
+        let name = name.to_lowercase();
+        self.chains.iter().find(|chain| chain.name == name)
+    }
+
+    /// 訊息含關鍵字的第一個流程（依設定檔順序）
+    pub fn matching(&self, text: &str) -> Option<&Chain> {
+        let text = text.to_lowercase();
+        self.chains.iter().find(|chain| chain.keywords.iter().any(|k| text.contains(k.as_str())))
+    }
+}
+
+impl Chain {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() || self.name.contains(char::is_whitespace) {
+            return Err("名稱不可為空白或含空白字元".to_string());
+        }
+        if self.steps.is_empty() {
+            return Err("至少需要一個 [[chain.step]]".to_string());
+        }
+        let mut known = vec![INPUT];
+        for (index, step) in self.steps.iter().enumerate() {
+            if step.name.is_empty() || known.contains(&step.name.as_str()) || step.name == PREVIOUS {
+                return Err(format!("第 {} 步的名稱 {:?} 無效或重複", index + 1, step.name));
+            }
+            if step.prompt.trim().is_empty() {
+                return Err(format!("步驟 {} 的 prompt 不可為空白", step.name));
+            }
+            for variable in variables(&step.prompt) {
+                let available = known.contains(&variable) || (variable == PREVIOUS && index > 0);
+                if !available {
+                    return Err(format!("步驟 {} 引用了未知或尚未產生的 {{{}}}", step.name, variable));
+                }
+            }
+            known.push(&step.name);
+        }
+        Ok(())
+    }
+
+    /// 依序執行每一步，回傳最後一步的輸出；任一步失敗即中止
+    pub async fn run(&self, client: &OpenClawClient, user_id: &str, instructions: &[ChatMessage], input: &str) -> Result<String, String> {
+        info!("Running chain {} for user={}", self.name, privacy::id(user_id));
+        let mut outputs: HashMap<&str, String> = HashMap::from([(INPUT, input.to_string())]);
+        let mut previous = input.to_string();
+        for step in &self.steps {
+            let prompt = render(&step.prompt, |name| if name == PREVIOUS { Some(&previous) } else { outputs.get(name) });
+            // 品牌與回答語言等共用指示放在最前面
+            let mut messages = instructions.to_vec();
+            if let Some(system) = &step.system {
+                messages.push(ChatMessage { role: "system".to_string(), content: system.clone() });
+            }
+            messages.push(ChatMessage { role: "user".to_string(), content: prompt });
+            let output = client
+                .send_chat(user_id, step.model.as_deref(), messages)
+                .await
+                .map_err(|e| format!("流程 {} 的步驟 {} 失敗: {}", self.name, step.name, e))?;
+            outputs.insert(&step.name, output.clone());
+            previous = output;
+        }
+        Ok(previous)
+    }
+}
+
+/// 處理 `/chain`（列出流程）與 `/chain <名稱> <訊息>`（以該流程回答）
+pub async fn handle_command(state: &AppState, user_id: &str, locale: Locale, name: Option<String>, input: String) -> Vec<OutgoingMessage> {
+    let t = |key: &str, args: &[(&str, &str)]| state.i18n.text(locale, key, args);
+    if !state.flags.is_enabled("chain_command", Some(user_id), true) {
+        return vec![TextMessage::new(t("command.unavailable", &[])).into()];
+    }
+    let text = match name.as_deref().map(|name| (name, state.chains.get(name))) {
+        None if state.chains.is_empty() => t("chain.none", &[]),
+        None => {
+            let lines: Vec<String> = state
+                .chains
+                .iter()
+                .map(|chain| match chain.description.is_empty() {
+                    true => format!("• {}", chain.name),
+                    false => format!("• {}：{}", chain.name, chain.description),
+                })
+                .collect();
+            format!("{}\n{}", t("chain.list", &[]), lines.join("\n"))
+        }
+        Some((name, None)) => t("chain.unknown", &[("name", name)]),
+        Some((_, Some(chain))) if input.is_empty() => t("chain.usage", &[("name", &chain.name)]),
+        Some((_, Some(chain))) => return crate::chat_messages(state, "message", user_id, locale, &input, Some(chain)).await,
+    };
+    vec![TextMessage::new(text).into()]
+}
+
+/// 提示中引用的 `{變數}`
+fn variables(prompt: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    render(prompt, |name| {
+        found.push(name);
+        None::<&str>
+    });
+    found
+}
+
+/// 一次替換提示中的 `{變數}`；`lookup` 找不到的部分原樣保留，已替換的內容不會再被展開
+fn render<'a, F, V>(prompt: &'a str, mut lookup: F) -> String
+where
+    F: FnMut(&'a str) -> Option<V>,
+    V: AsRef<str>,
+{
+    let mut rendered = String::with_capacity(prompt.len());
+    let mut rest = prompt;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name = after.find('}').map(|len| &after[..len]);
+        match name.filter(|n| !n.is_empty() && n.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')) {
+            Some(name) => {
+                match lookup(name) {
+                    Some(value) => rendered.push_str(value.as_ref()),
+                    None => {
+                        rendered.push('{');
+                        rendered.push_str(name);
+                        rendered.push('}');
+                    }
+                }
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
@@ -0,0 +1,111 @@
+//! 聊天通道模組
+//! 各通道的 API 客戶端實作 [`ChannelAdapter`] 後，即可用同一套流程送出 OpenClaw 的回答（長文依該通道的字數上限拆成多則）
+
+#[cfg(feature = "matrix")]
+use matrix_adapter::MatrixClient;
+use std::future::Future;
+use whatsapp_adapter::WhatsAppClient;
+
+use crate::formatting;
+use crate::line::{LineClient, OutgoingMessage, TextMessage};
+
+/// 聊天通道
+pub trait ChannelAdapter: Send + Sync {
+    /// 通道名稱（OpenClaw metadata 的 `channel` 與稽核紀錄使用）
+    fn name(&self) -> &'static str;
+    /// 單則文字訊息的字數上限
+    fn text_limit(&self) -> usize;
+    /// 主動送出一則文字訊息給使用者
+    fn send_text(&self, to: &str, text: &str) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+impl ChannelAdapter for LineClient {
+    fn name(&self) -> &'static str {
+        "line"
+    }
+
+    fn text_limit(&self) -> usize {
+        TextMessage::TEXT_LIMIT
+    }
+
+    async fn send_text(&self, to: &str, text: &str) -> Result<(), String> {
+        self.push_messages(to, &[TextMessage::new(text).into()]).await.map_err(|e| e.to_string())
+    }
+}
+
+impl ChannelAdapter for WhatsAppClient {
+    fn name(&self) -> &'static str {
+        "whatsapp"
+    }
+
+    fn text_limit(&self) -> usize {
+        whatsapp_adapter::TEXT_LIMIT
+    }
+
+    async fn send_text(&self, to: &str, text: &str) -> Result<(), String> {
+        WhatsAppClient::send_text(self, to, text).await.map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "matrix")]
+impl ChannelAdapter for MatrixClient {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    fn text_limit(&self) -> usize {
+        matrix_adapter::TEXT_LIMIT
+    }
+
+    async fn send_text(&self, to: &str, text: &str) -> Result<(), String> {
+        MatrixClient::send_text(self, to, text).await.map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+/// 將回答依通道的字數上限拆開後依序送出，回傳送出的則數
+pub async fn send_answer<C: ChannelAdapter>(channel: &C, to: &str, answer: &str) -> Result<usize, String> {
+    let chunks = formatting::split_text(answer, channel.text_limit());
+    for chunk in &chunks {
+        channel.send_text(to, chunk).await?;
+    }
+    Ok(chunks.len())
+}
+
+/// LINE 訊息的文字內容（只支援文字的通道送出指令回覆時使用）：Flex 與樣板訊息取替代文字，地圖取標題與地址，貼圖等略過
+pub fn plain_text(messages: &[OutgoingMessage]) -> String {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            OutgoingMessage::Text(message) => Some(message.text.clone()),
+            OutgoingMessage::TextV2(message) => Some(message.text.clone()),
+            OutgoingMessage::Flex(message) => Some(message.alt_text.clone()),
+            OutgoingMessage::Template(message) => Some(message.alt_text.clone()),
+            OutgoingMessage::Location(message) => Some(format!("{}\n{}", message.title, message.address)),
+            OutgoingMessage::Image(_) | OutgoingMessage::Audio(_) | OutgoingMessage::Sticker(_) => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line::{Bubble, FlexMessage, LocationMessage, StickerMessage};
+
+    #[test]
+    fn plain_text_keeps_text_alt_text_and_location() {
+        let messages: Vec<OutgoingMessage> = vec![
+            TextMessage::new("第一段").into(),
+            StickerMessage::new("446", "1988").into(),
+            FlexMessage::new("卡片摘要", Bubble::new()).into(),
+            LocationMessage::new("台北車站", "台北市中正區", 25.0478, 121.517).into(),
+        ];
+        assert_eq!(plain_text(&messages), "第一段\n\n卡片摘要\n\n台北車站\n台北市中正區");
+    }
+
+    #[test]
+    fn plain_text_without_text_is_empty() {
+        assert_eq!(plain_text(&[StickerMessage::new("446", "1988").into()]), "");
+        assert_eq!(plain_text(&[]), "");
+    }
+}
@@ -0,0 +1,155 @@
+//! 命令列介面模組
+//! 定義子指令與伺服器以外的維運工具
+
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+use futures::{Stream, StreamExt};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+use crate::dataset;
+use crate::encryption::{self, Recipient};
+use crate::export::{self, Dataset, ExportFormat};
+use crate::privacy;
+use crate::store::{ConversationStore, TimeRange};
+
+/// LINE-OpenClaw Bridge
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// 啟動 Webhook 伺服器（預設）
+    Serve,
+    /// 匯出稽核紀錄或用量統計
+    Export {
+        /// 資料集
+        #[arg(value_enum)]
+        dataset: Dataset,
+        /// 起始日期 YYYY-MM-DD（含）
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// 結束日期 YYYY-MM-DD（含）
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// 匯出格式
+        #[arg(long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+        /// 輸出檔案（預設寫到標準輸出）
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// 加密的收件者：`age1…` 公鑰，或 keyring 中的 GPG 金鑰 ID／email
+        #[arg(long)]
+        recipient: Option<String>,
+    },
+    /// 建立微調或評估用的資料集
+    Dataset {
+        #[command(subcommand)]
+        command: DatasetCommand,
+    },
+    /// 以 PRIVACY_HASH_KEY 將日誌或匯出中的雜湊反查回原始 ID
+    Resolve {
+        /// 雜湊值（例如 `U#3fa1…`）
+        hash: String,
+    },
+    /// 在終端機與 OpenClaw 對話（與 LINE 相同的流程，不傳送任何 LINE 訊息）
+    Chat {
+        /// 模擬的 LINE 使用者 ID（決定對話紀錄與個人設定）
+        #[arg(long, default_value = "cli")]
+        user: String,
+        /// 介面語系 zh-TW、ja、en（預設 DEFAULT_LOCALE）
+        #[arg(long)]
+        locale: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DatasetCommand {
+    /// 匯出去識別化的「提問 → 回答」配對（JSONL，含回饋標籤）
+    Export {
+        /// 起始日期 YYYY-MM-DD（含）
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// 結束日期 YYYY-MM-DD（含）
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// 一併匯出 OpenClaw 失敗時的備援回應
+        #[arg(long)]
+        include_fallback: bool,
+        /// 輸出檔案（預設寫到標準輸出）
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// 加密的收件者：`age1…` 公鑰，或 keyring 中的 GPG 金鑰 ID／email
+        #[arg(long)]
+        recipient: Option<String>,
+    },
+}
+
+/// 執行匯出並寫入檔案或標準輸出；指定 `recipient` 時加密後再寫入
+pub async fn export(
+    database_path: &str,
+    dataset: Dataset,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+    recipient: Option<String>,
+) -> Result<(), String> {
+    let store = ConversationStore::open(database_path).map_err(|e| format!("無法開啟資料庫: {}", e))?;
+    let chunks = export::stream(Arc::new(store), dataset, TimeRange::from_dates(from, to), format);
+    write_output(chunks, output, recipient).await
+}
+
+/// 匯出資料集（JSONL）並寫入檔案或標準輸出；指定 `recipient` 時加密後再寫入
+pub async fn export_dataset(
+    database_path: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    include_fallback: bool,
+    output: Option<PathBuf>,
+    recipient: Option<String>,
+) -> Result<(), String> {
+    let store = ConversationStore::open(database_path).map_err(|e| format!("無法開啟資料庫: {}", e))?;
+    let options = dataset::Options { range: TimeRange::from_dates(from, to), include_fallback };
+    write_output(dataset::stream(Arc::new(store), options), output, recipient).await
+}
+
+async fn write_output(
+    chunks: impl Stream<Item = Result<Vec<u8>, String>> + Send + 'static,
+    output: Option<PathBuf>,
+    recipient: Option<String>,
+) -> Result<(), String> {
+    let recipient = match recipient.as_deref() {
+        Some(value) => Some(Recipient::parse(value).await?),
+        None => None,
+    };
+    let mut out: Box<dyn tokio::io::AsyncWrite + Unpin> = match &output {
+        Some(path) => Box::new(
+            tokio::fs::File::create(path)
+                .await
+                .map_err(|e| format!("無法建立 {}: {}", path.display(), e))?,
+        ),
+        None => Box::new(tokio::io::stdout()),
+    };
+
+    let mut chunks = match recipient {
+        Some(recipient) => encryption::encrypt(chunks, recipient).boxed(),
+        None => chunks.boxed(),
+    };
+    while let Some(chunk) = chunks.next().await {
+        out.write_all(&chunk?).await.map_err(|e| e.to_string())?;
+    }
+    out.flush().await.map_err(|e| e.to_string())
+}
+
+/// 比對資料庫中出現過的 ID，找出雜湊對應的原始 ID
+pub fn resolve(database_path: &str, key: &str, hash: &str) -> Result<String, String> {
+    let store = ConversationStore::open(database_path).map_err(|e| format!("無法開啟資料庫: {}", e))?;
+    let ids = store.known_ids().map_err(|e| format!("讀取 ID 失敗: {}", e))?;
+    privacy::resolve(key.as_bytes(), hash, ids).ok_or_else(|| format!("資料庫中沒有符合 {} 的 ID（金鑰是否正確？）", hash))
+}
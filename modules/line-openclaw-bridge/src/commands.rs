@@ -0,0 +1,287 @@
+//! 斜線指令模組
+//! 解析使用者輸入的 `/指令`（亦接受訊息檔 `[alias]` 定義的別名，如 `/幫助`），其餘文字交由 OpenClaw 處理；
+//! 拼錯的指令以快速回覆確認要改用的指令，避免直接送給 AI
+
+use crate::i18n::{I18n, Locale};
+use crate::line::{Action, OutgoingMessage, TextMessage};
+
+/// 確認建議指令的 postback 前綴（其後為完整指令文字）
+const COMMAND_POSTBACK_PREFIX: &str = "command:";
+/// 不使用建議、改交給 AI 的 postback 前綴（其後為原始訊息）
+const CHAT_POSTBACK_PREFIX: &str = "chat:";
+/// postback data 與 displayText 的長度上限（LINE 限制 300 字）
+const MAX_POSTBACK_CHARS: usize = 300;
+
+/// 指令名稱（訊息檔的別名須對應其中之一）
+pub const NAMES: [&str; 13] = [
+    "search", "help", "poll", "quiz", "briefing", "link", "status", "chain", "stop", "history", "mention", "automation", "model",
+];
+
+/// 使用者指令
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// `/search <關鍵字...>` 搜尋過往對話
+    Search(Vec<String>),
+    /// `/help` 顯示可用指令
+    Help,
+    /// `/poll "問題" 選項...` 發起投票，`/poll close` 結束投票
+    Poll(Vec<String>),
+    /// `/quiz [主題]` 開始問答遊戲，`/quiz next|stop|rank` 跳題、結束與排行榜
+    Quiz(Vec<String>),
+    /// `/briefing [on|off|time|tz|city|calendar|rss|remind|now] ...` 每日簡報設定
+    Briefing(Vec<String>),
+    /// `/link [連結碼|remove]` 連結其他平台的身分，共用對話脈絡
+    Link(Vec<String>),
+    /// `/status [on|off]` 查看服務狀態、開關事故通知
+    Status(Vec<String>),
+    /// `/chain [名稱 訊息]` 列出或以指定的多步驟流程回答（訊息保留原樣，不切分參數）
+    Chain { name: Option<String>, input: String },
+    /// `/stop` 取消進行中的回答
+    Stop,
+    /// `/history` 列出最近的對話階段，可繼續或匯出
+    History,
+    /// `/mention [on|off]` 查看或切換群組只在被 @提及時回應
+    Mention(Vec<String>),
+    /// `/automation [list|delete <id>|描述]` 管理員以一句話建立排程，或列出、停用已建立的排程
+    Automation(Vec<String>),
+    /// `/model` 顯示目前使用的模型與 OpenClaw 提供的模型
+    Model,
+}
+
+/// 解析訊息文字，非指令時回傳 None
+pub fn parse(text: &str, i18n: &I18n) -> Option<Command> {
+    let text = text.trim();
+    let rest = text.strip_prefix('/')?;
+    let (name, raw_args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let args = tokenize(raw_args);
+
+    let name = name.to_lowercase();
+    match i18n.command_for_alias(&name).unwrap_or(&name) {
+        "search" => Some(Command::Search(args)),
+        "help" => Some(Command::Help),
+        "poll" => Some(Command::Poll(args)),
+        "quiz" => Some(Command::Quiz(args)),
+        "briefing" => Some(Command::Briefing(args)),
+        "link" => Some(Command::Link(args)),
+        "status" => Some(Command::Status(args)),
+        "stop" => Some(Command::Stop),
+        "history" => Some(Command::History),
+        "mention" => Some(Command::Mention(args)),
+        "automation" => Some(Command::Automation(args)),
+        "model" => Some(Command::Model),
+        "chain" => {
+            let raw_args = raw_args.trim();
+            let (chain, input) = raw_args.split_once(char::is_whitespace).unwrap_or((raw_args, ""));
+            Some(Command::Chain {
+                name: Some(chain.to_string()).filter(|c| !c.is_empty()),
+                input: input.trim().to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// 拼錯的指令與建議改用的指令
+#[derive(Debug)]
+pub struct Suggestion {
+    pub command: &'static str,
+    /// 改用建議指令後的完整訊息（保留原本的參數）
+    pub text: String,
+}
+
+/// 找出與未知指令最接近的指令：名稱或別名的前綴（至少 2 字），或編輯距離在容許範圍內且只有一個最接近的指令
+pub fn suggest(text: &str, i18n: &I18n) -> Option<Suggestion> {
+    let text = text.trim();
+    let rest = text.strip_prefix('/')?;
+    let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let name = name.to_lowercase();
+    let length = name.chars().count();
+    if length == 0 || text.chars().count() + CHAT_POSTBACK_PREFIX.len() > MAX_POSTBACK_CHARS {
+        return None;
+    }
+
+    let candidates: Vec<(&str, &'static str)> = NAMES.iter().map(|name| (*name, *name)).chain(i18n.command_aliases()).collect();
+    let unique = |matches: Vec<&'static str>| -> Option<&'static str> {
+        let first = *matches.first()?;
+        matches.iter().all(|command| *command == first).then_some(first)
+    };
+
+    let prefixed: Vec<&'static str> = candidates
+        .iter()
+        .filter(|(candidate, _)| length >= 2 && candidate.starts_with(name.as_str()))
+        .map(|(_, command)| *command)
+        .collect();
+    let command = unique(prefixed).or_else(|| {
+        // 4 字以內只容許 1 個錯字
+        let tolerance = if length <= 4 { 1 } else { 2 };
+        let scored: Vec<(usize, &'static str)> = candidates
+            .iter()
+            .map(|(candidate, command)| (edit_distance(&name, candidate), *command))
+            .filter(|(distance, _)| *distance <= tolerance)
+            .collect();
+        let best = scored.iter().map(|(distance, _)| *distance).min()?;
+        unique(scored.into_iter().filter(|(distance, _)| *distance == best).map(|(_, command)| command).collect())
+    })?;
+
+    let args = args.trim();
+    Some(Suggestion {
+        command,
+        text: if args.is_empty() { format!("/{}", command) } else { format!("/{} {}", command, args) },
+    })
+}
+
+/// 詢問是否改用建議指令的訊息：快速回覆可執行建議指令，或把原始訊息交給 AI
+pub fn suggestion_message(i18n: &I18n, locale: Locale, original: &str, suggestion: &Suggestion) -> OutgoingMessage {
+    let command = format!("/{}", suggestion.command);
+    let mut actions = Vec::new();
+    if suggestion.text.chars().count() + COMMAND_POSTBACK_PREFIX.len() <= MAX_POSTBACK_CHARS {
+        actions.push(Action::postback_with_text(
+            command.clone(),
+            format!("{}{}", COMMAND_POSTBACK_PREFIX, suggestion.text),
+            suggestion.text.as_str(),
+        ));
+    }
+    actions.push(Action::postback_with_text(
+        i18n.text(locale, "command.ask_ai", &[]),
+        format!("{}{}", CHAT_POSTBACK_PREFIX, original.trim()),
+        original.trim(),
+    ));
+    TextMessage::new(i18n.text(locale, "command.did_you_mean", &[("command", &command)]))
+        .with_quick_reply(actions)
+        .into()
+}
+
+/// 把訊息交給 AI 的按鈕；訊息過長無法放進 postback 時回傳 None
+pub fn chat_action(label: String, text: &str) -> Option<Action> {
+    let text = text.trim();
+    (text.chars().count() + CHAT_POSTBACK_PREFIX.len() <= MAX_POSTBACK_CHARS)
+        .then(|| Action::postback_with_text(label.clone(), format!("{}{}", CHAT_POSTBACK_PREFIX, text), label))
+}
+
+/// 解析確認建議指令的 postback 資料
+pub fn parse_postback(data: &str, i18n: &I18n) -> Option<Command> {
+    parse(data.strip_prefix(COMMAND_POSTBACK_PREFIX)?, i18n)
+}
+
+/// 解析改交給 AI 的 postback 資料，回傳原始訊息
+pub fn parse_chat_postback(data: &str) -> Option<&str> {
+    data.strip_prefix(CHAT_POSTBACK_PREFIX)
+}
+
+/// 以字元計算的編輯距離（相鄰字元對調算一次）
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// 以空白切分參數，引號（"…"、“…”、「…」）內的空白視為同一個參數
+fn tokenize(args: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut closing: Option<char> = None;
+    let mut quoted = false;
+
+    for c in args.chars() {
+        match closing {
+            Some(end) if c == end => closing = None,
+            Some(_) => current.push(c),
+            None => match c {
+                '"' => (closing, quoted) = (Some('"'), true),
+                '“' => (closing, quoted) = (Some('”'), true),
+                '「' => (closing, quoted) = (Some('」'), true),
+                c if c.is_whitespace() => {
+                    if !current.is_empty() || quoted {
+                        tokens.push(std::mem::take(&mut current));
+                    }
+                    quoted = false;
+                }
+                c => current.push(c),
+            },
+        }
+    }
+    if !current.is_empty() || quoted {
+        tokens.push(current);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn i18n() -> I18n {
+        I18n::load(Locale::ZhTw, None).unwrap()
+    }
+
+    #[test]
+    fn tokenize_whitespace() {
+        assert_eq!(tokenize("  a  b\tc "), vec!["a", "b", "c"]);
+        assert!(tokenize("   ").is_empty());
+    }
+
+    #[test]
+    fn tokenize_quoted_arguments() {
+        assert_eq!(tokenize(r#""午餐 吃什麼" 拉麵 "壽司 飯""#), vec!["午餐 吃什麼", "拉麵", "壽司 飯"]);
+        assert_eq!(tokenize("“晚餐 吃什麼” 「火鍋 店」 咖哩"), vec!["晚餐 吃什麼", "火鍋 店", "咖哩"]);
+    }
+
+    #[test]
+    fn tokenize_keeps_empty_quotes() {
+        assert_eq!(tokenize(r#""" a"#), vec!["", "a"]);
+    }
+
+    #[test]
+    fn tokenize_unclosed_quote_runs_to_end() {
+        assert_eq!(tokenize(r#"a "b c"#), vec!["a", "b c"]);
+    }
+
+    #[test]
+    fn edit_distance_counts_edits_and_transpositions() {
+        assert_eq!(edit_distance("help", "help"), 0);
+        assert_eq!(edit_distance("", "poll"), 4);
+        assert_eq!(edit_distance("hlep", "help"), 1);
+        assert_eq!(edit_distance("serch", "search"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("幫住", "幫助"), 1);
+    }
+
+    #[test]
+    fn suggest_typo_keeps_arguments() {
+        let suggestion = suggest("/serach rust 教學", &i18n()).unwrap();
+        assert_eq!(suggestion.command, "search");
+        assert_eq!(suggestion.text, "/search rust 教學");
+
+        let suggestion = suggest("/hlep", &i18n()).unwrap();
+        assert_eq!((suggestion.command, suggestion.text.as_str()), ("help", "/help"));
+    }
+
+    #[test]
+    fn suggest_unique_prefix_and_alias() {
+        assert_eq!(suggest("/brief on", &i18n()).unwrap().text, "/briefing on");
+        assert_eq!(suggest("/幫住", &i18n()).unwrap().command, "help");
+    }
+
+    #[test]
+    fn suggest_nothing_for_unrelated_text() {
+        assert!(suggest("hello", &i18n()).is_none());
+        assert!(suggest("/", &i18n()).is_none());
+        assert!(suggest("/p", &i18n()).is_none());
+        assert!(suggest("/weather", &i18n()).is_none());
+    }
+}
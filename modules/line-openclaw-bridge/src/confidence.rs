@@ -0,0 +1,103 @@
+//! 回答信心門檻模組
+//! 以結構化輸出要求 OpenClaw 附上信心分數與澄清問題，信心低於門檻時改問澄清問題，避免對模糊的訊息亂猜
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::openclaw::{ChatMessage, OpenClawClient};
+
+/// OpenClaw 回傳的結構化結果
+#[derive(Debug, Deserialize)]
+struct Assessed {
+    answer: String,
+    confidence: f64,
+    #[serde(default)]
+    clarification: Option<String>,
+}
+
+/// 附在對話前的系統指示
+pub fn instruction() -> String {
+    "Respond with JSON containing `answer`, `confidence` and `clarification`. `confidence` is a number from 0 to 1 \
+     telling how sure you are that you understood what the user wants. When the request is ambiguous or missing \
+     details you need, set `clarification` to one short question that would resolve it (in the language you would \
+     answer in); otherwise set it to null. Always fill `answer` with your best attempt."
+        .to_string()
+}
+
+fn schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "answer": { "type": "string" },
+            "confidence": { "type": "number", "minimum": 0, "maximum": 1 },
+            "clarification": { "type": ["string", "null"] }
+        },
+        "required": ["answer", "confidence", "clarification"],
+        "additionalProperties": false
+    })
+}
+
+/// 取得回答；信心低於 `threshold` 且有澄清問題時回傳澄清問題。一併回傳稽核紀錄用的說明。
+/// 回應不是預期的 JSON 時直接當作回答
+pub async fn ask(
+    client: &OpenClawClient,
+    user_id: &str,
+    mut history: Vec<ChatMessage>,
+    text: &str,
+    threshold: f64,
+) -> Result<(String, String), String> {
+    // 與其他系統指示放在一起，對話縮減時不會被捨棄
+    let pinned = history.iter().take_while(|m| m.role == "system").count();
+    history.insert(pinned, ChatMessage { role: "system".to_string(), content: instruction() });
+    let content = client.send_message_structured(user_id, history, text, "answer", schema()).await?;
+    let Ok(assessed) = serde_json::from_str::<Assessed>(&content) else {
+        return Ok((content, "confidence=unparsed".to_string()));
+    };
+    let confidence = assessed.confidence.clamp(0.0, 1.0);
+    match assessed.clarification.filter(|q| !q.trim().is_empty()) {
+        Some(question) if confidence < threshold => Ok((question, format!("confidence={:.2} clarification=asked", confidence))),
+        _ => Ok((assessed.answer, format!("confidence={:.2}", confidence))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Json, Router};
+
+    /// 模擬 OpenClaw：訊息超過 5 則時回傳 context 超限，否則回傳固定的結構化結果
+    async fn serve() -> String {
+        async fn completions(Json(request): Json<serde_json::Value>) -> Result<Json<serde_json::Value>, (axum::http::StatusCode, String)> {
+            let messages = request["messages"].as_array().map_or(0, Vec::len);
+            if messages > 5 {
+                return Err((axum::http::StatusCode::BAD_REQUEST, "maximum context length exceeded".to_string()));
+            }
+            let content = json!({ "answer": "答案", "confidence": 0.3, "clarification": format!("{} messages?", messages) });
+            Ok(Json(json!({
+                "id": "1",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "model",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": content.to_string() }, "finish_reason": "stop" }]
+            })))
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let router = Router::new().route("/v1/chat/completions", post(completions));
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn context_overflow_drops_older_history_and_retries() {
+        let client = OpenClawClient::new(serve().await, None, "model".to_string(), false);
+        let history: Vec<ChatMessage> = (0..4)
+            .map(|i| ChatMessage { role: if i % 2 == 0 { "user" } else { "assistant" }.to_string(), content: i.to_string() })
+            .collect();
+        // 系統指示 2 則（含信心指示）+ 對話 4 則 + 問題超限；捨棄較舊的 2 則對話後為 5 則
+        let history = [vec![ChatMessage { role: "system".to_string(), content: "品牌".to_string() }], history].concat();
+        let (answer, detail) = ask(&client, "U1", history, "問題", 0.5).await.unwrap();
+        assert_eq!(answer, "5 messages?");
+        assert_eq!(detail, "confidence=0.30 clarification=asked");
+    }
+}
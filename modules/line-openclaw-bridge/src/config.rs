@@ -0,0 +1,1265 @@
+//! 設定模組
+//! 從環境變數（或 `<KEY>_FILE` 指向的檔案）讀取伺服器設定，並產生遮蔽機密值的有效設定報告
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::attachments::AttachmentLinks;
+use crate::blob::BlobStore;
+use crate::clock::Clock;
+use crate::fanout::{FanoutConfig, Selector};
+use crate::files::FileHandler;
+use crate::i18n::Locale;
+use crate::language::ReplyLanguage;
+use crate::maintenance::{MaintenanceConfig, Window};
+use crate::moderation;
+use crate::proxy::{self, IpRange};
+use crate::openclaw::DEFAULT_MODEL;
+use crate::pipeline::Sampling;
+use crate::secrets::{SecretsBackend, SecretsConfig};
+use crate::store;
+use crate::supervisor::RecoveryConfig;
+use crate::tokens;
+use crate::whatsapp::WhatsAppConfig;
+
+/// 伺服器設定
+pub struct Config {
+    /// LINE access token；設定外部密鑰管理時由其提供並定期輪替
+    pub line_channel_access_token: String,
+    pub line_channel_secret: String,
+    /// 媒體與 LIFF 網址 JWT 的簽章金鑰；未設定時由 channel secret 衍生
+    pub token_signing_key: String,
+    /// 輪替 channel secret 期間仍接受的舊 secret，與接受期限
+    pub line_channel_secret_previous: Option<String>,
+    pub line_channel_secret_previous_until: Option<DateTime<Utc>>,
+    /// LINE Messaging API 位址
+    pub line_api_base_url: String,
+    /// 全域試運行：送往 LINE 的訊息只記錄、不實際送出
+    pub line_dry_run: bool,
+    /// WhatsApp Cloud API 通道，未設定 `WHATSAPP_PHONE_NUMBER_ID` 時停用
+    pub whatsapp: Option<WhatsAppConfig>,
+    /// Matrix 通道，未設定 `MATRIX_HOMESERVER_URL` 時停用；需以 `--features matrix` 編譯
+    pub matrix: Option<MatrixConfig>,
+    pub openclaw_base_url: String,
+    pub openclaw_gateway_token: Option<String>,
+    pub openclaw_model: String,
+    /// 以串流取得 OpenClaw 回答，並記錄首 token 時間與生成速度
+    pub openclaw_streaming: bool,
+    /// 在 OpenClaw 請求附上雜湊後的使用者 ID 與對話來源（`user`／`metadata`）
+    pub openclaw_metadata: bool,
+    /// 以系統指示告訴 OpenClaw 使用者的 LINE 顯示名稱，讓回答可以稱呼對方（預設關閉，隱私模式下不生效）
+    pub prompt_display_name: bool,
+    /// 附給 OpenClaw 的先前對話輪數（0 代表只送出當下訊息）；超出 context window 時自動減半重試
+    pub context_history_turns: usize,
+    /// 兩則訊息間隔超過此秒數即開始新的對話階段；對話脈絡只涵蓋目前階段
+    pub session_gap_secs: i64,
+    /// 強制的回答語言；None 代表不限制
+    pub reply_language: Option<ReplyLanguage>,
+    /// 回答禁用詞（小寫）；空白代表不審查
+    pub output_blocklist: Vec<String>,
+    /// 回答信心門檻（0–1）；設定後要求 OpenClaw 附上信心分數，低於門檻時改問澄清問題
+    pub clarify_threshold: Option<f64>,
+    /// 與上一則回答的相似度達此值（0–1）時改回覆簡短提示；None 代表不比對
+    pub repeat_answer_similarity: Option<f64>,
+    /// 平行詢問多個模型並擇優；以功能旗標 `fanout` 限定適用的使用者
+    pub fanout: Option<FanoutConfig>,
+    pub database_path: String,
+    /// 以 zstd 字典壓縮新寫入的對話內容，既有對話於每日維護時逐批壓縮
+    pub compress_conversations: bool,
+    /// 啟動時匯入的使用者資料快照（JSON）
+    pub snapshot_import_path: Option<String>,
+    /// 對外公開網址（媒體簽章網址與 LIFF 頁面使用）
+    pub public_base_url: Option<String>,
+    /// LIFF app ID，設定時頁面連結改以 `https://liff.line.me/<id>` 開啟
+    pub liff_id: Option<String>,
+    /// 回答超過此字數時改以完整回答頁呈現（0 代表停用）
+    pub long_answer_chars: usize,
+    /// 完整回答頁的保存天數
+    pub answer_page_ttl_days: i64,
+    /// 在 AI 回答後附上頁尾（模型、生成時間與「AI 生成內容」揭露）；指令回應不附加
+    pub ai_footer: bool,
+    /// 在 AI 回答附上快速回覆（重試、幫助、狀態）
+    pub answer_quick_replies: bool,
+    /// 允許 OpenClaw 以 ```line-menu 區塊輸出互動選單（轉成按鈕範本）
+    pub answer_menus: bool,
+    /// 含 Markdown 語法（標題、清單、粗體等）的回答轉為 Flex bubble
+    pub answer_markdown_flex: bool,
+    /// 保存使用者傳來的圖片（下載至物件儲存並記入對話紀錄）
+    pub save_incoming_images: bool,
+    /// 單張圖片的大小上限（位元組）
+    pub image_max_bytes: usize,
+    /// 保存使用者傳來的影片（等候 LINE 轉檔完成後下載）
+    pub save_incoming_videos: bool,
+    /// 單支影片的大小上限（位元組）
+    pub video_max_bytes: usize,
+    /// 保存使用者傳來的語音訊息（m4a，供日後轉文字）
+    pub save_incoming_audio: bool,
+    /// 單則語音的大小上限（位元組）
+    pub audio_max_bytes: usize,
+    /// 使用者傳來檔案的處理方式；None 代表回覆暫不支援
+    pub file_handler: Option<FileHandler>,
+    /// 單一檔案的大小上限（位元組）
+    pub file_max_bytes: usize,
+    /// 附件封存：媒體與檔案存入 `attachments/` 並回覆永久連結；None 代表停用
+    pub attachment_archive: Option<AttachmentLinks>,
+    /// 將使用者傳來的貼圖（關鍵字）交給 OpenClaw 回應；關閉時不回覆貼圖
+    pub sticker_replies: bool,
+    /// 將使用者分享的位置交給 OpenClaw 作為脈絡，並允許 AI 以地圖圖釘回答地點
+    pub location_messages: bool,
+    /// 群組與聊天室預設只在被 @提及時回應（各群組可用 `/mention on|off` 覆寫）
+    pub group_mention_only: bool,
+    /// 群組與聊天室中的回覆開頭 @提及提問的使用者
+    pub group_reply_mention: bool,
+    /// 群組與聊天室中的回覆引用使用者的原訊息
+    pub group_reply_quote: bool,
+    /// 一對一聊天收到訊息時顯示「輸入中」動畫的秒數（5–60，5 的倍數）；0 代表不顯示
+    pub loading_animation_secs: u32,
+    /// 亂數種子：設定時實驗分組與重試延遲皆可重現（僅供整合測試與重播評估）
+    pub random_seed: Option<u64>,
+    /// 頁尾生成時間使用的時區
+    pub ai_footer_timezone: Tz,
+    /// reply 遇到 5xx 或連線錯誤時的重試次數，之後改以 push 送出
+    pub reply_max_retries: u32,
+    /// 一般通知每批 multicast 之間的間隔（毫秒）；緊急通知不受此限
+    pub notify_pacing_ms: u64,
+    /// 一般通知的預設合併時間窗（秒），範本可個別覆寫；0 代表不合併
+    pub notify_batch_window_secs: i64,
+    /// 個人資料快取超過此秒數即在背景重新取得；None 代表不定期更新
+    pub profile_refresh_secs: Option<i64>,
+    /// 背景更新個人資料時每秒最多呼叫 LINE 的次數
+    pub profile_refresh_rate: u32,
+    /// 每位使用者每個時間窗最多送給 AI 的訊息數；None 代表不限制
+    pub user_rate_limit: Option<u32>,
+    /// 頻率限制的時間窗
+    pub user_rate_window: Duration,
+    /// 對話保存天數（超過即封存），未設定則不封存
+    pub retention_days: Option<i64>,
+    pub storage: StorageConfig,
+    pub admin_token: Option<String>,
+    /// 管理員的 LINE userId（公告預覽與核准用）
+    pub admin_line_user_id: Option<String>,
+    /// 允許 `POST /admin/broadcast` 不經預覽與核准直接廣播
+    pub admin_direct_broadcast: bool,
+    /// 隱私模式金鑰：設定後日誌與匯出中的 ID 以 HMAC 雜湊取代
+    pub privacy_hash_key: Option<String>,
+    /// 外部密鑰管理（Vault / AWS Secrets Manager），未設定則只讀環境變數
+    pub secrets: Option<SecretsConfig>,
+    /// 管理 API 的 mTLS 監聽埠，未設定則停用
+    pub admin_tls: Option<AdminTlsConfig>,
+    /// OpenClaw 自動復原，未設定復原指令則停用
+    pub recovery: Option<RecoveryConfig>,
+    /// OpenClaw 離線超過此時間即通知最近一小時內互動過的使用者，未設定則停用
+    pub incident_notice_after: Option<Duration>,
+    pub host: String,
+    pub port: String,
+    /// 受信任的反向代理；只有來自這些位址的連線才採用轉送標頭中的用戶端 IP
+    pub trusted_proxies: Vec<IpRange>,
+    /// Webhook 請求本文上限（位元組，以解壓縮後計算）
+    pub webhook_max_body_bytes: usize,
+    /// 資料庫維護（刪除過期資料、ANALYZE 與 VACUUM）
+    pub maintenance: MaintenanceConfig,
+    /// 資料表列數的軟上限（資料表 → 列數），超過時以 WARN 記錄
+    pub store_soft_limits: Vec<(String, u64)>,
+    /// Webhook 回應時限（毫秒）；處理一律在背景進行，驗證未能在時限內完成時仍先回應 200
+    pub webhook_response_timeout_ms: u64,
+    /// 超過此毫秒數的請求以 WARN 記錄
+    pub slow_request_ms: u64,
+    /// 訊息處理追蹤的抽樣設定（可由管理 API 在執行中調整）
+    pub trace_sampling: Sampling,
+    /// 錯誤回報（Sentry），未設定則停用
+    pub sentry_dsn: Option<String>,
+    pub sentry_environment: String,
+    /// 部署環境名稱（功能旗標與錯誤回報使用）
+    pub environment: String,
+    /// 功能旗標檔，未設定則所有旗標使用預設值
+    pub feature_flags_path: Option<String>,
+    /// 無法取得使用者語言時使用的語系
+    pub default_locale: Locale,
+    /// 覆寫內建訊息的目錄（`<語系>.toml`）
+    pub locales_dir: Option<String>,
+    /// 品牌設定檔（branding.toml），未設定則使用預設名稱
+    pub branding_path: Option<String>,
+    /// 多步驟回答流程設定檔（chains.toml），未設定則停用
+    pub chains_path: Option<String>,
+    /// 讀取過程中記錄的設定值（機密已遮蔽）
+    entries: Vec<ConfigEntry>,
+}
+
+/// 物件儲存設定
+pub enum StorageConfig {
+    Local {
+        root: String,
+        public_base_url: Option<String>,
+        signing_key: String,
+    },
+    S3 {
+        endpoint: Option<String>,
+        bucket: String,
+        region: String,
+        access_key_id: String,
+        secret_access_key: String,
+    },
+    WebDav {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+        public_base_url: Option<String>,
+        signing_key: String,
+    },
+}
+
+/// Matrix 設定
+#[derive(Clone)]
+#[cfg_attr(not(feature = "matrix"), allow(dead_code))]
+pub struct MatrixConfig {
+    pub homeserver_url: String,
+    /// 機器人專用裝置的存取權杖
+    pub access_token: String,
+    /// 同步狀態與 E2EE 金鑰的儲存目錄
+    pub store_path: PathBuf,
+    /// 加密儲存內容的密碼；未設定時金鑰以明文存放，啟動時記錄警告
+    pub store_passphrase: Option<String>,
+    /// 啟動時加入的房間（房間 ID 或 `#別名:伺服器`）
+    pub rooms: Vec<String>,
+}
+
+/// 管理 API 的 mTLS 監聽設定
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "mtls"), allow(dead_code))]
+pub struct AdminTlsConfig {
+    pub port: u16,
+    /// 伺服器憑證鏈（PEM）
+    pub cert_path: String,
+    /// 伺服器私鑰（PEM）
+    pub key_path: String,
+    /// 簽發用戶端憑證的 CA（PEM）
+    pub client_ca_path: String,
+    /// 各用戶端憑證 CN 可使用的路徑前綴
+    pub clients: Vec<ClientRule>,
+}
+
+/// 用戶端憑證 CN 與允許的路徑前綴（`*` 代表全部）
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "mtls"), allow(dead_code))]
+pub struct ClientRule {
+    pub common_name: String,
+    pub paths: Vec<String>,
+}
+
+#[cfg_attr(not(feature = "mtls"), allow(dead_code))]
+impl ClientRule {
+    /// 路徑前綴只在 `/` 邊界相符：`/admin/notify` 允許 `/admin/notify/1`，但不允許 `/admin/notifyx`
+    pub fn allows(&self, path: &str) -> bool {
+        self.paths.iter().any(|prefix| {
+            prefix == "*"
+                || path.strip_prefix(prefix.as_str()).is_some_and(|rest| {
+                    rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/')
+                })
+        })
+    }
+}
+
+/// 解析 `ci-bot=/admin/notify,/admin/templates;ops=*`
+fn parse_client_rules(value: &str) -> Result<Vec<ClientRule>, String> {
+    value
+        .split(';')
+        .filter(|rule| !rule.trim().is_empty())
+        .map(|rule| {
+            let (name, paths) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("ADMIN_TLS_CLIENTS 格式錯誤：{}", rule))?;
+            let paths: Vec<String> = paths.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+            if name.trim().is_empty() || paths.is_empty() {
+                return Err(format!("ADMIN_TLS_CLIENTS 格式錯誤：{}", rule));
+            }
+            Ok(ClientRule { common_name: name.trim().to_string(), paths })
+        })
+        .collect()
+}
+
+/// 解析 `資料表=列數,資料表=列數`
+fn parse_soft_limits(value: &str) -> Result<Vec<(String, u64)>, String> {
+    value
+        .split(',')
+        .filter(|rule| !rule.trim().is_empty())
+        .map(|rule| {
+            let (table, limit) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("STORE_SOFT_LIMITS 格式錯誤：{}", rule))?;
+            let table = table.trim();
+            if !store::TABLES.contains(&table) {
+                return Err(format!("STORE_SOFT_LIMITS 的資料表 {} 不存在，可用：{}", table, store::TABLES.join("、")));
+            }
+            let limit = limit.trim().parse().map_err(|_| format!("STORE_SOFT_LIMITS 格式錯誤：{}", rule))?;
+            Ok((table.to_string(), limit))
+        })
+        .collect()
+}
+
+/// 單一設定值
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigEntry {
+    pub key: &'static str,
+    /// 有效值；機密值已遮蔽，未設定為 null
+    pub value: Option<String>,
+    /// 值的來源：env、file（`<KEY>_FILE`）或 default
+    pub source: &'static str,
+}
+
+/// 功能啟用狀態
+#[derive(Debug, Serialize)]
+pub struct FeatureStatus {
+    pub name: &'static str,
+    pub enabled: bool,
+    pub detail: String,
+}
+
+/// 有效設定報告（啟動日誌與 `/admin/config` 使用）
+#[derive(Debug, Serialize)]
+pub struct ConfigReport {
+    pub version: &'static str,
+    pub model: String,
+    pub storage: String,
+    pub features: Vec<FeatureStatus>,
+    pub settings: Vec<ConfigEntry>,
+}
+
+impl Config {
+    /// 從環境變數讀取設定；必要設定缺漏時回傳錯誤
+    pub fn from_env() -> Result<Self, String> {
+        let mut env = EnvReader::default();
+
+        let secrets_backend = match env.optional("SECRETS_BACKEND", false).as_deref() {
+            None => None,
+            Some("vault") => Some(SecretsBackend::Vault {
+                addr: env.required("VAULT_ADDR", false)?.trim_end_matches('/').to_string(),
+                token: env.required("VAULT_TOKEN", true)?,
+                path: env.required("VAULT_SECRET_PATH", false)?,
+            }),
+            Some("aws") => Some(SecretsBackend::Aws {
+                endpoint: env.optional("SECRETS_MANAGER_ENDPOINT", false),
+                region: env.string("AWS_REGION", "us-east-1", false),
+                secret_id: env.required("AWS_SECRET_ID", false)?,
+                access_key_id: env.required("AWS_ACCESS_KEY_ID", true)?,
+                secret_access_key: env.required("AWS_SECRET_ACCESS_KEY", true)?,
+                session_token: env.optional("AWS_SESSION_TOKEN", true),
+            }),
+            Some(other) => return Err(format!("SECRETS_BACKEND 僅支援 vault 或 aws，目前為 {}", other)),
+        };
+        let secrets = match secrets_backend {
+            Some(backend) => Some(SecretsConfig {
+                backend,
+                refresh: Duration::from_secs(env.parse("SECRETS_REFRESH_MINUTES", Some(15u64))?.unwrap_or(15) * 60),
+            }),
+            None => None,
+        };
+
+        // 使用外部密鑰管理時 access token 可由其提供，啟動時再檢查
+        let line_channel_access_token = match secrets {
+            Some(_) => env.optional("LINE_CHANNEL_ACCESS_TOKEN", true).unwrap_or_default(),
+            None => env.required("LINE_CHANNEL_ACCESS_TOKEN", true)?,
+        };
+        let line_channel_secret = env.required("LINE_CHANNEL_SECRET", true)?;
+        let token_signing_key = env
+            .optional("TOKEN_SIGNING_KEY", true)
+            .unwrap_or_else(|| tokens::derive_key(&line_channel_secret));
+        let line_channel_secret_previous = env.optional("LINE_CHANNEL_SECRET_PREVIOUS", true);
+        let line_channel_secret_previous_until = env.parse::<DateTime<Utc>>("LINE_CHANNEL_SECRET_PREVIOUS_UNTIL", None)?;
+        if line_channel_secret_previous.is_some() != line_channel_secret_previous_until.is_some() {
+            return Err("LINE_CHANNEL_SECRET_PREVIOUS 與 LINE_CHANNEL_SECRET_PREVIOUS_UNTIL 需同時設定".to_string());
+        }
+        let line_api_base_url = env
+            .string("LINE_API_BASE_URL", "https://api.line.me", false)
+            .trim_end_matches('/')
+            .to_string();
+        let line_dry_run = env.parse("LINE_DRY_RUN", Some(false))?.unwrap_or(false);
+        let whatsapp = match env.optional("WHATSAPP_PHONE_NUMBER_ID", false).filter(|id| !id.trim().is_empty()) {
+            Some(phone_number_id) => Some(WhatsAppConfig {
+                phone_number_id,
+                access_token: env.required("WHATSAPP_ACCESS_TOKEN", true)?,
+                app_secret: env.required("WHATSAPP_APP_SECRET", true)?,
+                verify_token: env.required("WHATSAPP_VERIFY_TOKEN", true)?,
+                api_base_url: env.string("WHATSAPP_API_BASE_URL", whatsapp_adapter::DEFAULT_API_BASE_URL, false),
+            }),
+            None => None,
+        };
+        let matrix = match env.optional("MATRIX_HOMESERVER_URL", false).filter(|url| !url.trim().is_empty()) {
+            Some(_) if !cfg!(feature = "matrix") => {
+                return Err("MATRIX_HOMESERVER_URL 需以 `cargo build --features matrix` 編譯".to_string());
+            }
+            Some(homeserver_url) => Some(MatrixConfig {
+                homeserver_url,
+                access_token: env.required("MATRIX_ACCESS_TOKEN", true)?,
+                store_path: env.string("MATRIX_STORE_PATH", "matrix-store", false).into(),
+                store_passphrase: env.optional("MATRIX_STORE_PASSPHRASE", true),
+                rooms: env
+                    .string("MATRIX_ROOMS", "", false)
+                    .split(',')
+                    .map(|room| room.trim().to_string())
+                    .filter(|room| !room.is_empty())
+                    .collect(),
+            }),
+            None => None,
+        };
+        let openclaw_base_url = env.string("OPENCLAW_BASE_URL", "http://127.0.0.1:18789", false);
+        let openclaw_gateway_token = env.optional("OPENCLAW_GATEWAY_TOKEN", true);
+        let openclaw_model = env.string("OPENCLAW_MODEL", DEFAULT_MODEL, false);
+        let openclaw_streaming = env.parse("OPENCLAW_STREAMING", Some(false))?.unwrap_or(false);
+        let openclaw_metadata = env.parse("OPENCLAW_METADATA", Some(false))?.unwrap_or(false);
+        let prompt_display_name = env.parse("PROMPT_DISPLAY_NAME", Some(false))?.unwrap_or(false);
+        let context_history_turns = env.parse("CONTEXT_HISTORY_TURNS", Some(0usize))?.unwrap_or(0);
+        let session_gap_secs = match env.parse("SESSION_GAP_MINUTES", Some(60i64))?.unwrap_or(60) {
+            minutes if minutes > 0 => minutes * 60,
+            minutes => return Err(format!("SESSION_GAP_MINUTES 必須大於 0，目前為 {}", minutes)),
+        };
+        let reply_language = match env.optional("REPLY_LANGUAGE", false) {
+            Some(value) if !value.trim().is_empty() => Some(
+                ReplyLanguage::parse(&value)
+                    .ok_or_else(|| format!("REPLY_LANGUAGE 僅支援 auto、zh-TW、ja、en，目前為 {}", value))?,
+            ),
+            _ => None,
+        };
+        let output_blocklist = env
+            .optional("OUTPUT_BLOCKLIST", true)
+            .map(|value| moderation::parse_blocklist(&value))
+            .unwrap_or_default();
+        let clarify_threshold = env.parse::<f64>("CLARIFY_CONFIDENCE_THRESHOLD", None)?;
+        if clarify_threshold.is_some_and(|t| !(0.0..=1.0).contains(&t)) {
+            return Err("CLARIFY_CONFIDENCE_THRESHOLD 必須介於 0 與 1 之間".to_string());
+        }
+        let repeat_answer_similarity = env.parse::<f64>("REPEAT_ANSWER_SIMILARITY", None)?.filter(|s| *s > 0.0);
+        if repeat_answer_similarity.is_some_and(|s| s > 1.0) {
+            return Err("REPEAT_ANSWER_SIMILARITY 必須介於 0 與 1 之間".to_string());
+        }
+        let fanout_models: Vec<String> = env
+            .string("FANOUT_MODELS", "", false)
+            .split(',')
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .collect();
+        let fanout = match fanout_models.len() {
+            0 => None,
+            1 => return Err("FANOUT_MODELS 至少需要兩個模型（以逗號分隔）".to_string()),
+            _ => {
+                let selector = env.string("FANOUT_SELECT", "judge", false);
+                Some(FanoutConfig {
+                    models: fanout_models,
+                    selector: Selector::parse(&selector)
+                        .ok_or_else(|| format!("FANOUT_SELECT 僅支援 judge、length、latency，目前為 {}", selector))?,
+                    judge_model: env.optional("FANOUT_JUDGE_MODEL", false).filter(|m| !m.trim().is_empty()),
+                })
+            }
+        };
+        let database_path = env.string("DATABASE_PATH", "bridge.db", false);
+        let compress_conversations = env.parse("COMPRESS_CONVERSATIONS", Some(false))?.unwrap_or(false);
+        let snapshot_import_path = env.optional("SNAPSHOT_IMPORT_PATH", false).filter(|p| !p.is_empty());
+        let retention_days = env.parse::<i64>("RETENTION_DAYS", None)?.filter(|d| *d > 0);
+        let maintenance = MaintenanceConfig {
+            window: match env.optional("DB_MAINTENANCE_WINDOW", false) {
+                Some(value) if !value.trim().is_empty() => Some(
+                    Window::parse(&value)
+                        .ok_or_else(|| format!("DB_MAINTENANCE_WINDOW 格式錯誤（應為 HH:MM-HH:MM）：{}", value))?,
+                ),
+                _ => None,
+            },
+            timezone: env.parse("DB_MAINTENANCE_TIMEZONE", Some(chrono_tz::Asia::Taipei))?.unwrap_or(chrono_tz::Asia::Taipei),
+            prune_after_days: env.parse("DB_PRUNE_AFTER_DAYS", Some(90i64))?.unwrap_or(90).max(1),
+            audit_retention_days: env.parse::<i64>("AUDIT_RETENTION_DAYS", None)?.filter(|d| *d > 0),
+        };
+        let store_soft_limits = parse_soft_limits(&env.string("STORE_SOFT_LIMITS", "", false))?;
+
+        let public_base_url = env
+            .optional("PUBLIC_BASE_URL", false)
+            .map(|u| u.trim_end_matches('/').to_string());
+        let liff_id = env.optional("LIFF_ID", false);
+        let long_answer_chars = env.parse("LONG_ANSWER_CHARS", Some(1500usize))?.unwrap_or(1500);
+        let answer_page_ttl_days = env.parse("ANSWER_PAGE_TTL_DAYS", Some(30i64))?.unwrap_or(30).max(1);
+        let ai_footer = env.parse("AI_FOOTER", Some(false))?.unwrap_or(false);
+        let answer_quick_replies = env.parse("ANSWER_QUICK_REPLIES", Some(false))?.unwrap_or(false);
+        let answer_menus = env.parse("ANSWER_MENUS", Some(false))?.unwrap_or(false);
+        let answer_markdown_flex = env.parse("ANSWER_MARKDOWN_FLEX", Some(false))?.unwrap_or(false);
+        let save_incoming_images = env.parse("SAVE_INCOMING_IMAGES", Some(false))?.unwrap_or(false);
+        let image_max_bytes = env.parse("IMAGE_MAX_BYTES", Some(10 * 1024 * 1024usize))?.unwrap_or(10 * 1024 * 1024);
+        let save_incoming_videos = env.parse("SAVE_INCOMING_VIDEOS", Some(false))?.unwrap_or(false);
+        let video_max_bytes = env.parse("VIDEO_MAX_BYTES", Some(50 * 1024 * 1024usize))?.unwrap_or(50 * 1024 * 1024);
+        let save_incoming_audio = env.parse("SAVE_INCOMING_AUDIO", Some(false))?.unwrap_or(false);
+        let audio_max_bytes = env.parse("AUDIO_MAX_BYTES", Some(10 * 1024 * 1024usize))?.unwrap_or(10 * 1024 * 1024);
+        let file_handler = match env.string("FILE_HANDLER", "", false).trim() {
+            "" => None,
+            "store" => Some(FileHandler::Store),
+            "webhook" => Some(FileHandler::Webhook {
+                url: env
+                    .optional("FILE_WEBHOOK_URL", false)
+                    .filter(|u| !u.trim().is_empty())
+                    .ok_or("FILE_HANDLER=webhook 需設定 FILE_WEBHOOK_URL")?,
+                token: env.optional("FILE_WEBHOOK_TOKEN", true).filter(|t| !t.is_empty()),
+            }),
+            other => return Err(format!("FILE_HANDLER 僅支援 store、webhook，目前為 {}", other)),
+        };
+        let file_max_bytes = env.parse("FILE_MAX_BYTES", Some(20 * 1024 * 1024usize))?.unwrap_or(20 * 1024 * 1024);
+        let sticker_replies = env.parse("STICKER_REPLIES", Some(true))?.unwrap_or(true);
+        let location_messages = env.parse("LOCATION_MESSAGES", Some(true))?.unwrap_or(true);
+        let group_mention_only = env.parse("GROUP_MENTION_ONLY", Some(false))?.unwrap_or(false);
+        let group_reply_mention = env.parse("GROUP_REPLY_MENTION", Some(true))?.unwrap_or(true);
+        let group_reply_quote = env.parse("GROUP_REPLY_QUOTE", Some(true))?.unwrap_or(true);
+        let loading_animation_secs = match env.parse("LOADING_ANIMATION_SECONDS", Some(60u32))?.unwrap_or(60) {
+            secs if secs == 0 || ((5..=60).contains(&secs) && secs % 5 == 0) => secs,
+            secs => return Err(format!("LOADING_ANIMATION_SECONDS 必須為 0 或 5–60 之間 5 的倍數，目前為 {}", secs)),
+        };
+        let random_seed = env.parse::<u64>("RANDOM_SEED", None)?;
+        let ai_footer_timezone = env.parse("AI_FOOTER_TIMEZONE", Some(chrono_tz::Asia::Taipei))?.unwrap_or(chrono_tz::Asia::Taipei);
+        let reply_max_retries = env.parse("REPLY_MAX_RETRIES", Some(2u32))?.unwrap_or(2);
+        let notify_pacing_ms = env.parse("NOTIFY_PACING_MS", Some(1000u64))?.unwrap_or(1000);
+        let notify_batch_window_secs = env.parse("NOTIFY_BATCH_WINDOW_SECS", Some(300i64))?.unwrap_or(300).max(0);
+        let profile_refresh_secs = env.parse::<i64>("PROFILE_REFRESH_HOURS", None)?.filter(|h| *h > 0).map(|h| h * 3600);
+        let profile_refresh_rate = match env.parse("PROFILE_REFRESH_RATE", Some(2u32))?.unwrap_or(2) {
+            0 => return Err("PROFILE_REFRESH_RATE 必須大於 0".to_string()),
+            rate => rate,
+        };
+        let user_rate_limit = env.parse::<u32>("USER_RATE_LIMIT", None)?.filter(|limit| *limit > 0);
+        let user_rate_window = match env.parse("USER_RATE_WINDOW_SECS", Some(60u64))?.unwrap_or(60) {
+            0 => return Err("USER_RATE_WINDOW_SECS 必須大於 0".to_string()),
+            secs => Duration::from_secs(secs),
+        };
+
+        let media_signing_key = env.string("MEDIA_SIGNING_KEY", &token_signing_key, true);
+        let storage = match env.string("STORAGE_BACKEND", "local", false).as_str() {
+            "local" => StorageConfig::Local {
+                root: env.string("ARCHIVE_DIR", "data", false),
+                public_base_url: public_base_url.clone(),
+                signing_key: media_signing_key.clone(),
+            },
+            "s3" => StorageConfig::S3 {
+                endpoint: env.optional("S3_ENDPOINT", false),
+                bucket: env.required("S3_BUCKET", false)?,
+                region: env.string("S3_REGION", "us-east-1", false),
+                access_key_id: env.required("S3_ACCESS_KEY_ID", true)?,
+                secret_access_key: env.required("S3_SECRET_ACCESS_KEY", true)?,
+            },
+            "webdav" => StorageConfig::WebDav {
+                url: env.required("WEBDAV_URL", false)?,
+                username: env.optional("WEBDAV_USERNAME", false).filter(|u| !u.is_empty()),
+                password: env.optional("WEBDAV_PASSWORD", true).filter(|p| !p.is_empty()),
+                public_base_url: public_base_url.clone(),
+                signing_key: media_signing_key.clone(),
+            },
+            other => return Err(format!("STORAGE_BACKEND 僅支援 local、s3 或 webdav，目前為 {}", other)),
+        };
+        let attachment_archive = if env.parse("ATTACHMENT_ARCHIVE", Some(false))?.unwrap_or(false) {
+            match (env.optional("ATTACHMENT_LINK_BASE_URL", false).filter(|u| !u.trim().is_empty()), &public_base_url) {
+                (Some(base_url), _) => Some(AttachmentLinks::Public(base_url.trim_end_matches('/').to_string())),
+                (None, Some(base_url)) => Some(AttachmentLinks::Signed { base_url: base_url.clone(), signing_key: media_signing_key }),
+                (None, None) => return Err("ATTACHMENT_ARCHIVE 需設定 ATTACHMENT_LINK_BASE_URL 或 PUBLIC_BASE_URL".to_string()),
+            }
+        } else {
+            None
+        };
+
+        let admin_token = env.optional("ADMIN_TOKEN", true);
+        let privacy_hash_key = env.optional("PRIVACY_HASH_KEY", true);
+        if openclaw_metadata && privacy_hash_key.is_none() {
+            return Err("OPENCLAW_METADATA 需同時設定 PRIVACY_HASH_KEY（ID 以該金鑰雜湊後才送往 OpenClaw）".to_string());
+        }
+        // 隱私模式下管理員 userId 也不以明文出現在設定報告中
+        let admin_line_user_id = env.optional("ADMIN_LINE_USER_ID", privacy_hash_key.is_some());
+        let admin_direct_broadcast = env.parse("ADMIN_DIRECT_BROADCAST", Some(false))?.unwrap_or(false);
+
+        let recovery = match env.optional("OPENCLAW_RECOVERY_COMMAND", false) {
+            Some(command) => Some(RecoveryConfig {
+                command,
+                after: Duration::from_secs(env.parse("OPENCLAW_RECOVERY_AFTER_MINUTES", Some(5u64))?.unwrap_or(5) * 60),
+                cooldown: Duration::from_secs(env.parse("OPENCLAW_RECOVERY_COOLDOWN_MINUTES", Some(30u64))?.unwrap_or(30) * 60),
+            }),
+            None => None,
+        };
+        let incident_notice_after = env
+            .parse::<u64>("INCIDENT_NOTICE_AFTER_MINUTES", None)?
+            .filter(|m| *m > 0)
+            .map(|m| Duration::from_secs(m * 60));
+
+        let admin_tls = match env.parse::<u16>("ADMIN_TLS_PORT", None)? {
+            Some(_) if !cfg!(feature = "mtls") => {
+                return Err("ADMIN_TLS_PORT 需以 `cargo build --features mtls` 編譯".to_string());
+            }
+            Some(port) => Some(AdminTlsConfig {
+                port,
+                cert_path: env.required("ADMIN_TLS_CERT", false)?,
+                key_path: env.required("ADMIN_TLS_KEY", false)?,
+                client_ca_path: env.required("ADMIN_TLS_CLIENT_CA", false)?,
+                clients: parse_client_rules(&env.required("ADMIN_TLS_CLIENTS", false)?)?,
+            }),
+            None => None,
+        };
+
+        let host = env.string("SERVER_HOST", "0.0.0.0", false);
+        let port = env.string("SERVER_PORT", "3000", false);
+        let trusted_proxies =
+            proxy::parse_ranges(&env.string("TRUSTED_PROXIES", "", false)).map_err(|e| format!("TRUSTED_PROXIES {}", e))?;
+        let webhook_max_body_bytes = env.parse("WEBHOOK_MAX_BODY_BYTES", Some(1_048_576usize))?.unwrap_or(1_048_576);
+        let webhook_response_timeout_ms = env.parse("WEBHOOK_RESPONSE_TIMEOUT_MS", Some(2000u64))?.unwrap_or(2000);
+        if webhook_response_timeout_ms == 0 {
+            return Err("WEBHOOK_RESPONSE_TIMEOUT_MS 必須大於 0".to_string());
+        }
+        let slow_request_ms = env.parse("SLOW_REQUEST_MS", Some(3000u64))?.unwrap_or(3000);
+        let trace_sampling = Sampling {
+            percent: env.parse("TRACE_SAMPLE_PERCENT", Some(100u8))?.unwrap_or(100),
+            slow_ms: env.parse("TRACE_SLOW_MS", Some(slow_request_ms))?.unwrap_or(slow_request_ms),
+        };
+        if trace_sampling.percent > 100 {
+            return Err("TRACE_SAMPLE_PERCENT 必須介於 0 到 100".to_string());
+        }
+        let sentry_dsn = env.optional("SENTRY_DSN", true);
+        let environment = env.string("BRIDGE_ENV", "production", false);
+        let sentry_environment = env.string("SENTRY_ENVIRONMENT", &environment, false);
+        let feature_flags_path = env.optional("FEATURE_FLAGS_PATH", false);
+        let default_locale = env.string("DEFAULT_LOCALE", "zh-TW", false);
+        let default_locale = Locale::from_tag(&default_locale)
+            .ok_or_else(|| format!("DEFAULT_LOCALE 僅支援 zh-TW、ja、en，目前為 {}", default_locale))?;
+        let locales_dir = env.optional("LOCALES_DIR", false);
+        let branding_path = env.optional("BRANDING_PATH", false);
+        let chains_path = env.optional("CHAINS_PATH", false);
+
+        if !env.errors.is_empty() {
+            return Err(env.errors.join("；"));
+        }
+
+        Ok(Self {
+            line_channel_access_token,
+            line_channel_secret,
+            token_signing_key,
+            line_channel_secret_previous,
+            line_channel_secret_previous_until,
+            line_api_base_url,
+            line_dry_run,
+            whatsapp,
+            matrix,
+            openclaw_base_url,
+            openclaw_gateway_token,
+            openclaw_model,
+            openclaw_streaming,
+            openclaw_metadata,
+            prompt_display_name,
+            context_history_turns,
+            session_gap_secs,
+            reply_language,
+            output_blocklist,
+            clarify_threshold,
+            repeat_answer_similarity,
+            fanout,
+            database_path,
+            compress_conversations,
+            snapshot_import_path,
+            public_base_url,
+            liff_id,
+            long_answer_chars,
+            answer_page_ttl_days,
+            ai_footer,
+            answer_quick_replies,
+            answer_menus,
+            answer_markdown_flex,
+            save_incoming_images,
+            image_max_bytes,
+            save_incoming_videos,
+            video_max_bytes,
+            save_incoming_audio,
+            audio_max_bytes,
+            file_handler,
+            file_max_bytes,
+            attachment_archive,
+            sticker_replies,
+            location_messages,
+            group_mention_only,
+            group_reply_mention,
+            group_reply_quote,
+            loading_animation_secs,
+            random_seed,
+            ai_footer_timezone,
+            reply_max_retries,
+            notify_pacing_ms,
+            notify_batch_window_secs,
+            profile_refresh_secs,
+            profile_refresh_rate,
+            user_rate_limit,
+            user_rate_window,
+            retention_days,
+            storage,
+            admin_token,
+            admin_line_user_id,
+            admin_direct_broadcast,
+            privacy_hash_key,
+            secrets,
+            admin_tls,
+            recovery,
+            incident_notice_after,
+            host,
+            port,
+            trusted_proxies,
+            webhook_max_body_bytes,
+            webhook_response_timeout_ms,
+            maintenance,
+            store_soft_limits,
+            slow_request_ms,
+            trace_sampling,
+            sentry_dsn,
+            sentry_environment,
+            environment,
+            feature_flags_path,
+            default_locale,
+            locales_dir,
+            branding_path,
+            chains_path,
+            entries: env.entries,
+        })
+    }
+
+    /// 依設定建立物件儲存；簽章網址的期限以 `clock` 判斷
+    pub fn blob_store(&self, clock: Arc<dyn Clock>) -> BlobStore {
+        match &self.storage {
+            StorageConfig::Local { root, public_base_url, signing_key } => {
+                BlobStore::local(root.clone(), public_base_url.clone(), signing_key.clone(), clock)
+            }
+            StorageConfig::S3 { endpoint, bucket, region, access_key_id, secret_access_key } => BlobStore::s3(
+                endpoint.clone(),
+                bucket.clone(),
+                region.clone(),
+                access_key_id.clone(),
+                secret_access_key.clone(),
+            ),
+            StorageConfig::WebDav { url, username, password, public_base_url, signing_key } => BlobStore::webdav(
+                url.clone(),
+                username.clone(),
+                password.clone(),
+                public_base_url.clone(),
+                signing_key.clone(),
+                clock,
+            ),
+        }
+    }
+
+    /// 產生有效設定報告
+    pub fn report(&self, blobs: &BlobStore) -> ConfigReport {
+        let public_media = match &self.storage {
+            StorageConfig::Local { public_base_url, .. } | StorageConfig::WebDav { public_base_url, .. } => public_base_url.is_some(),
+            StorageConfig::S3 { .. } => true,
+        };
+        let features = vec![
+            FeatureStatus {
+                name: "admin_api",
+                enabled: self.admin_token.is_some(),
+                detail: "管理 API（/admin）".to_string(),
+            },
+            FeatureStatus {
+                name: "announcements",
+                enabled: self.admin_line_user_id.is_some(),
+                detail: "公告預覽與核准（需 ADMIN_LINE_USER_ID）".to_string(),
+            },
+            FeatureStatus {
+                name: "direct_broadcast",
+                enabled: self.admin_direct_broadcast,
+                detail: "POST /admin/broadcast 不經核准直接廣播（每次皆記錄為公告並寫入稽核紀錄）".to_string(),
+            },
+            FeatureStatus {
+                name: "archival",
+                enabled: self.retention_days.is_some(),
+                detail: match self.retention_days {
+                    Some(days) => format!("對話保存 {} 天後封存", days),
+                    None => "未設定 RETENTION_DAYS，不封存".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "db_maintenance",
+                enabled: self.maintenance.window.is_some(),
+                detail: match &self.maintenance.window {
+                    Some(window) => format!(
+                        "每天 {}（{}）刪除過期資料並執行 VACUUM；投票、問答、死信保留 {} 天，稽核紀錄{}",
+                        window.describe(),
+                        self.maintenance.timezone,
+                        self.maintenance.prune_after_days,
+                        match self.maintenance.audit_retention_days {
+                            Some(days) => format!("保留 {} 天", days),
+                            None => "不刪除".to_string(),
+                        }
+                    ),
+                    None => "未設定 DB_MAINTENANCE_WINDOW，僅可由 POST /admin/maintenance/run 手動執行".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "line_dry_run",
+                enabled: self.line_dry_run,
+                detail: "試運行：送往 LINE 的訊息只記錄於 GET /admin/dry-run，不實際送出".to_string(),
+            },
+            FeatureStatus {
+                name: "whatsapp",
+                enabled: self.whatsapp.is_some(),
+                detail: match &self.whatsapp {
+                    Some(whatsapp) => format!("WhatsApp 號碼 {} 的文字訊息交給 OpenClaw（webhook：/whatsapp/webhook）", whatsapp.phone_number_id),
+                    None => "WhatsApp Cloud API 通道（需 WHATSAPP_PHONE_NUMBER_ID 等設定）".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "matrix",
+                enabled: self.matrix.is_some(),
+                detail: match &self.matrix {
+                    Some(matrix) => format!(
+                        "Matrix（{}）一對一對話與房間中的提及交給 OpenClaw，啟動時加入 {} 個房間{}",
+                        matrix.homeserver_url,
+                        matrix.rooms.len(),
+                        if matrix.store_passphrase.is_none() { "；⚠️ 未設定 MATRIX_STORE_PASSPHRASE，E2EE 金鑰以明文儲存" } else { "" }
+                    ),
+                    None => "Matrix 通道（需以 `--features matrix` 編譯並設定 MATRIX_HOMESERVER_URL 與 MATRIX_ACCESS_TOKEN）".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "trace_sampling",
+                enabled: self.trace_sampling.percent < 100,
+                detail: if self.trace_sampling.percent < 100 {
+                    let always = match self.trace_sampling.slow_ms {
+                        0 => "失敗".to_string(),
+                        slow_ms => format!("失敗或超過 {} ms", slow_ms),
+                    };
+                    format!(
+                        "處理追蹤只保留 {}% 的正常事件，{}的事件一律保留（可由 PUT /admin/traces/sampling 調整）",
+                        self.trace_sampling.percent, always
+                    )
+                } else {
+                    "保留所有事件的處理追蹤".to_string()
+                },
+            },
+            FeatureStatus {
+                name: "trusted_proxies",
+                enabled: !self.trusted_proxies.is_empty(),
+                detail: if self.trusted_proxies.is_empty() {
+                    "不採用轉送標頭，用戶端 IP 一律為直接連線的位址".to_string()
+                } else {
+                    format!(
+                        "來自 {} 的連線採用 Forwarded／X-Forwarded-For 中的用戶端 IP",
+                        self.trusted_proxies.iter().map(ToString::to_string).collect::<Vec<_>>().join("、")
+                    )
+                },
+            },
+            FeatureStatus {
+                name: "channel_secret_rotation",
+                enabled: self.line_channel_secret_previous.is_some(),
+                detail: match self.line_channel_secret_previous_until {
+                    Some(until) => format!("Webhook 簽名一併接受舊的 channel secret，至 {} 為止", until.to_rfc3339()),
+                    None => "Webhook 簽名只接受目前的 channel secret".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "public_media",
+                enabled: public_media,
+                detail: "媒體簽章網址（本機儲存需 PUBLIC_BASE_URL）".to_string(),
+            },
+            FeatureStatus {
+                name: "liff_pages",
+                enabled: self.liff_id.is_some() || self.public_base_url.is_some(),
+                detail: match (&self.liff_id, &self.public_base_url) {
+                    (Some(id), _) => format!("LIFF 頁面（{}）", id),
+                    (None, Some(base)) => format!("網頁（{}/liff）", base),
+                    (None, None) => "未設定 PUBLIC_BASE_URL / LIFF_ID，程式碼不提供複製按鈕".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "answer_pages",
+                enabled: self.long_answer_chars > 0 && (self.liff_id.is_some() || self.public_base_url.is_some()),
+                detail: if self.long_answer_chars > 0 {
+                    format!("超過 {} 字的回答改以完整回答頁呈現（保存 {} 天）", self.long_answer_chars, self.answer_page_ttl_days)
+                } else {
+                    "LONG_ANSWER_CHARS=0，停用".to_string()
+                },
+            },
+            FeatureStatus {
+                name: "openclaw_streaming",
+                enabled: self.openclaw_streaming,
+                detail: "以串流取得回答並記錄首 token 時間與 tokens/sec（/metrics 與稽核紀錄）".to_string(),
+            },
+            FeatureStatus {
+                name: "openclaw_metadata",
+                enabled: self.openclaw_metadata,
+                detail: "請求附上以 PRIVACY_HASH_KEY 雜湊的使用者 ID 與對話來源（user／metadata），供 OpenClaw 端記錄與依使用者提供功能".to_string(),
+            },
+            FeatureStatus {
+                name: "prompt_display_name",
+                enabled: self.prompt_display_name && self.privacy_hash_key.is_none(),
+                detail: if self.prompt_display_name && self.privacy_hash_key.is_some() {
+                    "已設定但隱私模式開啟，不會把顯示名稱送往 OpenClaw".to_string()
+                } else {
+                    "以系統指示附上使用者的 LINE 顯示名稱（快取於個人資料），讓 OpenClaw 可以稱呼對方".to_string()
+                },
+            },
+            FeatureStatus {
+                name: "reply_language",
+                enabled: self.reply_language.is_some(),
+                detail: match self.reply_language {
+                    Some(language) => format!("要求以指定語言回答（{}），偵測到其他語言時請 OpenClaw 改寫一次", language.describe()),
+                    None => "未設定 REPLY_LANGUAGE，不限制回答語言".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "output_moderation",
+                enabled: !self.output_blocklist.is_empty(),
+                detail: if self.output_blocklist.is_empty() {
+                    "未設定 OUTPUT_BLOCKLIST，不審查回答".to_string()
+                } else {
+                    format!("回答含 {} 個禁用詞之一時以更嚴格的指示重問一次，仍命中則改回政策訊息", self.output_blocklist.len())
+                },
+            },
+            FeatureStatus {
+                name: "clarification",
+                enabled: self.clarify_threshold.is_some(),
+                detail: match self.clarify_threshold {
+                    Some(threshold) => format!("回答信心低於 {} 時改問澄清問題（停用串流）", threshold),
+                    None => "未設定 CLARIFY_CONFIDENCE_THRESHOLD，一律直接回答".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "repeat_answer",
+                enabled: self.repeat_answer_similarity.is_some(),
+                detail: match self.repeat_answer_similarity {
+                    Some(similarity) => format!("回答與上一則的相似度達 {} 時改回覆簡短提示並詢問是否需要更詳細的說明", similarity),
+                    None => "未設定 REPEAT_ANSWER_SIMILARITY，重複的回答照常送出".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "fanout",
+                enabled: self.fanout.is_some(),
+                detail: match &self.fanout {
+                    Some(fanout) => format!(
+                        "平行詢問 {}，以 {} 擇優（功能旗標 fanout 限定使用者，停用串流）",
+                        fanout.models.join("、"),
+                        fanout.selector.name()
+                    ),
+                    None => "未設定 FANOUT_MODELS".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "ai_footer",
+                enabled: self.ai_footer,
+                detail: format!("AI 回答附上模型、生成時間（{}）與「AI 生成內容」頁尾", self.ai_footer_timezone),
+            },
+            FeatureStatus {
+                name: "answer_quick_replies",
+                enabled: self.answer_quick_replies,
+                detail: "AI 回答附上「重試」「幫助」「狀態」快速回覆".to_string(),
+            },
+            FeatureStatus {
+                name: "answer_menus",
+                enabled: self.answer_menus,
+                detail: "OpenClaw 可輸出 line-menu 區塊，以按鈕範本呈現互動選單".to_string(),
+            },
+            FeatureStatus {
+                name: "answer_markdown_flex",
+                enabled: self.answer_markdown_flex,
+                detail: "含標題、清單、粗體等 Markdown 語法的回答以 Flex bubble 呈現，超過大小上限時改送純文字".to_string(),
+            },
+            FeatureStatus {
+                name: "incoming_images",
+                enabled: self.save_incoming_images,
+                detail: format!("保存使用者傳來的圖片（上限 {} KB）", self.image_max_bytes / 1024),
+            },
+            FeatureStatus {
+                name: "incoming_videos",
+                enabled: self.save_incoming_videos,
+                detail: format!("保存使用者傳來的影片（上限 {} KB）", self.video_max_bytes / 1024),
+            },
+            FeatureStatus {
+                name: "incoming_audio",
+                enabled: self.save_incoming_audio,
+                detail: format!("保存使用者傳來的語音訊息（上限 {} KB）", self.audio_max_bytes / 1024),
+            },
+            FeatureStatus {
+                name: "incoming_files",
+                enabled: self.file_handler.is_some(),
+                detail: match &self.file_handler {
+                    Some(FileHandler::Store) => format!("檔案存入物件儲存（上限 {} KB）", self.file_max_bytes / 1024),
+                    Some(FileHandler::Webhook { url, .. }) => format!("檔案轉送 {}（上限 {} KB）", url, self.file_max_bytes / 1024),
+                    None => "未設定 FILE_HANDLER，檔案訊息回覆暫不支援".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "attachment_archive",
+                enabled: self.attachment_archive.is_some(),
+                detail: {
+                    let files = if self.file_handler.is_some() { "；檔案訊息仍依 FILE_HANDLER 處理" } else { "" };
+                    match &self.attachment_archive {
+                        Some(AttachmentLinks::Public(base_url)) => format!("媒體與檔案封存至 attachments/，回覆 {} 下的永久連結{}", base_url, files),
+                        Some(AttachmentLinks::Signed { base_url, .. }) => {
+                            format!("媒體與檔案封存至 attachments/，回覆 {}/attachments/ 的永久簽章連結{}", base_url, files)
+                        }
+                        None => "媒體與檔案封存並回覆永久連結（需 ATTACHMENT_LINK_BASE_URL 或 PUBLIC_BASE_URL）".to_string(),
+                    }
+                },
+            },
+            FeatureStatus {
+                name: "sticker_replies",
+                enabled: self.sticker_replies,
+                detail: "貼圖的關鍵字交給 OpenClaw 作為脈絡並回應".to_string(),
+            },
+            FeatureStatus {
+                name: "location_messages",
+                enabled: self.location_messages,
+                detail: "位置訊息的座標交給 OpenClaw，回答地點時附上地圖圖釘".to_string(),
+            },
+            FeatureStatus {
+                name: "group_mention_only",
+                enabled: self.group_mention_only,
+                detail: "群組與聊天室預設只在被 @提及時回應（可用 /mention on|off 個別設定）".to_string(),
+            },
+            FeatureStatus {
+                name: "group_reply_mention",
+                enabled: self.group_reply_mention,
+                detail: "群組與聊天室中的回覆開頭 @提及提問的使用者".to_string(),
+            },
+            FeatureStatus {
+                name: "group_reply_quote",
+                enabled: self.group_reply_quote,
+                detail: "群組與聊天室中的回覆引用使用者的原訊息".to_string(),
+            },
+            FeatureStatus {
+                name: "loading_animation",
+                enabled: self.loading_animation_secs > 0,
+                detail: format!("一對一聊天收到訊息時顯示輸入中動畫（最長 {} 秒，回覆送出即消失）", self.loading_animation_secs),
+            },
+            FeatureStatus {
+                name: "seeded_random",
+                enabled: self.random_seed.is_some(),
+                detail: match self.random_seed {
+                    Some(seed) => format!("以固定種子 {} 產生亂數，結果可重現（正式環境請勿設定）", seed),
+                    None => "未設定 RANDOM_SEED，以系統亂數產生".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "profile_refresh",
+                enabled: self.profile_refresh_secs.is_some(),
+                detail: match self.profile_refresh_secs {
+                    Some(secs) => format!("每 {} 小時更新使用者個人資料（每秒最多 {} 次）", secs / 3600, self.profile_refresh_rate),
+                    None => "未設定 PROFILE_REFRESH_HOURS".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "conversation_compression",
+                enabled: self.compress_conversations,
+                detail: "以 zstd 字典壓縮對話內容（既有對話於資料庫維護時逐批壓縮）".to_string(),
+            },
+            FeatureStatus {
+                name: "snapshot_import",
+                enabled: self.snapshot_import_path.is_some(),
+                detail: match &self.snapshot_import_path {
+                    Some(path) => format!("啟動時匯入使用者資料快照 {}（已有資料者保留現有內容）", path),
+                    None => "未設定 SNAPSHOT_IMPORT_PATH".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "user_rate_limit",
+                enabled: self.user_rate_limit.is_some(),
+                detail: match self.user_rate_limit {
+                    Some(limit) => format!("每位使用者每 {} 秒最多 {} 則 AI 訊息", self.user_rate_window.as_secs(), limit),
+                    None => "未設定 USER_RATE_LIMIT".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "secrets_manager",
+                enabled: self.secrets.is_some(),
+                detail: match &self.secrets {
+                    Some(secrets) if secrets.refresh.is_zero() => format!("{}（僅啟動時讀取）", secrets.backend.describe()),
+                    Some(secrets) => format!("{}（每 {} 分鐘輪替）", secrets.backend.describe(), secrets.refresh.as_secs() / 60),
+                    None => "未設定 SECRETS_BACKEND，僅使用環境變數".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "admin_mtls",
+                enabled: self.admin_tls.is_some(),
+                detail: match &self.admin_tls {
+                    Some(tls) => format!(
+                        "管理 API mTLS 監聽埠 {}（允許 {}）",
+                        tls.port,
+                        tls.clients.iter().map(|c| c.common_name.as_str()).collect::<Vec<_>>().join("、")
+                    ),
+                    None => "未設定 ADMIN_TLS_PORT".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "recovery",
+                enabled: self.recovery.is_some(),
+                detail: match &self.recovery {
+                    Some(recovery) => format!("離線 {} 分鐘後執行復原指令", recovery.after.as_secs() / 60),
+                    None => "未設定 OPENCLAW_RECOVERY_COMMAND".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "incident_notice",
+                enabled: self.incident_notice_after.is_some(),
+                detail: match self.incident_notice_after {
+                    Some(after) => format!("離線 {} 分鐘後通知最近一小時內互動過的使用者，恢復後再通知", after.as_secs() / 60),
+                    None => "未設定 INCIDENT_NOTICE_AFTER_MINUTES".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "error_reporting",
+                enabled: self.sentry_dsn.is_some(),
+                detail: format!("Sentry 錯誤回報（環境 {}）", self.sentry_environment),
+            },
+            FeatureStatus {
+                name: "privacy",
+                enabled: self.privacy_hash_key.is_some(),
+                detail: "日誌、錯誤回報與匯出中的使用者/群組 ID 以 HMAC 雜湊取代（PRIVACY_HASH_KEY）".to_string(),
+            },
+            FeatureStatus {
+                name: "feature_flags",
+                enabled: self.feature_flags_path.is_some(),
+                detail: match &self.feature_flags_path {
+                    Some(path) => format!("功能旗標 {}（環境 {}）", path, self.environment),
+                    None => "未設定 FEATURE_FLAGS_PATH，使用預設值".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "branding",
+                enabled: self.branding_path.is_some(),
+                detail: match &self.branding_path {
+                    Some(path) => format!("品牌設定 {}（名稱、個性、表情符號風格與問候語）", path),
+                    None => "未設定 BRANDING_PATH，使用預設名稱".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "chains",
+                enabled: self.chains_path.is_some(),
+                detail: match &self.chains_path {
+                    Some(path) => format!("多步驟回答流程 {}（/chain 或關鍵字觸發）", path),
+                    None => "未設定 CHAINS_PATH".to_string(),
+                },
+            },
+            FeatureStatus {
+                name: "parquet_export",
+                enabled: cfg!(feature = "parquet"),
+                detail: "Parquet 匯出（編譯選項 `parquet`）".to_string(),
+            },
+        ];
+
+        ConfigReport {
+            version: env!("CARGO_PKG_VERSION"),
+            model: self.openclaw_model.clone(),
+            storage: blobs.describe(),
+            features,
+            settings: self.entries.clone(),
+        }
+    }
+}
+
+/// 讀取環境變數並記錄每個設定的有效值與來源
+#[derive(Default)]
+struct EnvReader {
+    entries: Vec<ConfigEntry>,
+    /// `_FILE` 檔案讀取失敗的錯誤（讀取結束後一併回報）
+    errors: Vec<String>,
+}
+
+impl EnvReader {
+    /// 讀取非空的環境變數；未設定時改讀 `<KEY>_FILE` 指向的檔案（Docker secrets），回傳值與來源
+    fn raw(&mut self, key: &str) -> Option<(String, &'static str)> {
+        let var = |key: &str| std::env::var(key).ok().filter(|v| !v.trim().is_empty());
+        if let Some(value) = var(key) {
+            return Some((value, "env"));
+        }
+        let path = var(&format!("{}_FILE", key))?;
+        match std::fs::read_to_string(path.trim()) {
+            Ok(content) => Some(content.trim().to_string()).filter(|v| !v.is_empty()).map(|v| (v, "file")),
+            Err(e) => {
+                self.errors.push(format!("無法讀取 {}_FILE（{}）: {}", key, path.trim(), e));
+                None
+            }
+        }
+    }
+
+    fn record(&mut self, key: &'static str, value: Option<&str>, secret: bool, source: &'static str) {
+        let value = value.map(|v| if secret { mask(v) } else { v.to_string() });
+        self.entries.push(ConfigEntry { key, value, source });
+    }
+
+    fn optional(&mut self, key: &'static str, secret: bool) -> Option<String> {
+        match self.raw(key) {
+            Some((value, source)) => {
+                self.record(key, Some(&value), secret, source);
+                Some(value)
+            }
+            None => {
+                self.record(key, None, secret, "default");
+                None
+            }
+        }
+    }
+
+    fn required(&mut self, key: &'static str, secret: bool) -> Result<String, String> {
+        self.optional(key, secret).ok_or_else(|| match self.errors.last() {
+            Some(error) if error.starts_with(&format!("無法讀取 {}_FILE", key)) => error.clone(),
+            _ => format!("{} 環境變數未設定", key),
+        })
+    }
+
+    fn string(&mut self, key: &'static str, default: &str, secret: bool) -> String {
+        match self.raw(key) {
+            Some((value, source)) => {
+                self.record(key, Some(&value), secret, source);
+                value
+            }
+            None => {
+                self.record(key, Some(default), secret, "default");
+                default.to_string()
+            }
+        }
+    }
+
+    /// 讀取數值設定；格式錯誤時回傳錯誤而非默默使用預設值
+    fn parse<T: std::str::FromStr + ToString>(&mut self, key: &'static str, default: Option<T>) -> Result<Option<T>, String> {
+        let Some((value, source)) = self.raw(key) else {
+            self.record(key, default.as_ref().map(|d| d.to_string()).as_deref(), false, "default");
+            return Ok(default);
+        };
+        self.record(key, Some(&value), false, source);
+        value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{} 格式錯誤：{}", key, value))
+    }
+}
+
+/// 遮蔽機密值，只保留前 4 碼供辨識
+fn mask(value: &str) -> String {
+    if value.chars().count() <= 8 {
+        return "****".to_string();
+    }
+    format!("{}****", value.chars().take(4).collect::<String>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_rules_parse_names_and_paths() {
+        let rules = parse_client_rules("ci-bot=/admin/notify, /admin/templates;ops=*;").unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].common_name, "ci-bot");
+        assert_eq!(rules[0].paths, ["/admin/notify", "/admin/templates"]);
+        assert!(parse_client_rules("ci-bot").is_err());
+        assert!(parse_client_rules("ci-bot=").is_err());
+    }
+
+    #[test]
+    fn client_rule_prefixes_match_on_path_boundaries() {
+        let rule = ClientRule { common_name: "ci".to_string(), paths: vec!["/admin/notify".to_string(), "/admin/media/".to_string()] };
+        assert!(rule.allows("/admin/notify"));
+        assert!(rule.allows("/admin/notify/3/events"));
+        assert!(!rule.allows("/admin/notifyx"));
+        assert!(!rule.allows("/admin/notify-all"));
+        assert!(rule.allows("/admin/media/a.png"));
+        assert!(!rule.allows("/admin/media"));
+        assert!(!rule.allows("/admin/templates"));
+        let all = ClientRule { common_name: "ops".to_string(), paths: vec!["*".to_string()] };
+        assert!(all.allows("/admin/anything"));
+    }
+}
@@ -0,0 +1,51 @@
+//! 管理儀表板模組
+//! 靜態頁面，管理員輸入 `ADMIN_TOKEN` 後由瀏覽器直接呼叫管理 API；頁面本身不含任何機密
+
+use axum::{extract::State, response::Html};
+
+use crate::liff;
+use crate::SharedState;
+
+/// 儀表板頁面（`GET /dashboard`）
+pub async fn page(State(state): State<SharedState>) -> Html<String> {
+    let state = state.read().await;
+    let locale = state.i18n.default_locale();
+    Html(liff::page(locale, "管理儀表板", BODY))
+}
+
+/// 快速測試：以 `POST /admin/test-message` 模擬訊息，預覽回應或實際推送
+const BODY: &str = r#"<article>
+<h2>管理儀表板</h2>
+<p><label>ADMIN_TOKEN<br><input id="token" type="password" autocomplete="off" style="width:100%"></label></p>
+<h3>快速測試</h3>
+<p><label>使用者 ID<br><input id="user" placeholder="U..." style="width:100%"></label></p>
+<p><label>語系<br><select id="locale"><option value="">預設</option><option>zh-TW</option><option>en</option><option>ja</option></select></label></p>
+<p><label>訊息<br><textarea id="text" rows="3" style="width:100%"></textarea></label></p>
+<p><button id="test" type="button">測試</button></p>
+<p><button id="send" type="button" style="background:#888">測試並推送給使用者</button></p>
+<pre id="result" hidden></pre>
+</article>
+<script>
+const $ = (id) => document.getElementById(id);
+$("token").value = sessionStorage.getItem("admin_token") || "";
+async function run(confirm) {
+  if (confirm && !window.confirm("確定要推送回應給這位使用者？")) return;
+  sessionStorage.setItem("admin_token", $("token").value);
+  const result = $("result");
+  result.hidden = false;
+  result.textContent = "…";
+  const response = await fetch("admin/test-message", {
+    method: "POST",
+    headers: { "Authorization": "Bearer " + $("token").value, "Content-Type": "application/json" },
+    body: JSON.stringify({ user_id: $("user").value, text: $("text").value, locale: $("locale").value || null, confirm }),
+  });
+  const body = await response.text();
+  try {
+    result.textContent = response.status + "\n" + JSON.stringify(JSON.parse(body), null, 2);
+  } catch {
+    result.textContent = response.status + "\n" + body;
+  }
+}
+$("test").onclick = () => run(false);
+$("send").onclick = () => run(true);
+</script>"#;
@@ -0,0 +1,129 @@
+//! 資料集匯出模組
+//! 從對話紀錄取出「使用者提問 → AI 回答」配對，移除使用者 ID 並遮蔽個資，附上由後續對話推得的回饋標籤，
+//! 以 JSONL 輸出供微調或評估使用
+
+use futures::Stream;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use crate::redact;
+use crate::store::{ConversationRecord, ConversationStore, TimeRange};
+
+/// 每批從資料庫讀取的筆數
+const BATCH_SIZE: usize = 1000;
+
+/// 匯出選項
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    pub range: TimeRange,
+    /// 一併匯出 OpenClaw 失敗時的備援回應（預設略過，不適合當作訓練資料）
+    pub include_fallback: bool,
+}
+
+/// JSONL 的一行
+#[derive(Debug, Serialize)]
+struct Pair {
+    prompt: String,
+    response: String,
+    /// 回答的模型（`fallback` 為備援回應）
+    model: Option<String>,
+    /// 回饋標籤：`regenerated`（使用者隨即重問同一個問題）、`continued`（同一對話階段繼續提問）、`fallback`
+    labels: Vec<&'static str>,
+    /// 對話階段（同一階段的配對可還原多輪對話）
+    session: Option<i64>,
+    created_at: i64,
+}
+
+/// 每位使用者尚在處理中的狀態：等待回答的提問，與等待下一則提問以決定標籤的配對
+#[derive(Default)]
+struct UserState {
+    prompt: Option<ConversationRecord>,
+    pair: Option<(Pair, String)>,
+}
+
+/// 以串流方式匯出資料集；讀取與遮蔽在背景執行緒分批進行
+pub fn stream(store: Arc<ConversationStore>, options: Options) -> impl Stream<Item = Result<Vec<u8>, String>> {
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, String>>(4);
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = write(&store, options, &tx) {
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+    futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) })
+}
+
+fn write(store: &ConversationStore, options: Options, tx: &mpsc::Sender<Result<Vec<u8>, String>>) -> Result<(), String> {
+    let mut users: HashMap<String, UserState> = HashMap::new();
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+    let mut after_id = 0;
+    loop {
+        let batch = store
+            .conversation_page(options.range, after_id, BATCH_SIZE)
+            .map_err(|e| format!("讀取對話紀錄失敗: {}", e))?;
+        let Some(last) = batch.last() else { break };
+        after_id = last.id;
+
+        let mut out = Vec::new();
+        for record in batch {
+            let state = users.entry(record.user_id.clone()).or_default();
+            match record.role.as_str() {
+                "user" => {
+                    if let Some((mut pair, prompt)) = state.pair.take() {
+                        if record.session_id.is_some() && record.session_id == pair.session {
+                            pair.labels.push(if record.content.trim() == prompt.trim() { "regenerated" } else { "continued" });
+                        }
+                        emit(&mut out, &pair, options)?;
+                    }
+                    state.prompt = Some(record);
+                }
+                "assistant" => {
+                    let Some(prompt) = state.prompt.take() else { continue };
+                    let names = names.entry(record.user_id.clone()).or_insert_with(|| display_names(store, &record.user_id));
+                    let fallback = record.model.as_deref() == Some("fallback");
+                    let pair = Pair {
+                        prompt: redact::redact(&prompt.content, names),
+                        response: redact::redact(&record.content, names),
+                        model: record.model,
+                        labels: if fallback { vec!["fallback"] } else { Vec::new() },
+                        session: prompt.session_id,
+                        created_at: prompt.created_at,
+                    };
+                    state.pair = Some((pair, prompt.content));
+                }
+                _ => {}
+            }
+        }
+        // 接收端已關閉（下載中斷）時停止讀取
+        if !out.is_empty() && tx.blocking_send(Ok(out)).is_err() {
+            return Ok(());
+        }
+    }
+
+    let mut out = Vec::new();
+    for (pair, _) in users.into_values().filter_map(|state| state.pair) {
+        emit(&mut out, &pair, options)?;
+    }
+    if !out.is_empty() {
+        let _ = tx.blocking_send(Ok(out));
+    }
+    Ok(())
+}
+
+fn emit(out: &mut Vec<u8>, pair: &Pair, options: Options) -> Result<(), String> {
+    if pair.labels.contains(&"fallback") && !options.include_fallback {
+        return Ok(());
+    }
+    serde_json::to_writer(&mut *out, pair).map_err(|e| e.to_string())?;
+    out.push(b'\n');
+    Ok(())
+}
+
+/// 使用者的顯示名稱（遮蔽用）；沒有快取的個人資料時回傳空清單
+fn display_names(store: &ConversationStore, user_id: &str) -> Vec<String> {
+    match store.profile(user_id) {
+        Ok(profile) => profile.and_then(|p| p.display_name).into_iter().collect(),
+        Err(_) => Vec::new(),
+    }
+}
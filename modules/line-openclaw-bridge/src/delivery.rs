@@ -0,0 +1,231 @@
+//! 回覆投遞模組
+//! reply 遇到 5xx 或連線錯誤時重試，仍失敗或 reply token 已過期、已使用（400 `Invalid reply token`）時改以 push 送出，
+//! 訊息內容不合法等其他 4xx 與最後仍失敗者寫入死信佇列；
+//! 每一步都以原因代碼記入稽核紀錄。
+//! 超過單次回覆則數上限的訊息在回覆成功後以 push 接著送出；一對一聊天在處理訊息前先顯示載入動畫。
+//! push 的重試沿用同一個 `X-Line-Retry-Key`，不會重複送達
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::line::{OutgoingMessage, Source, MAX_MESSAGES};
+use crate::pipeline;
+use crate::privacy;
+use crate::profiles;
+use crate::reporting;
+use crate::store::AuditEvent;
+use crate::AppState;
+
+/// 第一次重試前的等待時間，之後每次加倍（reply token 約一分鐘內有效）
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// 重試等待時間的隨機調整比例
+const RETRY_JITTER: f64 = 0.2;
+/// push 遇到暫時性錯誤時的重試次數
+const PUSH_MAX_RETRIES: u32 = 2;
+
+/// 失敗原因代碼：`<步驟>_5xx`、`<步驟>_4xx`、`<步驟>_timeout` 或 `<步驟>_network`；reply token 無效時為 `reply_invalid_token`
+fn reason_code(step: &str, error: &reqwest::Error) -> String {
+    let kind = match error.status() {
+        Some(status) if status.is_server_error() => "5xx",
+        Some(_) => "4xx",
+        None if error.is_timeout() => "timeout",
+        None => "network",
+    };
+    format!("{}_{}", step, kind)
+}
+
+/// 是否為暫時性錯誤（5xx、逾時或連線失敗）
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.status().is_none_or(|s| s.is_server_error())
+}
+
+/// LINE 的 `X-Line-Retry-Key`（UUID v4 格式）。一律取自作業系統亂數：LINE 保留 key 24 小時，
+/// 重複的 key 會被當成已送達（409），以固定種子產生會讓重新啟動後的推送被靜默丟棄
+pub fn retry_key() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let id = hex::encode(bytes);
+    format!("{}-{}-4{}-a{}-{}", &id[0..8], &id[8..12], &id[13..16], &id[17..20], &id[20..32])
+}
+
+/// 以 push 送出，暫時性錯誤時退避重試；每次重試附上同一個 retry key，
+/// 前一次其實已送達（例如回應逾時）時 LINE 不會再送一次
+pub async fn push(state: &AppState, to: &str, messages: &[OutgoingMessage]) -> Result<(), reqwest::Error> {
+    let retry_key = retry_key();
+    let mut attempt = 0;
+    loop {
+        match state.line_client.push_with_retry_key(to, messages, Some(&retry_key)).await {
+            Err(e) if is_transient(&e) && attempt < PUSH_MAX_RETRIES => {
+                warn!("Push to {} failed, retrying with the same retry key: {}", privacy::id(to), e);
+                tokio::time::sleep(state.random.jitter(RETRY_BASE_DELAY * 2u32.pow(attempt), RETRY_JITTER)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 一對一聊天顯示載入動畫，讓使用者知道回答正在產生；須在回覆之前完成，否則動畫會在回覆後才出現。
+/// 失敗不影響後續處理
+pub async fn start_loading(state: &AppState, source: &Source) {
+    let seconds = state.config.loading_animation_secs;
+    let Some(user_id) = source.user_id.as_deref() else { return };
+    if seconds == 0 || source.is_group() {
+        return;
+    }
+    if let Err(e) = state.line_client.start_loading(user_id, seconds).await {
+        warn!("Failed to start loading animation for {}: {}", privacy::id(user_id), e);
+    }
+}
+
+/// 回覆訊息：reply 重試 → push 至 `target` → 死信佇列；沒有訊息（例如回答已被 `/stop` 取消）時不回覆。
+/// 超過 [`MAX_MESSAGES`] 則時，前面的訊息以 reply 送出，其餘每 [`MAX_MESSAGES`] 則一批 push 至 `target`
+pub async fn reply(
+    state: &AppState,
+    event_type: &str,
+    user_id: &str,
+    target: &str,
+    reply_token: &str,
+    mut messages: Vec<OutgoingMessage>,
+) {
+    if messages.is_empty() {
+        return;
+    }
+    let overflow = messages.split_off(messages.len().min(MAX_MESSAGES));
+    let started = Instant::now();
+    let outcome = send(state, event_type, user_id, target, reply_token, &messages).await;
+    let mut detail = format!("messages={}", messages.len());
+    // 回覆沒有送達時不再補送後續訊息，避免使用者只收到後半段
+    if !overflow.is_empty() && outcome != "dead_letter" {
+        let pushed = push_overflow(state, event_type, user_id, target, overflow).await;
+        detail.push_str(&format!(" overflow={}", pushed));
+    }
+    pipeline::record("deliver", started, outcome, Some(detail));
+}
+
+/// 以 push 分批送出超過單次回覆上限的訊息；失敗時剩餘的訊息寫入死信佇列。回傳成功送出的則數
+async fn push_overflow(
+    state: &AppState,
+    event_type: &str,
+    user_id: &str,
+    target: &str,
+    messages: Vec<OutgoingMessage>,
+) -> usize {
+    for (index, batch) in messages.chunks(MAX_MESSAGES).enumerate() {
+        if let Err(e) = push(state, target, batch).await {
+            let reason = reason_code("push", &e);
+            warn!("Overflow push to {} failed ({}): {}", privacy::id(target), reason, e);
+            profiles::record_push_failure(state, target, &e);
+            audit(state, user_id, event_type, "push_overflow", "failed", &format!("reason={} error={}", reason, e));
+            let sent = index * MAX_MESSAGES;
+            dead_letter(state, event_type, user_id, target, &messages[sent..], &reason, &[format!("{}: {}", reason, e)]);
+            return sent;
+        }
+    }
+    info!("Pushed {} overflow messages to {}", messages.len(), privacy::id(target));
+    audit(state, user_id, event_type, "push_overflow", "ok", &format!("messages={}", messages.len()));
+    messages.len()
+}
+
+/// 依序嘗試 reply、push 與死信佇列，回傳 `reply`、`push_fallback` 或 `dead_letter`
+async fn send(
+    state: &AppState,
+    event_type: &str,
+    user_id: &str,
+    target: &str,
+    reply_token: &str,
+    messages: &[OutgoingMessage],
+) -> &'static str {
+    let max_retries = state.config.reply_max_retries;
+    let mut steps = Vec::new();
+    let mut attempt = 0;
+    let reason = loop {
+        // reply token 過期（約一分鐘）或已使用；OpenClaw 回答太慢時最常見
+        let (error, invalid_token) = match state.line_client.reply_messages(reply_token, messages).await {
+            Ok(()) => {
+                if attempt > 0 {
+                    info!("Reply to {} succeeded after {} retries", privacy::id(user_id), attempt);
+                    let detail = format!("reason=reply_retry_ok attempts={}", attempt + 1);
+                    audit(state, user_id, event_type, "reply", "ok", &detail);
+                }
+                return "reply";
+            }
+            Err(e) => (e.error, e.invalid_token),
+        };
+        let reason = if invalid_token { "reply_invalid_token".to_string() } else { reason_code("reply", &error) };
+        steps.push(format!("{}: {}", reason, error));
+        if !is_transient(&error) || attempt >= max_retries {
+            warn!("Reply to {} failed ({}): {}", privacy::id(user_id), reason, error);
+            audit(state, user_id, event_type, "reply", "failed", &format!("reason={} attempt={} error={}", reason, attempt + 1, error));
+            // token 過期或已使用時同樣改以 push 送出；其他 4xx（內容錯誤、權限）push 也無法補救，直接進死信佇列
+            if !is_transient(&error) && !invalid_token {
+                break reason;
+            }
+            match push(state, target, messages).await {
+                Ok(()) => {
+                    info!("Delivered reply to {} via push fallback", privacy::id(target));
+                    audit(state, user_id, event_type, "push_fallback", "ok", &format!("reason={}", reason));
+                    return "push_fallback";
+                }
+                Err(e) => {
+                    let reason = reason_code("push", &e);
+                    steps.push(format!("{}: {}", reason, e));
+                    warn!("Push fallback to {} failed ({}): {}", privacy::id(target), reason, e);
+                    profiles::record_push_failure(state, target, &e);
+                    audit(state, user_id, event_type, "push_fallback", "failed", &format!("reason={} error={}", reason, e));
+                    break reason;
+                }
+            }
+        }
+        warn!("Reply to {} failed ({}), retrying: {}", privacy::id(user_id), reason, error);
+        audit(state, user_id, event_type, "reply", "retry", &format!("reason={} attempt={}", reason, attempt + 1));
+        tokio::time::sleep(state.random.jitter(RETRY_BASE_DELAY * 2u32.pow(attempt), RETRY_JITTER)).await;
+        attempt += 1;
+    };
+
+    dead_letter(state, event_type, user_id, target, messages, &reason, &steps);
+    "dead_letter"
+}
+
+/// 寫入死信佇列並回報錯誤
+fn dead_letter(
+    state: &AppState,
+    event_type: &str,
+    user_id: &str,
+    target: &str,
+    messages: &[OutgoingMessage],
+    reason: &str,
+    steps: &[String],
+) {
+    let payload = serde_json::to_value(messages).unwrap_or_default();
+    let detail = match state.store.add_dead_letter(user_id, target, event_type, &payload, reason, steps) {
+        Ok(id) => format!("reason={} dead_letter={}", reason, id),
+        Err(e) => {
+            error!("Failed to write dead letter: {}", e);
+            format!("reason={} dead_letter=none", reason)
+        }
+    };
+    error!("Reply to {} dead-lettered ({})", privacy::id(user_id), reason);
+    reporting::capture(reporting::Level::Error, "reply dead-lettered", &reporting::Context {
+        event_type: Some(event_type),
+        user_id: Some(user_id),
+        model: None,
+        detail: Some(&detail),
+    });
+    audit(state, user_id, event_type, "dead_letter", "error", &detail);
+}
+
+fn audit(state: &AppState, user_id: &str, event_type: &str, action: &str, status: &str, detail: &str) {
+    if let Err(e) = state.store.audit(&AuditEvent {
+        user_id,
+        event_type,
+        action,
+        status,
+        detail: Some(detail),
+        ..Default::default()
+    }) {
+        warn!("Failed to write audit log: {}", e);
+    }
+}
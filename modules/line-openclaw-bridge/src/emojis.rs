@@ -0,0 +1,201 @@
+//! LINE 表情貼模組
+//! 品牌檔設定 `[emojis]` 時，AI 可在回答中寫 `$名稱$`，送出前轉成文字訊息的 `emojis` 替換（文字中留下 `$` 佔位字元）；
+//! Flex 訊息無法顯示表情貼，佔位直接移除；未設定的名稱與一般的 `$` 維持原文
+
+use tracing::warn;
+
+use crate::branding::Branding;
+use crate::line::{Emoji, FlexComponent, FlexContainer, OutgoingMessage};
+
+/// 單則文字訊息的表情貼上限
+const MAX_EMOJIS: usize = 20;
+/// 表情貼佔位字元
+const PLACEHOLDER: char = '$';
+
+/// 附在對話前的系統指示：可用的表情貼名稱；品牌檔未設定表情貼時回傳 None
+pub fn instruction(branding: &Branding) -> Option<String> {
+    if branding.emojis.is_empty() {
+        return None;
+    }
+    let mut names: Vec<String> = branding.emojis.keys().map(|name| format!("${}$", name)).collect();
+    names.sort_unstable();
+    Some(format!(
+        "You may insert LINE emoji inline by writing their placeholders exactly as shown: {}. \
+         Use at most a few per answer and never explain the placeholders.",
+        names.join(", ")
+    ))
+}
+
+/// 將 `$名稱$` 換成 `$` 並回傳對應的表情貼；超過上限的佔位略過
+pub fn substitute(branding: &Branding, text: &str) -> (String, Vec<Emoji>) {
+    let mut output = String::with_capacity(text.len());
+    let mut emojis = Vec::new();
+    // LINE 以 UTF-16 計算位置
+    let mut index = 0;
+    let mut dropped = 0;
+    let mut rest = text;
+    while let Some(start) = rest.find(PLACEHOLDER) {
+        let before = &rest[..start];
+        output.push_str(before);
+        index += before.encode_utf16().count();
+        let after = &rest[start + 1..];
+        let emoji = after
+            .find(PLACEHOLDER)
+            .and_then(|end| Some((end, branding.emojis.get(&after[..end])?)));
+        match emoji {
+            Some((end, _)) if emojis.len() >= MAX_EMOJIS => {
+                dropped += 1;
+                rest = &after[end + 1..];
+            }
+            Some((end, emoji)) => {
+                emojis.push(Emoji { index, product_id: emoji.product_id.clone(), emoji_id: emoji.emoji_id.clone() });
+                output.push(PLACEHOLDER);
+                index += 1;
+                rest = &after[end + 1..];
+            }
+            None => {
+                output.push(PLACEHOLDER);
+                index += 1;
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+    if dropped > 0 {
+        warn!("Dropped {} emoji placeholders over the limit of {}", dropped, MAX_EMOJIS);
+    }
+    (output, emojis)
+}
+
+/// 移除已設定的 `$名稱$` 佔位（Flex 文字無法顯示表情貼）；未設定的名稱與一般的 `$` 維持原文
+pub fn strip(branding: &Branding, text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(PLACEHOLDER) {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find(PLACEHOLDER).filter(|end| branding.emojis.contains_key(&after[..*end])) {
+            Some(end) => rest = &after[end + 1..],
+            None => {
+                output.push(PLACEHOLDER);
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// 替換回覆中各則文字訊息的表情貼佔位；Flex 訊息（Markdown 回答）的文字與替代文字則移除佔位
+pub fn apply(branding: &Branding, messages: &mut [OutgoingMessage]) {
+    if branding.emojis.is_empty() {
+        return;
+    }
+    for message in messages {
+        match message {
+            OutgoingMessage::Text(message) => {
+                let (text, emojis) = substitute(branding, &message.text);
+                message.text = text;
+                message.emojis = emojis;
+            }
+            OutgoingMessage::Flex(message) => {
+                message.alt_text = strip(branding, &message.alt_text);
+                let bubbles = match &mut message.contents {
+                    FlexContainer::Bubble(bubble) => std::slice::from_mut(bubble),
+                    FlexContainer::Carousel { contents } => contents.as_mut_slice(),
+                };
+                for bubble in bubbles {
+                    for component in [&mut bubble.header, &mut bubble.body, &mut bubble.footer].into_iter().flatten() {
+                        strip_component(branding, component);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn strip_component(branding: &Branding, component: &mut FlexComponent) {
+    match component {
+        FlexComponent::Box(flex_box) => {
+            for child in &mut flex_box.contents {
+                strip_component(branding, child);
+            }
+        }
+        FlexComponent::Text(text) => {
+            text.text = strip(branding, &text.text);
+            for span in &mut text.contents {
+                span.text = strip(branding, &span.text);
+            }
+            // LINE 不接受空字串
+            text.contents.retain(|span| !span.text.is_empty());
+            if text.text.is_empty() {
+                text.text = " ".to_string();
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::branding::EmojiRef;
+    use crate::line::{Bubble, FlexBox, FlexMessage, FlexSpan, FlexText};
+
+    fn branding() -> Branding {
+        let mut branding = Branding::default();
+        let emoji = EmojiRef { product_id: "p1".to_string(), emoji_id: "001".to_string() };
+        branding.emojis.insert("smile".to_string(), emoji);
+        branding
+    }
+
+    fn indices(emojis: &[Emoji]) -> Vec<usize> {
+        emojis.iter().map(|e| e.index).collect()
+    }
+
+    #[test]
+    fn substitute_replaces_configured_placeholders() {
+        let (text, emojis) = substitute(&branding(), "嗨$smile$，今天好嗎$smile$");
+        assert_eq!(text, "嗨$，今天好嗎$");
+        assert_eq!(indices(&emojis), [1, 7]);
+        assert!(emojis.iter().all(|e| e.product_id == "p1" && e.emoji_id == "001"));
+    }
+
+    #[test]
+    fn substitute_counts_utf16_units_and_keeps_plain_dollars() {
+        let (text, emojis) = substitute(&branding(), "😀$smile$ 要 $5 與 $unknown$，再$smile$");
+        assert_eq!(text, "😀$ 要 $5 與 $unknown$，再$");
+        // 😀 佔兩個 UTF-16 單位；未設定的名稱與一般的 `$` 照原文計算長度
+        assert_eq!(indices(&emojis), [2, 22]);
+    }
+
+    #[test]
+    fn substitute_drops_placeholders_over_the_limit() {
+        let text = "$smile$".repeat(MAX_EMOJIS + 2);
+        let (output, emojis) = substitute(&branding(), &text);
+        assert_eq!(emojis.len(), MAX_EMOJIS);
+        assert_eq!(output, "$".repeat(MAX_EMOJIS));
+        assert_eq!(emojis.last().unwrap().index, MAX_EMOJIS - 1);
+    }
+
+    #[test]
+    fn strip_removes_only_configured_placeholders() {
+        let branding = branding();
+        assert_eq!(strip(&branding, "嗨$smile$！"), "嗨！");
+        assert_eq!(strip(&branding, "$5 與 $unknown$"), "$5 與 $unknown$");
+        assert_eq!(strip(&branding, "$smile$"), "");
+    }
+
+    #[test]
+    fn apply_strips_placeholders_from_flex_answers() {
+        let text = FlexText::new("嗨$smile$").spans(vec![FlexSpan::new("$smile$"), FlexSpan::new("嗨")]);
+        let bubble = Bubble::new().body(FlexBox::vertical(vec![text.into()]));
+        let mut messages = vec![FlexMessage::new("嗨$smile$", bubble).into()];
+        apply(&branding(), &mut messages);
+        let json = serde_json::to_value(&messages[0]).unwrap();
+        assert_eq!(json["altText"], "嗨");
+        assert_eq!(json["contents"]["body"]["contents"][0]["text"], "嗨");
+        assert_eq!(json["contents"]["body"]["contents"][0]["contents"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,211 @@
+//! 匯出加密模組
+//! 依每次請求指定的收件者金鑰加密匯出資料：`age1…` 以 age 加密，其他值視為 GPG 收件者（金鑰須已匯入伺服器的 keyring）；
+//! 加密與匯出同樣以串流進行，不會一次載入全部資料
+
+use futures::{Stream, StreamExt};
+use std::io::Write;
+use std::process::Stdio;
+use std::str::FromStr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+/// 每次從 gpg 讀取的位元組數
+const READ_CHUNK: usize = 64 * 1024;
+
+/// 加密的收件者
+#[derive(Clone)]
+pub enum Recipient {
+    Age(age::x25519::Recipient),
+    /// GPG 金鑰 ID、指紋或 email
+    Gpg(String),
+}
+
+impl Recipient {
+    /// 解析收件者；GPG 收件者會先確認 keyring 中有此公鑰
+    pub async fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.starts_with("age1") {
+            return age::x25519::Recipient::from_str(value)
+                .map(Recipient::Age)
+                .map_err(|e| format!("age 收件者格式錯誤：{}", e));
+        }
+        if value.is_empty() || value.starts_with('-') {
+            return Err("收件者不可為空或以 - 開頭".to_string());
+        }
+        let status = Command::new("gpg")
+            .args(["--batch", "--list-keys", "--", value])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .map_err(|e| format!("無法執行 gpg：{}", e))?;
+        if !status.success() {
+            return Err(format!("GPG keyring 中找不到收件者 {} 的公鑰", value));
+        }
+        Ok(Recipient::Gpg(value.to_string()))
+    }
+
+    /// 加密檔的副檔名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Recipient::Age(_) => "age",
+            Recipient::Gpg(_) => "gpg",
+        }
+    }
+}
+
+/// 將匯出串流加密成另一個串流；任一端出錯時以錯誤結束
+pub fn encrypt(
+    input: impl Stream<Item = Result<Vec<u8>, String>> + Send + 'static,
+    recipient: Recipient,
+) -> impl Stream<Item = Result<Vec<u8>, String>> {
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, String>>(4);
+    match recipient {
+        Recipient::Age(recipient) => {
+            let (input_tx, input_rx) = mpsc::channel(4);
+            tokio::spawn(forward(input, input_tx));
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = encrypt_age(input_rx, &recipient, &tx) {
+                    let _ = tx.blocking_send(Err(e));
+                }
+            });
+        }
+        Recipient::Gpg(recipient) => {
+            tokio::spawn(async move {
+                if let Err(e) = encrypt_gpg(input, &recipient, &tx).await {
+                    let _ = tx.send(Err(e)).await;
+                }
+            });
+        }
+    }
+    futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) })
+}
+
+async fn forward(input: impl Stream<Item = Result<Vec<u8>, String>>, tx: mpsc::Sender<Result<Vec<u8>, String>>) {
+    let mut input = Box::pin(input);
+    while let Some(chunk) = input.next().await {
+        if tx.send(chunk).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// 在背景執行緒以 age 加密，輸出經 channel 送回
+fn encrypt_age(
+    mut input: mpsc::Receiver<Result<Vec<u8>, String>>,
+    recipient: &age::x25519::Recipient,
+    tx: &mpsc::Sender<Result<Vec<u8>, String>>,
+) -> Result<(), String> {
+    let encryptor = age::Encryptor::with_recipients(std::iter::once(recipient as &dyn age::Recipient))
+        .map_err(|e| format!("age 加密失敗：{}", e))?;
+    let mut writer = encryptor
+        .wrap_output(ChannelWriter(tx.clone()))
+        .map_err(|e| format!("age 加密失敗：{}", e))?;
+    while let Some(chunk) = input.blocking_recv() {
+        writer.write_all(&chunk?).map_err(|e| format!("age 加密失敗：{}", e))?;
+    }
+    writer.finish().map_err(|e| format!("age 加密失敗：{}", e))?;
+    Ok(())
+}
+
+/// 將 age 的輸出逐段送進 channel
+struct ChannelWriter(mpsc::Sender<Result<Vec<u8>, String>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .blocking_send(Ok(buf.to_vec()))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "export consumer went away"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 以系統的 gpg 加密：匯出資料寫入 stdin，加密結果自 stdout 讀出
+async fn encrypt_gpg(
+    input: impl Stream<Item = Result<Vec<u8>, String>> + Send + 'static,
+    recipient: &str,
+    tx: &mpsc::Sender<Result<Vec<u8>, String>>,
+) -> Result<(), String> {
+    let mut child = Command::new("gpg")
+        .args(["--batch", "--yes", "--trust-model", "always", "--encrypt", "--recipient", recipient, "--output", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("無法執行 gpg：{}", e))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    // stderr 須同時讀出，否則 gpg 輸出大量訊息塞滿管線時會卡住，stdout 也跟著停止
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let errors = tokio::spawn(async move {
+        let mut errors = Vec::new();
+        let _ = stderr.read_to_end(&mut errors).await;
+        errors
+    });
+
+    let writer = tokio::spawn(async move {
+        let mut input = Box::pin(input);
+        while let Some(chunk) = input.next().await {
+            stdin.write_all(&chunk?).await.map_err(|e| format!("寫入 gpg 失敗：{}", e))?;
+        }
+        // 關閉 stdin，gpg 才會輸出結尾
+        drop(stdin);
+        Ok::<_, String>(())
+    });
+
+    let mut buffer = vec![0u8; READ_CHUNK];
+    loop {
+        let read = stdout.read(&mut buffer).await.map_err(|e| format!("讀取 gpg 輸出失敗：{}", e))?;
+        if read == 0 {
+            break;
+        }
+        if tx.send(Ok(buffer[..read].to_vec())).await.is_err() {
+            return Ok(());
+        }
+    }
+    writer.await.map_err(|e| e.to_string())??;
+    let status = child.wait().await.map_err(|e| format!("gpg 執行失敗：{}", e))?;
+    let errors = errors.await.map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("gpg 加密失敗：{}", String::from_utf8_lossy(&errors).trim()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[tokio::test]
+    async fn age_output_decrypts_to_the_input() {
+        let identity = age::x25519::Identity::generate();
+        let chunks = futures::stream::iter(vec![Ok(b"first,".to_vec()), Ok(b"second".to_vec())]);
+        let encrypted: Vec<u8> = encrypt(chunks, Recipient::Age(identity.to_public()))
+            .map(Result::unwrap)
+            .concat()
+            .await;
+        let decryptor = age::Decryptor::new(&encrypted[..]).unwrap();
+        let mut plain = String::new();
+        decryptor
+            .decrypt(std::iter::once(&identity as &dyn age::Identity))
+            .unwrap()
+            .read_to_string(&mut plain)
+            .unwrap();
+        assert_eq!(plain, "first,second");
+    }
+
+    #[tokio::test]
+    async fn input_errors_end_the_encrypted_stream() {
+        let identity = age::x25519::Identity::generate();
+        let chunks = futures::stream::iter(vec![Ok(b"partial".to_vec()), Err("讀取失敗".to_string())]);
+        let results: Vec<_> = encrypt(chunks, Recipient::Age(identity.to_public())).collect().await;
+        assert_eq!(results.last(), Some(&Err("讀取失敗".to_string())));
+    }
+}
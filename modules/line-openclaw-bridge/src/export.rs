@@ -0,0 +1,256 @@
+//! 資料匯出模組
+//! 將稽核紀錄與用量統計以 CSV / Parquet 串流輸出
+
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use crate::privacy;
+use crate::store::{AuditRecord, ConversationStore, TimeRange};
+
+/// 每批從資料庫讀取的筆數
+const BATCH_SIZE: usize = 1000;
+
+/// 匯出的資料集
+#[derive(Debug, Clone, Copy, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Dataset {
+    /// 稽核紀錄
+    Audit,
+    /// 每日用量統計
+    Usage,
+}
+
+/// 匯出格式
+#[derive(Debug, Clone, Copy, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// 以串流方式匯出資料；資料庫讀取與編碼在背景執行緒分批進行，不會一次載入全部資料
+pub fn stream(
+    store: Arc<ConversationStore>,
+    dataset: Dataset,
+    range: TimeRange,
+    format: ExportFormat,
+) -> impl Stream<Item = Result<Vec<u8>, String>> {
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, String>>(4);
+
+    tokio::task::spawn_blocking(move || {
+        let result = match format {
+            ExportFormat::Csv => write_csv(&store, dataset, range, &tx),
+            ExportFormat::Parquet => parquet_export::write(&store, dataset, range, &tx),
+        };
+        if let Err(e) = result {
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+
+    futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) })
+}
+
+/// 逐批讀取稽核紀錄並交給 `emit`；隱私模式下 userId 以雜湊輸出
+fn for_each_audit_batch(
+    store: &ConversationStore,
+    range: TimeRange,
+    mut emit: impl FnMut(&[AuditRecord]) -> Result<bool, String>,
+) -> Result<(), String> {
+    let mut after_id = 0;
+    loop {
+        let mut batch = store
+            .audit_page(range, after_id, BATCH_SIZE)
+            .map_err(|e| format!("讀取稽核紀錄失敗: {}", e))?;
+        let Some(last) = batch.last() else { return Ok(()) };
+        after_id = last.id;
+        if privacy::enabled() {
+            for record in &mut batch {
+                record.user_id = privacy::id(&record.user_id).into_owned();
+            }
+        }
+        if !emit(&batch)? {
+            return Ok(());
+        }
+    }
+}
+
+fn write_csv(
+    store: &ConversationStore,
+    dataset: Dataset,
+    range: TimeRange,
+    tx: &mpsc::Sender<Result<Vec<u8>, String>>,
+) -> Result<(), String> {
+    match dataset {
+        Dataset::Audit => {
+            let mut first = true;
+            for_each_audit_batch(store, range, |batch| {
+                let bytes = encode_csv(batch, first)?;
+                first = false;
+                // 接收端已關閉（下載中斷）時停止讀取
+                Ok(tx.blocking_send(Ok(bytes)).is_ok())
+            })
+        }
+        Dataset::Usage => {
+            let usage = store.usage_daily(range).map_err(|e| format!("讀取用量統計失敗: {}", e))?;
+            let _ = tx.blocking_send(Ok(encode_csv(&usage, true)?));
+            Ok(())
+        }
+    }
+}
+
+fn encode_csv<T: Serialize>(rows: &[T], has_headers: bool) -> Result<Vec<u8>, String> {
+    let mut writer = csv::WriterBuilder::new().has_headers(has_headers).from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row).map_err(|e| e.to_string())?;
+    }
+    writer.into_inner().map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_export {
+    use super::*;
+    use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+    use crate::store::UsageRecord;
+    use parquet::arrow::ArrowWriter;
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    /// 讓 ArrowWriter 寫入共享緩衝區，以便每個 row group 完成後送出
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedBuffer {
+        fn take(&self) -> Vec<u8> {
+            std::mem::take(&mut self.0.lock().unwrap())
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn audit_schema() -> Arc<Schema> {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("created_at", DataType::Int64, false),
+            Field::new("user_id", DataType::Utf8, false),
+            Field::new("event_type", DataType::Utf8, false),
+            Field::new("action", DataType::Utf8, false),
+            Field::new("status", DataType::Utf8, false),
+            Field::new("model", DataType::Utf8, true),
+            Field::new("latency_ms", DataType::Int64, true),
+            Field::new("detail", DataType::Utf8, true),
+        ]))
+    }
+
+    fn usage_schema() -> Arc<Schema> {
+        Arc::new(Schema::new(vec![
+            Field::new("day", DataType::Utf8, false),
+            Field::new("model", DataType::Utf8, false),
+            Field::new("requests", DataType::Int64, false),
+            Field::new("fallbacks", DataType::Int64, false),
+            Field::new("errors", DataType::Int64, false),
+            Field::new("unique_users", DataType::Int64, false),
+            Field::new("avg_latency_ms", DataType::Float64, true),
+        ]))
+    }
+
+    fn audit_batch(schema: &Arc<Schema>, rows: &[AuditRecord]) -> Result<RecordBatch, String> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.id))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.created_at))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.user_id))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.event_type))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.action))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.status))),
+            Arc::new(StringArray::from_iter(rows.iter().map(|r| r.model.as_deref()))),
+            Arc::new(Int64Array::from_iter(rows.iter().map(|r| r.latency_ms))),
+            Arc::new(StringArray::from_iter(rows.iter().map(|r| r.detail.as_deref()))),
+        ];
+        RecordBatch::try_new(schema.clone(), columns).map_err(|e| e.to_string())
+    }
+
+    fn usage_batch(schema: &Arc<Schema>, rows: &[UsageRecord]) -> Result<RecordBatch, String> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.day))),
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.model))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.requests))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.fallbacks))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.errors))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.unique_users))),
+            Arc::new(Float64Array::from_iter(rows.iter().map(|r| r.avg_latency_ms))),
+        ];
+        RecordBatch::try_new(schema.clone(), columns).map_err(|e| e.to_string())
+    }
+
+    pub fn write(
+        store: &ConversationStore,
+        dataset: Dataset,
+        range: TimeRange,
+        tx: &mpsc::Sender<Result<Vec<u8>, String>>,
+    ) -> Result<(), String> {
+        let buffer = SharedBuffer::default();
+        let schema = match dataset {
+            Dataset::Audit => audit_schema(),
+            Dataset::Usage => usage_schema(),
+        };
+        let mut writer = ArrowWriter::try_new(buffer.clone(), schema.clone(), None).map_err(|e| e.to_string())?;
+
+        match dataset {
+            Dataset::Audit => for_each_audit_batch(store, range, |batch| {
+                writer.write(&audit_batch(&schema, batch)?).map_err(|e| e.to_string())?;
+                writer.flush().map_err(|e| e.to_string())?;
+                Ok(tx.blocking_send(Ok(buffer.take())).is_ok())
+            })?,
+            Dataset::Usage => {
+                let usage = store.usage_daily(range).map_err(|e| format!("讀取用量統計失敗: {}", e))?;
+                writer.write(&usage_batch(&schema, &usage)?).map_err(|e| e.to_string())?;
+            }
+        }
+
+        writer.close().map_err(|e| e.to_string())?;
+        let _ = tx.blocking_send(Ok(buffer.take()));
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "parquet"))]
+mod parquet_export {
+    use super::*;
+
+    pub fn write(
+        _store: &ConversationStore,
+        _dataset: Dataset,
+        _range: TimeRange,
+        _tx: &mpsc::Sender<Result<Vec<u8>, String>>,
+    ) -> Result<(), String> {
+        Err("此版本未啟用 Parquet 匯出（請以 `--features parquet` 編譯）".to_string())
+    }
+}
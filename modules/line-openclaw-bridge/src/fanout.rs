@@ -0,0 +1,208 @@
+//! 多模型擇優模組
+//! 同一則訊息平行詢問多個模型，再由評審提示（或回答長度、回應速度）選出較好的回答；
+//! 勝出的模型記錄在稽核紀錄，供日後比較模型表現
+
+use futures::future::{join_all, select_ok};
+use futures::FutureExt;
+use std::time::Instant;
+use tracing::{info, warn};
+
+use crate::openclaw::{ChatMessage, OpenClawClient};
+use crate::privacy;
+
+/// 擇優方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selector {
+    /// 以評審提示選出最佳回答，評審失敗時改選最長的回答
+    Judge,
+    /// 選最長的回答
+    Length,
+    /// 採用最先完成的回答（不等待其他模型）
+    Latency,
+}
+
+impl Selector {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "judge" => Some(Self::Judge),
+            "length" => Some(Self::Length),
+            "latency" => Some(Self::Latency),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Judge => "judge",
+            Self::Length => "length",
+            Self::Latency => "latency",
+        }
+    }
+}
+
+/// 多模型擇優設定
+#[derive(Debug, Clone)]
+pub struct FanoutConfig {
+    /// 平行詢問的模型（至少兩個）
+    pub models: Vec<String>,
+    pub selector: Selector,
+    /// 評審使用的模型，未設定則使用 OPENCLAW_MODEL
+    pub judge_model: Option<String>,
+}
+
+/// 單一模型的回答
+struct Candidate {
+    model: String,
+    answer: String,
+    latency_ms: u128,
+}
+
+/// 評審的系統指示
+fn judge_instruction(count: usize) -> String {
+    format!(
+        "You are judging {} candidate answers to the same user message. Pick the one that is most correct, helpful \
+         and complete, in the language the user wrote in. Reply with the number of the best answer only.",
+        count
+    )
+}
+
+/// 平行詢問各模型並選出回答；一併回傳勝出的模型與稽核紀錄用的說明。全部失敗時回傳最後一個錯誤
+pub async fn ask(
+    client: &OpenClawClient,
+    user_id: &str,
+    history: Vec<ChatMessage>,
+    text: &str,
+    config: &FanoutConfig,
+) -> Result<(String, String, String), String> {
+    let models = servable(client, &config.models);
+    info!("Fanning out to {} models for user={}", models.len(), privacy::id(user_id));
+    let mut messages = history;
+    messages.push(ChatMessage { role: "user".to_string(), content: text.to_string() });
+    let requests = models.iter().map(|model| {
+        let messages = messages.clone();
+        async move {
+            let started = Instant::now();
+            let answer = client.send_chat(user_id, Some(model), messages).await?;
+            Ok::<_, String>(Candidate { model: model.clone(), answer, latency_ms: started.elapsed().as_millis() })
+        }
+        .boxed()
+    });
+
+    if config.selector == Selector::Latency {
+        let (winner, _) = select_ok(requests).await?;
+        let detail = format!("fanout={} winner={} by=latency", models.join(","), winner.model);
+        return Ok((winner.answer, winner.model, detail));
+    }
+
+    let mut candidates = Vec::new();
+    let mut last_error = None;
+    for (model, result) in models.iter().zip(join_all(requests).await) {
+        match result {
+            Ok(candidate) => candidates.push(candidate),
+            Err(e) => {
+                warn!("Fan-out model {} failed: {}", model, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    let summary = candidates
+        .iter()
+        .map(|c| format!("{}:{}ms", c.model, c.latency_ms))
+        .collect::<Vec<_>>()
+        .join(",");
+    let (index, by) = match candidates.len() {
+        0 => return Err(last_error.unwrap_or_default()),
+        1 => (0, "only"),
+        _ if config.selector == Selector::Judge => match judge(client, user_id, text, &candidates, config).await {
+            Some(index) => (index, "judge"),
+            None => (longest(&candidates), "length"),
+        },
+        _ => (longest(&candidates), "length"),
+    };
+    let winner = candidates.swap_remove(index);
+    let detail = format!("fanout={} winner={} by={}", summary, winner.model, by);
+    Ok((winner.answer, winner.model, detail))
+}
+
+/// 實際詢問的模型：OpenClaw 不提供的模型會被改成目前使用的模型，變成同一個模型互相比較，因此略過（重複的也只問一次）；
+/// 全部都不提供時只詢問目前使用的模型
+fn servable(client: &OpenClawClient, models: &[String]) -> Vec<String> {
+    let mut servable: Vec<String> = Vec::new();
+    for model in models {
+        if !client.is_available(model) {
+            warn!("Fan-out model {} is not served by OpenClaw, skipping it", model);
+        } else if !servable.contains(model) {
+            servable.push(model.clone());
+        }
+    }
+    if servable.is_empty() {
+        let active = client.model();
+        warn!("No fan-out model is served by OpenClaw, asking only {}", active);
+        servable.push(active);
+    }
+    servable
+}
+
+/// 請評審選出最佳回答的索引；失敗或回覆無法解析時回傳 None
+async fn judge(client: &OpenClawClient, user_id: &str, text: &str, candidates: &[Candidate], config: &FanoutConfig) -> Option<usize> {
+    let mut prompt = format!("User message:\n{}\n", text);
+    for (index, candidate) in candidates.iter().enumerate() {
+        prompt.push_str(&format!("\nAnswer {}:\n{}\n", index + 1, candidate.answer));
+    }
+    let messages = vec![
+        ChatMessage { role: "system".to_string(), content: judge_instruction(candidates.len()) },
+        ChatMessage { role: "user".to_string(), content: prompt },
+    ];
+    let verdict = match client.send_chat(user_id, config.judge_model.as_deref(), messages).await {
+        Ok(verdict) => verdict,
+        Err(e) => {
+            warn!("Fan-out judge failed, picking the longest answer: {}", e);
+            return None;
+        }
+    };
+    let number: String = verdict.chars().skip_while(|c| !c.is_ascii_digit()).take_while(char::is_ascii_digit).collect();
+    match number.parse::<usize>() {
+        Ok(number) if (1..=candidates.len()).contains(&number) => Some(number - 1),
+        _ => {
+            warn!("Fan-out judge gave an unusable verdict, picking the longest answer");
+            None
+        }
+    }
+}
+
+/// 最長回答的索引
+fn longest(candidates: &[Candidate]) -> usize {
+    candidates
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, c)| c.answer.chars().count())
+        .map_or(0, |(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(available: &[&str]) -> OpenClawClient {
+        let client = OpenClawClient::new("http://127.0.0.1:9".to_string(), None, "model-a".to_string(), false);
+        client.set_available_models(available.iter().map(|m| m.to_string()).collect());
+        client
+    }
+
+    fn models(names: &[&str]) -> Vec<String> {
+        names.iter().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn unavailable_models_are_skipped_not_substituted() {
+        let client = client(&["model-a", "model-b"]);
+        assert_eq!(servable(&client, &models(&["model-b", "missing", "model-a"])), ["model-b", "model-a"]);
+        assert_eq!(servable(&client, &models(&["model-b", "model-b"])), ["model-b"]);
+    }
+
+    #[test]
+    fn no_servable_model_falls_back_to_the_active_one() {
+        let client = client(&["model-a"]);
+        assert_eq!(servable(&client, &models(&["missing", "other"])), ["model-a"]);
+    }
+}
@@ -0,0 +1,352 @@
+//! RSS 來源抓取模組
+//! 簡報的 RSS / Atom 來源以 `ETag` / `Last-Modified` 條件式抓取並遵守 robots.txt（行事曆網址也經由同一個檢查），未變更時沿用上次的標題；
+//! 每個來源記錄抓取統計，連續失敗時以指數退避暫停抓取，長時間在家用網路上執行也不會打擾來源網站
+
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER};
+use reqwest::{StatusCode, Url};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::outbound;
+use crate::store::{ConversationStore, FeedSource};
+
+/// robots.txt 中代表本程式的名稱（抓取時的 User-Agent 為 [`outbound::USER_AGENT`]）
+const ROBOTS_AGENT: &str = "line-openclaw-bridge";
+/// robots.txt 的快取時間
+const ROBOTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// 每個來源保留的標題數
+const MAX_TITLES: usize = 10;
+/// RSS / Atom 回應的大小上限
+const MAX_FEED_BYTES: usize = 2 * 1024 * 1024;
+/// robots.txt 的大小上限（Google 只讀前 500 KiB）
+const MAX_ROBOTS_BYTES: usize = 512 * 1024;
+/// 第一次失敗後暫停的秒數，之後每次加倍
+const BACKOFF_BASE_SECS: i64 = 15 * 60;
+/// 暫停抓取的上限
+const BACKOFF_MAX_SECS: i64 = 24 * 60 * 60;
+
+/// 各網站的 robots.txt 規則與取得時間（以 `scheme://host:port` 為鍵）
+type RobotsCache = HashMap<String, (Instant, Vec<Rule>)>;
+
+static ROBOTS: OnceLock<Mutex<RobotsCache>> = OnceLock::new();
+
+/// robots.txt 的一條 Allow / Disallow 規則
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// 抓取結果
+enum Fetched {
+    Updated { titles: Vec<String>, etag: Option<String>, last_modified: Option<String> },
+    NotModified,
+}
+
+/// 抓取失敗（`retry_after` 為來源以 `Retry-After` 指定的等待秒數）
+struct FetchError {
+    message: String,
+    retry_after: Option<i64>,
+}
+
+impl From<String> for FetchError {
+    fn from(message: String) -> Self {
+        FetchError { message, retry_after: None }
+    }
+}
+
+/// 來源目前的標題：未變更時沿用上次的結果；robots.txt 不允許、抓取失敗或暫停中時回傳空清單
+pub async fn titles(store: &ConversationStore, url: &str) -> Vec<String> {
+    let now = store.clock().timestamp();
+    let mut source = match store.feed_source(url) {
+        Ok(source) => source.unwrap_or_else(|| FeedSource { url: url.to_string(), ..Default::default() }),
+        Err(e) => {
+            warn!("Failed to load feed state for {}: {}", url, e);
+            FeedSource { url: url.to_string(), ..Default::default() }
+        }
+    };
+    if let Some(retry_at) = source.retry_at.filter(|t| *t > now) {
+        debug!("Skipping feed {} until {} after {} failures", url, retry_at, source.consecutive_failures);
+        return Vec::new();
+    }
+
+    let result = match robots_allowed(url).await {
+        Ok(true) => fetch(url, &source, now).await,
+        Ok(false) => {
+            info!("Feed {} is disallowed by robots.txt", url);
+            source.robots_blocked += 1;
+            source.last_error = Some("robots.txt 不允許抓取".to_string());
+            save(store, &source);
+            return Vec::new();
+        }
+        Err(e) => Err(e.into()),
+    };
+
+    source.fetches += 1;
+    source.last_fetched_at = Some(now);
+    let titles = match result {
+        Ok(fetched) => {
+            source.consecutive_failures = 0;
+            source.retry_at = None;
+            source.last_error = None;
+            match fetched {
+                Fetched::Updated { titles, etag, last_modified } => {
+                    source.titles = titles;
+                    source.etag = etag;
+                    source.last_modified = last_modified;
+                }
+                Fetched::NotModified => source.not_modified += 1,
+            }
+            source.titles.clone()
+        }
+        Err(e) => {
+            source.failures += 1;
+            source.consecutive_failures += 1;
+            let backoff = e.retry_after.unwrap_or_else(|| backoff_secs(source.consecutive_failures));
+            source.retry_at = Some(now + backoff);
+            warn!(
+                "Failed to fetch feed {} ({} in a row), retrying in {}s: {}",
+                url, source.consecutive_failures, backoff, e.message
+            );
+            source.last_error = Some(e.message);
+            Vec::new()
+        }
+    };
+    save(store, &source);
+    titles
+}
+
+fn save(store: &ConversationStore, source: &FeedSource) {
+    if let Err(e) = store.save_feed_source(source) {
+        warn!("Failed to save feed state for {}: {}", source.url, e);
+    }
+}
+
+/// 連續失敗 `failures` 次後的暫停秒數
+fn backoff_secs(failures: i64) -> i64 {
+    let exponent = (failures - 1).clamp(0, 16) as u32;
+    BACKOFF_BASE_SECS.saturating_mul(1 << exponent).min(BACKOFF_MAX_SECS)
+}
+
+/// 帶上次的驗證資訊條件式抓取；`now` 用來換算 HTTP 日期格式的 `Retry-After`
+async fn fetch(url: &str, source: &FeedSource, now: i64) -> Result<Fetched, FetchError> {
+    let mut headers = HeaderMap::new();
+    let validator = |value: &Option<String>| value.as_deref().and_then(|v| HeaderValue::from_str(v).ok());
+    if let Some(etag) = validator(&source.etag) {
+        headers.insert(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = validator(&source.last_modified) {
+        headers.insert(IF_MODIFIED_SINCE, last_modified);
+    }
+    let response = outbound::get_public(url, headers).await?;
+    let status = response.status();
+    if status == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    if !status.is_success() {
+        let retry_after = matches!(status, StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE)
+            .then(|| response.headers().get(RETRY_AFTER).and_then(|v| v.to_str().ok()).and_then(|v| retry_after_secs(v, now)))
+            .flatten();
+        return Err(FetchError { message: format!("HTTP {}", status), retry_after });
+    }
+    let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+    let body = outbound::read_limited(response, MAX_FEED_BYTES).await?;
+    Ok(Fetched::Updated { titles: parse_titles(&body), etag, last_modified })
+}
+
+/// `Retry-After` 的秒數或 HTTP 日期轉為等待秒數（上限同退避上限）
+fn retry_after_secs(value: &str, now: i64) -> Option<i64> {
+    let secs = match value.trim().parse::<i64>() {
+        Ok(secs) => secs,
+        Err(_) => chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?.timestamp() - now,
+    };
+    Some(secs.clamp(1, BACKOFF_MAX_SECS))
+}
+
+/// 依 robots.txt 判斷是否可抓取（RSS 來源與簡報的行事曆共用）；robots.txt 不存在（4xx）視為全部允許，
+/// 伺服器錯誤或無法連線則回傳錯誤
+pub async fn robots_allowed(url: &str) -> Result<bool, String> {
+    let url = Url::parse(url).map_err(|e| format!("網址格式錯誤: {}", e))?;
+    let origin = url.origin().ascii_serialization();
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let cache = ROBOTS.get_or_init(|| Mutex::new(HashMap::new()));
+    let cached = cache
+        .lock()
+        .unwrap()
+        .get(&origin)
+        .filter(|(fetched, _)| fetched.elapsed() < ROBOTS_TTL)
+        .map(|(_, rules)| rules.clone());
+    let rules = match cached {
+        Some(rules) => rules,
+        None => {
+            let response = outbound::get_public(&format!("{}/robots.txt", origin), HeaderMap::new())
+                .await
+                .map_err(|e| format!("無法取得 robots.txt: {}", e))?;
+            let rules = match response.status() {
+                // 過大的 robots.txt 視同無法解析，全部允許
+                status if status.is_success() => {
+                    parse_robots(&outbound::read_limited(response, MAX_ROBOTS_BYTES).await.unwrap_or_default())
+                }
+                status if status.is_client_error() => Vec::new(),
+                status => return Err(format!("無法取得 robots.txt: HTTP {}", status)),
+            };
+            cache.lock().unwrap().insert(origin, (Instant::now(), rules.clone()));
+            rules
+        }
+    };
+    Ok(is_allowed(&rules, &path))
+}
+
+/// 取出適用於本程式的規則：有指名本程式的群組時只用該群組，否則用 `*`
+fn parse_robots(body: &str) -> Vec<Rule> {
+    let (mut named, mut wildcard) = (Vec::new(), Vec::new());
+    let mut named_found = false;
+    let mut agents: Vec<String> = Vec::new();
+    let mut in_rules = false;
+    for line in body.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+        match key.as_str() {
+            "user-agent" => {
+                // 規則之後的 User-agent 開始新的群組
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                agents.push(value.to_ascii_lowercase());
+            }
+            "allow" | "disallow" => {
+                in_rules = true;
+                let is_named = agents.iter().any(|a| a == ROBOTS_AGENT);
+                named_found |= is_named;
+                // 空的 Disallow 代表全部允許
+                if value.is_empty() {
+                    continue;
+                }
+                let rule = Rule { allow: key == "allow", pattern: value.to_string() };
+                if is_named {
+                    named.push(rule.clone());
+                }
+                if agents.iter().any(|a| a == "*") {
+                    wildcard.push(rule);
+                }
+            }
+            _ => {}
+        }
+    }
+    if named_found {
+        named
+    } else {
+        wildcard
+    }
+}
+
+/// 最長的相符規則決定結果，長度相同時以 Allow 優先；沒有相符規則時允許
+fn is_allowed(rules: &[Rule], path: &str) -> bool {
+    rules
+        .iter()
+        .filter(|rule| matches(&rule.pattern, path))
+        .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+        .is_none_or(|rule| rule.allow)
+}
+
+/// robots.txt 路徑比對：前綴相符，`*` 代表任意字元，結尾的 `$` 代表必須完全相符
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default()) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    for (index, part) in parts.iter().enumerate() {
+        if anchored && index == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// 取出 `<item>`（RSS）或 `<entry>`（Atom）的標題
+fn parse_titles(xml: &str) -> Vec<String> {
+    let tag = if xml.contains("<item") { "item" } else { "entry" };
+    xml.split(&format!("<{}", tag))
+        .skip(1)
+        .filter_map(|item| {
+            let start = item.find("<title")?;
+            let rest = &item[start..];
+            let content_start = rest.find('>')? + 1;
+            let content_end = rest.find("</title>")?;
+            let title = rest.get(content_start..content_end)?.trim();
+            let title = title
+                .strip_prefix("<![CDATA[")
+                .and_then(|t| t.strip_suffix("]]>"))
+                .unwrap_or(title);
+            let title = title
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&#39;", "'")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&");
+            Some(title.trim().to_string()).filter(|t| !t.is_empty())
+        })
+        .take(MAX_TITLES)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(body: &str, path: &str) -> bool {
+        is_allowed(&parse_robots(body), path)
+    }
+
+    #[test]
+    fn named_group_overrides_wildcard() {
+        let body = "User-agent: *\nDisallow: /\n\nUser-agent: Line-OpenClaw-Bridge\nDisallow: /private\n";
+        assert!(allowed(body, "/feed.xml"));
+        assert!(!allowed(body, "/private/feed.xml"));
+        assert!(!allowed("User-agent: *\nDisallow: /\n", "/feed.xml"));
+    }
+
+    #[test]
+    fn consecutive_user_agents_share_rules() {
+        let body = "User-agent: other\nUser-agent: line-openclaw-bridge\nDisallow: /a # 註解\n\nUser-agent: other\nDisallow: /b\n";
+        assert!(!allowed(body, "/a/feed"));
+        assert!(allowed(body, "/b/feed"));
+    }
+
+    #[test]
+    fn longest_match_wins_and_allow_breaks_ties() {
+        let body = "User-agent: *\nDisallow: /feeds\nAllow: /feeds/public\nDisallow: /x\nAllow: /x\n";
+        assert!(!allowed(body, "/feeds/private.xml"));
+        assert!(allowed(body, "/feeds/public.xml"));
+        assert!(allowed(body, "/x"));
+        // 空的 Disallow 代表全部允許
+        assert!(allowed("User-agent: *\nDisallow:\n", "/anything"));
+    }
+
+    #[test]
+    fn patterns_support_wildcards_and_end_anchors() {
+        assert!(matches("/feeds/*.xml", "/feeds/a/b.xml"));
+        assert!(!matches("/feeds/*.xml", "/other/a.xml"));
+        assert!(matches("/*.ics$", "/calendar/basic.ics"));
+        assert!(!matches("/*.ics$", "/calendar/basic.ics?x=1"));
+        assert!(matches("/feed$", "/feed"));
+        assert!(!matches("/feed$", "/feeds"));
+        assert!(matches("/private", "/private/feed"));
+    }
+}
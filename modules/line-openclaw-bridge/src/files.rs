@@ -0,0 +1,64 @@
+//! 檔案訊息模組
+//! 使用者傳來的檔案下載後依 `FILE_HANDLER` 交給處理方式：`store` 存入物件儲存，
+//! `webhook` 以 JSON（內容為 base64）POST 給外部服務，服務可回傳 `{"reply": "..."}` 作為給使用者的回覆
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::incoming::{Content, Handled, INCOMING_PREFIX};
+use crate::outbound;
+use crate::AppState;
+
+/// 轉送 webhook 的逾時
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 檔案的處理方式
+#[derive(Debug, Clone)]
+pub enum FileHandler {
+    /// 存入物件儲存（`incoming/` 前綴）
+    Store,
+    /// 轉送給外部服務
+    Webhook { url: String, token: Option<String> },
+}
+
+#[derive(Deserialize)]
+struct WebhookResponse {
+    reply: Option<String>,
+}
+
+/// 將下載的檔案交給設定的處理方式
+pub async fn handle(state: &AppState, handler: &FileHandler, user_id: &str, content: Content) -> Result<Handled, String> {
+    let name = content.file_name.clone().unwrap_or_default();
+    let bytes = content.data.len();
+    match handler {
+        FileHandler::Store => {
+            let key = format!("{}{}.{}", INCOMING_PREFIX, content.message_id, content.extension());
+            state.blobs.put(&key, content.data).await?;
+            Ok(Handled { detail: format!("key={} name={} bytes={}", key, name, bytes), reference: format!("{} ({})", name, key), reply: None })
+        }
+        FileHandler::Webhook { url, token } => {
+            let body = json!({
+                "user_id": user_id,
+                "message_id": content.message_id,
+                "file_name": content.file_name,
+                "content_type": content.content_type,
+                "size": bytes,
+                "data": BASE64.encode(&content.data),
+            });
+            let mut request = outbound::client().post(url).timeout(WEBHOOK_TIMEOUT).json(&body);
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            let response = request
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| format!("檔案 webhook 失敗：{}", e))?;
+            // 回應不是 JSON 或沒有 reply 時使用預設回覆
+            let reply = response.json::<WebhookResponse>().await.ok().and_then(|r| r.reply).filter(|r| !r.trim().is_empty());
+            Ok(Handled { detail: format!("webhook name={} bytes={}", name, bytes), reference: name, reply })
+        }
+    }
+}
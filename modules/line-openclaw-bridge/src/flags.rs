@@ -0,0 +1,149 @@
+//! 功能旗標模組
+//! 從 TOML 檔讀取功能旗標並定期檢查更新，可依環境、使用者群組與比例逐步開放功能
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{error, info};
+
+/// 檢查旗標檔是否更新的間隔
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 旗標檔內容
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FlagFile {
+    /// 使用者群組：群組名稱 → LINE userId 清單
+    pub segments: HashMap<String, Vec<String>>,
+    pub flags: HashMap<String, Flag>,
+}
+
+/// 單一功能旗標
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Flag {
+    /// 未命中其他規則時的預設值
+    pub enabled: bool,
+    /// 僅在這些環境生效（空白代表所有環境）
+    pub environments: Vec<String>,
+    /// 一律開啟的使用者
+    pub users: Vec<String>,
+    /// 一律開啟的使用者群組
+    pub segments: Vec<String>,
+    /// 依使用者雜湊逐步開放的比例（0–100）
+    pub percentage: Option<u8>,
+}
+
+/// 功能旗標（可熱更新）
+pub struct FeatureFlags {
+    path: Option<PathBuf>,
+    environment: String,
+    /// 比例分組的雜湊種子（`RANDOM_SEED`）；未設定時與過去的分組相同
+    seed: Option<u64>,
+    current: RwLock<FlagFile>,
+}
+
+impl FeatureFlags {
+    /// 載入旗標檔；未指定路徑時所有旗標皆使用呼叫端的預設值
+    pub fn load(path: Option<PathBuf>, environment: String, seed: Option<u64>) -> Result<Self, String> {
+        let current = match &path {
+            Some(path) => read(path)?,
+            None => FlagFile::default(),
+        };
+        Ok(Self {
+            path,
+            environment,
+            seed,
+            current: RwLock::new(current),
+        })
+    }
+
+    /// 旗標是否對此使用者開啟；旗標未定義時回傳 `default`
+    pub fn is_enabled(&self, name: &str, user_id: Option<&str>, default: bool) -> bool {
+        let file = self.current.read().unwrap();
+        let Some(flag) = file.flags.get(name) else { return default };
+
+        if !flag.environments.is_empty() && !flag.environments.contains(&self.environment) {
+            return false;
+        }
+        if let Some(user_id) = user_id {
+            if flag.users.iter().any(|u| u == user_id) {
+                return true;
+            }
+            let in_segment = flag.segments.iter().any(|segment| {
+                file.segments
+                    .get(segment)
+                    .is_some_and(|members| members.iter().any(|u| u == user_id))
+            });
+            if in_segment {
+                return true;
+            }
+            if let Some(percentage) = flag.percentage {
+                if bucket(self.seed, name, user_id) < percentage.min(100) {
+                    return true;
+                }
+            }
+        }
+        flag.enabled
+    }
+
+    /// 目前的旗標內容（管理 API 使用）
+    pub fn snapshot(&self) -> FlagFile {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn environment(&self) -> &str {
+        &self.environment
+    }
+
+    /// 立即重新讀取旗標檔；格式錯誤時保留原設定
+    pub fn reload(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let file = read(path)?;
+        *self.current.write().unwrap() = file;
+        Ok(())
+    }
+}
+
+/// 在背景定期檢查旗標檔的修改時間，有變更即重新載入
+pub fn spawn_watcher(flags: Arc<FeatureFlags>) {
+    let Some(path) = flags.path.clone() else { return };
+    tokio::spawn(async move {
+        let mut last_modified = modified(&path);
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+        loop {
+            interval.tick().await;
+            let modified = modified(&path);
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+            match flags.reload() {
+                Ok(()) => info!("Feature flags reloaded from {}", path.display()),
+                Err(e) => error!("Failed to reload feature flags: {}", e),
+            }
+        }
+    });
+}
+
+fn read(path: &Path) -> Result<FlagFile, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("無法讀取 {}: {}", path.display(), e))?;
+    toml::from_str(&content).map_err(|e| format!("{} 格式錯誤: {}", path.display(), e))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 將使用者穩定分配到 0–99 的區間（同一旗標與種子下結果固定）
+fn bucket(seed: Option<u64>, name: &str, user_id: &str) -> u8 {
+    let key = match seed {
+        Some(seed) => format!("{}:{}:{}", seed, name, user_id),
+        None => format!("{}:{}", name, user_id),
+    };
+    let digest = Sha256::digest(key.as_bytes());
+    (u16::from_be_bytes([digest[0], digest[1]]) % 100) as u8
+}
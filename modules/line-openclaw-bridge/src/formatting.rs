@@ -0,0 +1,451 @@
+//! 回答排版模組
+//! 將 AI 回答依 Markdown 程式碼區塊切段，程式碼以 Flex bubble 呈現並附上複製按鈕，其餘含 Markdown 語法的文字可轉為 Flex；
+//! 超過 LINE 長度上限的文字依段落拆成多則，過長的回答改附完整回答頁連結
+
+use tracing::warn;
+
+use crate::commands;
+use crate::i18n::Locale;
+use crate::liff;
+use crate::markdown;
+use crate::menus;
+use crate::line::{
+    Action, Bubble, ButtonStyle, FlexBox, FlexButton, FlexComponent, FlexContainer, FlexMessage, FlexText, OutgoingMessage, QuickReply,
+    TextMessage, MAX_MESSAGES,
+};
+use crate::AppState;
+
+/// Flex bubble 內顯示的程式碼長度上限（完整內容可於複製頁取得）
+const MAX_CODE_CHARS: usize = 2000;
+/// 完整回答頁的摘要長度
+const SUMMARY_CHARS: usize = 200;
+/// 併入 Flex 回答的頁尾顏色
+const FOOTER_COLOR: &str = "#999999";
+
+/// 回答中的一段內容
+#[derive(Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Code { language: Option<&'a str>, code: &'a str },
+}
+
+/// 依 ``` 區塊切分回答；未結束的區塊視為延續到結尾。回傳各段與其在原文的起始位置
+pub fn split(answer: &str) -> Vec<(usize, Segment<'_>)> {
+    let mut segments = Vec::new();
+    let mut text_start = 0;
+    let mut code: Option<(usize, Option<&str>, usize)> = None;
+    let mut offset = 0;
+
+    for line in answer.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let Some(info) = line.trim_start().strip_prefix("```") else { continue };
+
+        match code.take() {
+            None => {
+                push_text(&mut segments, answer, text_start, line_start);
+                let language = Some(info.trim()).filter(|l| !l.is_empty());
+                code = Some((line_start, language, offset));
+            }
+            Some((fence_start, language, code_start)) => {
+                segments.push((fence_start, Segment::Code {
+                    language,
+                    code: answer[code_start..line_start].trim_end_matches(['\n', '\r']),
+                }));
+                text_start = offset;
+            }
+        }
+    }
+
+    match code {
+        Some((fence_start, language, code_start)) => segments.push((fence_start, Segment::Code {
+            language,
+            code: answer[code_start..].trim_end_matches(['\n', '\r']),
+        })),
+        None => push_text(&mut segments, answer, text_start, answer.len()),
+    }
+    segments
+}
+
+fn push_text<'a>(segments: &mut Vec<(usize, Segment<'a>)>, answer: &'a str, start: usize, end: usize) {
+    let text = answer[start..end].trim();
+    if !text.is_empty() {
+        segments.push((start, Segment::Text(text)));
+    }
+}
+
+/// 將回答轉為 LINE 訊息；沒有程式碼區塊且未超過長度上限時維持單則文字訊息。
+/// 超過單次回覆則數上限的部分由投遞時改以 push 送出
+pub fn answer_messages(state: &AppState, user_id: &str, locale: Locale, answer: &str) -> Vec<OutgoingMessage> {
+    let limit = state.config.long_answer_chars;
+    if limit > 0 && answer.chars().count() > limit {
+        if let Some(message) = answer_page_message(state, user_id, locale, answer) {
+            return vec![message];
+        }
+    }
+
+    let segments = split(answer);
+    let markdown_flex = state.config.answer_markdown_flex && markdown::has_formatting(answer);
+    if !markdown_flex && !segments.iter().any(|(_, s)| matches!(s, Segment::Code { .. })) {
+        return text_messages(answer);
+    }
+
+    let mut messages = Vec::new();
+    for (_, segment) in &segments {
+        match segment {
+            Segment::Text(text) if markdown_flex => messages.extend(markdown_messages(text)),
+            Segment::Text(text) => messages.extend(text_messages(text)),
+            Segment::Code { language: Some(menus::LANGUAGE), code } if state.config.answer_menus => messages
+                .push(menus::parse(code).unwrap_or_else(|| code_message(state, user_id, locale, Some(menus::LANGUAGE), code))),
+            Segment::Code { language, code } => messages.push(code_message(state, user_id, locale, *language, code)),
+        }
+    }
+    messages
+}
+
+/// 含 Markdown 語法的文字轉為 Flex bubble；沒有語法或超過 Flex 大小上限時改送去除標記的純文字
+fn markdown_messages(text: &str) -> Vec<OutgoingMessage> {
+    if !markdown::has_formatting(text) {
+        return text_messages(text);
+    }
+    let plain = markdown::to_plain(text);
+    match markdown::to_flex(text) {
+        // 替代文字（通知與聊天列表預覽）取純文字摘要
+        Some(bubble) => vec![FlexMessage::new(summary(&plain), bubble).into()],
+        None => text_messages(&plain),
+    }
+}
+
+/// 文字訊息；超過 LINE 長度上限時拆成多則
+fn text_messages(text: &str) -> Vec<OutgoingMessage> {
+    split_text(text, TextMessage::TEXT_LIMIT).into_iter().map(|chunk| TextMessage::new(chunk).into()).collect()
+}
+
+/// 將文字拆成不超過 `limit` 字的多段：優先在段落（空行）之間切開，段落過長時改在換行處，單行仍過長時才依字數硬切
+pub fn split_text(text: &str, limit: usize) -> Vec<String> {
+    if text.chars().count() <= limit {
+        return vec![text.to_string()];
+    }
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    pack(&mut chunks, &mut current, text, &["\n\n", "\n"], limit);
+    flush(&mut chunks, &mut current);
+    chunks
+}
+
+fn pack(chunks: &mut Vec<String>, current: &mut String, text: &str, separators: &[&str], limit: usize) {
+    let Some((separator, rest)) = separators.split_first() else {
+        let chars: Vec<char> = text.chars().collect();
+        for piece in chars.chunks(limit) {
+            flush(chunks, current);
+            current.extend(piece);
+        }
+        return;
+    };
+    for piece in text.split(separator) {
+        let length = piece.chars().count();
+        if length > limit {
+            // 過長的段落從新的一則開始，保持段落完整
+            flush(chunks, current);
+            pack(chunks, current, piece, rest, limit);
+        } else if current.is_empty() || current.chars().count() + separator.len() + length <= limit {
+            if !current.is_empty() {
+                current.push_str(separator);
+            }
+            current.push_str(piece);
+        } else {
+            flush(chunks, current);
+            current.push_str(piece);
+        }
+    }
+}
+
+fn flush(chunks: &mut Vec<String>, current: &mut String) {
+    let chunk = std::mem::take(current);
+    let chunk = chunk.trim_matches('\n');
+    if !chunk.trim().is_empty() {
+        chunks.push(chunk.to_string());
+    }
+}
+
+/// AI 回答的揭露頁尾（模型名稱、生成時間與「AI 生成內容」）；未啟用時回傳 None
+pub fn ai_footer(state: &AppState, locale: Locale, model: &str) -> Option<String> {
+    if !state.config.ai_footer {
+        return None;
+    }
+    let time = state
+        .clock
+        .now()
+        .with_timezone(&state.config.ai_footer_timezone)
+        .format("%Y-%m-%d %H:%M")
+        .to_string();
+    Some(state.i18n.text(locale, "answer.footer", &[("model", model), ("time", &time)]))
+}
+
+/// 將頁尾接在最後一則文字訊息之後，或以灰色小字加在 Markdown 回答 bubble 的最下方；
+/// 最後一則是其他訊息或超過長度上限時另起一則
+pub fn append_footer(messages: &mut Vec<OutgoingMessage>, footer: String) {
+    match messages.last_mut() {
+        Some(OutgoingMessage::Text(last))
+            if last.text.chars().count() + 2 + footer.chars().count() <= TextMessage::TEXT_LIMIT =>
+        {
+            last.text.push_str("\n\n");
+            last.text.push_str(&footer);
+            return;
+        }
+        Some(OutgoingMessage::Flex(last)) => {
+            if let Some(body) = text_body(last) {
+                body.contents.push(FlexText::new(footer).size("xxs").color(FOOTER_COLOR).wrap().margin("lg").into());
+                return;
+            }
+        }
+        _ => {}
+    }
+    messages.push(TextMessage::new(footer).into());
+}
+
+/// 移除回答中所有以 `prefix` 開頭、`]` 結尾的標籤（如 `[sticker:happy]`），回傳其餘文字與第一個標籤的內容；
+/// 沒有完整的標籤時原樣回傳
+pub fn take_tag(answer: &str, prefix: &str) -> (String, Option<String>) {
+    let mut text = String::with_capacity(answer.len());
+    let mut tag = None;
+    let mut rest = answer;
+    while let Some(start) = rest.find(prefix) {
+        let Some(end) = rest[start..].find(']') else { break };
+        text.push_str(&rest[..start]);
+        tag.get_or_insert_with(|| rest[start + prefix.len()..start + end].to_string());
+        rest = &rest[start + end + 1..];
+    }
+    let Some(tag) = tag else { return (answer.to_string(), None) };
+    text.push_str(rest);
+    (text.trim().to_string(), Some(tag))
+}
+
+/// 將由標籤轉成的訊息（貼圖、地圖圖釘）接在回覆之後；已達單次回覆的訊息上限時略過
+pub fn append_tagged(messages: &mut Vec<OutgoingMessage>, message: Option<OutgoingMessage>, kind: &str) {
+    let Some(message) = message else { return };
+    if messages.len() < MAX_MESSAGES {
+        messages.push(message);
+    } else {
+        warn!("No room for {} in reply", kind);
+    }
+}
+
+/// 單一 bubble、沒有 header 與背景色的內文 box（Markdown 回答、完整回答頁摘要）；程式碼等深色 bubble 回傳 None
+fn text_body(message: &mut FlexMessage) -> Option<&mut FlexBox> {
+    let FlexContainer::Bubble(bubble) = &mut message.contents else { return None };
+    if bubble.header.is_some() {
+        return None;
+    }
+    match bubble.body.as_deref_mut()? {
+        FlexComponent::Box(body) if body.background_color.is_none() => Some(body),
+        _ => None,
+    }
+}
+
+/// 在最後一則訊息附上後續動作的快速回覆（重試這則問題、幫助、狀態）；未啟用時不變更
+pub fn append_quick_reply(state: &AppState, locale: Locale, messages: &mut [OutgoingMessage], question: &str) {
+    if !state.config.answer_quick_replies {
+        return;
+    }
+    let Some(last) = messages.last_mut() else { return };
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    let mut quick_reply = QuickReply::new();
+    if let Some(retry) = commands::chat_action(t("quick_reply.retry"), question) {
+        quick_reply = quick_reply.action(retry);
+    }
+    last.set_quick_reply(quick_reply.message(t("quick_reply.help"), "/help").message(t("quick_reply.status"), "/status"));
+}
+
+/// 程式碼 Flex bubble
+fn code_message(state: &AppState, user_id: &str, locale: Locale, language: Option<&str>, code: &str) -> OutgoingMessage {
+    let mut shown: String = code.chars().take(MAX_CODE_CHARS).collect();
+    if shown.len() < code.len() {
+        shown.push_str("\n…");
+    }
+
+    let mut copy_url = None;
+    if liff::enabled(&state.config) {
+        let id = liff::new_id();
+        match state.store.save_snippet(&id, user_id, language, code) {
+            Ok(()) => copy_url = liff::url(&state.config, &format!("code/{}", id)),
+            Err(e) => warn!("Failed to save snippet: {}", e),
+        }
+    }
+
+    let mut bubble = Bubble::new()
+        .size("giga")
+        .header(
+            FlexBox::vertical(vec![FlexText::new(language.unwrap_or("code")).size("xs").color("#AAAAAA").into()])
+                .background_color("#2D2D2D")
+                .padding_all("8px"),
+        )
+        .body(
+            FlexBox::vertical(vec![FlexText::new(shown).wrap().size("xs").color("#D4D4D4").into()]).background_color("#1E1E1E"),
+        );
+    if let Some(url) = copy_url {
+        let button = FlexButton::new(Action::uri(state.i18n.text(locale, "code.copy", &[]), url))
+            .style(ButtonStyle::Secondary)
+            .height("sm");
+        bubble = bubble.footer(FlexBox::vertical(vec![button.into()]));
+    }
+
+    FlexMessage::new(state.i18n.text(locale, "code.alt", &[("language", language.unwrap_or("code"))]), bubble).into()
+}
+
+/// 過長的回答：保存為完整回答頁，回覆摘要與「閱讀完整回答」按鈕；無法產生網址時回傳 None
+pub(crate) fn answer_page_message(state: &AppState, user_id: &str, locale: Locale, answer: &str) -> Option<OutgoingMessage> {
+    if !liff::enabled(&state.config) {
+        return None;
+    }
+    let id = liff::new_id();
+    let ttl_secs = state.config.answer_page_ttl_days * 86_400;
+    if let Err(e) = state.store.save_answer_page(&id, user_id, answer, ttl_secs) {
+        warn!("Failed to save answer page: {}", e);
+        return None;
+    }
+    let expires = state.clock.timestamp() + ttl_secs;
+    let url = liff::signed_url(state, &format!("answer/{}", id), expires)?;
+
+    let summary = summary(answer);
+    let button = FlexButton::new(Action::uri(state.i18n.text(locale, "answer.read_more", &[]), url))
+        .style(ButtonStyle::Primary)
+        .color("#06C755")
+        .height("sm");
+    let bubble = Bubble::new()
+        .body(FlexBox::vertical(vec![FlexText::new(summary.as_str()).wrap().size("sm").into()]))
+        .footer(FlexBox::vertical(vec![button.into()]));
+    Some(FlexMessage::new(state.i18n.text(locale, "answer.alt", &[("summary", &summary)]), bubble).into())
+}
+
+/// 摘要：取程式碼區塊以外、非標題的第一段文字，截斷至 SUMMARY_CHARS 字
+fn summary(answer: &str) -> String {
+    let first = split(answer)
+        .into_iter()
+        .filter_map(|(_, segment)| match segment {
+            Segment::Text(text) => Some(text),
+            Segment::Code { .. } => None,
+        })
+        .flat_map(|text| text.split("\n\n"))
+        .map(str::trim)
+        .find(|paragraph| !paragraph.is_empty() && !paragraph.starts_with('#'))
+        .unwrap_or(answer.trim());
+    let mut summary: String = first.chars().take(SUMMARY_CHARS).collect();
+    if summary.len() < first.len() {
+        summary.push('…');
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_tag_removes_every_tag_and_keeps_the_first() {
+        assert_eq!(take_tag("好[x:a] 的[x:b]", "[x:"), ("好 的".to_string(), Some("a".to_string())));
+        assert_eq!(take_tag("沒有標籤", "[x:"), ("沒有標籤".to_string(), None));
+        assert_eq!(take_tag("未結束 [x:a", "[x:"), ("未結束 [x:a".to_string(), None));
+    }
+
+    #[test]
+    fn append_tagged_skips_messages_when_the_reply_is_full() {
+        let mut messages: Vec<OutgoingMessage> = vec![TextMessage::new("回答").into()];
+        append_tagged(&mut messages, Some(TextMessage::new("附加").into()), "sticker");
+        assert_eq!(messages.len(), 2);
+        append_tagged(&mut messages, None, "sticker");
+        assert_eq!(messages.len(), 2);
+        let mut full: Vec<OutgoingMessage> = (0..MAX_MESSAGES).map(|_| TextMessage::new("回答").into()).collect();
+        append_tagged(&mut full, Some(TextMessage::new("附加").into()), "sticker");
+        assert_eq!(full.len(), MAX_MESSAGES);
+    }
+
+    #[test]
+    fn split_plain_text() {
+        assert_eq!(split("  hello\n"), vec![(0, Segment::Text("hello"))]);
+        assert!(split("  \n").is_empty());
+    }
+
+    #[test]
+    fn split_code_block_with_language() {
+        let answer = "Try this:\n```rust\nfn main() {}\n```\nDone.";
+        assert_eq!(
+            split(answer),
+            vec![
+                (0, Segment::Text("Try this:")),
+                (10, Segment::Code { language: Some("rust"), code: "fn main() {}" }),
+                (35, Segment::Text("Done.")),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_unclosed_block_runs_to_end() {
+        let answer = "```\nls -la\n";
+        assert_eq!(split(answer), vec![(0, Segment::Code { language: None, code: "ls -la" })]);
+    }
+
+    #[test]
+    fn split_indented_fence() {
+        let answer = "a\n  ```sh\n  echo hi\n  ```\n";
+        assert_eq!(
+            split(answer),
+            vec![(0, Segment::Text("a")), (2, Segment::Code { language: Some("sh"), code: "  echo hi" })]
+        );
+    }
+
+    #[test]
+    fn split_text_keeps_short_text() {
+        assert_eq!(split_text("hello", 10), vec!["hello"]);
+    }
+
+    #[test]
+    fn split_text_on_paragraphs() {
+        assert_eq!(split_text("aaaa\n\nbbbb\n\ncccc", 10), vec!["aaaa\n\nbbbb", "cccc"]);
+    }
+
+    #[test]
+    fn split_text_long_paragraph_on_lines() {
+        assert_eq!(split_text("aaaa\nbbbb\ncccc", 9), vec!["aaaa\nbbbb", "cccc"]);
+    }
+
+    #[test]
+    fn split_text_hard_cuts_by_chars() {
+        assert_eq!(split_text("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(split_text("一二三四五", 2), vec!["一二", "三四", "五"]);
+    }
+
+    #[test]
+    fn split_text_chunks_fit_limit() {
+        let text = format!("{}\n\n{}\n{}", "a".repeat(30), "b".repeat(12), "c".repeat(5));
+        let chunks = split_text(&text, 10);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 10), "{:?}", chunks);
+        assert_eq!(chunks.concat().replace('\n', ""), text.replace('\n', ""));
+    }
+
+    #[test]
+    fn footer_merges_into_text_and_markdown_bubbles() {
+        let mut messages = vec![TextMessage::new("回答").into()];
+        append_footer(&mut messages, "頁尾".to_string());
+        assert_eq!(messages.len(), 1);
+        assert!(matches!(&messages[0], OutgoingMessage::Text(text) if text.text == "回答\n\n頁尾"));
+
+        let mut messages = markdown_messages("# 標題\n內容");
+        append_footer(&mut messages, "頁尾".to_string());
+        assert_eq!(messages.len(), 1);
+        let json = serde_json::to_value(&messages[0]).unwrap();
+        let contents = json["contents"]["body"]["contents"].as_array().unwrap();
+        assert_eq!(contents.last().unwrap()["text"], "頁尾");
+    }
+
+    #[test]
+    fn footer_follows_code_bubbles_as_text() {
+        let bubble = Bubble::new()
+            .header(FlexBox::vertical(vec![FlexText::new("rust").into()]))
+            .body(FlexBox::vertical(vec![FlexText::new("fn main() {}").into()]).background_color("#1E1E1E"));
+        let mut messages = vec![FlexMessage::new("code", bubble).into()];
+        append_footer(&mut messages, "頁尾".to_string());
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(&messages[1], OutgoingMessage::Text(text) if text.text == "頁尾"));
+    }
+}
@@ -0,0 +1,85 @@
+//! 進行中回答模組
+//! 記錄每位使用者進行中的 OpenClaw 請求，`/stop` 可立即取消：放棄等待結果並中斷串流連線
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tracing::info;
+
+use crate::i18n::Locale;
+use crate::privacy;
+use crate::AppState;
+
+/// 回答編號與取消通知
+type Entry = (u64, Arc<Notify>);
+
+/// 所有使用者進行中的回答
+#[derive(Default)]
+pub struct Generations {
+    active: Mutex<HashMap<String, Vec<Entry>>>,
+    next_id: AtomicU64,
+}
+
+/// 一次進行中的回答；結束（drop）時自動移除
+pub struct Generation<'a> {
+    registry: &'a Generations,
+    user_id: String,
+    id: u64,
+    cancel: Arc<Notify>,
+}
+
+impl Generations {
+    /// 登記一次回答
+    pub fn start(&self, user_id: &str) -> Generation<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let cancel = Arc::new(Notify::new());
+        self.active
+            .lock()
+            .unwrap()
+            .entry(user_id.to_string())
+            .or_default()
+            .push((id, cancel.clone()));
+        Generation { registry: self, user_id: user_id.to_string(), id, cancel }
+    }
+
+    /// 取消使用者所有進行中的回答，回傳取消的數量
+    pub fn cancel(&self, user_id: &str) -> usize {
+        let cancelled = self.active.lock().unwrap().remove(user_id).unwrap_or_default();
+        for (_, cancel) in &cancelled {
+            // notify_one 會保留通知，尚未開始等待的回答也能收到
+            cancel.notify_one();
+        }
+        cancelled.len()
+    }
+}
+
+impl Generation<'_> {
+    /// 等到此回答被取消
+    pub async fn cancelled(&self) {
+        self.cancel.notified().await
+    }
+}
+
+impl Drop for Generation<'_> {
+    fn drop(&mut self) {
+        let mut active = self.registry.active.lock().unwrap();
+        if let Some(entries) = active.get_mut(&self.user_id) {
+            entries.retain(|(id, _)| *id != self.id);
+            if entries.is_empty() {
+                active.remove(&self.user_id);
+            }
+        }
+    }
+}
+
+/// 處理 `/stop`
+pub fn handle_command(state: &AppState, user_id: &str, locale: Locale) -> String {
+    match state.generations.cancel(user_id) {
+        0 => state.i18n.text(locale, "stop.none", &[]),
+        count => {
+            info!("Cancelled {} in-flight generation(s) for user={}", count, privacy::id(user_id));
+            state.i18n.text(locale, "stop.stopped", &[])
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! 多語系訊息模組
+//! 所有給使用者看的訊息集中於 `locales/*.toml`，依使用者的 LINE 語言設定挑選
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::debug;
+
+use crate::commands;
+use crate::line::LineClient;
+use crate::privacy;
+
+/// 內建訊息檔
+const BUILTIN: [(Locale, &str); 3] = [
+    (Locale::ZhTw, include_str!("../locales/zh-TW.toml")),
+    (Locale::Ja, include_str!("../locales/ja.toml")),
+    (Locale::En, include_str!("../locales/en.toml")),
+];
+
+/// 支援的語系
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Locale {
+    ZhTw,
+    Ja,
+    En,
+}
+
+impl Locale {
+    /// 由 BCP 47 語言標籤（如 `zh-TW`、`ja`、`en-US`）判斷語系
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.to_ascii_lowercase();
+        match tag.split(['-', '_']).next()? {
+            "zh" => Some(Locale::ZhTw),
+            "ja" => Some(Locale::Ja),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::ZhTw => "zh-TW",
+            Locale::Ja => "ja",
+            Locale::En => "en",
+        }
+    }
+
+    /// 給 OpenClaw 指示用的語言名稱
+    pub fn language_name(self) -> &'static str {
+        match self {
+            Locale::ZhTw => "Traditional Chinese (Taiwan)",
+            Locale::Ja => "Japanese",
+            Locale::En => "English",
+        }
+    }
+}
+
+/// 訊息目錄與使用者語系快取
+pub struct I18n {
+    default_locale: Locale,
+    messages: HashMap<Locale, HashMap<String, String>>,
+    /// 指令別名（不分語系，取自各訊息檔的 `[alias]`）→ 指令名稱
+    aliases: HashMap<String, &'static str>,
+    /// LINE userId → 語系（取自 LINE 個人資料）
+    user_locales: Mutex<HashMap<String, Locale>>,
+}
+
+impl I18n {
+    /// 載入內建訊息；`override_dir` 中的 `<語系>.toml` 可覆寫個別訊息
+    pub fn load(default_locale: Locale, override_dir: Option<&Path>) -> Result<Self, String> {
+        let mut messages = HashMap::new();
+        for (locale, content) in BUILTIN {
+            let mut table = parse(content).map_err(|e| format!("內建 {} 訊息檔格式錯誤: {}", locale.tag(), e))?;
+            if let Some(dir) = override_dir {
+                let path = dir.join(format!("{}.toml", locale.tag()));
+                if path.exists() {
+                    let content = std::fs::read_to_string(&path)
+                        .map_err(|e| format!("無法讀取 {}: {}", path.display(), e))?;
+                    table.extend(parse(&content).map_err(|e| format!("{} 格式錯誤: {}", path.display(), e))?);
+                }
+            }
+            messages.insert(locale, table);
+        }
+        let aliases = collect_aliases(&messages)?;
+        Ok(Self {
+            default_locale,
+            messages,
+            aliases,
+            user_locales: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn default_locale(&self) -> Locale {
+        self.default_locale
+    }
+
+    /// 取得訊息並代入 `{名稱}` 變數；缺漏時改用預設語系，仍找不到則回傳 key
+    pub fn text(&self, locale: Locale, key: &str, args: &[(&str, &str)]) -> String {
+        let template = self
+            .messages
+            .get(&locale)
+            .and_then(|m| m.get(key))
+            .or_else(|| self.messages.get(&self.default_locale).and_then(|m| m.get(key)));
+        let Some(template) = template else { return key.to_string() };
+
+        let mut text = template.clone();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+
+    /// 將別名（如 `幫助`）對應回指令名稱；非別名時回傳 None
+    pub fn command_for_alias(&self, alias: &str) -> Option<&'static str> {
+        self.aliases.get(&alias.to_lowercase()).copied()
+    }
+
+    /// 所有別名與其指令名稱
+    pub fn command_aliases(&self) -> impl Iterator<Item = (&str, &'static str)> {
+        self.aliases.iter().map(|(alias, command)| (alias.as_str(), *command))
+    }
+
+    /// 以預設語系取得訊息（管理員通知等無特定使用者的訊息）
+    pub fn text_default(&self, key: &str, args: &[(&str, &str)]) -> String {
+        self.text(self.default_locale, key, args)
+    }
+
+    /// 清除快取的使用者語系（個人資料更新後，下次重新判斷）
+    pub fn forget_locale(&self, user_id: &str) {
+        self.user_locales.lock().unwrap().remove(user_id);
+    }
+
+    /// 使用者的語系：優先使用 LINE 個人資料的語言設定（會快取），取不到時使用預設語系
+    pub async fn locale_for(&self, line: &LineClient, user_id: &str) -> Locale {
+        if user_id.is_empty() {
+            return self.default_locale;
+        }
+        if let Some(locale) = self.user_locales.lock().unwrap().get(user_id) {
+            return *locale;
+        }
+
+        match line.get_profile(user_id).await {
+            Ok(profile) => {
+                let locale = profile
+                    .language
+                    .as_deref()
+                    .and_then(Locale::from_tag)
+                    .unwrap_or(self.default_locale);
+                self.user_locales.lock().unwrap().insert(user_id.to_string(), locale);
+                locale
+            }
+            Err(e) => {
+                debug!("Failed to fetch profile for {}: {}", privacy::id(user_id), e);
+                self.default_locale
+            }
+        }
+    }
+}
+
+/// 收集各語系 `alias.<指令>` 的別名（以逗號分隔）；同一別名不可指向不同指令
+fn collect_aliases(messages: &HashMap<Locale, HashMap<String, String>>) -> Result<HashMap<String, &'static str>, String> {
+    let mut aliases = HashMap::new();
+    for (locale, table) in messages {
+        for (key, value) in table {
+            let Some(name) = key.strip_prefix("alias.") else { continue };
+            let command = commands::NAMES
+                .iter()
+                .find(|command| **command == name)
+                .ok_or_else(|| format!("{} 訊息檔的 [alias] 含未知指令 {}", locale.tag(), name))?;
+            for alias in value.split(',').map(|a| a.trim().trim_start_matches('/').to_lowercase()) {
+                if alias.is_empty() {
+                    continue;
+                }
+                if alias.contains(char::is_whitespace) {
+                    return Err(format!("{} 訊息檔的指令別名不可含空白：{}", locale.tag(), alias));
+                }
+                match aliases.insert(alias.clone(), *command) {
+                    Some(previous) if previous != *command => {
+                        return Err(format!("指令別名 {} 同時對應 /{} 與 /{}", alias, previous, command));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(aliases)
+}
+
+/// 將巢狀 TOML 表格攤平成 `section.key` 形式
+fn parse(content: &str) -> Result<HashMap<String, String>, toml::de::Error> {
+    fn flatten(prefix: &str, table: &toml::Table, out: &mut HashMap<String, String>) {
+        for (key, value) in table {
+            let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+            match value {
+                toml::Value::String(text) => {
+                    out.insert(key, text.clone());
+                }
+                toml::Value::Table(inner) => flatten(&key, inner, out),
+                _ => {}
+            }
+        }
+    }
+
+    let table: toml::Table = content.parse()?;
+    let mut out = HashMap::new();
+    flatten("", &table, &mut out);
+    Ok(out)
+}
+
+/// 簡單的回應生成器（當 OpenClaw 不可用時使用）
+pub fn fallback_response(i18n: &I18n, locale: Locale, name: &str, message: &str) -> String {
+    let lower = message.to_lowercase();
+    let key = if ["你好", "こんにちは", "hello"].iter().any(|k| lower.contains(k)) {
+        "fallback.greeting"
+    } else if ["幫助", "ヘルプ", "help"].iter().any(|k| lower.contains(k)) {
+        "fallback.help"
+    } else if ["狀態", "状態", "status"].iter().any(|k| lower.contains(k)) {
+        "fallback.status"
+    } else {
+        "fallback.default"
+    };
+    i18n.text(locale, key, &[("message", message), ("name", name)])
+}
@@ -0,0 +1,282 @@
+//! 使用者傳來的媒體模組
+//! 圖片、影片、語音與檔案由 LINE 內容 API 下載（影片與語音先輪詢轉檔狀態），下載結果以 [`Content`] 交給後續處理（如語音轉文字）；
+//! 媒體存入物件儲存（`incoming/` 前綴），檔案交給 `FILE_HANDLER` 設定的處理方式，並以 `[image] <key>` 等記入對話紀錄；未開啟的種類回覆暫不支援。
+//! 開啟附件封存時所有種類改存入 `attachments/` 並回覆永久連結
+
+use reqwest::header::CONTENT_TYPE;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::attachments;
+use crate::config::Config;
+use crate::files;
+use crate::i18n::Locale;
+use crate::line::{Message, OutgoingMessage, TextMessage, TranscodingStatus};
+use crate::privacy;
+use crate::store::AuditEvent;
+use crate::AppState;
+
+/// 使用者媒體在物件儲存中的路徑前綴
+pub const INCOMING_PREFIX: &str = "incoming/";
+/// 輪詢影片與語音轉檔狀態的間隔與次數
+const TRANSCODING_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const TRANSCODING_POLL_ATTEMPTS: u32 = 15;
+
+/// 使用者傳來的媒體種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Image,
+    Video,
+    /// 語音訊息（m4a）
+    Audio,
+    /// 文件等檔案
+    File,
+}
+
+impl Kind {
+    /// 由 LINE 訊息類型判斷；非媒體訊息回傳 None
+    pub fn parse(message_type: &str) -> Option<Self> {
+        match message_type {
+            "image" => Some(Kind::Image),
+            "video" => Some(Kind::Video),
+            "audio" => Some(Kind::Audio),
+            "file" => Some(Kind::File),
+            _ => None,
+        }
+    }
+
+    /// 訊息類型名稱（也是語系 key 與對話紀錄的前綴）
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Image => "image",
+            Kind::Video => "video",
+            Kind::Audio => "audio",
+            Kind::File => "file",
+        }
+    }
+
+    fn enabled(self, config: &Config) -> bool {
+        if config.attachment_archive.is_some() {
+            return true;
+        }
+        match self {
+            Kind::Image => config.save_incoming_images,
+            Kind::Video => config.save_incoming_videos,
+            Kind::Audio => config.save_incoming_audio,
+            Kind::File => config.file_handler.is_some(),
+        }
+    }
+
+    fn max_bytes(self, config: &Config) -> usize {
+        match self {
+            Kind::Image => config.image_max_bytes,
+            Kind::Video => config.video_max_bytes,
+            Kind::Audio => config.audio_max_bytes,
+            Kind::File => config.file_max_bytes,
+        }
+    }
+
+    /// 需等 LINE 轉檔完成才能下載
+    fn needs_transcoding(self) -> bool {
+        matches!(self, Kind::Video | Kind::Audio)
+    }
+
+}
+
+/// 下載完成的媒體內容
+pub struct Content {
+    pub message_id: String,
+    pub kind: Kind,
+    pub content_type: Option<String>,
+    /// 檔案訊息的原始檔名
+    pub file_name: Option<String>,
+    /// 影片與語音的長度（毫秒）
+    pub duration_ms: Option<u64>,
+    pub data: Vec<u8>,
+}
+
+impl Content {
+    /// 存檔用的副檔名：檔案沿用原檔名的副檔名，其餘依內容格式判斷
+    pub fn extension(&self) -> String {
+        let from_name = self
+            .file_name
+            .as_deref()
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, extension)| extension.to_lowercase())
+            .filter(|extension| (1..=8).contains(&extension.len()) && extension.chars().all(|c| c.is_ascii_alphanumeric()));
+        if let Some(extension) = from_name {
+            return extension;
+        }
+        match self.content_type.as_deref() {
+            Some("image/jpeg") => "jpg",
+            Some("image/png") => "png",
+            Some("image/gif") => "gif",
+            Some("image/webp") => "webp",
+            Some("video/mp4") => "mp4",
+            Some("video/quicktime") => "mov",
+            Some("audio/x-m4a" | "audio/m4a" | "audio/mp4" | "audio/aac") => "m4a",
+            Some("application/pdf") => "pdf",
+            _ => "bin",
+        }
+        .to_string()
+    }
+}
+
+/// 處理結果：記入對話紀錄的參照、稽核細節，以及取代預設回覆的文字
+pub struct Handled {
+    pub reference: String,
+    pub detail: String,
+    pub reply: Option<String>,
+}
+
+/// 下載失敗的原因
+pub enum DownloadError {
+    TooLarge,
+    Failed(String),
+}
+
+/// 處理媒體訊息，回傳要回覆的訊息
+pub async fn handle(state: &AppState, user_id: &str, locale: Locale, kind: Kind, message: &Message) -> Vec<OutgoingMessage> {
+    let t = |key: &str| state.i18n.text(locale, &format!("{}.{}", kind.name(), key), &[]);
+    if !kind.enabled(&state.config) {
+        return vec![TextMessage::new(t("unsupported")).into()];
+    }
+
+    let started = Instant::now();
+    // 外部來源的媒體只記錄網址，LINE 來源的才下載
+    let external = message
+        .content_provider
+        .as_ref()
+        .filter(|provider| provider.provider_type == "external")
+        .and_then(|provider| provider.original_content_url.clone());
+    let Handled { reference, mut detail, reply } = match external {
+        Some(url) => Handled { detail: format!("url={}", url), reference: url, reply: None },
+        None => match download(state, kind, message).await {
+            Ok(content) => match dispatch(state, user_id, locale, content).await {
+                Ok(handled) => handled,
+                Err(e) => return failed(state, user_id, kind, locale, started, &e),
+            },
+            Err(DownloadError::TooLarge) => {
+                audit(state, user_id, kind, "too_large", started, &format!("message_id={}", message.id));
+                let limit = match kind.max_bytes(&state.config) {
+                    bytes if bytes >= 1024 * 1024 => format!("{} MB", bytes / 1024 / 1024),
+                    bytes => format!("{} KB", bytes.div_ceil(1024)),
+                };
+                let text = state.i18n.text(locale, &format!("{}.too_large", kind.name()), &[("limit", &limit)]);
+                return vec![TextMessage::new(text).into()];
+            }
+            Err(DownloadError::Failed(e)) => return failed(state, user_id, kind, locale, started, &e),
+        },
+    };
+    if let Some(duration) = message.duration {
+        detail.push_str(&format!(" duration_ms={}", duration));
+    }
+
+    let session = state
+        .store
+        .continue_session(user_id, state.config.session_gap_secs)
+        .map_err(|e| warn!("Failed to continue conversation session: {}", e))
+        .ok();
+    if let Err(e) = state.store.record(user_id, "user", &format!("[{}] {}", kind.name(), reference), None, session) {
+        warn!("Failed to store {} message: {}", kind.name(), e);
+    }
+    info!("Saved {} from {} ({})", kind.name(), privacy::id(user_id), detail);
+    audit(state, user_id, kind, "ok", started, &detail);
+    let reply = reply.unwrap_or_else(|| {
+        let name = message.file_name.as_deref().unwrap_or_default();
+        state.i18n.text(locale, &format!("{}.saved", kind.name()), &[("name", name)])
+    });
+    vec![TextMessage::new(reply).into()]
+}
+
+/// 將下載的內容交給處理方式：檔案有設定 `FILE_HANDLER` 時依其處理，其餘在開啟附件封存時封存，否則存入物件儲存
+async fn dispatch(state: &AppState, user_id: &str, locale: Locale, content: Content) -> Result<Handled, String> {
+    if let (Kind::File, Some(handler)) = (content.kind, &state.config.file_handler) {
+        return files::handle(state, handler, user_id, content).await;
+    }
+    if let Some(links) = &state.config.attachment_archive {
+        return attachments::archive(state, links, locale, content).await;
+    }
+    let bytes = content.data.len();
+    let key = format!("{}{}.{}", INCOMING_PREFIX, content.message_id, content.extension());
+    state.blobs.put(&key, content.data).await?;
+    Ok(Handled { detail: format!("key={} bytes={}", key, bytes), reference: key, reply: None })
+}
+
+/// 下載媒體內容（影片與語音先等候轉檔完成）；超過該種類的大小上限時中止下載
+pub async fn download(state: &AppState, kind: Kind, message: &Message) -> Result<Content, DownloadError> {
+    let limit = kind.max_bytes(&state.config);
+    // 檔案訊息附有大小，超過上限時不必下載
+    if message.file_size.is_some_and(|size| size as usize > limit) {
+        return Err(DownloadError::TooLarge);
+    }
+    let message_id = message.id.as_str();
+    if kind.needs_transcoding() {
+        wait_for_transcoding(state, message_id).await?;
+    }
+    let mut response = state
+        .line_client
+        .get_message_content(message_id)
+        .await
+        .map_err(|e| DownloadError::Failed(e.to_string()))?;
+    if response.content_length().is_some_and(|length| length as usize > limit) {
+        return Err(DownloadError::TooLarge);
+    }
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or(v).trim().to_string());
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| DownloadError::Failed(e.to_string()))? {
+        if data.len() + chunk.len() > limit {
+            return Err(DownloadError::TooLarge);
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(Content {
+        message_id: message_id.to_string(),
+        kind,
+        content_type,
+        file_name: message.file_name.clone(),
+        duration_ms: message.duration,
+        data,
+    })
+}
+
+/// 輪詢轉檔狀態直到完成；失敗或逾時回傳錯誤
+async fn wait_for_transcoding(state: &AppState, message_id: &str) -> Result<(), DownloadError> {
+    for attempt in 0..TRANSCODING_POLL_ATTEMPTS {
+        match state.line_client.get_content_transcoding(message_id).await {
+            Ok(TranscodingStatus::Succeeded) => return Ok(()),
+            Ok(TranscodingStatus::Failed) => return Err(DownloadError::Failed("LINE 轉檔失敗".to_string())),
+            Ok(TranscodingStatus::Processing) => {}
+            Err(e) => return Err(DownloadError::Failed(e.to_string())),
+        }
+        if attempt + 1 < TRANSCODING_POLL_ATTEMPTS {
+            tokio::time::sleep(TRANSCODING_POLL_INTERVAL).await;
+        }
+    }
+    Err(DownloadError::Failed(format!(
+        "等候轉檔逾時（{} 秒）",
+        TRANSCODING_POLL_INTERVAL.as_secs() * u64::from(TRANSCODING_POLL_ATTEMPTS)
+    )))
+}
+
+fn failed(state: &AppState, user_id: &str, kind: Kind, locale: Locale, started: Instant, error: &str) -> Vec<OutgoingMessage> {
+    warn!("Failed to save {} from {}: {}", kind.name(), privacy::id(user_id), error);
+    audit(state, user_id, kind, "error", started, error);
+    vec![TextMessage::new(state.i18n.text(locale, &format!("{}.error", kind.name()), &[])).into()]
+}
+
+fn audit(state: &AppState, user_id: &str, kind: Kind, status: &str, started: Instant, detail: &str) {
+    crate::audit(state, &AuditEvent {
+        user_id,
+        event_type: "message",
+        action: kind.name(),
+        status,
+        latency_ms: Some(started.elapsed().as_millis() as i64),
+        detail: Some(detail),
+        ..Default::default()
+    });
+}
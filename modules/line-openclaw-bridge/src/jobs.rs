@@ -0,0 +1,179 @@
+//! 背景工作模組
+//! 耗時的管理操作（匯出、封存、資料庫維護）以 `?async=true` 改在背景執行，立即回傳工作 ID，
+//! 呼叫端以 `GET /admin/jobs/:id` 輪詢進度與結果，不必占住 HTTP 連線數分鐘
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::clock::Clock;
+
+/// 保留在記憶體中的工作數（進行中的工作不受限制）
+const MAX_JOBS: usize = 100;
+
+/// 工作狀態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// 工作的進度與結果
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: u64,
+    /// 工作種類（如 `export`、`archive`、`maintenance`）
+    pub kind: String,
+    pub status: JobStatus,
+    /// 已完成的量（單位依工作種類而定，如匯出的位元組數）
+    pub done: u64,
+    /// 總量，無法預估時為 None
+    pub total: Option<u64>,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+/// 交給工作本身回報進度
+pub struct JobHandle {
+    sender: watch::Sender<Job>,
+}
+
+impl JobHandle {
+    /// 更新已完成的量與總量
+    pub fn progress(&self, done: u64, total: Option<u64>) {
+        self.sender.send_modify(|job| {
+            job.done = done;
+            job.total = total;
+        });
+    }
+}
+
+/// 進行中與最近完成的背景工作
+pub struct Jobs {
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<u64, watch::Receiver<Job>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Jobs {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self { next_id: AtomicU64::new(0), jobs: Mutex::default(), clock }
+    }
+
+    /// 建立工作並在背景執行，回傳初始狀態
+    pub fn start<F, Fut>(&self, kind: &str, work: F) -> Job
+    where
+        F: FnOnce(JobHandle) -> Fut,
+        Fut: Future<Output = Result<serde_json::Value, String>> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let job = Job {
+            id,
+            kind: kind.to_string(),
+            status: JobStatus::Running,
+            done: 0,
+            total: None,
+            result: None,
+            error: None,
+            started_at: self.clock.timestamp(),
+            finished_at: None,
+        };
+        let (sender, receiver) = watch::channel(job.clone());
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.insert(id, receiver);
+            prune(&mut jobs);
+        }
+
+        info!("Job {} ({}) started", id, kind);
+        let future = work(JobHandle { sender: sender.clone() });
+        let clock = self.clock.clone();
+        tokio::spawn(async move {
+            let outcome = future.await;
+            sender.send_modify(|job| {
+                job.finished_at = Some(clock.timestamp());
+                match outcome {
+                    Ok(result) => {
+                        job.status = JobStatus::Succeeded;
+                        job.result = Some(result);
+                    }
+                    Err(e) => {
+                        job.status = JobStatus::Failed;
+                        job.error = Some(e);
+                    }
+                }
+            });
+            let job = sender.borrow();
+            match &job.error {
+                None => info!("Job {} ({}) succeeded", job.id, job.kind),
+                Some(e) => warn!("Job {} ({}) failed: {}", job.id, job.kind, e),
+            }
+        });
+        job
+    }
+
+    /// 目前狀態
+    pub fn get(&self, id: u64) -> Option<Job> {
+        self.jobs.lock().unwrap().get(&id).map(|r| r.borrow().clone())
+    }
+
+    /// 所有保留中的工作（新的在前）
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().values().rev().map(|r| r.borrow().clone()).collect()
+    }
+}
+
+/// 超過 MAX_JOBS 時由舊到新移除已結束的工作；進行中的工作一律保留，仍可查詢進度
+fn prune(jobs: &mut BTreeMap<u64, watch::Receiver<Job>>) {
+    let excess = jobs.len().saturating_sub(MAX_JOBS);
+    let finished: Vec<u64> = jobs
+        .iter()
+        .filter(|(_, job)| job.borrow().status != JobStatus::Running)
+        .map(|(id, _)| *id)
+        .take(excess)
+        .collect();
+    for id in finished {
+        jobs.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pruning_keeps_running_jobs() {
+        let jobs = Jobs::new(crate::clock::system());
+        let running = jobs.start("export", |_| std::future::pending());
+        for _ in 0..MAX_JOBS {
+            jobs.start("archive", |_| async { Ok(serde_json::Value::Null) });
+        }
+        // 等背景工作寫回結果
+        while jobs.list().iter().any(|job| job.kind == "archive" && job.status == JobStatus::Running) {
+            tokio::task::yield_now().await;
+        }
+        let newest = jobs.start("maintenance", |_| std::future::pending());
+
+        assert_eq!(jobs.list().len(), MAX_JOBS);
+        assert_eq!(jobs.get(running.id).unwrap().status, JobStatus::Running);
+        assert!(jobs.get(running.id + 1).is_none());
+        assert!(jobs.get(newest.id).is_some());
+    }
+
+    #[tokio::test]
+    async fn running_jobs_may_exceed_the_limit() {
+        let jobs = Jobs::new(crate::clock::system());
+        for _ in 0..MAX_JOBS + 5 {
+            jobs.start("export", |_| std::future::pending());
+        }
+        assert_eq!(jobs.list().len(), MAX_JOBS + 5);
+    }
+}
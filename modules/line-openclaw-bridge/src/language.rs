@@ -0,0 +1,131 @@
+//! 回答語言模組
+//! 以系統指示要求 OpenClaw 使用指定語言回答，並依文字種類判斷回答的語言，不符時要求改寫
+
+use crate::formatting::{self, Segment};
+use crate::i18n::Locale;
+
+/// 判斷語言所需的最少文字單位（漢字、假名各算一個，英文以單字計）
+const MIN_UNITS: usize = 10;
+
+/// 強制的回答語言
+#[derive(Debug, Clone, Copy)]
+pub enum ReplyLanguage {
+    /// 依使用者的 LINE 語言設定
+    User,
+    Fixed(Locale),
+}
+
+impl ReplyLanguage {
+    /// `auto` 或語言標籤（`zh-TW`、`ja`、`en`）
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "auto" => Some(ReplyLanguage::User),
+            tag => Locale::from_tag(tag).map(ReplyLanguage::Fixed),
+        }
+    }
+
+    pub fn resolve(self, user_locale: Locale) -> Locale {
+        match self {
+            ReplyLanguage::User => user_locale,
+            ReplyLanguage::Fixed(locale) => locale,
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            ReplyLanguage::User => "依使用者語言".to_string(),
+            ReplyLanguage::Fixed(locale) => locale.tag().to_string(),
+        }
+    }
+}
+
+/// 附在對話前的系統指示
+pub fn instruction(locale: Locale) -> String {
+    format!(
+        "Always answer in {}, even if the user or earlier messages use another language. \
+         Keep code, commands and proper nouns as they are.",
+        locale.language_name()
+    )
+}
+
+/// 回答語言不符時的改寫要求
+pub fn retry_prompt(locale: Locale) -> String {
+    format!(
+        "Your previous answer was not written in {0}. Rewrite the same answer in {0} without adding anything else.",
+        locale.language_name()
+    )
+}
+
+/// 依程式碼區塊以外的文字判斷語言；文字太少或無法判斷時回傳 None
+pub fn detect(text: &str) -> Option<Locale> {
+    let (mut han, mut kana, mut latin) = (0usize, 0usize, 0usize);
+    for (_, segment) in formatting::split(text) {
+        let Segment::Text(text) = segment else { continue };
+        let mut in_word = false;
+        for c in text.chars() {
+            match c {
+                '\u{3040}'..='\u{30FF}' => kana += 1,
+                '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' => han += 1,
+                _ if c.is_ascii_alphabetic() => {
+                    if !in_word {
+                        latin += 1;
+                    }
+                    in_word = true;
+                    continue;
+                }
+                _ => {}
+            }
+            in_word = false;
+        }
+    }
+
+    let total = han + kana + latin;
+    if total < MIN_UNITS {
+        return None;
+    }
+    // 日文必含假名；中文以漢字為主；英文幾乎不含漢字與假名
+    if kana * 20 >= total {
+        Some(Locale::Ja)
+    } else if han * 2 >= total {
+        Some(Locale::ZhTw)
+    } else if latin * 10 >= total * 8 {
+        Some(Locale::En)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_each_supported_language() {
+        assert_eq!(detect("今天台北的天氣晴朗，適合出門走走。"), Some(Locale::ZhTw));
+        assert_eq!(detect("今日の東京はとても良い天気ですね。散歩に行きましょう。"), Some(Locale::Ja));
+        assert_eq!(detect("The weather in Taipei is sunny today, a good day for a walk."), Some(Locale::En));
+    }
+
+    #[test]
+    fn short_or_mixed_text_is_undecided() {
+        assert_eq!(detect("好的"), None);
+        assert_eq!(detect("OK thanks"), None);
+        assert_eq!(detect("請用 cargo build and cargo test 檢查 the workspace"), None);
+    }
+
+    #[test]
+    fn code_blocks_are_ignored() {
+        let answer = "請執行以下指令來建置整個專案：\n```sh\ncargo build --workspace --all-targets --release\n```\n";
+        assert_eq!(detect(answer), Some(Locale::ZhTw));
+        assert_eq!(detect("```\nlet answer = compute_the_value_of_everything();\n```"), None);
+    }
+
+    #[test]
+    fn parses_auto_and_locale_tags() {
+        assert!(matches!(ReplyLanguage::parse(" auto "), Some(ReplyLanguage::User)));
+        assert!(matches!(ReplyLanguage::parse("ja"), Some(ReplyLanguage::Fixed(Locale::Ja))));
+        assert!(ReplyLanguage::parse("fr").is_none());
+        assert_eq!(ReplyLanguage::User.resolve(Locale::En), Locale::En);
+        assert_eq!(ReplyLanguage::Fixed(Locale::Ja).resolve(Locale::En), Locale::Ja);
+    }
+}
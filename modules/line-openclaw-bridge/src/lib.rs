@@ -0,0 +1,1187 @@
+//! LINE-OpenClaw Bridge 函式庫
+//! 以 `build_router` 建立完整的 axum Router，可掛在其他服務的路徑前綴下，不必另外啟動程序
+
+mod admin;
+mod announcements;
+mod archive;
+mod attachments;
+mod automations;
+mod blob;
+mod branding;
+mod briefing;
+mod chains;
+mod channels;
+pub mod cli;
+mod commands;
+mod confidence;
+pub mod config;
+mod dashboard;
+mod dataset;
+mod delivery;
+mod emojis;
+mod encryption;
+pub mod export;
+mod fanout;
+mod feeds;
+mod files;
+mod flags;
+mod formatting;
+mod generations;
+mod i18n;
+mod incoming;
+mod jobs;
+mod language;
+mod liff;
+mod linking;
+mod locations;
+pub mod logging;
+mod maintenance;
+mod markdown;
+#[cfg(feature = "matrix")]
+mod matrix;
+mod media;
+mod mentions;
+mod menus;
+mod metrics;
+mod models;
+#[cfg(feature = "mtls")]
+mod mtls;
+mod notify;
+mod outbound;
+mod pipeline;
+mod polls;
+mod profiles;
+mod proxy;
+mod quiz;
+mod quota;
+mod random;
+mod ratelimit;
+mod redact;
+pub mod repl;
+mod repetition;
+mod reporting;
+mod resources;
+mod secrets;
+mod sessions;
+mod sigv4;
+mod snapshot;
+mod status;
+mod stickers;
+mod supervisor;
+mod tokens;
+mod whatsapp;
+
+use bridge_core::{chat, clock, moderation, openclaw, privacy, store};
+use line_adapter as line;
+
+pub use crate::config::Config;
+pub use bridge_core::openclaw::OpenClawClient;
+pub use bridge_core::store::ConversationStore;
+pub use line_adapter::LineClient;
+use whatsapp_adapter::WhatsAppClient;
+
+use axum::{
+    extract::{DefaultBodyLimit, Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, RwLock};
+use tower_http::decompression::RequestDecompressionLayer;
+use tracing::{debug, info, error, warn};
+
+use crate::blob::BlobStore;
+use crate::clock::Clock;
+use crate::branding::Branding;
+use crate::chains::{Chain, Chains};
+use crate::commands::Command;
+use crate::flags::FeatureFlags;
+use crate::generations::Generations;
+use crate::i18n::{I18n, Locale, fallback_response};
+use crate::jobs::Jobs;
+use crate::line::{Event, MessageEvent, OutgoingMessage, Template, TemplateMessage, TextMessage};
+use crate::metrics::Metrics;
+use crate::notify::Notifications;
+use crate::pipeline::Traces;
+use crate::random::Random;
+use crate::ratelimit::RateLimiter;
+use crate::secrets::{Secrets, SecretsConfig};
+use crate::status::StatusTracker;
+use crate::openclaw::ChatMessage;
+use crate::store::{AuditEvent, SearchQuery};
+
+/// Webhook 回應時限；不經過 `SharedState` 的鎖，寫入鎖等待中也能準時回應
+#[derive(Clone)]
+struct WebhookGuard {
+    timeout: Duration,
+    metrics: Arc<Metrics>,
+}
+
+/// 跨 handler 共用的應用程式狀態
+type SharedState = Arc<RwLock<AppState>>;
+
+/// 應用程式狀態
+struct AppState {
+    line_client: LineClient,
+    /// 背景排程在狀態鎖外呼叫 OpenClaw 時複製使用
+    openclaw_client: Arc<OpenClawClient>,
+    store: Arc<ConversationStore>,
+    /// 封存檔等物件的儲存位置
+    blobs: Arc<BlobStore>,
+    /// 啟動時讀取的設定
+    config: Arc<Config>,
+    /// 各路由的請求數與延遲
+    metrics: Arc<Metrics>,
+    /// 功能旗標（可熱更新）
+    flags: Arc<FeatureFlags>,
+    /// 多語系訊息
+    i18n: I18n,
+    /// 機器人名稱、個性與問候語
+    branding: Branding,
+    /// 多步驟回答流程
+    chains: Chains,
+    /// 管理 API 發出的推播通知與進度
+    notifications: Arc<Notifications>,
+    /// 管理 API 以 `?async=true` 啟動的背景工作
+    jobs: Arc<Jobs>,
+    /// 最近 webhook 事件的各階段耗時
+    traces: Arc<Traces>,
+    /// 運行時間與 OpenClaw 狀態（公開狀態頁使用）
+    status: Arc<StatusTracker>,
+    /// 進行中的回答（`/stop` 取消用）
+    generations: Generations,
+    /// 每位使用者的 AI 訊息頻率限制；未設定 `USER_RATE_LIMIT` 時為 None
+    rate_limiter: Option<RateLimiter>,
+    /// 排程與時間判斷使用的時鐘（與資料庫共用）
+    clock: Arc<dyn Clock>,
+    /// 實驗分組與重試延遲使用的亂數來源（設定 `RANDOM_SEED` 時可重現）
+    random: Arc<Random>,
+    /// WhatsApp 通道；未設定時為 None
+    whatsapp_client: Option<WhatsAppClient>,
+    /// 最近處理過的 WhatsApp 訊息 ID（略過重送的訊息）
+    whatsapp_recent: pipeline::RecentIds,
+    /// 最近處理過的 LINE webhook 事件 ID（略過 LINE 重送的事件）
+    line_recent: pipeline::RecentIds,
+    /// 背景統計的資料表列數（資源指標使用）
+    row_counts: Arc<resources::RowCounts>,
+}
+
+/// 依設定建立客戶端與應用程式狀態（不啟動背景工作）；一併回傳外部密鑰管理讀到的初始值
+async fn build_state(mut config: Config) -> Result<(AppState, Option<(SecretsConfig, Secrets)>), String> {
+    if let Some(key) = &config.privacy_hash_key {
+        privacy::init(key);
+    }
+    if let Some(dsn) = &config.sentry_dsn {
+        reporting::init(dsn, &config.sentry_environment)?;
+    }
+
+    // 從外部密鑰管理讀取 token（優先於環境變數）
+    let secrets = match &config.secrets {
+        Some(secrets_config) => {
+            let fetched = secrets::fetch(&secrets_config.backend).await?;
+            if let Some(token) = &fetched.line_channel_access_token {
+                config.line_channel_access_token = token.clone();
+            }
+            if fetched.openclaw_gateway_token.is_some() {
+                config.openclaw_gateway_token = fetched.openclaw_gateway_token.clone();
+            }
+            Some((secrets_config.clone(), fetched))
+        }
+        None => None,
+    };
+    if config.line_channel_access_token.is_empty() {
+        return Err("LINE_CHANNEL_ACCESS_TOKEN 未設定，密鑰管理中也沒有此欄位".to_string());
+    }
+
+    // 建立客戶端
+    let line_client = LineClient::new(
+        config.line_channel_access_token.clone(),
+        config.line_channel_secret.clone(),
+        config.line_api_base_url.clone(),
+    )
+    .with_previous_secret(config.line_channel_secret_previous.clone());
+    line_client.set_dry_run(config.line_dry_run);
+    if let Some(until) = config.line_channel_secret_previous_until {
+        info!("Accepting webhooks signed with the previous channel secret until {}", until.to_rfc3339());
+    }
+    let openclaw_client = Arc::new(
+        OpenClawClient::new(
+            config.openclaw_base_url.clone(),
+            config.openclaw_gateway_token.clone(),
+            config.openclaw_model.clone(),
+            config.openclaw_streaming,
+        )
+        .with_metadata(config.openclaw_metadata),
+    );
+    let clock = clock::system();
+    let store = Arc::new(
+        ConversationStore::open_with_clock(&config.database_path, clock.clone())
+            .map_err(|e| format!("無法開啟對話資料庫: {}", e))?,
+    );
+    if config.compress_conversations {
+        store.enable_compression();
+    }
+    if let Some(path) = &config.snapshot_import_path {
+        snapshot::import_file(&store, path)?;
+    }
+    let recorder_store = store.clone();
+    line_client.set_dry_run_recorder(move |message| {
+        info!("Dry run: intercepted LINE {} to {}", message.endpoint, message.target.as_deref().map(privacy::id).unwrap_or("all".into()));
+        if let Err(e) = recorder_store.add_dry_run_message(message.endpoint, message.target.as_deref(), &message.body) {
+            warn!("Failed to store dry-run message: {}", e);
+        }
+    });
+    quota::install(&line_client, store.clone());
+    let blobs = Arc::new(config.blob_store(clock.clone()));
+    let random = Arc::new(Random::new(config.random_seed));
+    if let Some(seed) = config.random_seed {
+        warn!("RANDOM_SEED is set to {}; random values are reproducible, do not use this in production", seed);
+    }
+    let flags = Arc::new(FeatureFlags::load(
+        config.feature_flags_path.as_ref().map(Into::into),
+        config.environment.clone(),
+        config.random_seed,
+    )?);
+    flags::spawn_watcher(flags.clone());
+    let i18n = I18n::load(config.default_locale, config.locales_dir.as_deref().map(std::path::Path::new))?;
+    let branding = Branding::load(config.branding_path.as_deref().map(std::path::Path::new))?;
+    let chains = Chains::load(config.chains_path.as_deref().map(std::path::Path::new))?;
+    let metrics = Arc::new(Metrics::new(std::time::Duration::from_millis(config.slow_request_ms)));
+    let rate_limiter = config.user_rate_limit.map(|limit| RateLimiter::new(limit, config.user_rate_window, clock.clone()));
+
+    let traces = Arc::new(Traces::new(config.trace_sampling, random.clone()));
+    let whatsapp_client = config.whatsapp.as_ref().map(|whatsapp| whatsapp.client());
+    let state = AppState {
+        line_client,
+        openclaw_client,
+        store,
+        blobs,
+        config: Arc::new(config),
+        metrics,
+        flags,
+        i18n,
+        branding,
+        chains,
+        notifications: Arc::new(Notifications::new(clock.clone())),
+        jobs: Arc::new(Jobs::new(clock.clone())),
+        traces,
+        status: Arc::new(StatusTracker::new(clock.clone())),
+        generations: Generations::default(),
+        rate_limiter,
+        clock,
+        random,
+        whatsapp_client,
+        whatsapp_recent: pipeline::RecentIds::default(),
+        line_recent: pipeline::RecentIds::default(),
+        row_counts: Arc::default(),
+    };
+    Ok((state, secrets))
+}
+
+/// 依設定建立 Bridge 的完整路由（webhook、管理 API、LIFF 等）並啟動背景工作；
+/// 掛在路徑前綴下時，`PUBLIC_BASE_URL` 須包含該前綴
+pub async fn build_router(config: Config) -> Result<Router, String> {
+    let (state, secrets) = build_state(config).await?;
+    let config = state.config.clone();
+    let metrics = state.metrics.clone();
+    if let Some(days) = config.retention_days {
+        archive::spawn(state.store.clone(), state.blobs.clone(), days);
+    }
+    if let Some(window) = config.maintenance.window {
+        maintenance::spawn(state.store.clone(), config.maintenance.clone(), window);
+    }
+    resources::spawn_monitor(state.store.clone(), state.row_counts.clone(), config.store_soft_limits.clone());
+    let report = config.report(&state.blobs);
+    let state = Arc::new(RwLock::new(state));
+
+    if let Some(recovery) = config.recovery.clone() {
+        supervisor::spawn(state.clone(), recovery);
+    }
+    status::spawn(state.clone());
+    models::spawn(state.clone());
+    polls::spawn(state.clone());
+    briefing::spawn(state.clone());
+    automations::spawn(state.clone());
+    #[cfg(feature = "matrix")]
+    if let Some(matrix) = &config.matrix {
+        matrix::spawn(state.clone(), matrix);
+    }
+    notify::spawn(state.clone());
+    admin::resume_narrowcasts(&state).await;
+    profiles::spawn(state.clone());
+    quota::spawn(state.clone());
+    if let Some((secrets_config, fetched)) = secrets {
+        secrets::spawn(state.clone(), secrets_config, fetched);
+    }
+    #[cfg(feature = "mtls")]
+    if let Some(tls) = config.admin_tls.clone() {
+        mtls::spawn(state.clone(), &config.host, tls).await?;
+    }
+
+    info!("🤖 預設模型: {}", report.model);
+    info!("🗄️ 物件儲存: {}", report.storage);
+    for feature in &report.features {
+        info!("{} {}: {}", if feature.enabled { "✅" } else { "⬜" }, feature.name, feature.detail);
+    }
+    for entry in &report.settings {
+        info!("⚙️ {} = {} ({})", entry.key, entry.value.as_deref().unwrap_or("(未設定)"), entry.source);
+    }
+
+    // 建立路由；代理轉送的 gzip/deflate webhook 先解壓縮，大小上限以解壓縮後計算
+    let webhook = Router::new()
+        .route("/callback", post(webhook_callback))
+        .layer(Extension(WebhookGuard {
+            timeout: Duration::from_millis(config.webhook_response_timeout_ms),
+            metrics: metrics.clone(),
+        }))
+        .layer(RequestDecompressionLayer::new())
+        .layer(DefaultBodyLimit::max(config.webhook_max_body_bytes));
+    Ok(Router::new()
+        .route("/", get(root))
+        .route("/health", get(health_check))
+        .route("/metrics", get(metrics_endpoint))
+        .route("/status", get(status::page))
+        .route("/dashboard", get(dashboard::page))
+        .merge(webhook)
+        .route("/files/*key", get(signed_file))
+        .route("/attachments/*path", get(attachments::serve))
+        .nest("/admin", admin::router(state.clone()))
+        .nest("/liff", liff::router())
+        .nest("/whatsapp", whatsapp::router())
+        .layer(middleware::from_fn(dry_run_header))
+        .layer(middleware::from_fn_with_state(metrics, metrics::track))
+        .layer(middleware::from_fn_with_state(Arc::new(config.trusted_proxies.clone()), proxy::client_ip))
+        .with_state(state))
+}
+
+/// 帶 `X-Dry-Run: true` 標頭的請求以試運行模式處理：期間送往 LINE 的訊息只記錄、不送出
+async fn dry_run_header(request: axum::extract::Request, next: middleware::Next) -> axum::response::Response {
+    let dry_run = request
+        .headers()
+        .get("x-dry-run")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"));
+    if dry_run {
+        line::dry_run(next.run(request)).await
+    } else {
+        next.run(request).await
+    }
+}
+
+/// 根路徑
+async fn root(State(state): State<SharedState>) -> String {
+    format!("{} Service v{}", state.read().await.branding.name, env!("CARGO_PKG_VERSION"))
+}
+
+/// 健康檢查端點
+async fn health_check(
+    State(state): State<SharedState>,
+) -> Json<serde_json::Value> {
+    let state = state.read().await;
+    let openclaw_status = match state.openclaw_client.health_check().await {
+        Ok(true) => "online",
+        Ok(false) => "offline",
+        Err(_) => "unreachable",
+    };
+    
+    Json(json!({
+        "status": "ok",
+        "service": "line-openclaw-bridge",
+        "openclaw": openclaw_status,
+        "line_quota": quota::current(&state),
+    }))
+}
+
+/// Prometheus 指標端點
+async fn metrics_endpoint(State(state): State<SharedState>) -> impl IntoResponse {
+    let state = state.read().await;
+    let mut body = state.metrics.render();
+    match resources::snapshot(&state.store, &state.row_counts, &state.config.store_soft_limits) {
+        Ok(snapshot) => body.push_str(&snapshot.render()),
+        Err(e) => warn!("Failed to collect resource stats: {}", e),
+    }
+    ([(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// 簽章網址參數
+#[derive(serde::Deserialize)]
+struct SignedFileParams {
+    token: String,
+}
+
+/// 以簽章網址提供本機儲存的媒體檔（LINE 會以此網址下載圖片/音訊）
+async fn signed_file(
+    State(state): State<SharedState>,
+    Path(key): Path<String>,
+    Query(params): Query<SignedFileParams>,
+) -> Result<([(axum::http::header::HeaderName, &'static str); 1], Vec<u8>), StatusCode> {
+    let state = state.read().await;
+    let data = state.blobs.get_signed(&key, &params.token).await.map_err(|e| {
+        warn!("Rejected file request {}: {}", key, e);
+        StatusCode::NOT_FOUND
+    })?;
+    Ok(([(axum::http::header::CONTENT_TYPE, media::content_type(&key))], data))
+}
+
+/// LINE Webhook 回調端點
+async fn webhook_callback(
+    State(state): State<SharedState>,
+    Extension(guard): Extension<WebhookGuard>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<&'static str, StatusCode> {
+    let start = Instant::now();
+    // 取得簽名
+    let signature = headers
+        .get("x-line-signature")
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| {
+            warn!("Missing X-Line-Signature header");
+            StatusCode::BAD_REQUEST
+        })?
+        .to_string();
+
+    // 驗證與處理都在背景工作中進行；時限內完成驗證則回傳其結果，否則先回應 200 避免 LINE 逾時重送
+    let (verified_tx, verified_rx) = oneshot::channel();
+    // 背景工作不在請求的試運行範圍內，需另外帶入
+    if line::in_dry_run() {
+        tokio::spawn(line::dry_run(process_webhook(state, signature, body, start, verified_tx)));
+    } else {
+        tokio::spawn(process_webhook(state, signature, body, start, verified_tx));
+    }
+    let (result, timed_out) = match tokio::time::timeout(guard.timeout, verified_rx).await {
+        Ok(Ok(result)) => (result, false),
+        Ok(Err(_)) => (Err(StatusCode::INTERNAL_SERVER_ERROR), false),
+        Err(_) => {
+            warn!(
+                timeout_ms = guard.timeout.as_millis() as u64,
+                "Webhook verification exceeded the response deadline, acknowledging early"
+            );
+            (Ok(()), true)
+        }
+    };
+    guard.metrics.observe_webhook(start.elapsed(), timed_out);
+    result.map(|_| "OK")
+}
+
+/// 驗證 webhook 簽名：接受目前的 channel secret，輪替期限前也接受舊的 secret；結果依相符的 secret 記入指標
+fn check_signature(state: &AppState, body: &[u8], signature: &str) -> bool {
+    let matched = state.line_client.verify_signature(body, signature);
+    let (key, accepted) = signature_outcome(matched, state.config.line_channel_secret_previous_until, state.clock.now());
+    state.metrics.observe_signature(key);
+    match key {
+        "previous_expired" => error!("Rejected webhook signed with the previous channel secret after its deadline"),
+        "invalid" => error!("Invalid signature"),
+        _ => {}
+    }
+    accepted
+}
+
+/// 簽名驗證結果的指標標籤與是否接受；舊的 secret 只在輪替期限 `deadline` 之前有效
+fn signature_outcome(
+    matched: Option<line::SignatureKey>,
+    deadline: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> (&'static str, bool) {
+    match matched {
+        Some(line::SignatureKey::Current) => ("current", true),
+        Some(line::SignatureKey::Previous) if deadline.is_some_and(|until| now < until) => ("previous", true),
+        Some(line::SignatureKey::Previous) => ("previous_expired", false),
+        None => ("invalid", false),
+    }
+}
+
+/// 驗證簽名並處理 webhook 事件；驗證結果經 `verified` 送回 handler，`received` 為收到請求的時間（追蹤用）
+async fn process_webhook(
+    state: SharedState,
+    signature: String,
+    body: String,
+    received: Instant,
+    verified: oneshot::Sender<Result<(), StatusCode>>,
+) {
+    let state_guard = state.read().await;
+    
+    // 驗證簽名
+    if !check_signature(&state_guard, body.as_bytes(), &signature) {
+        let _ = verified.send(Err(StatusCode::UNAUTHORIZED));
+        return;
+    }
+    
+    // 解析事件
+    let webhook_event = match state_guard.line_client.parse_events(&body) {
+        Ok(event) => event,
+        Err(e) => {
+            error!("Failed to parse webhook event: {}", e);
+            let _ = verified.send(Err(StatusCode::BAD_REQUEST));
+            return;
+        }
+    };
+    let _ = verified.send(Ok(()));
+    let verified_at = Instant::now();
+
+    info!("Received {} events", webhook_event.events.len());
+    
+    // 處理每個事件
+    for event in webhook_event.events {
+        let (kind, user_id) = pipeline::describe(&event);
+        let user = user_id.map(|id| privacy::id(id).into_owned());
+        let context = request_context(&event);
+        state_guard
+            .traces
+            .run(kind, user, received, verified_at, openclaw::with_context(context, handle_new_event(&state_guard, event)))
+            .await;
+    }
+}
+
+/// 略過 LINE 重送的事件（以 `webhookEventId` 判斷），其他事件交給 [`handle_event`]
+async fn handle_new_event(state_guard: &AppState, event: Event) {
+    let started = Instant::now();
+    if let Some(id) = event.webhook_event_id() {
+        if !state_guard.line_recent.first_seen(id) {
+            debug!("Skipping redelivered webhook event {}", id);
+            pipeline::record("dedup", started, "duplicate", None);
+            pipeline::outcome("duplicate");
+            return;
+        }
+    }
+    pipeline::record("dedup", started, "ok", None);
+    handle_event(state_guard, event).await
+}
+
+/// 事件的對話來源，附在這次處理中送往 OpenClaw 的請求
+fn request_context(event: &Event) -> openclaw::RequestContext {
+    let source = event.source();
+    let chat_type = match source {
+        Some(source) if source.group_id.is_some() => "group",
+        Some(source) if source.room_id.is_some() => "room",
+        _ => "user",
+    };
+    openclaw::RequestContext {
+        channel: "line",
+        chat_type,
+        group_id: source.and_then(|source| source.group_id.clone().or_else(|| source.room_id.clone())),
+    }
+}
+
+/// 將非文字訊息（貼圖、位置）轉成的描述交給 OpenClaw，回覆使用者（受頻率限制）
+async fn reply_to_prompt(state_guard: &AppState, msg_event: &MessageEvent, prompt: &str) {
+    delivery::start_loading(state_guard, &msg_event.source).await;
+    let user_id = msg_event.source.user_id.clone().unwrap_or_default();
+    let chat_id = msg_event.source.chat_id().unwrap_or_default();
+    let locale = state_guard.i18n.locale_for(&state_guard.line_client, &user_id).await;
+    let mut messages = match throttled(state_guard, "message", &user_id, locale) {
+        Some(message) => vec![message],
+        None => chat_messages(state_guard, "message", &user_id, locale, prompt, None).await,
+    };
+    mentions::tag_asker(state_guard, &msg_event.source, &mut messages);
+    mentions::quote(state_guard, &msg_event.source, &msg_event.message, &mut messages);
+    delivery::reply(state_guard, "message", &user_id, chat_id, &msg_event.reply_token, messages).await;
+    sessions::ensure_title(state_guard, &user_id).await;
+}
+
+/// 處理單一 webhook 事件
+async fn handle_event(state_guard: &AppState, event: Event) {
+    match event {
+        Event::Message(msg_event) => {
+            let filter_started = Instant::now();
+            if mentions::ignored(state_guard, &msg_event.source, &msg_event.message) {
+                debug!("Ignoring group message without mention");
+                pipeline::record("filter", filter_started, "ignored", Some("mention_only".to_string()));
+                pipeline::outcome("ignored");
+                return;
+            }
+            pipeline::record("filter", filter_started, "ok", None);
+            // 訊息貼圖也帶有 text，須先於文字訊息判斷
+            if msg_event.message.message_type == "sticker" {
+                if !state_guard.config.sticker_replies {
+                    return;
+                }
+                info!(
+                    "Sticker message: {}/{}",
+                    msg_event.message.package_id.as_deref().unwrap_or("-"),
+                    msg_event.message.sticker_id.as_deref().unwrap_or("-")
+                );
+                reply_to_prompt(state_guard, &msg_event, &stickers::describe(&msg_event.message)).await;
+            } else if msg_event.message.message_type == "location" {
+                if !state_guard.config.location_messages {
+                    return;
+                }
+                let Some(prompt) = locations::describe(&msg_event.message) else { return };
+                info!("Location message: {}", privacy::text(msg_event.message.title.as_deref().unwrap_or("-")));
+                reply_to_prompt(state_guard, &msg_event, &prompt).await;
+            } else if let Some(text) = mentions::strip_self(&msg_event.message) {
+                info!("Text message: {}", privacy::text(&text));
+                delivery::start_loading(state_guard, &msg_event.source).await;
+                
+                let user_id = msg_event.source.user_id.clone().unwrap_or_default();
+                let chat_id = msg_event.source.chat_id().unwrap_or_default();
+                if msg_event.source.is_group() && !user_id.is_empty() {
+                    info!("Group message from {} in {}", profiles::describe(state_guard, &user_id).await, privacy::id(chat_id));
+                }
+                let locale = state_guard.i18n.locale_for(&state_guard.line_client, &user_id).await;
+                
+                let mut messages = respond(state_guard, "message", chat_id, &user_id, locale, &text).await;
+                mentions::tag_asker(state_guard, &msg_event.source, &mut messages);
+                mentions::quote(state_guard, &msg_event.source, &msg_event.message, &mut messages);
+                
+                // 回覆 LINE
+                delivery::reply(state_guard, "message", &user_id, chat_id, &msg_event.reply_token, messages).await;
+                // 回覆送出後才為新的對話階段產生標題
+                sessions::ensure_title(state_guard, &user_id).await;
+            } else if let Some(kind) = incoming::Kind::parse(&msg_event.message.message_type) {
+                delivery::start_loading(state_guard, &msg_event.source).await;
+                let user_id = msg_event.source.user_id.clone().unwrap_or_default();
+                let chat_id = msg_event.source.chat_id().unwrap_or_default();
+                let locale = state_guard.i18n.locale_for(&state_guard.line_client, &user_id).await;
+                let mut messages = incoming::handle(state_guard, &user_id, locale, kind, &msg_event.message).await;
+                mentions::tag_asker(state_guard, &msg_event.source, &mut messages);
+                mentions::quote(state_guard, &msg_event.source, &msg_event.message, &mut messages);
+                delivery::reply(state_guard, "message", &user_id, chat_id, &msg_event.reply_token, messages).await;
+            }
+        }
+        Event::Postback(pb_event) => {
+            info!("Postback: {}", privacy::text(&pb_event.postback.data));
+            
+            let user_id = pb_event.source.user_id.clone().unwrap_or_default();
+            let locale = state_guard.i18n.locale_for(&state_guard.line_client, &user_id).await;
+            let chat_id = pb_event.source.chat_id().unwrap_or_default();
+            let data = &pb_event.postback.data;
+            // AI 產生的選單按鈕只交回 OpenClaw，不經過其他 postback 處理
+            let mut messages = if let Some(text) = menus::parse_postback(data) {
+                chat_messages(state_guard, "postback", &user_id, locale, text, None).await
+            } else if let Some(command) = commands::parse_postback(data, &state_guard.i18n) {
+                handle_command(state_guard, chat_id, &user_id, locale, command).await
+            } else if let Some(text) = commands::parse_chat_postback(data) {
+                chat_messages(state_guard, "postback", &user_id, locale, text, None).await
+            } else if let Some(action) = sessions::parse_postback(data) {
+                sessions::handle_postback(state_guard, &user_id, locale, action)
+            } else if let Some(answer) = quiz::parse_postback(data) {
+                quiz::handle_answer(state_guard, chat_id, &user_id, locale, answer).await
+            } else if let Some(decision) = automations::parse_postback(data) {
+                vec![TextMessage::new(automations::handle_decision(state_guard, &user_id, locale, decision)).into()]
+            } else {
+                let (response, footer) = match (polls::parse_postback(data), announcements::parse_postback(data)) {
+                    (Some(vote), _) => (polls::handle_vote(state_guard, &user_id, locale, vote), None),
+                    (None, Some(decision)) => {
+                        (announcements::handle_decision(state_guard, &user_id, locale, decision).await, None)
+                    }
+                    (None, None) => match chat(state_guard, "postback", &user_id, locale, data, None, |data| {
+                        state_guard.i18n.text(locale, "fallback.postback", &[("data", data)])
+                    })
+                    .await
+                    {
+                        Reply::Answer { text, footer } => (text, footer),
+                        // 已被 /stop 取消，不回覆任何訊息
+                        Reply::Cancelled => (String::new(), None),
+                    },
+                };
+                let (response, sticker) = stickers::take(&state_guard.branding, &response);
+                let (response, location) = locations::take(&response);
+                let mut messages = Vec::new();
+                if !response.is_empty() {
+                    messages.push(TextMessage::new(response).into());
+                }
+                if let Some(footer) = footer {
+                    formatting::append_footer(&mut messages, footer);
+                }
+                formatting::append_tagged(&mut messages, location, "location");
+                formatting::append_tagged(&mut messages, sticker, "sticker");
+                emojis::apply(&state_guard.branding, &mut messages);
+                messages
+            };
+            mentions::tag_asker(state_guard, &pb_event.source, &mut messages);
+            
+            delivery::reply(state_guard, "postback", &user_id, chat_id, &pb_event.reply_token, messages).await;
+        }
+        Event::Follow(follow_event) => {
+            let user_id = follow_event.source.user_id.clone().unwrap_or_default();
+            info!("New follower: {}", privacy::id(&user_id));
+            profiles::mark_blocked(state_guard, &user_id, false);
+            let locale = state_guard.i18n.locale_for(&state_guard.line_client, &user_id).await;
+            let chat_id = follow_event.source.chat_id().unwrap_or_default();
+            let greeting = state_guard.branding.greeting(&state_guard.i18n, locale);
+            audit(state_guard, &AuditEvent {
+                user_id: &user_id,
+                event_type: "follow",
+                action: "greeting",
+                status: "ok",
+                ..Default::default()
+            });
+            delivery::reply(state_guard, "follow", &user_id, chat_id, &follow_event.reply_token, vec![TextMessage::new(greeting).into()]).await;
+        }
+        Event::Unfollow(unfollow_event) => {
+            let user_id = unfollow_event.source.user_id.clone().unwrap_or_default();
+            info!("Unfollowed by {}", privacy::id(&user_id));
+            profiles::mark_blocked(state_guard, &user_id, true);
+            audit(state_guard, &AuditEvent {
+                user_id: &user_id,
+                event_type: "unfollow",
+                action: "block",
+                status: "ok",
+                ..Default::default()
+            });
+        }
+        Event::Unknown => {
+            info!("Unknown event type, skipping");
+        }
+    }
+}
+
+/// 與 OpenClaw 對話並排版成 LINE 訊息（含 AI 揭露頁尾）
+async fn chat_messages(
+    state: &AppState,
+    event_type: &str,
+    user_id: &str,
+    locale: Locale,
+    text: &str,
+    chain: Option<&Chain>,
+) -> Vec<OutgoingMessage> {
+    let Reply::Answer { text: answer, footer } = chat(state, event_type, user_id, locale, text, chain, |text| {
+        fallback_response(&state.i18n, locale, &state.branding.name, text)
+    })
+    .await
+    else {
+        return Vec::new();
+    };
+    let started = Instant::now();
+    let (answer, sticker) = stickers::take(&state.branding, &answer);
+    let (answer, location) = locations::take(&answer);
+    let mut messages = if answer.is_empty() {
+        Vec::new()
+    } else {
+        formatting::answer_messages(state, user_id, locale, &answer)
+    };
+    if let Some(footer) = footer {
+        formatting::append_footer(&mut messages, footer);
+    }
+    formatting::append_tagged(&mut messages, location, "location");
+    formatting::append_tagged(&mut messages, sticker, "sticker");
+    emojis::apply(&state.branding, &mut messages);
+    formatting::append_quick_reply(state, locale, &mut messages, text);
+    pipeline::record("post_process", started, "ok", Some(format!("messages={}", messages.len())));
+    messages
+}
+
+/// 一次對話的結果
+enum Reply {
+    /// 回答（OpenClaw 失敗或回答空白時為 `fallback` 產生的回應）與 AI 揭露頁尾（僅 OpenClaw 回答且已啟用時）
+    Answer { text: String, footer: Option<String> },
+    /// 已被 `/stop` 取消，不回覆
+    Cancelled,
+}
+
+/// 與 OpenClaw 對話並保存紀錄，失敗時以 `fallback` 產生回應。
+/// 未指定 `chain` 時，訊息含流程關鍵字則改走該流程
+async fn chat(
+    state: &AppState,
+    event_type: &str,
+    user_id: &str,
+    locale: Locale,
+    text: &str,
+    chain: Option<&Chain>,
+    fallback: impl FnOnce(&str) -> String,
+) -> Reply {
+    // 先取出目前對話階段的先前對話作為脈絡，再保存這次的訊息
+    let mut turn = chat::Turn::begin(&state.store, user_id, state.config.session_gap_secs, state.config.context_history_turns);
+    let reply_language = state.config.reply_language.map(|l| l.resolve(locale));
+    if let Some(language) = reply_language {
+        turn.instruct(language::instruction(language));
+    }
+    // 隱私模式下不把使用者自訂的名稱送往 OpenClaw
+    if state.config.prompt_display_name && !privacy::enabled() && !user_id.is_empty() {
+        let group = openclaw::current_context().is_some_and(|context| context.channel == "line" && context.group_id.is_some());
+        let name = profiles::display_name(state, user_id).await;
+        if let Some(instruction) = name.as_deref().and_then(|name| profiles::instruction(name, group)) {
+            turn.instruct(instruction);
+        }
+    }
+    if state.config.answer_menus {
+        turn.instruct(menus::instruction());
+    }
+    if state.config.location_messages {
+        turn.instruct(locations::instruction());
+    }
+    if let Some(instruction) = emojis::instruction(&state.branding) {
+        turn.instruct(instruction);
+    }
+    if let Some(instruction) = stickers::instruction(&state.branding) {
+        turn.instruct(instruction);
+    }
+    if let Some(instruction) = state.branding.instruction() {
+        turn.instruct(instruction);
+    }
+    turn.record(&state.store, user_id, "user", text, None);
+    let session = turn.session;
+
+    // 嘗試發送給 OpenClaw
+    let started = Instant::now();
+    // 多步驟流程只帶品牌與語言等系統指示；多模型擇優時記錄勝出的模型；
+    // 設定信心門檻時改以結構化輸出取得回答，信心不足則改問澄清問題
+    let mut clarification = None;
+    let mut fanout = None;
+    let mut answered_by = None;
+    let chain = chain.or_else(|| state.chains.matching(text));
+    let fanout_config = state.config.fanout.as_ref().filter(|_| state.flags.is_enabled("fanout", Some(user_id), true));
+    let mut language_retry = None;
+    let mut moderation_retry = None;
+    // /stop 取消時放棄等待：捨棄此 future 即中斷進行中的請求與串流連線
+    let work = async {
+        let mut result = match (chain, fanout_config, state.config.clarify_threshold) {
+            (Some(chain), _, _) => chain
+                .run(&state.openclaw_client, user_id, turn.instructions(), text)
+                .await
+                .map(|answer| (answer, None)),
+            (None, Some(config), _) => fanout::ask(&state.openclaw_client, user_id, turn.history.clone(), text, config)
+                .await
+                .map(|(answer, model, detail)| {
+                    answered_by = Some(model);
+                    fanout = Some(detail);
+                    (answer, None)
+                }),
+            (None, None, Some(threshold)) => confidence::ask(&state.openclaw_client, user_id, turn.history.clone(), text, threshold)
+                .await
+                .map(|(answer, detail)| {
+                    clarification = Some(detail);
+                    (answer, None)
+                }),
+            (None, None, None) => state.openclaw_client.send_message(user_id, turn.history.clone(), text).await,
+        };
+        // 回答語言不符時請 OpenClaw 改寫一次；改寫失敗則沿用原回答
+        if let (Ok((answer, _)), Some(expected)) = (&result, reply_language) {
+            if let Some(detected) = language::detect(answer).filter(|d| *d != expected) {
+                warn!(
+                    "OpenClaw answered user={} in {} instead of {}, asking for a rewrite",
+                    privacy::id(user_id),
+                    detected.tag(),
+                    expected.tag()
+                );
+                let mut retry_history = turn.history.clone();
+                retry_history.push(ChatMessage { role: "user".to_string(), content: text.to_string() });
+                retry_history.push(ChatMessage { role: "assistant".to_string(), content: answer.clone() });
+                let rewritten = state
+                    .openclaw_client
+                    .send_message(user_id, retry_history, &language::retry_prompt(expected))
+                    .await;
+                language_retry = Some(match rewritten {
+                    Ok(rewritten) => {
+                        let still = language::detect(&rewritten.0).filter(|d| *d != expected);
+                        result = Ok(rewritten);
+                        format!("language_retry={}->{}", detected.tag(), still.map_or("ok", |d| d.tag()))
+                    }
+                    Err(e) => {
+                        warn!("Language rewrite request failed, keeping original answer: {}", e);
+                        format!("language_retry={}->failed", detected.tag())
+                    }
+                });
+            }
+        }
+        // 回答含禁用詞時以更嚴格的指示重問一次，仍含禁用詞則改回政策訊息
+        moderation_retry =
+            chat::moderate(&state.openclaw_client, user_id, &mut turn.history, text, &state.config.output_blocklist, &mut result).await;
+        result
+    };
+    let generation = state.generations.start(user_id);
+    let result = tokio::select! {
+        result = work => result,
+        _ = generation.cancelled() => {
+            info!("Generation for user={} cancelled by /stop", privacy::id(user_id));
+            pipeline::record("llm", started, "cancelled", None);
+            pipeline::outcome("cancelled");
+            audit(state, &AuditEvent {
+                user_id,
+                event_type,
+                action: "chat",
+                status: "cancelled",
+                model: Some(&state.openclaw_client.model()),
+                latency_ms: Some(started.elapsed().as_millis() as i64),
+                detail: None,
+            });
+            return Reply::Cancelled;
+        }
+    };
+    drop(generation);
+    let chain = chain.map(|chain| format!("chain={} steps={}", chain.name, chain.steps.len()));
+    let latency_ms = started.elapsed().as_millis() as i64;
+
+    let active_model = state.openclaw_client.model();
+    let model = answered_by.as_deref().unwrap_or(&active_model);
+    let mut repeated = None;
+    let (response, status, detail) = match result {
+        _ if moderation_retry == Some("blocked") => {
+            reporting::openclaw_success();
+            warn!("OpenClaw answer for user={} blocked by moderation", privacy::id(user_id));
+            (state.i18n.text(locale, "answer.blocked", &[]), "blocked", Some("moderation_retry=blocked".to_string()))
+        }
+        Ok((resp, _)) if resp.trim().is_empty() => {
+            warn!("OpenClaw returned an empty answer for user={}", privacy::id(user_id));
+            (fallback(text), "fallback", Some("empty_answer".to_string()))
+        }
+        Ok((resp, timings)) => {
+            reporting::openclaw_success();
+            // 串流回應另記錄首 token 時間與生成速度
+            let timings = timings.map(|t| {
+                state.metrics.observe_stream(model, &t);
+                format!("ttft_ms={} tokens={} tokens_per_sec={:.1}", t.first_token_ms, t.tokens, t.tokens_per_sec())
+            });
+            let moderation_retry = moderation_retry.map(|outcome| format!("moderation_retry={}", outcome));
+            // 與上一則回答幾乎相同時改以簡短提示回覆；對話紀錄仍保存原回答，之後的重複也能偵測
+            repeated = repeated_answer(state, event_type, session, &resp);
+            let detail = [timings, chain, fanout, clarification, language_retry, moderation_retry]
+                .into_iter()
+                .flatten()
+                .chain(repeated.as_ref().map(|(score, _)| format!("repeated={:.2}", score)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            (resp, "ok", Some(detail).filter(|d| !d.is_empty()))
+        }
+        Err(e) => {
+            warn!("OpenClaw error: {}", e);
+            reporting::openclaw_failure(&reporting::Context {
+                event_type: Some(event_type),
+                user_id: Some(user_id),
+                model: Some(model),
+                detail: Some(&e),
+            });
+            (fallback(text), "fallback", Some(e))
+        }
+    };
+    audit(state, &AuditEvent {
+        user_id,
+        event_type,
+        action: "chat",
+        status,
+        model: Some(model),
+        latency_ms: Some(latency_ms),
+        detail: detail.as_deref(),
+    });
+    pipeline::record("llm", started, status, Some(format!("model={}", model)));
+
+    let stored_model = if status == "ok" { model } else { "fallback" };
+    turn.record(&state.store, user_id, "assistant", &response, Some(stored_model));
+    let footer = if status == "ok" { formatting::ai_footer(state, locale, model) } else { None };
+    let text = match repeated {
+        Some((_, previous)) => {
+            info!("OpenClaw repeated its previous answer to user={}, replying with a short notice", privacy::id(user_id));
+            let excerpt = repetition::excerpt(&previous);
+            state.i18n.text(locale, "answer.repeated", &[("excerpt", &excerpt)])
+        }
+        None => response,
+    };
+    Reply::Answer { text, footer }
+}
+
+/// 回答與同一對話階段的上一則回答幾乎相同時，回傳相似度與上一則回答。
+/// 只檢查一般訊息：按下「重試這則問題」等 postback 是使用者主動要求重新回答，相似的回答照常送出
+fn repeated_answer(state: &AppState, event_type: &str, session: Option<i64>, answer: &str) -> Option<(f64, String)> {
+    let threshold = state.config.repeat_answer_similarity.filter(|_| event_type == "message")?;
+    // 最近兩則為上一則回答與這次的提問
+    let messages = state
+        .store
+        .session_messages(session?, 2)
+        .map_err(|e| warn!("Failed to load previous answer: {}", e))
+        .ok()?;
+    let (_, previous) = messages.into_iter().rev().find(|(role, _)| role == "assistant")?;
+    repetition::repeated(&previous, answer, threshold).map(|score| (score, previous))
+}
+
+/// 寫入稽核紀錄（失敗僅記錄警告）；狀態為 error 的事件同時回報
+fn audit(state: &AppState, event: &AuditEvent) {
+    if event.status == "error" {
+        reporting::capture(reporting::Level::Error, &format!("{} failed", event.action), &reporting::Context {
+            event_type: Some(event.event_type),
+            user_id: Some(event.user_id),
+            model: event.model,
+            detail: event.detail,
+        });
+    }
+    if let Err(e) = state.store.audit(event) {
+        warn!("Failed to write audit log: {}", e);
+    }
+}
+
+/// 處理一則文字訊息：斜線指令、拼錯指令的確認或交給 AI 回答；Webhook 與 `POST /admin/test-message` 共用
+async fn respond(state: &AppState, event_type: &str, chat_id: &str, user_id: &str, locale: Locale, text: &str) -> Vec<OutgoingMessage> {
+    if let Some(messages) = command_response(state, event_type, chat_id, user_id, locale, text).await {
+        return messages;
+    }
+    match throttled(state, event_type, user_id, locale) {
+        Some(message) => vec![message],
+        None => chat_messages(state, event_type, user_id, locale, text, None).await,
+    }
+}
+
+/// 斜線指令的回覆，或拼錯指令時的確認；一般訊息回傳 None。各通道共用
+async fn command_response(
+    state: &AppState,
+    event_type: &str,
+    chat_id: &str,
+    user_id: &str,
+    locale: Locale,
+    text: &str,
+) -> Option<Vec<OutgoingMessage>> {
+    match commands::parse(text, &state.i18n) {
+        Some(command) => {
+            let messages = handle_command(state, chat_id, user_id, locale, command).await;
+            audit(state, &AuditEvent {
+                user_id,
+                event_type,
+                action: "command",
+                status: "ok",
+                ..Default::default()
+            });
+            Some(messages)
+        }
+        // 拼錯的指令先確認，不直接送給 AI
+        None => {
+            let suggestion = commands::suggest(text, &state.i18n)?;
+            audit(state, &AuditEvent {
+                user_id,
+                event_type,
+                action: "command_suggestion",
+                status: "ok",
+                detail: Some(suggestion.command),
+                ..Default::default()
+            });
+            Some(vec![commands::suggestion_message(&state.i18n, locale, text, &suggestion)])
+        }
+    }
+}
+
+/// 使用者超過頻率限制時回傳告知重置時間的訊息（管理 API 的測試訊息不受限制）
+fn throttled(state: &AppState, event_type: &str, user_id: &str, locale: Locale) -> Option<OutgoingMessage> {
+    let limiter = state.rate_limiter.as_ref().filter(|_| event_type == "message")?;
+    let started = Instant::now();
+    let decision = limiter.check(user_id);
+    pipeline::record("rate_limit", started, if decision.allowed { "ok" } else { "limited" }, None);
+    if decision.allowed {
+        debug!("{} has {} AI messages left, window resets in {}s", privacy::id(user_id), decision.remaining, decision.retry_after_secs());
+        return None;
+    }
+    let seconds = decision.retry_after_secs().to_string();
+    info!("Rate limited {} for {}s", privacy::id(user_id), seconds);
+    audit(state, &AuditEvent {
+        user_id,
+        event_type,
+        action: "rate_limited",
+        status: "rejected",
+        detail: Some(&format!("retry_after={}s", seconds)),
+        ..Default::default()
+    });
+    Some(TextMessage::new(state.i18n.text(locale, "rate_limit.exceeded", &[("seconds", &seconds)])).into())
+}
+
+/// 處理斜線指令
+async fn handle_command(state: &AppState, chat_id: &str, user_id: &str, locale: Locale, command: Command) -> Vec<OutgoingMessage> {
+    let response = match command {
+        Command::Poll(args) => return polls::handle_command(state, chat_id, user_id, locale, args),
+        Command::Quiz(args) => return quiz::handle_command(state, chat_id, user_id, locale, args).await,
+        Command::Help => state.i18n.text(locale, "command.help", &[]),
+        Command::Briefing(args) => briefing::handle_command(state, user_id, locale, args).await,
+        Command::Search(keywords) => return search_command(state, user_id, locale, keywords),
+        Command::Link(args) => linking::handle_command(state, user_id, locale, args),
+        Command::Status(args) => status::handle_command(state, chat_id, user_id, locale, args).await,
+        Command::Stop => generations::handle_command(state, user_id, locale),
+        Command::History => return sessions::handle_command(state, user_id, locale).await,
+        Command::Mention(args) => mentions::handle_command(state, chat_id, user_id, locale, args),
+        Command::Model => models::handle_command(state, user_id, locale),
+        Command::Chain { name, input } => return chains::handle_command(state, user_id, locale, name, input).await,
+        Command::Automation(args) => return automations::handle_command(state, chat_id, user_id, locale, args).await,
+    };
+    vec![TextMessage::new(response).into()]
+}
+
+/// 搜尋結果延伸提問時引用的對話長度
+const SEARCH_PROMPT_EXCERPT_CHARS: usize = 100;
+
+/// `/search`：搜尋自己的過往對話；結果以 carousel 呈現，每張卡片可就該段對話延伸提問
+fn search_command(state: &AppState, user_id: &str, locale: Locale, keywords: Vec<String>) -> Vec<OutgoingMessage> {
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    let text = |text: String| vec![TextMessage::new(text).into()];
+    if !state.flags.is_enabled("search_command", Some(user_id), true) {
+        return text(t("command.unavailable"));
+    }
+    if keywords.is_empty() {
+        return text(t("search.usage"));
+    }
+    let query = SearchQuery {
+        keywords,
+        user_id: Some(user_id.to_string()),
+        include_linked: true,
+        limit: 5,
+        ..Default::default()
+    };
+    match state.store.search(&query) {
+        Ok(hits) if hits.is_empty() => text(t("search.empty")),
+        Ok(hits) => {
+            let columns: Vec<line::CarouselColumn> = hits
+                .iter()
+                .filter_map(|hit| {
+                    let time = chrono::DateTime::from_timestamp(hit.created_at, 0)
+                        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_default();
+                    let who = if hit.role == "user" { t("search.you") } else { t("search.ai") };
+                    let excerpt: String = hit.excerpt.chars().take(SEARCH_PROMPT_EXCERPT_CHARS).collect();
+                    let prompt = state.i18n.text(locale, "search.follow_up_prompt", &[("excerpt", &excerpt)]);
+                    // 每欄都需有追問按鈕（LINE 要求各欄按鈕數相同），放不進 postback 的結果略過
+                    let action = commands::chat_action(t("search.follow_up"), &prompt)?;
+                    Some(line::CarouselColumn::new(hit.excerpt.as_str()).title(format!("{}・{}", time, who)).action(action))
+                })
+                .collect();
+            if columns.is_empty() {
+                return text(t("search.empty"));
+            }
+            let alt = state.i18n.text(locale, "search.alt", &[("count", &columns.len().to_string())]);
+            match Template::carousel(columns) {
+                Ok(template) => vec![TemplateMessage::new(alt, template).into()],
+                Err(e) => {
+                    error!("Failed to build search results: {}", e);
+                    text(t("search.error"))
+                }
+            }
+        }
+        Err(e) => {
+            error!("Search failed: {}", e);
+            reporting::capture(reporting::Level::Error, "search failed", &reporting::Context {
+                user_id: Some(user_id),
+                detail: Some(&e.to_string()),
+                ..Default::default()
+            });
+            text(t("search.error"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn previous_secret_is_accepted_before_deadline() {
+        let outcome = signature_outcome(Some(line::SignatureKey::Previous), Some(at(12)), at(11));
+        assert_eq!(outcome, ("previous", true));
+    }
+
+    #[test]
+    fn previous_secret_is_rejected_after_deadline() {
+        assert_eq!(signature_outcome(Some(line::SignatureKey::Previous), Some(at(12)), at(12)), ("previous_expired", false));
+        assert_eq!(signature_outcome(Some(line::SignatureKey::Previous), None, at(11)), ("previous_expired", false));
+    }
+
+    #[test]
+    fn current_secret_ignores_deadline() {
+        assert_eq!(signature_outcome(Some(line::SignatureKey::Current), Some(at(12)), at(13)), ("current", true));
+        assert_eq!(signature_outcome(None, Some(at(12)), at(11)), ("invalid", false));
+    }
+}
@@ -0,0 +1,156 @@
+//! LIFF 頁面模組
+//! 提供在 LINE 內開啟的網頁（程式碼複製頁、完整回答頁），頁面 ID 為不可猜測的隨機字串
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Html,
+    routing::get,
+    Router,
+};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::Deserialize;
+use tracing::error;
+
+use crate::config::Config;
+use crate::i18n::Locale;
+use crate::markdown::{self, escape};
+use crate::tokens::{Audience, TokenError, TokenSigner};
+use crate::{AppState, SharedState};
+
+/// 簽章網址的查詢參數
+#[derive(Debug, Deserialize)]
+struct Signature {
+    token: String,
+}
+
+/// 建立 LIFF 路由（掛載於 `/liff`，即 LIFF app 的 Endpoint URL）
+pub fn router() -> Router<SharedState> {
+    Router::new()
+        .route("/code/:id", get(code_page))
+        .route("/answer/:id", get(answer_page))
+}
+
+/// 是否能產生頁面網址
+pub fn enabled(config: &Config) -> bool {
+    config.liff_id.is_some() || config.public_base_url.is_some()
+}
+
+/// 頁面的開啟網址；設定 LIFF_ID 時經由 LINE 開啟，否則直接使用 PUBLIC_BASE_URL
+pub fn url(config: &Config, path: &str) -> Option<String> {
+    match (&config.liff_id, &config.public_base_url) {
+        (Some(liff_id), _) => Some(format!("https://liff.line.me/{}/{}", liff_id, path)),
+        (None, Some(base)) => Some(format!("{}/liff/{}", base, path)),
+        (None, None) => None,
+    }
+}
+
+/// 附上限時權杖的頁面網址（以 `TOKEN_SIGNING_KEY` 簽章）
+pub fn signed_url(state: &AppState, path: &str, expires: i64) -> Option<String> {
+    let token = signer(state).issue(Audience::Liff, path, expires);
+    url(&state.config, path).map(|url| format!("{}?token={}", url, token))
+}
+
+fn signer(state: &AppState) -> TokenSigner {
+    TokenSigner::new(&state.config.token_signing_key, state.clock.clone())
+}
+
+/// 產生頁面 ID（32 字元十六進位）；一律取自作業系統亂數，不受 `RANDOM_SEED` 影響，網址無法被預測
+pub fn new_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// 程式碼複製頁
+async fn code_page(State(state): State<SharedState>, Path(id): Path<String>) -> Result<Html<String>, StatusCode> {
+    let state = state.read().await;
+    let snippet = state
+        .store
+        .get_snippet(&id)
+        .map_err(|e| {
+            error!("Failed to load snippet {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let locale = state.i18n.default_locale();
+    let title = snippet.language.as_deref().unwrap_or("code");
+    let body = format!(
+        r#"<pre id="code">{code}</pre>
+<button id="copy" onclick="navigator.clipboard.writeText(document.getElementById('code').innerText).then(() => {{ this.textContent = '{copied}'; }})">{copy}</button>"#,
+        code = escape(&snippet.code),
+        copy = escape(&state.i18n.text(locale, "code.copy", &[])),
+        copied = escape(&state.i18n.text(locale, "code.copied", &[])),
+    );
+    Ok(Html(page(locale, title, &body)))
+}
+
+/// 完整回答頁（需有效的簽章網址）
+async fn answer_page(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    Query(query): Query<Signature>,
+) -> Result<Html<String>, StatusCode> {
+    let state = state.read().await;
+    let verified = signer(&state).verify(Audience::Liff, &format!("answer/{}", id), &query.token);
+    if verified == Err(TokenError::Invalid) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let answer = state
+        .store
+        .get_answer_page(&id)
+        .map_err(|e| {
+            error!("Failed to load answer page {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let locale = state.i18n.default_locale();
+    let now = state.clock.timestamp();
+    if verified == Err(TokenError::Expired) || answer.expires_at < now {
+        let message = state.i18n.text(locale, "answer.expired", &[]);
+        return Ok(Html(page(locale, &message, &format!("<p>{}</p>", escape(&message)))));
+    }
+
+    let created_at = chrono::DateTime::from_timestamp(answer.created_at, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default();
+    let body = format!(
+        r#"<article>{content}</article>
+<footer>{created_at}</footer>"#,
+        content = markdown::to_html(&answer.markdown),
+        created_at = escape(&created_at),
+    );
+    Ok(Html(page(locale, &state.i18n.text(locale, "answer.title", &[]), &body)))
+}
+
+/// 共用的頁面外框
+pub fn page(locale: Locale, title: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ margin: 0; padding: 16px; font-family: -apple-system, "Noto Sans TC", sans-serif; background: #f5f5f5; }}
+pre {{ background: #1e1e1e; color: #d4d4d4; padding: 12px; border-radius: 8px; overflow-x: auto; font: 13px/1.5 ui-monospace, Menlo, Consolas, monospace; }}
+button {{ width: 100%; padding: 12px; border: 0; border-radius: 8px; background: #06c755; color: #fff; font-size: 16px; }}
+article {{ background: #fff; padding: 16px; border-radius: 8px; line-height: 1.7; word-wrap: break-word; }}
+article :not(pre) > code {{ background: #eee; padding: 1px 4px; border-radius: 4px; font-size: 90%; }}
+blockquote {{ margin: 0; padding-left: 12px; border-left: 4px solid #ddd; color: #666; }}
+footer {{ margin-top: 8px; color: #999; font-size: 12px; text-align: right; }}
+</style>
+</head>
+<body>
+{body}
+</body>
+</html>"#,
+        lang = locale.tag(),
+        title = escape(title),
+        body = body,
+    )
+}
@@ -0,0 +1,121 @@
+//! 帳號連結模組
+//! `/link` 產生一次性連結碼，在另一個平台（或另一個 LINE 帳號）輸入 `/link <連結碼>` 即可連結身分，
+//! 連結後的對話脈絡與 `/search` 會涵蓋所有連結的身分
+
+use rand::rngs::OsRng;
+use rand::Rng;
+use tracing::{error, info, warn};
+
+use crate::i18n::Locale;
+use crate::privacy;
+use crate::store::LinkRedemption;
+use crate::AppState;
+
+/// 連結碼有效時間
+const CODE_TTL_SECS: i64 = 600;
+/// 連結碼長度
+const CODE_LENGTH: usize = 10;
+/// 連結碼使用的字元（去除容易混淆的 0/O、1/I/L），31 個字元，10 字元約 2^49 種組合
+const CODE_ALPHABET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+/// 時間窗內允許輸入錯誤的次數，超過即暫停受理
+const MAX_FAILURES: u32 = 5;
+/// 輸入錯誤次數的計算時間窗
+const FAILURE_WINDOW_SECS: i64 = 3600;
+
+/// 處理 `/link`、`/link <連結碼>`、`/link remove`
+pub fn handle_command(state: &AppState, user_id: &str, locale: Locale, args: Vec<String>) -> String {
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    if !state.flags.is_enabled("link_command", Some(user_id), true) {
+        return t("command.unavailable");
+    }
+    match args.first().map(String::as_str) {
+        None => create_code(state, user_id, locale),
+        Some(arg) if arg.eq_ignore_ascii_case("remove") => match state.store.unlink_identity(user_id) {
+            Ok(true) => {
+                info!("Identity {} unlinked", privacy::id(user_id));
+                t("link.removed")
+            }
+            Ok(false) => t("link.none"),
+            Err(e) => {
+                error!("Failed to unlink identity: {}", e);
+                t("link.error")
+            }
+        },
+        Some(code) if is_code(code) => {
+            let code = code.to_ascii_uppercase();
+            match state.store.redeem_link_code(&code, user_id, MAX_FAILURES, FAILURE_WINDOW_SECS) {
+                Ok(LinkRedemption::Linked(owner)) => {
+                    info!("Identity {} linked with {}", privacy::id(user_id), privacy::id(&owner));
+                    t("link.linked")
+                }
+                Ok(LinkRedemption::Invalid) => t("link.invalid"),
+                Ok(LinkRedemption::Locked) => {
+                    warn!("Link code attempts locked for {}", privacy::id(user_id));
+                    t("link.locked")
+                }
+                Err(e) => {
+                    error!("Failed to redeem link code: {}", e);
+                    t("link.error")
+                }
+            }
+        }
+        Some(_) => t("link.usage"),
+    }
+}
+
+/// 是否為連結碼格式（不分大小寫）
+fn is_code(code: &str) -> bool {
+    code.len() == CODE_LENGTH && code.bytes().all(|b| CODE_ALPHABET.contains(&b.to_ascii_uppercase()))
+}
+
+/// 一律取自作業系統亂數，無法預測
+fn new_code() -> String {
+    (0..CODE_LENGTH).map(|_| CODE_ALPHABET[OsRng.gen_range(0..CODE_ALPHABET.len())] as char).collect()
+}
+
+/// 產生連結碼，並列出目前已連結的身分
+fn create_code(state: &AppState, user_id: &str, locale: Locale) -> String {
+    let code = new_code();
+    if let Err(e) = state.store.create_link_code(user_id, &code, CODE_TTL_SECS) {
+        error!("Failed to create link code: {}", e);
+        return state.i18n.text(locale, "link.error", &[]);
+    }
+
+    let minutes = (CODE_TTL_SECS / 60).to_string();
+    let mut text = state.i18n.text(locale, "link.code", &[("code", &code), ("minutes", &minutes)]);
+    match state.store.linked_identities(user_id) {
+        Ok(linked) if !linked.is_empty() => {
+            text.push_str("\n\n");
+            text.push_str(&state.i18n.text(locale, "link.current", &[("count", &linked.len().to_string())]));
+        }
+        Ok(_) => {}
+        Err(e) => error!("Failed to load linked identities: {}", e),
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_codes_are_valid_and_distinct() {
+        let first = new_code();
+        assert!(is_code(&first), "{}", first);
+        assert_ne!(first, new_code());
+    }
+
+    #[test]
+    fn codes_are_case_insensitive() {
+        assert!(is_code("abcdefgh23"));
+        assert!(is_code("ABCDEFGH23"));
+    }
+
+    #[test]
+    fn rejects_wrong_length_and_ambiguous_characters() {
+        assert!(!is_code("12345678"));
+        assert!(!is_code("ABCDEFGH2"));
+        assert!(!is_code("ABCDEFGH20"));
+        assert!(!is_code("ABCDEFGHI2"));
+    }
+}
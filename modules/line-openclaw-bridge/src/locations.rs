@@ -0,0 +1,70 @@
+//! 位置訊息模組
+//! 使用者分享的位置（名稱、地址與座標）轉成給 OpenClaw 的描述；AI 回答「X 在哪裡」時可附上
+//! `[location:名稱|地址|緯度,經度]` 標籤，由此轉成地圖圖釘接在回答之後
+
+use tracing::warn;
+
+use crate::formatting;
+use crate::line::{LocationMessage, Message, OutgoingMessage};
+
+/// AI 回答中的位置標籤前綴
+const TAG_PREFIX: &str = "[location:";
+/// LINE 位置訊息名稱與地址的長度上限
+const FIELD_LIMIT: usize = 100;
+
+/// 將位置訊息轉為給 OpenClaw 的文字；缺少座標時回傳 None
+pub fn describe(message: &Message) -> Option<String> {
+    let (latitude, longitude) = (message.latitude?, message.longitude?);
+    let title = message.title.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    let address = message.address.as_deref().map(str::trim).filter(|a| !a.is_empty());
+    let place = match (title, address) {
+        (Some(title), Some(address)) => format!(" \"{}\" ({})", title, address),
+        (Some(place), None) | (None, Some(place)) => format!(" \"{}\"", place),
+        (None, None) => String::new(),
+    };
+    Some(format!(
+        "[location] The user shared a location{} at latitude {:.6}, longitude {:.6}. \
+         Use it as context for nearby places, directions or local information.",
+        place, latitude, longitude
+    ))
+}
+
+/// 附在對話前的系統指示：如何以標籤附上地圖圖釘
+pub fn instruction() -> String {
+    format!(
+        "When the user asks where a specific place is and you know its coordinates, you may end your answer with one tag \
+         {}<name>|<address>|<latitude>,<longitude>] to send a map pin. Only use coordinates you are confident about \
+         and never explain the tag.",
+        TAG_PREFIX
+    )
+}
+
+/// 移除回答中的位置標籤，回傳其餘文字與第一個有效標籤對應的位置訊息
+pub fn take(answer: &str) -> (String, Option<OutgoingMessage>) {
+    let (text, tag) = formatting::take_tag(answer, TAG_PREFIX);
+    let Some(tag) = tag else { return (text, None) };
+    let location = parse(&tag);
+    if location.is_none() {
+        warn!("Ignoring invalid location tag: {}", tag);
+    }
+    (text, location.map(Into::into))
+}
+
+/// 解析 `名稱|地址|緯度,經度`；座標超出範圍時回傳 None
+fn parse(tag: &str) -> Option<LocationMessage> {
+    let mut parts = tag.splitn(3, '|').map(str::trim);
+    let (title, address, coordinates) = (parts.next()?, parts.next()?, parts.next()?);
+    let (latitude, longitude) = coordinates.split_once(',')?;
+    let latitude: f64 = latitude.trim().parse().ok().filter(|v: &f64| (-90.0..=90.0).contains(v))?;
+    let longitude: f64 = longitude.trim().parse().ok().filter(|v: &f64| (-180.0..=180.0).contains(v))?;
+    if title.is_empty() {
+        return None;
+    }
+    // LINE 要求地址不得為空，沒有地址時以名稱代替
+    let address = if address.is_empty() { title } else { address };
+    Some(LocationMessage::new(truncate(title), truncate(address), latitude, longitude))
+}
+
+fn truncate(value: &str) -> String {
+    value.chars().take(FIELD_LIMIT).collect()
+}
@@ -0,0 +1,121 @@
+//! 日誌檔模組
+//! 設定 LOG_DIR 時另將日誌寫入每日輪替的檔案，輪替後的檔案以 gzip 壓縮並依保留天數刪除，適合沒有日誌收集器的 systemd 部署
+
+use chrono::{NaiveDate, Utc};
+use flate2::{write::GzEncoder, Compression};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+/// 壓縮與清理的檢查間隔
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 日誌檔設定
+#[derive(Debug, Clone)]
+pub struct FileLogConfig {
+    pub dir: PathBuf,
+    /// 檔名前綴；檔名為 `<前綴>.<YYYY-MM-DD>.log`（UTC 日期）
+    pub prefix: String,
+    /// 保留天數，0 代表不刪除
+    pub retention_days: u32,
+    /// 是否以 gzip 壓縮輪替後的檔案
+    pub compress: bool,
+}
+
+impl FileLogConfig {
+    /// 從環境變數讀取；未設定 LOG_DIR 時回傳 None
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(dir) = std::env::var("LOG_DIR").ok().filter(|v| !v.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let prefix = std::env::var("LOG_FILE_PREFIX").unwrap_or_else(|_| "bridge".to_string());
+        if prefix.is_empty() || prefix.contains(['/', '\\']) {
+            return Err(format!("LOG_FILE_PREFIX 格式錯誤：{}", prefix));
+        }
+        let retention_days = match std::env::var("LOG_RETENTION_DAYS") {
+            Ok(value) => value.trim().parse().map_err(|_| format!("LOG_RETENTION_DAYS 必須是非負整數，目前為 {}", value))?,
+            Err(_) => 14,
+        };
+        let compress = match std::env::var("LOG_COMPRESS").as_deref().map(str::trim) {
+            Err(_) | Ok("true") | Ok("1") => true,
+            Ok("false") | Ok("0") => false,
+            Ok(value) => return Err(format!("LOG_COMPRESS 必須是 true 或 false，目前為 {}", value)),
+        };
+        Ok(Some(Self {
+            dir: PathBuf::from(dir),
+            prefix,
+            retention_days,
+            compress,
+        }))
+    }
+
+    /// 解析檔名中的日期與是否已壓縮；不屬於本服務的檔案回傳 None
+    fn parse_file_name(&self, name: &str) -> Option<(NaiveDate, bool)> {
+        let rest = name.strip_prefix(&self.prefix)?.strip_prefix('.')?;
+        let (date, compressed) = match rest.strip_suffix(".log.gz") {
+            Some(date) => (date, true),
+            None => (rest.strip_suffix(".log")?, false),
+        };
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").ok().map(|date| (date, compressed))
+    }
+}
+
+/// 建立每日輪替的非阻塞 writer；回傳的 guard 須保留到程式結束，才會寫出緩衝中的日誌
+pub fn file_writer(config: &FileLogConfig) -> Result<(NonBlocking, WorkerGuard), String> {
+    std::fs::create_dir_all(&config.dir).map_err(|e| format!("無法建立 {}: {}", config.dir.display(), e))?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(&config.prefix)
+        .filename_suffix("log")
+        .build(&config.dir)
+        .map_err(|e| format!("無法開啟日誌檔: {}", e))?;
+    Ok(tracing_appender::non_blocking(appender))
+}
+
+/// 定期壓縮輪替後的檔案並刪除過期檔案
+pub fn spawn_maintenance(config: FileLogConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let config = config.clone();
+            match tokio::task::spawn_blocking(move || maintain(&config)).await {
+                Ok(Err(e)) => warn!("Log file maintenance failed: {}", e),
+                Err(e) => warn!("Log file maintenance task failed: {}", e),
+                Ok(Ok(())) => {}
+            }
+        }
+    });
+}
+
+fn maintain(config: &FileLogConfig) -> std::io::Result<()> {
+    // tracing-appender 以 UTC 日期輪替，今天的檔案仍在寫入
+    let today = Utc::now().date_naive();
+    let cutoff = (config.retention_days > 0).then(|| today - chrono::Duration::days(config.retention_days as i64));
+    for entry in std::fs::read_dir(&config.dir)? {
+        let path = entry?.path();
+        let Some((date, compressed)) = path.file_name().and_then(|n| n.to_str()).and_then(|n| config.parse_file_name(n)) else {
+            continue;
+        };
+        if cutoff.is_some_and(|cutoff| date < cutoff) {
+            std::fs::remove_file(&path)?;
+            info!("Removed expired log file {}", path.display());
+        } else if config.compress && !compressed && date < today {
+            gzip(&path)?;
+            info!("Compressed rotated log file {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// 壓縮為 `<檔名>.gz` 後刪除原檔
+fn gzip(path: &Path) -> std::io::Result<()> {
+    let target = path.with_extension("log.gz");
+    let mut encoder = GzEncoder::new(File::create(&target)?, Compression::default());
+    std::io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    std::fs::remove_file(path)
+}
@@ -1,183 +1,136 @@
 //! LINE-OpenClaw Bridge
 //! 連接 LINE Bot 和本地 OpenClaw AI 助理的 Rust 服務
 
-mod line;
-mod openclaw;
-
-use axum::{
-    extract::State,
-    http::{HeaderMap, StatusCode},
-    routing::{get, post},
-    Router,
-    Json,
-};
-use serde_json::json;
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{info, error, warn};
-
-use crate::line::{LineClient, Event};
-use crate::openclaw::{OpenClawClient, fallback_response};
-
-/// 應用程式狀態
-struct AppState {
-    line_client: LineClient,
-    openclaw_client: OpenClawClient,
-}
+use bridge_core::privacy;
+use clap::Parser;
+use line_openclaw_bridge::cli::{self, Cli, CliCommand, DatasetCommand};
+use line_openclaw_bridge::logging::{self, FileLogConfig};
+use line_openclaw_bridge::repl;
+use line_openclaw_bridge::Config;
+use std::net::SocketAddr;
+use tracing::{error, info};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() {
     // 初始化環境變數
     dotenvy::dotenv().ok();
+    let cli = Cli::parse();
     
-    // 初始化日誌
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("line_openclaw_bridge=debug".parse().unwrap())
-        )
-        .init();
-
-    // 讀取設定
-    let channel_access_token = std::env::var("LINE_CHANNEL_ACCESS_TOKEN")
-        .expect("LINE_CHANNEL_ACCESS_TOKEN 環境變數未設定");
-    let channel_secret = std::env::var("LINE_CHANNEL_SECRET")
-        .expect("LINE_CHANNEL_SECRET 環境變數未設定");
-    let openclaw_base_url = std::env::var("OPENCLAW_BASE_URL")
-        .unwrap_or_else(|_| "http://127.0.0.1:18789".to_string());
-    let openclaw_gateway_token = std::env::var("OPENCLAW_GATEWAY_TOKEN").ok();
-    
-    let host = std::env::var("SERVER_HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port = std::env::var("SERVER_PORT").unwrap_or_else(|_| "3000".to_string());
-    
-    // 建立客戶端
-    let line_client = LineClient::new(channel_access_token, channel_secret);
-    let openclaw_client = OpenClawClient::new(openclaw_base_url.clone(), openclaw_gateway_token);
-    
-    let state = Arc::new(RwLock::new(AppState {
-        line_client,
-        openclaw_client,
-    }));
-
-    // 建立路由
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/health", get(health_check))
-        .route("/callback", post(webhook_callback))
-        .with_state(state);
-
-    // 啟動伺服器
-    let addr = format!("{}:{}", host, port);
-    info!("🚀 LINE-OpenClaw Bridge 啟動中...");
-    info!("📍 監聽地址: http://{}", addr);
-    info!("📌 Webhook URL: http://your-domain:{}/callback", port);
-    info!("🔗 OpenClaw: {}", openclaw_base_url);
-    info!("\n💡 提示：使用 ngrok 建立公開 URL：ngrok http {}", port);
-    
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
-}
-
-/// 根路徑
-async fn root() -> &'static str {
-    "LINE-OpenClaw Bridge Service v0.1.0"
-}
-
-/// 健康檢查端點
-async fn health_check(
-    State(state): State<Arc<RwLock<AppState>>>,
-) -> Json<serde_json::Value> {
-    let state = state.read().await;
-    let openclaw_status = match state.openclaw_client.health_check().await {
-        Ok(true) => "online",
-        Ok(false) => "offline",
-        Err(_) => "unreachable",
+    // 初始化日誌（維運子指令寫到 stderr，避免混入匯出內容）
+    let filter = || {
+        tracing_subscriber::EnvFilter::from_default_env()
+            .add_directive("line_openclaw_bridge=debug".parse().unwrap())
+            .add_directive("bridge_core=debug".parse().unwrap())
+            .add_directive("line_adapter=debug".parse().unwrap())
+            .add_directive("matrix_adapter=debug".parse().unwrap())
     };
-    
-    Json(json!({
-        "status": "ok",
-        "service": "line-openclaw-bridge",
-        "openclaw": openclaw_status
-    }))
-}
-
-/// LINE Webhook 回調端點
-async fn webhook_callback(
-    State(state): State<Arc<RwLock<AppState>>>,
-    headers: HeaderMap,
-    body: String,
-) -> Result<&'static str, StatusCode> {
-    // 取得簽名
-    let signature = headers
-        .get("x-line-signature")
-        .and_then(|v| v.to_str().ok())
-        .ok_or_else(|| {
-            warn!("Missing X-Line-Signature header");
-            StatusCode::BAD_REQUEST
-        })?;
-
-    let state_guard = state.read().await;
-    
-    // 驗證簽名
-    if !state_guard.line_client.verify_signature(body.as_bytes(), signature) {
-        error!("Invalid signature");
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-    
-    // 解析事件
-    let webhook_event = state_guard.line_client.parse_events(&body)
-        .map_err(|e| {
-            error!("Failed to parse webhook event: {}", e);
-            StatusCode::BAD_REQUEST
-        })?;
-
-    info!("Received {} events", webhook_event.events.len());
-    
-    // 處理每個事件
-    for event in webhook_event.events {
-        match event {
-            Event::Message(msg_event) => {
-                if let Some(text) = &msg_event.message.text {
-                    info!("Text message: {}", text);
-                    
-                    let user_id = msg_event.source.user_id.clone().unwrap_or_default();
-                    
-                    // 嘗試發送給 OpenClaw
-                    let response = match state_guard.openclaw_client.send_message(&user_id, text).await {
-                        Ok(resp) => resp,
-                        Err(e) => {
-                            warn!("OpenClaw error: {}", e);
-                            fallback_response(text)
-                        }
-                    };
-                    
-                    // 回覆 LINE
-                    if let Err(e) = state_guard.line_client.reply_message(&msg_event.reply_token, &response).await {
-                        error!("Failed to reply: {}", e);
-                    }
+    // 伺服器模式可另寫入輪替的日誌檔；guard 須保留到程式結束
+    let _log_guard = match cli.command {
+        None | Some(CliCommand::Serve) => {
+            let file_logs = FileLogConfig::from_env().unwrap_or_else(|e| {
+                eprintln!("設定錯誤: {}", e);
+                std::process::exit(1);
+            });
+            let (file_layer, guard) = match &file_logs {
+                Some(file_logs) => {
+                    let (writer, guard) = logging::file_writer(file_logs).unwrap_or_else(|e| {
+                        eprintln!("設定錯誤: {}", e);
+                        std::process::exit(1);
+                    });
+                    (Some(fmt::layer().with_ansi(false).with_writer(writer)), Some(guard))
                 }
+                None => (None, None),
+            };
+            tracing_subscriber::registry().with(filter()).with(fmt::layer()).with(file_layer).init();
+            if let Some(file_logs) = file_logs {
+                info!("📝 日誌檔: {}/{}.<日期>.log（保留 {} 天）", file_logs.dir.display(), file_logs.prefix, file_logs.retention_days);
+                logging::spawn_maintenance(file_logs);
+            }
+            guard
+        }
+        // 對話模式只顯示警告，避免日誌打斷對話
+        Some(CliCommand::Chat { .. }) => {
+            tracing_subscriber::fmt()
+                .with_env_filter(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "warn".into()))
+                .with_writer(std::io::stderr)
+                .init();
+            None
+        }
+        Some(_) => {
+            tracing_subscriber::fmt().with_env_filter(filter()).with_writer(std::io::stderr).init();
+            None
+        }
+    };
+
+    match cli.command {
+        None | Some(CliCommand::Serve) => {
+            let config = Config::from_env().unwrap_or_else(|e| {
+                error!("設定錯誤: {}", e);
+                std::process::exit(1);
+            });
+            serve(config).await
+        }
+        Some(CliCommand::Export { dataset, from, to, format, output, recipient }) => {
+            let database_path = std::env::var("DATABASE_PATH").unwrap_or_else(|_| "bridge.db".to_string());
+            if let Ok(key) = std::env::var("PRIVACY_HASH_KEY") {
+                privacy::init(&key);
+            }
+            if let Err(e) = cli::export(&database_path, dataset, from, to, format, output, recipient).await {
+                error!("匯出失敗: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(CliCommand::Dataset { command: DatasetCommand::Export { from, to, include_fallback, output, recipient } }) => {
+            let database_path = std::env::var("DATABASE_PATH").unwrap_or_else(|_| "bridge.db".to_string());
+            if let Err(e) = cli::export_dataset(&database_path, from, to, include_fallback, output, recipient).await {
+                error!("資料集匯出失敗: {}", e);
+                std::process::exit(1);
             }
-            Event::Postback(pb_event) => {
-                info!("Postback: {}", pb_event.postback.data);
-                
-                let user_id = pb_event.source.user_id.clone().unwrap_or_default();
-                let response = match state_guard.openclaw_client.send_message(&user_id, &pb_event.postback.data).await {
-                    Ok(resp) => resp,
-                    Err(e) => {
-                        warn!("OpenClaw error: {}", e);
-                        format!("收到按鈕點擊：{}", pb_event.postback.data)
-                    }
-                };
-                
-                if let Err(e) = state_guard.line_client.reply_message(&pb_event.reply_token, &response).await {
-                    error!("Failed to reply: {}", e);
+        }
+        Some(CliCommand::Resolve { hash }) => {
+            let database_path = std::env::var("DATABASE_PATH").unwrap_or_else(|_| "bridge.db".to_string());
+            let Ok(key) = std::env::var("PRIVACY_HASH_KEY") else {
+                error!("PRIVACY_HASH_KEY 環境變數未設定");
+                std::process::exit(1);
+            };
+            match cli::resolve(&database_path, &key, &hash) {
+                Ok(id) => println!("{}", id),
+                Err(e) => {
+                    error!("反查失敗: {}", e);
+                    std::process::exit(1);
                 }
             }
-            Event::Unknown => {
-                info!("Unknown event type, skipping");
+        }
+        Some(CliCommand::Chat { user, locale }) => {
+            let config = Config::from_env().unwrap_or_else(|e| {
+                error!("設定錯誤: {}", e);
+                std::process::exit(1);
+            });
+            if let Err(e) = repl::run(config, &user, locale.as_deref()).await {
+                error!("對話模式失敗: {}", e);
+                std::process::exit(1);
             }
         }
     }
-    
-    Ok("OK")
+}
+
+/// 啟動 Webhook 伺服器
+async fn serve(config: Config) {
+    info!("🚀 LINE-OpenClaw Bridge v{} 啟動中...", env!("CARGO_PKG_VERSION"));
+    let addr = format!("{}:{}", config.host, config.port);
+    let port = config.port.clone();
+    let app = line_openclaw_bridge::build_router(config).await.unwrap_or_else(|e| {
+        error!("設定錯誤: {}", e);
+        std::process::exit(1);
+    });
+
+    // 啟動伺服器
+    info!("📍 監聽地址: http://{}", addr);
+    info!("📌 Webhook URL: http://your-domain:{}/callback", port);
+    info!("\n💡 提示：使用 ngrok 建立公開 URL：ngrok http {}", port);
+
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    // 連線位址供反向代理判斷（TRUSTED_PROXIES）與存取日誌使用
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
@@ -0,0 +1,147 @@
+//! 資料庫維護模組
+//! 每天在設定的時段刪除過期資料、壓縮尚未壓縮的對話（`COMPRESS_CONVERSATIONS`）並執行 ANALYZE / VACUUM，
+//! 回報回收的空間，避免常駐主機上的 SQLite 無限成長
+
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+use crate::store::{CompressionReport, ConversationStore};
+
+/// 檢查是否進入維護時段的間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(300);
+/// 每次維護最多壓縮的既有對話筆數
+const COMPRESS_BATCH: usize = 50_000;
+
+/// 維護設定
+#[derive(Debug, Clone)]
+pub struct MaintenanceConfig {
+    /// 每日維護時段，未設定則只能由管理 API 手動執行
+    pub window: Option<Window>,
+    pub timezone: Tz,
+    /// 已送達的提醒、死信、已結束的投票與問答保留天數
+    pub prune_after_days: i64,
+    /// 稽核紀錄保留天數，未設定則不刪除
+    pub audit_retention_days: Option<i64>,
+}
+
+/// 每日時段（可跨午夜，例如 `23:30-01:00`）
+#[derive(Debug, Clone, Copy)]
+pub struct Window {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Window {
+    /// 解析 `HH:MM-HH:MM`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.split_once('-')?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+        (start != end).then_some(Self { start, end })
+    }
+
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+/// 單一資料表刪除的列數
+#[derive(Debug, Serialize)]
+pub struct PrunedRows {
+    pub table: &'static str,
+    pub rows: usize,
+}
+
+/// 維護結果
+#[derive(Debug, Serialize)]
+pub struct MaintenanceReport {
+    pub pruned: Vec<PrunedRows>,
+    /// 開啟對話壓縮時，這次壓縮的既有對話
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionReport>,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub reclaimed_bytes: u64,
+    pub duration_ms: u64,
+}
+
+/// 刪除過期資料並重建資料庫（同步執行，呼叫端應放在 blocking 執行緒）
+pub fn run_once(store: &ConversationStore, config: &MaintenanceConfig) -> Result<MaintenanceReport, String> {
+    let start = Instant::now();
+    let now = store.clock().timestamp();
+    let bytes_before = store.database_bytes().map_err(|e| format!("無法取得資料庫大小: {}", e))?;
+    let pruned = store
+        .prune(
+            now,
+            now - config.prune_after_days * 86_400,
+            config.audit_retention_days.map(|days| now - days * 86_400),
+        )
+        .map_err(|e| format!("刪除過期資料失敗: {}", e))?;
+    let compression = if store.compression_enabled() {
+        Some(store.compress_conversations(COMPRESS_BATCH).map_err(|e| format!("壓縮對話失敗: {}", e))?)
+    } else {
+        None
+    };
+    store.vacuum().map_err(|e| format!("VACUUM 失敗: {}", e))?;
+    let bytes_after = store.database_bytes().map_err(|e| format!("無法取得資料庫大小: {}", e))?;
+
+    let report = MaintenanceReport {
+        pruned: pruned.into_iter().map(|(table, rows)| PrunedRows { table, rows }).collect(),
+        compression,
+        bytes_before,
+        bytes_after,
+        reclaimed_bytes: bytes_before.saturating_sub(bytes_after),
+        duration_ms: start.elapsed().as_millis() as u64,
+    };
+    info!(
+        pruned_rows = report.pruned.iter().map(|p| p.rows).sum::<usize>(),
+        compressed_rows = report.compression.as_ref().map_or(0, |c| c.compressed_rows),
+        bytes_before = report.bytes_before,
+        bytes_after = report.bytes_after,
+        reclaimed_bytes = report.reclaimed_bytes,
+        duration_ms = report.duration_ms,
+        "Database maintenance finished"
+    );
+    Ok(report)
+}
+
+/// 啟動背景維護：每天進入時段後執行一次
+pub fn spawn(store: Arc<ConversationStore>, config: MaintenanceConfig, window: Window) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut last_run: Option<NaiveDate> = None;
+        loop {
+            interval.tick().await;
+            let now = store.clock().now().with_timezone(&config.timezone);
+            // 跨午夜的時段以開始那天計算，避免午夜後再執行一次
+            let day = if window.start > window.end && now.time() < window.end {
+                now.date_naive().pred_opt().unwrap_or(now.date_naive())
+            } else {
+                now.date_naive()
+            };
+            if !window.contains(now.time()) || last_run == Some(day) {
+                continue;
+            }
+            last_run = Some(day);
+            let task = {
+                let (store, config) = (store.clone(), config.clone());
+                tokio::task::spawn_blocking(move || run_once(&store, &config))
+            };
+            if let Err(e) = task.await.map_err(|e| e.to_string()).and_then(|result| result) {
+                error!("Database maintenance failed: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,491 @@
+//! Markdown 轉換模組
+//! 僅支援 AI 回答常見的語法：標題、段落、清單、引用、分隔線、程式碼區塊、粗體、行內程式碼與連結；
+//! 可轉為 HTML（完整回答頁）、Flex bubble（LINE 回覆）或去除標記的純文字
+
+use crate::line::{Bubble, FlexBox, FlexComponent, FlexSpan, FlexText};
+
+/// Flex bubble 的 JSON 大小上限（LINE 限制 30 KB），超過時改用純文字
+const MAX_FLEX_BYTES: usize = 30_000;
+/// 行內程式碼與連結的顏色
+const CODE_COLOR: &str = "#C7254E";
+const LINK_COLOR: &str = "#1E6FD9";
+const QUOTE_COLOR: &str = "#888888";
+
+/// 區塊類型（用於判斷段落何時結束）
+#[derive(PartialEq)]
+enum Block {
+    None,
+    Paragraph,
+    UnorderedList,
+    OrderedList,
+    Quote,
+}
+
+/// 程式碼區塊以外的一行
+enum Line<'a> {
+    Blank,
+    Heading(usize, &'a str),
+    Rule,
+    Bullet(&'a str),
+    /// (編號, 項目)
+    Numbered(&'a str, &'a str),
+    Quote(&'a str),
+    Text(&'a str),
+}
+
+/// 行內語法的片段
+enum Inline<'a> {
+    Text(&'a str),
+    Bold(&'a str),
+    Code(&'a str),
+    Link { label: &'a str, url: &'a str },
+}
+
+fn classify(trimmed: &str) -> Line<'_> {
+    if trimmed.is_empty() {
+        Line::Blank
+    } else if let Some((level, title)) = heading(trimmed) {
+        Line::Heading(level, title)
+    } else if trimmed.len() >= 3 && ['-', '*', '_'].iter().any(|c| trimmed.chars().all(|t| t == *c)) {
+        Line::Rule
+    } else if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|p| trimmed.strip_prefix(p)) {
+        Line::Bullet(item)
+    } else if let Some((number, item)) = ordered_item(trimmed) {
+        Line::Numbered(number, item)
+    } else if let Some(quote) = trimmed.strip_prefix('>') {
+        Line::Quote(quote.trim())
+    } else {
+        Line::Text(trimmed)
+    }
+}
+
+/// 將 Markdown 轉為 HTML；所有文字皆經跳脫，不允許原始 HTML
+pub fn to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut block = Block::None;
+    let mut code: Option<String> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+
+        if let Some(info) = trimmed.strip_prefix("```") {
+            match code.take() {
+                Some(content) => html.push_str(&format!("{}</code></pre>\n", escape(&content))),
+                None => {
+                    close(&mut html, &mut block);
+                    let language = info.trim();
+                    if language.is_empty() {
+                        html.push_str("<pre><code>");
+                    } else {
+                        html.push_str(&format!("<pre><code class=\"language-{}\">", escape(language)));
+                    }
+                    code = Some(String::new());
+                }
+            }
+            continue;
+        }
+        if let Some(content) = code.as_mut() {
+            content.push_str(line);
+            content.push('\n');
+            continue;
+        }
+
+        match classify(trimmed) {
+            Line::Blank => close(&mut html, &mut block),
+            Line::Heading(level, title) => {
+                close(&mut html, &mut block);
+                html.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline(title)));
+            }
+            Line::Rule => {
+                close(&mut html, &mut block);
+                html.push_str("<hr>\n");
+            }
+            Line::Bullet(item) => {
+                open(&mut html, &mut block, Block::UnorderedList);
+                html.push_str(&format!("<li>{}</li>\n", inline(item)));
+            }
+            Line::Numbered(_, item) => {
+                open(&mut html, &mut block, Block::OrderedList);
+                html.push_str(&format!("<li>{}</li>\n", inline(item)));
+            }
+            Line::Quote(quote) => {
+                open(&mut html, &mut block, Block::Quote);
+                html.push_str(&format!("{}<br>\n", inline(quote)));
+            }
+            Line::Text(text) => {
+                if block == Block::Paragraph {
+                    html.push_str("<br>\n");
+                } else {
+                    open(&mut html, &mut block, Block::Paragraph);
+                }
+                html.push_str(&inline(text));
+            }
+        }
+    }
+
+    // 未結束的程式碼區塊延續到結尾
+    if let Some(content) = code {
+        html.push_str(&format!("{}</code></pre>\n", escape(&content)));
+    }
+    close(&mut html, &mut block);
+    html
+}
+
+fn open(html: &mut String, block: &mut Block, next: Block) {
+    if *block == next {
+        return;
+    }
+    close(html, block);
+    html.push_str(match next {
+        Block::Paragraph => "<p>",
+        Block::UnorderedList => "<ul>\n",
+        Block::OrderedList => "<ol>\n",
+        Block::Quote => "<blockquote>",
+        Block::None => "",
+    });
+    *block = next;
+}
+
+fn close(html: &mut String, block: &mut Block) {
+    html.push_str(match block {
+        Block::Paragraph => "</p>\n",
+        Block::UnorderedList => "</ul>\n",
+        Block::OrderedList => "</ol>\n",
+        Block::Quote => "</blockquote>\n",
+        Block::None => "",
+    });
+    *block = Block::None;
+}
+
+/// 是否含有 Flex 能呈現得比純文字好的語法（標題、清單、引用、分隔線、粗體、行內程式碼或連結）
+pub fn has_formatting(markdown: &str) -> bool {
+    markdown.lines().any(|line| match classify(line.trim()) {
+        Line::Blank => false,
+        Line::Text(text) => inline_tokens(text).iter().any(|token| !matches!(token, Inline::Text(_))),
+        _ => true,
+    })
+}
+
+/// 將 Markdown（不含程式碼區塊）轉為 Flex bubble：標題放大加粗、清單以項目符號對齊、引用以灰字呈現；
+/// 超過 LINE 大小上限時回傳 None，由呼叫端改送純文字
+pub fn to_flex(markdown: &str) -> Option<Bubble> {
+    let mut contents = Vec::new();
+    // 空行之後的下一個元件與前文保持間距
+    let mut gap = false;
+    for line in markdown.lines() {
+        let margin = if gap && !contents.is_empty() { "lg" } else { "sm" };
+        let component: FlexComponent = match classify(line.trim()) {
+            Line::Blank => {
+                gap = true;
+                continue;
+            }
+            Line::Heading(level, title) => {
+                let size = match level {
+                    1 => "lg",
+                    2 => "md",
+                    _ => "sm",
+                };
+                let margin = if contents.is_empty() { "none" } else { "lg" };
+                FlexText::new(plain_inline(title)).spans(spans(title)).size(size).bold().wrap().margin(margin).into()
+            }
+            Line::Rule => FlexComponent::separator_with_margin("lg"),
+            Line::Bullet(item) => list_item("•", item).margin(margin).into(),
+            Line::Numbered(number, item) => list_item(&format!("{}.", number), item).margin(margin).into(),
+            Line::Quote(quote) => FlexText::new(plain_inline(quote))
+                .spans(spans(quote))
+                .size("sm")
+                .color(QUOTE_COLOR)
+                .wrap()
+                .margin(margin)
+                .into(),
+            Line::Text(text) => FlexText::new(plain_inline(text)).spans(spans(text)).size("sm").wrap().margin(margin).into(),
+        };
+        contents.push(component);
+        gap = false;
+    }
+    if contents.is_empty() {
+        return None;
+    }
+    let bubble = Bubble::new().size("giga").body(FlexBox::vertical(contents));
+    let size = serde_json::to_vec(&bubble).map(|json| json.len()).unwrap_or(usize::MAX);
+    (size <= MAX_FLEX_BYTES).then_some(bubble)
+}
+
+/// 清單項目：項目符號或編號固定寬度，內容自動換行
+fn list_item(marker: &str, item: &str) -> FlexBox {
+    FlexBox::horizontal(vec![
+        FlexText::new(marker).size("sm").flex(0).into(),
+        FlexText::new(plain_inline(item)).spans(spans(item)).size("sm").wrap().flex(1).into(),
+    ])
+    .spacing("sm")
+}
+
+/// 行內語法轉為 span：粗體加粗、行內程式碼與連結改變顏色（Flex 文字無法點擊，連結附上網址）；
+/// 沒有行內語法時回傳空清單，直接使用文字元件的 `text`
+fn spans(text: &str) -> Vec<FlexSpan> {
+    let tokens = inline_tokens(text);
+    if tokens.iter().all(|token| matches!(token, Inline::Text(_))) {
+        return Vec::new();
+    }
+    let mut spans = Vec::new();
+    for token in tokens {
+        match token {
+            Inline::Text(text) => spans.push(FlexSpan::new(text)),
+            Inline::Bold(text) => spans.push(FlexSpan::new(text).bold()),
+            Inline::Code(code) => spans.push(FlexSpan::new(code).color(CODE_COLOR)),
+            Inline::Link { label, url } => {
+                spans.push(FlexSpan::new(label.replace("**", "")).color(LINK_COLOR).underline());
+                spans.push(FlexSpan::new(format!(" {}", url)).color(LINK_COLOR).size("xs"));
+            }
+        }
+    }
+    // LINE 不接受空字串的 span
+    spans.retain(|span| !span.text.is_empty());
+    spans
+}
+
+/// 去除 Markdown 標記的純文字（Flex 無法使用時的備援與替代文字）；程式碼區塊保留內容
+pub fn to_plain(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    for line in markdown.lines() {
+        if line.trim().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(line.to_string());
+            continue;
+        }
+        lines.push(match classify(line.trim()) {
+            Line::Blank => String::new(),
+            Line::Heading(_, title) => plain_inline(title),
+            Line::Rule => "──────────".to_string(),
+            Line::Bullet(item) => format!("• {}", plain_inline(item)),
+            Line::Numbered(number, item) => format!("{}. {}", number, plain_inline(item)),
+            Line::Quote(quote) => format!("｜{}", plain_inline(quote)),
+            Line::Text(text) => plain_inline(text),
+        });
+    }
+    lines.join("\n").trim().to_string()
+}
+
+fn plain_inline(text: &str) -> String {
+    inline_tokens(text)
+        .into_iter()
+        .map(|token| match token {
+            Inline::Text(text) | Inline::Bold(text) | Inline::Code(text) => text.to_string(),
+            Inline::Link { label, url } => format!("{} ({})", label.replace("**", ""), url),
+        })
+        .collect()
+}
+
+/// `# 標題` → (層級, 標題)
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    line[level..].strip_prefix(' ').map(|title| (level, title.trim()))
+}
+
+/// `1. 項目` → (編號, 項目)
+fn ordered_item(line: &str) -> Option<(&str, &str)> {
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    line[digits..].strip_prefix(". ").map(|item| (&line[..digits], item))
+}
+
+/// 行內語法轉為 HTML
+fn inline(text: &str) -> String {
+    inline_html(&inline_tokens(text))
+}
+
+fn inline_html(tokens: &[Inline]) -> String {
+    let mut html = String::new();
+    for token in tokens {
+        match token {
+            Inline::Text(text) => html.push_str(&escape(text)),
+            Inline::Bold(text) => html.push_str(&format!("<strong>{}</strong>", escape(text))),
+            Inline::Code(code) => html.push_str(&format!("<code>{}</code>", escape(code))),
+            Inline::Link { label, url } => {
+                let mut label_tokens = Vec::new();
+                bold_tokens(label, &mut label_tokens);
+                html.push_str(&format!("<a href=\"{}\">{}</a>", escape(url), inline_html(&label_tokens)));
+            }
+        }
+    }
+    html
+}
+
+/// 切分行內語法：`程式碼`、**粗體**、[文字](網址)
+fn inline_tokens(text: &str) -> Vec<Inline<'_>> {
+    let mut tokens = Vec::new();
+    let ticks = text.matches('`').count();
+    for (index, part) in text.split('`').enumerate() {
+        // 奇數段位於反引號之間；反引號未成對時最後一段照原樣輸出
+        if index % 2 == 1 && index < ticks {
+            tokens.push(Inline::Code(part));
+            continue;
+        }
+        if index % 2 == 1 {
+            tokens.push(Inline::Text("`"));
+        }
+        link_tokens(part, &mut tokens);
+    }
+    tokens
+}
+
+fn link_tokens<'a>(text: &'a str, tokens: &mut Vec<Inline<'a>>) {
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        let parsed = rest[start + 1..].split_once("](").and_then(|(label, tail)| {
+            let (url, after) = tail.split_once(')')?;
+            (!label.contains('[') && (url.starts_with("https://") || url.starts_with("http://")))
+                .then_some((label, url, after))
+        });
+        match parsed {
+            Some((label, url, after)) => {
+                bold_tokens(&rest[..start], tokens);
+                tokens.push(Inline::Link { label, url });
+                rest = after;
+            }
+            None => {
+                bold_tokens(&rest[..=start], tokens);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    bold_tokens(rest, tokens);
+}
+
+fn bold_tokens<'a>(text: &'a str, tokens: &mut Vec<Inline<'a>>) {
+    let parts: Vec<&str> = text.split("**").collect();
+    for (index, part) in parts.iter().enumerate() {
+        if index % 2 == 1 && index < parts.len() - 1 {
+            tokens.push(Inline::Bold(part));
+        } else {
+            if index % 2 == 1 {
+                tokens.push(Inline::Text("**"));
+            }
+            if !part.is_empty() {
+                tokens.push(Inline::Text(part));
+            }
+        }
+    }
+}
+
+/// HTML 跳脫
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flex_json(markdown: &str) -> serde_json::Value {
+        serde_json::to_value(to_flex(markdown).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn html_escapes_text_and_raw_html() {
+        assert_eq!(to_html("<script>alert('x')</script> & \"q\""), "<p>&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; &quot;q&quot;</p>\n");
+        assert_eq!(to_html("`<b>`"), "<p><code>&lt;b&gt;</code></p>\n");
+        assert_eq!(to_html("```html\n<div>\n```"), "<pre><code class=\"language-html\">&lt;div&gt;\n</code></pre>\n");
+    }
+
+    #[test]
+    fn html_escapes_link_urls_and_rejects_other_schemes() {
+        assert_eq!(
+            to_html("[**a**](https://e.com/?q=\"x\")"),
+            "<p><a href=\"https://e.com/?q=&quot;x&quot;\"><strong>a</strong></a></p>\n"
+        );
+        assert_eq!(to_html("[a](javascript:alert(1))"), "<p>[a](javascript:alert(1))</p>\n");
+    }
+
+    #[test]
+    fn html_blocks() {
+        let html = to_html("# 標題\n\n- 一\n- 二\n\n1. 甲\n> 引用\n\n---\n第一行\n第二行");
+        assert_eq!(
+            html,
+            "<h1>標題</h1>\n<ul>\n<li>一</li>\n<li>二</li>\n</ul>\n<ol>\n<li>甲</li>\n</ol>\n\
+             <blockquote>引用<br>\n</blockquote>\n<hr>\n<p>第一行<br>\n第二行</p>\n"
+        );
+    }
+
+    #[test]
+    fn unpaired_markers_stay_literal() {
+        assert_eq!(to_plain("a `b"), "a `b");
+        assert_eq!(to_plain("a **b"), "a **b");
+        assert_eq!(to_plain("`a` `b"), "a `b");
+        assert_eq!(to_plain("**a** **b"), "a **b");
+        assert_eq!(to_plain("[a](b"), "[a](b");
+        assert!(!has_formatting("a `b **c"));
+    }
+
+    #[test]
+    fn plain_removes_markup_and_keeps_code_blocks() {
+        let markdown = "## 步驟\n1. 執行 `ls`\n- **注意** [文件](https://e.com)\n> 提示\n\n```\n# 不是標題\n```";
+        assert_eq!(to_plain(markdown), "步驟\n1. 執行 ls\n• 注意 文件 (https://e.com)\n｜提示\n\n# 不是標題");
+    }
+
+    #[test]
+    fn plain_text_round_trips() {
+        let text = "第一行\n\n第二段，沒有任何標記";
+        assert!(!has_formatting(text));
+        assert_eq!(to_plain(text), text);
+        assert_eq!(to_plain(&to_plain(text)), text);
+    }
+
+    #[test]
+    fn has_formatting_detects_blocks_and_inline_syntax() {
+        assert!(has_formatting("# 標題"));
+        assert!(has_formatting("- 項目"));
+        assert!(has_formatting("文字 **粗體**"));
+        assert!(has_formatting("見 [這裡](https://e.com)"));
+        assert!(!has_formatting("價格 $5 * 2"));
+    }
+
+    #[test]
+    fn flex_text_matches_plain_text() {
+        let json = flex_json("# 標題\n見 **粗體** 與 `code`");
+        let contents = json["body"]["contents"].as_array().unwrap();
+        assert_eq!(contents[0]["text"], "標題");
+        assert_eq!(contents[0]["weight"], "bold");
+        assert_eq!(contents[1]["text"], "見 粗體 與 code");
+        let spans: Vec<_> = contents[1]["contents"].as_array().unwrap().iter().map(|s| s["text"].as_str().unwrap()).collect();
+        assert_eq!(spans, ["見 ", "粗體", " 與 ", "code"]);
+    }
+
+    #[test]
+    fn flex_list_items_and_links() {
+        let json = flex_json("- [文件](https://e.com)");
+        let item = &json["body"]["contents"][0];
+        assert_eq!(item["type"], "box");
+        assert_eq!(item["contents"][0]["text"], "•");
+        assert_eq!(item["contents"][1]["text"], "文件 (https://e.com)");
+        assert_eq!(item["contents"][1]["contents"][1]["text"], " https://e.com");
+    }
+
+    #[test]
+    fn flex_rejects_empty_and_oversized_input() {
+        assert!(to_flex("").is_none());
+        assert!(to_flex("\n\n").is_none());
+        let long = "- **項目**\n".repeat(2_000);
+        assert!(to_flex(&long).is_none());
+    }
+}
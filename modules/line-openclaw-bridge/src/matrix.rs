@@ -0,0 +1,210 @@
+//! Matrix 通道模組
+//! 設定 `MATRIX_HOMESERVER_URL` 後於背景以 matrix-sdk 同步：加入 `MATRIX_ROOMS` 列出的房間並接受一對一對話邀請，一對一對話的文字訊息
+//! 與房間中提及機器人的訊息走與 LINE 相同的斜線指令與 OpenClaw 對話流程；E2EE 房間的訊息由 SDK 解密，回覆時自動加密
+
+use matrix_adapter::{ConnectOptions, Incoming, MatrixClient};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use crate::channels::{self, ChannelAdapter};
+use crate::config::MatrixConfig;
+use crate::i18n::fallback_response;
+use crate::locations;
+use crate::markdown;
+use crate::openclaw;
+use crate::privacy;
+use crate::stickers;
+use crate::store::AuditEvent;
+use crate::{AppState, Reply, SharedState};
+
+/// Matrix 使用者與房間在對話紀錄中的 ID 前綴，與 LINE 的 ID 區隔
+pub const USER_PREFIX: &str = "mx:";
+/// 連線或第一次同步失敗後重試的間隔
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+impl MatrixConfig {
+    fn connect_options(&self) -> ConnectOptions<'_> {
+        ConnectOptions {
+            homeserver_url: &self.homeserver_url,
+            access_token: &self.access_token,
+            store_path: &self.store_path,
+            store_passphrase: self.store_passphrase.as_deref(),
+        }
+    }
+}
+
+/// 在背景連線、加入設定的房間並持續接收事件
+pub fn spawn(state: SharedState, config: &MatrixConfig) {
+    if config.store_passphrase.is_none() {
+        warn!("MATRIX_STORE_PASSPHRASE is not set; Matrix E2EE keys are stored unencrypted in {}", config.store_path.display());
+    }
+    let config = config.clone();
+    tokio::spawn(async move {
+        let client = loop {
+            match MatrixClient::connect(&config.connect_options()).await {
+                Ok(client) => break client,
+                Err(e) => {
+                    error!("Failed to log in to Matrix: {}", e);
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        };
+        info!("Matrix connected as {}", client.user_id());
+        for room in &config.rooms {
+            match client.join(room).await {
+                Ok(room_id) => info!("Joined Matrix room {}", room_id),
+                Err(e) => warn!("Failed to join Matrix room {}: {}", room, e),
+            }
+        }
+
+        // 第一次同步成功後 `run` 不會結束；第一次同步失敗時尚未註冊事件處理器，可直接重試
+        loop {
+            let (state, handler_client) = (state.clone(), client.clone());
+            let on_message = move |message: Incoming| {
+                let Some(message) = accepted(handler_client.user_id(), message) else { return };
+                let (state, client) = (state.clone(), handler_client.clone());
+                tokio::spawn(async move {
+                    let state = state.read().await;
+                    let direct = message.direct;
+                    let context = openclaw::RequestContext {
+                        channel: client.name(),
+                        chat_type: if direct { "user" } else { "room" },
+                        group_id: (!direct).then(|| message.room_id.clone()),
+                    };
+                    openclaw::with_context(context, handle_message(&state, &client, &message)).await;
+                });
+            };
+            if let Err(e) = client.run(on_message).await {
+                warn!("Matrix sync stopped: {}", e);
+            }
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    });
+}
+
+/// 一對一對話的訊息，或房間中提及機器人的訊息（去除提及的 ID）；其他訊息回傳 None
+fn accepted(bot_id: &str, mut message: Incoming) -> Option<Incoming> {
+    if !message.direct && !message.mentioned {
+        return None;
+    }
+    let text = message.body.replace(bot_id, "");
+    let text = text.trim().trim_start_matches([':', ',']).trim();
+    if text.is_empty() {
+        return None;
+    }
+    debug!("Matrix message {} in {} (encrypted: {})", message.event_id, message.room_id, message.encrypted);
+    message.body = text.to_string();
+    Some(message)
+}
+
+/// 斜線指令直接回覆，其他訊息交給 OpenClaw；一對一對話以使用者、房間以房間 ID 延續對話紀錄，頻率限制則以傳送者計算
+async fn handle_message(state: &AppState, client: &MatrixClient, message: &Incoming) {
+    let started = Instant::now();
+    let user_id = format!("{}{}", USER_PREFIX, message.sender);
+    let conversation_id = if message.direct { user_id.clone() } else { format!("{}{}", USER_PREFIX, message.room_id) };
+    let locale = state.config.default_locale;
+    let text = message.body.as_str();
+
+    if let Some(messages) = crate::command_response(state, "matrix", &conversation_id, &user_id, locale, text).await {
+        let reply = channels::plain_text(&messages);
+        if !reply.is_empty() {
+            send(state, client, &user_id, &message.room_id, &reply, started, "command").await;
+        }
+        return;
+    }
+    if let Some(limiter) = &state.rate_limiter {
+        let decision = limiter.check(&user_id);
+        if !decision.allowed {
+            let seconds = decision.retry_after_secs().to_string();
+            info!("Rate limited {} for {}s", privacy::id(&user_id), seconds);
+            let notice = state.i18n.text(locale, "rate_limit.exceeded", &[("seconds", &seconds)]);
+            send(state, client, &user_id, &message.room_id, &notice, started, &format!("rate_limited retry_after={}s", seconds)).await;
+            return;
+        }
+    }
+
+    if let Err(e) = client.set_typing(&message.room_id, true).await {
+        debug!("Failed to set Matrix typing notice: {}", e);
+    }
+    let Reply::Answer { text: answer, footer } = crate::chat(state, "matrix", &conversation_id, locale, text, None, |text| {
+        fallback_response(&state.i18n, locale, &state.branding.name, text)
+    })
+    .await
+    else {
+        // 已被 /stop 取消
+        if let Err(e) = client.set_typing(&message.room_id, false).await {
+            debug!("Failed to clear Matrix typing notice: {}", e);
+        }
+        return;
+    };
+    // 貼圖與地圖圖釘是 LINE 專屬的訊息，Matrix 只送文字；Markdown 轉為純文字
+    let (answer, _) = stickers::take(&state.branding, &answer);
+    let (answer, _) = locations::take(&answer);
+    let mut answer = markdown::to_plain(&answer);
+    if let Some(footer) = footer {
+        answer.push_str(&format!("\n\n{}", footer));
+    }
+    send(state, client, &user_id, &message.room_id, &answer, started, "answer").await;
+}
+
+async fn send(state: &AppState, client: &MatrixClient, user_id: &str, room_id: &str, text: &str, started: Instant, detail: &str) {
+    let (status, detail) = match channels::send_answer(client, room_id, text).await {
+        Ok(count) => {
+            info!("Replied to Matrix user {} ({} messages)", privacy::id(user_id), count);
+            ("ok", format!("{} room={} messages={}", detail, room_id, count))
+        }
+        Err(e) => {
+            warn!("Failed to reply to Matrix user {}: {}", privacy::id(user_id), e);
+            ("error", format!("{} room={} error={}", detail, room_id, e))
+        }
+    };
+    crate::audit(state, &AuditEvent {
+        user_id,
+        event_type: "matrix",
+        action: "reply",
+        status,
+        latency_ms: Some(started.elapsed().as_millis() as i64),
+        detail: Some(&detail),
+        ..Default::default()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOT: &str = "@bot:example.org";
+
+    fn message(body: &str, direct: bool, mentioned: bool) -> Incoming {
+        Incoming {
+            room_id: "!room:example.org".to_string(),
+            event_id: "$event".to_string(),
+            sender: "@alice:example.org".to_string(),
+            body: body.to_string(),
+            mentioned,
+            direct,
+            encrypted: false,
+        }
+    }
+
+    #[test]
+    fn accepts_direct_messages() {
+        assert_eq!(accepted(BOT, message(" 你好 ", true, false)).unwrap().body, "你好");
+    }
+
+    #[test]
+    fn ignores_unmentioned_room_messages() {
+        assert!(accepted(BOT, message("大家好", false, false)).is_none());
+    }
+
+    #[test]
+    fn strips_mention_in_rooms() {
+        let accepted = accepted(BOT, message("@bot:example.org: 今天天氣如何", false, true)).unwrap();
+        assert_eq!(accepted.body, "今天天氣如何");
+    }
+
+    #[test]
+    fn ignores_bare_mentions() {
+        assert!(accepted(BOT, message("@bot:example.org, ", false, true)).is_none());
+    }
+}
@@ -0,0 +1,51 @@
+//! 媒體訊息模組
+//! 將存放於物件儲存的圖片/音訊轉成帶簽章網址的 LINE 訊息
+
+use std::time::Duration;
+
+use crate::blob::BlobStore;
+use crate::line::{AudioMessage, ImageMessage, OutgoingMessage};
+use crate::store::Attachment;
+
+/// 媒體檔在物件儲存中的路徑前綴
+pub const MEDIA_PREFIX: &str = "media/";
+/// 媒體網址有效期（LINE 會在使用者開啟時才下載內容）
+const MEDIA_URL_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// 依附件產生 LINE 訊息（網址為物件儲存的預簽網址）
+pub fn to_message(blobs: &BlobStore, attachment: &Attachment) -> Result<OutgoingMessage, String> {
+    match attachment {
+        Attachment::Image { key, preview_key } => {
+            let original = blobs.presigned_url(&media_key(key), MEDIA_URL_TTL)?;
+            let preview = match preview_key {
+                Some(preview) => blobs.presigned_url(&media_key(preview), MEDIA_URL_TTL)?,
+                None => original.clone(),
+            };
+            Ok(ImageMessage::new(original, preview).into())
+        }
+        Attachment::Audio { key, duration_ms } => {
+            let url = blobs.presigned_url(&media_key(key), MEDIA_URL_TTL)?;
+            Ok(AudioMessage::new(url, *duration_ms).into())
+        }
+    }
+}
+
+/// 媒體檔完整的物件 key
+pub fn media_key(key: &str) -> String {
+    format!("{}{}", MEDIA_PREFIX, key.trim_start_matches('/'))
+}
+
+/// 依副檔名推斷 Content-Type
+pub fn content_type(key: &str) -> &'static str {
+    let extension = key.rsplit('.').next().unwrap_or_default().to_lowercase();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "m4a" => "audio/mp4",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
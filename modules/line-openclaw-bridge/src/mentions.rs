@@ -0,0 +1,161 @@
+//! 群組提及模組
+//! 解析訊息中的 @提及；群組或聊天室可設為「只在被 @提及時回應」（`/mention on|off`，預設值由 `GROUP_MENTION_ONLY` 決定），
+//! 加入熱鬧的群組時不會把每則訊息都交給 OpenClaw。交給 AI 的文字會移除對機器人本身的提及；
+//! 群組中的回覆以 textV2 訊息 @提及提問的使用者（`GROUP_REPLY_MENTION`），並引用原本的訊息（`GROUP_REPLY_QUOTE`）
+
+use tracing::{error, info};
+
+use crate::i18n::Locale;
+use crate::line::{Message, OutgoingMessage, Source, TextMessage, TextV2Message};
+use crate::privacy;
+use crate::AppState;
+
+/// 此群組或聊天室是否只在被 @提及時回應
+pub fn mention_only(state: &AppState, chat_id: &str) -> bool {
+    match state.store.mention_only(chat_id) {
+        Ok(setting) => setting.unwrap_or(state.config.group_mention_only),
+        Err(e) => {
+            error!("Failed to load group settings: {}", e);
+            state.config.group_mention_only
+        }
+    }
+}
+
+/// 略過此訊息：群組設為只在被提及時回應，且訊息既非指令也未提及機器人
+pub fn ignored(state: &AppState, source: &Source, message: &Message) -> bool {
+    let is_command = message.text.as_deref().is_some_and(|t| t.trim_start().starts_with('/'));
+    let Some(chat_id) = source.chat_id().filter(|_| source.is_group()) else { return false };
+    !is_command && !message.mentions_self() && mention_only(state, chat_id)
+}
+
+/// 移除文字中對機器人本身的提及（位置以 UTF-16 計算）；只有提及、沒有其他內容時保留原文
+pub fn strip_self(message: &Message) -> Option<String> {
+    let text = message.text.as_deref()?;
+    let Some(mention) = message.mention.as_ref().filter(|_| message.mentions_self()) else {
+        return Some(text.to_string());
+    };
+    let mut units: Vec<u16> = text.encode_utf16().collect();
+    let mut ranges: Vec<(usize, usize)> = mention
+        .mentionees
+        .iter()
+        .filter(|m| m.is_self)
+        .map(|m| (m.index, (m.index + m.length).min(units.len())))
+        .filter(|(start, end)| start < end)
+        .collect();
+    // 先合併重疊或相鄰的範圍，再由後往前刪除，前面的位置不受影響
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    for (start, end) in merged.into_iter().rev() {
+        units.drain(start..end);
+    }
+    let stripped = String::from_utf16_lossy(&units).trim().to_string();
+    Some(if stripped.is_empty() { text.to_string() } else { stripped })
+}
+
+/// 回覆中提問者的替換 key
+const ASKER_KEY: &str = "asker";
+
+/// 群組與聊天室中的回覆：第一則文字訊息改為 textV2，開頭 @提及提問的使用者；
+/// 回覆沒有文字訊息（例如整則以 Flex 呈現的 Markdown 回答）時，在最前面加上一則只有提及的訊息
+pub fn tag_asker(state: &AppState, source: &Source, messages: &mut Vec<OutgoingMessage>) {
+    if !state.config.group_reply_mention || !source.is_group() || messages.is_empty() {
+        return;
+    }
+    let Some(user_id) = source.user_id.as_deref() else { return };
+    for message in messages.iter_mut() {
+        if let OutgoingMessage::Text(text) = message {
+            let text = std::mem::replace(text, TextMessage::new(""));
+            *message = TextV2Message::from(text).mention_first(ASKER_KEY, user_id).into();
+            return;
+        }
+    }
+    let mut mention = TextV2Message::from(TextMessage::new("")).mention_first(ASKER_KEY, user_id);
+    mention.text.truncate(mention.text.trim_end().len());
+    messages.insert(0, mention.into());
+}
+
+/// 群組與聊天室中的回覆：第一則可引用的訊息（文字或貼圖）引用使用者的原訊息
+pub fn quote(state: &AppState, source: &Source, message: &Message, messages: &mut [OutgoingMessage]) {
+    if !state.config.group_reply_quote || !source.is_group() {
+        return;
+    }
+    let Some(quote_token) = message.quote_token.as_deref() else { return };
+    for message in messages.iter_mut() {
+        if message.set_quote_token(quote_token) {
+            return;
+        }
+    }
+}
+
+/// 處理 `/mention [on|off]`：查看或切換此群組只在被提及時回應（一對一對話的 `chat_id` 即為使用者本身）
+pub fn handle_command(state: &AppState, chat_id: &str, user_id: &str, locale: Locale, args: Vec<String>) -> String {
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    if chat_id == user_id {
+        return t("mention.group_only");
+    }
+    let enabled = match args.first().map(|arg| arg.to_lowercase()).as_deref() {
+        Some("on") => true,
+        Some("off") => false,
+        Some(_) => return t("mention.usage"),
+        None => return t(if mention_only(state, chat_id) { "mention.status_on" } else { "mention.status_off" }),
+    };
+    match state.store.set_mention_only(chat_id, enabled) {
+        Ok(()) => {
+            info!("Mention-only mode for {} set to {}", privacy::id(chat_id), enabled);
+            t(if enabled { "mention.on" } else { "mention.off" })
+        }
+        Err(e) => {
+            error!("Failed to update group settings: {}", e);
+            t("mention.error")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text_message(text: &str, mentionees: serde_json::Value) -> Message {
+        serde_json::from_value(json!({
+            "id": "1",
+            "type": "text",
+            "text": text,
+            "mention": { "mentionees": mentionees },
+        }))
+        .unwrap()
+    }
+
+    fn mentionee(index: usize, length: usize, is_self: bool) -> serde_json::Value {
+        json!({ "index": index, "length": length, "type": "user", "isSelf": is_self })
+    }
+
+    #[test]
+    fn strips_only_self_mentions() {
+        let message = text_message("@Bot 你好 @Amy", json!([mentionee(0, 4, true), mentionee(8, 4, false)]));
+        assert_eq!(strip_self(&message).as_deref(), Some("你好 @Amy"));
+    }
+
+    #[test]
+    fn overlapping_ranges_are_merged() {
+        // 同一個提及重複出現、或兩個範圍互相重疊時，不可因位置偏移而刪掉其他文字
+        let message = text_message("@Bot 今天天氣如何", json!([mentionee(0, 4, true), mentionee(0, 4, true), mentionee(2, 3, true)]));
+        assert_eq!(strip_self(&message).as_deref(), Some("今天天氣如何"));
+    }
+
+    #[test]
+    fn utf16_positions_and_mention_only_messages() {
+        let message = text_message("😀 @Bot 早安", json!([mentionee(3, 4, true)]));
+        assert_eq!(strip_self(&message).as_deref(), Some("😀  早安"));
+        let only = text_message("@Bot", json!([mentionee(0, 4, true)]));
+        assert_eq!(strip_self(&only).as_deref(), Some("@Bot"));
+        let beyond = text_message("@Bot 嗨", json!([mentionee(10, 4, true)]));
+        assert_eq!(strip_self(&beyond).as_deref(), Some("@Bot 嗨"));
+    }
+}
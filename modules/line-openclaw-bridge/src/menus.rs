@@ -0,0 +1,138 @@
+//! 互動選單模組
+//! OpenClaw（或其工具）在回答中以 ```line-menu 區塊輸出 JSON 選單時，轉成 LINE 按鈕範本；
+//! postback 按鈕的 data 加上專用前綴，點選後只會原樣交回 OpenClaw，由它決定下一步，不會觸發指令、投票等內部 postback
+
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::line::{Action, ButtonsTemplate, OutgoingMessage, Template, TemplateMessage};
+
+/// 選單區塊的語言標記
+pub const LANGUAGE: &str = "line-menu";
+/// 按鈕文字上限（LINE 限制 20 字）
+const LABEL_LIMIT: usize = 20;
+/// postback data 上限（LINE 限制 300 字）
+const DATA_LIMIT: usize = 300;
+/// 替代文字上限（LINE 限制 400 字）
+const ALT_LIMIT: usize = 400;
+/// 選單 postback data 的前綴
+const POSTBACK_PREFIX: &str = "menu:";
+/// Bridge 內部 postback 使用的前綴；AI 產生的 data 以這些開頭時捨棄該按鈕
+const INTERNAL_PREFIXES: [&str; 8] =
+    ["menu:", "command:", "chat:", "history:", "quiz:", "poll:", "announcement:", "automation:"];
+
+/// OpenClaw 輸出的選單
+#[derive(Debug, Deserialize)]
+struct Menu {
+    title: Option<String>,
+    text: String,
+    image: Option<String>,
+    actions: Vec<MenuAction>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum MenuAction {
+    Postback { label: String, data: String },
+    Uri { label: String, uri: String },
+    Message { label: String, text: String },
+}
+
+/// 附在對話前的系統指示
+pub fn instruction() -> String {
+    format!(
+        "When the user should pick from a few options, you may end your answer with a fenced code block tagged `{}` \
+         containing JSON: {{\"title\": optional string, \"text\": string, \"image\": optional https URL, \"actions\": \
+         [1-4 items, each {{\"type\": \"postback\", \"label\", \"data\"}} or {{\"type\": \"uri\", \"label\", \"uri\"}} or \
+         {{\"type\": \"message\", \"label\", \"text\"}}]}}. Labels are at most 20 characters. The `data` of a chosen \
+         postback button is sent back to you as the next user message.",
+        LANGUAGE
+    )
+}
+
+/// 將選單 JSON 轉成按鈕範本；格式不符或沒有可用的按鈕時回傳 None
+pub fn parse(json: &str) -> Option<OutgoingMessage> {
+    let menu: Menu = match serde_json::from_str(json) {
+        Ok(menu) => menu,
+        Err(e) => {
+            warn!("Ignoring malformed {} block: {}", LANGUAGE, e);
+            return None;
+        }
+    };
+    let mut template = ButtonsTemplate::new(menu.text.as_str());
+    if let Some(title) = &menu.title {
+        template = template.title(title.as_str());
+    }
+    if let Some(image) = menu.image.filter(|url| url.starts_with("https://")) {
+        template = template.thumbnail(image);
+    }
+    for action in menu.actions {
+        if let Some(action) = convert(action) {
+            template = template.action(action);
+        }
+    }
+    if template.actions.is_empty() {
+        warn!("Ignoring {} block without usable actions", LANGUAGE);
+        return None;
+    }
+    let alt: String = match &menu.title {
+        Some(title) => format!("{}\n{}", title, menu.text),
+        None => menu.text,
+    }
+    .chars()
+    .take(ALT_LIMIT)
+    .collect();
+    Some(TemplateMessage::new(alt, Template::buttons(template)).into())
+}
+
+/// 解析選單按鈕的 postback 資料，回傳要交給 OpenClaw 的原始 data
+pub fn parse_postback(data: &str) -> Option<&str> {
+    data.strip_prefix(POSTBACK_PREFIX)
+}
+
+/// 轉成 LINE 按鈕；網址不是 http(s)、postback data 過長或冒用內部前綴時捨棄
+fn convert(action: MenuAction) -> Option<Action> {
+    let label = |label: &str| label.chars().take(LABEL_LIMIT).collect::<String>();
+    match action {
+        MenuAction::Postback { data, .. } if INTERNAL_PREFIXES.iter().any(|prefix| data.starts_with(prefix)) => {
+            warn!("Ignoring {} postback that uses an internal prefix", LANGUAGE);
+            None
+        }
+        MenuAction::Postback { label: l, data } if data.chars().count() + POSTBACK_PREFIX.len() <= DATA_LIMIT => {
+            Some(Action::postback_with_text(label(&l), format!("{}{}", POSTBACK_PREFIX, data), l))
+        }
+        MenuAction::Uri { label: l, uri } if uri.starts_with("https://") || uri.starts_with("http://") => {
+            Some(Action::uri(label(&l), uri))
+        }
+        MenuAction::Message { label: l, text } => Some(Action::message(label(&l), text)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn postback(data: &str) -> MenuAction {
+        MenuAction::Postback { label: "Pick".to_string(), data: data.to_string() }
+    }
+
+    #[test]
+    fn postback_data_gets_menu_prefix() {
+        let Some(Action::Postback { data, .. }) = convert(postback("size=large")) else { panic!("expected a postback") };
+        assert_eq!(data, "menu:size=large");
+        assert_eq!(parse_postback(&data), Some("size=large"));
+    }
+
+    #[test]
+    fn internal_prefixes_are_rejected() {
+        for data in ["command:/reset", "history:export:1", "poll:vote:1:0", "automation:cancel:3", "menu:x"] {
+            assert!(convert(postback(data)).is_none(), "{}", data);
+        }
+    }
+
+    #[test]
+    fn other_postbacks_are_not_menu_postbacks() {
+        assert_eq!(parse_postback("command:/reset"), None);
+    }
+}
@@ -0,0 +1,235 @@
+//! 請求指標模組
+//! 記錄每個路由的請求數與延遲分布，寫入日誌並以 Prometheus 格式提供於 `/metrics`；
+//! 亦記錄 OpenClaw 串流回應的首 token 時間與生成速度，以及 webhook 回應時間、逾時次數與簽名驗證結果
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::openclaw::StreamTimings;
+use crate::privacy;
+use crate::proxy::ClientIp;
+
+/// 延遲直方圖的分桶上限（秒）
+const BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// 路由層級的請求指標
+pub struct Metrics {
+    /// 超過此延遲的請求以 WARN 記錄
+    slow_threshold: Duration,
+    routes: Mutex<BTreeMap<RouteKey, Histogram>>,
+    /// 依模型區分的 OpenClaw 串流統計
+    streams: Mutex<BTreeMap<String, StreamStats>>,
+    /// `/callback` 從收到請求到回應的時間
+    webhook: Mutex<Histogram>,
+    /// 超過回應時限而提前回應的 webhook 數
+    webhook_deadline_exceeded: AtomicU64,
+    /// 依相符的 channel secret 區分的簽名驗證次數（`current`、`previous`、`previous_expired`、`invalid`）
+    signatures: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+/// 單一模型的串流統計：首 token 時間直方圖與生成 token 數／時間的累計（以 rate 相除即為 tokens/sec）
+#[derive(Default)]
+struct StreamStats {
+    first_token: Histogram,
+    tokens: u64,
+    generation_seconds: f64,
+}
+
+/// 以 method、路由樣板、狀態碼區分的統計鍵
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct RouteKey {
+    method: String,
+    route: String,
+    status: u16,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// 各分桶的累計次數（Prometheus `le` 語意）
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+impl Metrics {
+    pub fn new(slow_threshold: Duration) -> Self {
+        Self {
+            slow_threshold,
+            routes: Mutex::new(BTreeMap::new()),
+            streams: Mutex::new(BTreeMap::new()),
+            webhook: Mutex::new(Histogram::default()),
+            webhook_deadline_exceeded: AtomicU64::new(0),
+            signatures: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// 記錄一次 OpenClaw 串流回應
+    pub fn observe_stream(&self, model: &str, timings: &StreamTimings) {
+        let mut streams = self.streams.lock().unwrap();
+        let stats = streams.entry(model.to_string()).or_default();
+        stats.first_token.observe(timings.first_token_ms as f64 / 1000.0);
+        stats.tokens += timings.tokens;
+        stats.generation_seconds += timings.total_ms.saturating_sub(timings.first_token_ms) as f64 / 1000.0;
+    }
+
+    /// 記錄一次 webhook 回應
+    pub fn observe_webhook(&self, elapsed: Duration, deadline_exceeded: bool) {
+        self.webhook.lock().unwrap().observe(elapsed.as_secs_f64());
+        if deadline_exceeded {
+            self.webhook_deadline_exceeded.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 記錄一次 webhook 簽名驗證結果
+    pub fn observe_signature(&self, key: &'static str) {
+        *self.signatures.lock().unwrap().entry(key).or_default() += 1;
+    }
+
+    fn observe(&self, key: RouteKey, elapsed: Duration) {
+        self.routes
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// 以 Prometheus 文字格式輸出
+    pub fn render(&self) -> String {
+        let routes = self.routes.lock().unwrap();
+        let mut out = String::new();
+        out.push_str("# HELP bridge_http_request_duration_seconds HTTP request latency by route.\n");
+        out.push_str("# TYPE bridge_http_request_duration_seconds histogram\n");
+        for (key, histogram) in routes.iter() {
+            let labels = format!(
+                "method=\"{}\",route=\"{}\",status=\"{}\"",
+                key.method,
+                escape_label(&key.route),
+                key.status
+            );
+            for (count, bound) in histogram.buckets.iter().zip(BUCKETS) {
+                let _ = writeln!(out, "bridge_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}", labels, bound, count);
+            }
+            let _ = writeln!(out, "bridge_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, histogram.count);
+            let _ = writeln!(out, "bridge_http_request_duration_seconds_sum{{{}}} {}", labels, histogram.sum);
+            let _ = writeln!(out, "bridge_http_request_duration_seconds_count{{{}}} {}", labels, histogram.count);
+        }
+
+        let webhook = self.webhook.lock().unwrap();
+        out.push_str("# HELP bridge_webhook_response_seconds Time from receiving a LINE webhook to responding.\n");
+        out.push_str("# TYPE bridge_webhook_response_seconds histogram\n");
+        for (count, bound) in webhook.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(out, "bridge_webhook_response_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+        }
+        let _ = writeln!(out, "bridge_webhook_response_seconds_bucket{{le=\"+Inf\"}} {}", webhook.count);
+        let _ = writeln!(out, "bridge_webhook_response_seconds_sum {}", webhook.sum);
+        let _ = writeln!(out, "bridge_webhook_response_seconds_count {}", webhook.count);
+        out.push_str("# HELP bridge_webhook_deadline_exceeded_total Webhooks acknowledged early because verification exceeded the response deadline.\n");
+        out.push_str("# TYPE bridge_webhook_deadline_exceeded_total counter\n");
+        let _ = writeln!(
+            out,
+            "bridge_webhook_deadline_exceeded_total {}",
+            self.webhook_deadline_exceeded.load(Ordering::Relaxed)
+        );
+        let signatures = self.signatures.lock().unwrap();
+        if !signatures.is_empty() {
+            out.push_str("# HELP bridge_webhook_signature_total Webhook signature checks by matched channel secret.\n");
+            out.push_str("# TYPE bridge_webhook_signature_total counter\n");
+            for (key, count) in signatures.iter() {
+                let _ = writeln!(out, "bridge_webhook_signature_total{{key=\"{}\"}} {}", key, count);
+            }
+        }
+
+        let streams = self.streams.lock().unwrap();
+        if streams.is_empty() {
+            return out;
+        }
+        out.push_str("# HELP bridge_openclaw_time_to_first_token_seconds Time from request to first streamed token.\n");
+        out.push_str("# TYPE bridge_openclaw_time_to_first_token_seconds histogram\n");
+        for (model, stats) in streams.iter() {
+            let labels = format!("model=\"{}\"", escape_label(model));
+            let histogram = &stats.first_token;
+            for (count, bound) in histogram.buckets.iter().zip(BUCKETS) {
+                let _ = writeln!(out, "bridge_openclaw_time_to_first_token_seconds_bucket{{{},le=\"{}\"}} {}", labels, bound, count);
+            }
+            let _ = writeln!(out, "bridge_openclaw_time_to_first_token_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, histogram.count);
+            let _ = writeln!(out, "bridge_openclaw_time_to_first_token_seconds_sum{{{}}} {}", labels, histogram.sum);
+            let _ = writeln!(out, "bridge_openclaw_time_to_first_token_seconds_count{{{}}} {}", labels, histogram.count);
+        }
+        out.push_str("# HELP bridge_openclaw_generated_tokens_total Tokens generated in streamed responses.\n");
+        out.push_str("# TYPE bridge_openclaw_generated_tokens_total counter\n");
+        for (model, stats) in streams.iter() {
+            let _ = writeln!(out, "bridge_openclaw_generated_tokens_total{{model=\"{}\"}} {}", escape_label(model), stats.tokens);
+        }
+        out.push_str("# HELP bridge_openclaw_generation_seconds_total Time spent generating after the first token.\n");
+        out.push_str("# TYPE bridge_openclaw_generation_seconds_total counter\n");
+        for (model, stats) in streams.iter() {
+            let _ = writeln!(out, "bridge_openclaw_generation_seconds_total{{model=\"{}\"}} {}", escape_label(model), stats.generation_seconds);
+        }
+        out
+    }
+}
+
+/// 記錄每個請求的 method、路由、狀態碼與延遲
+pub async fn track(State(metrics): State<Arc<Metrics>>, request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let client = request
+        .extensions()
+        .get::<ClientIp>()
+        .map_or_else(|| "-".to_string(), |ClientIp(ip)| privacy::ip(*ip).to_string());
+    // 以路由樣板（如 `/admin/templates/:name`）統計，避免路徑參數造成標籤爆量
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+    let elapsed = start.elapsed();
+    let status = response.status().as_u16();
+
+    if elapsed >= metrics.slow_threshold {
+        warn!(
+            method = %method,
+            route = %route,
+            path = %path,
+            client = %client,
+            status,
+            latency_ms = elapsed.as_millis() as u64,
+            threshold_ms = metrics.slow_threshold.as_millis() as u64,
+            "Slow request"
+        );
+    } else {
+        debug!(method = %method, route = %route, client = %client, status, latency_ms = elapsed.as_millis() as u64, "Request handled");
+    }
+
+    metrics.observe(RouteKey { method, route, status }, elapsed);
+    response
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
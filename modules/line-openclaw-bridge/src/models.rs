@@ -0,0 +1,129 @@
+//! 模型清單模組
+//! 啟動時與每隔一段時間取得 OpenClaw 的 `/v1/models`，檢查設定中用到的模型（OPENCLAW_MODEL、FANOUT_MODELS、流程步驟）
+//! 是否存在並警告缺少的模型；OPENCLAW_MODEL 不存在時改用預設模型，避免打錯模型名稱造成每次呼叫都 404
+
+use serde::Serialize;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::i18n::Locale;
+use crate::{AppState, SharedState};
+
+/// 重新取得模型清單的間隔
+const REFRESH_INTERVAL: Duration = Duration::from_secs(600);
+/// `/model` 最多列出的模型數
+const MAX_LISTED: usize = 30;
+
+/// 設定中用到的模型
+#[derive(Debug, Serialize)]
+pub struct ConfiguredModel {
+    /// 設定來源（`OPENCLAW_MODEL`、`FANOUT_MODELS`、`FANOUT_JUDGE_MODEL` 或 `chain:<名稱>/<步驟>`）
+    pub source: String,
+    pub model: String,
+    /// 尚未取得模型清單時為 null
+    pub available: Option<bool>,
+}
+
+/// 模型檢查結果（管理 API）
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub configured_model: String,
+    /// 實際使用的模型（設定的模型不存在時為改用的模型）
+    pub active_model: String,
+    /// OpenClaw 回報的可用模型；無法取得時為 null
+    pub available: Option<Vec<String>>,
+    pub configured: Vec<ConfiguredModel>,
+}
+
+/// 在背景定期更新模型清單（第一次於啟動時立即執行）
+pub fn spawn(state: SharedState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+            refresh(&*state.read().await).await;
+        }
+    });
+}
+
+/// 取得模型清單並檢查設定；OpenClaw 不支援 `/v1/models` 或暫時無法連線時沿用上次的清單
+async fn refresh(state: &AppState) {
+    let models = match state.openclaw_client.fetch_models().await {
+        Ok(models) => models,
+        Err(e) => {
+            warn!("Model discovery failed: {}", e);
+            return;
+        }
+    };
+    info!("OpenClaw serves {} models", models.len());
+    state.openclaw_client.set_available_models(models);
+    for configured in configured(state).into_iter().filter(|m| m.available == Some(false)) {
+        warn!("Model {} ({}) is not served by OpenClaw", configured.model, configured.source);
+    }
+}
+
+/// 設定中用到的模型與是否可用
+fn configured(state: &AppState) -> Vec<ConfiguredModel> {
+    let client = &state.openclaw_client;
+    let mut models = vec![("OPENCLAW_MODEL".to_string(), client.configured_model().to_string())];
+    if let Some(fanout) = &state.config.fanout {
+        models.extend(fanout.models.iter().map(|model| ("FANOUT_MODELS".to_string(), model.clone())));
+        models.extend(fanout.judge_model.iter().map(|model| ("FANOUT_JUDGE_MODEL".to_string(), model.clone())));
+    }
+    for chain in state.chains.iter() {
+        for step in &chain.steps {
+            if let Some(model) = &step.model {
+                models.push((format!("chain:{}/{}", chain.name, step.name), model.clone()));
+            }
+        }
+    }
+    let known = client.available_models().is_some();
+    models
+        .into_iter()
+        .map(|(source, model)| ConfiguredModel {
+            available: known.then(|| client.is_available(&model)),
+            source,
+            model,
+        })
+        .collect()
+}
+
+/// 管理 API 的檢查結果
+pub fn report(state: &AppState) -> Report {
+    Report {
+        configured_model: state.openclaw_client.configured_model().to_string(),
+        active_model: state.openclaw_client.model(),
+        available: state.openclaw_client.available_models(),
+        configured: configured(state),
+    }
+}
+
+/// 處理 `/model`：目前使用的模型與 OpenClaw 提供的模型
+pub fn handle_command(state: &AppState, user_id: &str, locale: Locale) -> String {
+    if !state.flags.is_enabled("model_command", Some(user_id), true) {
+        return state.i18n.text(locale, "command.unavailable", &[]);
+    }
+    let client = &state.openclaw_client;
+    let active = client.model();
+    let mut text = if active == client.configured_model() {
+        state.i18n.text(locale, "model.current", &[("model", &active)])
+    } else {
+        state.i18n.text(locale, "model.fallback", &[("model", &active), ("configured", client.configured_model())])
+    };
+    text.push_str("\n\n");
+    match client.available_models() {
+        Some(models) if !models.is_empty() => {
+            let mut lines: Vec<String> = models
+                .iter()
+                .take(MAX_LISTED)
+                .map(|model| format!("{} {}", if *model == active { "▶" } else { "•" }, model))
+                .collect();
+            if models.len() > MAX_LISTED {
+                lines.push(state.i18n.text(locale, "model.more", &[("count", &(models.len() - MAX_LISTED).to_string())]));
+            }
+            text.push_str(&state.i18n.text(locale, "model.available", &[("models", &lines.join("\n"))]));
+        }
+        _ => text.push_str(&state.i18n.text(locale, "model.unknown", &[])),
+    }
+    text
+}
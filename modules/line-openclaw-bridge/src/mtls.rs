@@ -0,0 +1,170 @@
+//! 管理 API mTLS 監聽模組
+//! 在獨立連接埠提供管理端點，用戶端須出示由指定 CA 簽發的憑證，並依憑證 CN 限制可用路徑；憑證檔變更時自動重新載入
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware::{self, Next},
+    response::Response,
+    Extension, Router,
+};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+use tracing::{error, info, warn};
+
+use crate::admin;
+use crate::config::{AdminTlsConfig, ClientRule};
+use crate::SharedState;
+
+/// 檢查憑證檔是否變更的間隔
+const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+/// TLS 交握的時間上限；逾時即關閉連線，避免不完成交握的用戶端佔住連線
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 啟動 mTLS 監聽；憑證載入失敗時回傳錯誤
+pub async fn spawn(state: SharedState, host: &str, config: AdminTlsConfig) -> Result<(), String> {
+    let acceptor = Arc::new(RwLock::new(acceptor(&config)?));
+    let addr = format!("{}:{}", host, config.port);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .map_err(|e| format!("無法監聽 {}: {}", addr, e))?;
+    info!("🔐 管理 API mTLS 監聽: https://{}/admin", addr);
+
+    spawn_reloader(acceptor.clone(), config.clone());
+
+    let app = Router::new()
+        .nest("/admin", admin::routes())
+        .layer(middleware::from_fn(authorize))
+        .with_state(state);
+    let rules = Arc::new(config.clients);
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    warn!("Failed to accept mTLS connection: {}", e);
+                    continue;
+                }
+            };
+            let acceptor = acceptor.read().unwrap().clone();
+            let app = app.clone();
+            let rules = rules.clone();
+            tokio::spawn(async move {
+                let stream = match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        warn!("mTLS handshake with {} failed: {}", peer, e);
+                        return;
+                    }
+                    Err(_) => {
+                        warn!("mTLS handshake with {} timed out after {:?}", peer, HANDSHAKE_TIMEOUT);
+                        return;
+                    }
+                };
+                let common_name = stream
+                    .get_ref()
+                    .1
+                    .peer_certificates()
+                    .and_then(|certs| certs.first())
+                    .and_then(|cert| common_name(cert.as_ref()));
+                let Some(rule) = common_name
+                    .as_deref()
+                    .and_then(|cn| rules.iter().find(|r| r.common_name == cn))
+                else {
+                    warn!("Rejected mTLS client {} with unmapped CN {:?}", peer, common_name);
+                    return;
+                };
+
+                let service = TowerToHyperService::new(app.layer(Extension(Arc::new(rule.clone()))));
+                if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    warn!("mTLS connection from {} ended with error: {}", peer, e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// 依用戶端憑證 CN 對應的路徑前綴授權
+async fn authorize(Extension(client): Extension<Arc<ClientRule>>, request: Request, next: Next) -> Result<Response, StatusCode> {
+    if !client.allows(request.uri().path()) {
+        warn!("mTLS client {} is not allowed to access {}", client.common_name, request.uri().path());
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(next.run(request).await)
+}
+
+/// 憑證主體的 CN
+fn common_name(der: &[u8]) -> Option<String> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+    let name = cert.subject().iter_common_name().next()?.as_str().ok()?.to_string();
+    Some(name)
+}
+
+/// 讀取憑證、私鑰與用戶端 CA，建立要求用戶端憑證的 TLS 設定
+fn acceptor(config: &AdminTlsConfig) -> Result<TlsAcceptor, String> {
+    let certs = read_pem(&config.cert_path, |reader| rustls_pemfile::certs(reader).collect())?;
+    let key = read_pem(&config.key_path, |reader| rustls_pemfile::private_key(reader))?
+        .ok_or_else(|| format!("{} 中沒有私鑰", config.key_path))?;
+    let mut roots = RootCertStore::empty();
+    for ca in read_pem(&config.client_ca_path, |reader| rustls_pemfile::certs(reader).collect::<Result<Vec<_>, _>>())? {
+        roots.add(ca).map_err(|e| format!("無效的 CA 憑證 {}: {}", config.client_ca_path, e))?;
+    }
+
+    let provider = Arc::new(ring::default_provider());
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .map_err(|e| format!("無法建立用戶端憑證驗證: {}", e))?;
+    let mut server = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("無效的伺服器憑證: {}", e))?;
+    server.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(server)))
+}
+
+fn read_pem<T>(path: &str, parse: impl FnOnce(&mut BufReader<std::fs::File>) -> std::io::Result<T>) -> Result<T, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("無法讀取 {}: {}", path, e))?;
+    parse(&mut BufReader::new(file)).map_err(|e| format!("無法解析 {}: {}", path, e))
+}
+
+/// 在背景定期檢查憑證檔的修改時間，有變更即重新載入；載入失敗時保留原設定
+fn spawn_reloader(acceptor: Arc<RwLock<TlsAcceptor>>, config: AdminTlsConfig) {
+    tokio::spawn(async move {
+        let modified = || {
+            [&config.cert_path, &config.key_path, &config.client_ca_path]
+                .map(|path| std::fs::metadata(Path::new(path)).and_then(|m| m.modified()).ok())
+        };
+        let mut last_modified: [Option<SystemTime>; 3] = modified();
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+        loop {
+            interval.tick().await;
+            let current = modified();
+            if current == last_modified {
+                continue;
+            }
+            last_modified = current;
+            match self::acceptor(&config) {
+                Ok(new) => {
+                    *acceptor.write().unwrap() = new;
+                    info!("Admin TLS certificates reloaded");
+                }
+                Err(e) => error!("Failed to reload admin TLS certificates: {}", e),
+            }
+        }
+    });
+}
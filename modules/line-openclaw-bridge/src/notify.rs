@@ -0,0 +1,270 @@
+//! 推播通知模組
+//! 以 multicast 分批推送給指定使用者；一般通知批次間保持間隔，緊急通知略過間隔，進度可透過 SSE 即時追蹤。
+//! 一般通知亦可先排入合併佇列，時間窗內同一使用者的通知合併為一則摘要送出
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+use crate::clock::Clock;
+use crate::delivery;
+use crate::i18n::I18n;
+use crate::line::{OutgoingMessage, TextMessage, MULTICAST_LIMIT};
+use crate::privacy;
+use crate::profiles;
+use crate::quota;
+use crate::store::AuditEvent;
+use crate::{AppState, SharedState};
+
+/// 遇到 429 / 5xx 時的重試次數（間隔約 1、2、4 秒）
+const MAX_RETRIES: u32 = 3;
+/// 重試等待時間的隨機調整比例
+const RETRY_JITTER: f64 = 0.2;
+/// 保留在記憶體中的通知工作數
+const MAX_JOBS: usize = 50;
+/// 進度中保留的錯誤訊息數
+const MAX_ERRORS: usize = 10;
+/// 檢查合併佇列的間隔
+const DIGEST_INTERVAL: Duration = Duration::from_secs(30);
+/// LINE 文字訊息長度上限
+const TEXT_LIMIT: usize = 5000;
+/// 單次推送的訊息數上限
+const PUSH_LIMIT: usize = 5;
+
+/// 通知工作的進度
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+    pub id: u64,
+    pub urgent: bool,
+    pub recipients: usize,
+    pub sent: usize,
+    pub failed: usize,
+    pub batches: usize,
+    pub batches_done: usize,
+    pub done: bool,
+    pub errors: Vec<String>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+/// 進行中與最近完成的通知工作
+pub struct Notifications {
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<u64, watch::Receiver<Progress>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Notifications {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self { next_id: AtomicU64::new(0), jobs: Mutex::default(), clock }
+    }
+
+    /// 建立通知工作並在背景發送，回傳初始進度
+    pub fn start(
+        &self,
+        state: SharedState,
+        mut recipients: Vec<String>,
+        messages: Vec<OutgoingMessage>,
+        urgent: bool,
+        pacing: Duration,
+    ) -> Progress {
+        recipients.sort();
+        recipients.dedup();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let progress = Progress {
+            id,
+            urgent,
+            recipients: recipients.len(),
+            sent: 0,
+            failed: 0,
+            batches: recipients.len().div_ceil(MULTICAST_LIMIT),
+            batches_done: 0,
+            done: false,
+            errors: Vec::new(),
+            started_at: self.clock.timestamp(),
+            finished_at: None,
+        };
+        let (sender, receiver) = watch::channel(progress.clone());
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.insert(id, receiver);
+            // 只移除已完成的通知，發送中的通知仍可查詢進度
+            let excess = jobs.len().saturating_sub(MAX_JOBS);
+            let finished: Vec<u64> = jobs.iter().filter(|(_, p)| p.borrow().done).map(|(id, _)| *id).take(excess).collect();
+            for id in finished {
+                jobs.remove(&id);
+            }
+        }
+
+        info!("Notification {} started: {} recipients, urgent={}", id, recipients.len(), urgent);
+        let clock = self.clock.clone();
+        tokio::spawn(async move {
+            for (index, batch) in recipients.chunks(MULTICAST_LIMIT).enumerate() {
+                // 一般通知在批次之間保持間隔；緊急通知立即送出下一批
+                if index > 0 && !urgent {
+                    tokio::time::sleep(pacing).await;
+                }
+                let result = {
+                    let state = state.read().await;
+                    send_batch(&state, batch, &messages).await
+                };
+                sender.send_modify(|p| {
+                    p.batches_done += 1;
+                    match &result {
+                        Ok(()) => p.sent += batch.len(),
+                        Err(e) => {
+                            p.failed += batch.len();
+                            if p.errors.len() < MAX_ERRORS {
+                                p.errors.push(e.clone());
+                            }
+                        }
+                    }
+                });
+            }
+            sender.send_modify(|p| {
+                p.done = true;
+                p.finished_at = Some(clock.timestamp());
+            });
+
+            let progress = sender.borrow().clone();
+            info!("Notification {} finished: {} sent, {} failed", id, progress.sent, progress.failed);
+            let detail = format!("id={} urgent={} sent={} failed={}", id, urgent, progress.sent, progress.failed);
+            let state = state.read().await;
+            if let Err(e) = state.store.audit(&AuditEvent {
+                user_id: "admin",
+                event_type: "admin",
+                action: "notify",
+                status: if progress.failed == 0 { "ok" } else { "error" },
+                detail: Some(&detail),
+                ..Default::default()
+            }) {
+                warn!("Failed to write audit log: {}", e);
+            }
+        });
+        progress
+    }
+
+    /// 目前進度
+    pub fn progress(&self, id: u64) -> Option<Progress> {
+        self.jobs.lock().unwrap().get(&id).map(|r| r.borrow().clone())
+    }
+
+    /// 訂閱進度變化（SSE 使用）
+    pub fn subscribe(&self, id: u64) -> Option<watch::Receiver<Progress>> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+}
+
+/// 送出一批；遇到 LINE 的 429 或 5xx 時退避重試，其他錯誤或本月額度用完時直接失敗。
+/// 重試沿用同一個 retry key，逾時但其實已送達的批次不會重複送出
+async fn send_batch(state: &AppState, batch: &[String], messages: &[OutgoingMessage]) -> Result<(), String> {
+    let retry_key = delivery::retry_key();
+    let mut attempt = 0;
+    loop {
+        if let Some(resets_at) = quota::exhausted(state) {
+            return Err(quota::exhausted_message(resets_at));
+        }
+        match state.line_client.multicast_with_retry_key(batch, messages, Some(&retry_key)).await {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                let retryable = e
+                    .status()
+                    .map_or(e.is_connect() || e.is_timeout(), |s| s.as_u16() == 429 || s.is_server_error());
+                if !retryable || attempt >= MAX_RETRIES {
+                    return Err(e.to_string());
+                }
+                warn!("Multicast failed, retrying: {}", e);
+                tokio::time::sleep(state.random.jitter(Duration::from_secs(1 << attempt), RETRY_JITTER)).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// 在背景定期將到期的合併佇列整理成摘要推送給各使用者
+pub fn spawn(state: SharedState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DIGEST_INTERVAL);
+        loop {
+            interval.tick().await;
+            let state = state.read().await;
+            // 額度用完時摘要留在佇列中，重置後再送
+            if quota::exhausted(&state).is_some() {
+                continue;
+            }
+            let users = match state.store.due_notification_users(state.clock.timestamp()) {
+                Ok(users) => users,
+                Err(e) => {
+                    error!("Failed to load due notifications: {}", e);
+                    continue;
+                }
+            };
+            for user_id in users {
+                // 先取出再推送，避免推送失敗時下一輪重複送出
+                let items = match state.store.take_queued_notifications(&user_id) {
+                    Ok(items) if !items.is_empty() => items,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        error!("Failed to take queued notifications for {}: {}", privacy::id(&user_id), e);
+                        continue;
+                    }
+                };
+                let texts = digest_texts(&state.i18n, &items);
+                let mut status = "ok";
+                for chunk in texts.chunks(PUSH_LIMIT) {
+                    let messages: Vec<OutgoingMessage> = chunk.iter().map(|t| TextMessage::new(t.clone()).into()).collect();
+                    if let Err(e) = delivery::push(&state, &user_id, &messages).await {
+                        warn!("Failed to push notification digest to {}: {}", privacy::id(&user_id), e);
+                        profiles::record_push_failure(&state, &user_id, &e);
+                        status = "error";
+                        break;
+                    }
+                }
+                let detail = format!("digest items={}", items.len());
+                if let Err(e) = state.store.audit(&AuditEvent {
+                    user_id: &user_id,
+                    event_type: "admin",
+                    action: "notify",
+                    status,
+                    detail: Some(&detail),
+                    ..Default::default()
+                }) {
+                    warn!("Failed to write audit log: {}", e);
+                }
+            }
+        }
+    });
+}
+
+/// 摘要文字：單則通知原樣送出；多則時加上標題並逐條列出，超過 LINE 長度上限時拆成多則
+fn digest_texts(i18n: &I18n, items: &[String]) -> Vec<String> {
+    if let [item] = items {
+        return vec![truncate(item, TEXT_LIMIT)];
+    }
+    let mut texts = Vec::new();
+    let mut current = i18n.text_default("notify.digest", &[("count", &items.len().to_string())]);
+    for item in items {
+        let entry = truncate(&format!("• {}", item), TEXT_LIMIT);
+        if current.chars().count() + 2 + entry.chars().count() > TEXT_LIMIT {
+            texts.push(std::mem::take(&mut current));
+        } else if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(&entry);
+    }
+    texts.push(current);
+    texts
+}
+
+fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(limit - 1).collect();
+    truncated.push('…');
+    truncated
+}
@@ -0,0 +1,204 @@
+//! 對外 HTTP 請求模組
+//! 提供呼叫固定服務（Open-Meteo、Vault、AWS、檔案 webhook）的共用客戶端；抓取使用者提供的網址（行事曆、RSS）時，
+//! 每一跳（含重新導向）都先解析主機並拒絕迴路、私有、鏈路本地等非公開位址，再固定連線到檢查過的位址，並限制回應大小，避免 SSRF
+
+use reqwest::header::{HeaderMap, LOCATION};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Response, Url};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// 對外請求的 User-Agent
+pub const USER_AGENT: &str = concat!("line-openclaw-bridge/", env!("CARGO_PKG_VERSION"));
+/// 預設逾時
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// 使用者提供的網址最多跟隨的重新導向次數
+const MAX_REDIRECTS: usize = 5;
+
+/// 呼叫固定服務用的共用客戶端（10 秒逾時；個別請求可另設逾時）
+pub fn client() -> &'static Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT.get_or_init(|| Client::builder().timeout(TIMEOUT).user_agent(USER_AGENT).build().unwrap_or_default())
+}
+
+/// 檢查使用者提供的網址：限 http(s)，主機為 IP 時須為公開位址（主機名稱於連線前解析檢查）
+pub fn check_url(url: &str) -> Result<Url, String> {
+    let url = Url::parse(url.trim()).map_err(|e| format!("網址格式錯誤: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("只接受 http(s) 網址：{}", url.scheme()));
+    }
+    match literal_ip(&url) {
+        _ if url.host_str().is_none() => Err("網址缺少主機".to_string()),
+        Some(ip) if !is_public(ip) => Err(format!("不允許連線到非公開位址 {}", ip)),
+        _ => Ok(url),
+    }
+}
+
+/// 網址主機為 IP 時回傳該位址（IPv6 去除方括號）
+fn literal_ip(url: &Url) -> Option<IpAddr> {
+    url.host_str()?.trim_start_matches('[').trim_end_matches(']').parse().ok()
+}
+
+/// 對使用者提供的網址送出 GET；重新導向逐跳檢查，回應為最後一跳的結果
+pub async fn get_public(url: &str, headers: HeaderMap) -> Result<Response, String> {
+    let mut url = check_url(url)?;
+    for _ in 0..=MAX_REDIRECTS {
+        let mut builder = Client::builder().timeout(TIMEOUT).user_agent(USER_AGENT).redirect(Policy::none()).no_proxy();
+        if let (Some(domain), None) = (url.host_str(), literal_ip(&url)) {
+            builder = builder.resolve_to_addrs(domain, &public_addrs(domain, &url).await?);
+        }
+        let client = builder.build().map_err(|e| e.to_string())?;
+        let response = client.get(url.clone()).headers(headers.clone()).send().await.map_err(|e| e.to_string())?;
+        let location = response.headers().get(LOCATION).and_then(|v| v.to_str().ok());
+        match location {
+            Some(location) if response.status().is_redirection() => {
+                let next = url.join(location).map_err(|e| format!("重新導向網址格式錯誤: {}", e))?;
+                url = check_url(next.as_str())?;
+            }
+            _ => return Ok(response),
+        }
+    }
+    Err(format!("重新導向超過 {} 次", MAX_REDIRECTS))
+}
+
+/// 解析主機名稱；任何一個位址不是公開位址就拒絕（避免 DNS 同時回傳內部位址）
+async fn public_addrs(domain: &str, url: &Url) -> Result<Vec<SocketAddr>, String> {
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((domain, port))
+        .await
+        .map_err(|e| format!("無法解析 {}: {}", domain, e))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("無法解析 {}", domain));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+        return Err(format!("{} 解析為非公開位址 {}", domain, addr.ip()));
+    }
+    Ok(addrs)
+}
+
+/// 讀取回應內容；超過 `limit` 位元組時回傳錯誤，不把整個內容讀進記憶體
+pub async fn read_limited(mut response: Response, limit: usize) -> Result<String, String> {
+    let too_large = || format!("回應超過 {} KB", limit / 1024);
+    if response.content_length().is_some_and(|length| length > limit as u64) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// 是否為可從網際網路連線的位址（排除迴路、私有、鏈路本地、CGNAT、保留與多播位址）
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        || a >= 240
+        // 100.64.0.0/10（CGNAT）與 192.0.0.0/24（IETF 保留）
+        || (a == 100 && (64..128).contains(&b))
+        || ip.octets()[..3] == [192, 0, 0]
+        // 198.18.0.0/15（效能測試）
+        || (a == 198 && (18..20).contains(&b)))
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        // 2001:db8::/32（文件用）
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8)
+        // 64:ff9b::/96 等 NAT64 前綴可能轉到內部 IPv4
+        || first == 0x0064)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn public_addresses() {
+        for text in ["8.8.8.8", "1.1.1.1", "203.0.114.1", "2606:4700:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(is_public(ip(text)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn non_public_addresses() {
+        for text in [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "192.0.0.8",
+            "198.18.0.1",
+            "203.0.113.5",
+            "224.0.0.1",
+            "240.0.0.1",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "fc00::1",
+            "fe80::1",
+            "2001:db8::1",
+            "64:ff9b::a00:1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(!is_public(ip(text)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn check_url_accepts_public_http() {
+        assert!(check_url("https://example.com/feed.xml").is_ok());
+        assert!(check_url(" http://8.8.8.8/calendar.ics ").is_ok());
+        assert!(check_url("https://[2606:4700:4700::1111]/").is_ok());
+    }
+
+    #[test]
+    fn check_url_rejects_other_schemes_and_private_hosts() {
+        for url in [
+            "ftp://example.com/file",
+            "file:///etc/passwd",
+            "not a url",
+            "http://127.0.0.1:8080/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]/",
+            "http://[fe80::1]/",
+        ] {
+            assert!(check_url(url).is_err(), "{}", url);
+        }
+    }
+}
@@ -0,0 +1,429 @@
+//! 訊息處理追蹤模組
+//! 記錄每個 webhook 事件在各階段（驗證 → 去重 → 過濾 → 頻率限制 → LLM → 後處理 → 送出）花費的時間，
+//! 最近的紀錄保留在記憶體中，管理 API 以 JSON 或瀑布圖 HTML 呈現，用來找出變慢或沒有回覆的訊息；
+//! 流量大時可只抽樣保留部分正常事件（失敗與過慢的事件一律保留）
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::i18n::{I18n, Locale};
+use crate::liff;
+use crate::line::Event;
+use crate::markdown::escape;
+use crate::random::Random;
+
+/// 保留在記憶體中的追蹤紀錄數
+const MAX_TRACES: usize = 200;
+/// 去重時記住的最近事件 ID 數
+const RECENT_IDS: usize = 256;
+
+tokio::task_local! {
+    /// 目前處理中事件的追蹤紀錄
+    static CURRENT: Arc<Mutex<Trace>>;
+}
+
+/// 單一事件的處理紀錄
+#[derive(Debug, Clone, Serialize)]
+pub struct Trace {
+    pub id: u64,
+    /// 事件種類（`message:text`、`postback`、`follow` 等）
+    pub event: String,
+    /// 使用者（設定 `PRIVACY_HASH_KEY` 時為雜湊）
+    pub user: Option<String>,
+    /// 收到 webhook 的時間（Unix 毫秒）
+    pub received_at: i64,
+    /// 從收到 webhook 到處理完成的毫秒數
+    pub total_ms: f64,
+    /// 送出階段的結果（`reply`、`push_fallback`、`dead_letter`），或 `duplicate`、`ignored`、`cancelled`、`no_reply`
+    pub outcome: String,
+    /// 保留的原因：`error`（失敗或備援）、`slow`（超過門檻）或 `sampled`（依比例抽樣）
+    pub kept_as: &'static str,
+    pub stages: Vec<Stage>,
+    #[serde(skip)]
+    received: Instant,
+}
+
+/// 處理階段
+#[derive(Debug, Clone, Serialize)]
+pub struct Stage {
+    /// `verify`、`dedup`、`filter`、`rate_limit`、`llm`、`post_process` 或 `deliver`
+    pub name: &'static str,
+    /// 相對於收到 webhook 的開始時間（毫秒）
+    pub start_ms: f64,
+    pub duration_ms: f64,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// 視為失敗的結果與階段狀態，不論抽樣比例一律保留
+const ERROR_STATUSES: [&str; 3] = ["push_fallback", "dead_letter", "fallback"];
+
+/// 抽樣設定
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Sampling {
+    /// 正常事件的保留比例（0–100%）
+    pub percent: u8,
+    /// 總耗時達此毫秒數的事件一律保留（0 代表不以耗時判斷）
+    pub slow_ms: u64,
+}
+
+/// 管理 API 調整抽樣設定時只需提供要變更的欄位
+#[derive(Debug, Default, Deserialize)]
+pub struct SamplingUpdate {
+    pub percent: Option<u8>,
+    pub slow_ms: Option<u64>,
+}
+
+/// 抽樣設定與統計（管理 API）
+#[derive(Debug, Serialize)]
+pub struct SamplingReport {
+    #[serde(flatten)]
+    pub sampling: Sampling,
+    /// 啟動後處理的事件數
+    pub seen: u64,
+    /// 因抽樣而未保留的事件數
+    pub dropped: u64,
+}
+
+/// 最近的追蹤紀錄
+pub struct Traces {
+    next_id: AtomicU64,
+    recent: Mutex<VecDeque<Trace>>,
+    sampling: RwLock<Sampling>,
+    dropped: AtomicU64,
+    random: Arc<Random>,
+}
+
+impl Traces {
+    pub fn new(sampling: Sampling, random: Arc<Random>) -> Self {
+        Self {
+            next_id: AtomicU64::new(0),
+            recent: Mutex::new(VecDeque::new()),
+            sampling: RwLock::new(sampling),
+            dropped: AtomicU64::new(0),
+            random,
+        }
+    }
+
+    /// 執行中調整抽樣設定（不寫回環境變數，重新啟動後恢復 `TRACE_SAMPLE_PERCENT`／`TRACE_SLOW_MS`）
+    pub fn update_sampling(&self, update: SamplingUpdate) -> Result<Sampling, String> {
+        if update.percent.is_some_and(|percent| percent > 100) {
+            return Err("percent 必須介於 0 到 100".to_string());
+        }
+        let mut sampling = self.sampling.write().unwrap();
+        if let Some(percent) = update.percent {
+            sampling.percent = percent;
+        }
+        if let Some(slow_ms) = update.slow_ms {
+            sampling.slow_ms = slow_ms;
+        }
+        Ok(*sampling)
+    }
+
+    pub fn sampling(&self) -> SamplingReport {
+        SamplingReport {
+            sampling: *self.sampling.read().unwrap(),
+            seen: self.next_id.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 保留的原因；依比例未抽中時為 None。抽樣取自共用的亂數來源，設定 `RANDOM_SEED` 時結果可重現
+    fn keep(&self, trace: &Trace) -> Option<&'static str> {
+        let sampling = *self.sampling.read().unwrap();
+        let failed = ERROR_STATUSES.contains(&trace.outcome.as_str())
+            || trace.stages.iter().any(|stage| ERROR_STATUSES.contains(&stage.status.as_str()));
+        if failed {
+            Some("error")
+        } else if sampling.slow_ms > 0 && trace.total_ms >= sampling.slow_ms as f64 {
+            Some("slow")
+        } else if self.random.next_f64() * 100.0 < sampling.percent as f64 {
+            Some("sampled")
+        } else {
+            None
+        }
+    }
+
+    /// 在追蹤範圍內處理一個事件；`received` 為收到 webhook、`verified` 為簽章驗證完成的時間
+    pub async fn run<F: Future>(
+        &self,
+        event: String,
+        user: Option<String>,
+        received: Instant,
+        verified: Instant,
+        future: F,
+    ) -> F::Output {
+        let received_at = chrono::Utc::now().timestamp_millis() - received.elapsed().as_millis() as i64;
+        let trace = Arc::new(Mutex::new(Trace {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            event,
+            user,
+            received_at,
+            total_ms: 0.0,
+            outcome: String::new(),
+            kept_as: "sampled",
+            stages: vec![Stage {
+                name: "verify",
+                start_ms: 0.0,
+                duration_ms: millis(verified.saturating_duration_since(received)),
+                status: "ok".to_string(),
+                detail: None,
+            }],
+            received,
+        }));
+        let output = CURRENT.scope(trace.clone(), future).await;
+
+        let mut trace = trace.lock().unwrap().clone();
+        trace.total_ms = millis(received.elapsed());
+        if trace.outcome.is_empty() {
+            trace.outcome = trace
+                .stages
+                .iter()
+                .rev()
+                .find(|stage| stage.name == "deliver")
+                .map_or_else(|| "no_reply".to_string(), |stage| stage.status.clone());
+        }
+        match self.keep(&trace) {
+            Some(reason) => trace.kept_as = reason,
+            None => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return output;
+            }
+        }
+        let mut recent = self.recent.lock().unwrap();
+        recent.push_back(trace);
+        while recent.len() > MAX_TRACES {
+            recent.pop_front();
+        }
+        output
+    }
+
+    /// 最近的紀錄（新的在前）
+    pub fn list(&self) -> Vec<Trace> {
+        self.recent.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<Trace> {
+        self.recent.lock().unwrap().iter().find(|trace| trace.id == id).cloned()
+    }
+}
+
+/// 最近處理過的事件或訊息 ID；LINE 與 WhatsApp 沒收到 200 時會重送同一事件，去重階段以此略過
+#[derive(Default)]
+pub struct RecentIds(Mutex<VecDeque<String>>);
+
+impl RecentIds {
+    /// 第一次看到此 ID 時回傳 true；只記住最近 [`RECENT_IDS`] 個
+    pub fn first_seen(&self, id: &str) -> bool {
+        let mut recent = self.0.lock().unwrap();
+        if recent.iter().any(|seen| seen == id) {
+            return false;
+        }
+        if recent.len() >= RECENT_IDS {
+            recent.pop_front();
+        }
+        recent.push_back(id.to_string());
+        true
+    }
+}
+
+/// 事件種類與使用者
+pub fn describe(event: &Event) -> (String, Option<&str>) {
+    match event {
+        Event::Message(event) => (format!("message:{}", event.message.message_type), event.source.user_id.as_deref()),
+        Event::Postback(event) => ("postback".to_string(), event.source.user_id.as_deref()),
+        Event::Follow(event) => ("follow".to_string(), event.source.user_id.as_deref()),
+        Event::Unfollow(event) => ("unfollow".to_string(), event.source.user_id.as_deref()),
+        Event::Unknown => ("unknown".to_string(), None),
+    }
+}
+
+/// 記錄從 `started` 到現在的一個階段；不在追蹤範圍內（例如管理 API 的測試訊息）時不做任何事
+pub fn record(name: &'static str, started: Instant, status: &str, detail: Option<String>) {
+    let _ = CURRENT.try_with(|trace| {
+        let mut trace = trace.lock().unwrap();
+        let start_ms = millis(started.saturating_duration_since(trace.received));
+        trace.stages.push(Stage {
+            name,
+            start_ms,
+            duration_ms: millis(started.elapsed()),
+            status: status.to_string(),
+            detail,
+        });
+    });
+}
+
+/// 指定處理結果（未指定時取送出階段的結果）
+pub fn outcome(outcome: &str) {
+    let _ = CURRENT.try_with(|trace| trace.lock().unwrap().outcome = outcome.to_string());
+}
+
+fn millis(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1000.0 * 10.0).round() / 10.0
+}
+
+/// 以瀑布圖呈現一筆紀錄
+pub fn render_html(i18n: &I18n, locale: Locale, trace: &Trace) -> String {
+    let t = |key: &str| escape(&i18n.text(locale, &format!("trace.{}", key), &[]));
+    let total = trace.total_ms.max(1.0);
+    let mut rows = String::new();
+    for stage in &trace.stages {
+        let left = stage.start_ms / total * 100.0;
+        let width = (stage.duration_ms / total * 100.0).max(0.5);
+        let color = if matches!(stage.status.as_str(), "ok" | "reply") { "#06c755" } else { "#e0a800" };
+        let _ = write!(
+            rows,
+            r#"<tr><td>{name}</td><td>{status}</td><td style="text-align:right">{start:.1}</td><td style="text-align:right">{duration:.1}</td>
+<td style="width:50%"><div style="position:relative;height:14px;background:#eee"><div style="position:absolute;left:{left:.2}%;width:{width:.2}%;height:100%;background:{color}"></div></div></td>
+<td>{detail}</td></tr>
+"#,
+            name = stage.name,
+            status = escape(&stage.status),
+            start = stage.start_ms,
+            duration = stage.duration_ms,
+            detail = escape(stage.detail.as_deref().unwrap_or("")),
+        );
+    }
+    let received_at = chrono::DateTime::from_timestamp_millis(trace.received_at)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S%.3f UTC").to_string())
+        .unwrap_or_default();
+    let title = i18n.text(locale, "trace.title", &[("id", &trace.id.to_string())]);
+    let body = format!(
+        r#"<article>
+<h2>{heading}</h2>
+<p>{user_label}{user}<br>{received_label}{received_at}<br>{total_label}{total:.1} ms<br>{outcome_label}{outcome}</p>
+<table style="width:100%;border-collapse:collapse;font-size:13px">
+<tr><th>{stage}</th><th>{status}</th><th>{start}</th><th>{duration}</th><th></th><th>{detail}</th></tr>
+{rows}</table>
+</article>"#,
+        heading = escape(&i18n.text(locale, "trace.heading", &[("id", &trace.id.to_string()), ("event", &trace.event)])),
+        user_label = t("user"),
+        user = escape(trace.user.as_deref().unwrap_or("-")),
+        received_label = t("received_at"),
+        total_label = t("total"),
+        total = trace.total_ms,
+        outcome_label = t("outcome"),
+        outcome = escape(&trace.outcome),
+        stage = t("stage"),
+        status = t("status"),
+        start = t("start"),
+        duration = t("duration"),
+        detail = t("detail"),
+    );
+    liff::page(locale, &title, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn traces(percent: u8, slow_ms: u64) -> Traces {
+        Traces::new(Sampling { percent, slow_ms }, Arc::new(Random::new(Some(1))))
+    }
+
+    fn trace(outcome: &str, total_ms: f64) -> Trace {
+        Trace {
+            id: 1,
+            event: "message:text".to_string(),
+            user: None,
+            received_at: 0,
+            total_ms,
+            outcome: outcome.to_string(),
+            kept_as: "sampled",
+            stages: Vec::new(),
+            received: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn failed_and_slow_events_are_always_kept() {
+        let traces = traces(0, 1000);
+        assert_eq!(traces.keep(&trace("dead_letter", 5.0)), Some("error"));
+        assert_eq!(traces.keep(&trace("reply", 1500.0)), Some("slow"));
+        assert_eq!(traces.keep(&trace("reply", 5.0)), None);
+        let mut fallback = trace("reply", 5.0);
+        fallback.stages.push(Stage { name: "llm", start_ms: 0.0, duration_ms: 1.0, status: "fallback".to_string(), detail: None });
+        assert_eq!(traces.keep(&fallback), Some("error"));
+    }
+
+    #[test]
+    fn sampling_follows_the_percentage() {
+        assert!((0..100).all(|_| traces(100, 0).keep(&trace("reply", 5.0)) == Some("sampled")));
+        let traces = traces(20, 0);
+        let kept = (0..1000).filter(|_| traces.keep(&trace("reply", 5.0)).is_some()).count();
+        assert!((120..=280).contains(&kept), "kept {}", kept);
+    }
+
+    #[test]
+    fn sampling_update_rejects_out_of_range_percent() {
+        let traces = traces(10, 0);
+        assert!(traces.update_sampling(SamplingUpdate { percent: Some(101), slow_ms: None }).is_err());
+        let updated = traces.update_sampling(SamplingUpdate { percent: None, slow_ms: Some(500) }).unwrap();
+        assert_eq!((updated.percent, updated.slow_ms), (10, 500));
+    }
+
+    #[tokio::test]
+    async fn run_records_stages_and_outcome() {
+        let traces = traces(100, 0);
+        let now = Instant::now();
+        traces
+            .run("message:text".to_string(), None, now, now, async {
+                record("dedup", Instant::now(), "ok", None);
+                record("deliver", Instant::now(), "reply", Some("messages=1".to_string()));
+            })
+            .await;
+        traces.run("follow".to_string(), None, now, now, async {}).await;
+        let recent = traces.list();
+        assert_eq!(recent[0].outcome, "no_reply");
+        assert_eq!(recent[1].outcome, "reply");
+        let stages: Vec<_> = recent[1].stages.iter().map(|stage| stage.name).collect();
+        assert_eq!(stages, ["verify", "dedup", "deliver"]);
+        assert_eq!(traces.get(recent[1].id).unwrap().event, "message:text");
+    }
+
+    #[tokio::test]
+    async fn dropped_events_are_counted() {
+        let traces = traces(0, 0);
+        let now = Instant::now();
+        traces.run("follow".to_string(), None, now, now, async {}).await;
+        assert!(traces.list().is_empty());
+        let report = traces.sampling();
+        assert_eq!((report.seen, report.dropped), (1, 1));
+    }
+
+    #[test]
+    fn redelivered_ids_are_skipped() {
+        let recent = RecentIds::default();
+        assert!(recent.first_seen("event-1"));
+        assert!(!recent.first_seen("event-1"));
+        assert!(recent.first_seen("event-2"));
+    }
+
+    #[test]
+    fn oldest_id_is_forgotten() {
+        let recent = RecentIds::default();
+        for i in 0..=RECENT_IDS {
+            assert!(recent.first_seen(&format!("event-{}", i)));
+        }
+        assert!(recent.first_seen("event-0"));
+        assert!(!recent.first_seen(&format!("event-{}", RECENT_IDS)));
+    }
+
+    #[test]
+    fn html_uses_the_locale_and_escapes_fields() {
+        let i18n = I18n::load(Locale::ZhTw, None).unwrap();
+        let mut trace = trace("reply", 12.0);
+        trace.event = "<script>".to_string();
+        let html = render_html(&i18n, Locale::En, &trace);
+        assert!(html.contains("Event #1"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("總耗時"));
+        assert!(render_html(&i18n, Locale::ZhTw, &trace).contains("總耗時"));
+    }
+}
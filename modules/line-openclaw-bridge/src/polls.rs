@@ -0,0 +1,277 @@
+//! 投票模組
+//! `/poll` 在聊天中發起投票（Flex 按鈕 + postback），每人限投一票，於 `/poll close` 或截止時公布結果
+
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::i18n::Locale;
+use crate::line::{Action, Bubble, ButtonStyle, FlexBox, FlexButton, FlexComponent, FlexMessage, FlexText, OutgoingMessage, TextMessage};
+use crate::privacy;
+use crate::store::Poll;
+use crate::{AppState, SharedState};
+
+/// postback 資料前綴
+const POSTBACK_PREFIX: &str = "poll:vote:";
+/// 選項數量上限
+const MAX_OPTIONS: usize = 10;
+/// 按鈕文字長度上限（LINE 限制 40 字）
+const MAX_LABEL_CHARS: usize = 40;
+/// 投票期限上限（一年）
+const MAX_DURATION: Duration = Duration::from_secs(365 * 86_400);
+/// 檢查截止投票的間隔
+const DEADLINE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 使用者點選的選項
+#[derive(Debug, PartialEq)]
+pub struct Vote {
+    pub poll_id: i64,
+    pub option: usize,
+}
+
+/// 處理 `/poll` 指令
+pub fn handle_command(state: &AppState, chat_id: &str, user_id: &str, locale: Locale, args: Vec<String>) -> Vec<OutgoingMessage> {
+    let text = |key: &str| vec![TextMessage::new(state.i18n.text(locale, key, &[])).into()];
+    if !state.flags.is_enabled("poll_command", Some(user_id), true) {
+        return text("command.unavailable");
+    }
+    if args.len() == 1 && args[0].eq_ignore_ascii_case("close") {
+        return close_command(state, chat_id, user_id, locale);
+    }
+
+    let Some((question, options, duration)) = parse_args(args) else { return text("poll.usage") };
+    match state.store.open_poll(chat_id) {
+        Ok(Some(_)) => return text("poll.already_open"),
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to look up open poll: {}", e);
+            return text("poll.error");
+        }
+    }
+
+    let deadline = duration.map(|d| state.clock.timestamp() + d.as_secs() as i64);
+    match state.store.create_poll(chat_id, user_id, &question, &options, deadline) {
+        Ok(id) => {
+            info!("Poll {} created in {}", id, privacy::id(chat_id));
+            vec![poll_message(state, locale, id, &question, &options, deadline)]
+        }
+        Err(e) => {
+            error!("Failed to create poll: {}", e);
+            text("poll.error")
+        }
+    }
+}
+
+/// 解析 `"問題" 選項1 選項2 ... [--for 30m]`
+fn parse_args(mut args: Vec<String>) -> Option<(String, Vec<String>, Option<Duration>)> {
+    let mut duration = None;
+    if let Some(index) = args.iter().position(|a| a == "--for") {
+        duration = Some(parse_duration(args.get(index + 1)?)?);
+        args.drain(index..index + 2);
+    }
+    let mut args = args.into_iter();
+    let question = args.next().filter(|q| !q.trim().is_empty())?;
+    let options: Vec<String> = args.filter(|o| !o.trim().is_empty()).collect();
+    if options.len() < 2 || options.len() > MAX_OPTIONS {
+        return None;
+    }
+    Some((question, options, duration))
+}
+
+/// `30m`、`2h`、`1d`；超過 MAX_DURATION 或溢位時回傳 None
+fn parse_duration(text: &str) -> Option<Duration> {
+    let unit = text.chars().last()?;
+    let number: u64 = text[..text.len() - unit.len_utf8()].parse().ok().filter(|n| *n > 0)?;
+    let secs = match unit {
+        'm' => number.checked_mul(60)?,
+        'h' => number.checked_mul(3600)?,
+        'd' => number.checked_mul(86_400)?,
+        _ => return None,
+    };
+    Some(Duration::from_secs(secs)).filter(|duration| *duration <= MAX_DURATION)
+}
+
+/// 投票 Flex bubble（每個選項一個按鈕）
+fn poll_message(state: &AppState, locale: Locale, id: i64, question: &str, options: &[String], deadline: Option<i64>) -> OutgoingMessage {
+    let buttons: Vec<FlexComponent> = options
+        .iter()
+        .enumerate()
+        .map(|(index, option)| {
+            let label: String = option.chars().take(MAX_LABEL_CHARS).collect();
+            let action = Action::postback_with_text(label, format!("{}{}:{}", POSTBACK_PREFIX, id, index), option.as_str());
+            FlexButton::new(action).style(ButtonStyle::Secondary).height("sm").margin("sm").into()
+        })
+        .collect();
+    let note = match deadline.and_then(|d| chrono::DateTime::from_timestamp(d, 0)) {
+        Some(deadline) => state.i18n.text(locale, "poll.deadline", &[(
+            "time",
+            &deadline.with_timezone(&chrono::Local).format("%m-%d %H:%M").to_string(),
+        )]),
+        None => state.i18n.text(locale, "poll.no_deadline", &[]),
+    };
+
+    let bubble = Bubble::new()
+        .header(FlexBox::vertical(vec![
+            FlexText::new(state.i18n.text(locale, "poll.title", &[])).size("xs").color("#06C755").bold().into(),
+            FlexText::new(question).wrap().bold().size("md").into(),
+        ]))
+        .body(FlexBox::vertical(buttons))
+        .footer(FlexBox::vertical(vec![FlexText::new(note).size("xxs").color("#999999").wrap().into()]));
+    FlexMessage::new(state.i18n.text(locale, "poll.alt", &[("question", question)]), bubble).into()
+}
+
+/// `/poll close`：僅發起人或管理員可結束
+fn close_command(state: &AppState, chat_id: &str, user_id: &str, locale: Locale) -> Vec<OutgoingMessage> {
+    let text = |key: &str| vec![TextMessage::new(state.i18n.text(locale, key, &[])).into()];
+    let poll = match state.store.open_poll(chat_id) {
+        Ok(Some(poll)) => poll,
+        Ok(None) => return text("poll.none_open"),
+        Err(e) => {
+            error!("Failed to look up open poll: {}", e);
+            return text("poll.error");
+        }
+    };
+    if poll.creator_id != user_id && state.config.admin_line_user_id.as_deref() != Some(user_id) {
+        return text("poll.creator_only");
+    }
+    match state.store.close_poll(poll.id) {
+        Ok(true) => vec![TextMessage::new(results(state, locale, &poll)).into()],
+        Ok(false) => text("poll.none_open"),
+        Err(e) => {
+            error!("Failed to close poll {}: {}", poll.id, e);
+            text("poll.error")
+        }
+    }
+}
+
+/// 解析投票按鈕的 postback 資料
+pub fn parse_postback(data: &str) -> Option<Vote> {
+    let (poll_id, option) = data.strip_prefix(POSTBACK_PREFIX)?.split_once(':')?;
+    Some(Vote {
+        poll_id: poll_id.parse().ok()?,
+        option: option.parse().ok()?,
+    })
+}
+
+/// 記錄投票，回傳要回覆給投票者的文字
+pub fn handle_vote(state: &AppState, user_id: &str, locale: Locale, vote: Vote) -> String {
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    let poll = match state.store.get_poll(vote.poll_id) {
+        Ok(Some(poll)) => poll,
+        Ok(None) => return t("poll.not_found"),
+        Err(e) => {
+            error!("Failed to load poll {}: {}", vote.poll_id, e);
+            return t("poll.error");
+        }
+    };
+    let Some(option) = poll.options.get(vote.option) else { return t("poll.not_found") };
+    let expired = poll.deadline.is_some_and(|d| d <= state.clock.timestamp());
+    if poll.status != "open" || expired {
+        return t("poll.closed");
+    }
+
+    match state.store.vote(poll.id, user_id, vote.option) {
+        Ok(true) => state.i18n.text(locale, "poll.voted", &[("option", option)]),
+        Ok(false) => t("poll.already_voted"),
+        Err(e) => {
+            error!("Failed to record vote for poll {}: {}", poll.id, e);
+            t("poll.error")
+        }
+    }
+}
+
+/// 結果文字：各選項票數與比例
+fn results(state: &AppState, locale: Locale, poll: &Poll) -> String {
+    let tally = state.store.poll_tally(poll.id, poll.options.len()).unwrap_or_else(|e| {
+        error!("Failed to tally poll {}: {}", poll.id, e);
+        vec![0; poll.options.len()]
+    });
+    let total: u64 = tally.iter().sum();
+    let lines: Vec<String> = poll
+        .options
+        .iter()
+        .zip(&tally)
+        .map(|(option, count)| {
+            let percent = (count * 100).checked_div(total).unwrap_or(0);
+            format!("• {}：{}（{}%）", option, count, percent)
+        })
+        .collect();
+    format!(
+        "{}\n\n{}\n\n{}",
+        state.i18n.text(locale, "poll.results", &[("question", &poll.question)]),
+        lines.join("\n"),
+        state.i18n.text(locale, "poll.total", &[("total", &total.to_string())])
+    )
+}
+
+/// 在背景定期結束已截止的投票，並將結果推送到原聊天
+pub fn spawn(state: SharedState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DEADLINE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let state = state.read().await;
+            let polls = match state.store.due_polls(state.clock.timestamp()) {
+                Ok(polls) => polls,
+                Err(e) => {
+                    error!("Failed to load due polls: {}", e);
+                    continue;
+                }
+            };
+            for poll in polls {
+                match state.store.close_poll(poll.id) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(e) => {
+                        error!("Failed to close poll {}: {}", poll.id, e);
+                        continue;
+                    }
+                }
+                let text = results(&state, state.i18n.default_locale(), &poll);
+                match state.line_client.push_message(&poll.chat_id, &text).await {
+                    Ok(()) => info!("Poll {} closed at deadline", poll.id),
+                    Err(e) => warn!("Failed to push results of poll {}: {}", poll.id, e),
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("30m"), Some(Duration::from_secs(1800)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Some(Duration::from_secs(86_400)));
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("5s"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflow_and_out_of_range() {
+        assert_eq!(parse_duration("365d"), Some(MAX_DURATION));
+        assert_eq!(parse_duration("366d"), None);
+        assert_eq!(parse_duration(&format!("{}d", u64::MAX / 86_400 + 1)), None);
+        assert_eq!(parse_duration(&format!("{}m", u64::MAX)), None);
+        assert_eq!(parse_duration("99999999999999999999h"), None);
+    }
+
+    #[test]
+    fn parse_args_with_deadline() {
+        let (question, options, duration) = parse_args(args(&["午餐？", "麵", "飯", "--for", "30m"])).unwrap();
+        assert_eq!(question, "午餐？");
+        assert_eq!(options, ["麵", "飯"]);
+        assert_eq!(duration, Some(Duration::from_secs(1800)));
+        assert!(parse_args(args(&["午餐？", "麵", "飯", "--for", "9999999d"])).is_none());
+        assert!(parse_args(args(&["午餐？", "麵", "--for"])).is_none());
+        assert!(parse_args(args(&["午餐？", "麵"])).is_none());
+    }
+}
@@ -0,0 +1,237 @@
+//! 反向代理模組
+//! 只有直接連線的對象屬於 `TRUSTED_PROXIES` 時，才採用 `Forwarded`／`X-Forwarded-For` 中的用戶端 IP，
+//! 避免任何人自帶標頭偽造來源；解析結果以 `ClientIp` 放入請求 extension，供日誌等使用
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// IP 範圍（CIDR，單一位址視為 /32 或 /128）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, canonical(ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || format!("IP 範圍格式錯誤：{}", value);
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+        let network = canonical(address.trim().parse().map_err(|_| error())?);
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.trim().parse().ok().filter(|p| *p <= max).ok_or_else(error)?,
+            None => max,
+        };
+        Ok(Self { network, prefix })
+    }
+}
+
+impl std::fmt::Display for IpRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// 解析以逗號分隔的 IP 範圍清單
+pub fn parse_ranges(value: &str) -> Result<Vec<IpRange>, String> {
+    value.split(',').map(str::trim).filter(|range| !range.is_empty()).map(str::parse).collect()
+}
+
+/// 解析後的用戶端 IP
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+/// 在請求 extension 放入 `ClientIp`；沒有連線資訊（例如行程內直接呼叫路由）時不放入
+pub async fn client_ip(State(trusted): State<Arc<Vec<IpRange>>>, mut request: Request, next: Next) -> Response {
+    if let Some(ConnectInfo(peer)) = request.extensions().get::<ConnectInfo<SocketAddr>>().copied() {
+        let ip = resolve(peer.ip(), request.headers(), &trusted);
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+    next.run(request).await
+}
+
+/// 用戶端 IP：直接連線的對象不是受信任的代理時即為該對象；否則由轉送鏈最右邊往回找，
+/// 跳過受信任的代理，第一個不受信任的位址即為用戶端（無法解析的項目視為鏈的終點）
+pub fn resolve(peer: IpAddr, headers: &HeaderMap, trusted: &[IpRange]) -> IpAddr {
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|range| range.contains(ip));
+    let mut client = canonical(peer);
+    if !is_trusted(client) {
+        return client;
+    }
+    for hop in forwarded_chain(headers).into_iter().rev() {
+        let Some(ip) = hop else { break };
+        client = ip;
+        if !is_trusted(ip) {
+            break;
+        }
+    }
+    client
+}
+
+/// 轉送鏈中的位址（由左至右）；有 `Forwarded` 時以其為準，否則使用 `X-Forwarded-For`
+fn forwarded_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let values = |name: &str| -> Vec<String> {
+        headers.get_all(name).iter().filter_map(|v| v.to_str().ok()).map(str::to_string).collect()
+    };
+    let forwarded = values("forwarded");
+    if !forwarded.is_empty() {
+        return forwarded
+            .iter()
+            .flat_map(|value| value.split(','))
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node))
+            })
+            .collect();
+    }
+    values("x-forwarded-for").iter().flat_map(|value| value.split(',')).map(parse_node).collect()
+}
+
+/// 解析 `192.0.2.1`、`192.0.2.1:8080`、`"[2001:db8::1]:443"` 等格式的位址
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    let address = match node.strip_prefix('[') {
+        Some(rest) => rest.split(']').next()?,
+        None if node.matches(':').count() == 1 => node.split(':').next()?,
+        None => node,
+    };
+    address.parse().ok().map(canonical)
+}
+
+/// IPv4-mapped IPv6 位址（`::ffff:a.b.c.d`）視為 IPv4
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn range_parse_and_contains() {
+        let range: IpRange = "10.0.0.0/8".parse().unwrap();
+        assert!(range.contains(ip("10.1.2.3")));
+        assert!(range.contains(ip("::ffff:10.1.2.3")));
+        assert!(!range.contains(ip("11.0.0.1")));
+        assert!(!range.contains(ip("::1")));
+
+        let single: IpRange = "192.0.2.1".parse().unwrap();
+        assert_eq!(single.to_string(), "192.0.2.1/32");
+        assert!(single.contains(ip("192.0.2.1")));
+        assert!(!single.contains(ip("192.0.2.2")));
+
+        let v6: IpRange = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(ip("2001:db8:1::1")));
+        assert!(!v6.contains(ip("2001:db9::1")));
+
+        let all: IpRange = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains(ip("203.0.113.9")));
+    }
+
+    #[test]
+    fn range_rejects_invalid() {
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("::/129".parse::<IpRange>().is_err());
+        assert!("not-an-ip".parse::<IpRange>().is_err());
+        assert!("10.0.0.0/x".parse::<IpRange>().is_err());
+    }
+
+    #[test]
+    fn parse_range_list() {
+        let ranges = parse_ranges(" 10.0.0.0/8, ,::1 ").unwrap();
+        assert_eq!(ranges, vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()]);
+        assert!(parse_ranges("").unwrap().is_empty());
+        assert!(parse_ranges("10.0.0.0/8,bad").is_err());
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_headers() {
+        let trusted = parse_ranges("10.0.0.0/8").unwrap();
+        let headers = headers(&[("x-forwarded-for", "198.51.100.7")]);
+        assert_eq!(resolve(ip("203.0.113.9"), &headers, &trusted), ip("203.0.113.9"));
+    }
+
+    #[test]
+    fn trusted_peer_uses_x_forwarded_for() {
+        let trusted = parse_ranges("10.0.0.0/8").unwrap();
+        // 最右邊的受信任代理被跳過，偽造的最左邊項目不採用
+        let headers = headers(&[("x-forwarded-for", "1.1.1.1, 198.51.100.7, 10.0.0.2")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &headers, &trusted), ip("198.51.100.7"));
+        assert_eq!(resolve(ip("10.0.0.1"), &HeaderMap::new(), &trusted), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn trusted_peer_stops_at_unparsable_hop() {
+        let trusted = parse_ranges("10.0.0.0/8").unwrap();
+        let headers = headers(&[("x-forwarded-for", "198.51.100.7, unknown, 10.0.0.2")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &headers, &trusted), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn forwarded_takes_precedence() {
+        let trusted = parse_ranges("10.0.0.0/8").unwrap();
+        let headers = headers(&[
+            ("x-forwarded-for", "192.0.2.99"),
+            ("forwarded", "for=198.51.100.7;proto=https, For=\"[2001:db8::1]:443\""),
+        ]);
+        assert_eq!(resolve(ip("10.0.0.1"), &headers, &trusted), ip("2001:db8::1"));
+    }
+
+    #[test]
+    fn node_formats() {
+        assert_eq!(parse_node("192.0.2.1"), Some(ip("192.0.2.1")));
+        assert_eq!(parse_node(" 192.0.2.1:8080 "), Some(ip("192.0.2.1")));
+        assert_eq!(parse_node("\"[2001:db8::1]:443\""), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("2001:db8::1"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("::ffff:192.0.2.1"), Some(ip("192.0.2.1")));
+        assert_eq!(parse_node("unknown"), None);
+        assert_eq!(parse_node("_hidden"), None);
+    }
+}
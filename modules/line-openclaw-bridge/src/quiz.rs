@@ -0,0 +1,305 @@
+//! 問答遊戲模組
+//! `/quiz` 由 OpenClaw 以結構化輸出出題，題目以快速回覆作答，依聊天累計排行榜
+
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, info, warn};
+
+use crate::i18n::Locale;
+use crate::line::{Action, OutgoingMessage, TextMessage};
+use crate::privacy;
+use crate::profiles;
+use crate::reporting;
+use crate::store::{Quiz, QuizQuestion};
+use crate::AppState;
+
+/// postback 資料前綴
+const POSTBACK_PREFIX: &str = "quiz:";
+/// 每局題數
+const QUESTIONS_PER_QUIZ: usize = 5;
+/// 快速回覆按鈕文字長度上限（LINE 限制 20 字）
+const MAX_LABEL_CHARS: usize = 20;
+/// 排行榜顯示人數
+const LEADERBOARD_SIZE: usize = 10;
+/// 選項代號
+const LETTERS: [&str; 4] = ["A", "B", "C", "D"];
+
+#[derive(Debug, Deserialize)]
+struct GeneratedQuiz {
+    questions: Vec<QuizQuestion>,
+}
+
+/// 使用者的作答
+#[derive(Debug, PartialEq)]
+pub struct Answer {
+    pub quiz_id: i64,
+    pub question: usize,
+    pub option: usize,
+}
+
+/// 處理 `/quiz [主題]`、`/quiz next`、`/quiz stop`、`/quiz rank`
+pub async fn handle_command(state: &AppState, chat_id: &str, user_id: &str, locale: Locale, args: Vec<String>) -> Vec<OutgoingMessage> {
+    let text = |key: &str| vec![TextMessage::new(state.i18n.text(locale, key, &[])).into()];
+    if !state.flags.is_enabled("quiz_command", Some(user_id), true) {
+        return text("command.unavailable");
+    }
+
+    let active = match state.store.active_quiz(chat_id) {
+        Ok(active) => active,
+        Err(e) => {
+            error!("Failed to look up active quiz: {}", e);
+            return text("quiz.error");
+        }
+    };
+    let subcommand = args.first().map(|a| a.to_lowercase());
+    match (subcommand.as_deref(), active) {
+        (Some("rank"), _) => vec![TextMessage::new(leaderboard(state, chat_id, locale).await).into()],
+        (Some("next"), Some(quiz)) => {
+            let Some(question) = quiz.questions.get(quiz.current) else { return text("quiz.error") };
+            let reveal = state.i18n.text(locale, "quiz.skipped", &[
+                ("answer", &answer_label(question)),
+                ("explanation", &question.explanation),
+            ]);
+            advance(state, &quiz, locale, reveal).await
+        }
+        (Some("stop"), Some(quiz)) => match state.store.finish_quiz(quiz.id, "stopped") {
+            Ok(_) => {
+                let board = leaderboard(state, chat_id, locale).await;
+                vec![TextMessage::new(format!("{}\n\n{}", state.i18n.text(locale, "quiz.stopped", &[]), board)).into()]
+            }
+            Err(e) => {
+                error!("Failed to stop quiz {}: {}", quiz.id, e);
+                text("quiz.error")
+            }
+        },
+        (Some("next" | "stop"), None) => text("quiz.none_active"),
+        (_, Some(_)) => text("quiz.already_active"),
+        (_, None) => start(state, chat_id, user_id, locale, &args.join(" ")).await,
+    }
+}
+
+/// 請 OpenClaw 出題並送出第一題
+async fn start(state: &AppState, chat_id: &str, user_id: &str, locale: Locale, topic: &str) -> Vec<OutgoingMessage> {
+    let text = |key: &str| vec![TextMessage::new(state.i18n.text(locale, key, &[])).into()];
+    let topic = Some(topic.trim()).filter(|t| !t.is_empty());
+
+    let questions = match generate(state, user_id, locale, topic).await {
+        Ok(questions) => questions,
+        Err(e) => {
+            warn!("Quiz generation failed: {}", e);
+            reporting::capture(reporting::Level::Error, "quiz generation failed", &reporting::Context {
+                user_id: Some(user_id),
+                model: Some(&state.openclaw_client.model()),
+                detail: Some(&e),
+                ..Default::default()
+            });
+            return text("quiz.generation_failed");
+        }
+    };
+
+    match state.store.create_quiz(chat_id, user_id, topic, &questions) {
+        Ok(id) => {
+            info!("Quiz {} started in {} ({} questions)", id, privacy::id(chat_id), questions.len());
+            let intro = state.i18n.text(locale, "quiz.started", &[
+                ("topic", topic.unwrap_or("-")),
+                ("count", &questions.len().to_string()),
+            ]);
+            vec![TextMessage::new(intro).into(), question_message(state, locale, id, 0, &questions[0])]
+        }
+        Err(e) => {
+            error!("Failed to create quiz: {}", e);
+            text("quiz.error")
+        }
+    }
+}
+
+/// 以結構化輸出產生題目並驗證
+async fn generate(state: &AppState, user_id: &str, locale: Locale, topic: Option<&str>) -> Result<Vec<QuizQuestion>, String> {
+    let instructions = format!(
+        "You are a quiz master. Write {} multiple-choice questions in {}. \
+         Each question has 2 to 4 short options, exactly one correct answer (`answer` is its 0-based index) \
+         and a one-sentence explanation. Reply with JSON only.",
+        QUESTIONS_PER_QUIZ,
+        locale.language_name()
+    );
+    let request = match topic {
+        Some(topic) => format!("Topic: {}", topic),
+        None => "Topic: general knowledge".to_string(),
+    };
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "questions": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "question": { "type": "string" },
+                        "options": { "type": "array", "items": { "type": "string" }, "minItems": 2, "maxItems": 4 },
+                        "answer": { "type": "integer" },
+                        "explanation": { "type": "string" }
+                    },
+                    "required": ["question", "options", "answer", "explanation"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["questions"],
+        "additionalProperties": false
+    });
+
+    let content = state
+        .openclaw_client
+        .send_structured(user_id, &instructions, &request, "quiz", schema)
+        .await?;
+    let generated: GeneratedQuiz = serde_json::from_str(&content).map_err(|e| format!("題目格式錯誤: {}", e))?;
+    let questions: Vec<QuizQuestion> = generated
+        .questions
+        .into_iter()
+        .filter(|q| {
+            !q.question.trim().is_empty() && (2..=LETTERS.len()).contains(&q.options.len()) && q.answer < q.options.len()
+        })
+        .take(QUESTIONS_PER_QUIZ)
+        .collect();
+    if questions.is_empty() {
+        return Err("沒有可用的題目".to_string());
+    }
+    Ok(questions)
+}
+
+/// 題目訊息：題目與選項文字，選項以快速回覆作答
+fn question_message(state: &AppState, locale: Locale, quiz_id: i64, index: usize, question: &QuizQuestion) -> OutgoingMessage {
+    let options: Vec<String> = question
+        .options
+        .iter()
+        .zip(LETTERS)
+        .map(|(option, letter)| format!("{}. {}", letter, option))
+        .collect();
+    let actions = options
+        .iter()
+        .enumerate()
+        .map(|(option, text)| {
+            let label: String = text.chars().take(MAX_LABEL_CHARS).collect();
+            Action::postback_with_text(label, format!("{}{}:{}:{}", POSTBACK_PREFIX, quiz_id, index, option), text.as_str())
+        })
+        .collect::<Vec<_>>();
+    let header = state.i18n.text(locale, "quiz.question", &[("number", &(index + 1).to_string())]);
+    TextMessage::new(format!("{}\n{}\n\n{}", header, question.question, options.join("\n")))
+        .with_quick_reply(actions)
+        .into()
+}
+
+fn answer_label(question: &QuizQuestion) -> String {
+    format!("{}. {}", LETTERS[question.answer], question.options[question.answer])
+}
+
+/// 解析作答的 postback 資料
+pub fn parse_postback(data: &str) -> Option<Answer> {
+    let mut parts = data.strip_prefix(POSTBACK_PREFIX)?.split(':');
+    let answer = Answer {
+        quiz_id: parts.next()?.parse().ok()?,
+        question: parts.next()?.parse().ok()?,
+        option: parts.next()?.parse().ok()?,
+    };
+    parts.next().is_none().then_some(answer)
+}
+
+/// 記錄作答；答對（或一對一聊天中作答）後進到下一題
+pub async fn handle_answer(state: &AppState, chat_id: &str, user_id: &str, locale: Locale, answer: Answer) -> Vec<OutgoingMessage> {
+    let text = |key: &str| vec![TextMessage::new(state.i18n.text(locale, key, &[])).into()];
+    let quiz = match state.store.get_quiz(answer.quiz_id) {
+        Ok(Some(quiz)) => quiz,
+        Ok(None) => return text("quiz.expired"),
+        Err(e) => {
+            error!("Failed to load quiz {}: {}", answer.quiz_id, e);
+            return text("quiz.error");
+        }
+    };
+    if quiz.status != "active" || quiz.current != answer.question {
+        return text("quiz.expired");
+    }
+    let Some(question) = quiz.questions.get(answer.question) else { return text("quiz.expired") };
+    if answer.option >= question.options.len() {
+        return text("quiz.expired");
+    }
+
+    let correct = answer.option == question.answer;
+    match state.store.record_quiz_answer(&quiz, answer.question, user_id, answer.option, correct) {
+        Ok(true) => {}
+        Ok(false) => return text("quiz.already_answered"),
+        Err(e) => {
+            error!("Failed to record answer for quiz {}: {}", quiz.id, e);
+            return text("quiz.error");
+        }
+    }
+
+    let one_on_one = chat_id == user_id;
+    let result = if correct {
+        state.i18n.text(locale, "quiz.correct", &[("answer", &answer_label(question)), ("explanation", &question.explanation)])
+    } else if one_on_one {
+        state.i18n.text(locale, "quiz.wrong_reveal", &[("answer", &answer_label(question)), ("explanation", &question.explanation)])
+    } else {
+        state.i18n.text(locale, "quiz.wrong", &[])
+    };
+    if correct || one_on_one {
+        advance(state, &quiz, locale, result).await
+    } else {
+        vec![TextMessage::new(result).into()]
+    }
+}
+
+/// 進到下一題；最後一題後結束並附上排行榜
+async fn advance(state: &AppState, quiz: &Quiz, locale: Locale, result: String) -> Vec<OutgoingMessage> {
+    match state.store.advance_quiz(quiz.id, quiz.current) {
+        Ok(true) => {}
+        // 其他人已先答對，只回覆本次結果
+        Ok(false) => return vec![TextMessage::new(result).into()],
+        Err(e) => {
+            error!("Failed to advance quiz {}: {}", quiz.id, e);
+            return vec![TextMessage::new(result).into()];
+        }
+    }
+
+    let next = quiz.current + 1;
+    match quiz.questions.get(next) {
+        Some(question) => vec![TextMessage::new(result).into(), question_message(state, locale, quiz.id, next, question)],
+        None => {
+            if let Err(e) = state.store.finish_quiz(quiz.id, "finished") {
+                error!("Failed to finish quiz {}: {}", quiz.id, e);
+            }
+            let board = leaderboard(state, &quiz.chat_id, locale).await;
+            vec![
+                TextMessage::new(result).into(),
+                TextMessage::new(format!("{}\n\n{}", state.i18n.text(locale, "quiz.finished", &[]), board)).into(),
+            ]
+        }
+    }
+}
+
+/// 聊天的排行榜文字（以 LINE 顯示名稱呈現，取不到時顯示部分 userId）
+async fn leaderboard(state: &AppState, chat_id: &str, locale: Locale) -> String {
+    let scores = match state.store.quiz_leaderboard(chat_id, LEADERBOARD_SIZE) {
+        Ok(scores) => scores,
+        Err(e) => {
+            error!("Failed to load quiz leaderboard: {}", e);
+            return state.i18n.text(locale, "quiz.error", &[]);
+        }
+    };
+    if scores.is_empty() {
+        return state.i18n.text(locale, "quiz.leaderboard_empty", &[]);
+    }
+
+    let mut lines = vec![state.i18n.text(locale, "quiz.leaderboard", &[])];
+    for (rank, score) in scores.iter().enumerate() {
+        let name = profiles::display_name(state, &score.user_id)
+            .await
+            .unwrap_or_else(|| format!("{}…", score.user_id.chars().take(8).collect::<String>()));
+        lines.push(state.i18n.text(locale, "quiz.leaderboard_row", &[
+            ("rank", &(rank + 1).to_string()),
+            ("name", &name),
+            ("correct", &score.correct.to_string()),
+            ("answered", &score.answered.to_string()),
+        ]));
+    }
+    lines.join("\n")
+}
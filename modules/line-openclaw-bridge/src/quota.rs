@@ -0,0 +1,115 @@
+//! LINE 訊息額度模組
+//! 推送因本月額度用完被拒時記為事故，並只向管理員回報一次（錯誤回報與日誌；額度用完時無法再推送給管理員）；
+//! 到下個月（日本時間）重置前，一般通知延後送出，摘要、簡報與事故通知暫停，不再反覆呼叫必定失敗的 API
+
+use chrono::{DateTime, Datelike, TimeZone};
+use chrono_tz::Asia::Tokyo;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::line::LineClient;
+use crate::reporting;
+use crate::store::{AuditEvent, ConversationStore};
+use crate::{AppState, SharedState};
+
+/// 額度用完事故的類別
+pub const LINE_QUOTA: &str = "line_quota";
+/// 檢查是否已到重置時間的間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+/// 額度狀態（`/health` 使用）
+#[derive(Debug, Serialize)]
+pub struct QuotaState {
+    pub exhausted: bool,
+    pub since: Option<i64>,
+    pub resets_at: Option<i64>,
+}
+
+/// 讓 LINE 客戶端在額度用完時開啟事故；同一次用完只回報一次
+pub fn install(line: &LineClient, store: Arc<ConversationStore>) {
+    line.set_quota_listener(move || match store.open_incident(LINE_QUOTA, Some("monthly limit reached")) {
+        Ok(Some(id)) => {
+            warn!("Incident #{} opened: LINE monthly message quota exhausted", id);
+            reporting::capture(reporting::Level::Error, "LINE monthly message quota exhausted", &reporting::Context {
+                detail: Some("non-urgent pushes are deferred until the quota resets"),
+                ..Default::default()
+            });
+            if let Err(e) = store.audit(&AuditEvent {
+                user_id: "system",
+                event_type: "quota",
+                action: "exhausted",
+                status: "error",
+                ..Default::default()
+            }) {
+                warn!("Failed to write audit log: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => error!("Failed to open quota incident: {}", e),
+    });
+}
+
+/// 額度用完時回傳重置時間，否則回傳 None
+pub fn exhausted(state: &AppState) -> Option<i64> {
+    match state.store.current_incident(LINE_QUOTA) {
+        Ok(incident) => incident.map(|incident| next_reset(incident.started_at)),
+        Err(e) => {
+            error!("Failed to load quota incident: {}", e);
+            None
+        }
+    }
+}
+
+/// 目前的額度狀態
+pub fn current(state: &AppState) -> QuotaState {
+    let since = state.store.current_incident(LINE_QUOTA).ok().flatten().map(|incident| incident.started_at);
+    QuotaState { exhausted: since.is_some(), since, resets_at: since.map(next_reset) }
+}
+
+/// 重置時間（日本時間）
+pub fn format_reset(resets_at: i64) -> String {
+    DateTime::from_timestamp(resets_at, 0)
+        .map(|t| t.with_timezone(&Tokyo).format("%Y-%m-%d %H:%M JST").to_string())
+        .unwrap_or_default()
+}
+
+/// 管理 API 回報額度用完的錯誤訊息
+pub fn exhausted_message(resets_at: i64) -> String {
+    format!("本月 LINE 訊息額度已用完，將於 {} 重置", format_reset(resets_at))
+}
+
+/// 在背景檢查是否已到重置時間，到了即排除事故，延後的通知隨之送出
+pub fn spawn(state: SharedState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let state = state.read().await;
+            let Some(resets_at) = exhausted(&state) else { continue };
+            if state.clock.timestamp() < resets_at {
+                continue;
+            }
+            match state.store.resolve_incident(LINE_QUOTA) {
+                Ok(Some(id)) => info!("Incident #{} resolved: LINE monthly message quota reset", id),
+                Ok(None) => {}
+                Err(e) => error!("Failed to resolve quota incident: {}", e),
+            }
+        }
+    });
+}
+
+/// `after` 之後的下一個月初（LINE 以日本時間計算每月額度）
+fn next_reset(after: i64) -> i64 {
+    let local = DateTime::from_timestamp(after, 0).unwrap_or_default().with_timezone(&Tokyo);
+    let (year, month) = match local.month() {
+        12 => (local.year() + 1, 1),
+        month => (local.year(), month + 1),
+    };
+    Tokyo
+        .with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .map(|t| t.timestamp())
+        .unwrap_or(after + 31 * 86400)
+}
@@ -0,0 +1,63 @@
+//! 亂數模組
+//! 實驗分組、重試的隨機延遲與處理追蹤抽樣等隨機值都經由 `Random` 產生；設定 `RANDOM_SEED` 時以固定種子產生，
+//! 整合測試與重播評估每次執行的結果都相同。未設定時以系統熵初始化，行為與一般執行相同。
+//! 頁面 ID、連結碼與 LINE 的 retry key 須無法預測且每次啟動都不同，一律取自作業系統亂數
+//! （見 [`crate::liff::new_id`]、[`crate::delivery::retry_key`]），不經由此模組
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 亂數來源（`rand` 的 `StdRng`）
+#[derive(Debug)]
+pub struct Random {
+    rng: Mutex<StdRng>,
+}
+
+impl Random {
+    /// 依設定建立：有種子時可重現，否則以系統熵初始化
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self { rng: Mutex::new(rng) }
+    }
+
+    /// `0..1` 之間的浮點數
+    pub fn next_f64(&self) -> f64 {
+        self.rng.lock().unwrap().gen()
+    }
+
+    /// 在 `delay` 上下 `ratio` 比例內隨機調整，避免多個重試同時送出
+    pub fn jitter(&self, delay: Duration, ratio: f64) -> Duration {
+        let factor = 1.0 + ratio * (self.next_f64() * 2.0 - 1.0);
+        delay.mul_f64(factor.max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_reproduces_the_sequence() {
+        let sequence = |seed| {
+            let random = Random::new(Some(seed));
+            (0..8).map(|_| random.next_f64()).collect::<Vec<_>>()
+        };
+        assert_eq!(sequence(42), sequence(42));
+        assert_ne!(sequence(42), sequence(43));
+    }
+
+    #[test]
+    fn jitter_stays_within_ratio() {
+        let random = Random::new(Some(7));
+        for _ in 0..1000 {
+            let delay = random.jitter(Duration::from_millis(1000), 0.2);
+            assert!((800..=1200).contains(&delay.as_millis()));
+        }
+        assert!((0..1000).all(|_| (0.0..1.0).contains(&random.next_f64())));
+    }
+}
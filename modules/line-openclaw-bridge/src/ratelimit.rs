@@ -0,0 +1,115 @@
+//! 使用者頻率限制模組
+//! 以固定時間窗計算每位使用者送給 AI 的訊息數，超過 `USER_RATE_LIMIT` 時不轉交 OpenClaw；
+//! 檢查結果帶有剩餘次數與重置時間，回覆可告訴使用者多久後再試（指令不受限制）
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::clock::Clock;
+
+/// 記錄超過此數量時清除已過期的時間窗
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// 每位使用者的頻率限制
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    windows: Mutex<HashMap<String, Window>>,
+    clock: Arc<dyn Clock>,
+}
+
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+/// 單次檢查的結果
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    pub allowed: bool,
+    /// 本時間窗內還可送出的訊息數
+    pub remaining: u32,
+    /// 距離時間窗重置的時間
+    pub reset_after: Duration,
+}
+
+impl Decision {
+    /// 重置前需等待的秒數（無條件進位，至少 1 秒）
+    pub fn retry_after_secs(&self) -> u64 {
+        (self.reset_after.as_millis() as u64).div_ceil(1000).max(1)
+    }
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self { limit, window, windows: Mutex::new(HashMap::new()), clock }
+    }
+
+    /// 計入一則訊息並回傳是否允許；被拒絕的訊息不計入
+    pub fn check(&self, user_id: &str) -> Decision {
+        let now = self.clock.instant();
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() > PRUNE_THRESHOLD {
+            windows.retain(|_, w| now.duration_since(w.started) < self.window);
+        }
+        let window = windows.entry(user_id.to_string()).or_insert(Window { started: now, count: 0 });
+        if now.duration_since(window.started) >= self.window {
+            *window = Window { started: now, count: 0 };
+        }
+        let reset_after = self.window.saturating_sub(now.duration_since(window.started));
+        if window.count >= self.limit {
+            return Decision { allowed: false, remaining: 0, reset_after };
+        }
+        window.count += 1;
+        Decision { allowed: true, remaining: self.limit - window.count, reset_after }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use chrono::{TimeZone, Utc};
+
+    fn limiter(limit: u32) -> (RateLimiter, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()));
+        (RateLimiter::new(limit, Duration::from_secs(60), clock.clone()), clock)
+    }
+
+    #[test]
+    fn rejects_after_the_limit_until_the_window_resets() {
+        let (limiter, clock) = limiter(2);
+        let first = limiter.check("U1");
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 1);
+        assert!(limiter.check("U1").allowed);
+
+        clock.advance(Duration::from_secs(45));
+        let rejected = limiter.check("U1");
+        assert!(!rejected.allowed);
+        assert_eq!(rejected.remaining, 0);
+        assert_eq!(rejected.retry_after_secs(), 15);
+
+        clock.advance(Duration::from_secs(15));
+        let reset = limiter.check("U1");
+        assert!(reset.allowed);
+        assert_eq!(reset.remaining, 1);
+    }
+
+    #[test]
+    fn users_are_limited_separately() {
+        let (limiter, _) = limiter(1);
+        assert!(limiter.check("U1").allowed);
+        assert!(!limiter.check("U1").allowed);
+        assert!(limiter.check("U2").allowed);
+    }
+
+    #[test]
+    fn retry_after_rounds_up_to_at_least_a_second() {
+        let decision = |millis| Decision { allowed: false, remaining: 0, reset_after: Duration::from_millis(millis) };
+        assert_eq!(decision(0).retry_after_secs(), 1);
+        assert_eq!(decision(1).retry_after_secs(), 1);
+        assert_eq!(decision(1001).retry_after_secs(), 2);
+    }
+}
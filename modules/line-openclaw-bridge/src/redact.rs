@@ -0,0 +1,170 @@
+//! 個資遮蔽模組
+//! 將文字中的 email、LINE ID、電話、身分證與居留證號碼等長數字，以及已知的使用者名稱換成 `[EMAIL]`、`[LINE_ID]`、
+//! `[NUMBER]`、`[NAME]` 標記；以字元掃描判斷，寧可多遮也不漏遮
+
+/// 連續數字（可夾雜空白、`-`、`.` 與括號）達到此位數時視為電話或證件號碼
+const MIN_NUMBER_DIGITS: usize = 9;
+/// 市話號碼（不含區碼）的分段：`2345-6789`、`234-5678`
+const LOCAL_PHONE_GROUPS: [[usize; 2]; 2] = [[4, 4], [3, 4]];
+/// 身分證與居留證號碼：英文字母加 9 位數字，第一位數字為性別碼（1、2，新式居留證為 8、9）
+const NATIONAL_ID_DIGITS: usize = 9;
+/// LINE userId／groupId／roomId 前綴之後的十六進位字元數
+const LINE_ID_HEX: usize = 32;
+
+/// 遮蔽文字中的個資；`names` 為要一併遮蔽的名稱（例如使用者的顯示名稱）
+pub fn redact(text: &str, names: &[String]) -> String {
+    let mut text = text.to_string();
+    for name in names.iter().map(|n| n.trim()).filter(|n| n.chars().count() >= 2) {
+        text = text.replace(name, "[NAME]");
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let matched = line_id_end(&chars, i)
+            .map(|end| (end, "[LINE_ID]"))
+            .or_else(|| email_end(&chars, i).map(|end| (end, "[EMAIL]")))
+            .or_else(|| national_id_end(&chars, i).map(|end| (end, "[NUMBER]")))
+            .or_else(|| number_end(&chars, i).map(|end| (end, "[NUMBER]")));
+        match matched {
+            Some((end, tag)) => {
+                out.push_str(tag);
+                i = end;
+            }
+            None => {
+                out.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// 位置 `i` 之前是否為英數字（比對只從單字開頭開始）
+fn inside_word(chars: &[char], i: usize) -> bool {
+    i > 0 && chars[i - 1].is_ascii_alphanumeric()
+}
+
+/// `U`／`C`／`R` 加 32 個十六進位字元
+fn line_id_end(chars: &[char], i: usize) -> Option<usize> {
+    if !matches!(chars[i], 'U' | 'C' | 'R') || inside_word(chars, i) {
+        return None;
+    }
+    let end = i + 1 + LINE_ID_HEX;
+    let hex = chars.get(i + 1..end)?;
+    let is_hex = hex.iter().all(|c| c.is_ascii_digit() || ('a'..='f').contains(c));
+    (is_hex && !chars.get(end).is_some_and(|c| c.is_ascii_alphanumeric())).then_some(end)
+}
+
+fn email_end(chars: &[char], i: usize) -> Option<usize> {
+    let local = |c: &char| c.is_ascii_alphanumeric() || "._%+-".contains(*c);
+    let domain = |c: &char| c.is_ascii_alphanumeric() || *c == '-' || *c == '.';
+    if !chars[i].is_ascii_alphanumeric() || (i > 0 && local(&chars[i - 1])) {
+        return None;
+    }
+    let at = i + chars[i..].iter().take_while(|c| local(c)).count();
+    if chars.get(at) != Some(&'@') {
+        return None;
+    }
+    let mut end = at + 1 + chars[at + 1..].iter().take_while(|c| domain(c)).count();
+    // 句尾的句點不屬於網域
+    while end > at + 1 && chars[end - 1] == '.' {
+        end -= 1;
+    }
+    let host: String = chars[at + 1..end].iter().collect();
+    let tld = host.rsplit_once('.').map(|(_, tld)| tld)?;
+    (tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic())).then_some(end)
+}
+
+/// 英文字母（不分大小寫）加 9 位數字，之後不能緊接英數字
+fn national_id_end(chars: &[char], i: usize) -> Option<usize> {
+    if !chars[i].is_ascii_alphabetic() || inside_word(chars, i) {
+        return None;
+    }
+    let end = i + 1 + NATIONAL_ID_DIGITS;
+    let digits = chars.get(i + 1..end)?;
+    let valid = matches!(digits[0], '1' | '2' | '8' | '9') && digits.iter().all(char::is_ascii_digit);
+    (valid && !chars.get(end).is_some_and(|c| c.is_ascii_alphanumeric())).then_some(end)
+}
+
+fn number_end(chars: &[char], i: usize) -> Option<usize> {
+    let start_ok = chars[i].is_ascii_digit() || (chars[i] == '+' && chars.get(i + 1).is_some_and(char::is_ascii_digit));
+    if !start_ok || inside_word(chars, i) {
+        return None;
+    }
+    let (mut digits, mut end) = (0, i);
+    // 以 `-` 或空白分隔的各段位數，用來辨識不含區碼的市話
+    let mut groups = vec![0];
+    let mut separated = true;
+    for (offset, c) in chars[i..].iter().enumerate() {
+        match c {
+            c if c.is_ascii_digit() => {
+                digits += 1;
+                end = i + offset + 1;
+                *groups.last_mut().unwrap() += 1;
+            }
+            ' ' | '-' => groups.push(0),
+            '.' | '(' | ')' | '+' => separated = false,
+            _ => break,
+        }
+    }
+    groups.retain(|&g| g > 0);
+    let local_phone = separated && LOCAL_PHONE_GROUPS.iter().any(|pattern| groups == pattern);
+    (digits >= MIN_NUMBER_DIGITS || local_phone).then_some(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(text: &str) -> String {
+        redact(text, &[])
+    }
+
+    #[test]
+    fn redacts_emails_and_line_ids() {
+        assert_eq!(plain("mail me at a.b+c@example.com."), "mail me at [EMAIL].");
+        assert_eq!(plain("id U0123456789abcdef0123456789abcdef ok"), "id [LINE_ID] ok");
+    }
+
+    #[test]
+    fn redacts_national_ids() {
+        assert_eq!(plain("身分證 A123456789 請保密"), "身分證 [NUMBER] 請保密");
+        assert_eq!(plain("居留證F800000001"), "居留證[NUMBER]");
+        assert_eq!(plain("lower a223456789"), "lower [NUMBER]");
+    }
+
+    #[test]
+    fn keeps_codes_that_are_not_national_ids() {
+        assert_eq!(plain("model A323456789"), "model A323456789");
+        assert_eq!(plain("SKU XA123456789"), "SKU XA123456789");
+        assert_eq!(plain("A1234567890"), "A1234567890");
+    }
+
+    #[test]
+    fn redacts_phone_numbers() {
+        assert_eq!(plain("call 0912-345-678 now"), "call [NUMBER] now");
+        assert_eq!(plain("+886 2 2345 6789"), "[NUMBER]");
+        assert_eq!(plain("02-2345-6789"), "[NUMBER]");
+    }
+
+    #[test]
+    fn redacts_local_phone_numbers() {
+        assert_eq!(plain("市話 2345-6789 找我"), "市話 [NUMBER] 找我");
+        assert_eq!(plain("分機 234-5678"), "分機 [NUMBER]");
+        assert_eq!(plain("電話 2345 6789"), "電話 [NUMBER]");
+    }
+
+    #[test]
+    fn keeps_dates_amounts_and_short_numbers() {
+        assert_eq!(plain("2024-01-15 meeting"), "2024-01-15 meeting");
+        assert_eq!(plain("price 1234.5678"), "price 1234.5678");
+        assert_eq!(plain("room 1234"), "room 1234");
+    }
+
+    #[test]
+    fn redacts_names() {
+        let names = vec!["王小明".to_string(), "X".to_string()];
+        assert_eq!(redact("王小明 said X", &names), "[NAME] said X");
+    }
+}
@@ -0,0 +1,106 @@
+//! 重複回答偵測模組
+//! 比對 OpenClaw 這次的回答與同一對話階段的上一則回答（正規化後以字元 bigram 計算相似度），
+//! 幾乎相同時改回覆簡短的提示並詢問是否需要更詳細的說明，避免在對話迴圈中一再重複同樣的內容
+
+use std::collections::HashMap;
+
+/// 正規化後至少需要的字數；較短的回答（如「不客氣！」）重複是正常的
+const MIN_CHARS: usize = 20;
+/// 提示中引用上一則回答的字數
+const EXCERPT_CHARS: usize = 40;
+
+/// 只保留文字與數字並轉為小寫，忽略空白、標點與 Markdown 符號
+fn normalize(text: &str) -> Vec<char> {
+    text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// 字元 bigram 的 Dice 相似度（0–1）
+fn similarity(a: &[char], b: &[char]) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    if a.len() < 2 || b.len() < 2 {
+        return 0.0;
+    }
+    let mut bigrams: HashMap<(char, char), usize> = HashMap::new();
+    for pair in a.windows(2) {
+        *bigrams.entry((pair[0], pair[1])).or_default() += 1;
+    }
+    let mut shared = 0;
+    for pair in b.windows(2) {
+        if let Some(count) = bigrams.get_mut(&(pair[0], pair[1])).filter(|count| **count > 0) {
+            *count -= 1;
+            shared += 1;
+        }
+    }
+    2.0 * shared as f64 / (a.len() + b.len() - 2) as f64
+}
+
+/// 兩則回答的相似度達 `threshold` 時回傳相似度；太短的回答不比對
+pub fn repeated(previous: &str, answer: &str, threshold: f64) -> Option<f64> {
+    let (previous, answer) = (normalize(previous), normalize(answer));
+    if previous.len() < MIN_CHARS || answer.len() < MIN_CHARS {
+        return None;
+    }
+    Some(similarity(&previous, &answer)).filter(|score| *score >= threshold)
+}
+
+/// 上一則回答的開頭（第一行，過長時截斷），放在提示中讓使用者知道指的是哪則回覆
+pub fn excerpt(answer: &str) -> String {
+    let line = answer.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    let line = line.trim_start_matches(['#', '*', '-', '>', ' ']);
+    if line.chars().count() > EXCERPT_CHARS {
+        format!("{}…", line.chars().take(EXCERPT_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn similarity_of_identical_and_disjoint_texts() {
+        assert_eq!(similarity(&chars("abcdef"), &chars("abcdef")), 1.0);
+        assert_eq!(similarity(&chars("abcdef"), &chars("uvwxyz")), 0.0);
+        assert_eq!(similarity(&chars("a"), &chars("b")), 0.0);
+    }
+
+    #[test]
+    fn similarity_counts_shared_bigrams() {
+        // night: ni ig gh ht；nacht: na ac ch ht；共有 ht
+        assert!((similarity(&chars("night"), &chars("nacht")) - 0.25).abs() < 1e-9);
+        // 重複的 bigram 只能配對一次
+        assert!((similarity(&chars("aaaa"), &chars("aa")) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn normalize_ignores_case_spacing_and_markdown() {
+        assert_eq!(normalize("**Hello**, World!"), chars("helloworld"));
+    }
+
+    #[test]
+    fn repeated_detects_near_identical_answers() {
+        let previous = "The capital of France is Paris, a city on the Seine.";
+        let answer = "**The capital of France is Paris**, a city on the Seine!";
+        assert_eq!(repeated(previous, answer, 0.9), Some(1.0));
+        assert!(repeated(previous, "Tokyo is the capital of Japan and its largest city.", 0.9).is_none());
+    }
+
+    #[test]
+    fn repeated_skips_short_answers() {
+        assert!(repeated("不客氣！", "不客氣！", 0.5).is_none());
+    }
+
+    #[test]
+    fn excerpt_uses_first_line_and_truncates() {
+        assert_eq!(excerpt("\n## Title\nbody"), "Title");
+        let long = "x".repeat(50);
+        assert_eq!(excerpt(&long), format!("{}…", "x".repeat(40)));
+    }
+}
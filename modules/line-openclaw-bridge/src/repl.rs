@@ -0,0 +1,131 @@
+//! 終端機對話模組
+//! `chat` 子指令經由與 webhook 相同的流程（對話脈絡、品牌、回答語言、審查、斜線指令）與 OpenClaw 對話，不必傳送 LINE 訊息即可測試提示與設定
+
+use std::io::Write;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::commands;
+use crate::i18n::{fallback_response, Locale};
+use crate::line::{Action, OutgoingMessage, QuickReply, Template};
+use crate::locations;
+use crate::stickers;
+use crate::{Config, Reply};
+
+/// 結束對話的指令
+const EXIT_COMMANDS: [&str; 2] = ["/exit", "/quit"];
+
+/// 逐行讀取標準輸入並印出回應，直到 EOF 或 `/exit`
+pub async fn run(config: Config, user_id: &str, locale: Option<&str>) -> Result<(), String> {
+    let (state, _) = crate::build_state(config).await?;
+    let locale = match locale {
+        Some(tag) => Locale::from_tag(tag).ok_or_else(|| format!("--locale 僅支援 zh-TW、ja、en，目前為 {}", tag))?,
+        None => state.i18n.default_locale(),
+    };
+    println!(
+        "💬 {} · {} · 使用者 {} · 語系 {}（/exit 結束）",
+        state.branding.name,
+        state.openclaw_client.model(),
+        user_id,
+        locale.tag()
+    );
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        print!("> ");
+        std::io::stdout().flush().ok();
+        let Some(line) = lines.next_line().await.map_err(|e| format!("無法讀取輸入: {}", e))? else {
+            println!();
+            break;
+        };
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if EXIT_COMMANDS.contains(&text) {
+            break;
+        }
+
+        match commands::parse(text, &state.i18n) {
+            Some(command) => {
+                for message in crate::handle_command(&state, user_id, user_id, locale, command).await {
+                    println!("{}\n", describe(&message));
+                }
+            }
+            None => match commands::suggest(text, &state.i18n) {
+                Some(suggestion) => {
+                    println!("{}\n", describe(&commands::suggestion_message(&state.i18n, locale, text, &suggestion)));
+                }
+                None => {
+                    let Reply::Answer { text: answer, footer } = crate::chat(&state, "cli", user_id, locale, text, None, |text| {
+                        fallback_response(&state.i18n, locale, &state.branding.name, text)
+                    })
+                    .await
+                    else {
+                        continue;
+                    };
+                    let (answer, sticker) = stickers::take(&state.branding, &answer);
+                    let (answer, location) = locations::take(&answer);
+                    println!("{}\n", answer);
+                    if let Some(location) = location {
+                        println!("{}\n", describe(&location));
+                    }
+                    if let Some(sticker) = sticker {
+                        println!("{}\n", describe(&sticker));
+                    }
+                    if let Some(footer) = footer {
+                        println!("{}\n", footer);
+                    }
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+/// 以文字呈現 LINE 訊息（非文字訊息只顯示種類與替代文字，快速回覆只顯示按鈕文字）
+fn describe(message: &OutgoingMessage) -> String {
+    match message {
+        OutgoingMessage::Text(message) => with_quick_reply(&message.text, message.quick_reply.as_ref()),
+        OutgoingMessage::TextV2(message) => with_quick_reply(&message.text, message.quick_reply.as_ref()),
+        OutgoingMessage::Flex(message) => format!("[Flex] {}", message.alt_text),
+        OutgoingMessage::Template(message) => match &message.template {
+            Template::Carousel { columns } => {
+                let cards: Vec<String> = columns
+                    .iter()
+                    .map(|c| format!("• {}{}", c.title.as_deref().map(|t| format!("{}：", t)).unwrap_or_default(), c.text))
+                    .collect();
+                format!("[Template] {}\n{}", message.alt_text, cards.join("\n"))
+            }
+            Template::Buttons(buttons) => {
+                let labels: Vec<&str> = buttons.actions.iter().map(label).collect();
+                format!("[Template] {}\n[{}]", message.alt_text, labels.join(" | "))
+            }
+            Template::Confirm { .. } => format!("[Template] {}", message.alt_text),
+        },
+        OutgoingMessage::Image(message) => format!("[Image] {}", message.original_content_url),
+        OutgoingMessage::Audio(message) => format!("[Audio] {}", message.original_content_url),
+        OutgoingMessage::Sticker(message) => format!("[Sticker] {}/{}", message.package_id, message.sticker_id),
+        OutgoingMessage::Location(message) => format!(
+            "[Location] {} ({}) {:.6},{:.6}",
+            message.title, message.address, message.latitude, message.longitude
+        ),
+    }
+}
+
+/// 文字訊息之後列出快速回覆的按鈕文字
+fn with_quick_reply(text: &str, quick_reply: Option<&QuickReply>) -> String {
+    match quick_reply {
+        Some(quick_reply) => {
+            let labels: Vec<&str> = quick_reply.items.iter().map(|item| label(&item.action)).collect();
+            format!("{}\n[{}]", text, labels.join(" | "))
+        }
+        None => text.to_string(),
+    }
+}
+
+/// 按鈕文字
+fn label(action: &Action) -> &str {
+    match action {
+        Action::Postback { label, .. } | Action::Uri { label, .. } | Action::Message { label, .. } => label,
+    }
+}
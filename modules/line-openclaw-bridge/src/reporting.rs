@@ -0,0 +1,197 @@
+//! 錯誤回報模組
+//! 設定 `SENTRY_DSN` 時，將處理錯誤、panic 與 OpenClaw 連續失敗送到 Sentry
+
+use reqwest::{Client, Url};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+use crate::privacy;
+
+/// OpenClaw 連續失敗幾次後回報
+const REPEATED_FAILURE_THRESHOLD: u32 = 3;
+
+static REPORTER: OnceLock<Reporter> = OnceLock::new();
+/// OpenClaw 目前的連續失敗次數
+static OPENCLAW_FAILURES: AtomicU32 = AtomicU32::new(0);
+static EVENT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// 事件嚴重程度
+#[derive(Debug, Clone, Copy)]
+pub enum Level {
+    Error,
+    Fatal,
+}
+
+impl Level {
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Fatal => "fatal",
+        }
+    }
+}
+
+/// 事件的附加資訊
+#[derive(Debug, Default)]
+pub struct Context<'a> {
+    /// LINE 事件類型（message / postback…）
+    pub event_type: Option<&'a str>,
+    /// LINE userId，送出前會雜湊
+    pub user_id: Option<&'a str>,
+    pub model: Option<&'a str>,
+    pub detail: Option<&'a str>,
+}
+
+struct Reporter {
+    client: Client,
+    dsn: String,
+    envelope_url: Url,
+    auth_header: String,
+    environment: String,
+}
+
+/// 啟用錯誤回報並安裝 panic hook；DSN 格式錯誤時回傳錯誤
+pub fn init(dsn: &str, environment: &str) -> Result<(), String> {
+    let url = Url::parse(dsn).map_err(|e| format!("SENTRY_DSN 格式錯誤: {}", e))?;
+    let public_key = url.username();
+    let (prefix, project_id) = url
+        .path()
+        .trim_end_matches('/')
+        .rsplit_once('/')
+        .ok_or("SENTRY_DSN 缺少專案 ID")?;
+    if public_key.is_empty() || project_id.is_empty() {
+        return Err("SENTRY_DSN 缺少金鑰或專案 ID".to_string());
+    }
+
+    let host = url.host_str().ok_or("SENTRY_DSN 缺少主機名稱")?;
+    let port = url.port().map(|p| format!(":{}", p)).unwrap_or_default();
+    let envelope_url = Url::parse(&format!(
+        "{}://{}{}{}/api/{}/envelope/",
+        url.scheme(),
+        host,
+        port,
+        prefix,
+        project_id
+    ))
+    .map_err(|e| format!("SENTRY_DSN 格式錯誤: {}", e))?;
+
+    let reporter = Reporter {
+        client: Client::new(),
+        dsn: dsn.to_string(),
+        envelope_url,
+        auth_header: format!(
+            "Sentry sentry_version=7, sentry_key={}, sentry_client=line-openclaw-bridge/{}",
+            public_key,
+            env!("CARGO_PKG_VERSION")
+        ),
+        environment: environment.to_string(),
+    };
+    if REPORTER.set(reporter).is_err() {
+        return Ok(());
+    }
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic| {
+        let location = panic
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()))
+            .unwrap_or_default();
+        let payload = panic
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        capture(Level::Fatal, &format!("panic: {}", payload), &Context {
+            detail: Some(&location),
+            ..Default::default()
+        });
+        previous(panic);
+    }));
+
+    info!("Error reporting enabled ({})", environment);
+    Ok(())
+}
+
+/// 送出事件（未啟用時不做任何事；傳送在背景進行，不阻塞呼叫端）
+pub fn capture(level: Level, message: &str, context: &Context) {
+    let Some(reporter) = REPORTER.get() else { return };
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else { return };
+
+    let event_id = event_id(message);
+    let mut tags = BTreeMap::new();
+    if let Some(event_type) = context.event_type {
+        tags.insert("event_type", event_type.to_string());
+    }
+    if let Some(model) = context.model {
+        tags.insert("model", model.to_string());
+    }
+    let event = json!({
+        "event_id": event_id,
+        "timestamp": chrono::Utc::now().timestamp(),
+        "platform": "other",
+        "level": level.as_str(),
+        "logger": "line-openclaw-bridge",
+        "release": concat!("line-openclaw-bridge@", env!("CARGO_PKG_VERSION")),
+        "environment": reporter.environment,
+        "message": { "formatted": message },
+        "tags": tags,
+        "user": context.user_id.map(|id| json!({ "id": hash_user(id) })),
+        "extra": { "detail": context.detail },
+    });
+    let body = format!(
+        "{}\n{}\n{}\n",
+        json!({ "event_id": event_id, "dsn": reporter.dsn }),
+        json!({ "type": "event" }),
+        event
+    );
+
+    let request = reporter
+        .client
+        .post(reporter.envelope_url.clone())
+        .header("X-Sentry-Auth", &reporter.auth_header)
+        .header("Content-Type", "application/x-sentry-envelope")
+        .body(body);
+    runtime.spawn(async move {
+        if let Err(e) = request.send().await.and_then(|r| r.error_for_status()) {
+            warn!("Failed to send error report: {}", e);
+        }
+    });
+}
+
+/// 記錄一次 OpenClaw 失敗；連續失敗達門檻時回報一次
+pub fn openclaw_failure(context: &Context) {
+    let failures = OPENCLAW_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+    if failures == REPEATED_FAILURE_THRESHOLD {
+        capture(
+            Level::Error,
+            &format!("OpenClaw failed {} times in a row", failures),
+            context,
+        );
+    }
+}
+
+/// OpenClaw 回應成功，重設連續失敗次數
+pub fn openclaw_success() {
+    OPENCLAW_FAILURES.store(0, Ordering::Relaxed);
+}
+
+/// 將 LINE userId 雜湊，避免個資送出；隱私模式下改用可反查的金鑰雜湊
+pub fn hash_user(user_id: &str) -> String {
+    if privacy::enabled() {
+        return privacy::id(user_id).into_owned();
+    }
+    hex::encode(&Sha256::digest(user_id.as_bytes())[..8])
+}
+
+/// 產生 32 字元的事件 ID
+fn event_id(message: &str) -> String {
+    let seq = EVENT_SEQ.fetch_add(1, Ordering::Relaxed);
+    let nanos = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    let digest = Sha256::digest(format!("{}:{}:{}:{}", nanos, seq, std::process::id(), message).as_bytes());
+    hex::encode(&digest[..16])
+}
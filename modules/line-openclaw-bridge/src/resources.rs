@@ -0,0 +1,172 @@
+//! 資源指標模組
+//! 收集行程記憶體、tokio 工作數與佇列深度、各資料表列數與資料庫大小，提供給 `/metrics` 與 `/admin/stats`，
+//! 並在資料表超過軟上限時以 WARN 記錄，方便長時間運行於小型 VPS 時及早發現成長。
+//! 資料表列數由背景工作定期統計後快取，抓取指標時不在請求中佔用資料庫鎖逐表 COUNT(*)
+
+use serde::Serialize;
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::store::ConversationStore;
+
+/// 軟上限的檢查間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+/// 重新統計資料表列數的間隔
+const COUNT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 最近一次統計的各資料表列數；啟動後第一次統計完成前為空
+#[derive(Default)]
+pub struct RowCounts(RwLock<Vec<(&'static str, u64)>>);
+
+impl RowCounts {
+    pub fn get(&self) -> Vec<(&'static str, u64)> {
+        self.0.read().unwrap().clone()
+    }
+
+    /// 重新統計並更新快取
+    pub fn refresh(&self, store: &ConversationStore) -> Result<(), String> {
+        let counts = store.table_counts().map_err(|e| format!("無法統計資料表: {}", e))?;
+        *self.0.write().unwrap() = counts;
+        Ok(())
+    }
+}
+
+/// 單一資料表的統計
+#[derive(Debug, Serialize)]
+pub struct TableStats {
+    pub name: &'static str,
+    pub rows: u64,
+    /// 軟上限，未設定為 null
+    pub soft_limit: Option<u64>,
+}
+
+/// 某一時刻的資源使用狀況
+#[derive(Debug, Serialize)]
+pub struct Snapshot {
+    /// 行程常駐記憶體（位元組）；非 Linux 平台為 null
+    pub rss_bytes: Option<u64>,
+    pub tokio_workers: usize,
+    /// 尚未結束的 tokio 工作數
+    pub tokio_alive_tasks: usize,
+    /// tokio 全域佇列中等待執行的工作數
+    pub tokio_global_queue_depth: usize,
+    pub database_bytes: u64,
+    pub tables: Vec<TableStats>,
+}
+
+/// 收集目前的資源使用狀況；資料表列數取自背景統計的快取
+pub fn snapshot(store: &ConversationStore, counts: &RowCounts, soft_limits: &[(String, u64)]) -> Result<Snapshot, String> {
+    let runtime = tokio::runtime::Handle::current().metrics();
+    let tables = counts
+        .get()
+        .into_iter()
+        .map(|(name, rows)| TableStats {
+            name,
+            rows,
+            soft_limit: soft_limits.iter().find(|(table, _)| table == name).map(|(_, limit)| *limit),
+        })
+        .collect();
+    Ok(Snapshot {
+        rss_bytes: rss_bytes(),
+        tokio_workers: runtime.num_workers(),
+        tokio_alive_tasks: runtime.num_alive_tasks(),
+        tokio_global_queue_depth: runtime.global_queue_depth(),
+        database_bytes: store.database_bytes().map_err(|e| format!("無法取得資料庫大小: {}", e))?,
+        tables,
+    })
+}
+
+/// 從 `/proc/self/status` 讀取 VmRSS
+fn rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+impl Snapshot {
+    /// 以 Prometheus 文字格式輸出
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if let Some(rss) = self.rss_bytes {
+            out.push_str("# HELP bridge_process_resident_memory_bytes Resident memory of the bridge process.\n");
+            out.push_str("# TYPE bridge_process_resident_memory_bytes gauge\n");
+            let _ = writeln!(out, "bridge_process_resident_memory_bytes {}", rss);
+        }
+        let gauges = [
+            ("bridge_tokio_workers", "Tokio worker threads.", self.tokio_workers),
+            ("bridge_tokio_alive_tasks", "Tokio tasks that have not finished.", self.tokio_alive_tasks),
+            ("bridge_tokio_global_queue_depth", "Tasks waiting in the tokio global queue.", self.tokio_global_queue_depth),
+        ];
+        for (name, help, value) in gauges {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge\n{} {}", name, help, name, name, value);
+        }
+        out.push_str("# HELP bridge_store_database_bytes Size of the SQLite database including free pages.\n");
+        out.push_str("# TYPE bridge_store_database_bytes gauge\n");
+        let _ = writeln!(out, "bridge_store_database_bytes {}", self.database_bytes);
+        out.push_str("# HELP bridge_store_rows Rows per store table.\n");
+        out.push_str("# TYPE bridge_store_rows gauge\n");
+        for table in &self.tables {
+            let _ = writeln!(out, "bridge_store_rows{{table=\"{}\"}} {}", table.name, table.rows);
+        }
+        out
+    }
+}
+
+/// 定期統計資料表列數（在阻塞執行緒上執行），並檢查是否超過軟上限
+pub fn spawn_monitor(store: Arc<ConversationStore>, counts: Arc<RowCounts>, soft_limits: Vec<(String, u64)>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(COUNT_INTERVAL);
+        let mut last_check: Option<Instant> = None;
+        loop {
+            interval.tick().await;
+            let (task_store, task_counts) = (store.clone(), counts.clone());
+            let refreshed = tokio::task::spawn_blocking(move || task_counts.refresh(&task_store)).await;
+            match refreshed {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    warn!("Failed to collect resource stats: {}", e);
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Resource stats task failed: {}", e);
+                    continue;
+                }
+            }
+            if soft_limits.is_empty() || last_check.is_some_and(|at| at.elapsed() < CHECK_INTERVAL) {
+                continue;
+            }
+            last_check = Some(Instant::now());
+            for (name, rows) in counts.get() {
+                let limit = soft_limits.iter().find(|(table, _)| table == name).map(|(_, limit)| *limit);
+                if let Some(limit) = limit.filter(|limit| rows > *limit) {
+                    warn!(table = name, rows, soft_limit = limit, "Store table exceeds its soft limit");
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn snapshot_reads_cached_row_counts() {
+        let store = ConversationStore::open(":memory:").unwrap();
+        let counts = RowCounts::default();
+        let limits = vec![("conversations".to_string(), 1)];
+        assert!(snapshot(&store, &counts, &limits).unwrap().tables.is_empty());
+
+        store.record("U1", "user", "hi", None, None).unwrap();
+        counts.refresh(&store).unwrap();
+        store.record("U1", "assistant", "hello", None, None).unwrap();
+        let snapshot = snapshot(&store, &counts, &limits).unwrap();
+        let conversations = snapshot.tables.iter().find(|table| table.name == "conversations").unwrap();
+        assert_eq!(conversations.rows, 1);
+        assert_eq!(conversations.soft_limit, Some(1));
+        assert!(snapshot.render().contains("bridge_store_rows{table=\"conversations\"} 1"));
+    }
+}
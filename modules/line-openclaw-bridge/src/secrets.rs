@@ -0,0 +1,182 @@
+//! 外部密鑰管理模組
+//! 啟動時從 Vault（KV）或 AWS Secrets Manager 讀取 LINE access token 與 OpenClaw gateway token，並定期輪替而不需重啟
+
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::outbound;
+use crate::sigv4;
+use crate::SharedState;
+
+/// 密鑰中 LINE access token 的欄位名稱
+const LINE_TOKEN_FIELD: &str = "LINE_CHANNEL_ACCESS_TOKEN";
+/// 密鑰中 OpenClaw gateway token 的欄位名稱
+const OPENCLAW_TOKEN_FIELD: &str = "OPENCLAW_GATEWAY_TOKEN";
+
+/// 外部密鑰管理設定
+#[derive(Debug, Clone)]
+pub struct SecretsConfig {
+    pub backend: SecretsBackend,
+    /// 重新讀取的間隔；0 代表只在啟動時讀取
+    pub refresh: Duration,
+}
+
+/// 密鑰來源
+#[derive(Debug, Clone)]
+pub enum SecretsBackend {
+    /// HashiCorp Vault KV（v1 或 v2）
+    Vault { addr: String, token: String, path: String },
+    /// AWS Secrets Manager（SecretString 為 JSON 物件）
+    Aws {
+        endpoint: Option<String>,
+        region: String,
+        secret_id: String,
+        access_key_id: String,
+        secret_access_key: String,
+        session_token: Option<String>,
+    },
+}
+
+impl SecretsBackend {
+    pub fn describe(&self) -> String {
+        match self {
+            SecretsBackend::Vault { addr, path, .. } => format!("Vault {}/v1/{}", addr, path),
+            SecretsBackend::Aws { region, secret_id, .. } => format!("AWS Secrets Manager {}（{}）", secret_id, region),
+        }
+    }
+}
+
+/// 從密鑰管理讀到的值；欄位不存在時為 None
+#[derive(Debug, Default, PartialEq)]
+pub struct Secrets {
+    pub line_channel_access_token: Option<String>,
+    pub openclaw_gateway_token: Option<String>,
+}
+
+/// 讀取目前的密鑰
+pub async fn fetch(backend: &SecretsBackend) -> Result<Secrets, String> {
+    let data = match backend {
+        SecretsBackend::Vault { addr, token, path } => {
+            let body: Value = outbound::client()
+                .get(format!("{}/v1/{}", addr, path.trim_start_matches('/')))
+                .header("X-Vault-Token", token)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| format!("讀取 Vault 密鑰失敗: {}", e))?
+                .json()
+                .await
+                .map_err(|e| format!("Vault 回應格式錯誤: {}", e))?;
+            // KV v2 的內容位於 data.data，v1 位於 data
+            match body["data"].get("data") {
+                Some(data) if data.is_object() => data.clone(),
+                _ => body["data"].clone(),
+            }
+        }
+        SecretsBackend::Aws { .. } => {
+            let body = aws_get_secret_value(backend).await?;
+            let secret = body["SecretString"].as_str().ok_or("AWS 密鑰缺少 SecretString")?;
+            serde_json::from_str(secret).map_err(|e| format!("AWS 密鑰不是 JSON 物件: {}", e))?
+        }
+    };
+
+    let field = |name: &str| data[name].as_str().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+    Ok(Secrets {
+        line_channel_access_token: field(LINE_TOKEN_FIELD),
+        openclaw_gateway_token: field(OPENCLAW_TOKEN_FIELD),
+    })
+}
+
+/// 呼叫 `secretsmanager.GetSecretValue`（AWS Signature V4 標頭簽章）
+async fn aws_get_secret_value(backend: &SecretsBackend) -> Result<Value, String> {
+    let SecretsBackend::Aws { endpoint, region, secret_id, access_key_id, secret_access_key, session_token } = backend else {
+        return Err("不是 AWS 密鑰設定".to_string());
+    };
+    let endpoint = endpoint
+        .clone()
+        .unwrap_or_else(|| format!("https://secretsmanager.{}.amazonaws.com", region));
+    let url = reqwest::Url::parse(&endpoint).map_err(|e| format!("Secrets Manager 網址錯誤: {}", e))?;
+    let host = sigv4::host(&url).ok_or("Secrets Manager 端點缺少主機名稱")?;
+
+    let body = json!({ "SecretId": secret_id }).to_string();
+    let scope = sigv4::Scope::new(chrono::Utc::now(), region, "secretsmanager");
+    let mut headers = vec![
+        ("content-type", "application/x-amz-json-1.1".to_string()),
+        ("host", host),
+        ("x-amz-date", scope.amz_date.clone()),
+        ("x-amz-target", "secretsmanager.GetSecretValue".to_string()),
+    ];
+    if let Some(token) = session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let signed_headers = headers.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "POST\n{}\n\n{}\n{}\n{}",
+        url.path(),
+        headers.iter().map(|(k, v)| format!("{}:{}\n", k, v)).collect::<String>(),
+        signed_headers,
+        sigv4::sha256_hex(body.as_bytes())
+    );
+    let signature = scope.sign(secret_access_key, &canonical_request);
+
+    let mut request = outbound::client().post(url).header(
+        "Authorization",
+        format!(
+            "{} Credential={}, SignedHeaders={}, Signature={}",
+            sigv4::ALGORITHM,
+            scope.credential(access_key_id),
+            signed_headers,
+            signature
+        ),
+    );
+    for (name, value) in headers.iter().filter(|(k, _)| *k != "host") {
+        request = request.header(*name, value);
+    }
+    request
+        .body(body)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("讀取 AWS 密鑰失敗: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("AWS 回應格式錯誤: {}", e))
+}
+
+/// 在背景定期重新讀取密鑰，token 有變更時立即套用到 LINE 與 OpenClaw 客戶端；讀取失敗時沿用原 token
+pub fn spawn(state: SharedState, config: SecretsConfig, initial: Secrets) {
+    if config.refresh.is_zero() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut current = initial;
+        let mut interval = tokio::time::interval(config.refresh);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let secrets = match fetch(&config.backend).await {
+                Ok(secrets) => secrets,
+                Err(e) => {
+                    warn!("Failed to refresh secrets: {}", e);
+                    continue;
+                }
+            };
+            if secrets == current {
+                continue;
+            }
+            let state = state.read().await;
+            if let Some(token) = &secrets.line_channel_access_token {
+                if current.line_channel_access_token.as_ref() != Some(token) {
+                    state.line_client.set_access_token(token.clone());
+                    info!("LINE channel access token rotated");
+                }
+            }
+            if secrets.openclaw_gateway_token.is_some() && secrets.openclaw_gateway_token != current.openclaw_gateway_token {
+                state.openclaw_client.set_gateway_token(secrets.openclaw_gateway_token.clone());
+                info!("OpenClaw gateway token rotated");
+            }
+            current = secrets;
+        }
+    });
+}
@@ -0,0 +1,209 @@
+//! 對話階段模組
+//! 兩則訊息間隔超過 `SESSION_GAP_MINUTES` 即開新階段，AI 的對話脈絡只涵蓋目前階段；
+//! 每個階段由 OpenClaw 產生簡短標題，`/history` 列出最近的階段，可繼續或匯出
+
+use futures::future::join_all;
+use tracing::{error, info, warn};
+
+use crate::formatting;
+use crate::i18n::Locale;
+use crate::line::{Action, CarouselColumn, OutgoingMessage, Template, TemplateMessage, TextMessage};
+use crate::openclaw::ChatMessage;
+use crate::privacy;
+use crate::store::Session;
+use crate::AppState;
+
+/// 繼續對話階段的 postback 前綴
+const RESUME_PREFIX: &str = "history:resume:";
+/// 匯出對話階段的 postback 前綴
+const EXPORT_PREFIX: &str = "history:export:";
+/// `/history` 列出的階段數
+const LIST_LIMIT: usize = 5;
+/// 產生標題時附上的訊息則數與每則長度
+const TITLE_CONTEXT_MESSAGES: usize = 4;
+const TITLE_CONTEXT_CHARS: usize = 500;
+/// 標題長度上限
+const TITLE_CHARS: usize = 30;
+/// 無法產生 Web 頁面時，以文字匯出的長度上限（LINE 限制 5000 字）
+const EXPORT_TEXT_CHARS: usize = 5000;
+
+/// `/history` 卡片上的動作
+#[derive(Debug, PartialEq)]
+pub enum HistoryAction {
+    Resume(i64),
+    Export(i64),
+}
+
+/// 處理 `/history`：最近的對話階段，尚無標題的先產生標題
+pub async fn handle_command(state: &AppState, user_id: &str, locale: Locale) -> Vec<OutgoingMessage> {
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    if !state.flags.is_enabled("history_command", Some(user_id), true) {
+        return vec![TextMessage::new(t("command.unavailable")).into()];
+    }
+    let mut sessions = match state.store.recent_sessions(user_id, LIST_LIMIT) {
+        Ok(sessions) if sessions.is_empty() => return vec![TextMessage::new(t("history.empty")).into()],
+        Ok(sessions) => sessions,
+        Err(e) => {
+            error!("Failed to list sessions: {}", e);
+            return vec![TextMessage::new(t("history.error")).into()];
+        }
+    };
+    let titles = join_all(sessions.iter().filter(|s| s.title.is_none()).map(|s| generate_title(state, s))).await;
+    for (session, title) in sessions.iter_mut().filter(|s| s.title.is_none()).zip(titles) {
+        session.title = title;
+    }
+
+    let columns = sessions
+        .iter()
+        .enumerate()
+        .map(|(index, session)| {
+            let time = format_time(state, session.last_at);
+            let mut text = state.i18n.text(locale, "history.summary", &[("time", &time), ("count", &session.messages.to_string())]);
+            if index == 0 {
+                text.push_str(&t("history.current"));
+            }
+            let title = title_or_default(state, locale, session);
+            CarouselColumn::new(text)
+                .title(title.as_str())
+                .action(Action::postback_with_text(t("history.resume"), format!("{}{}", RESUME_PREFIX, session.id), title.as_str()))
+                .action(Action::postback(t("history.export"), format!("{}{}", EXPORT_PREFIX, session.id)))
+        })
+        .collect();
+    let alt = state.i18n.text(locale, "history.alt", &[("count", &sessions.len().to_string())]);
+    match Template::carousel(columns) {
+        Ok(template) => vec![TemplateMessage::new(alt, template).into()],
+        Err(e) => {
+            error!("Failed to build session list: {}", e);
+            vec![TextMessage::new(t("history.error")).into()]
+        }
+    }
+}
+
+/// 解析 `/history` 卡片的 postback 資料
+pub fn parse_postback(data: &str) -> Option<HistoryAction> {
+    if let Some(id) = data.strip_prefix(RESUME_PREFIX) {
+        return id.parse().ok().map(HistoryAction::Resume);
+    }
+    data.strip_prefix(EXPORT_PREFIX)?.parse().ok().map(HistoryAction::Export)
+}
+
+/// 繼續或匯出對話階段（只能操作自己或連結身分的階段）
+pub fn handle_postback(state: &AppState, user_id: &str, locale: Locale, action: HistoryAction) -> Vec<OutgoingMessage> {
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    let id = match &action {
+        HistoryAction::Resume(id) | HistoryAction::Export(id) => *id,
+    };
+    let session = match state.store.session(user_id, id) {
+        Ok(Some(session)) => session,
+        Ok(None) => return vec![TextMessage::new(t("history.not_found")).into()],
+        Err(e) => {
+            error!("Failed to load session: {}", e);
+            return vec![TextMessage::new(t("history.error")).into()];
+        }
+    };
+    let title = title_or_default(state, locale, &session);
+    match action {
+        HistoryAction::Resume(_) => match state.store.resume_session(session.id) {
+            Ok(()) => {
+                info!("User {} resumed session {}", privacy::id(user_id), session.id);
+                vec![TextMessage::new(state.i18n.text(locale, "history.resumed", &[("title", &title)])).into()]
+            }
+            Err(e) => {
+                error!("Failed to resume session: {}", e);
+                vec![TextMessage::new(t("history.error")).into()]
+            }
+        },
+        HistoryAction::Export(_) => match state.store.session_transcript(session.id) {
+            Ok(messages) => {
+                let lines: Vec<String> = messages
+                    .iter()
+                    .map(|(role, content, created_at)| {
+                        let who = if role == "user" { t("search.you") } else { t("search.ai") };
+                        format!("**{}**（{}）\n\n{}", who, format_time(state, *created_at), content)
+                    })
+                    .collect();
+                let heading = state.i18n.text(locale, "history.export_title", &[("title", &title)]);
+                let transcript = format!("# {}\n\n{}", heading, lines.join("\n\n---\n\n"));
+                match formatting::answer_page_message(state, user_id, locale, &transcript) {
+                    Some(message) => vec![message],
+                    None => vec![TextMessage::new(transcript.chars().take(EXPORT_TEXT_CHARS).collect::<String>()).into()],
+                }
+            }
+            Err(e) => {
+                error!("Failed to export session: {}", e);
+                vec![TextMessage::new(t("history.error")).into()]
+            }
+        },
+    }
+}
+
+/// 為目前階段產生標題（已有標題或尚無 AI 回答時略過）；於回覆送出後呼叫，不影響回應時間
+pub async fn ensure_title(state: &AppState, user_id: &str) {
+    if !state.flags.is_enabled("history_command", Some(user_id), true) {
+        return;
+    }
+    let session = match state.store.recent_sessions(user_id, 1) {
+        Ok(mut sessions) => match sessions.pop() {
+            Some(session) if session.title.is_none() && session.messages >= 2 => session,
+            _ => return,
+        },
+        Err(e) => {
+            warn!("Failed to load current session: {}", e);
+            return;
+        }
+    };
+    generate_title(state, &session).await;
+}
+
+/// 請 OpenClaw 依階段開頭的對話產生標題並保存
+async fn generate_title(state: &AppState, session: &Session) -> Option<String> {
+    let transcript = match state.store.session_transcript(session.id) {
+        Ok(messages) => messages,
+        Err(e) => {
+            warn!("Failed to load session transcript: {}", e);
+            return None;
+        }
+    };
+    let excerpt: Vec<String> = transcript
+        .iter()
+        .take(TITLE_CONTEXT_MESSAGES)
+        .map(|(role, content, _)| format!("{}: {}", role, content.chars().take(TITLE_CONTEXT_CHARS).collect::<String>()))
+        .collect();
+    let messages = vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: "Write a short title (at most 6 words) for this conversation, in the language the user wrote in. \
+                      Reply with the title only, without quotes or punctuation at the end."
+                .to_string(),
+        },
+        ChatMessage { role: "user".to_string(), content: excerpt.join("\n\n") },
+    ];
+    let title = match state.openclaw_client.send_chat(&session.user_id, None, messages).await {
+        Ok(title) => title,
+        Err(e) => {
+            warn!("Failed to generate session title: {}", e);
+            return None;
+        }
+    };
+    let title: String = title
+        .lines()
+        .map(|line| line.trim().trim_matches(|c| matches!(c, '"' | '「' | '」' | '*' | '#')).trim())
+        .find(|line| !line.is_empty())?
+        .chars()
+        .take(TITLE_CHARS)
+        .collect();
+    if let Err(e) = state.store.set_session_title(session.id, &title) {
+        warn!("Failed to save session title: {}", e);
+    }
+    Some(title)
+}
+
+fn title_or_default(state: &AppState, locale: Locale, session: &Session) -> String {
+    session.title.clone().unwrap_or_else(|| state.i18n.text(locale, "history.untitled", &[]))
+}
+
+fn format_time(state: &AppState, timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.with_timezone(&state.config.ai_footer_timezone).format("%m/%d %H:%M").to_string())
+        .unwrap_or_default()
+}
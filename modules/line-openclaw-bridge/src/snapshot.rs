@@ -0,0 +1,129 @@
+//! 使用者資料快照模組
+//! 舊實例以 `GET /admin/snapshot` 匯出對話紀錄與個人設定（JSON），新實例啟動時依 `SNAPSHOT_IMPORT_PATH` 匯入，
+//! 升級時不會遺失使用者資料；已有資料者保留現有內容，重複匯入不會重複寫入，此設定可一直保留
+
+use futures::Stream;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::store::{AuditEvent, ConversationStore, SnapshotImport, TimeRange, UserSnapshot, SNAPSHOT_VERSION};
+
+/// 匯出時每批讀取的對話筆數
+const BATCH_SIZE: usize = 1000;
+
+/// 以串流匯出快照（JSON）：先輸出設定等較小的部分，對話紀錄在背景執行緒分批讀取後接在最後，
+/// 不會一次載入全部對話，也不會在匯出期間一直佔用資料庫連線
+pub fn stream(store: Arc<ConversationStore>) -> impl Stream<Item = Result<Vec<u8>, String>> {
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, String>>(4);
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = write(&store, &tx) {
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+    futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) })
+}
+
+fn write(store: &ConversationStore, tx: &mpsc::Sender<Result<Vec<u8>, String>>) -> Result<(), String> {
+    let settings = store.snapshot_settings().map_err(|e| format!("讀取快照失敗: {}", e))?;
+    let mut head = serde_json::to_vec(&settings).map_err(|e| format!("快照編碼失敗: {}", e))?;
+    // 去掉結尾的 `}`，接著輸出對話紀錄陣列
+    head.pop();
+    head.extend_from_slice(br#","conversations":["#);
+    // 接收端已關閉（下載中斷）時停止讀取
+    if tx.blocking_send(Ok(head)).is_err() {
+        return Ok(());
+    }
+    let mut after_id = 0;
+    loop {
+        let batch = store
+            .conversation_page(TimeRange::default(), after_id, BATCH_SIZE)
+            .map_err(|e| format!("讀取對話紀錄失敗: {}", e))?;
+        let Some(last) = batch.last() else { break };
+        let mut bytes = Vec::new();
+        for record in &batch {
+            if after_id > 0 || !bytes.is_empty() {
+                bytes.push(b',');
+            }
+            serde_json::to_writer(&mut bytes, record).map_err(|e| format!("快照編碼失敗: {}", e))?;
+        }
+        after_id = last.id;
+        if tx.blocking_send(Ok(bytes)).is_err() {
+            return Ok(());
+        }
+    }
+    let _ = tx.blocking_send(Ok(b"]}".to_vec()));
+    Ok(())
+}
+
+/// 讀取快照檔並匯入資料庫
+pub fn import_file(store: &ConversationStore, path: &str) -> Result<SnapshotImport, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("無法讀取快照 {}: {}", path, e))?;
+    let snapshot: UserSnapshot = serde_json::from_str(&content).map_err(|e| format!("快照 {} 格式錯誤: {}", path, e))?;
+    if snapshot.version > SNAPSHOT_VERSION {
+        return Err(format!("快照 {} 的版本 {} 較新，此版本僅支援到 {}", path, snapshot.version, SNAPSHOT_VERSION));
+    }
+    let imported = store.import_snapshot(&snapshot).map_err(|e| format!("匯入快照失敗: {}", e))?;
+    info!(
+        "Imported snapshot {}: {} conversations, {} sessions, {} briefings, {} reminders, {} links, {} opt-outs, {} profiles",
+        path,
+        imported.conversations,
+        imported.sessions,
+        imported.briefings,
+        imported.reminders,
+        imported.identity_links,
+        imported.incident_opt_outs,
+        imported.profiles
+    );
+    let detail = serde_json::to_string(&imported).unwrap_or_default();
+    if let Err(e) = store.audit(&AuditEvent {
+        user_id: "system",
+        event_type: "snapshot",
+        action: "import",
+        status: "ok",
+        detail: Some(&detail),
+        ..Default::default()
+    }) {
+        warn!("Failed to write audit log: {}", e);
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn streamed_snapshot_imports_with_sessions() {
+        let source = Arc::new(ConversationStore::open(":memory:").unwrap());
+        let session = source.continue_session("U1", 3600).unwrap();
+        source.record("U1", "user", "第一題", None, Some(session)).unwrap();
+        source.record("U1", "assistant", "第一個回答", Some("model"), Some(session)).unwrap();
+        source.record("U2", "user", "沒有階段", None, None).unwrap();
+
+        let chunks: Vec<Vec<u8>> = stream(source).map(Result::unwrap).collect().await;
+        let snapshot: UserSnapshot = serde_json::from_slice(&chunks.concat()).unwrap();
+        assert_eq!(snapshot.version, SNAPSHOT_VERSION);
+        assert_eq!(snapshot.conversations.len(), 3);
+        assert_eq!(snapshot.sessions.len(), 1);
+
+        let target = ConversationStore::open(":memory:").unwrap();
+        // 先建立一個階段，讓匯入的階段編號與原本不同
+        target.continue_session("U3", 3600).unwrap();
+        let imported = target.import_snapshot(&snapshot).unwrap();
+        assert_eq!((imported.conversations, imported.sessions), (3, 1));
+        let session = target.continue_session("U1", i64::MAX / 2).unwrap();
+        let history = target.session_messages(session, 10).unwrap();
+        assert_eq!(history, [("user".to_string(), "第一題".to_string()), ("assistant".to_string(), "第一個回答".to_string())]);
+        assert_eq!(target.import_snapshot(&snapshot).unwrap().conversations, 0);
+    }
+
+    #[tokio::test]
+    async fn empty_store_streams_valid_json() {
+        let store = Arc::new(ConversationStore::open(":memory:").unwrap());
+        let chunks: Vec<Vec<u8>> = stream(store).map(Result::unwrap).collect().await;
+        let snapshot: UserSnapshot = serde_json::from_slice(&chunks.concat()).unwrap();
+        assert!(snapshot.conversations.is_empty());
+    }
+}
@@ -0,0 +1,330 @@
+//! 服務狀態模組
+//! 公開狀態頁 `GET /status` 不需驗證即可查看運行時間、OpenClaw 狀態、最近一次事故與過去 24 小時的訊息量，
+//! 讓使用者回報問題前先確認機器人是否正常；OpenClaw 離線過久時推送事故通知（`/status off` 可關閉），恢復後再通知同一批使用者
+
+use axum::{extract::State, http::StatusCode, response::Html};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::clock::Clock;
+use crate::i18n::Locale;
+use crate::liff;
+use crate::line::{self, TextMessage};
+use crate::markdown::escape;
+use crate::privacy;
+use crate::quota;
+use crate::{AppState, SharedState};
+
+/// 健康檢查間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// 連續失敗幾次後記錄為事故（避免單次逾時就公告離線）
+const FAILURES_FOR_INCIDENT: u32 = 2;
+/// OpenClaw 離線事故的類別
+pub const OPENCLAW_DOWN: &str = "openclaw_down";
+/// 事故通知的對象：這段時間內與機器人互動過的使用者
+const NOTICE_ACTIVE_WINDOW_SECS: i64 = 3600;
+/// 訊息量走勢圖的格數與每格秒數（24 小時，每格 1 小時）
+const VOLUME_BUCKETS: usize = 24;
+const VOLUME_BUCKET_SECS: i64 = 3600;
+
+/// 服務啟動時間與最近一次 OpenClaw 健康檢查結果
+pub struct StatusTracker {
+    started_at: DateTime<Utc>,
+    openclaw: Mutex<Option<HealthCheck>>,
+    clock: Arc<dyn Clock>,
+}
+
+/// 單次健康檢查結果
+#[derive(Debug, Clone, Copy)]
+struct HealthCheck {
+    online: bool,
+    checked_at: DateTime<Utc>,
+    /// 連續失敗次數
+    failures: u32,
+}
+
+impl StatusTracker {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            started_at: clock.now(),
+            openclaw: Mutex::new(None),
+            clock,
+        }
+    }
+
+    /// 記錄一次健康檢查，回傳更新後的連續失敗次數
+    fn record(&self, online: bool) -> u32 {
+        let mut openclaw = self.openclaw.lock().unwrap();
+        let failures = match (online, *openclaw) {
+            (true, _) => 0,
+            (false, Some(previous)) => previous.failures + 1,
+            (false, None) => 1,
+        };
+        *openclaw = Some(HealthCheck {
+            online,
+            checked_at: self.clock.now(),
+            failures,
+        });
+        failures
+    }
+}
+
+/// 啟動背景健康檢查：連續失敗時開始事故，恢復後排除；
+/// 設定 `INCIDENT_NOTICE_AFTER_MINUTES` 時另通知最近互動過的使用者
+pub fn spawn(state: SharedState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let guard = state.read().await;
+            let result = guard.openclaw_client.health_check().await;
+            let online = matches!(result, Ok(true));
+            let failures = guard.status.record(online);
+
+            if online {
+                let incident = match guard.store.current_incident(OPENCLAW_DOWN) {
+                    Ok(Some(incident)) => incident,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        error!("Failed to load current incident: {}", e);
+                        continue;
+                    }
+                };
+                if let Err(e) = guard.store.resolve_incident(OPENCLAW_DOWN) {
+                    error!("Failed to resolve incident: {}", e);
+                    continue;
+                }
+                info!("Incident #{} resolved: OpenClaw is back online", incident.id);
+                // 恢復通知只送給收到離線通知的使用者
+                if let Some(recipients) = incident.announced_to.filter(|r| !r.is_empty()) {
+                    announce(&state, &guard, recipients, "incident.recovered").await;
+                }
+            } else if failures >= FAILURES_FOR_INCIDENT {
+                if failures == FAILURES_FOR_INCIDENT {
+                    let detail = match &result {
+                        Ok(_) => "health check returned unhealthy".to_string(),
+                        Err(e) => e.to_string(),
+                    };
+                    match guard.store.open_incident(OPENCLAW_DOWN, Some(&detail)) {
+                        Ok(Some(id)) => warn!("Incident #{} opened: OpenClaw is offline ({})", id, detail),
+                        Ok(None) => {}
+                        Err(e) => error!("Failed to open incident: {}", e),
+                    }
+                }
+                if let Some(after) = guard.config.incident_notice_after {
+                    announce_outage(&state, &guard, after).await;
+                }
+            }
+        }
+    });
+}
+
+/// 事故持續超過 `after` 且尚未通知時，通知最近一小時內互動過的使用者（每個事故只通知一次）
+async fn announce_outage(state: &SharedState, guard: &AppState, after: Duration) {
+    if quota::exhausted(guard).is_some() {
+        return;
+    }
+    let now = guard.clock.timestamp();
+    let incident = match guard.store.current_incident(OPENCLAW_DOWN) {
+        Ok(Some(incident)) if incident.announced_to.is_none() && now - incident.started_at >= after.as_secs() as i64 => {
+            incident
+        }
+        Ok(_) => return,
+        Err(e) => {
+            error!("Failed to load current incident: {}", e);
+            return;
+        }
+    };
+    let recipients: Vec<String> = match guard.store.recent_users(now - NOTICE_ACTIVE_WINDOW_SECS) {
+        Ok(users) => users.into_iter().filter(|id| line::is_user_id(id)).collect(),
+        Err(e) => {
+            error!("Failed to load recent users: {}", e);
+            return;
+        }
+    };
+    // 先記錄再推送，避免推送期間重啟後重複通知
+    if let Err(e) = guard.store.mark_incident_announced(incident.id, &recipients) {
+        error!("Failed to mark incident as announced: {}", e);
+        return;
+    }
+    info!("Incident #{}: notifying {} recent users of the outage", incident.id, recipients.len());
+    if !recipients.is_empty() {
+        announce(state, guard, recipients, "incident.outage").await;
+    }
+}
+
+/// 依使用者語系分組推送事故通知
+async fn announce(state: &SharedState, guard: &AppState, recipients: Vec<String>, key: &str) {
+    let mut by_locale: HashMap<Locale, Vec<String>> = HashMap::new();
+    for user_id in recipients {
+        let locale = guard.i18n.locale_for(&guard.line_client, &user_id).await;
+        by_locale.entry(locale).or_default().push(user_id);
+    }
+    for (locale, users) in by_locale {
+        let text = guard.i18n.text(locale, key, &[("name", &guard.branding.name)]);
+        guard
+            .notifications
+            .start(state.clone(), users, vec![TextMessage::new(text).into()], true, Duration::ZERO);
+    }
+}
+
+/// `/status`：查看服務狀態，`/status on|off` 開關事故通知
+pub async fn handle_command(state: &AppState, chat_id: &str, user_id: &str, locale: Locale, args: Vec<String>) -> String {
+    let t = |key: &str| state.i18n.text(locale, key, &[]);
+    let enabled = match args.first().map(|arg| arg.to_lowercase()).as_deref() {
+        Some("on") => Some(true),
+        Some("off") => Some(false),
+        Some(_) => return t("incident.usage"),
+        None => None,
+    };
+    if let Some(enabled) = enabled {
+        return match state.store.set_incident_notices(user_id, enabled) {
+            Ok(()) => {
+                info!("Incident notices for {} set to {}", privacy::id(user_id), enabled);
+                t(if enabled { "incident.notices_on" } else { "incident.notices_off" })
+            }
+            Err(e) => {
+                error!("Failed to update incident notices: {}", e);
+                t("incident.error")
+            }
+        };
+    }
+
+    let check = *state.status.openclaw.lock().unwrap();
+    let status = match check {
+        Some(check) if check.online => t("status.online"),
+        Some(_) => t("status.offline"),
+        None => t("status.unknown"),
+    };
+    let mut text = state.i18n.text(locale, "incident.summary", &[("name", &state.branding.name), ("status", &status)]);
+    if let Some(group) = group_line(state, chat_id, locale).await {
+        text.push_str(&format!("\n{}", group));
+    }
+    if let Some(base) = &state.config.public_base_url {
+        text.push_str(&format!("\n{}/status", base));
+    }
+    let notices = state.store.incident_notices_enabled(user_id).unwrap_or_else(|e| {
+        error!("Failed to load incident notice setting: {}", e);
+        true
+    });
+    text.push_str("\n\n");
+    text.push_str(&t(if notices { "incident.notices_enabled" } else { "incident.notices_disabled" }));
+    text
+}
+
+/// 在群組中查詢時附上群組名稱與成員人數；聊天室沒有群組資訊，查詢失敗時略過
+async fn group_line(state: &AppState, chat_id: &str, locale: Locale) -> Option<String> {
+    if !chat_id.starts_with('C') {
+        return None;
+    }
+    let (summary, count) =
+        tokio::join!(state.line_client.get_group_summary(chat_id), state.line_client.get_group_member_count(chat_id));
+    match (summary, count) {
+        (Ok(summary), Ok(count)) => {
+            Some(state.i18n.text(locale, "incident.group", &[("group", &summary.group_name), ("count", &count.to_string())]))
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            warn!("Failed to fetch summary of group {}: {}", privacy::id(chat_id), e);
+            None
+        }
+    }
+}
+
+/// 公開狀態頁
+pub async fn page(State(state): State<SharedState>) -> Result<Html<String>, StatusCode> {
+    let state = state.read().await;
+    let i18n = &state.i18n;
+    let locale = i18n.default_locale();
+    let t = |key: &str, args: &[(&str, &str)]| escape(&i18n.text(locale, key, args));
+    let now = state.clock.now();
+
+    let since = now.timestamp() - VOLUME_BUCKETS as i64 * VOLUME_BUCKET_SECS;
+    let (incident, volume) = state
+        .store
+        .last_incident()
+        .and_then(|incident| Ok((incident, state.store.message_volume(since, VOLUME_BUCKET_SECS, VOLUME_BUCKETS)?)))
+        .map_err(|e| {
+            error!("Failed to load status page data: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let check = *state.status.openclaw.lock().unwrap();
+    let openclaw = match check {
+        Some(check) if check.online => t("status.online", &[]),
+        Some(_) => t("status.offline", &[]),
+        None => t("status.unknown", &[]),
+    };
+    let checked_at = check
+        .map(|check| t("status.checked_at", &[("time", &format_time(check.checked_at))]))
+        .unwrap_or_default();
+
+    let incident = match incident {
+        None => t("status.no_incident", &[]),
+        Some(incident) => {
+            let title = t(&format!("status.incident_{}", incident.kind), &[]);
+            let started = DateTime::from_timestamp(incident.started_at, 0).map(format_time).unwrap_or_default();
+            let period = match incident.resolved_at {
+                Some(resolved_at) => {
+                    let minutes = ((resolved_at - incident.started_at).max(0) / 60).to_string();
+                    t("status.resolved", &[("start", &started), ("minutes", &minutes)])
+                }
+                None => t("status.ongoing", &[("start", &started)]),
+            };
+            format!("{}<br>{}", title, period)
+        }
+    };
+
+    let uptime = (now - state.status.started_at).num_minutes().max(0);
+    let (days, hours, minutes) = ((uptime / 1440).to_string(), (uptime / 60 % 24).to_string(), (uptime % 60).to_string());
+    let total: u64 = volume.iter().sum();
+    let body = format!(
+        r#"<article>
+<h2>{heading}</h2>
+<p>{openclaw_label}：{openclaw}<br><small>{checked_at}</small></p>
+<p>{uptime_label}：{uptime}</p>
+<h3>{incident_label}</h3>
+<p>{incident}</p>
+<h3>{volume_label}</h3>
+{sparkline}
+<p><small>{volume_total}</small></p>
+</article>
+<footer>v{version}</footer>"#,
+        heading = t("status.title", &[("name", &state.branding.name)]),
+        openclaw_label = t("status.openclaw", &[]),
+        uptime_label = t("status.uptime", &[]),
+        uptime = t("status.uptime_value", &[("days", &days), ("hours", &hours), ("minutes", &minutes)]),
+        incident_label = t("status.last_incident", &[]),
+        volume_label = t("status.volume", &[]),
+        sparkline = sparkline(&volume),
+        volume_total = t("status.volume_total", &[("count", &total.to_string())]),
+        version = env!("CARGO_PKG_VERSION"),
+    );
+    Ok(Html(liff::page(locale, &i18n.text(locale, "status.title", &[("name", &state.branding.name)]), &body)))
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+/// 以內嵌 SVG 折線畫出訊息量走勢
+fn sparkline(volume: &[u64]) -> String {
+    const WIDTH: f64 = 240.0;
+    const HEIGHT: f64 = 48.0;
+    let max = volume.iter().copied().max().unwrap_or(0).max(1) as f64;
+    let step = WIDTH / (volume.len().max(2) - 1) as f64;
+    let mut points = String::new();
+    for (i, count) in volume.iter().enumerate() {
+        let y = HEIGHT - 2.0 - (*count as f64 / max) * (HEIGHT - 4.0);
+        let _ = write!(points, "{:.1},{:.1} ", i as f64 * step, y);
+    }
+    format!(
+        r##"<svg viewBox="0 0 {w} {h}" width="100%" height="{h}" preserveAspectRatio="none" role="img"><polyline fill="none" stroke="#06c755" stroke-width="2" points="{points}"/></svg>"##,
+        w = WIDTH,
+        h = HEIGHT,
+        points = points.trim_end(),
+    )
+}
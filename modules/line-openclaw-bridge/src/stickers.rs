@@ -0,0 +1,119 @@
+//! 貼圖模組
+//! 使用者傳來的貼圖依 LINE 提供的關鍵字（與訊息貼圖上的文字）轉成給 OpenClaw 的描述，讓 AI 依貼圖表達的情緒回應；
+//! 品牌檔設定 `[stickers]` 時，AI 可在回答中附上 `[sticker:<情緒>]` 標籤，由此轉成貼圖訊息接在回答之後
+
+use tracing::warn;
+
+use crate::branding::Branding;
+use crate::formatting;
+use crate::line::{Message, OutgoingMessage, StickerMessage};
+
+/// 交給 OpenClaw 的關鍵字上限
+const MAX_KEYWORDS: usize = 8;
+/// AI 回答中的貼圖標籤前綴
+const TAG_PREFIX: &str = "[sticker:";
+
+/// 將貼圖訊息轉為給 OpenClaw 的文字
+pub fn describe(message: &Message) -> String {
+    let keywords: Vec<&str> = message
+        .keywords
+        .iter()
+        .map(|k| k.trim())
+        .filter(|k| !k.is_empty())
+        .take(MAX_KEYWORDS)
+        .collect();
+    // 訊息貼圖（stickerResourceType MESSAGE 等）帶有使用者輸入的文字
+    let text = message.text.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    let mut prompt = match (keywords.is_empty(), text) {
+        (true, None) => "[sticker] The user sent a LINE sticker without a description.".to_string(),
+        (true, Some(text)) => format!("[sticker] The user sent a LINE sticker that says: \"{}\".", text),
+        (false, None) => format!("[sticker] The user sent a LINE sticker expressing: {}.", keywords.join(", ")),
+        (false, Some(text)) => format!(
+            "[sticker] The user sent a LINE sticker expressing: {}. The sticker says: \"{}\".",
+            keywords.join(", "),
+            text
+        ),
+    };
+    prompt.push_str(" Reply briefly and naturally to what the sticker conveys.");
+    prompt
+}
+
+/// 附在對話前的系統指示：可用的情緒標籤；品牌檔未設定貼圖時回傳 None
+pub fn instruction(branding: &Branding) -> Option<String> {
+    if branding.stickers.is_empty() {
+        return None;
+    }
+    let mut tags: Vec<&str> = branding.stickers.keys().map(String::as_str).collect();
+    tags.sort_unstable();
+    Some(format!(
+        "When it suits the mood, you may end your answer with one tag {}<sentiment>] to send a matching LINE sticker. \
+         Available sentiments: {}. Do not use any other sentiment and never explain the tag.",
+        TAG_PREFIX,
+        tags.join(", ")
+    ))
+}
+
+/// 貼圖訊息（供各處理流程回覆貼圖）
+pub fn sticker(package_id: &str, sticker_id: &str) -> OutgoingMessage {
+    StickerMessage::new(package_id, sticker_id).into()
+}
+
+/// 情緒標籤對應的貼圖；未設定的標籤回傳 None
+pub fn for_sentiment(branding: &Branding, sentiment: &str) -> Option<OutgoingMessage> {
+    let sticker_ref = branding.stickers.get(&sentiment.to_lowercase())?;
+    Some(sticker(&sticker_ref.package_id, &sticker_ref.sticker_id))
+}
+
+/// 移除回答中的貼圖標籤，回傳其餘文字與第一個標籤對應的貼圖
+pub fn take(branding: &Branding, answer: &str) -> (String, Option<OutgoingMessage>) {
+    let (text, tag) = formatting::take_tag(answer, TAG_PREFIX);
+    let Some(tag) = tag else { return (text, None) };
+    let sentiment = tag.trim();
+    let sticker = for_sentiment(branding, sentiment);
+    if sticker.is_none() {
+        warn!("Ignoring unknown sticker sentiment: {}", sentiment);
+    }
+    (text, sticker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::branding::StickerRef;
+
+    fn branding() -> Branding {
+        let mut branding = Branding::default();
+        let sticker = StickerRef { package_id: "446".to_string(), sticker_id: "1988".to_string() };
+        branding.stickers.insert("happy".to_string(), sticker);
+        branding
+    }
+
+    fn sticker_ids(sticker: &Option<OutgoingMessage>) -> Option<(&str, &str)> {
+        match sticker {
+            Some(OutgoingMessage::Sticker(s)) => Some((s.package_id.as_str(), s.sticker_id.as_str())),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn take_removes_tags_and_maps_the_first_sentiment() {
+        let (text, sticker) = take(&branding(), "太好了！[sticker: Happy ] 祝你順利[sticker:sad]");
+        assert_eq!(text, "太好了！ 祝你順利");
+        assert_eq!(sticker_ids(&sticker), Some(("446", "1988")));
+    }
+
+    #[test]
+    fn take_drops_unknown_sentiments() {
+        let (text, sticker) = take(&branding(), "嗯……[sticker:angry]");
+        assert_eq!(text, "嗯……");
+        assert!(sticker.is_none());
+    }
+
+    #[test]
+    fn take_keeps_answers_without_complete_tags() {
+        assert_eq!(take(&branding(), "沒有標籤").0, "沒有標籤");
+        let (text, sticker) = take(&branding(), "未結束的 [sticker:happy");
+        assert_eq!(text, "未結束的 [sticker:happy");
+        assert!(sticker.is_none());
+    }
+}
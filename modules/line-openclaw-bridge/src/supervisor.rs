@@ -0,0 +1,118 @@
+//! OpenClaw 監控模組
+//! 健康檢查持續失敗超過設定時間時執行復原指令（如 systemctl restart），並通知管理員
+
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::store::AuditEvent;
+use crate::SharedState;
+
+/// 健康檢查間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// 復原指令的執行時間上限
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// 自動復原設定
+#[derive(Clone)]
+pub struct RecoveryConfig {
+    /// 以 `sh -c` 執行的復原指令
+    pub command: String,
+    /// 連續失敗多久後執行復原
+    pub after: Duration,
+    /// 兩次復原之間的最短間隔
+    pub cooldown: Duration,
+}
+
+/// 啟動背景監控
+pub fn spawn(state: SharedState, config: RecoveryConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut down_since: Option<Instant> = None;
+        let mut last_recovery: Option<Instant> = None;
+
+        loop {
+            interval.tick().await;
+            let healthy = matches!(state.read().await.openclaw_client.health_check().await, Ok(true));
+
+            if healthy {
+                if let Some(since) = down_since.take() {
+                    info!("OpenClaw is back online after {:?}", since.elapsed());
+                    if last_recovery.is_some_and(|t| t >= since) {
+                        let text = state.read().await.i18n.text_default("supervisor.recovered", &[]);
+                        notify_admin(&state, &text).await;
+                    }
+                }
+                continue;
+            }
+
+            let since = *down_since.get_or_insert_with(Instant::now);
+            let cooling_down = last_recovery.is_some_and(|t| t.elapsed() < config.cooldown);
+            if since.elapsed() < config.after || cooling_down {
+                continue;
+            }
+
+            warn!("OpenClaw unhealthy for {:?}, running recovery command", since.elapsed());
+            last_recovery = Some(Instant::now());
+            let result = run_command(&config.command).await;
+
+            let text = {
+                let state = state.read().await;
+                let (status, message) = match result {
+                    Ok(output) => (
+                        "ok",
+                        state.i18n.text_default("supervisor.command_ok", &[("command", &config.command), ("output", &output)]),
+                    ),
+                    Err(e) => (
+                        "error",
+                        state.i18n.text_default("supervisor.command_failed", &[("command", &config.command), ("error", &e)]),
+                    ),
+                };
+                if let Err(e) = state.store.audit(&AuditEvent {
+                    user_id: "system",
+                    event_type: "supervisor",
+                    action: "recovery",
+                    status,
+                    detail: Some(&message),
+                    ..Default::default()
+                }) {
+                    warn!("Failed to write audit log: {}", e);
+                }
+                let minutes = (since.elapsed().as_secs() / 60).to_string();
+                state.i18n.text_default("supervisor.offline", &[("minutes", &minutes), ("detail", &message)])
+            };
+            notify_admin(&state, &text).await;
+        }
+    });
+}
+
+/// 執行復原指令，回傳輸出摘要
+async fn run_command(command: &str) -> Result<String, String> {
+    let output = tokio::time::timeout(
+        COMMAND_TIMEOUT,
+        tokio::process::Command::new("sh").arg("-c").arg(command).kill_on_drop(true).output(),
+    )
+    .await
+    .map_err(|_| "執行逾時".to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    let text: String = text.trim().chars().take(500).collect();
+    if output.status.success() {
+        Ok(text)
+    } else {
+        Err(format!("{} {}", output.status, text))
+    }
+}
+
+/// 推送通知給管理員（未設定 ADMIN_LINE_USER_ID 時僅記錄日誌）
+async fn notify_admin(state: &SharedState, text: &str) {
+    let state = state.read().await;
+    let Some(admin) = &state.config.admin_line_user_id else {
+        info!("Admin notification (no ADMIN_LINE_USER_ID): {}", text);
+        return;
+    };
+    if let Err(e) = state.line_client.push_message(admin, text).await {
+        error!("Failed to notify admin: {}", e);
+    }
+}
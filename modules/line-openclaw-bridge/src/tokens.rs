@@ -0,0 +1,162 @@
+//! 簽章權杖模組
+//! 以短效 JWT（HS256）嵌入媒體與 LIFF 網址，持有相同金鑰的任何副本都能無狀態驗證
+
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+
+use crate::clock::Clock;
+
+/// 未設定 `TOKEN_SIGNING_KEY` 時，由 channel secret 衍生金鑰使用的標籤
+const DERIVE_LABEL: &[u8] = b"line-openclaw-bridge/token-signing-key";
+
+/// 權杖用途（寫入 `aud`，避免不同網址的權杖互相挪用）
+#[derive(Debug, Clone, Copy)]
+pub enum Audience {
+    /// `/files/*key` 媒體檔
+    Media,
+    /// `/liff/*` 頁面
+    Liff,
+    /// `/attachments/*key` 封存的附件（永久連結）
+    Attachment,
+}
+
+impl Audience {
+    fn as_str(self) -> &'static str {
+        match self {
+            Audience::Media => "media",
+            Audience::Liff => "liff",
+            Audience::Attachment => "attachment",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    aud: String,
+    /// 權杖對應的資源（物件 key 或頁面路徑）
+    sub: String,
+    iat: i64,
+    exp: i64,
+}
+
+/// 驗證失敗原因
+#[derive(Debug, PartialEq)]
+pub enum TokenError {
+    Expired,
+    Invalid,
+}
+
+/// 由 LINE channel secret 衍生獨立的簽章金鑰（HMAC-SHA256），避免權杖直接以 channel secret 簽章
+pub fn derive_key(channel_secret: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(channel_secret.as_bytes()).expect("HMAC 可接受任意長度金鑰");
+    mac.update(DERIVE_LABEL);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// JWT 簽發與驗證；簽發時間與期限以注入的時鐘判斷
+pub struct TokenSigner {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    clock: Arc<dyn Clock>,
+}
+
+impl TokenSigner {
+    pub fn new(secret: &str, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            clock,
+        }
+    }
+
+    /// 簽發與驗證使用的時鐘
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// 簽發到 `expires_at`（Unix 秒）為止有效的權杖
+    pub fn issue(&self, audience: Audience, subject: &str, expires_at: i64) -> String {
+        let claims = Claims {
+            aud: audience.as_str().to_string(),
+            sub: subject.to_string(),
+            iat: self.clock.timestamp(),
+            exp: expires_at,
+        };
+        jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &self.encoding).expect("HS256 簽章不會失敗")
+    }
+
+    /// 驗證權杖的簽章、用途、資源與期限；簽章由 jsonwebtoken 以固定時間比對，不會因比對提早結束而洩漏正確簽章
+    pub fn verify(&self, audience: Audience, subject: &str, token: &str) -> Result<(), TokenError> {
+        // 期限改以注入的時鐘判斷（jsonwebtoken 內建的檢查固定使用系統時間）
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.validate_exp = false;
+        validation.set_audience(&[audience.as_str()]);
+        validation.sub = Some(subject.to_string());
+        match jsonwebtoken::decode::<Claims>(token, &self.decoding, &validation) {
+            Ok(data) if data.claims.exp < self.clock.timestamp() => Err(TokenError::Expired),
+            Ok(_) => Ok(()),
+            Err(_) => Err(TokenError::Invalid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn expiry_follows_the_injected_clock() {
+        let clock = Arc::new(ManualClock::new(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()));
+        let signer = TokenSigner::new("secret", clock.clone());
+        let token = signer.issue(Audience::Media, "media/a.jpg", clock.timestamp() + 60);
+        assert_eq!(signer.verify(Audience::Media, "media/a.jpg", &token), Ok(()));
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(signer.verify(Audience::Media, "media/a.jpg", &token), Ok(()));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(signer.verify(Audience::Media, "media/a.jpg", &token), Err(TokenError::Expired));
+    }
+
+    #[test]
+    fn liff_tokens_reject_tampering_and_other_pages() {
+        let clock = Arc::new(ManualClock::new(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()));
+        let signer = TokenSigner::new("secret", clock.clone());
+        let token = signer.issue(Audience::Liff, "answer/abc", clock.timestamp() + 60);
+        assert_eq!(signer.verify(Audience::Liff, "answer/abc", &token), Ok(()));
+        assert_eq!(signer.verify(Audience::Liff, "answer/other", &token), Err(TokenError::Invalid));
+        assert_eq!(signer.verify(Audience::Media, "answer/abc", &token), Err(TokenError::Invalid));
+
+        let (payload, signature) = token.rsplit_once('.').unwrap();
+        let flipped = if signature.starts_with('A') { "B" } else { "A" };
+        let tampered = format!("{}.{}{}", payload, flipped, &signature[1..]);
+        assert_eq!(signer.verify(Audience::Liff, "answer/abc", &tampered), Err(TokenError::Invalid));
+        let other_key = TokenSigner::new("other", clock);
+        assert_eq!(other_key.verify(Audience::Liff, "answer/abc", &token), Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn derived_key_is_stable_and_differs_from_the_secret() {
+        let key = derive_key("channel-secret");
+        assert_eq!(key, derive_key("channel-secret"));
+        assert_eq!(key.len(), 64);
+        assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(key, derive_key("other-secret"));
+    }
+
+    #[test]
+    fn malformed_tokens_are_invalid() {
+        let clock = Arc::new(ManualClock::new(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()));
+        let signer = TokenSigner::new("secret", clock.clone());
+        assert_eq!(signer.verify(Audience::Attachment, "a/b.png", ""), Err(TokenError::Invalid));
+        assert_eq!(signer.verify(Audience::Attachment, "a/b.png", "not.a.jwt"), Err(TokenError::Invalid));
+        let token = signer.issue(Audience::Attachment, "a/b.png", clock.timestamp() + 60);
+        assert_eq!(signer.verify(Audience::Attachment, "a/b.png", &token), Ok(()));
+        assert_eq!(signer.verify(Audience::Media, "a/b.png", &token), Err(TokenError::Invalid));
+    }
+}
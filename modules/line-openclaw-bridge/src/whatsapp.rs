@@ -0,0 +1,194 @@
+//! WhatsApp 通道模組
+//! 設定 `WHATSAPP_PHONE_NUMBER_ID` 後，`/whatsapp/webhook` 接收 WhatsApp Business Cloud API 的事件：GET 驗證訂閱（hub.challenge），
+//! POST 驗證 X-Hub-Signature-256 後將文字訊息交給與 LINE 相同的 OpenClaw 對話流程，回答經 [`ChannelAdapter`] 送回
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::get,
+    Router,
+};
+use std::collections::HashMap;
+use std::time::Instant;
+use tracing::{debug, info, warn};
+use whatsapp_adapter::{InboundMessage, WhatsAppClient, WebhookPayload, SIGNATURE_HEADER};
+
+use crate::channels::{self, ChannelAdapter};
+use crate::i18n::fallback_response;
+use crate::locations;
+use crate::markdown;
+use crate::openclaw;
+use crate::privacy;
+use crate::stickers;
+use crate::store::{AuditEvent, ProfileFields};
+use crate::{AppState, Reply, SharedState};
+
+/// WhatsApp 使用者在對話紀錄中的 ID 前綴，與 LINE 的 userId 區隔
+pub const USER_PREFIX: &str = "wa:";
+
+/// WhatsApp Cloud API 設定
+pub struct WhatsAppConfig {
+    pub phone_number_id: String,
+    pub access_token: String,
+    /// 驗證 webhook 簽章的 App Secret
+    pub app_secret: String,
+    /// 訂閱 webhook 時於 Meta 後台填入的驗證權杖
+    pub verify_token: String,
+    /// Graph API 位址（含版本）
+    pub api_base_url: String,
+}
+
+impl WhatsAppConfig {
+    pub fn client(&self) -> WhatsAppClient {
+        WhatsAppClient::new(
+            self.access_token.clone(),
+            self.app_secret.clone(),
+            self.phone_number_id.clone(),
+            self.api_base_url.clone(),
+        )
+    }
+}
+
+/// `/whatsapp` 底下的路由
+pub fn router() -> Router<SharedState> {
+    Router::new().route("/webhook", get(verify).post(receive))
+}
+
+/// 訂閱驗證：權杖相符時原樣回傳 `hub.challenge`
+async fn verify(State(state): State<SharedState>, Query(params): Query<HashMap<String, String>>) -> Result<String, StatusCode> {
+    let state = state.read().await;
+    let Some(config) = &state.config.whatsapp else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let param = |key: &str| params.get(key).map(String::as_str);
+    whatsapp_adapter::verify_subscription(param("hub.mode"), param("hub.verify_token"), param("hub.challenge"), &config.verify_token)
+        .ok_or_else(|| {
+            warn!("Rejected WhatsApp webhook verification");
+            StatusCode::FORBIDDEN
+        })
+}
+
+/// 接收事件：驗證簽章後立即回應 200，訊息在背景處理
+async fn receive(State(shared): State<SharedState>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    let payload = {
+        let state = shared.read().await;
+        let Some(client) = &state.whatsapp_client else {
+            return StatusCode::NOT_FOUND;
+        };
+        let signature = headers.get(SIGNATURE_HEADER).and_then(|v| v.to_str().ok()).unwrap_or_default();
+        if !client.verify_signature(&body, signature) {
+            warn!("Invalid WhatsApp webhook signature");
+            return StatusCode::UNAUTHORIZED;
+        }
+        match client.parse_payload(&body) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to parse WhatsApp webhook: {}", e);
+                return StatusCode::BAD_REQUEST;
+            }
+        }
+    };
+    tokio::spawn(async move {
+        let state = shared.read().await;
+        handle_payload(&state, &payload).await;
+    });
+    StatusCode::OK
+}
+
+async fn handle_payload(state: &AppState, payload: &WebhookPayload) {
+    let Some(client) = &state.whatsapp_client else { return };
+    for (message, name) in payload.messages() {
+        if !state.whatsapp_recent.first_seen(&message.id) {
+            debug!("Skipping redelivered WhatsApp message {}", message.id);
+            continue;
+        }
+        let context = openclaw::RequestContext { channel: client.name(), chat_type: "user", group_id: None };
+        openclaw::with_context(context, handle_message(state, client, message, name)).await;
+    }
+}
+
+/// 處理一則訊息：斜線指令直接回覆，其他文字交給 OpenClaw，其他類型回覆只支援文字
+async fn handle_message(state: &AppState, client: &WhatsAppClient, message: &InboundMessage, name: Option<&str>) {
+    let started = Instant::now();
+    let user_id = format!("{}{}", USER_PREFIX, message.from);
+    // 顯示名稱隨訊息附上，存入個人資料快取供回答稱呼使用者
+    if let Some(name) = name {
+        let profile = ProfileFields {
+            display_name: name,
+            language: None,
+            picture_url: None,
+            status_message: None,
+            member_of: None,
+        };
+        if let Err(e) = state.store.save_profile(&user_id, &profile) {
+            warn!("Failed to save WhatsApp profile of {}: {}", privacy::id(&user_id), e);
+        }
+    }
+    if let Err(e) = client.mark_read(&message.id).await {
+        debug!("Failed to mark WhatsApp message as read: {}", e);
+    }
+
+    let locale = state.config.default_locale;
+    let Some(text) = message.text.as_ref().map(|text| text.body.trim()).filter(|text| !text.is_empty()) else {
+        let notice = state.i18n.text(locale, "whatsapp.unsupported", &[]);
+        send(state, client, &user_id, &message.from, &notice, started, &format!("unsupported type={}", message.message_type)).await;
+        return;
+    };
+    if let Some(messages) = crate::command_response(state, "whatsapp", &user_id, &user_id, locale, text).await {
+        let reply = channels::plain_text(&messages);
+        if !reply.is_empty() {
+            send(state, client, &user_id, &message.from, &reply, started, "command").await;
+        }
+        return;
+    }
+    if let Some(limiter) = &state.rate_limiter {
+        let decision = limiter.check(&user_id);
+        if !decision.allowed {
+            let seconds = decision.retry_after_secs().to_string();
+            info!("Rate limited {} for {}s", privacy::id(&user_id), seconds);
+            let notice = state.i18n.text(locale, "rate_limit.exceeded", &[("seconds", &seconds)]);
+            send(state, client, &user_id, &message.from, &notice, started, &format!("rate_limited retry_after={}s", seconds)).await;
+            return;
+        }
+    }
+
+    let Reply::Answer { text: answer, footer } = crate::chat(state, "whatsapp", &user_id, locale, text, None, |text| {
+        fallback_response(&state.i18n, locale, &state.branding.name, text)
+    })
+    .await
+    else {
+        // 已被 /stop 取消
+        return;
+    };
+    // 貼圖與地圖圖釘是 LINE 專屬的訊息，WhatsApp 只送文字；Markdown 轉為純文字
+    let (answer, _) = stickers::take(&state.branding, &answer);
+    let (answer, _) = locations::take(&answer);
+    let mut answer = markdown::to_plain(&answer);
+    if let Some(footer) = footer {
+        answer.push_str(&format!("\n\n{}", footer));
+    }
+    send(state, client, &user_id, &message.from, &answer, started, "answer").await;
+}
+
+async fn send(state: &AppState, client: &WhatsAppClient, user_id: &str, to: &str, text: &str, started: Instant, detail: &str) {
+    let (status, detail) = match channels::send_answer(client, to, text).await {
+        Ok(count) => {
+            info!("Replied to WhatsApp user {} ({} messages)", privacy::id(user_id), count);
+            ("ok", format!("{} messages={}", detail, count))
+        }
+        Err(e) => {
+            warn!("Failed to reply to WhatsApp user {}: {}", privacy::id(user_id), e);
+            ("error", format!("{} error={}", detail, e))
+        }
+    };
+    crate::audit(state, &AuditEvent {
+        user_id,
+        event_type: "whatsapp",
+        action: "reply",
+        status,
+        latency_ms: Some(started.elapsed().as_millis() as i64),
+        detail: Some(&detail),
+        ..Default::default()
+    });
+}
@@ -0,0 +1,111 @@
+//! reply token 失效時改以 push 送達：以模擬 LINE 與模擬 OpenClaw 跑完整的 webhook 流程
+
+use line_openclaw_bridge::{build_router, Config};
+use serde_json::{json, Value};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+const USER_ID: &str = "U0123456789abcdef0123456789abcdef";
+
+/// 測試結束時關閉模擬伺服器
+struct Mock(Child);
+
+impl Drop for Mock {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+    }
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+async fn wait_until_up(http: &reqwest::Client, url: &str) {
+    for _ in 0..100 {
+        if http.get(url).send().await.is_ok() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("{} did not start", url);
+}
+
+/// 等到模擬 LINE 收到 `path` 的請求後回傳全部請求
+async fn requests_after(http: &reqwest::Client, line_url: &str, path: &str) -> Vec<Value> {
+    for _ in 0..100 {
+        let requests: Vec<Value> = http.get(format!("{}/mock/requests", line_url)).send().await.unwrap().json().await.unwrap();
+        if requests.iter().any(|r| r["path"] == path) {
+            return requests;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("mock LINE never received {}", path);
+}
+
+#[tokio::test]
+async fn expired_reply_token_falls_back_to_push() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bridge_url = format!("http://{}", listener.local_addr().unwrap());
+    let (line_port, openclaw_port) = (free_port(), free_port());
+    let line_url = format!("http://127.0.0.1:{}", line_port);
+    let database = std::env::temp_dir().join(format!("reply-fallback-{}.db", std::process::id()));
+
+    let _line = Mock(
+        Command::new(env!("CARGO_BIN_EXE_mock-line"))
+            .env("MOCK_LINE_PORT", line_port.to_string())
+            .env("MOCK_LINE_CHANNEL_SECRET", "test-secret")
+            .env("MOCK_LINE_WEBHOOK_URL", format!("{}/callback", bridge_url))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+    let _openclaw = Mock(
+        Command::new(env!("CARGO_BIN_EXE_mock-openclaw"))
+            .env("MOCK_OPENCLAW_PORT", openclaw_port.to_string())
+            .env("MOCK_OPENCLAW_REPLY", "pong")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+
+    std::env::set_var("LINE_CHANNEL_ACCESS_TOKEN", "test-token");
+    std::env::set_var("LINE_CHANNEL_SECRET", "test-secret");
+    std::env::set_var("LINE_API_BASE_URL", &line_url);
+    std::env::set_var("OPENCLAW_BASE_URL", format!("http://127.0.0.1:{}", openclaw_port));
+    std::env::set_var("DATABASE_PATH", &database);
+    std::env::set_var("REPLY_MAX_RETRIES", "0");
+    let router = build_router(Config::from_env().unwrap()).await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    let http = reqwest::Client::new();
+    wait_until_up(&http, &format!("{}/health", bridge_url)).await;
+    wait_until_up(&http, &format!("{}/mock/requests", line_url)).await;
+    wait_until_up(&http, &format!("http://127.0.0.1:{}/health", openclaw_port)).await;
+
+    let event = json!({
+        "destination": "Umockbot",
+        "events": [{
+            "type": "message",
+            "mode": "active",
+            "timestamp": 1_700_000_000_000_i64,
+            "webhookEventId": "expired-event-1",
+            "replyToken": "expired-token-1",
+            "source": { "type": "user", "userId": USER_ID },
+            "message": { "type": "text", "id": "1", "text": "ping" }
+        }]
+    });
+    let response = http.post(format!("{}/mock/webhook", line_url)).json(&event).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    let requests = requests_after(&http, &line_url, "/v2/bot/message/push").await;
+    let replies: Vec<_> = requests.iter().filter(|r| r["path"] == "/v2/bot/message/reply").collect();
+    let push = requests.iter().find(|r| r["path"] == "/v2/bot/message/push").unwrap();
+    // 模擬 LINE 拒絕過期 token 時不記錄 reply；push 送往原本的對象且帶有 retry key
+    assert!(replies.is_empty());
+    assert_eq!(push["body"]["to"], USER_ID);
+    assert!(push["retry_key"].as_str().is_some_and(|key| !key.is_empty()));
+
+    let _ = std::fs::remove_file(&database);
+}